@@ -0,0 +1,195 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use fxhash::FxHashSet;
+
+use tracing::{error, info};
+
+use elfo_core::{
+    messages::{ActorStatusReport, ConfigUpdated, SubscribeToActorStatuses},
+    msg,
+    stream::Stream,
+    time::{Delay, Interval},
+    ActorStatus, Addr, Context, SourceHandle, Topology,
+};
+
+use crate::{
+    config::Config,
+    http,
+    protocol::{ActorInfo, GetStatuses, RefreshGroups, RestartServer, Server, ServerFailed},
+    server,
+};
+
+/// How long to wait before restarting a failed server.
+const RESTART_DELAY: Duration = Duration::from_secs(5);
+
+/// How often to look for groups mounted at runtime.
+const REFRESH_GROUPS_INTERVAL: Duration = Duration::from_secs(1);
+
+struct Admin {
+    ctx: Context<Config>,
+    topology: Topology,
+    server: Option<Stream<ServerFailed>>,
+    http_server: Option<Stream<ServerFailed>>,
+    /// Groups whose statuses are subscribed to.
+    subscribed: FxHashSet<Addr>,
+    statuses: BTreeMap<(String, String), ActorStatus>,
+}
+
+pub(crate) async fn exec(ctx: Context<Config>, topology: Topology) {
+    Admin {
+        ctx,
+        topology,
+        server: None,
+        http_server: None,
+        subscribed: FxHashSet::default(),
+        statuses: BTreeMap::new(),
+    }
+    .main()
+    .await
+}
+
+impl Admin {
+    async fn main(mut self) {
+        // Groups can be mounted at runtime, so the list is refreshed.
+        self.refresh_groups().await;
+        self.ctx
+            .attach(Interval::new(RefreshGroups))
+            .start(REFRESH_GROUPS_INTERVAL);
+
+        let config = self.ctx.config();
+        let mut socket = (config.socket.clone(), config.socket_mode);
+        self.start_server();
+
+        let mut http = self.ctx.config().http;
+        self.start_http_server();
+
+        while let Some(envelope) = self.ctx.recv().await {
+            msg!(match envelope {
+                ConfigUpdated => {
+                    let config = self.ctx.config();
+
+                    if (&config.socket, config.socket_mode) != (&socket.0, socket.1) {
+                        info!(
+                            message = "socket changed, rerun the server",
+                            old = ?socket.0,
+                            new = ?config.socket,
+                            old_mode = format_args!("{:o}", socket.1),
+                            new_mode = format_args!("{:o}", config.socket_mode),
+                        );
+                        socket = (config.socket.clone(), config.socket_mode);
+                        self.start_server();
+                    }
+
+                    let config = self.ctx.config();
+
+                    if config.http != http {
+                        info!(
+                            message = "HTTP address changed, rerun the server",
+                            old = ?http,
+                            new = ?config.http,
+                        );
+                        http = config.http;
+                        self.start_http_server();
+                    }
+                }
+                ActorStatusReport { meta, status, .. } => {
+                    let key = (meta.group.clone(), meta.key.clone());
+                    if status.kind().is_terminated() {
+                        self.statuses.remove(&key);
+                    } else {
+                        self.statuses.insert(key, status);
+                    }
+                }
+                (GetStatuses { group }, token) => {
+                    let statuses = self
+                        .statuses
+                        .iter()
+                        .filter(|((g, _), _)| group.as_ref().map_or(true, |group| g == group))
+                        .map(|((group, key), status)| ActorInfo {
+                            group: group.clone(),
+                            key: key.clone(),
+                            status: status.clone(),
+                        })
+                        .collect();
+
+                    self.ctx.respond(token, statuses);
+                }
+                ServerFailed(server, err) => {
+                    error!(?server, error = %err, "server failed, will be restarted");
+                    self.ctx
+                        .attach(Delay::new(RESTART_DELAY, RestartServer(server)));
+                }
+                RestartServer(server) => match server {
+                    Server::Unix => self.start_server(),
+                    Server::Http => self.start_http_server(),
+                },
+                RefreshGroups => self.refresh_groups().await,
+            });
+        }
+    }
+
+    async fn refresh_groups(&mut self) {
+        let groups = self.topology.locals().collect::<Vec<_>>();
+
+        // Forget unmounted groups, their addresses aren't reused.
+        self.subscribed
+            .retain(|addr| groups.iter().any(|group| group.addr == *addr));
+
+        for group in groups {
+            if group.addr == self.ctx.group() || self.subscribed.contains(&group.addr) {
+                continue;
+            }
+
+            // Statuses are handled by supervisors, so it doesn't block.
+            // Fails if the group is declared, but not mounted yet.
+            let res = self
+                .ctx
+                .send_to(group.addr, SubscribeToActorStatuses::default())
+                .await;
+
+            if res.is_ok() {
+                self.subscribed.insert(group.addr);
+            }
+        }
+    }
+
+    fn start_server(&mut self) {
+        // Terminate a running server.
+        if let Some(source) = self.server.take() {
+            source.terminate();
+        }
+
+        // Start a new one if enabled.
+        let config = self.ctx.config();
+        let Some(socket) = config.socket.clone() else {
+            return;
+        };
+
+        let source = Stream::once(server::server(
+            socket,
+            config.socket_mode,
+            config.idle_timeout,
+            self.topology.clone(),
+            self.ctx.pruned(),
+        ));
+
+        self.server = Some(self.ctx.attach(source));
+    }
+
+    fn start_http_server(&mut self) {
+        // Terminate a running server.
+        if let Some(source) = self.http_server.take() {
+            source.terminate();
+        }
+
+        // Start a new one if enabled.
+        let Some(addr) = self.ctx.config().http else {
+            return;
+        };
+
+        let pruned_ctx = self.ctx.pruned();
+        let source = Stream::once(http::server(addr, self.topology.clone(), pruned_ctx));
+
+        self.http_server = Some(self.ctx.attach(source));
+    }
+}
@@ -0,0 +1,54 @@
+//! Configuration for the admin.
+//!
+//! Note: all types here are exported only for documentation purposes
+//! and are not subject to stable guarantees. However, the config
+//! structure (usually encoded in TOML) follows stable guarantees.
+
+use std::{net::SocketAddr, path::PathBuf, time::Duration};
+
+use serde::Deserialize;
+
+/// Admin configuration.
+///
+/// # Example
+/// ```toml
+/// [system.admins]
+/// socket = "/run/myservice/admin.sock"
+/// socket_mode = 0o660
+/// http = "127.0.0.1:9043"
+/// ```
+#[derive(Debug, Deserialize)]
+pub struct Config {
+    /// The path to the Unix socket to listen on.
+    /// A stale socket file is removed on start.
+    ///
+    /// Disabled by default.
+    #[serde(default)]
+    pub socket: Option<PathBuf>,
+    /// Permissions of the socket file. The socket is bound in a private
+    /// directory and moved to `socket` once it has these permissions.
+    /// Anyone who can connect to the socket can operate the node.
+    ///
+    /// `0o600` (only the owner) by default.
+    #[serde(default = "default_socket_mode")]
+    pub socket_mode: u32,
+    /// How long an idle connection is kept open.
+    ///
+    /// `30s` by default.
+    #[serde(with = "humantime_serde", default = "default_idle_timeout")]
+    pub idle_timeout: Duration,
+    /// The address to expose the HTTP introspection endpoint on.
+    /// See the crate's docs for the list of routes.
+    ///
+    /// Disabled by default.
+    #[serde(default)]
+    pub http: Option<SocketAddr>,
+}
+
+fn default_socket_mode() -> u32 {
+    0o600
+}
+
+fn default_idle_timeout() -> Duration {
+    Duration::from_secs(30)
+}
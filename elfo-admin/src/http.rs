@@ -0,0 +1,167 @@
+use std::{convert::Infallible, net::SocketAddr, time::Duration};
+
+use http_body_util::Full;
+use hyper::{
+    body::{Body, Bytes},
+    header::CONTENT_TYPE,
+    server::conn,
+    service, Method, Request, Response, StatusCode,
+};
+use hyper_util::rt::{TokioIo, TokioTimer};
+use serde::Serialize;
+use serde_json::{json, Value};
+use tokio::{net::TcpListener, time::timeout};
+use tracing::{debug, info, warn};
+
+use elfo_configurer::GetConfigs;
+use elfo_core::{scope, tracing::TraceId, Context, Topology};
+
+use crate::protocol::{Server, ServerFailed};
+
+const HEADER_READ_TIMEOUT: Duration = Duration::from_secs(3);
+const SERVE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Runs a simple HTTP server that responds to introspection requests.
+/// * It supports only HTTP/1.
+/// * It doesn't support keep-alive connections.
+/// * It doesn't support TLS.
+/// * It handles requests one by one with some reasonable timeouts.
+pub(crate) async fn server(addr: SocketAddr, topology: Topology, ctx: Context) -> ServerFailed {
+    let listener = match TcpListener::bind(addr).await {
+        Ok(listener) => listener,
+        Err(err) => return ServerFailed(Server::Http, format!("cannot bind a listener: {err}")),
+    };
+
+    info!(bind = %addr, "listening TCP connections");
+
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(pair) => pair,
+            Err(err) => {
+                return ServerFailed(Server::Http, format!("cannot accept a connection: {err}"))
+            }
+        };
+
+        // The server doesn't support keep-alive connections, so every connection is a
+        // new request. Thus, we can start a new trace right here.
+        scope::set_trace_id(TraceId::generate());
+
+        debug!(peer = %peer, "accepted a TCP connection");
+        let topology = &topology;
+        let ctx = &ctx;
+
+        let serving = conn::http1::Builder::new()
+            .timer(TokioTimer::new())
+            .keep_alive(false) // KA is meaningless for rare requests.
+            .header_read_timeout(HEADER_READ_TIMEOUT)
+            .serve_connection(
+                TokioIo::new(stream),
+                service::service_fn(move |req| handle(req, topology, ctx)),
+            );
+
+        match flat_error(timeout(SERVE_TIMEOUT, serving).await) {
+            Ok(()) => debug!(peer = %peer, "finished serving a HTTP connection"),
+            Err(err) => warn!(
+                message = "failed to serve a HTTP connection",
+                error = %err,
+                peer = %peer,
+            ),
+        }
+    }
+}
+
+type ResBody = Full<Bytes>;
+
+async fn handle(
+    req: Request<impl Body>,
+    topology: &Topology,
+    ctx: &Context,
+) -> Result<Response<ResBody>, Infallible> {
+    if req.method() != Method::GET {
+        return Ok(empty(StatusCode::METHOD_NOT_ALLOWED));
+    }
+
+    let body = match req.uri().path() {
+        "/topology" => topology.export_json(),
+        "/actors" => render_actors(topology).to_string(),
+        "/restarts" => render_restarts(topology).to_string(),
+        "/configs" => match render_configs(ctx).await {
+            Ok(configs) => configs.to_string(),
+            Err(err) => {
+                warn!(error = %err, "failed to get configs for HTTP response");
+                return Ok(empty(StatusCode::SERVICE_UNAVAILABLE));
+            }
+        },
+        _ => return Ok(empty(StatusCode::NOT_FOUND)),
+    };
+
+    Ok(Response::builder()
+        .header(CONTENT_TYPE, "application/json")
+        .body(Full::new(Bytes::from(body)))
+        .unwrap())
+}
+
+fn empty(status: StatusCode) -> Response<ResBody> {
+    Response::builder()
+        .status(status)
+        .body(<_>::default())
+        .unwrap()
+}
+
+fn render_actors(topology: &Topology) -> Value {
+    let actors = topology.inspect().into_iter().flat_map(|group| {
+        let name = group.name;
+        group
+            .actors
+            .into_iter()
+            .map(move |actor| with_group(&actor, &name))
+    });
+
+    Value::Array(actors.collect())
+}
+
+fn render_restarts(topology: &Topology) -> Value {
+    let restarts = topology.inspect().into_iter().flat_map(|group| {
+        let name = group.name;
+        group
+            .restarts
+            .into_iter()
+            .map(move |restart| with_group(&restart, &name))
+    });
+
+    Value::Array(restarts.collect())
+}
+
+fn with_group(item: &impl Serialize, group: &str) -> Value {
+    let mut value = serde_json::to_value(item).expect("cannot serialize an inspection");
+    value["group"] = group.into();
+    value
+}
+
+async fn render_configs(ctx: &Context) -> Result<Value, String> {
+    let configs = ctx
+        .request(GetConfigs::default())
+        .resolve()
+        .await
+        .map_err(|err| err.to_string())?;
+
+    let configs = configs.into_iter().map(|config| {
+        // Configs are rendered as JSON by the configurer.
+        let value = serde_json::from_str(&config.config).unwrap_or(Value::String(config.config));
+        json!({
+            "group": config.group,
+            "config": value,
+            "staged": config.staged,
+        })
+    });
+
+    Ok(Value::Array(configs.collect()))
+}
+
+fn flat_error(res: Result<Result<(), impl ToString>, impl ToString>) -> Result<(), String> {
+    match res {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(err)) => Err(err.to_string()),
+        Err(err) => Err(err.to_string()),
+    }
+}
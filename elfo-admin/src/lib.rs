@@ -0,0 +1,60 @@
+//! Exposes a local control socket to operate a running node. [Configuration].
+//!
+//! The socket is a Unix socket accepting newline-delimited JSON commands,
+//! see [`protocol`] for details. It allows querying statuses of actors,
+//! reloading configs, changing log levels and toggling dump classes.
+//! The `elfoctl` command-line tool is a client of the socket.
+//!
+//! Changes of log levels and dump classes are implemented as config
+//! overrides, so they're applied by the configurer and kept on reloading,
+//! but lost on restarts of the node. Thus, the admin group must be routed to
+//! the configurer.
+//!
+//! Only the local node is controlled. A few connections are served
+//! concurrently, commands of a connection are executed sequentially.
+//! The socket is disabled by default, and it's supported only on Unix.
+//!
+//! Optionally, an HTTP server is exposed to inspect the node on any platform.
+//! It's read-only and responds to `GET` requests with JSON:
+//! * `/topology` — groups and connections between them.
+//! * `/actors` — statuses and mailboxes of actors.
+//! * `/restarts` — recent restarts of actors.
+//! * `/configs` — configs applied to groups, secrets are masked.
+//!
+//! [Configuration]: config::Config
+
+use std::time::Duration;
+
+use elfo_core::{ActorGroup, Blueprint, RestartParams, RestartPolicy, Topology};
+
+pub mod config;
+pub mod protocol;
+
+mod actor;
+mod http;
+mod server;
+
+/// Creates a blueprint.
+///
+/// # Example
+/// ```
+/// # use elfo_core as elfo;
+/// let topology = elfo::Topology::empty();
+/// let configurers = topology.local("system.configurers");
+/// let admins = topology.local("system.admins");
+///
+/// // Usually, it's `elfo::batteries::admin::new`.
+/// admins.route_all_to(&configurers);
+/// admins.mount(elfo_admin::new(&topology));
+/// ```
+pub fn new(topology: &Topology) -> Blueprint {
+    let topology = topology.clone();
+    ActorGroup::new()
+        .config::<config::Config>()
+        .restart_policy(RestartPolicy::on_failure(RestartParams::new(
+            Duration::from_secs(5),
+            Duration::from_secs(30),
+        )))
+        .stop_order(100)
+        .exec(move |ctx| actor::exec(ctx, topology.clone()))
+}
@@ -0,0 +1,108 @@
+//! Contains the protocol of the control socket.
+//!
+//! Every line written to the socket is a JSON-encoded [`Command`] and
+//! every line read from it is a JSON-encoded [`Response`], e.g.
+//! ```text
+//! > {"cmd":"status","group":"orders"}
+//! < {"ok":[{"group":"orders","key":"_","status":{"kind":"Normal","details":null}}]}
+//! > {"cmd":"set_log_level","group":"orders","level":"debug"}
+//! < {"ok":null}
+//! > {"cmd":"set_dump_class","group":"orders","class":"slow","enabled":true,"ttl":"30s"}
+//! < {"ok":null}
+//! > {"cmd":"reload_configs","force":true}
+//! < {"error":"orders: invalid config"}
+//! ```
+
+use std::time::Duration;
+
+use elfo_core::{message, ActorStatus};
+
+/// A command sent to the control socket.
+#[message(part)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum Command {
+    /// Returns a list of [`ActorInfo`] of all local actors or only the
+    /// specified group.
+    Status {
+        /// The group to filter actors.
+        #[serde(default)]
+        group: Option<String>,
+    },
+    /// Reloads configs, see `elfo_configurer::ReloadConfigs`.
+    ReloadConfigs {
+        /// Resend up-to-date configs too.
+        #[serde(default)]
+        force: bool,
+    },
+    /// Overrides `system.logging.max_level` of the group.
+    SetLogLevel {
+        /// The group to change.
+        group: String,
+        /// The level, case-insensitive, e.g. `debug` or `Off`.
+        /// If omitted, the override is removed.
+        #[serde(default)]
+        level: Option<String>,
+    },
+    /// Overrides `system.dumping.classes.<class>.disabled` of the group.
+    SetDumpClass {
+        /// The group to change.
+        group: String,
+        /// The dump class to toggle.
+        class: String,
+        /// Whether the class must be dumped.
+        /// If omitted, the override is removed.
+        #[serde(default)]
+        enabled: Option<bool>,
+        /// Restores the previous state after the time, e.g. `30s`.
+        /// See `elfo_configurer::OverrideConfig::with_ttl()`.
+        #[serde(default, with = "humantime_serde")]
+        ttl: Option<Duration>,
+    },
+}
+
+/// A response to a [`Command`].
+#[message(part)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum Response {
+    /// The command succeeded, the value depends on the command.
+    Ok(serde_json::Value),
+    /// The command failed.
+    Error(String),
+}
+
+/// The status of an actor, a response to [`Command::Status`].
+#[message(part)]
+#[non_exhaustive]
+pub struct ActorInfo {
+    /// The actor's group.
+    pub group: String,
+    /// The actor's key.
+    pub key: String,
+    /// The last reported status.
+    pub status: ActorStatus,
+}
+
+#[message(ret = Vec<ActorInfo>)]
+pub(crate) struct GetStatuses {
+    pub(crate) group: Option<String>,
+}
+
+/// A server of the admin, see `ServerFailed` and `RestartServer`.
+#[message(part)]
+#[derive(Copy, PartialEq)]
+pub(crate) enum Server {
+    Unix,
+    Http,
+}
+
+#[message]
+pub(crate) struct ServerFailed(pub(crate) Server, pub(crate) String);
+
+#[message]
+pub(crate) struct RestartServer(pub(crate) Server);
+
+/// Subscribes to statuses of groups mounted since the last refresh.
+#[message]
+pub(crate) struct RefreshGroups;
@@ -0,0 +1,341 @@
+use std::{path::PathBuf, time::Duration};
+
+use elfo_core::{Context, Topology};
+
+use crate::protocol::{Server, ServerFailed};
+
+#[cfg(unix)]
+pub(crate) use self::unix::server;
+
+/// Unix sockets aren't supported on this platform.
+#[cfg(not(unix))]
+pub(crate) async fn server(
+    _path: PathBuf,
+    _mode: u32,
+    _idle_timeout: Duration,
+    _topology: Topology,
+    _ctx: Context,
+) -> ServerFailed {
+    ServerFailed(
+        Server::Unix,
+        "unix sockets are not supported on this platform".into(),
+    )
+}
+
+#[cfg(unix)]
+mod unix {
+    use std::{
+        fs::{DirBuilder, Permissions},
+        io,
+        os::unix::fs::{DirBuilderExt, FileTypeExt, MetadataExt, PermissionsExt},
+        path::Path,
+    };
+
+    use futures::stream::{FuturesUnordered, StreamExt};
+    use tokio::{
+        io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+        net::{UnixListener, UnixStream},
+        select,
+        time::timeout,
+    };
+    use tracing::{debug, info, warn};
+
+    use elfo_configurer::{OverrideConfig, ReloadConfigs, ReloadConfigsRejected};
+    use elfo_core::{errors::RequestError, scope, tracing::TraceId};
+
+    use super::*;
+    use crate::protocol::{Command, GetStatuses, Response};
+
+    const LEVELS: &[&str] = &["Trace", "Debug", "Info", "Warn", "Error", "Off"];
+    const MAX_CONNECTIONS: usize = 4;
+    const MAX_COMMAND_SIZE: usize = 64 * 1024;
+
+    /// Runs a server that executes newline-delimited JSON commands.
+    /// * Up to `MAX_CONNECTIONS` connections are handled concurrently,
+    ///   others wait in the backlog.
+    /// * Commands of a connection are executed sequentially.
+    /// * Idle connections are closed after `idle_timeout`.
+    /// * Commands longer than `MAX_COMMAND_SIZE` close the connection.
+    /// * The socket file gets `mode` permissions before it appears at `path`.
+    /// * The socket file is removed once the server is stopped.
+    pub(crate) async fn server(
+        path: PathBuf,
+        mode: u32,
+        idle_timeout: Duration,
+        topology: Topology,
+        ctx: Context,
+    ) -> ServerFailed {
+        let fail = |error| ServerFailed(Server::Unix, error);
+
+        if let Err(err) = remove_stale_socket(&path) {
+            return fail(format!("cannot remove a stale socket: {err}"));
+        }
+
+        let listener = match bind(&path, mode) {
+            Ok(listener) => listener,
+            Err(err) => return fail(format!("cannot bind a listener: {err}")),
+        };
+
+        let guard = match SocketGuard::new(path) {
+            Ok(guard) => guard,
+            Err(err) => return fail(format!("cannot stat a bound socket: {err}")),
+        };
+
+        info!(socket = %guard.path.display(), "listening Unix connections");
+
+        let mut connections = FuturesUnordered::new();
+
+        loop {
+            select! {
+                accepted = listener.accept(), if connections.len() < MAX_CONNECTIONS => {
+                    let stream = match accepted {
+                        Ok((stream, _)) => stream,
+                        Err(err) => {
+                            return fail(format!("cannot accept a connection: {err}"))
+                        }
+                    };
+
+                    debug!("accepted a Unix connection");
+
+                    // Every connection has own trace ids, so it's run in own scope.
+                    let serving = serve(stream, idle_timeout, &topology, &ctx);
+                    connections.push(scope::with(Clone::clone).within(serving));
+                }
+                Some(result) = connections.next() => match result {
+                    Ok(()) => debug!("finished serving a Unix connection"),
+                    Err(err) => warn!(error = %err, "failed to serve a Unix connection"),
+                },
+            }
+        }
+    }
+
+    /// Binds the socket in a private directory next to `path` and moves it to
+    /// `path` once it has `mode` permissions, so it's never accessible with
+    /// permissions derived from the umask.
+    fn bind(path: &Path, mode: u32) -> io::Result<UnixListener> {
+        let parent = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+
+        // Nobody else can access the directory, even to connect to the socket.
+        let dir = parent.join(format!(".elfo-admin.{}", std::process::id()));
+        DirBuilder::new().mode(0o700).create(&dir)?;
+
+        let tmp_path = dir.join("s");
+        let result = UnixListener::bind(&tmp_path).and_then(|listener| {
+            std::fs::set_permissions(&tmp_path, Permissions::from_mode(mode))?;
+            std::fs::rename(&tmp_path, path)?;
+            Ok(listener)
+        });
+
+        let _ = std::fs::remove_file(&tmp_path);
+        let _ = std::fs::remove_dir(&dir);
+        result
+    }
+
+    /// Removes a socket file left by a previous run.
+    /// Other files are never removed to avoid losing data on misconfiguration.
+    fn remove_stale_socket(path: &Path) -> io::Result<()> {
+        let metadata = match std::fs::symlink_metadata(path) {
+            Ok(metadata) => metadata,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err),
+        };
+
+        if !metadata.file_type().is_socket() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                "the path is occupied by a file that is not a socket",
+            ));
+        }
+
+        std::fs::remove_file(path)
+    }
+
+    /// Removes the socket file on drop, unless it's replaced by another server.
+    struct SocketGuard {
+        path: PathBuf,
+        id: (u64, u64),
+    }
+
+    impl SocketGuard {
+        fn new(path: PathBuf) -> io::Result<Self> {
+            let metadata = std::fs::symlink_metadata(&path)?;
+            let id = (metadata.dev(), metadata.ino());
+            Ok(Self { path, id })
+        }
+    }
+
+    impl Drop for SocketGuard {
+        fn drop(&mut self) {
+            let Ok(metadata) = std::fs::symlink_metadata(&self.path) else {
+                return;
+            };
+
+            if (metadata.dev(), metadata.ino()) == self.id {
+                let _ = std::fs::remove_file(&self.path);
+            }
+        }
+    }
+
+    async fn serve(
+        stream: UnixStream,
+        idle_timeout: Duration,
+        topology: &Topology,
+        ctx: &Context,
+    ) -> io::Result<()> {
+        let (reader, mut writer) = stream.into_split();
+        let mut reader = BufReader::new(reader);
+
+        loop {
+            let Ok(line) = timeout(idle_timeout, read_line(&mut reader)).await else {
+                debug!("closing an idle Unix connection");
+                return Ok(());
+            };
+            let line = match line {
+                Ok(Some(line)) => line,
+                Ok(None) => return Ok(()),
+                // The rest of the line cannot be skipped reliably, so close the connection.
+                Err(err) if err.kind() == io::ErrorKind::InvalidData => {
+                    write_response(&mut writer, &Response::Error(err.to_string())).await?;
+                    return Ok(());
+                }
+                Err(err) => return Err(err),
+            };
+
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            // Every command is a new request, so start a new trace.
+            scope::set_trace_id(TraceId::generate());
+
+            let response = match serde_json::from_str::<Command>(&line) {
+                Ok(command) => {
+                    info!(?command, "executing a command");
+                    execute(command, topology, ctx).await
+                }
+                Err(err) => Response::Error(format!("invalid command: {err}")),
+            };
+
+            write_response(&mut writer, &response).await?;
+        }
+    }
+
+    /// Reads a line up to `MAX_COMMAND_SIZE` bytes. Returns `None` on EOF.
+    async fn read_line(reader: &mut (impl AsyncBufRead + Unpin)) -> io::Result<Option<String>> {
+        let mut buf = Vec::new();
+        let limit = MAX_COMMAND_SIZE as u64 + 1;
+
+        if reader.take(limit).read_until(b'\n', &mut buf).await? == 0 {
+            return Ok(None);
+        }
+
+        if buf.last() != Some(&b'\n') && buf.len() > MAX_COMMAND_SIZE {
+            let message = format!("command is longer than {MAX_COMMAND_SIZE} bytes");
+            return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+        }
+
+        String::from_utf8(buf)
+            .map(Some)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    async fn write_response(
+        writer: &mut (impl AsyncWriteExt + Unpin),
+        response: &Response,
+    ) -> io::Result<()> {
+        let mut output = serde_json::to_vec(response).expect("cannot serialize a response");
+        output.push(b'\n');
+        writer.write_all(&output).await
+    }
+
+    async fn execute(command: Command, topology: &Topology, ctx: &Context) -> Response {
+        match command {
+            Command::Status { group } => {
+                match ctx
+                    .request_to(ctx.addr(), GetStatuses { group })
+                    .resolve()
+                    .await
+                {
+                    Ok(statuses) => Response::Ok(serde_json::to_value(statuses).unwrap()),
+                    Err(err) => Response::Error(err.to_string()),
+                }
+            }
+            Command::ReloadConfigs { force } => {
+                let request = if force {
+                    ReloadConfigs::forcing()
+                } else {
+                    ReloadConfigs::default()
+                };
+                into_response(ctx.request(request).resolve().await)
+            }
+            Command::SetLogLevel { group, level } => {
+                if let Err(err) = check_group(&group, topology) {
+                    return err;
+                }
+
+                let path = format!("{group}.system.logging.max_level");
+                let request = match level {
+                    Some(level) => {
+                        let Some(level) = LEVELS.iter().find(|l| l.eq_ignore_ascii_case(&level))
+                        else {
+                            return Response::Error(format!(
+                                "unknown level `{level}`, expected one of {}",
+                                LEVELS.join(", ")
+                            ));
+                        };
+                        OverrideConfig::new(path, format!("\"{level}\""))
+                    }
+                    None => OverrideConfig::remove(path),
+                };
+                into_response(ctx.request(request).resolve().await)
+            }
+            Command::SetDumpClass {
+                group,
+                class,
+                enabled,
+                ttl,
+            } => {
+                if let Err(err) = check_group(&group, topology) {
+                    return err;
+                }
+
+                let path = format!("{group}.system.dumping.classes.{class}.disabled");
+                let request = match enabled {
+                    Some(enabled) => OverrideConfig::new(path, (!enabled).to_string()),
+                    None => OverrideConfig::remove(path),
+                };
+                let request = match ttl {
+                    Some(ttl) => request.with_ttl(ttl),
+                    None => request,
+                };
+                into_response(ctx.request(request).resolve().await)
+            }
+        }
+    }
+
+    fn check_group(group: &str, topology: &Topology) -> Result<(), Response> {
+        if topology.locals().any(|g| g.name == group) {
+            Ok(())
+        } else {
+            Err(Response::Error(format!("unknown group `{group}`")))
+        }
+    }
+
+    fn into_response(result: Result<Result<(), ReloadConfigsRejected>, RequestError>) -> Response {
+        match result {
+            Ok(Ok(())) => Response::Ok(serde_json::Value::Null),
+            Ok(Err(rejected)) => Response::Error(
+                rejected
+                    .errors
+                    .iter()
+                    .map(|err| format!("{}: {}", err.group, err.reason))
+                    .collect::<Vec<_>>()
+                    .join("; "),
+            ),
+            Err(err) => Response::Error(format!("configurer is unavailable: {err}")),
+        }
+    }
+}
@@ -0,0 +1,224 @@
+//! Computes differences between configs to report what a reload changed.
+//!
+//! Maps are compared key by key, other values (including arrays) are compared
+//! as a whole. Values under keys that look like secrets and resolved secrets
+//! are masked.
+
+use std::fmt;
+
+use serde_value::Value;
+
+use crate::{protocol::ConfigChange as Change, secrets::SecretValues};
+
+const MASK: &str = "***";
+const SECRET_MARKERS: &[&str] = &[
+    "password",
+    "passwd",
+    "secret",
+    "token",
+    "credential",
+    "private_key",
+    "api_key",
+];
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let old = self.old.as_deref().unwrap_or("<none>");
+        let new = self.new.as_deref().unwrap_or("<none>");
+        write!(f, "{}: {old} -> {new}", self.path)
+    }
+}
+
+/// Secret values are used to mask resolved secrets, see `secrets.rs`.
+pub(crate) fn diff(old: &Value, new: &Value, secrets: &[&SecretValues]) -> Vec<Change> {
+    let mut ctx = Ctx {
+        path: String::new(),
+        secrets,
+        out: Vec::new(),
+    };
+    diff_inner(&mut ctx, old, new, false);
+    ctx.out
+}
+
+struct Ctx<'a> {
+    path: String,
+    secrets: &'a [&'a SecretValues],
+    out: Vec<Change>,
+}
+
+fn diff_inner(ctx: &mut Ctx<'_>, old: &Value, new: &Value, masked: bool) {
+    match (old, new) {
+        (Value::Map(old), Value::Map(new)) => {
+            let keys = old
+                .keys()
+                .chain(new.keys().filter(|k| !old.contains_key(k)));
+
+            for key in keys {
+                let len = ctx.path.len();
+                let name = render_key(key);
+                if !ctx.path.is_empty() {
+                    ctx.path.push('.');
+                }
+                ctx.path.push_str(&name);
+
+                let masked = masked || is_secret(&name);
+
+                match (old.get(key), new.get(key)) {
+                    (Some(old), Some(new)) => diff_inner(ctx, old, new, masked),
+                    (old, new) => {
+                        let change = Change {
+                            path: ctx.path.clone(),
+                            old: old.map(|v| render_value(v, masked, ctx.secrets)),
+                            new: new.map(|v| render_value(v, masked, ctx.secrets)),
+                        };
+                        ctx.out.push(change);
+                    }
+                }
+
+                ctx.path.truncate(len);
+            }
+        }
+        (old, new) if old != new => {
+            let change = Change {
+                path: ctx.path.clone(),
+                old: Some(render_value(old, masked, ctx.secrets)),
+                new: Some(render_value(new, masked, ctx.secrets)),
+            };
+            ctx.out.push(change);
+        }
+        _ => {}
+    }
+}
+
+/// Renders the value as JSON, masking secrets.
+pub(crate) fn render(value: &Value, secrets: &SecretValues) -> String {
+    render_value(value, false, &[secrets])
+}
+
+fn is_secret(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    SECRET_MARKERS.iter().any(|marker| key.contains(marker))
+}
+
+fn render_key(key: &Value) -> String {
+    match key {
+        Value::String(key) => key.clone(),
+        key => render_value(key, false, &[]),
+    }
+}
+
+fn render_value(value: &Value, masked: bool, secrets: &[&SecretValues]) -> String {
+    if masked || matches!(value, Value::String(s) if is_resolved(s, secrets)) {
+        return MASK.into();
+    }
+
+    let value = redact(value.clone(), secrets);
+    serde_json::to_string(&value).unwrap_or_else(|_| format!("{value:?}"))
+}
+
+fn is_resolved(value: &str, secrets: &[&SecretValues]) -> bool {
+    secrets.iter().any(|s| s.contains(value))
+}
+
+fn redact(value: Value, secrets: &[&SecretValues]) -> Value {
+    match value {
+        Value::Map(map) => Value::Map(
+            map.into_iter()
+                .map(|(k, v)| {
+                    let v = if is_secret(&render_key(&k)) {
+                        Value::String(MASK.into())
+                    } else {
+                        redact(v, secrets)
+                    };
+                    (k, v)
+                })
+                .collect(),
+        ),
+        Value::Seq(seq) => Value::Seq(seq.into_iter().map(|v| redact(v, secrets)).collect()),
+        Value::String(s) if is_resolved(&s, secrets) => Value::String(MASK.into()),
+        value => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Value {
+        toml::from_str(s).unwrap()
+    }
+
+    fn change(path: &str, old: Option<&str>, new: Option<&str>) -> Change {
+        Change {
+            path: path.into(),
+            old: old.map(Into::into),
+            new: new.map(Into::into),
+        }
+    }
+
+    #[test]
+    fn changes() {
+        let old = parse(
+            r#"
+            limit = 10
+            list = [1, 2]
+            removed = "a"
+            [server]
+            host = "a"
+            port = 80
+            password = "old"
+            "#,
+        );
+        let new = parse(
+            r#"
+            limit = 20
+            list = [1, 2]
+            added = true
+            [server]
+            host = "a"
+            port = 8080
+            password = "new"
+            [auth]
+            tokens = ["x"]
+            "#,
+        );
+
+        let mut changes = diff(&old, &new, &[]);
+        changes.sort_by(|a, b| a.path.cmp(&b.path));
+
+        assert_eq!(
+            changes,
+            [
+                change("added", None, Some("true")),
+                change("auth", None, Some(r#"{"tokens":"***"}"#)),
+                change("limit", Some("10"), Some("20")),
+                change("removed", Some(r#""a""#), None),
+                change("server.password", Some(MASK), Some(MASK)),
+                change("server.port", Some("80"), Some("8080")),
+            ]
+        );
+        assert_eq!(changes[2].to_string(), "limit: 10 -> 20");
+        assert_eq!(changes[0].to_string(), "added: <none> -> true");
+
+        assert!(diff(&old, &old, &[]).is_empty());
+    }
+
+    #[test]
+    fn masking() {
+        let old = parse("[db]\nurl = \"a\"\n[db.credentials]\nuser = \"a\"");
+        let new = parse("[db]\nurl = \"b\"\n[db.credentials]\nuser = \"b\"\n[c]\nd = \"b\"");
+        let secrets = ["b".to_string()].into_iter().collect();
+
+        let mut changes = diff(&old, &new, &[&secrets]);
+        changes.sort_by(|a, b| a.path.cmp(&b.path));
+
+        assert_eq!(
+            changes,
+            [
+                change("c", None, Some(r#"{"d":"***"}"#)),
+                change("db.credentials.user", Some(MASK), Some(MASK)),
+                change("db.url", Some(r#""a""#), Some(MASK)),
+            ]
+        );
+    }
+}
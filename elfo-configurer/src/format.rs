@@ -0,0 +1,90 @@
+use std::path::Path;
+
+use serde_value::Value;
+
+/// Formats of config files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Format {
+    /// TOML, used by default.
+    Toml,
+    /// YAML, detected by `.yaml` and `.yml` extensions.
+    Yaml,
+    /// JSON, detected by the `.json` extension.
+    Json,
+}
+
+impl Format {
+    /// Detects the format by the extension of the file.
+    /// Unknown extensions are treated as TOML.
+    pub fn detect(path: &Path) -> Self {
+        let extension = path.extension().and_then(|ext| ext.to_str());
+
+        match extension.map(str::to_ascii_lowercase).as_deref() {
+            Some("yaml" | "yml") => Self::Yaml,
+            Some("json") => Self::Json,
+            _ => Self::Toml,
+        }
+    }
+
+    pub(crate) fn parse(self, content: &str) -> Result<Value, String> {
+        match self {
+            Self::Toml => toml::from_str(content).map_err(|err| err.to_string()),
+            Self::Yaml => serde_yaml::from_str(content).map_err(|err| err.to_string()),
+            Self::Json => serde_json::from_str(content).map_err(|err| err.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::helpers::lookup_value;
+
+    #[test]
+    fn detect() {
+        assert_eq!(Format::detect(Path::new("config.toml")), Format::Toml);
+        assert_eq!(Format::detect(Path::new("config")), Format::Toml);
+        assert_eq!(Format::detect(Path::new("a/config.yaml")), Format::Yaml);
+        assert_eq!(Format::detect(Path::new("config.YML")), Format::Yaml);
+        assert_eq!(Format::detect(Path::new("config.json")), Format::Json);
+    }
+
+    #[test]
+    fn parse() {
+        let toml = r#"
+            [system.loggers]
+            path = "a.log"
+            [foo]
+            limit = 10
+        "#;
+        let yaml = r#"
+            system:
+              loggers:
+                path: a.log
+            foo:
+              limit: 10
+        "#;
+        let json = r#"{ "system": { "loggers": { "path": "a.log" } }, "foo": { "limit": 10 } }"#;
+
+        for (format, content) in [
+            (Format::Toml, toml),
+            (Format::Yaml, yaml),
+            (Format::Json, json),
+        ] {
+            let value = format.parse(content).unwrap();
+
+            let path = lookup_value(&value, "system.loggers.path").unwrap();
+            assert_eq!(path, &Value::String("a.log".into()), "{format:?}");
+
+            let limit = lookup_value(&value, "foo.limit").unwrap();
+            let limit = u64::deserialize(limit.clone()).unwrap();
+            assert_eq!(limit, 10, "{format:?}");
+        }
+
+        assert!(Format::Yaml.parse("foo: [").is_err());
+        assert!(Format::Json.parse("{").is_err());
+    }
+}
@@ -11,6 +11,15 @@ pub(crate) fn lookup_value<'a>(mut value: &'a Value, path: &str) -> Option<&'a V
     Some(value)
 }
 
+/// Makes a tree containing only the value by the dot-separated path.
+pub(crate) fn make_tree(path: &str, value: Value) -> Value {
+    path.rsplit('.').fold(value, |value, part| {
+        let mut map = std::collections::BTreeMap::new();
+        map.insert(Value::String(part.to_owned()), value);
+        Value::Map(map)
+    })
+}
+
 pub(crate) fn add_defaults(config: Option<Value>, default: &Value) -> Value {
     use Value::*;
 
@@ -0,0 +1,199 @@
+//! Loads a config file with other files included by the top-level `include`
+//! key, e.g. `include = ["common.toml", "overrides/prod.toml"]`.
+//!
+//! Included files are deeply merged in the listed order, so later files
+//! override earlier ones, and the including file overrides all of them.
+//! Maps are merged key by key, other values (including arrays) are replaced.
+//!
+//! Paths are relative to the including file. Included files can include other
+//! files, their formats are detected by extensions.
+
+use std::{
+    future::Future,
+    path::{Path, PathBuf},
+    pin::Pin,
+};
+
+use serde_value::Value;
+use tokio::fs;
+
+use crate::{format::Format, helpers};
+
+const INCLUDE_KEY: &str = "include";
+
+type LoadFuture<'a> = Pin<Box<dyn Future<Output = Result<Loaded, String>> + Send + 'a>>;
+
+/// The result of loading, see [`load()`].
+#[derive(Debug)]
+pub(crate) struct Loaded {
+    pub(crate) config: Value,
+    /// Paths of the loaded file and all included ones, as they are specified.
+    pub(crate) files: Vec<PathBuf>,
+}
+
+pub(crate) fn load(path: &Path, format: Format) -> LoadFuture<'static> {
+    load_recursive(path.to_path_buf(), format, Vec::new())
+}
+
+fn load_recursive(path: PathBuf, format: Format, mut stack: Vec<PathBuf>) -> LoadFuture<'static> {
+    Box::pin(async move {
+        let canonical = fs::canonicalize(&path)
+            .await
+            .map_err(|err| format!("{}: {err}", path.display()))?;
+
+        if stack.contains(&canonical) {
+            return Err(format!("{}: recursive include", path.display()));
+        }
+
+        let content = fs::read_to_string(&path)
+            .await
+            .map_err(|err| format!("{}: {err}", path.display()))?;
+
+        let mut config = format
+            .parse(&content)
+            .map_err(|err| format!("{}: {err}", path.display()))?;
+
+        let includes =
+            take_includes(&mut config).map_err(|err| format!("{}: {err}", path.display()))?;
+
+        let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+        let mut files = vec![path];
+
+        if includes.is_empty() {
+            return Ok(Loaded { config, files });
+        }
+
+        stack.push(canonical);
+        let mut merged = Value::Map(Default::default());
+
+        for include in includes {
+            let path = dir.join(include);
+            let format = Format::detect(&path);
+            let included = load_recursive(path, format, stack.clone()).await?;
+            merged = helpers::add_defaults(Some(included.config), &merged);
+            files.extend(included.files);
+        }
+
+        let config = helpers::add_defaults(Some(config), &merged);
+        Ok(Loaded { config, files })
+    })
+}
+
+fn take_includes(config: &mut Value) -> Result<Vec<String>, String> {
+    let Value::Map(map) = config else {
+        return Ok(Vec::new());
+    };
+
+    let includes = match map.remove(&Value::String(INCLUDE_KEY.into())) {
+        None => return Ok(Vec::new()),
+        Some(Value::String(path)) => return Ok(vec![path]),
+        Some(Value::Seq(paths)) => paths,
+        Some(_) => return Err(format!("`{INCLUDE_KEY}` must be a string or an array")),
+    };
+
+    includes
+        .into_iter()
+        .map(|path| match path {
+            Value::String(path) => Ok(path),
+            _ => Err(format!("`{INCLUDE_KEY}` must contain only strings")),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn prepare(files: &[(&str, &str)]) -> TempDir {
+        let dir = tempfile::tempdir().unwrap();
+
+        for (path, content) in files {
+            let path = dir.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+
+        dir
+    }
+
+    #[tokio::test]
+    async fn merge() {
+        let dir = prepare(&[
+            (
+                "root.toml",
+                r#"
+                    include = ["common.toml", "overrides/prod.yaml"]
+                    [group]
+                    c = "root"
+                    "#,
+            ),
+            (
+                "common.toml",
+                r#"
+                    [group]
+                    a = "common"
+                    b = "common"
+                    c = "common"
+                    list = [1, 2]
+                    "#,
+            ),
+            (
+                "overrides/prod.yaml",
+                r#"
+                    include: nested.json
+                    group:
+                      b: prod
+                      c: prod
+                      list: [3]
+                    "#,
+            ),
+            ("overrides/nested.json", r#"{ "other": { "d": "nested" } }"#),
+        ]);
+
+        let loaded = load(&dir.path().join("root.toml"), Format::Toml)
+            .await
+            .unwrap();
+        let config = loaded.config;
+        let lookup = |path| helpers::lookup_value(&config, path).cloned();
+        let string = |s: &str| Some(Value::String(s.into()));
+
+        assert_eq!(lookup("group.a"), string("common"));
+        assert_eq!(lookup("group.b"), string("prod"));
+        assert_eq!(lookup("group.c"), string("root"));
+        assert_eq!(lookup("other.d"), string("nested"));
+        assert!(matches!(lookup("group.list"), Some(Value::Seq(list)) if list.len() == 1));
+        assert_eq!(lookup("include"), None);
+
+        let files = [
+            "root.toml",
+            "common.toml",
+            "overrides/prod.yaml",
+            "overrides/nested.json",
+        ];
+        assert_eq!(loaded.files, files.map(|file| dir.path().join(file)));
+    }
+
+    #[tokio::test]
+    async fn errors() {
+        let dir = prepare(&[
+            ("a.toml", r#"include = "b.toml""#),
+            ("b.toml", r#"include = ["a.toml"]"#),
+            ("c.toml", r#"include = ["missing.toml"]"#),
+            ("d.toml", r#"include = 42"#),
+        ]);
+
+        let load = |name: &str| load(&dir.path().join(name), Format::Toml);
+
+        assert!(load("a.toml")
+            .await
+            .unwrap_err()
+            .contains("recursive include"));
+        assert!(load("c.toml").await.unwrap_err().contains("missing.toml"));
+        assert!(load("d.toml")
+            .await
+            .unwrap_err()
+            .contains("must be a string"));
+    }
+}
@@ -0,0 +1,140 @@
+//! Substitutes environment variables in string values:
+//! * `${VAR}` is replaced with the value of `VAR`, which must be set.
+//! * `${VAR:-default}` is replaced with `default` if `VAR` is unset or empty.
+//! * `$${` is replaced with `${` to escape the substitution.
+//!
+//! Substituted values aren't masked in reports, credentials must be referred
+//! as secrets instead, e.g. `{ "$secret" = "env:VAR" }`, see `secrets.rs`.
+
+use serde_value::Value;
+
+pub(crate) fn interpolate_env(value: Value) -> Result<Value, String> {
+    interpolate(value, &|name| std::env::var(name).ok())
+}
+
+fn interpolate(value: Value, env: &impl Fn(&str) -> Option<String>) -> Result<Value, String> {
+    Ok(match value {
+        Value::String(s) => Value::String(interpolate_str(&s, env)?),
+        Value::Option(Some(v)) => Value::Option(Some(Box::new(interpolate(*v, env)?))),
+        Value::Newtype(v) => Value::Newtype(Box::new(interpolate(*v, env)?)),
+        Value::Seq(seq) => Value::Seq(
+            seq.into_iter()
+                .map(|v| interpolate(v, env))
+                .collect::<Result<_, _>>()?,
+        ),
+        Value::Map(map) => Value::Map(
+            map.into_iter()
+                .map(|(k, v)| Ok((k, interpolate(v, env)?)))
+                .collect::<Result<_, String>>()?,
+        ),
+        v => v,
+    })
+}
+
+fn interpolate_str(s: &str, env: &impl Fn(&str) -> Option<String>) -> Result<String, String> {
+    let mut result = String::with_capacity(s.len());
+    let mut rest = s;
+
+    while let Some(pos) = rest.find('$') {
+        result.push_str(&rest[..pos]);
+        rest = &rest[pos..];
+
+        if let Some(tail) = rest.strip_prefix("$${") {
+            result.push_str("${");
+            rest = tail;
+            continue;
+        }
+
+        let Some(tail) = rest.strip_prefix("${") else {
+            result.push('$');
+            rest = &rest[1..];
+            continue;
+        };
+
+        let end = tail
+            .find('}')
+            .ok_or_else(|| format!("unclosed `${{` in \"{s}\""))?;
+
+        let expr = &tail[..end];
+        let (name, default) = match expr.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (expr, None),
+        };
+
+        let value = match (env(name), default) {
+            (Some(value), Some(default)) if value.is_empty() => default.to_owned(),
+            (Some(value), _) => value,
+            (None, Some(default)) => default.to_owned(),
+            (None, None) => return Err(format!("environment variable `{name}` is not set")),
+        };
+
+        result.push_str(&value);
+        rest = &tail[end + 1..];
+    }
+
+    result.push_str(rest);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(name: &str) -> Option<String> {
+        match name {
+            "HOST" => Some("example.com".into()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        }
+    }
+
+    fn check(s: &str) -> Result<String, String> {
+        interpolate_str(s, &env)
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(check("plain").unwrap(), "plain");
+        assert_eq!(check("${HOST}").unwrap(), "example.com");
+        assert_eq!(
+            check("http://${HOST}:80/").unwrap(),
+            "http://example.com:80/"
+        );
+        assert_eq!(check("${HOST}${HOST}").unwrap(), "example.comexample.com");
+        assert_eq!(check("${HOST:-other}").unwrap(), "example.com");
+        assert_eq!(check("${PORT:-8080}").unwrap(), "8080");
+        assert_eq!(check("${PORT:-}").unwrap(), "");
+        assert_eq!(check("${EMPTY:-default}").unwrap(), "default");
+        assert_eq!(check("${EMPTY}").unwrap(), "");
+        assert_eq!(check("$$ and $HOST").unwrap(), "$$ and $HOST");
+        assert_eq!(check("$${HOST}").unwrap(), "${HOST}");
+
+        assert!(check("${PORT}").unwrap_err().contains("`PORT` is not set"));
+        assert!(check("${HOST").unwrap_err().contains("unclosed"));
+    }
+
+    #[test]
+    fn values() {
+        let config: Value = toml::from_str(
+            r#"
+            [group]
+            url = "http://${HOST}"
+            limit = 10
+            hosts = ["${HOST}", "b"]
+            "#,
+        )
+        .unwrap();
+
+        let expected: Value = toml::from_str(
+            r#"
+            [group]
+            url = "http://example.com"
+            limit = 10
+            hosts = ["example.com", "b"]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(interpolate(config, &env).unwrap(), expected);
+    }
+}
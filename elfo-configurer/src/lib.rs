@@ -1,14 +1,73 @@
 //! Loads and validates configs from a file or a fixture.
 //! Usually, it's used as an entrypoint in the topology.
+//!
+//! Config files can include other files by the top-level `include` key, e.g.
+//! `include = ["common.toml", "overrides/prod.toml"]`. Included files are
+//! deeply merged in the listed order, later files override earlier ones, and
+//! the including file overrides all of them. Paths are relative to the
+//! including file.
+//!
+//! String values can refer to environment variables, which are substituted on
+//! every loading: `${VAR}` requires `VAR` to be set, `${VAR:-default}` falls
+//! back to `default` if `VAR` is unset or empty, and `$${` is an escaped `${`.
+//! Substituted values aren't masked in reports, refer to credentials in
+//! environment variables as secrets instead, e.g. `{ "$secret" = "env:VAR" }`.
+//!
+//! Values can refer to secrets, which are resolved on every loading and
+//! masked in reports: `password = { "$secret" = "<provider>:<path>" }`. The
+//! `env:VAR` and `file:/path` providers are built-in, others (e.g.
+//! [`VaultProvider`]) are registered by [`Builder::secret_provider()`]. If
+//! secrets have TTL, configs are reloaded when the first one expires. Use
+//! [`Secret`](elfo_core::config::Secret) in configs to mask values in logs.
+//!
+//! Sections of keyed groups can override fields for specific actors by
+//! `[<group>.system.key_overrides.<key>]`, see [`AnyConfig`] for details.
+//!
+//! Every update logs changed values per group as `path: old -> new` and
+//! publishes [`ConfigChanged`] to the [`CONFIG_CHANGES_TOPIC`] topic. Values
+//! under keys like `password`, `token` or `secret` and resolved secrets are
+//! masked.
+//!
+//! The configurer's own section (usually, `[system.configurers]`) supports:
+//! * `export_topology = "path/to/topology"` to write the topology to
+//!   `topology.dot` and `topology.json` files on startup and reloading. See
+//!   [`Topology::export_dot()`] for details.
+//! * `export_schema = "path/to/schema.json"` to write the JSON Schema of the
+//!   config file on startup and reloading, e.g. for editors and CI. See
+//!   [`Topology::export_config_schema()`] for details.
+//! * `watch = true` to reload configs when the config file or included ones are
+//!   changed, e.g. by inotify on Linux and kqueue on BSD/macOS. Changes are
+//!   debounced by `watch_debounce` (`"500ms"` by default) and applied as by
+//!   `poll_interval`. Used only by [`from_path()`].
+//! * `poll_interval = "30s"` to reload configs periodically. Groups are updated
+//!   only if the loaded configs are changed. Disabled by default.
+//! * `fetch_retries = 3` to retry fetching from a remote [`Source`] with a
+//!   growing delay. Used only by [`from_source()`].
+//! * `rollout.<group> = { keys = ["a"], fraction = 0.1, soak = "10m" }` to
+//!   apply updates of the group's config to canaries first: actors with listed
+//!   keys and the fraction of others. The config is promoted to all actors
+//!   after `soak` or on [`PromoteConfigs`]. Only updates are staged, the config
+//!   at startup is applied to all actors.
+//! * `validation_timeout = "10s"` to limit how long every group can validate
+//!   configs. Groups that don't respond in time reject the reload, see
+//!   [`ReloadConfigsRejected`]. Unlimited by default.
+//! * `history_size = 10` to keep applied configs for [`RollbackConfig`].
+//!
+//! Values can be overridden at runtime by [`OverrideConfig`], e.g. to change
+//! the log level of a group without editing files, also temporarily by
+//! [`OverrideConfig::with_ttl()`].
 
 use std::{
+    collections::{BTreeMap, VecDeque},
     future::Future,
     path::{Path, PathBuf},
+    sync::Arc,
     time::Duration,
 };
 
 use futures::future;
-use fxhash::FxHashMap;
+use fxhash::{FxHashMap, FxHashSet};
+use metrics::increment_counter;
 use serde::{de::Deserializer, Deserialize};
 use serde_value::Value;
 use tokio::{fs, select, time};
@@ -16,18 +75,42 @@ use tracing::{error, info, warn};
 
 use elfo_core::{
     config::AnyConfig,
+    errors::{StartError, TrySendError},
+    init, message,
     messages::{
-        EntrypointError, StartEntrypoint, StartEntrypointRejected, UpdateConfig, ValidateConfig,
+        EntrypointError, StartEntrypoint, StartEntrypointRejected, UpdateConfig,
+        UpdateConfigCanary, ValidateConfig,
     },
     msg, scope,
     signal::{Signal, SignalKind},
+    time::{Delay, Interval},
     ActorGroup, ActorStatus, Addr, Blueprint, Context, RestartParams, RestartPolicy, Topology,
 };
 
-pub use self::protocol::*;
+use self::{
+    secrets::{SecretValues, Secrets},
+    tree::TreeValidator,
+    watch::{FileWatcher, FilesChanged},
+};
+
+pub use self::{
+    format::Format,
+    protocol::*,
+    secrets::{ResolvedSecret, SecretProvider, VaultProvider},
+    source::{HttpSource, Source},
+    tree::ConfigTree,
+};
 
+mod diff;
+mod format;
 mod helpers;
+mod include;
+mod interpolation;
 mod protocol;
+mod secrets;
+mod source;
+mod tree;
+mod watch;
 
 // How often warn if a group is updating a config too long.
 const WARN_INTERVAL: Duration = Duration::from_secs(5);
@@ -50,12 +133,11 @@ const WARN_INTERVAL: Duration = Duration::from_secs(5);
 /// }));
 /// ```
 pub fn fixture(topology: &Topology, config: impl for<'de> Deserializer<'de>) -> Blueprint {
-    let config = Value::deserialize(config).map_err(|err| err.to_string());
-    let source = ConfigSource::Fixture(config);
-    blueprint(topology, source)
+    builder(topology).fixture(config)
 }
 
-/// Creates a blueprint for a configurer that reads the provided TOML file.
+/// Creates a blueprint for a configurer that reads the provided file.
+/// The format is detected by the extension, see [`Format::detect()`].
 ///
 /// # Example
 /// ```
@@ -68,33 +150,397 @@ pub fn fixture(topology: &Topology, config: impl for<'de> Deserializer<'de>) ->
 /// configurers.mount(elfo_configurer::from_path(&topology, "config.toml"));
 /// ```
 pub fn from_path(topology: &Topology, path_to_config: impl AsRef<Path>) -> Blueprint {
-    let source = ConfigSource::File(path_to_config.as_ref().to_path_buf());
-    blueprint(topology, source)
+    builder(topology).from_path(path_to_config)
+}
+
+/// Creates a blueprint for a configurer that reads the provided file
+/// in the specified format regardless of the extension.
+pub fn from_path_with_format(
+    topology: &Topology,
+    path_to_config: impl AsRef<Path>,
+    format: Format,
+) -> Blueprint {
+    builder(topology).from_path_with_format(path_to_config, format)
 }
 
-fn blueprint(topology: &Topology, source: ConfigSource) -> Blueprint {
-    let topology = topology.clone();
-    ActorGroup::new()
-        .stop_order(100)
-        .restart_policy(RestartPolicy::on_failure(RestartParams::new(
-            Duration::from_secs(5),
-            Duration::from_secs(30),
-        )))
-        .exec(move |ctx| Configurer::new(ctx, topology.clone(), source.clone()).main())
+/// Creates a blueprint for a configurer that fetches configs from the
+/// provided source, e.g. [`HttpSource`]. Remote configs don't support the
+/// `include` key.
+///
+/// Failed fetches are retried, see `fetch_retries` in the crate's docs.
+/// Use `poll_interval` to fetch configs periodically.
+///
+/// # Example
+/// ```
+/// # use elfo_core as elfo;
+/// use elfo_configurer::HttpSource;
+///
+/// let topology = elfo::Topology::empty();
+/// let configurers = topology.local("configurers");
+///
+/// let source = HttpSource::new("http://configs.local/app.toml");
+/// configurers.mount(elfo_configurer::from_source(&topology, source));
+/// ```
+pub fn from_source(topology: &Topology, source: impl Source) -> Blueprint {
+    builder(topology).from_source(source)
+}
+
+/// Loads the config file and validates it against the topology without
+/// starting any actors. Useful in CI and before deploying a new config.
+///
+/// The `system.configurers` entrypoint group is added to the topology, so an
+/// error is returned if it already contains one. If a custom configurer is
+/// required (e.g. created by [`builder()`]), mount it and use
+/// [`elfo_core::init::check_only()`] instead.
+///
+/// # Example
+/// ```no_run
+/// # use elfo_core as elfo;
+/// # async fn exec() {
+/// let topology = elfo::Topology::empty();
+/// # let blueprint = elfo::ActorGroup::new().exec(|_| async {});
+/// topology.local("examples").mount(blueprint);
+///
+/// if let Err(err) = elfo_configurer::check_config(topology, "config.toml").await {
+///     eprintln!("invalid config: {err}");
+///     std::process::exit(1);
+/// }
+/// # }
+/// ```
+pub async fn check_config(
+    topology: Topology,
+    path_to_config: impl AsRef<Path>,
+) -> Result<(), StartError> {
+    const GROUP: &str = "system.configurers";
+
+    if topology.locals().any(|group| group.name == GROUP) {
+        return Err(StartError::single(
+            GROUP.into(),
+            "the group is already mounted, use `init::check_only()` instead".into(),
+        ));
+    }
+
+    let configurers = topology.local(GROUP).entrypoint();
+    configurers.mount(from_path(&topology, path_to_config));
+    init::check_only(topology).await
+}
+
+/// Loads the config file and renders sections of all mounted groups as they
+/// are decoded by groups' config types, with default values filled in.
+/// Useful for snapshot tests to catch accidental changes of defaults and
+/// renamed fields.
+///
+/// Sections are ordered by group names and rendered by `Debug`. Environment
+/// variables are substituted, but secrets aren't resolved, so sample configs
+/// should contain plain values instead.
+///
+/// # Example
+/// ```no_run
+/// # use elfo_core as elfo;
+/// # async fn exec() {
+/// let topology = elfo::Topology::empty();
+/// # let blueprint = elfo::ActorGroup::new().exec(|_| async {});
+/// topology.local("examples").mount(blueprint);
+///
+/// let snapshot = elfo_configurer::snapshot_config(&topology, "config.toml")
+///     .await
+///     .unwrap();
+///
+/// // Compare with the stored snapshot, e.g. by `insta::assert_snapshot!()`.
+/// assert!(snapshot.starts_with("[examples]"));
+/// # }
+/// ```
+pub async fn snapshot_config(
+    topology: &Topology,
+    path_to_config: impl AsRef<Path>,
+) -> Result<String, String> {
+    let path = path_to_config.as_ref();
+    let loaded = include::load(path, Format::detect(path)).await?;
+    let config = interpolation::interpolate_env(loaded.config)?;
+
+    let mut configs = match_configs(topology, &config);
+    configs.sort_by(|a, b| a.group_name.cmp(&b.group_name));
+
+    let groups = topology.locals().collect::<Vec<_>>();
+    let mut snapshot = String::new();
+
+    for item in configs {
+        let group = groups.iter().find(|group| group.addr == item.addr);
+        let Some(rendered) = group.and_then(|group| group.render_config(&item.config)) else {
+            continue;
+        };
+
+        let rendered = rendered.map_err(|err| format!("{}: {err}", item.group_name))?;
+        snapshot.push_str(&format!("[{}]\n{rendered}\n\n", item.group_name));
+    }
+
+    snapshot.pop();
+    Ok(snapshot)
+}
+
+/// Creates a builder to customize the configurer before creating a blueprint.
+///
+/// # Example
+/// ```
+/// # use elfo_core as elfo;
+/// # use serde::Deserialize;
+/// use elfo_configurer::ReloadConfigsError;
+///
+/// #[derive(Deserialize)]
+/// struct Batch {
+///     size: u32,
+///     window: u32,
+/// }
+///
+/// let topology = elfo::Topology::empty();
+/// let configurers = topology.local("configurers");
+///
+/// configurers.mount(
+///     elfo_configurer::builder(&topology)
+///         .validate_tree(|tree| {
+///             let Some(Ok(a)) = tree.get::<Batch>("a") else {
+///                 return Ok(());
+///             };
+///             let Some(Ok(b)) = tree.get::<Batch>("b") else {
+///                 return Ok(());
+///             };
+///
+///             if b.window % a.size != 0 {
+///                 let reason = "the batch size must divide `b.window`";
+///                 return Err(vec![ReloadConfigsError::new("a", reason)]);
+///             }
+///
+///             Ok(())
+///         })
+///         .from_path("config.toml"),
+/// );
+/// ```
+pub fn builder(topology: &Topology) -> Builder {
+    Builder {
+        topology: topology.clone(),
+        settings: Settings::default(),
+    }
+}
+
+/// A builder of the configurer, see [`builder()`].
+#[must_use]
+pub struct Builder {
+    topology: Topology,
+    settings: Settings,
+}
+
+#[derive(Default)]
+struct Settings {
+    tree_validators: Vec<TreeValidator>,
+    secrets: Secrets,
+}
+
+impl Builder {
+    /// Adds a validator of the whole config tree, called before sending
+    /// `ValidateConfig` to groups. It's useful to check relationships between
+    /// groups' configs. If any validator fails, the update is rejected.
+    ///
+    /// Validators are called on every reloading, even if configs are
+    /// up-to-date, and in the "check only" mode.
+    pub fn validate_tree(
+        mut self,
+        validator: impl Fn(&ConfigTree<'_>) -> Result<(), Vec<ReloadConfigsError>>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        self.settings.tree_validators.push(Box::new(validator));
+        self
+    }
+
+    /// Registers a provider of secrets referenced in configs as
+    /// `{ "$secret" = "<name>:<path>" }`. The `env` and `file` providers are
+    /// registered by default. See the crate's docs for details.
+    ///
+    /// # Example
+    /// ```
+    /// # use elfo_core as elfo;
+    /// use elfo_configurer::VaultProvider;
+    ///
+    /// let topology = elfo::Topology::empty();
+    /// let configurers = topology.local("configurers");
+    ///
+    /// configurers.mount(
+    ///     elfo_configurer::builder(&topology)
+    ///         .secret_provider("vault", VaultProvider::new("http://127.0.0.1:8200", "s.token"))
+    ///         .from_path("config.toml"),
+    /// );
+    /// ```
+    pub fn secret_provider(
+        mut self,
+        name: impl Into<String>,
+        provider: impl SecretProvider,
+    ) -> Self {
+        self.settings.secrets.add(name.into(), Arc::new(provider));
+        self
+    }
+
+    /// Creates a blueprint using the fixture, see [`fixture()`].
+    pub fn fixture(self, config: impl for<'de> Deserializer<'de>) -> Blueprint {
+        let config = Value::deserialize(config).map_err(|err| err.to_string());
+        self.build(ConfigSource::Fixture(config))
+    }
+
+    /// Creates a blueprint reading the file, see [`from_path()`].
+    pub fn from_path(self, path_to_config: impl AsRef<Path>) -> Blueprint {
+        let format = Format::detect(path_to_config.as_ref());
+        self.from_path_with_format(path_to_config, format)
+    }
+
+    /// Creates a blueprint reading the file in the specified format,
+    /// see [`from_path_with_format()`].
+    pub fn from_path_with_format(
+        self,
+        path_to_config: impl AsRef<Path>,
+        format: Format,
+    ) -> Blueprint {
+        let path = path_to_config.as_ref().to_path_buf();
+        self.build(ConfigSource::File(path, format))
+    }
+
+    /// Creates a blueprint fetching from the source, see [`from_source()`].
+    pub fn from_source(self, source: impl Source) -> Blueprint {
+        self.build(ConfigSource::Remote(Arc::new(source)))
+    }
+
+    fn build(self, source: ConfigSource) -> Blueprint {
+        let topology = self.topology;
+        let settings = Arc::new(self.settings);
+
+        ActorGroup::new()
+            .stop_order(100)
+            .restart_policy(RestartPolicy::on_failure(RestartParams::new(
+                Duration::from_secs(5),
+                Duration::from_secs(30),
+            )))
+            .exec(move |ctx| {
+                Configurer::new(ctx, topology.clone(), source.clone(), settings.clone()).main()
+            })
+    }
 }
 
 struct Configurer {
     ctx: Context,
     topology: Topology,
     source: ConfigSource,
-    /// Stores hashes of configs per group.
-    versions: FxHashMap<String, u64>,
+    settings: Arc<Settings>,
+    /// Stores applied configs per group. Addresses are used as keys,
+    /// because groups can be remounted at runtime with the same name.
+    versions: FxHashMap<Addr, Version>,
+    /// The configurer's own config, taken from the last loaded configs.
+    config: Config,
+    /// The hash of the last loaded configs to skip unchanged ones on polling.
+    last_hash: Option<u64>,
+    poll_interval: Interval<PollTick>,
+    /// Reloads configs when resolved secrets expire.
+    secrets_refresh: Interval<SecretsExpired>,
+    /// Values of secrets resolved by the last loading, to mask them.
+    /// Versions keep values they are resolved with, so old values are
+    /// masked too until all versions containing them are dropped.
+    secret_values: Arc<SecretValues>,
+    /// Configs applied only to canaries, waiting for promotion.
+    staged: FxHashMap<Addr, Staged>,
+    /// The config file and included ones, taken from the last loading.
+    files: Vec<PathBuf>,
+    watcher: Option<FileWatcher>,
+    /// Postpones reloading until files stop changing.
+    watch_debounce: Interval<FilesSettled>,
+    /// Successfully applied configs, the last one is the current.
+    history: VecDeque<Version>,
+    /// Values set by `OverrideConfig` by their paths.
+    overrides: BTreeMap<String, Value>,
+    /// Overrides with TTL by their paths, see `OverrideConfig::with_ttl()`.
+    expiring: FxHashMap<String, Expiring>,
+    /// The id of the last scheduled `OverrideExpired`.
+    last_expiring_id: u64,
 }
 
 #[derive(Clone)]
 enum ConfigSource {
-    File(PathBuf),
+    File(PathBuf, Format),
     Fixture(Result<Value, String>),
+    Remote(Arc<dyn Source>),
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(default)]
+struct Config {
+    export_topology: Option<PathBuf>,
+    export_schema: Option<PathBuf>,
+    watch: bool,
+    #[serde(with = "humantime_serde")]
+    watch_debounce: Duration,
+    #[serde(with = "humantime_serde")]
+    poll_interval: Option<Duration>,
+    fetch_retries: u32,
+    #[serde(with = "humantime_serde")]
+    validation_timeout: Option<Duration>,
+    history_size: usize,
+    rollout: FxHashMap<String, RolloutConfig>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            export_topology: None,
+            export_schema: None,
+            watch: false,
+            watch_debounce: Duration::from_millis(500),
+            poll_interval: None,
+            fetch_retries: 3,
+            validation_timeout: None,
+            history_size: 10,
+            rollout: FxHashMap::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+struct RolloutConfig {
+    #[serde(default)]
+    keys: Vec<String>,
+    #[serde(default)]
+    fraction: f64,
+    #[serde(with = "humantime_serde", default)]
+    soak: Option<Duration>,
+}
+
+struct Staged {
+    group_name: String,
+    config: AnyConfig,
+    hash: u64,
+}
+
+#[message]
+struct SoakElapsed {
+    group: String,
+    hash: u64,
+}
+
+#[message]
+struct PollTick;
+
+#[message]
+struct SecretsExpired;
+
+#[message]
+struct FilesSettled;
+
+#[message]
+struct OverrideExpired {
+    path: String,
+    id: u64,
+}
+
+struct Expiring {
+    id: u64,
+    /// The override to restore, `None` if the path wasn't overridden.
+    prev: Option<Value>,
 }
 
 #[derive(Clone)]
@@ -102,16 +548,43 @@ struct ConfigWithMeta {
     group_name: String,
     addr: Addr,
     config: AnyConfig,
+    value: Value,
     hash: u64,
 }
 
+struct Version {
+    hash: u64,
+    value: Value,
+    /// Values to mask, collected by the loading the version is made by.
+    secrets: Arc<SecretValues>,
+}
+
 impl Configurer {
-    fn new(ctx: Context, topology: Topology, source: ConfigSource) -> Self {
+    fn new(
+        mut ctx: Context,
+        topology: Topology,
+        source: ConfigSource,
+        settings: Arc<Settings>,
+    ) -> Self {
         Self {
+            poll_interval: ctx.attach(Interval::new(PollTick)),
+            secrets_refresh: ctx.attach(Interval::new(SecretsExpired)),
+            watch_debounce: ctx.attach(Interval::new(FilesSettled)),
+            secret_values: Arc::default(),
             ctx,
             topology,
             source,
+            settings,
             versions: FxHashMap::default(),
+            config: Config::default(),
+            last_hash: None,
+            staged: FxHashMap::default(),
+            files: Vec::new(),
+            watcher: None,
+            history: VecDeque::new(),
+            overrides: BTreeMap::new(),
+            expiring: FxHashMap::default(),
+            last_expiring_id: 0,
         }
     }
 
@@ -124,11 +597,17 @@ impl Configurer {
                     // actor is first started, `UpdateConfig` is consumed by the supervisor.
                     (StartEntrypoint { is_check_only, .. }, token) => {
                         fn convert_to_protocol(
-                            errors: Vec<ReloadConfigsError>,
+                            rejected: ReloadConfigsRejected,
                         ) -> Vec<EntrypointError> {
-                            errors
+                            let timed_out = rejected.timed_out.into_iter().map(|group| {
+                                EntrypointError::new(group, "the validation is timed out".into())
+                            });
+
+                            rejected
+                                .errors
                                 .into_iter()
                                 .map(|e| EntrypointError::new(e.group, e.reason))
+                                .chain(timed_out)
                                 .collect()
                         }
 
@@ -184,29 +663,96 @@ impl Configurer {
         } {
             msg!(match envelope {
                 (ReloadConfigs { force }, token) => {
-                    let response = self
-                        .load_and_update_configs(force)
-                        .await
-                        .map_err(|errors| ReloadConfigsRejected { errors });
+                    let response = self.load_and_update_configs(force).await;
+
+                    self.ctx.respond(token, response);
+                }
+                (RollbackConfig { steps }, token) => {
+                    let response = self.rollback(steps).await;
 
                     self.ctx.respond(token, response);
                 }
+                (OverrideConfig { path, value, ttl }, token) => {
+                    let response = self.override_config(path, value, ttl).await;
+
+                    self.ctx.respond(token, response);
+                }
+                OverrideExpired { path, id } => {
+                    if self.expiring.get(&path).is_some_and(|e| e.id == id) {
+                        let prev = self.expiring.remove(&path).and_then(|e| e.prev);
+                        self.restore_override(path, prev).await;
+                    }
+                }
+                PollTick | SecretsExpired => self.poll().await,
+                FilesChanged => {
+                    let debounce = self.config.watch_debounce.max(Duration::from_millis(1));
+                    self.watch_debounce.start_after(debounce, debounce);
+                }
+                FilesSettled => {
+                    self.watch_debounce.stop();
+                    self.poll().await;
+                }
+                (GetConfigs { group }, token) => {
+                    let configs = self.get_configs(group.as_deref());
+
+                    self.ctx.respond(token, configs);
+                }
+                (PromoteConfigs { group }, token) => {
+                    let addrs = self
+                        .staged
+                        .iter()
+                        .filter(|(_, s)| group.as_ref().map_or(true, |g| *g == s.group_name))
+                        .map(|(addr, _)| *addr)
+                        .collect::<Vec<_>>();
+
+                    for addr in addrs {
+                        self.promote(addr);
+                    }
+
+                    self.ctx.respond(token, ());
+                }
+                SoakElapsed { group, hash } => {
+                    let addr = self
+                        .staged
+                        .iter()
+                        .find(|(_, s)| s.group_name == group && s.hash == hash)
+                        .map(|(addr, _)| *addr);
+
+                    if let Some(addr) = addr {
+                        self.promote(addr);
+                    }
+                }
             })
         }
     }
 
-    async fn load_configs(&self) -> Result<Value, Vec<ReloadConfigsError>> {
+    async fn load_configs(&mut self) -> Result<Value, Vec<ReloadConfigsError>> {
         let config = match &self.source {
-            ConfigSource::File(path) => {
+            ConfigSource::File(path, format) => {
                 info!(message = "loading a config", path = %path.to_string_lossy());
-                load_raw_config(path).await
+                include::load(path, *format).await.map(|loaded| {
+                    self.files = loaded.files;
+                    loaded.config
+                })
             }
             ConfigSource::Fixture(value) => {
                 info!("using a fixture");
                 value.clone()
             }
+            ConfigSource::Remote(source) => {
+                info!("fetching a config");
+                self.fetch(&**source).await
+            }
         };
 
+        let config = config.and_then(interpolation::interpolate_env);
+        let config = match config {
+            Ok(config) => self.resolve_secrets(config).await,
+            Err(error) => Err(error),
+        };
+
+        let config = config.map(|config| self.apply_overrides(config));
+
         let config = match config {
             Ok(config) => config,
             Err(error) => {
@@ -227,34 +773,172 @@ impl Configurer {
         })
     }
 
-    async fn load_and_check_configs(&self) -> Result<(), Vec<ReloadConfigsError>> {
+    fn apply_overrides(&self, mut config: Value) -> Value {
+        for (path, value) in &self.overrides {
+            let tree = helpers::make_tree(path, value.clone());
+            config = helpers::add_defaults(Some(tree), &config);
+        }
+        config
+    }
+
+    async fn override_config(
+        &mut self,
+        path: String,
+        value: Option<String>,
+        ttl: Option<Duration>,
+    ) -> Result<(), ReloadConfigsRejected> {
+        let prev = match value {
+            Some(value) => match serde_json::from_str::<Value>(&value) {
+                Ok(value) => self.overrides.insert(path.clone(), value),
+                Err(error) => {
+                    let group = scope::meta().group.clone();
+                    let reason = format!("invalid value of `{path}`: {error}");
+                    return Err(vec![ReloadConfigsError { group, reason }].into());
+                }
+            },
+            None => self.overrides.remove(&path),
+        };
+
+        info!(message = "overriding a config", path = %path, value = ?self.overrides.get(&path));
+        let result = self.load_and_update_configs(false).await;
+
+        match (&result, ttl) {
+            (Err(_), _) => {
+                warn!(path = %path, "the override is rejected, reverting");
+                match prev {
+                    Some(prev) => self.overrides.insert(path, prev),
+                    None => self.overrides.remove(&path),
+                };
+            }
+            (Ok(()), Some(ttl)) => self.expire_override(path, prev, ttl),
+            (Ok(()), None) => {
+                self.expiring.remove(&path);
+            }
+        }
+
+        result
+    }
+
+    fn expire_override(&mut self, path: String, prev: Option<Value>, ttl: Duration) {
+        self.last_expiring_id += 1;
+        let id = self.last_expiring_id;
+
+        // Restore the value before the first override if they're repeated.
+        let prev = match self.expiring.remove(&path) {
+            Some(expiring) => expiring.prev,
+            None => prev,
+        };
+
+        let message = OverrideExpired {
+            path: path.clone(),
+            id,
+        };
+        self.ctx.attach(Delay::new(ttl, message));
+        self.expiring.insert(path, Expiring { id, prev });
+    }
+
+    async fn restore_override(&mut self, path: String, prev: Option<Value>) {
+        info!(message = "the override is expired, restoring", path = %path, value = ?prev);
+
+        match prev {
+            Some(prev) => self.overrides.insert(path, prev),
+            None => self.overrides.remove(&path),
+        };
+
+        // Failures are logged, the restored override is applied on next reloads.
+        let _ = self.load_and_update_configs(false).await;
+    }
+
+    async fn resolve_secrets(&mut self, config: Value) -> Result<Value, String> {
+        let resolved = self.settings.secrets.resolve(config).await?;
+        self.secret_values = Arc::new(resolved.values);
+
+        match resolved.ttl.filter(|ttl| !ttl.is_zero()) {
+            Some(ttl) => self.secrets_refresh.start_after(ttl, ttl),
+            None => self.secrets_refresh.stop(),
+        }
+
+        Ok(resolved.config)
+    }
+
+    async fn fetch(&self, source: &dyn Source) -> Result<Value, String> {
+        let mut attempt = 0;
+
+        loop {
+            match source.fetch().await {
+                Ok(content) => return source.format().parse(&content),
+                Err(error) if attempt < self.config.fetch_retries => {
+                    attempt += 1;
+                    let delay = Duration::from_secs(u64::from(attempt));
+                    warn!(%error, ?delay, "cannot fetch a config, retrying");
+                    time::sleep(delay).await;
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    async fn load_and_check_configs(&mut self) -> Result<(), ReloadConfigsRejected> {
         let configs = self.load_configs().await?;
 
         // Here we rely on the fact that the first `ValidateConfig` message is consumed
         // by the supervisor and no actors are actually started.
         let configs = match_configs(&self.topology, &configs);
+        self.validate_tree(&configs)?;
         self.validate_all(&configs).await
     }
 
-    async fn load_and_update_configs(
+    async fn load_and_update_configs(&mut self, force: bool) -> Result<(), ReloadConfigsRejected> {
+        let configs = self.load_configs().await?;
+        self.update_configs(configs, force).await
+    }
+
+    async fn poll(&mut self) {
+        let Ok(configs) = self.load_configs().await else {
+            return;
+        };
+
+        if self.last_hash == Some(fxhash::hash64(&configs)) {
+            return;
+        }
+
+        let _ = self.update_configs(configs, false).await;
+    }
+
+    async fn update_configs(
         &mut self,
+        configs: Value,
         force: bool,
-    ) -> Result<(), Vec<ReloadConfigsError>> {
-        let configs = self.load_configs().await?;
+    ) -> Result<(), ReloadConfigsRejected> {
+        let applied = Version {
+            hash: fxhash::hash64(&configs),
+            value: configs.clone(),
+            secrets: self.secret_values.clone(),
+        };
+
+        self.last_hash = Some(applied.hash);
+        self.forget_unmounted();
+        self.apply_own_config(&configs).await;
 
         let mut configs = match_configs(&self.topology, &configs);
 
+        if let Err(errors) = self.validate_tree(&configs) {
+            error!("config tree validation failed");
+            return Err(errors.into());
+        }
+
         // Filter out up-to-date configs if needed.
         if !force {
             configs.retain(|c| {
                 self.versions
-                    .get(&c.group_name)
-                    .map_or(true, |v| c.hash != *v)
+                    .get(&c.addr)
+                    .map_or(true, |v| c.hash != v.hash)
             });
         }
 
         if configs.is_empty() {
             info!("all groups' configs are up-to-date, nothing to update");
+            self.remember(applied);
             return Ok(());
         }
 
@@ -266,10 +950,10 @@ impl Configurer {
         let status = ActorStatus::NORMAL.with_details("validating");
         self.ctx.set_status(status);
 
-        if let Err(errors) = self.validate_all(&configs).await {
+        if let Err(rejected) = self.validate_all(&configs).await {
             error!("config validation failed");
             self.ctx.set_status(ActorStatus::NORMAL);
-            return Err(errors);
+            return Err(rejected);
         }
 
         // Updating.
@@ -280,26 +964,237 @@ impl Configurer {
         self.ctx.set_status(ActorStatus::NORMAL);
 
         // Update versions.
-        let updated_groups: Vec<String> = configs
-            .into_iter()
-            .inspect(|config| {
-                self.versions.insert(config.group_name.clone(), config.hash);
-            })
-            .map(|config| config.group_name)
-            .collect();
+        let mut updated_groups = Vec::with_capacity(configs.len());
+        for config in configs {
+            let version = Version {
+                hash: config.hash,
+                value: config.value.clone(),
+                secrets: self.secret_values.clone(),
+            };
+
+            if let Some(prev) = self.versions.insert(config.addr, version) {
+                let new = &self.versions[&config.addr];
+                self.report_diff(&config.group_name, &prev, new);
+            }
+
+            updated_groups.push(config.group_name);
+        }
 
         info!(
             message = "groups' configs are updated",
             groups = ?updated_groups,
         );
 
+        self.remember(applied);
         Ok(())
     }
 
-    async fn validate_all(
-        &self,
-        configs: &[ConfigWithMeta],
-    ) -> Result<(), Vec<ReloadConfigsError>> {
+    /// Drops the state of groups unmounted since the last update.
+    fn forget_unmounted(&mut self) {
+        let mounted = self
+            .topology
+            .locals()
+            .map(|local| local.addr)
+            .collect::<FxHashSet<_>>();
+
+        self.versions.retain(|addr, _| mounted.contains(addr));
+        self.staged.retain(|addr, _| mounted.contains(addr));
+    }
+
+    fn get_configs(&self, group: Option<&str>) -> Vec<GroupConfig> {
+        self.topology
+            .locals()
+            .filter(|local| group.map_or(true, |g| g == local.name))
+            .filter_map(|local| {
+                let version = self.versions.get(&local.addr)?;
+                Some(GroupConfig {
+                    config: diff::render(&version.value, &version.secrets),
+                    staged: self.staged.contains_key(&local.addr),
+                    group: local.name,
+                })
+            })
+            .collect()
+    }
+
+    fn remember(&mut self, applied: Version) {
+        if self.history.back().is_some_and(|v| v.hash == applied.hash) {
+            return;
+        }
+
+        self.history.push_back(applied);
+        while self.history.len() > self.config.history_size.max(1) {
+            self.history.pop_front();
+        }
+    }
+
+    async fn rollback(&mut self, steps: usize) -> Result<(), ReloadConfigsRejected> {
+        let Some(index) = self.history.len().checked_sub(steps + 1) else {
+            let reason = format!(
+                "cannot roll back {steps} steps, only {} previous configs are kept",
+                self.history.len().saturating_sub(1)
+            );
+            error!(%reason, "invalid rollback");
+            return Err(vec![ReloadConfigsError::new(scope::meta().group.clone(), reason)].into());
+        };
+
+        info!(steps, "rolling back configs");
+
+        // Forget rolled back configs before applying, so the restored version
+        // is the last one and isn't remembered again, and repeated rollbacks go
+        // further back. They are returned if the rollback fails.
+        let rolled_back = self.history.split_off(index + 1);
+
+        // Keep the hash of the loaded configs, so polling and watching don't
+        // reapply them until they are changed.
+        let last_hash = self.last_hash;
+        let configs = self.history[index].value.clone();
+        let result = self.update_configs(configs, false).await;
+        self.last_hash = last_hash;
+
+        if let Err(rejected) = result {
+            self.history.extend(rolled_back);
+            return Err(rejected);
+        }
+
+        // Rollbacks are mitigations, so they aren't staged. All staged configs
+        // belong to the rolled back version now, even if they are unchanged.
+        let staged = self.staged.keys().copied().collect::<Vec<_>>();
+        for addr in staged {
+            self.promote(addr);
+        }
+
+        Ok(())
+    }
+
+    fn report_diff(&self, group: &str, old: &Version, new: &Version) {
+        let diff = diff::diff(&old.value, &new.value, &[&old.secrets, &new.secrets]);
+        if diff.is_empty() {
+            return;
+        }
+
+        let changes = diff
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("; ");
+
+        info!(message = "the config is changed", %group, %changes);
+
+        let message = ConfigChanged {
+            group: group.into(),
+            diff,
+        };
+
+        // Slow subscribers must not block updating configs, so changes are
+        // dropped for them. Fails also if there are no subscribers, it's fine.
+        if let Err(TrySendError::Full(_)) = self.ctx.try_publish(CONFIG_CHANGES_TOPIC, message) {
+            increment_counter!("elfo_dropped_config_changes_total");
+        }
+    }
+
+    async fn apply_own_config(&mut self, configs: &Value) {
+        let group = &scope::meta().group;
+        let config = helpers::lookup_value(configs, group).cloned();
+        let config = match config.map(Config::deserialize).transpose() {
+            Ok(config) => config.unwrap_or_default(),
+            Err(error) => {
+                warn!(%error, "invalid configurer's config");
+                return;
+            }
+        };
+
+        if config.poll_interval != self.config.poll_interval {
+            match config.poll_interval.filter(|period| !period.is_zero()) {
+                Some(period) => self.poll_interval.start_after(period, period),
+                None => self.poll_interval.stop(),
+            }
+        }
+
+        self.config = config;
+        self.watch_files();
+        self.export_topology().await;
+        self.export_schema().await;
+    }
+
+    fn watch_files(&mut self) {
+        if !self.config.watch || self.files.is_empty() {
+            self.watcher = None;
+            return;
+        }
+
+        if self
+            .watcher
+            .as_ref()
+            .is_some_and(|w| w.files() == self.files)
+        {
+            return;
+        }
+
+        // Drop the previous watcher first to avoid duplicated events.
+        self.watcher = None;
+        match FileWatcher::new(&mut self.ctx, self.files.clone()) {
+            Ok(watcher) => {
+                info!(dirs = ?watcher.dirs(), "watching config files");
+                self.watcher = Some(watcher);
+            }
+            Err(error) => warn!(%error, "cannot watch config files"),
+        }
+    }
+
+    async fn export_topology(&self) {
+        let Some(path) = &self.config.export_topology else {
+            return;
+        };
+
+        let exports = [
+            ("dot", self.topology.export_dot()),
+            ("json", self.topology.export_json()),
+        ];
+
+        for (extension, content) in exports {
+            let path = path.with_extension(extension);
+            if let Err(error) = fs::write(&path, content).await {
+                warn!(%error, path = %path.to_string_lossy(), "cannot export the topology");
+            }
+        }
+
+        info!(path = %path.to_string_lossy(), "the topology is exported");
+    }
+
+    async fn export_schema(&self) {
+        let Some(path) = &self.config.export_schema else {
+            return;
+        };
+
+        let schema = self.topology.export_config_schema();
+        match fs::write(path, schema).await {
+            Ok(()) => info!(path = %path.to_string_lossy(), "the config schema is exported"),
+            Err(error) => {
+                warn!(%error, path = %path.to_string_lossy(), "cannot export the config schema")
+            }
+        }
+    }
+
+    fn validate_tree(&self, configs: &[ConfigWithMeta]) -> Result<(), Vec<ReloadConfigsError>> {
+        let tree = ConfigTree::new(configs);
+        let errors = self
+            .settings
+            .tree_validators
+            .iter()
+            .filter_map(|validator| validator(&tree).err())
+            .flatten()
+            .inspect(|e| error!(group = %e.group, reason = %e.reason, "invalid config tree"))
+            .collect::<Vec<_>>();
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    async fn validate_all(&self, configs: &[ConfigWithMeta]) -> Result<(), ReloadConfigsRejected> {
+        let deadline = self.config.validation_timeout;
         let futures = configs
             .iter()
             .cloned()
@@ -311,6 +1206,14 @@ impl Configurer {
                     .all()
                     .resolve();
 
+                // `None` if the group doesn't respond in time.
+                let fut = async move {
+                    match deadline {
+                        Some(deadline) => time::timeout(deadline, fut).await.ok(),
+                        None => Some(fut.await),
+                    }
+                };
+
                 wrap_long_running_future(
                     fut,
                     group,
@@ -319,30 +1222,51 @@ impl Configurer {
             })
             .collect::<Vec<_>>();
 
-        let errors = future::join_all(futures)
-            .await
-            .into_iter()
-            .flat_map(|(group, results)| results.into_iter().map(move |res| (group.clone(), res)))
-            .filter_map(|(group, result)| match result {
-                // NOTE: Since actors discard `ValidateConfig` by default, it is ok to receive
-                // `Err(RequestError::Closed(..))` here.
-                Ok(Ok(_)) | Err(_) => None,
-                Ok(Err(reject)) => Some((group, reject.reason)),
-            })
-            // TODO: include actor keys in the error message.
-            .inspect(|(group, reason)| error!(%group, %reason, "invalid config"))
-            .map(|(group, reason)| ReloadConfigsError { group, reason })
-            .collect::<Vec<_>>();
+        let mut rejected = ReloadConfigsRejected::default();
 
-        if errors.is_empty() {
+        for (group, results) in future::join_all(futures).await {
+            let Some(results) = results else {
+                error!(%group, ?deadline, "the config validation is timed out");
+                rejected.timed_out.push(group);
+                continue;
+            };
+
+            let errors = results
+                .into_iter()
+                .filter_map(|result| match result {
+                    // NOTE: Since actors discard `ValidateConfig` by default, it is ok to receive
+                    // `Err(RequestError::Closed(..))` here.
+                    Ok(Ok(_)) | Err(_) => None,
+                    Ok(Err(reject)) => Some(reject.reason),
+                })
+                // TODO: include actor keys in the error message.
+                .inspect(|reason| error!(%group, %reason, "invalid config"))
+                .map(|reason| ReloadConfigsError::new(group.clone(), reason))
+                .collect::<Vec<_>>();
+
+            if errors.is_empty() {
+                rejected.validated.push(group);
+            } else {
+                rejected.errors.extend(errors);
+            }
+        }
+
+        if rejected.errors.is_empty() && rejected.timed_out.is_empty() {
             Ok(())
         } else {
-            Err(errors)
+            Err(rejected)
         }
     }
 
-    async fn update_all(&self, configs: &[ConfigWithMeta]) {
+    async fn update_all(&mut self, configs: &[ConfigWithMeta]) {
         for item in configs {
+            let rollout = self.config.rollout.get(&item.group_name);
+            if let Some(rollout) = rollout.filter(|_| self.versions.contains_key(&item.addr)) {
+                self.stage(item, rollout.clone());
+                continue;
+            }
+
+            self.staged.remove(&item.addr);
             let message = UpdateConfig::new(item.config.clone()); // cheap due to `Arc`s.
 
             // While `UpdateConfig` is defined as a request to cover more use cases, default
@@ -353,6 +1277,47 @@ impl Configurer {
             let _ = self.ctx.unbounded_send_to(item.addr, message);
         }
     }
+
+    fn stage(&mut self, item: &ConfigWithMeta, rollout: RolloutConfig) {
+        let message = UpdateConfigCanary::new(item.config.clone(), rollout.keys, rollout.fraction);
+        let _ = self.ctx.unbounded_send_to(item.addr, message);
+
+        info!(
+            message = "the config is applied to canaries",
+            group = %item.group_name,
+            soak = ?rollout.soak,
+        );
+
+        if let Some(soak) = rollout.soak {
+            let message = SoakElapsed {
+                group: item.group_name.clone(),
+                hash: item.hash,
+            };
+            self.ctx.attach(Delay::new(soak, message));
+        }
+
+        let staged = Staged {
+            group_name: item.group_name.clone(),
+            config: item.config.clone(),
+            hash: item.hash,
+        };
+        self.staged.insert(item.addr, staged);
+    }
+
+    fn promote(&mut self, addr: Addr) {
+        let Some(staged) = self.staged.remove(&addr) else {
+            return;
+        };
+
+        let _ = self
+            .ctx
+            .unbounded_send_to(addr, UpdateConfig::new(staged.config));
+
+        info!(
+            message = "the config is promoted to all actors",
+            group = %staged.group_name,
+        );
+    }
 }
 
 async fn wrap_long_running_future<F: Future>(
@@ -371,13 +1336,6 @@ async fn wrap_long_running_future<F: Future>(
     }
 }
 
-async fn load_raw_config(path: impl AsRef<Path>) -> Result<Value, String> {
-    let content = fs::read_to_string(path)
-        .await
-        .map_err(|err| err.to_string())?;
-    toml::from_str(&content).map_err(|err| err.to_string())
-}
-
 fn match_configs(topology: &Topology, config: &Value) -> Vec<ConfigWithMeta> {
     let mut configs: Vec<ConfigWithMeta> = topology
         .locals()
@@ -391,7 +1349,8 @@ fn match_configs(topology: &Topology, config: &Value) -> Vec<ConfigWithMeta> {
                 group_name: group.name.clone(),
                 addr: group.addr,
                 hash: fxhash::hash64(&group_config),
-                config: AnyConfig::from_value(group_config),
+                config: AnyConfig::from_value(group_config.clone()),
+                value: group_config,
             }
         })
         .collect();
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use elfo_core::message;
 
 /// The request to reload configs and send changed ones.
@@ -17,12 +19,138 @@ impl ReloadConfigs {
     }
 }
 
-/// The response to `ReloadConfigs`.
+/// The request to apply staged configs to all actors of groups.
+/// Configs are staged if the rollout is configured for a group, see the
+/// crate's docs. By default, all groups are promoted.
+#[message(ret = ())]
+#[derive(Default)]
+pub struct PromoteConfigs {
+    pub(crate) group: Option<String>,
+}
+
+impl PromoteConfigs {
+    /// Only the specified group will be promoted.
+    pub fn group(name: impl Into<String>) -> Self {
+        Self {
+            group: Some(name.into()),
+        }
+    }
+}
+
+/// The request to reapply one of previously applied configs, `steps` back
+/// from the current one. Configs are validated and distributed as by
+/// `ReloadConfigs`, rolled back ones are forgotten, so repeated requests go
+/// further back. Configs are applied to all actors, even if the rollout is
+/// configured. See `history_size` in the crate's docs.
+///
+/// Polling and watching don't reapply loaded configs until they are changed.
+#[message(ret = Result<(), ReloadConfigsRejected>)]
+pub struct RollbackConfig {
+    pub(crate) steps: usize,
+}
+
+impl RollbackConfig {
+    /// Creates a request to roll back `steps` applied configs.
+    pub fn new(steps: usize) -> Self {
+        Self { steps }
+    }
+}
+
+/// The request to override a value of loaded configs at runtime by the
+/// dot-separated path, e.g. `orders.system.logging.max_level`. Overrides are
+/// applied on top of every loading until removed, and configs are reloaded as
+/// by `ReloadConfigs`. If the reload is rejected, the override is reverted.
+///
+/// Overrides are kept in memory, so they're lost on restarts of the configurer.
+#[message(ret = Result<(), ReloadConfigsRejected>)]
+pub struct OverrideConfig {
+    pub(crate) path: String,
+    pub(crate) value: Option<String>,
+    pub(crate) ttl: Option<Duration>,
+}
+
+impl OverrideConfig {
+    /// Creates a request to set the value encoded as JSON, e.g. `"Debug"`.
+    pub fn new(path: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            value: Some(value.into()),
+            ttl: None,
+        }
+    }
+
+    /// Creates a request to remove the override of the value.
+    pub fn remove(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            value: None,
+            ttl: None,
+        }
+    }
+
+    /// Restores the previous override of the path (or its absence) after `ttl`.
+    /// Overrides of the path without TTL cancel restoring, and overrides with
+    /// TTL postpone it, still restoring the value before the first one.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+}
+
+/// The request to get configs applied to groups. Values are masked as in
+/// [`ConfigChanged`]. By default, configs of all groups are returned.
+#[message(ret = Vec<GroupConfig>)]
+#[derive(Default)]
+pub struct GetConfigs {
+    pub(crate) group: Option<String>,
+}
+
+impl GetConfigs {
+    /// Only the config of the specified group will be returned.
+    pub fn group(name: impl Into<String>) -> Self {
+        Self {
+            group: Some(name.into()),
+        }
+    }
+}
+
+/// The config applied to a group, a response to [`GetConfigs`].
 #[message(part)]
 #[non_exhaustive]
+pub struct GroupConfig {
+    /// The group's name.
+    pub group: String,
+    /// The config rendered as JSON, masked the same way as [`ConfigChanged`].
+    pub config: String,
+    /// Whether the config is applied only to canaries, see [`PromoteConfigs`].
+    pub staged: bool,
+}
+
+/// The response to `ReloadConfigs`, `RollbackConfig` and `OverrideConfig`.
+///
+/// Groups are validated only if configs are loaded and the config tree is
+/// valid, otherwise `validated` and `timed_out` are empty.
+#[message(part)]
+#[derive(Default)]
+#[non_exhaustive]
 pub struct ReloadConfigsRejected {
     /// All reasons why configs cannot be updated.
     pub errors: Vec<ReloadConfigsError>,
+    /// Groups that accepted their configs.
+    #[serde(default)]
+    pub validated: Vec<String>,
+    /// Groups that didn't respond in `validation_timeout`.
+    #[serde(default)]
+    pub timed_out: Vec<String>,
+}
+
+impl From<Vec<ReloadConfigsError>> for ReloadConfigsRejected {
+    fn from(errors: Vec<ReloadConfigsError>) -> Self {
+        Self {
+            errors,
+            ..Self::default()
+        }
+    }
 }
 
 /// Contains a reason why some actor rejects the config.
@@ -34,3 +162,55 @@ pub struct ReloadConfigsError {
     /// The reason why the config is rejected.
     pub reason: String,
 }
+
+impl ReloadConfigsError {
+    /// Creates a new error, used by tree validators.
+    pub fn new(group: impl Into<String>, reason: impl Into<String>) -> Self {
+        Self {
+            group: group.into(),
+            reason: reason.into(),
+        }
+    }
+}
+
+/// The name of the topic, where [`ConfigChanged`] events are published.
+///
+/// Events are published without waiting, so subscribers with full mailboxes
+/// miss them, which is counted by `elfo_dropped_config_changes_total`.
+///
+/// # Example
+/// ```ignore
+/// ctx.subscribe(elfo::batteries::configurer::CONFIG_CHANGES_TOPIC);
+///
+/// while let Some(envelope) = ctx.recv().await {
+///     msg!(match envelope {
+///         ConfigChanged { group, diff } => { /* ... */ }
+///     });
+/// }
+/// ```
+pub const CONFIG_CHANGES_TOPIC: &str = "system.configurers.changes";
+
+/// Published to [`CONFIG_CHANGES_TOPIC`] when the config of a group is updated.
+/// It isn't published when the group gets its first config.
+#[message]
+#[non_exhaustive]
+pub struct ConfigChanged {
+    /// The updated group.
+    pub group: String,
+    /// Changed values. Values under keys that look like secrets and resolved
+    /// secrets are masked.
+    pub diff: Vec<ConfigChange>,
+}
+
+/// A single changed value in [`ConfigChanged`].
+#[message(part)]
+#[derive(PartialEq)]
+#[non_exhaustive]
+pub struct ConfigChange {
+    /// The dot-separated path to the value, e.g. `server.port`.
+    pub path: String,
+    /// The old value rendered as JSON, `None` if the value is added.
+    pub old: Option<String>,
+    /// The new value rendered as JSON, `None` if the value is removed.
+    pub new: Option<String>,
+}
@@ -0,0 +1,398 @@
+//! Resolves references to secrets in configs, e.g.
+//! `password = { "$secret" = "vault:secret/db#password" }`.
+//!
+//! A reference is a map with the only `$secret` key, which value is
+//! `<provider>:<path>`. Built-in providers:
+//! * `env:VAR` reads the environment variable.
+//! * `file:/path/to/file` reads the file, trailing newlines are trimmed.
+//!
+//! Other providers are registered by
+//! [`Builder::secret_provider()`](crate::Builder::secret_provider).
+
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+
+use futures::future::BoxFuture;
+use fxhash::{FxHashMap, FxHashSet};
+use hyper::Uri;
+use serde_value::Value;
+use tokio::time::timeout;
+
+use crate::source;
+
+const REFERENCE_KEY: &str = "$secret";
+
+/// A provider of secrets referenced in configs, see
+/// [`Builder::secret_provider()`](crate::Builder::secret_provider).
+pub trait SecretProvider: Send + Sync + 'static {
+    /// Resolves the secret by the path, which is a part of the reference
+    /// after `<provider>:`.
+    fn resolve<'a>(&'a self, path: &'a str) -> BoxFuture<'a, Result<ResolvedSecret, String>>;
+}
+
+/// A secret returned by [`SecretProvider`].
+#[derive(Clone)]
+#[non_exhaustive]
+pub struct ResolvedSecret {
+    /// The value of the secret.
+    pub value: String,
+    /// How long the secret is valid. Configs are reloaded after expiration.
+    pub ttl: Option<Duration>,
+}
+
+impl ResolvedSecret {
+    /// Creates a secret without expiration.
+    pub fn new(value: impl Into<String>) -> Self {
+        Self {
+            value: value.into(),
+            ttl: None,
+        }
+    }
+
+    /// Sets how long the secret is valid.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+}
+
+/// Fetches secrets from HashiCorp Vault's KV secrets engine.
+/// References look like `vault:secret/data/db#password`, where the path is
+/// requested as `GET /v1/<path>` and `password` is a key in the secret.
+/// Both KV v1 and v2 responses are supported.
+///
+/// * It supports only HTTP/1.
+/// * `https` requires the `tls` feature, servers are verified by Mozilla's root
+///   certificates. Without TLS, the token and secrets are sent in plaintext, so
+///   use `http` only for local agents.
+///
+/// # Example
+/// ```
+/// use elfo_configurer::VaultProvider;
+///
+/// let provider = VaultProvider::new("http://127.0.0.1:8200", "s.token");
+/// ```
+pub struct VaultProvider {
+    uri: Uri,
+    token: String,
+    timeout: Duration,
+}
+
+impl VaultProvider {
+    /// Creates a provider for the Vault's address and the token.
+    ///
+    /// # Panics
+    /// If the URL is invalid or its scheme isn't supported.
+    #[track_caller]
+    pub fn new(url: &str, token: impl Into<String>) -> Self {
+        Self {
+            uri: source::parse_url(url),
+            token: token.into(),
+            timeout: Duration::from_secs(10),
+        }
+    }
+
+    /// Sets the timeout of requests, 10s by default.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    async fn do_resolve(&self, path: &str) -> Result<ResolvedSecret, String> {
+        let (path, key) = path
+            .split_once('#')
+            .ok_or("the reference must contain `#key`")?;
+
+        let base = self.uri.to_string();
+        let uri = format!("{}/v1/{path}", base.trim_end_matches('/'));
+        let uri = uri.parse::<Uri>().map_err(|err| err.to_string())?;
+
+        let response = source::get(&uri, &[("x-vault-token", &self.token)])
+            .await
+            .map_err(|err| err.to_string())?;
+
+        let body: serde_json::Value =
+            serde_json::from_slice(response.body()).map_err(|err| err.to_string())?;
+
+        let data = &body["data"];
+        // KV v2 wraps secrets into one more `data` object.
+        let data = if data["data"].is_object() {
+            &data["data"]
+        } else {
+            data
+        };
+
+        let value = match &data[key] {
+            serde_json::Value::String(value) => value.clone(),
+            serde_json::Value::Null => return Err(format!("no `{key}` in the secret")),
+            value => value.to_string(),
+        };
+
+        let secret = ResolvedSecret::new(value);
+        Ok(match body["lease_duration"].as_u64() {
+            Some(secs) if secs > 0 => secret.with_ttl(Duration::from_secs(secs)),
+            _ => secret,
+        })
+    }
+}
+
+impl SecretProvider for VaultProvider {
+    fn resolve<'a>(&'a self, path: &'a str) -> BoxFuture<'a, Result<ResolvedSecret, String>> {
+        Box::pin(async move {
+            timeout(self.timeout, self.do_resolve(path))
+                .await
+                .map_err(|_| "vault is too slow".to_owned())?
+        })
+    }
+}
+
+struct EnvProvider;
+
+impl SecretProvider for EnvProvider {
+    fn resolve<'a>(&'a self, path: &'a str) -> BoxFuture<'a, Result<ResolvedSecret, String>> {
+        let value = std::env::var(path).map_err(|err| err.to_string());
+        Box::pin(async move { value.map(ResolvedSecret::new) })
+    }
+}
+
+struct FileProvider;
+
+impl SecretProvider for FileProvider {
+    fn resolve<'a>(&'a self, path: &'a str) -> BoxFuture<'a, Result<ResolvedSecret, String>> {
+        Box::pin(async move {
+            let value = tokio::fs::read_to_string(path)
+                .await
+                .map_err(|err| err.to_string())?;
+            Ok(ResolvedSecret::new(value.trim_end_matches(['\r', '\n'])))
+        })
+    }
+}
+
+pub(crate) struct Secrets {
+    providers: FxHashMap<String, Arc<dyn SecretProvider>>,
+}
+
+impl Default for Secrets {
+    fn default() -> Self {
+        let mut providers = FxHashMap::<_, Arc<dyn SecretProvider>>::default();
+        providers.insert("env".into(), Arc::new(EnvProvider));
+        providers.insert("file".into(), Arc::new(FileProvider));
+        Self { providers }
+    }
+}
+
+/// The result of resolving, see [`Secrets::resolve()`].
+pub(crate) struct Resolved {
+    pub(crate) config: Value,
+    /// The minimal TTL of resolved secrets.
+    pub(crate) ttl: Option<Duration>,
+    /// Values of resolved secrets to mask them in reports.
+    pub(crate) values: SecretValues,
+}
+
+/// Values of resolved secrets to mask in reports. Empty values are ignored,
+/// masking them everywhere reveals nothing, but makes reports useless.
+///
+/// It's only for masking, the same values are kept in configs sent to groups.
+/// Use [`Secret`](elfo_core::config::Secret) to avoid printing them.
+#[derive(Default)]
+pub(crate) struct SecretValues(FxHashSet<String>);
+
+impl SecretValues {
+    pub(crate) fn contains(&self, value: &str) -> bool {
+        self.0.contains(value)
+    }
+}
+
+impl FromIterator<String> for SecretValues {
+    fn from_iter<I: IntoIterator<Item = String>>(iter: I) -> Self {
+        Self(iter.into_iter().filter(|v| !v.is_empty()).collect())
+    }
+}
+
+impl Secrets {
+    pub(crate) fn add(&mut self, name: String, provider: Arc<dyn SecretProvider>) {
+        self.providers.insert(name, provider);
+    }
+
+    pub(crate) async fn resolve(&self, config: Value) -> Result<Resolved, String> {
+        let mut references = Vec::new();
+        collect(&config, &mut references)?;
+
+        let mut resolved = FxHashMap::default();
+        let mut ttl = None::<Duration>;
+
+        for reference in references {
+            if resolved.contains_key(&reference) {
+                continue;
+            }
+
+            let (name, path) = reference
+                .split_once(':')
+                .ok_or_else(|| format!("invalid secret reference `{reference}`"))?;
+
+            let provider = self
+                .providers
+                .get(name)
+                .ok_or_else(|| format!("unknown secret provider `{name}`"))?;
+
+            // Don't include paths into errors, they can be sensitive too.
+            let secret = provider
+                .resolve(path)
+                .await
+                .map_err(|err| format!("cannot resolve a secret from `{name}`: {err}"))?;
+
+            ttl = match (ttl, secret.ttl) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+            resolved.insert(reference, secret.value);
+        }
+
+        let config = substitute(config, &resolved);
+        let values = resolved.into_values().collect();
+        Ok(Resolved {
+            config,
+            ttl,
+            values,
+        })
+    }
+}
+
+fn reference(map: &BTreeMap<Value, Value>) -> Option<Result<&str, String>> {
+    let value = map.get(&Value::String(REFERENCE_KEY.into()))?;
+
+    Some(match value {
+        Value::String(reference) if map.len() == 1 => Ok(reference),
+        _ => Err(format!(
+            "`{REFERENCE_KEY}` must be the only key with a string value"
+        )),
+    })
+}
+
+fn collect(value: &Value, out: &mut Vec<String>) -> Result<(), String> {
+    match value {
+        Value::Map(map) => match reference(map) {
+            Some(reference) => out.push(reference?.to_owned()),
+            None => map.values().try_for_each(|v| collect(v, out))?,
+        },
+        Value::Seq(seq) => seq.iter().try_for_each(|v| collect(v, out))?,
+        Value::Option(Some(v)) | Value::Newtype(v) => collect(v, out)?,
+        _ => {}
+    }
+
+    Ok(())
+}
+
+fn substitute(value: Value, resolved: &FxHashMap<String, String>) -> Value {
+    match value {
+        Value::Map(map) => match reference(&map) {
+            Some(Ok(reference)) => Value::String(resolved[reference].clone()),
+            _ => Value::Map(
+                map.into_iter()
+                    .map(|(k, v)| (k, substitute(v, resolved)))
+                    .collect(),
+            ),
+        },
+        Value::Seq(seq) => Value::Seq(seq.into_iter().map(|v| substitute(v, resolved)).collect()),
+        Value::Option(Some(v)) => Value::Option(Some(Box::new(substitute(*v, resolved)))),
+        Value::Newtype(v) => Value::Newtype(Box::new(substitute(*v, resolved))),
+        v => v,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        helpers::lookup_value,
+        source::tests::{response, serve},
+    };
+
+    use super::*;
+
+    struct Static;
+
+    impl SecretProvider for Static {
+        fn resolve<'a>(&'a self, path: &'a str) -> BoxFuture<'a, Result<ResolvedSecret, String>> {
+            let ttl = Duration::from_secs(path.len() as u64);
+            let secret = ResolvedSecret::new(path.to_uppercase()).with_ttl(ttl);
+            Box::pin(async move { Ok(secret) })
+        }
+    }
+
+    #[tokio::test]
+    async fn resolve() {
+        let mut secrets = Secrets::default();
+        secrets.add("static".into(), Arc::new(Static));
+
+        let config: Value = toml::from_str(
+            r#"
+            [group]
+            plain = "value"
+            password = { "$secret" = "static:abc" }
+            tokens = [{ "$secret" = "static:a" }, "b"]
+            "#,
+        )
+        .unwrap();
+
+        let resolved = secrets.resolve(config).await.unwrap();
+        let lookup = |path| lookup_value(&resolved.config, path).cloned();
+
+        assert_eq!(lookup("group.plain"), Some(Value::String("value".into())));
+        assert_eq!(lookup("group.password"), Some(Value::String("ABC".into())));
+        assert_eq!(
+            lookup("group.tokens"),
+            Some(Value::Seq(vec![
+                Value::String("A".into()),
+                Value::String("b".into())
+            ]))
+        );
+        assert_eq!(resolved.ttl, Some(Duration::from_secs(1)));
+        assert!(resolved.values.contains("ABC"));
+        assert!(resolved.values.contains("A"));
+
+        let values = ["".to_owned(), "a".to_owned()]
+            .into_iter()
+            .collect::<SecretValues>();
+        assert!(!values.contains(""));
+    }
+
+    #[tokio::test]
+    async fn vault() {
+        let body = r#"{ "lease_duration": 60, "data": { "data": { "password": "qwerty" } } }"#;
+        let url = serve(response(body, "")).await;
+
+        let provider = VaultProvider::new(&url, "token");
+        let secret = provider.resolve("secret/data/db#password").await.unwrap();
+        assert_eq!(secret.value, "qwerty");
+        assert_eq!(secret.ttl, Some(Duration::from_secs(60)));
+
+        let err = provider.resolve("secret/data/db#user").await.err().unwrap();
+        assert!(err.contains("no `user`"));
+        let err = provider.resolve("secret/data/db").await.err().unwrap();
+        assert!(err.contains("#key"));
+    }
+
+    #[tokio::test]
+    async fn errors() {
+        let secrets = Secrets::default();
+        let check = |s: &str| {
+            let config: Value = toml::from_str(s).unwrap();
+            secrets.resolve(config)
+        };
+
+        let err = check(r#"a = { "$secret" = "unknown:abc" }"#).await;
+        assert!(err.err().unwrap().contains("unknown secret provider"));
+
+        let err = check(r#"a = { "$secret" = "abc" }"#).await;
+        assert!(err.err().unwrap().contains("invalid secret reference"));
+
+        let err = check(r#"a = { "$secret" = "env:abc", b = 1 }"#).await;
+        assert!(err.err().unwrap().contains("must be the only key"));
+
+        let err = check(r#"a = { "$secret" = "env:ELFO_SURELY_UNSET_VAR" }"#).await;
+        assert!(err
+            .err()
+            .unwrap()
+            .contains("cannot resolve a secret from `env`"));
+    }
+}
@@ -0,0 +1,400 @@
+//! Remote sources of configs.
+//!
+//! Changes are detected only by polling, see `poll_interval` in the crate's
+//! docs. Watches of KV stores (e.g. etcd's watch API or Consul's blocking
+//! queries) aren't supported.
+
+use std::{io, path::Path, time::Duration};
+
+use futures::future::BoxFuture;
+use http_body_util::{BodyExt, Empty, Limited};
+use hyper::{
+    body::Bytes, client::conn::http1, header::HOST, http::uri::Authority, Method, Request,
+    Response, Uri,
+};
+use hyper_util::rt::TokioIo;
+use sha2::{Digest, Sha256};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpStream,
+    time::timeout,
+};
+use tracing::debug;
+
+use elfo_core::scope;
+
+use crate::Format;
+
+/// The maximum size of response bodies, larger ones are rejected.
+const MAX_BODY_SIZE: usize = 16 * 1024 * 1024;
+
+/// A source of configs, used by [`from_source()`](crate::from_source).
+///
+/// Implement it to load configs from KV stores (e.g. etcd) or other places.
+/// Consul's KV can be used with [`HttpSource`] directly, requesting a key
+/// with the `?raw` query.
+pub trait Source: Send + Sync + 'static {
+    /// Fetches the content of the config.
+    fn fetch(&self) -> BoxFuture<'_, Result<String, String>>;
+
+    /// Returns the format of the content, TOML by default.
+    fn format(&self) -> Format {
+        Format::Toml
+    }
+}
+
+/// Fetches configs from an HTTP endpoint.
+/// * It supports only HTTP/1.
+/// * `https` requires the `tls` feature, servers are verified by Mozilla's root
+///   certificates.
+/// * Responses larger than 16MiB are rejected.
+///
+/// # Example
+/// ```
+/// use elfo_configurer::{Format, HttpSource};
+///
+/// let source = HttpSource::new("http://consul:8500/v1/kv/app/config?raw")
+///     .content_format(Format::Yaml)
+///     .checksum_header("x-config-sha256");
+/// ```
+#[derive(Debug, Clone)]
+pub struct HttpSource {
+    uri: Uri,
+    format: Format,
+    timeout: Duration,
+    checksum_header: Option<String>,
+}
+
+impl HttpSource {
+    /// Creates a source fetching configs by `GET` requests to the URL.
+    ///
+    /// # Panics
+    /// If the URL is invalid or its scheme isn't supported.
+    #[track_caller]
+    pub fn new(url: &str) -> Self {
+        let uri = parse_url(url);
+        let format = Format::detect(Path::new(uri.path()));
+
+        Self {
+            uri,
+            format,
+            timeout: Duration::from_secs(10),
+            checksum_header: None,
+        }
+    }
+
+    /// Sets the format of the content.
+    /// By default, it's detected by the URL's extension.
+    pub fn content_format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Sets the timeout of requests, 10s by default.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Requires responses to contain the header with the hex-encoded SHA-256
+    /// of the content. Responses with a missing or mismatched checksum are
+    /// rejected, protecting from partially written or corrupted configs.
+    pub fn checksum_header(mut self, name: &str) -> Self {
+        self.checksum_header = Some(name.to_owned());
+        self
+    }
+
+    async fn do_fetch(&self) -> io::Result<String> {
+        let response = get(&self.uri, &[]).await?;
+        let checksum = self.checksum_header.as_ref().map(|name| {
+            response
+                .headers()
+                .get(name.as_str())
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_owned)
+        });
+
+        let body = response.into_body();
+
+        match checksum {
+            Some(None) => return Err(io::Error::other("no checksum in the response")),
+            Some(Some(expected)) if !expected.eq_ignore_ascii_case(&sha256_hex(&body)) => {
+                return Err(io::Error::other("checksum mismatch"));
+            }
+            _ => {}
+        }
+
+        String::from_utf8(body.to_vec()).map_err(io::Error::other)
+    }
+}
+
+impl Source for HttpSource {
+    fn fetch(&self) -> BoxFuture<'_, Result<String, String>> {
+        Box::pin(async move {
+            timeout(self.timeout, self.do_fetch())
+                .await
+                .map_err(|_| "the source is too slow".to_owned())?
+                .map_err(|err| format!("{}: {err}", self.uri))
+        })
+    }
+
+    fn format(&self) -> Format {
+        self.format
+    }
+}
+
+/// Parses the URL, panics if it's invalid or its scheme isn't supported.
+#[track_caller]
+pub(crate) fn parse_url(url: &str) -> Uri {
+    let uri = url.parse::<Uri>().expect("invalid URL");
+    assert!(uri.authority().is_some(), "invalid URL");
+
+    match uri.scheme_str() {
+        Some("http") => {}
+        Some("https") if cfg!(feature = "tls") => {}
+        Some("https") => panic!("`https` requires the `tls` feature"),
+        _ => panic!("only `http` and `https` are supported"),
+    }
+
+    uri
+}
+
+/// Performs a `GET` request, non-successful responses are errors.
+pub(crate) async fn get(uri: &Uri, headers: &[(&str, &str)]) -> io::Result<Response<Bytes>> {
+    let authority = uri.authority().expect("invalid URL");
+    let is_tls = uri.scheme_str() == Some("https");
+    let port = authority
+        .port_u16()
+        .unwrap_or(if is_tls { 443 } else { 80 });
+    // IPv6 addresses are bracketed in URLs, e.g. `http://[::1]:8500`.
+    let host = authority.host();
+    let host = host
+        .strip_prefix('[')
+        .and_then(|h| h.strip_suffix(']'))
+        .unwrap_or(host);
+
+    let stream = TcpStream::connect((host, port)).await?;
+
+    if is_tls {
+        #[cfg(feature = "tls")]
+        return send(tls::connect(host, stream).await?, uri, authority, headers).await;
+        #[cfg(not(feature = "tls"))]
+        unreachable!("rejected by `parse_url()`");
+    }
+
+    send(stream, uri, authority, headers).await
+}
+
+async fn send(
+    stream: impl AsyncRead + AsyncWrite + Send + Unpin + 'static,
+    uri: &Uri,
+    authority: &Authority,
+    headers: &[(&str, &str)],
+) -> io::Result<Response<Bytes>> {
+    let (mut sender, connection) = http1::handshake(TokioIo::new(stream))
+        .await
+        .map_err(io::Error::other)?;
+
+    scope::runtime().spawn(Box::pin(async move {
+        if let Err(err) = connection.await {
+            debug!(error = %err, "the connection to the config source is closed");
+        }
+    }));
+
+    let mut request = Request::builder()
+        .method(Method::GET)
+        .uri(uri.path_and_query().map_or("/", |p| p.as_str()))
+        .header(HOST, authority.as_str());
+
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+
+    let request = request
+        .body(Empty::<Bytes>::new())
+        .map_err(io::Error::other)?;
+
+    let response = sender
+        .send_request(request)
+        .await
+        .map_err(io::Error::other)?;
+
+    let (parts, body) = response.into_parts();
+    let body = Limited::new(body, MAX_BODY_SIZE)
+        .collect()
+        .await
+        .map_err(io::Error::other)?
+        .to_bytes();
+
+    if !parts.status.is_success() {
+        return Err(io::Error::other(format!(
+            "the source responded with {}: {}",
+            parts.status,
+            String::from_utf8_lossy(&body)
+        )));
+    }
+
+    Ok(Response::from_parts(parts, body))
+}
+
+#[cfg(feature = "tls")]
+mod tls {
+    use std::{
+        io,
+        sync::{Arc, OnceLock},
+    };
+
+    use tokio::net::TcpStream;
+    use tokio_rustls::{
+        client::TlsStream,
+        rustls::{crypto::ring, pki_types::ServerName, ClientConfig, RootCertStore},
+        TlsConnector,
+    };
+
+    fn connector() -> &'static TlsConnector {
+        static CONNECTOR: OnceLock<TlsConnector> = OnceLock::new();
+
+        CONNECTOR.get_or_init(|| {
+            let roots = RootCertStore {
+                roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+            };
+
+            // Don't rely on the process-wide provider, it can be ambiguous.
+            let config = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+                .with_safe_default_protocol_versions()
+                .expect("ring supports default versions")
+                .with_root_certificates(roots)
+                .with_no_client_auth();
+
+            TlsConnector::from(Arc::new(config))
+        })
+    }
+
+    pub(super) async fn connect(host: &str, stream: TcpStream) -> io::Result<TlsStream<TcpStream>> {
+        let name = ServerName::try_from(host.to_owned())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+
+        connector().connect(name, stream).await
+    }
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use super::*;
+
+    pub(crate) async fn serve(response: String) -> String {
+        serve_on("127.0.0.1:0", response).await
+    }
+
+    async fn serve_on(addr: &str, response: String) -> String {
+        let listener = TcpListener::bind(addr).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buf = [0; 1024];
+                let _ = stream.read(&mut buf).await.unwrap();
+                // The client can close the connection, e.g. on too large bodies.
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+
+        format!("http://{addr}")
+    }
+
+    pub(crate) fn response(body: &str, headers: &str) -> String {
+        format!(
+            "HTTP/1.1 200 OK\r\ncontent-length: {}\r\n{headers}\r\n{body}",
+            body.len()
+        )
+    }
+
+    #[tokio::test]
+    async fn fetch() {
+        let body = "foo: 42";
+        let url = serve(response(body, "")).await + "/config.yaml?raw";
+
+        let source = HttpSource::new(&url);
+        assert_eq!(source.format(), Format::Yaml);
+        assert_eq!(source.fetch().await.unwrap(), body);
+
+        let source = source.checksum_header("x-checksum");
+        assert!(source.fetch().await.unwrap_err().contains("no checksum"));
+    }
+
+    #[tokio::test]
+    async fn checksum() {
+        let body = "foo: 42";
+        let checksum = sha256_hex(body.as_bytes());
+
+        let url = serve(response(body, &format!("x-checksum: {checksum}\r\n"))).await;
+        let source = HttpSource::new(&url).checksum_header("x-checksum");
+        assert_eq!(source.fetch().await.unwrap(), body);
+
+        let url = serve(response("foo: 43", &format!("x-checksum: {checksum}\r\n"))).await;
+        let source = HttpSource::new(&url).checksum_header("x-checksum");
+        assert!(source.fetch().await.unwrap_err().contains("mismatch"));
+    }
+
+    #[tokio::test]
+    async fn ipv6() {
+        if std::net::TcpListener::bind("[::1]:0").is_err() {
+            return; // IPv6 is unavailable.
+        }
+
+        let body = "foo = 42";
+        let url = serve_on("[::1]:0", response(body, "")).await;
+        assert!(url.starts_with("http://[::1]:"));
+        assert_eq!(HttpSource::new(&url).fetch().await.unwrap(), body);
+    }
+
+    #[tokio::test]
+    async fn too_large() {
+        let body = "a".repeat(MAX_BODY_SIZE + 1);
+        let url = serve(response(&body, "")).await;
+        assert!(HttpSource::new(&url).fetch().await.is_err());
+
+        let body = "a".repeat(MAX_BODY_SIZE);
+        let url = serve(response(&body, "")).await;
+        assert_eq!(
+            HttpSource::new(&url).fetch().await.unwrap().len(),
+            body.len()
+        );
+    }
+
+    #[cfg(feature = "tls")]
+    #[tokio::test]
+    async fn tls_handshake_error() {
+        let url = serve(response("foo = 42", "")).await;
+        let url = url.replace("http://", "https://");
+        // The server speaks plain HTTP, so the handshake fails.
+        assert!(HttpSource::new(&url).fetch().await.is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "only `http` and `https` are supported")]
+    fn unsupported_scheme() {
+        HttpSource::new("ftp://configs.local/app.toml");
+    }
+
+    #[test]
+    fn sha256() {
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+}
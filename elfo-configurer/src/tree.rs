@@ -0,0 +1,70 @@
+use serde::de::DeserializeOwned;
+
+use crate::{protocol::ReloadConfigsError, ConfigWithMeta};
+
+pub(crate) type TreeValidator =
+    Box<dyn Fn(&ConfigTree<'_>) -> Result<(), Vec<ReloadConfigsError>> + Send + Sync>;
+
+/// The whole config tree, passed to validators registered by
+/// [`Builder::validate_tree()`](crate::Builder::validate_tree).
+///
+/// It's used to check relationships between groups' configs before they are
+/// distributed across the system.
+pub struct ConfigTree<'a> {
+    configs: &'a [ConfigWithMeta],
+}
+
+impl<'a> ConfigTree<'a> {
+    pub(crate) fn new(configs: &'a [ConfigWithMeta]) -> Self {
+        Self { configs }
+    }
+
+    /// Returns names of all local groups.
+    pub fn groups(&self) -> impl Iterator<Item = &str> + '_ {
+        self.configs.iter().map(|c| c.group_name.as_str())
+    }
+
+    /// Deserializes the config of the group, the `common` section is applied.
+    /// Returns `None` if there is no such group in the topology.
+    pub fn get<C: DeserializeOwned>(&self, group: &str) -> Option<Result<C, String>> {
+        let item = self.configs.iter().find(|c| c.group_name == group)?;
+        Some(C::deserialize(item.config.clone()).map_err(|err| err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use serde_value::Value;
+
+    use elfo_core::{config::AnyConfig, Addr};
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Config {
+        size: u32,
+    }
+
+    fn item(group_name: &str, config: &str) -> ConfigWithMeta {
+        let value: Value = toml::from_str(config).unwrap();
+        ConfigWithMeta {
+            group_name: group_name.into(),
+            addr: Addr::NULL,
+            hash: fxhash::hash64(&value),
+            config: AnyConfig::from_value(value.clone()),
+            value,
+        }
+    }
+
+    #[test]
+    fn get() {
+        let configs = [item("a", "size = 10"), item("b", "size = \"10\"")];
+        let tree = ConfigTree::new(&configs);
+
+        assert_eq!(tree.groups().collect::<Vec<_>>(), ["a", "b"]);
+        assert_eq!(tree.get::<Config>("a").unwrap(), Ok(Config { size: 10 }));
+        assert!(tree.get::<Config>("b").unwrap().is_err());
+        assert!(tree.get::<Config>("c").is_none());
+    }
+}
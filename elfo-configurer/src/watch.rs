@@ -0,0 +1,161 @@
+//! Watches config files to reload configs on changes, see the `watch` option.
+//!
+//! Directories of files are watched instead of files themselves, because
+//! editors and orchestrators (e.g. k8s's `ConfigMap`s) replace files by
+//! renaming, which breaks watches of the replaced files. Events of other files
+//! in these directories (e.g. exported topology) are ignored.
+//!
+//! k8s mounts `ConfigMap`s as symlinks to files in the `..data` directory,
+//! which is a symlink itself, and it's atomically swapped on updates. Thus,
+//! events of `..data` are also considered as changes of watched files.
+
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+use futures::channel::mpsc;
+use fxhash::FxHashSet;
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher as _};
+
+use elfo_core::{message, stream::Stream, Context};
+
+const K8S_DATA_DIR: &str = "..data";
+
+#[message]
+pub(crate) struct FilesChanged;
+
+pub(crate) struct FileWatcher {
+    files: Vec<PathBuf>,
+    dirs: Vec<PathBuf>,
+    // Stops watching and closes the stream on drop.
+    _watcher: RecommendedWatcher,
+}
+
+impl FileWatcher {
+    /// Starts watching files and attaches a stream of [`FilesChanged`].
+    pub(crate) fn new(ctx: &mut Context, files: Vec<PathBuf>) -> notify::Result<Self> {
+        let (tx, rx) = mpsc::unbounded();
+        let names = names_of(&files);
+
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            if event.is_ok_and(|event| is_relevant(&event, &names)) {
+                let _ = tx.unbounded_send(FilesChanged);
+            }
+        })?;
+
+        let dirs = dirs_of(&files);
+        for dir in &dirs {
+            watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        }
+
+        ctx.attach(Stream::from_futures03(rx));
+
+        Ok(Self {
+            files,
+            dirs,
+            _watcher: watcher,
+        })
+    }
+
+    pub(crate) fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    pub(crate) fn dirs(&self) -> &[PathBuf] {
+        &self.dirs
+    }
+}
+
+/// Backends can report canonicalized paths (e.g. FSEvents on macOS),
+/// so only names of files are compared.
+fn names_of(files: &[PathBuf]) -> FxHashSet<OsString> {
+    files
+        .iter()
+        .filter_map(|file| file.file_name())
+        .map(Into::into)
+        .chain([K8S_DATA_DIR.into()])
+        .collect()
+}
+
+fn is_relevant(event: &Event, names: &FxHashSet<OsString>) -> bool {
+    // Reading files by the configurer itself produces access events.
+    !event.kind.is_access()
+        && event
+            .paths
+            .iter()
+            .filter_map(|path| path.file_name())
+            .any(|name| names.contains(name))
+}
+
+/// Returns sorted unique directories of the files.
+fn dirs_of(files: &[PathBuf]) -> Vec<PathBuf> {
+    let mut dirs = files
+        .iter()
+        .map(|file| match file.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => Path::new(".").to_path_buf(),
+        })
+        .collect::<Vec<_>>();
+
+    dirs.sort();
+    dirs.dedup();
+    dirs
+}
+
+#[cfg(test)]
+mod tests {
+    use notify::EventKind;
+
+    use super::*;
+
+    #[test]
+    fn relevant() {
+        use notify::event::{AccessKind, CreateKind, ModifyKind, RenameMode};
+
+        let names = names_of(&["/etc/app/config.toml", "a/b.toml"].map(PathBuf::from));
+        let event = |kind, paths: &[&str]| Event {
+            kind,
+            paths: paths.iter().map(PathBuf::from).collect(),
+            attrs: Default::default(),
+        };
+
+        let modify = EventKind::Modify(ModifyKind::Any);
+        assert!(is_relevant(
+            &event(modify, &["/etc/app/config.toml"]),
+            &names
+        ));
+        assert!(is_relevant(&event(modify, &["a/b.toml"]), &names));
+        assert!(!is_relevant(
+            &event(modify, &["/etc/app/topology.dot"]),
+            &names
+        ));
+        assert!(!is_relevant(&event(modify, &[]), &names));
+
+        let access = EventKind::Access(AccessKind::Any);
+        assert!(!is_relevant(
+            &event(access, &["/etc/app/config.toml"]),
+            &names
+        ));
+
+        // Editors write to a temporary file and rename it.
+        let rename = EventKind::Modify(ModifyKind::Name(RenameMode::Both));
+        let paths = ["/etc/app/.config.toml.swp", "/etc/app/config.toml"];
+        assert!(is_relevant(&event(rename, &paths), &names));
+
+        // k8s swaps the `..data` symlink.
+        let paths = ["/etc/app/..data_tmp", "/etc/app/..data"];
+        assert!(is_relevant(&event(rename, &paths), &names));
+        let create = EventKind::Create(CreateKind::Any);
+        assert!(!is_relevant(
+            &event(create, &["/etc/app/..2024_01_01"]),
+            &names
+        ));
+    }
+
+    #[test]
+    fn dirs() {
+        let files = ["config.toml", "a/b.toml", "a/c.toml", "/etc/d.toml"].map(PathBuf::from);
+        assert_eq!(dirs_of(&files), ["/etc", ".", "a"].map(PathBuf::from));
+    }
+}
@@ -1,8 +1,12 @@
 use std::{
     fmt, mem,
-    sync::{atomic, Arc},
+    sync::{
+        atomic::{self, AtomicBool, AtomicUsize},
+        Arc,
+    },
 };
 
+use futures::future::AbortHandle;
 use futures_intrusive::sync::ManualResetEvent;
 use metrics::{decrement_gauge, increment_counter, increment_gauge};
 use parking_lot::RwLock;
@@ -14,7 +18,9 @@ use crate::{
     envelope::Envelope,
     errors::{SendError, TrySendError},
     group::TerminationPolicy,
+    inspection::{ActorInspection, MailboxInspection},
     mailbox::{config::MailboxConfig, Mailbox, RecvResult},
+    memory_budget::MemoryBudget,
     messages::{ActorStatusReport, Terminate},
     msg,
     request_table::RequestTable,
@@ -116,6 +122,13 @@ pub(crate) struct Actor {
     control: RwLock<Control>,
     finished: ManualResetEvent, // TODO: remove in favor of `status_subscription`?
     status_subscription: Arc<SubscriptionManager>,
+    memory_budget: Arc<MemoryBudget>,
+    /// Reported via `Context::report_memory_usage()`.
+    reported_memory_usage: AtomicUsize,
+    /// Whether the status has been changed due to the exceeded memory budget.
+    memory_alarming: AtomicBool,
+    /// Aborts the actor's exec, see `messages::RestartActor`.
+    abort_handle: AbortHandle,
 }
 
 struct Control {
@@ -135,12 +148,14 @@ impl Actor {
         mailbox_config: &MailboxConfig,
         termination_policy: TerminationPolicy,
         status_subscription: Arc<SubscriptionManager>,
+        memory_budget: Arc<MemoryBudget>,
+        abort_handle: AbortHandle,
     ) -> Self {
         Actor {
             status_kind: AtomicActorStatusKind::from(ActorStatusKind::Initializing),
             meta,
             termination_policy,
-            mailbox: Mailbox::new(mailbox_config),
+            mailbox: Mailbox::new(mailbox_config, memory_budget.clone()),
             request_table: RequestTable::new(addr),
             control: RwLock::new(Control {
                 status: ActorStatus::INITIALIZING,
@@ -150,6 +165,10 @@ impl Actor {
             }),
             finished: ManualResetEvent::new(false),
             status_subscription,
+            memory_budget,
+            reported_memory_usage: AtomicUsize::new(0),
+            memory_alarming: AtomicBool::new(false),
+            abort_handle,
         }
     }
 
@@ -230,6 +249,47 @@ impl Actor {
         self.mailbox.set_capacity(capacity);
     }
 
+    pub(crate) fn report_memory_usage(&self, usage: usize) {
+        let prev = self
+            .reported_memory_usage
+            .swap(usage, atomic::Ordering::Relaxed);
+        self.memory_budget.on_reported(prev, usage);
+    }
+
+    /// Changes the status to `Alarming` if the group's memory budget is
+    /// exceeded and back to `Normal` once the usage goes down.
+    ///
+    /// It's called by the supervisor when the budget's state is changed and
+    /// when the actor becomes `Normal`, e.g. after initialization.
+    ///
+    /// Note that this method should be called inside a right scope.
+    pub(crate) fn check_memory_budget(&self) {
+        let exceeded = self.memory_budget.exceeded();
+        let alarming = self.memory_alarming.load(atomic::Ordering::Relaxed);
+
+        match (exceeded, alarming) {
+            (Some((usage, soft_limit)), false) => {
+                // Don't override statuses set by the actor itself.
+                if !self.status_kind().is_normal() {
+                    return;
+                }
+
+                self.memory_alarming.store(true, atomic::Ordering::Relaxed);
+                self.set_status(ActorStatus::ALARMING.with_details(format_args!(
+                    "memory budget is exceeded: {usage} of {soft_limit} bytes"
+                )));
+            }
+            (None, true) => {
+                self.memory_alarming.store(false, atomic::Ordering::Relaxed);
+
+                if self.status_kind().is_alarming() {
+                    self.set_status(ActorStatus::NORMAL);
+                }
+            }
+            _ => {}
+        }
+    }
+
     pub(crate) fn restart_policy(&self) -> Option<RestartPolicy> {
         self.control.read().restart_policy.clone()
     }
@@ -238,6 +298,10 @@ impl Actor {
         self.control.write().restart_policy = policy;
     }
 
+    pub(crate) fn meta(&self) -> &Arc<ActorMeta> {
+        &self.meta
+    }
+
     pub(crate) fn status_kind(&self) -> ActorStatusKind {
         self.status_kind.load(atomic::Ordering::Acquire)
     }
@@ -261,6 +325,7 @@ impl Actor {
             self.close();
             // Drop all messages to release requests immediately.
             self.mailbox.drop_all();
+            self.report_memory_usage(0);
             self.finished.set();
         }
 
@@ -277,6 +342,11 @@ impl Actor {
             increment_counter!("elfo_actor_status_changes_total", "status" => status.kind.as_str());
         }
 
+        // The budget can be exceeded before the actor becomes `Normal`.
+        if status.kind().is_normal() {
+            self.check_memory_budget();
+        }
+
         // TODO: use `sdnotify` to provide a detailed status to systemd.
         //       or use another actor to listen all statuses for this.
     }
@@ -300,6 +370,23 @@ impl Actor {
         })
     }
 
+    /// Aborts the actor's exec at the next `.await` point.
+    pub(crate) fn abort(&self) {
+        self.abort_handle.abort();
+    }
+
+    pub(crate) fn inspect(&self, addr: Addr) -> ActorInspection {
+        ActorInspection {
+            addr,
+            key: self.meta.key.clone(),
+            status: self.control.read().status.clone(),
+            mailbox: MailboxInspection {
+                len: self.mailbox.len(),
+                capacity: self.mailbox.capacity(),
+            },
+        }
+    }
+
     fn send_status_to_subscribers(&self, control: &Control) {
         self.status_subscription.send(ActorStatusReport {
             meta: self.meta.clone(),
@@ -1,6 +1,9 @@
 use std::{
     fmt, mem,
-    sync::atomic::{self, AtomicU8},
+    sync::{
+        atomic::{self, AtomicU8},
+        Arc,
+    },
 };
 
 use serde::{Deserialize, Serialize};
@@ -13,6 +16,9 @@ use serde::{Deserialize, Serialize};
 pub struct ActorStatus {
     pub(crate) kind: ActorStatusKind,
     pub(crate) details: Option<String>,
+    // Messages are encoded with field names, so older nodes ignore it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) backtrace: Option<Arc<str>>,
 }
 
 impl ActorStatus {
@@ -20,6 +26,7 @@ impl ActorStatus {
         Self {
             kind,
             details: None,
+            backtrace: None,
         }
     }
 
@@ -28,9 +35,15 @@ impl ActorStatus {
         ActorStatus {
             kind: self.kind,
             details: Some(details.to_string()),
+            backtrace: None,
         }
     }
 
+    pub(crate) fn with_backtrace(mut self, backtrace: impl fmt::Display) -> Self {
+        self.backtrace = Some(backtrace.to_string().into());
+        self
+    }
+
     /// Returns the corresponding [`ActorStatusKind`] for this status.
     pub fn kind(&self) -> ActorStatusKind {
         self.kind
@@ -40,6 +53,15 @@ impl ActorStatus {
     pub fn details(&self) -> Option<&str> {
         self.details.as_deref()
     }
+
+    /// Returns the backtrace of the panic that caused the failure, if it has
+    /// been captured (see [`std::backtrace::Backtrace::capture()`]).
+    ///
+    /// It isn't a part of [`ActorStatus::details()`], but it's dumped and sent
+    /// over the network along with the status.
+    pub fn backtrace(&self) -> Option<&str> {
+        self.backtrace.as_deref()
+    }
 }
 
 #[cfg(not(feature = "test-util"))]
@@ -163,3 +185,27 @@ impl AtomicActorStatusKind {
         unsafe { mem::transmute::<u8, ActorStatusKind>(result) }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scope::{self, SerdeMode};
+
+    #[test]
+    fn backtrace_is_serialized() {
+        let status = ActorStatus::FAILED
+            .with_details("panic: oops")
+            .with_backtrace("0: main");
+
+        for mode in [SerdeMode::Normal, SerdeMode::Dumping, SerdeMode::Network] {
+            let json = scope::with_serde_mode(mode, || serde_json::to_string(&status)).unwrap();
+            let actual: ActorStatus = serde_json::from_str(&json).unwrap();
+            assert_eq!(actual, status);
+            assert_eq!(actual.backtrace(), Some("0: main"));
+        }
+
+        // Statuses without backtraces are compatible with older nodes.
+        let json = serde_json::to_string(&ActorStatus::NORMAL).unwrap();
+        assert_eq!(json, r#"{"kind":"Normal","details":null}"#);
+    }
+}
@@ -310,7 +310,7 @@ const_assert_eq!(
 
 // === random_u64 ===
 
-fn random_u64() -> u64 {
+pub(crate) fn random_u64() -> u64 {
     use std::{
         collections::hash_map::RandomState,
         hash::{BuildHasher, Hash, Hasher},
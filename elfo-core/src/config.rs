@@ -11,6 +11,7 @@ use std::{
 };
 
 use derive_more::From;
+use fxhash::FxHashMap;
 use serde::{de, de::value::Error as DeError, Deserialize, Deserializer, Serialize, Serializer};
 use serde_value::{Value, ValueDeserializer};
 
@@ -31,6 +32,19 @@ assert_impl_all!((): Config);
 /// Usually not created directly outside tests sending [`ValidateConfig`] or
 /// [`UpdateConfig`] messages.
 ///
+/// The `system.key_overrides` section overrides fields for specific actors
+/// of keyed groups. Overrides are deeply merged into the config: maps are
+/// merged key by key, other values are replaced. Actors with listed keys
+/// (as they are displayed) get merged configs at spawn and on updates.
+/// ```toml
+/// [exchanges]
+/// limit = 10
+/// retries = 3
+///
+/// [exchanges.system.key_overrides.binance]
+/// limit = 100
+/// ```
+///
 /// [`ValidateConfig`]: crate::messages::ValidateConfig
 /// [`UpdateConfig`]: crate::messages::UpdateConfig
 ///
@@ -55,8 +69,13 @@ struct Decoded {
     system: Arc<SystemConfig>,
     // Actually, we store `Arc<Arc<C>>` here.
     user: Arc<dyn Any + Send + Sync>,
+    // Actually, we store `Arc<FxHashMap<String, Arc<C>>>` here.
+    overrides: Arc<dyn Any + Send + Sync>,
 }
 
+/// Lives in the `system` section to never collide with fields of user configs.
+const KEY_OVERRIDES: &str = "key_overrides";
+
 impl AnyConfig {
     /// Creates `AnyConfig` from `serde_value::Value`.
     ///
@@ -78,6 +97,21 @@ impl AnyConfig {
             .expect("must be decoded")
     }
 
+    /// Returns the config for the actor with the key,
+    /// see `system.key_overrides`.
+    pub(crate) fn get_user_for<C: 'static>(&self, key: &str) -> &Arc<C> {
+        self.get_overrides::<C>()
+            .get(key)
+            .unwrap_or_else(|| self.get_user())
+    }
+
+    pub(crate) fn get_overrides<C: 'static>(&self) -> &FxHashMap<String, Arc<C>> {
+        self.decoded
+            .as_ref()
+            .and_then(|local| local.overrides.downcast_ref())
+            .expect("must be decoded")
+    }
+
     pub(crate) fn get_system(&self) -> &Arc<SystemConfig> {
         &self.decoded.as_ref().expect("must be decoded").system
     }
@@ -86,15 +120,20 @@ impl AnyConfig {
         match panic::sync_catch(|| self.do_decode::<C>()) {
             Ok(Ok(config)) => Ok(config),
             Ok(Err(err)) => Err(err),
-            Err(panic) => Err(panic),
+            Err(panic) => Err(panic.into()),
         }
     }
 
     fn do_decode<C: Config>(&self) -> Result<AnyConfig, String> {
         let mut raw = (*self.raw).clone();
 
+        let mut overrides_raw = None;
         let system_decoded = if let Value::Map(map) = &mut raw {
-            if let Some(system_raw) = map.remove(&Value::String("system".into())) {
+            if let Some(mut system_raw) = map.remove(&Value::String("system".into())) {
+                if let Value::Map(system_map) = &mut system_raw {
+                    overrides_raw = system_map.remove(&Value::String(KEY_OVERRIDES.into()));
+                }
+
                 let de = ValueDeserializer::<DeError>::new(system_raw);
                 let config = SystemConfig::deserialize(de).map_err(|err| err.to_string())?;
                 Arc::new(config)
@@ -106,19 +145,45 @@ impl AnyConfig {
         };
 
         // Handle the special case of default config.
-        let user_decoded = if TypeId::of::<C>() == TypeId::of::<()>() {
-            Arc::new(Arc::new(())) as Arc<_>
-        } else {
-            let de = ValueDeserializer::<DeError>::new(raw);
-            let config = C::deserialize(de).map_err(|err| err.to_string())?;
-            Arc::new(Arc::new(config)) as Arc<_>
-        };
+        if TypeId::of::<C>() == TypeId::of::<()>() {
+            return Ok(AnyConfig {
+                raw: self.raw.clone(),
+                decoded: Some(Local::from(Decoded {
+                    system: system_decoded,
+                    user: Arc::new(Arc::new(())),
+                    overrides: Arc::new(FxHashMap::<String, Arc<()>>::default()),
+                })),
+            });
+        }
+
+        let mut overrides = FxHashMap::<String, Arc<C>>::default();
+        if let Some(overrides_raw) = overrides_raw {
+            let Value::Map(overrides_raw) = overrides_raw else {
+                return Err(format!("`system.{KEY_OVERRIDES}` must be a map"));
+            };
+
+            for (key, override_raw) in overrides_raw {
+                let Value::String(key) = key else {
+                    return Err(format!("keys of `system.{KEY_OVERRIDES}` must be strings"));
+                };
+
+                let merged = merge(raw.clone(), override_raw);
+                let de = ValueDeserializer::<DeError>::new(merged);
+                let config = C::deserialize(de)
+                    .map_err(|err| format!("system.{KEY_OVERRIDES}.{key}: {err}"))?;
+                overrides.insert(key, Arc::new(config));
+            }
+        }
+
+        let de = ValueDeserializer::<DeError>::new(raw);
+        let config = C::deserialize(de).map_err(|err| err.to_string())?;
 
         Ok(AnyConfig {
             raw: self.raw.clone(),
             decoded: Some(Local::from(Decoded {
                 system: system_decoded,
-                user: user_decoded,
+                user: Arc::new(Arc::new(config)),
+                overrides: Arc::new(overrides),
             })),
         })
     }
@@ -128,6 +193,24 @@ impl AnyConfig {
     }
 }
 
+/// Deeply merges `patch` into `base`: maps are merged key by key,
+/// other values are replaced.
+fn merge(base: Value, patch: Value) -> Value {
+    match (base, patch) {
+        (Value::Map(mut base), Value::Map(patch)) => {
+            for (key, value) in patch {
+                let merged = match base.remove(&key) {
+                    Some(prev) => merge(prev, value),
+                    None => value,
+                };
+                base.insert(key, merged);
+            }
+            Value::Map(base)
+        }
+        (_, patch) => patch,
+    }
+}
+
 impl Default for AnyConfig {
     fn default() -> Self {
         Self::from_value(Value::Map(Default::default()))
@@ -202,8 +285,10 @@ pub mod system {
     use super::*;
 
     pub use crate::{
-        dumping::config as dumping, logging::config as logging, mailbox::config as mailbox,
-        restarting::config as restart_policy, telemetry::config as telemetry,
+        coop::config as coop, dumping::config as dumping, logging::config as logging,
+        mailbox::config as mailbox, memory_budget::config as memory_budget,
+        persistence::config as persistence, restarting::config as restart_policy,
+        runtime::config as runtime, telemetry::config as telemetry, tracing::config as tracing,
     };
 
     /// The `system.*` section in configs.
@@ -216,6 +301,11 @@ pub mod system {
     /// system.dumping.max_rate = 10_000
     /// system.telemetry.per_actor_key = true
     /// system.restart_policy.when = "Never"
+    /// system.memory_budget.soft_limit = "100MiB"
+    /// system.runtime.cpu_affinity = [2, 3]
+    /// system.persistence.snapshot_interval = 500
+    /// system.tracing.sampling_rate = 0.1
+    /// system.coop.send_budget = 1000
     /// ```
     #[derive(Debug, Default, Deserialize)]
     #[serde(default)]
@@ -230,6 +320,16 @@ pub mod system {
         pub telemetry: telemetry::TelemetryConfig,
         /// Restarting configuration.
         pub restart_policy: restart_policy::RestartPolicyConfig,
+        /// Memory budget configuration.
+        pub memory_budget: memory_budget::MemoryBudgetConfig,
+        /// Dedicated runtime configuration.
+        pub runtime: runtime::RuntimeConfig,
+        /// Persistence configuration.
+        pub persistence: persistence::PersistenceConfig,
+        /// Tracing configuration.
+        pub tracing: tracing::TracingConfig,
+        /// Cooperative budgeting configuration.
+        pub coop: coop::CoopConfig,
     }
 }
 
@@ -1,4 +1,12 @@
-use std::{future::poll_fn, marker::PhantomData, pin::Pin, sync::Arc, task::Poll};
+use std::{
+    any::Any as StdAny,
+    future::poll_fn,
+    hash::Hash,
+    marker::PhantomData,
+    pin::Pin,
+    sync::{Arc, Mutex, PoisonError},
+    task::Poll,
+};
 
 use futures::{pin_mut, Stream};
 use idr_ebr::EbrGuard;
@@ -17,24 +25,28 @@ use crate::{
     demux::Demux,
     dumping::{Direction, Dump, Dumper, INTERNAL_CLASS},
     envelope::{Envelope, MessageKind},
-    errors::{RequestError, SendError, TryRecvError, TrySendError},
+    errors::{RequestError, SendError, TryRecvError, TrySendError, TypedRequestError},
     mailbox::RecvResult,
     message::{Message, Request},
     messages, msg,
     object::{BorrowedObject, Object, OwnedObject},
+    persistence::{EventLog, EventSourced},
     request_table::ResponseToken,
     restarting::RestartPolicy,
     routers::Singleton,
     scope,
-    source::{SourceHandle, Sources, UnattachedSource},
+    sender::ExternalSender,
+    source::{KeyedSources, SourceHandle, Sources, UnattachedSource},
+    topic::Topics,
     ActorStatusKind,
 };
 
-use self::stats::Stats;
+use self::{span::SpanTracker, stats::Stats};
 
+mod span;
 mod stats;
 
-static DUMPER: Lazy<Dumper> = Lazy::new(|| Dumper::new(INTERNAL_CLASS));
+pub(crate) static DUMPER: Lazy<Dumper> = Lazy::new(|| Dumper::new(INTERNAL_CLASS));
 
 /// An actor execution context.
 pub struct Context<C = (), K = Singleton> {
@@ -44,11 +56,17 @@ pub struct Context<C = (), K = Singleton> {
     actor_start_info: Option<ActorStartInfo>, // `None` for group's context,
     group_addr: Addr,
     demux: Demux,
+    topics: Topics,
     config: Arc<C>,
     key: K,
     sources: Sources,
+    keyed_sources: KeyedSources,
     stage: Stage,
     stats: Stats,
+    span: SpanTracker,
+    /// `EventLog<S>` recovered by the supervisor, see `take_event_log()`.
+    /// `Mutex` is used only to keep `Context: Sync`, it's never locked.
+    event_log: Option<Mutex<Box<dyn StdAny + Send>>>,
 }
 
 #[derive(Clone, Copy, PartialEq)]
@@ -86,6 +104,27 @@ impl<C, K> Context<C, K> {
         &self.key
     }
 
+    /// Takes the log recovered by the supervisor before the actor started,
+    /// see [`ActorGroup::event_sourced()`].
+    ///
+    /// Returns `None` if the group isn't event-sourced, `S` doesn't match
+    /// the type passed to the blueprint, or the log has been already taken.
+    ///
+    /// [`ActorGroup::event_sourced()`]: crate::ActorGroup::event_sourced
+    pub fn take_event_log<S: EventSourced>(&mut self) -> Option<EventLog<S>> {
+        let event_log = self.event_log.take()?;
+        let event_log = event_log
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner);
+        match event_log.downcast() {
+            Ok(event_log) => Some(*event_log),
+            Err(event_log) => {
+                self.event_log = Some(Mutex::new(event_log));
+                None
+            }
+        }
+    }
+
     /// Attaches the provided source to the context.
     ///
     /// Messages produced by the source will be available via
@@ -94,6 +133,41 @@ impl<C, K> Context<C, K> {
         source.attach_to(&mut self.sources)
     }
 
+    /// Attaches the provided source to the context under the provided key.
+    ///
+    /// Unlike [`Context::attach()`], the source can be detached later by
+    /// [`Context::detach()`]. If a source with the same key is already
+    /// attached, it's terminated and replaced by the new one.
+    ///
+    /// # Example
+    /// ```
+    /// # use elfo_core as elfo;
+    /// # use elfo::{messages::Ping, stream::Stream};
+    /// # fn exec(mut ctx: elfo::Context, id: u32, updates: futures::stream::Pending<Ping>) {
+    /// // Attach a stream per subscription.
+    /// ctx.attach_keyed(id, Stream::from_futures03(updates));
+    ///
+    /// // Detach it on unsubscription, unreceived messages are dropped.
+    /// ctx.detach(&id);
+    /// # }
+    /// ```
+    pub fn attach_keyed<Q, S1>(&mut self, key: Q, source: UnattachedSource<S1>) -> S1
+    where
+        Q: Hash + Eq + Send + Sync + 'static,
+        S1: SourceHandle,
+    {
+        source.attach_keyed_to(&mut self.sources, &mut self.keyed_sources, key)
+    }
+
+    /// Detaches the source attached by [`Context::attach_keyed()`].
+    ///
+    /// The source is terminated immediately, messages produced by it but not
+    /// received yet are dropped. Returns `false` if there is no source with
+    /// the provided key or it has already been terminated.
+    pub fn detach<Q: Hash + Eq + 'static>(&mut self, key: &Q) -> bool {
+        self.keyed_sources.remove(key)
+    }
+
     /// Updates the actor's status.
     ///
     /// # Example
@@ -153,6 +227,23 @@ impl<C, K> Context<C, K> {
             .set_mailbox_capacity_override(capacity.into());
     }
 
+    /// Reports the amount of memory used by the actor, in bytes.
+    ///
+    /// It's added to the usage of the group's memory budget along with
+    /// messages stored in mailboxes. The previously reported value is
+    /// replaced. See `system.memory_budget` in the config for details.
+    ///
+    /// # Example
+    /// ```
+    /// # use elfo_core as elfo;
+    /// # fn exec(ctx: elfo::Context, cache: Vec<u8>) {
+    /// ctx.report_memory_usage(cache.capacity());
+    /// # }
+    /// ```
+    pub fn report_memory_usage(&self, usage: usize) {
+        ward!(self.actor.as_ref().and_then(|o| o.as_actor())).report_memory_usage(usage);
+    }
+
     /// Overrides the group's default restart policy, which set in the config.
     ///
     /// Note: after restart the actor will be created from scratch, so this
@@ -249,6 +340,7 @@ impl<C, K> Context<C, K> {
         let kind = MessageKind::regular(self.actor_addr);
 
         self.stats.on_sent_message(&message); // TODO: only if successful?
+        self.span.on_sent_message(&message);
 
         trace!("> {:?}", message);
         if let Some(permit) = DUMPER.acquire_m(&message) {
@@ -257,19 +349,25 @@ impl<C, K> Context<C, K> {
 
         let envelope = Envelope::new(message, kind);
         let addrs = self.demux.filter(&envelope);
+        self.do_try_send_envelope(envelope, &addrs)
+            .map_err(|err| err.map(e2m))
+    }
 
+    fn do_try_send_envelope(
+        &self,
+        envelope: Envelope,
+        addrs: &[Addr],
+    ) -> Result<(), TrySendError<Envelope>> {
         if addrs.is_empty() {
-            return Err(TrySendError::Closed(e2m(envelope)));
+            return Err(TrySendError::Closed(envelope));
         }
 
         let guard = EbrGuard::new();
 
         if addrs.len() == 1 {
             return match self.book.get(addrs[0], &guard) {
-                Some(object) => object
-                    .try_send(Addr::NULL, envelope)
-                    .map_err(|err| err.map(e2m)),
-                None => Err(TrySendError::Closed(e2m(envelope))),
+                Some(object) => object.try_send(Addr::NULL, envelope),
+                None => Err(TrySendError::Closed(envelope)),
             };
         }
 
@@ -277,7 +375,7 @@ impl<C, K> Context<C, K> {
         let mut has_full = false;
         let mut success = false;
 
-        for (addr, envelope) in addrs_with_envelope(envelope, &addrs) {
+        for (addr, envelope) in addrs_with_envelope(envelope, addrs) {
             match self.book.get(addr, &guard) {
                 Some(object) => match object.try_send(Addr::NULL, envelope) {
                     Ok(()) => success = true,
@@ -293,9 +391,9 @@ impl<C, K> Context<C, K> {
         if success {
             Ok(())
         } else if has_full {
-            Err(TrySendError::Full(e2m(unused.unwrap())))
+            Err(TrySendError::Full(unused.unwrap()))
         } else {
-            Err(TrySendError::Closed(e2m(unused.unwrap())))
+            Err(TrySendError::Closed(unused.unwrap()))
         }
     }
 
@@ -334,6 +432,7 @@ impl<C, K> Context<C, K> {
         let kind = MessageKind::regular(self.actor_addr);
 
         self.stats.on_sent_message(&message); // TODO: only if successful?
+        self.span.on_sent_message(&message);
 
         trace!("> {:?}", message);
         if let Some(permit) = DUMPER.acquire_m(&message) {
@@ -425,6 +524,7 @@ impl<C, K> Context<C, K> {
         kind: MessageKind,
     ) -> Result<(), SendError<M>> {
         self.stats.on_sent_message(&message); // TODO: only if successful?
+        self.span.on_sent_message(&message);
 
         trace!("> {:?}", message);
         if let Some(permit) = DUMPER.acquire_m(&message) {
@@ -433,9 +533,19 @@ impl<C, K> Context<C, K> {
 
         let envelope = Envelope::new(message, kind);
         let addrs = self.demux.filter(&envelope);
+        let result = self.do_send_envelope(envelope, &addrs).await;
+
+        coop::consume_send_budget().await;
+        result.map_err(|err| err.map(e2m))
+    }
 
+    async fn do_send_envelope(
+        &self,
+        envelope: Envelope,
+        addrs: &[Addr],
+    ) -> Result<(), SendError<Envelope>> {
         if addrs.is_empty() {
-            return Err(SendError(e2m(envelope)));
+            return Err(SendError(envelope));
         }
 
         if addrs.len() == 1 {
@@ -443,18 +553,17 @@ impl<C, K> Context<C, K> {
             return {
                 let guard = EbrGuard::new();
                 let entry = self.book.get(recipient, &guard);
-                let object = ward!(entry, return Err(SendError(e2m(envelope))));
+                let object = ward!(entry, return Err(SendError(envelope)));
                 Object::send(object, Addr::NULL, envelope)
             }
-            .await
-            .map_err(|err| err.map(e2m));
+            .await;
         }
 
         let mut unused = None;
         let mut success = false;
 
         // TODO: send concurrently.
-        for (recipient, envelope) in addrs_with_envelope(envelope, &addrs) {
+        for (recipient, envelope) in addrs_with_envelope(envelope, addrs) {
             let returned_envelope = {
                 let guard = EbrGuard::new();
                 let entry = self.book.get(recipient, &guard);
@@ -477,7 +586,7 @@ impl<C, K> Context<C, K> {
         if success {
             Ok(())
         } else {
-            Err(SendError(e2m(unused.unwrap())))
+            Err(SendError(unused.unwrap()))
         }
     }
 
@@ -514,11 +623,14 @@ impl<C, K> Context<C, K> {
         message: M,
     ) -> Result<(), SendError<M>> {
         let kind = MessageKind::regular(self.actor_addr);
-        self.do_send_to(recipient, message, kind, |object, envelope| {
-            Object::send(object, recipient, envelope)
-        })?
-        .await
-        .map_err(|err| err.map(e2m))
+        let result = self
+            .do_send_to(recipient, message, kind, |object, envelope| {
+                Object::send(object, recipient, envelope)
+            })?
+            .await;
+
+        coop::consume_send_budget().await;
+        result.map_err(|err| err.map(e2m))
     }
 
     /// Tries to send a message to the specified recipient.
@@ -612,6 +724,7 @@ impl<C, K> Context<C, K> {
         f: impl FnOnce(BorrowedObject<'_>, Envelope) -> R,
     ) -> Result<R, SendError<M>> {
         self.stats.on_sent_message(&message); // TODO: only if successful?
+        self.span.on_sent_message(&message);
 
         trace!(to = %recipient, "> {:?}", message);
         if let Some(permit) = DUMPER.acquire_m(&message) {
@@ -626,6 +739,192 @@ impl<C, K> Context<C, K> {
         Ok(f(object, envelope))
     }
 
+    /// Subscribes the actor to the named topic. Messages published to the topic
+    /// by [`Context::publish()`] or [`Context::try_publish()`] are delivered
+    /// to the actor's mailbox.
+    ///
+    /// Subscriptions are removed automatically when the actor terminates
+    /// or fails.
+    ///
+    /// Returns `false` if the actor is already subscribed to the topic.
+    ///
+    /// # Example
+    /// ```
+    /// # use elfo_core as elfo;
+    /// # async fn exec(mut ctx: elfo::Context) {
+    /// # use elfo::{message, msg};
+    /// #[message]
+    /// struct PriceChanged(f64);
+    ///
+    /// ctx.subscribe("prices");
+    ///
+    /// while let Some(envelope) = ctx.recv().await {
+    ///     msg!(match envelope {
+    ///         PriceChanged(price) => { /* ... */ },
+    ///     });
+    /// }
+    /// # }
+    /// ```
+    pub fn subscribe(&self, topic: &str) -> bool {
+        self.topics.subscribe(topic, self.actor_addr)
+    }
+
+    /// Unsubscribes the actor from the named topic.
+    ///
+    /// Returns `false` if the actor isn't subscribed to the topic.
+    pub fn unsubscribe(&self, topic: &str) -> bool {
+        self.topics.unsubscribe(topic, self.actor_addr)
+    }
+
+    /// Publishes a message to all subscribers of the named topic.
+    /// Waits if the subscriber's mailbox is full.
+    ///
+    /// Returns `Err` if the message hasn't reached any mailboxes, e.g.
+    /// if there are no subscribers.
+    ///
+    /// # Cancel safety
+    ///
+    /// If cancelled, subscribers with full mailboxes wont't receive the
+    /// message.
+    ///
+    /// # Example
+    /// ```
+    /// # use elfo_core as elfo;
+    /// # async fn exec(mut ctx: elfo::Context) {
+    /// # use elfo::message;
+    /// #[message]
+    /// struct PriceChanged(f64);
+    ///
+    /// let _ = ctx.publish("prices", PriceChanged(42.)).await;
+    /// # }
+    /// ```
+    pub async fn publish<M: Message>(&self, topic: &str, message: M) -> Result<(), SendError<M>> {
+        let envelope = self.prepare_publish(topic, message);
+        let addrs = self.topics.subscribers(topic, &self.book);
+        self.do_send_envelope(envelope, &addrs)
+            .await
+            .map_err(|err| err.map(e2m))
+    }
+
+    /// Tries to publish a message to all subscribers of the named topic.
+    ///
+    /// Returns
+    /// * `Ok(())` if the message has been added to any mailbox.
+    /// * `Err(Full(_))` if some mailboxes are full.
+    /// * `Err(Closed(_))` otherwise, e.g. if there are no subscribers.
+    pub fn try_publish<M: Message>(&self, topic: &str, message: M) -> Result<(), TrySendError<M>> {
+        let envelope = self.prepare_publish(topic, message);
+        let addrs = self.topics.subscribers(topic, &self.book);
+        self.do_try_send_envelope(envelope, &addrs)
+            .map_err(|err| err.map(e2m))
+    }
+
+    fn prepare_publish<M: Message>(&self, topic: &str, message: M) -> Envelope {
+        let kind = MessageKind::regular(self.actor_addr);
+
+        self.stats.on_sent_message(&message); // TODO: only if successful?
+        self.span.on_sent_message(&message);
+
+        trace!(%topic, "> {:?}", message);
+        if let Some(permit) = DUMPER.acquire_m(&message) {
+            permit.record(Dump::message(&message, &kind, Direction::Out));
+        }
+
+        Envelope::new(message, kind)
+    }
+
+    /// Forwards the received request using the [inter-group routing] system.
+    /// The final responder replies directly to the original requester, thus
+    /// this actor doesn't participate in the request anymore.
+    ///
+    /// Waits if the recipient's mailbox is full.
+    ///
+    /// Returns `Err` if the request hasn't reached any mailboxes. In this case,
+    /// the requester receives [`RequestError::Failed`].
+    ///
+    /// # Example
+    /// ```
+    /// # use elfo_core as elfo;
+    /// # async fn exec(mut ctx: elfo::Context) {
+    /// # use elfo::{message, msg};
+    /// #[message(ret = u32)]
+    /// struct GetValue;
+    ///
+    /// while let Some(envelope) = ctx.recv().await {
+    ///     msg!(match envelope {
+    ///         (GetValue, token) => {
+    ///             let _ = ctx.forward(GetValue, token).await;
+    ///         }
+    ///     });
+    /// }
+    /// # }
+    /// ```
+    ///
+    /// [inter-group routing]: https://actoromicon.rs/ch04-01-routing.html
+    pub async fn forward<R: Request>(
+        &self,
+        request: R,
+        token: ResponseToken<R>,
+    ) -> Result<(), SendError<R>> {
+        let kind = self.forwarded_kind(token);
+        self.do_send_async(request, kind).await
+    }
+
+    /// Forwards the received request to the specified recipient.
+    /// The final responder replies directly to the original requester.
+    ///
+    /// See [`Context::forward()`] for details.
+    pub async fn forward_to<R: Request>(
+        &self,
+        recipient: Addr,
+        request: R,
+        token: ResponseToken<R>,
+    ) -> Result<(), SendError<R>> {
+        let kind = self.forwarded_kind(token);
+        self.do_send_to(recipient, request, kind, |object, envelope| {
+            Object::send(object, recipient, envelope)
+        })?
+        .await
+        .map_err(|err| err.map(e2m))
+    }
+
+    /// Forwards the received envelope as is, e.g. a request of unknown type.
+    /// The final responder replies directly to the original requester.
+    #[doc(hidden)]
+    #[cfg(feature = "test-util")]
+    pub async fn forward_envelope_to(
+        &self,
+        recipient: Addr,
+        envelope: Envelope,
+    ) -> Result<(), SendError<Envelope>> {
+        let fut = {
+            let guard = EbrGuard::new();
+            let object = ward!(
+                self.book.get(recipient, &guard),
+                return Err(SendError(envelope))
+            );
+            Object::send(object, recipient, envelope)
+        };
+        fut.await
+    }
+
+    fn forwarded_kind<R>(&self, token: ResponseToken<R>) -> MessageKind {
+        if token.is_forgotten() {
+            // The requester doesn't wait for the response.
+            MessageKind::regular(self.actor_addr)
+        } else {
+            MessageKind::RequestAny(token.into_forwarded())
+        }
+    }
+
+    /// Returns a handle to send messages to the specified recipient from code
+    /// outside the actor system, e.g. from callbacks of other frameworks.
+    ///
+    /// See [`ExternalSender`] for details.
+    pub fn sender_to(&self, recipient: Addr) -> ExternalSender {
+        ExternalSender::new(self.book.clone(), recipient)
+    }
+
     /// Responds to the requester with the provided response.
     ///
     /// The token can be used only once.
@@ -638,29 +937,14 @@ impl<C, K> Context<C, K> {
     /// })
     /// ```
     pub fn respond<R: Request>(&self, token: ResponseToken<R>, message: R::Response) {
-        if token.is_forgotten() {
-            return;
-        }
-
-        let token = token.into_untyped();
-        let recipient = token.sender();
-        let message = R::Wrapper::from(message);
-        self.stats.on_sent_message(&message); // TODO: only if successful?
-
-        let kind = MessageKind::Response {
-            sender: self.addr(),
-            request_id: token.request_id(),
-        };
-
-        trace!(to = %recipient, "> {:?}", message);
-        if let Some(permit) = DUMPER.acquire_m(&message) {
-            permit.record(Dump::message(&message, &kind, Direction::Out));
-        }
-
-        let envelope = Envelope::new(message, kind);
-        let guard = EbrGuard::new();
-        let object = ward!(self.book.get(recipient, &guard));
-        object.respond(token, Ok(envelope));
+        do_respond(
+            &self.book,
+            &self.stats,
+            &self.span,
+            self.addr(),
+            token,
+            message,
+        );
     }
 
     /// Receives the next envelope from the mailbox or sources.
@@ -726,6 +1010,9 @@ impl<C, K> Context<C, K> {
                 }
             };
 
+            #[cfg(feature = "test-util")]
+            let envelope = ward!(crate::faults::apply(envelope).await, continue 'outer);
+
             if let Some(envelope) = self.post_recv(envelope) {
                 return Some(envelope);
             }
@@ -828,6 +1115,9 @@ impl<C, K> Context<C, K> {
                 return Err(TryRecvError::Empty);
             };
 
+            #[cfg(feature = "test-util")]
+            let envelope = ward!(crate::faults::apply(envelope).await, continue);
+
             if let Some(envelope) = self.post_recv(envelope) {
                 return Ok(envelope);
             }
@@ -868,9 +1158,13 @@ impl<C, K> Context<C, K> {
 
     async fn pre_recv(&mut self) {
         self.stats.on_recv();
+        self.span.on_recv();
 
         coop::consume_budget().await;
 
+        #[cfg(feature = "test-util")]
+        crate::scheduling::perturb().await;
+
         if unlikely(self.stage == Stage::Closed) {
             panic!("calling `recv()` or `try_recv()` after `None` is returned, an infinite loop?");
         }
@@ -888,11 +1182,14 @@ impl<C, K> Context<C, K> {
     where
         C: 'static,
     {
-        scope::set_trace_id(envelope.trace_id());
+        scope::with(|scope| {
+            scope.set_trace_id(envelope.trace_id());
+            scope.set_baggage(envelope.baggage().clone());
+        });
 
         let envelope = msg!(match envelope {
             (messages::UpdateConfig { config }, token) => {
-                self.config = config.get_user::<C>().clone();
+                self.config = config.get_user_for::<C>(&scope::meta().key).clone();
                 info!("config updated");
                 let message = messages::ConfigUpdated {};
                 let kind = MessageKind::regular(self.actor_addr);
@@ -917,6 +1214,7 @@ impl<C, K> Context<C, K> {
         }
 
         self.stats.on_received_envelope(&envelope);
+        self.span.on_received_envelope(&envelope);
 
         msg!(match envelope {
             (messages::Ping, token) => {
@@ -961,11 +1259,15 @@ impl<C, K> Context<C, K> {
             actor_start_info: self.actor_start_info.clone(),
             group_addr: self.group_addr,
             demux: self.demux.clone(),
+            topics: self.topics.clone(),
             config: Arc::new(()),
             key: Singleton,
             sources: Sources::new(),
+            keyed_sources: KeyedSources::default(),
             stage: self.stage,
             stats: Stats::empty(),
+            span: SpanTracker::empty(),
+            event_log: None,
         }
     }
 
@@ -975,6 +1277,10 @@ impl<C, K> Context<C, K> {
         &self.book
     }
 
+    pub(crate) fn topics(&self) -> &Topics {
+        &self.topics
+    }
+
     pub(crate) fn with_config<C1>(self, config: Arc<C1>) -> Context<C1, K> {
         Context {
             book: self.book,
@@ -983,11 +1289,15 @@ impl<C, K> Context<C, K> {
             actor_start_info: self.actor_start_info,
             group_addr: self.group_addr,
             demux: self.demux,
+            topics: self.topics,
             config,
             key: self.key,
             sources: self.sources,
+            keyed_sources: self.keyed_sources,
             stage: self.stage,
             stats: self.stats,
+            span: self.span,
+            event_log: self.event_log,
         }
     }
 
@@ -1009,6 +1319,11 @@ impl<C, K> Context<C, K> {
         self
     }
 
+    pub(crate) fn with_event_log(mut self, event_log: Box<dyn StdAny + Send>) -> Self {
+        self.event_log = Some(Mutex::new(event_log));
+        self
+    }
+
     pub(crate) fn with_key<K1>(self, key: K1) -> Context<C, K1> {
         Context {
             book: self.book,
@@ -1017,15 +1332,77 @@ impl<C, K> Context<C, K> {
             actor_start_info: self.actor_start_info,
             group_addr: self.group_addr,
             demux: self.demux,
+            topics: self.topics,
             config: self.config,
             key,
             sources: self.sources,
+            keyed_sources: self.keyed_sources,
             stage: self.stage,
             stats: self.stats,
+            span: self.span,
+            event_log: self.event_log,
         }
     }
 }
 
+/// Used by [`DetachedResponseToken::respond()`].
+///
+/// [`DetachedResponseToken::respond()`]: crate::DetachedResponseToken::respond()
+pub(crate) fn respond_detached<R: Request>(
+    responder: Addr,
+    token: ResponseToken<R>,
+    message: R::Response,
+) {
+    let book = ward!(token.book()).clone();
+    do_respond(
+        &book,
+        &Stats::empty(),
+        &SpanTracker::empty(),
+        responder,
+        token,
+        message,
+    );
+}
+
+fn do_respond<R: Request>(
+    book: &AddressBook,
+    stats: &Stats,
+    span: &SpanTracker,
+    responder: Addr,
+    token: ResponseToken<R>,
+    message: R::Response,
+) {
+    if token.is_forgotten() {
+        return;
+    }
+
+    let token = token.into_untyped();
+    let recipient = token.sender();
+    let message = R::Wrapper::from(message);
+    stats.on_sent_message(&message); // TODO: only if successful?
+    span.on_responded(&message);
+
+    scope::try_with(|scope| {
+        let caches = scope.response_caches();
+        caches.on_response::<R>(recipient, token.request_id(), &message);
+    });
+
+    let kind = MessageKind::Response {
+        sender: responder,
+        request_id: token.request_id(),
+    };
+
+    trace!(to = %recipient, "> {:?}", message);
+    if let Some(permit) = DUMPER.acquire_m(&message) {
+        permit.record(Dump::message(&message, &kind, Direction::Out));
+    }
+
+    let envelope = Envelope::new(message, kind);
+    let guard = EbrGuard::new();
+    let object = ward!(book.get(recipient, &guard));
+    object.respond(token, Ok(envelope));
+}
+
 #[cold]
 fn e2m<M: Message>(envelope: Envelope) -> M {
     envelope.unpack().expect("invalid message").0
@@ -1061,9 +1438,10 @@ fn addrs_with_envelope(
 }
 
 impl Context {
-    pub(crate) fn new(book: AddressBook, demux: Demux) -> Self {
+    pub(crate) fn new(book: AddressBook, topics: Topics, demux: Demux) -> Self {
         Self {
             book,
+            topics,
             actor: None,
             actor_addr: Addr::NULL,
             group_addr: Addr::NULL,
@@ -1072,8 +1450,11 @@ impl Context {
             config: Arc::new(()),
             key: Singleton,
             sources: Sources::new(),
+            keyed_sources: KeyedSources::default(),
             stage: Stage::PreRecv,
             stats: Stats::empty(),
+            span: SpanTracker::empty(),
+            event_log: None,
         }
     }
 }
@@ -1088,11 +1469,15 @@ impl<C, K: Clone> Clone for Context<C, K> {
             actor_start_info: self.actor_start_info.clone(),
             group_addr: self.group_addr,
             demux: self.demux.clone(),
+            topics: self.topics.clone(),
             config: self.config.clone(),
             key: self.key.clone(),
             sources: Sources::new(),
+            keyed_sources: KeyedSources::default(),
             stage: self.stage,
             stats: Stats::empty(),
+            span: SpanTracker::empty(),
+            event_log: None,
         }
     }
 }
@@ -1179,6 +1564,36 @@ impl<'c, C: 'static, K, R: Request> RequestBuilder<'c, C, K, R, Any> {
         debug_assert_eq!(responses.len(), 1);
         prepare_response::<R>(responses.pop().expect("missing response"))
     }
+
+    /// Waits for the response of the request with `Result<T, E>` response
+    /// (`#[message(ret = Result<T, E>)]`), so errors of the responder are
+    /// returned as [`TypedRequestError::Responded`] instead of `Ok(Err(_))`.
+    ///
+    /// ```
+    /// # use elfo_core as elfo;
+    /// # async fn exec(ctx: elfo::Context) {
+    /// # use elfo::{errors::TypedRequestError, message};
+    /// #[message]
+    /// enum ParseError {
+    ///     Empty,
+    /// }
+    ///
+    /// #[message(ret = Result<u32, ParseError>)]
+    /// struct Parse(String);
+    ///
+    /// match ctx.request(Parse("42".into())).resolve_typed().await {
+    ///     Ok(number) => { /* ... */ }
+    ///     Err(TypedRequestError::Responded(ParseError::Empty)) => { /* ... */ }
+    ///     Err(TypedRequestError::Request(err)) => { /* ... */ }
+    /// }
+    /// # }
+    /// ```
+    pub async fn resolve_typed<T, E>(self) -> Result<T, TypedRequestError<E>>
+    where
+        R: Request<Response = Result<T, E>>,
+    {
+        self.resolve().await?.map_err(TypedRequestError::Responded)
+    }
 }
 
 impl<'c, C: 'static, K, R: Request> RequestBuilder<'c, C, K, R, All> {
@@ -1208,6 +1623,20 @@ impl<'c, C: 'static, K, R: Request> RequestBuilder<'c, C, K, R, All> {
             .map(prepare_response::<R>)
             .collect()
     }
+
+    /// Waits for the responses of the request with `Result<T, E>` response,
+    /// so errors of responders are returned as
+    /// [`TypedRequestError::Responded`] instead of `Ok(Err(_))`.
+    pub async fn resolve_typed<T, E>(self) -> Vec<Result<T, TypedRequestError<E>>>
+    where
+        R: Request<Response = Result<T, E>>,
+    {
+        self.resolve()
+            .await
+            .into_iter()
+            .map(|response| response?.map_err(TypedRequestError::Responded))
+            .collect()
+    }
 }
 
 fn prepare_response<R: Request>(
@@ -0,0 +1,123 @@
+use std::time::Duration;
+
+use parking_lot::Mutex;
+
+use elfo_utils::time::{Instant, SystemTime};
+
+use crate::{
+    dumping::MessageKind,
+    envelope::Envelope,
+    message::Message,
+    scope,
+    tracing::{span_recorder, Span, SpanEvent, SpanEventKind, TraceId},
+};
+
+/// Builds a span of the currently handled message if a span recorder is set.
+pub(super) struct SpanTracker {
+    // `Mutex` is required because messages are sent by `&Context`.
+    current: Mutex<Option<InHandling>>,
+    // Whether `current` is set, to avoid locking for unsampled messages.
+    tracking: bool,
+}
+
+struct InHandling {
+    span: Span,
+    start_time: Instant,
+}
+
+impl SpanTracker {
+    pub(super) fn empty() -> Self {
+        Self {
+            current: Mutex::new(None),
+            tracking: false,
+        }
+    }
+
+    pub(super) fn on_recv(&mut self) {
+        self.finish();
+    }
+
+    pub(super) fn on_received_envelope(&mut self, envelope: &Envelope) {
+        debug_assert!(self.current.get_mut().is_none());
+
+        let recorder = ward!(span_recorder());
+        if !recorder.enabled() {
+            return;
+        }
+
+        let (meta, is_sampled) = ward!(scope::try_with(|scope| (
+            scope.meta().clone(),
+            scope.is_sampled()
+        )));
+
+        // Only sampled traces are exported, see `system.tracing`.
+        if !is_sampled {
+            return;
+        }
+
+        let message = envelope.message();
+
+        let span_id = TraceId::generate_default();
+        scope::with(|scope| scope.set_span_id(Some(span_id)));
+
+        let span = Span {
+            meta,
+            trace_id: envelope.trace_id(),
+            span_id,
+            parent_span_id: envelope.parent_span_id(),
+            baggage: envelope.baggage().clone(),
+            message_name: message.name(),
+            message_protocol: message.protocol(),
+            message_kind: MessageKind::from_message_kind(envelope.message_kind()),
+            start_time: SystemTime::now(),
+            duration: Duration::ZERO,
+            events: Vec::new(),
+        };
+
+        *self.current.get_mut() = Some(InHandling {
+            span,
+            start_time: Instant::now(),
+        });
+        self.tracking = true;
+    }
+
+    pub(super) fn on_sent_message(&self, message: &impl Message) {
+        self.add_event(SpanEventKind::Sent, message);
+    }
+
+    pub(super) fn on_responded(&self, message: &impl Message) {
+        self.add_event(SpanEventKind::Responded, message);
+    }
+
+    fn add_event(&self, kind: SpanEventKind, message: &impl Message) {
+        if !self.tracking {
+            return;
+        }
+
+        let mut current = self.current.lock();
+        let in_handling = ward!(current.as_mut());
+
+        in_handling.span.events.push(SpanEvent {
+            timestamp: SystemTime::now(),
+            kind,
+            message_name: message.name(),
+            message_protocol: message.protocol(),
+        });
+    }
+
+    fn finish(&mut self) {
+        let mut in_handling = ward!(self.current.get_mut().take());
+        self.tracking = false;
+        scope::try_with(|scope| scope.set_span_id(None));
+
+        let recorder = ward!(span_recorder());
+        in_handling.span.duration = in_handling.start_time.elapsed();
+        recorder.record(in_handling.span);
+    }
+}
+
+impl Drop for SpanTracker {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
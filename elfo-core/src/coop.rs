@@ -32,6 +32,16 @@
 //!
 //! These limits cannot be configured for now.
 //!
+//! # Send budget
+//!
+//! Also, an actor that sends many messages in one handler can starve
+//! receivers of these messages (often, actors of its own group), especially if
+//! mailboxes aren't full. Thus, [`Context::send()`] and [`Context::send_to()`]
+//! can consume a separate send budget and yield to the executor after a certain
+//! number of sent messages, configured by `system.coop.send_budget` (see
+//! [`CoopConfig`]). It's disabled by default. Forced yields are counted by the
+//! `elfo_send_budget_yields_total` metric.
+//!
 //! # Coordination with tokio's budget system
 //!
 //! Tokio has its own budget system, which is unstable and cannot be used by
@@ -43,17 +53,58 @@
 //!
 //! [`Context::recv()`]: crate::context::Context::recv
 //! [`Context::try_recv()`]: crate::context::Context::try_recv
+//! [`Context::send()`]: crate::context::Context::send
+//! [`Context::send_to()`]: crate::context::Context::send_to
+//! [`CoopConfig`]: config::CoopConfig
 
 use std::cell::Cell;
 
+use metrics::Key;
+
 use elfo_utils::time::Instant;
 
+use crate::scope;
+
+// === CoopConfig ===
+
+pub mod config {
+    //! [Config]
+    //!
+    //! [Config]: CoopConfig
+
+    /// Cooperative budgeting configuration.
+    ///
+    /// # Example
+    /// ```toml
+    /// [some_group]
+    /// system.coop.send_budget = 1000
+    /// ```
+    #[derive(Debug, Default, PartialEq, serde::Deserialize)]
+    #[serde(default)]
+    pub struct CoopConfig {
+        /// The number of messages sent by [`Context::send()`] and
+        /// [`Context::send_to()`] in one poll of an actor, after which the
+        /// actor yields to the executor. `0` disables the send budget.
+        ///
+        /// `0` by default.
+        ///
+        /// [`Context::send()`]: crate::Context::send
+        /// [`Context::send_to()`]: crate::Context::send_to
+        pub send_budget: u32,
+    }
+}
+
+// === Budget ===
+
+static SEND_BUDGET_YIELDS: Key = Key::from_static_name("elfo_send_budget_yields_total");
+
 // TODO: make it configurable as `system.budget = "5ms" | 64 | "Unlimited"`
 const MAX_TIME_NS: u64 = 5_000_000; // 5ms
 const MAX_COUNT: u32 = 64;
 
 thread_local! {
     static BUDGET: Cell<Budget> = const { Cell::new(Budget::ByCount(0)) };
+    static SENT: Cell<u32> = const { Cell::new(0) };
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -69,6 +120,7 @@ enum Budget {
 #[inline]
 pub(crate) fn reset(busy_since: Option<Instant>) {
     BUDGET.with(|budget| budget.set(busy_since.map_or(Budget::ByCount(MAX_COUNT), Budget::ByTime)));
+    SENT.with(|sent| sent.set(0));
 }
 
 /// Consumes a unit of budget and returns the execution back to the executor,
@@ -115,6 +167,33 @@ pub async fn consume_budget() {
     }
 }
 
+/// Consumes a unit of the send budget and returns the execution back to the
+/// executor, but only if the budget configured for the group has been
+/// exhausted.
+#[inline]
+pub(crate) async fn consume_send_budget() {
+    let limit = scope::try_with(|scope| scope.send_budget()).unwrap_or(0);
+
+    if limit == 0 {
+        return;
+    }
+
+    let to_yield = SENT.with(|cell| {
+        let sent = cell.get() + 1;
+        let exhausted = sent >= limit;
+        cell.set(if exhausted { 0 } else { sent });
+        exhausted
+    });
+
+    if to_yield {
+        if let Some(recorder) = metrics::try_recorder() {
+            recorder.increment_counter(&SEND_BUDGET_YIELDS, 1);
+        }
+
+        tokio::task::yield_now().await;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::{
@@ -130,6 +209,7 @@ mod tests {
     use elfo_utils::time::with_instant_mock;
 
     use super::*;
+    use crate::scope::Scope;
 
     fn current_budget() -> Budget {
         BUDGET.with(Cell::get)
@@ -193,4 +273,31 @@ mod tests {
             rt.block_on(ResetOnPoll(true, task));
         })
     }
+
+    fn scope(send_budget: u32) -> Scope {
+        Scope::test_configured(|config| config.coop.send_budget = send_budget)
+    }
+
+    #[test]
+    fn send_budget() {
+        let rt = Builder::new_current_thread().build().unwrap();
+
+        for (send_budget, expected_polls) in [(0, 1), (10, 11), (30, 4), (1000, 1)] {
+            let mut polls = 0;
+
+            let task = async {
+                for _ in 0..100 {
+                    consume_send_budget().await;
+                }
+            };
+
+            let mut task = pin!(scope(send_budget).within(ResetOnPoll(false, task)));
+            rt.block_on(std::future::poll_fn(|cx| {
+                polls += 1;
+                task.as_mut().poll(cx)
+            }));
+
+            assert_eq!(polls, expected_polls, "{send_budget}");
+        }
+    }
 }
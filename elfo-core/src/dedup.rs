@@ -0,0 +1,122 @@
+//! Contains `Deduplicators` that drop duplicate incoming messages.
+//! See [`ActorGroup::deduplicate_by()`] for details.
+//!
+//! [`ActorGroup::deduplicate_by()`]: crate::ActorGroup::deduplicate_by()
+
+use std::{any::TypeId, fmt, hash::Hash, time::Duration};
+
+use fxhash::FxHashMap;
+use parking_lot::Mutex;
+use tokio::time::Instant;
+
+use crate::{envelope::Envelope, message::Message};
+
+/// Deduplicators of messages of different types, one per group.
+#[derive(Default)]
+pub(crate) struct Deduplicators {
+    dedups: Vec<Box<dyn AnyDedup>>,
+}
+
+impl Deduplicators {
+    /// Adds a deduplicator for messages of the type `M`,
+    /// replacing the previous one.
+    pub(crate) fn add<M, K>(
+        &mut self,
+        window: Duration,
+        key: impl Fn(&M) -> K + Send + Sync + 'static,
+    ) where
+        M: Message,
+        K: Hash + Eq + Send + 'static,
+    {
+        let dedup = Dedup {
+            window,
+            key: Box::new(key),
+            inner: Mutex::new(DedupInner {
+                seen: FxHashMap::default(),
+                next_cleanup: Instant::now() + window,
+            }),
+        };
+
+        self.dedups
+            .retain(|d| d.message_type() != TypeId::of::<M>());
+        self.dedups.push(Box::new(dedup));
+    }
+
+    /// Returns `true` if the message with the same key has already been seen
+    /// within the window. Otherwise, remembers the message.
+    pub(crate) fn is_duplicate(&self, envelope: &Envelope) -> bool {
+        self.dedups.iter().any(|d| d.is_duplicate(envelope))
+    }
+
+    /// Forgets the key of the message that has not been delivered,
+    /// so that a retry of the message isn't considered a duplicate.
+    pub(crate) fn forget(&self, envelope: &Envelope) {
+        for dedup in &self.dedups {
+            dedup.forget(envelope);
+        }
+    }
+}
+
+impl fmt::Debug for Deduplicators {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Deduplicators")
+            .field("len", &self.dedups.len())
+            .finish()
+    }
+}
+
+trait AnyDedup: Send + Sync {
+    fn message_type(&self) -> TypeId;
+    fn is_duplicate(&self, envelope: &Envelope) -> bool;
+    fn forget(&self, envelope: &Envelope);
+}
+
+struct Dedup<M, K> {
+    window: Duration,
+    key: Box<dyn Fn(&M) -> K + Send + Sync>,
+    inner: Mutex<DedupInner<K>>,
+}
+
+struct DedupInner<K> {
+    /// Keys along with the time they were seen for the first time.
+    seen: FxHashMap<K, Instant>,
+    next_cleanup: Instant,
+}
+
+impl<M, K> AnyDedup for Dedup<M, K>
+where
+    M: Message,
+    K: Hash + Eq + Send + 'static,
+{
+    fn message_type(&self) -> TypeId {
+        TypeId::of::<M>()
+    }
+
+    fn is_duplicate(&self, envelope: &Envelope) -> bool {
+        let message = ward!(envelope.message().downcast_ref::<M>(), return false);
+        let key = (self.key)(message);
+
+        let now = Instant::now();
+        let mut inner = self.inner.lock();
+
+        if now >= inner.next_cleanup {
+            let window = self.window;
+            inner.seen.retain(|_, at| now.duration_since(*at) < window);
+            inner.next_cleanup = now + window;
+        }
+
+        match inner.seen.get(&key) {
+            Some(at) if now.duration_since(*at) < self.window => true,
+            _ => {
+                inner.seen.insert(key, now);
+                false
+            }
+        }
+    }
+
+    fn forget(&self, envelope: &Envelope) {
+        let message = ward!(envelope.message().downcast_ref::<M>());
+        let key = (self.key)(message);
+        self.inner.lock().seen.remove(&key);
+    }
+}
@@ -1,37 +1,71 @@
-use std::sync::Arc;
+use std::{fmt, sync::Arc};
 
+use arc_swap::ArcSwap;
 use smallvec::SmallVec;
 
 use crate::{envelope::Envelope, Addr};
 
 const OPTIMAL_COUNT: usize = 5;
 type Addrs = SmallVec<[Addr; OPTIMAL_COUNT]>;
+type Filter = Arc<dyn Fn(&Envelope, &mut Addrs) + Send + Sync>;
 
 // Actually, it's a private type, `pub` is for `Destination` only.
+/// Routes are shared between all clones and can be changed at runtime.
 #[derive(Default, Clone)]
 pub struct Demux {
-    #[allow(clippy::type_complexity)]
-    filter: Option<Arc<dyn Fn(&Envelope, &mut Addrs) + Send + Sync>>,
+    routes: Arc<ArcSwap<Vec<Route>>>,
+}
+
+#[derive(Clone)]
+struct Route {
+    /// A local group, `None` for remote ones.
+    to: Option<Addr>,
+    filter: Filter,
 }
 
 impl Demux {
-    pub(crate) fn append(&mut self, f: impl Fn(&Envelope, &mut Addrs) + Send + Sync + 'static) {
-        self.filter = Some(if let Some(prev) = self.filter.take() {
-            Arc::new(move |envelope, addrs| {
-                prev(envelope, addrs);
-                f(envelope, addrs);
-            })
-        } else {
-            Arc::new(f)
-        })
+    pub(crate) fn append(
+        &self,
+        to: Option<Addr>,
+        f: impl Fn(&Envelope, &mut Addrs) + Send + Sync + 'static,
+    ) {
+        let filter: Filter = Arc::new(f);
+
+        self.routes.rcu(|routes| {
+            let mut routes = (**routes).clone();
+            routes.push(Route {
+                to,
+                filter: filter.clone(),
+            });
+            routes
+        });
+    }
+
+    /// Removes all routes to the specified local group.
+    pub(crate) fn remove_routes_to(&self, addr: Addr) {
+        self.routes.rcu(|routes| {
+            routes
+                .iter()
+                .filter(|route| route.to != Some(addr))
+                .cloned()
+                .collect::<Vec<_>>()
+        });
     }
 
     // TODO: return an iterator?
     pub(crate) fn filter(&self, envelope: &Envelope) -> Addrs {
         let mut addrs = Addrs::new();
-        if let Some(filter) = &self.filter {
-            (filter)(envelope, &mut addrs);
+        for route in self.routes.load().iter() {
+            (route.filter)(envelope, &mut addrs);
         }
         addrs
     }
 }
+
+impl fmt::Debug for Demux {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Demux")
+            .field("routes", &self.routes.load().len())
+            .finish()
+    }
+}
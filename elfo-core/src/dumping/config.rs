@@ -2,6 +2,7 @@
 //!
 //! [Config]: DumpingConfig
 
+use fxhash::FxHashMap;
 use serde::Deserialize;
 
 /// Dumping configuration.
@@ -11,6 +12,8 @@ use serde::Deserialize;
 /// [some_group]
 /// system.dumping.disabled = false
 /// system.dumping.max_rate = 1_000
+/// system.dumping.failures = false
+/// system.dumping.classes.slow = { disabled = true }
 /// ```
 #[derive(Debug, Clone, Deserialize)]
 #[serde(default)]
@@ -24,7 +27,23 @@ pub struct DumpingConfig {
     ///
     /// `100_000` by default.
     pub max_rate: u64,
-    // TODO: per class overrides.
+    /// Whether to dump statuses of actors failed by panics, including
+    /// backtraces if captured.
+    ///
+    /// `true` by default.
+    pub failures: bool,
+    /// Overrides for specific dump classes, e.g. to enable only some class
+    /// while dumping is disabled for others.
+    ///
+    /// Empty by default.
+    pub classes: FxHashMap<String, DumpingClassConfig>,
+}
+
+impl DumpingConfig {
+    /// Whether dumping is enabled for at least one class.
+    pub(crate) fn is_enabled(&self) -> bool {
+        !self.disabled || self.classes.values().any(|c| c.disabled == Some(false))
+    }
 }
 
 impl Default for DumpingConfig {
@@ -32,6 +51,18 @@ impl Default for DumpingConfig {
         Self {
             disabled: false,
             max_rate: 100_000,
+            failures: true,
+            classes: FxHashMap::default(),
         }
     }
 }
+
+/// Overrides of [`DumpingConfig`] for a specific dump class.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct DumpingClassConfig {
+    /// Overrides `disabled` for the class.
+    pub disabled: Option<bool>,
+    /// Overrides `max_rate` for the class.
+    pub max_rate: Option<u64>,
+}
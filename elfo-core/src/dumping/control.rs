@@ -36,12 +36,16 @@ impl PerClass {
     }
 
     fn with_config(&self, config: &DumpingConfig) -> Self {
+        let overrides = config.classes.get(self.class);
+        let max_rate = overrides.and_then(|c| c.max_rate);
+        let disabled = overrides.and_then(|c| c.disabled);
+
         let limiter = self.limiter.clone();
-        limiter.configure(RateLimit::Rps(config.max_rate));
+        limiter.configure(RateLimit::Rps(max_rate.unwrap_or(config.max_rate)));
 
         Self {
             class: self.class,
-            disabled: config.disabled,
+            disabled: disabled.unwrap_or(config.disabled),
             limiter,
         }
     }
@@ -117,3 +121,35 @@ pub enum CheckResult {
 fn find_class<'a>(classes: &'a [PerClass], class: &'static str) -> Option<&'a PerClass> {
     classes.iter().find(|c| c.class == class)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dumping::config::DumpingClassConfig;
+
+    #[test]
+    fn class_overrides() {
+        let control = DumpingControl::default();
+        let mut config = DumpingConfig {
+            disabled: true,
+            ..DumpingConfig::default()
+        };
+        assert!(!config.is_enabled());
+
+        let enabled = DumpingClassConfig {
+            disabled: Some(false),
+            ..DumpingClassConfig::default()
+        };
+        config.classes.insert("slow".into(), enabled);
+        assert!(config.is_enabled());
+
+        control.configure(&config);
+        assert!(matches!(control.check("slow"), CheckResult::Passed));
+        assert!(matches!(control.check("other"), CheckResult::NotInterested));
+
+        // Existing classes are reconfigured.
+        config.classes.clear();
+        control.configure(&config);
+        assert!(matches!(control.check("slow"), CheckResult::NotInterested));
+    }
+}
@@ -7,7 +7,13 @@ use smallbox::{smallbox, SmallBox};
 use elfo_utils::time::SystemTime;
 
 use super::{extract_name::extract_name, sequence_no::SequenceNo};
-use crate::{actor::ActorMeta, envelope, scope, thread::ThreadId, tracing::TraceId, Message};
+use crate::{
+    actor::ActorMeta,
+    envelope, scope,
+    thread::ThreadId,
+    tracing::{Baggage, TraceId},
+    Message,
+};
 
 // === Dump ===
 
@@ -18,6 +24,7 @@ pub struct Dump {
     pub sequence_no: SequenceNo,
     pub timestamp: SystemTime,
     pub trace_id: TraceId,
+    pub baggage: Baggage,
     pub thread_id: ThreadId,
     pub direction: Direction,
     pub message_name: MessageName,
@@ -31,7 +38,7 @@ pub struct Dump {
 pub type ErasedMessage = SmallBox<dyn ErasedSerialize + Send, [usize; 24]>;
 
 assert_impl_all!(Dump: Send);
-assert_eq_size!(Dump, [u8; 320]);
+assert_eq_size!(Dump, [u8; 328]);
 
 impl Dump {
     #[stability::unstable]
@@ -115,10 +122,11 @@ impl DumpBuilder {
     }
 
     fn do_finish(&mut self, message: ErasedMessage) -> Dump {
-        let (meta, trace_id, sequence_no) = scope::with(|scope| {
+        let (meta, trace_id, baggage, sequence_no) = scope::with(|scope| {
             (
                 scope.meta().clone(),
                 scope.trace_id(),
+                scope.baggage(),
                 scope.dumping().next_sequence_no(),
             )
         });
@@ -128,6 +136,7 @@ impl DumpBuilder {
             sequence_no,
             timestamp: self.timestamp.unwrap_or_else(SystemTime::now),
             trace_id,
+            baggage,
             thread_id: crate::thread::id(),
             direction: self.direction,
             message_name: self.message_name.take().unwrap_or_default(),
@@ -1,6 +1,9 @@
 //! Includes structs and functions to work with dumping.
 //! For more details see [The Actoromicon](https://actoromicon.rs/ch05-03-dumping.md.html).
 
+use std::fmt;
+
+use once_cell::sync::Lazy;
 use serde::{Serialize, Serializer};
 
 #[cfg(feature = "unstable")] // TODO: patch `stability`, again.
@@ -46,3 +49,84 @@ pub fn hide<T: Serialize, S: Serializer>(value: &T, serializer: S) -> Result<S::
         value.serialize(serializer)
     }
 }
+
+/// Dumps a field as `<redacted>`.
+/// Used for `#[message(redact)]` fields.
+pub fn redact<T: Serialize, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+    if crate::scope::serde_mode() == crate::scope::SerdeMode::Dumping {
+        serializer.serialize_str("<redacted>")
+    } else {
+        value.serialize(serializer)
+    }
+}
+
+/// Dumps a field as `<redacted:HASH>`, see [`RedactedHash`] for details.
+/// Used for `#[message(redact(hash))]` fields.
+pub fn redact_hash<T: Serialize, S: Serializer>(
+    value: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    if crate::scope::serde_mode() == crate::scope::SerdeMode::Dumping {
+        serializer.collect_str(&RedactedHash(value))
+    } else {
+        value.serialize(serializer)
+    }
+}
+
+/// Prints `#[message(redact)]` fields in generated `Debug` impls.
+#[doc(hidden)]
+pub struct RedactedMask;
+
+impl fmt::Debug for RedactedMask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("<redacted>")
+    }
+}
+
+/// The env variable containing a key to hash `#[message(redact(hash))]`
+/// fields. It should be the same for all nodes of a deployment to correlate
+/// values between them. If unset, a random key is generated on every start.
+pub const REDACT_KEY_ENV: &str = "ELFO_REDACT_KEY";
+
+static REDACT_KEY: Lazy<[u8; 32]> = Lazy::new(|| {
+    const CONTEXT: &str = "elfo 2024-09-01 redact(hash) key";
+
+    match std::env::var(REDACT_KEY_ENV) {
+        Ok(key) => blake3::derive_key(CONTEXT, key.as_bytes()),
+        Err(_) => {
+            let seed = (0..4).flat_map(|_| crate::addr::random_u64().to_le_bytes());
+            blake3::derive_key(CONTEXT, &seed.collect::<Vec<_>>())
+        }
+    }
+});
+
+/// Prints `#[message(redact(hash))]` fields in generated `Debug` impls.
+///
+/// The hash is a keyed BLAKE3 of the value serialized to JSON, truncated to
+/// 64 bits. It's intended to correlate equal values, not to keep them secret:
+/// anyone knowing the key (see [`REDACT_KEY_ENV`]) can check guesses of
+/// low-entropy values, e.g. emails or card numbers. Use `#[message(redact)]`
+/// if values mustn't be correlated at all.
+#[doc(hidden)]
+pub struct RedactedHash<'a, T: ?Sized>(pub &'a T);
+
+impl<T: Serialize + ?Sized> fmt::Debug for RedactedHash<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl<T: Serialize + ?Sized> fmt::Display for RedactedHash<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut hasher = blake3::Hasher::new_keyed(&REDACT_KEY);
+
+        // Hash the actual value, even if nested fields are hidden in dumps.
+        let mode = crate::scope::SerdeMode::Normal;
+        crate::scope::with_serde_mode(mode, || serde_json::to_writer(&mut hasher, self.0))
+            .map_err(|_| fmt::Error)?;
+
+        let hash = hasher.finalize();
+        let hash = u64::from_le_bytes(hash.as_bytes()[..8].try_into().unwrap());
+        write!(f, "<redacted:{hash:016x}>")
+    }
+}
@@ -3,10 +3,10 @@ use std::{alloc, fmt, mem, ptr, ptr::NonNull};
 use elfo_utils::time::Instant;
 
 use crate::{
-    mailbox,
+    envelope_pool, mailbox,
     message::{AnyMessageRef, Message, MessageRepr, MessageTypeId, Request},
     request_table::{RequestId, ResponseToken},
-    tracing::TraceId,
+    tracing::{Baggage, TraceId},
     Addr,
 };
 
@@ -28,16 +28,22 @@ assert_not_impl_any!(Envelope: Sync);
 assert_impl_all!(Envelope: Send);
 assert_eq_size!(Envelope, usize);
 
-// TODO: the current size (on x86-64) is 64 bytes, but it can be reduced.
+// TODO: the current size (on x86-64) is 80 bytes, but it can be reduced.
 // And... it should be reduced once `TraceId` is extended to 16 bytes.
 pub(crate) struct EnvelopeHeader {
     /// See `mailbox.rs` for more details.
     pub(crate) link: mailbox::Link,
     created_time: Instant, // Now used also as a sent time.
     trace_id: TraceId,
+    baggage: Baggage,
+    parent_span_id: Option<TraceId>,
     kind: MessageKind,
     /// Offset from the beginning of the envelope to the `MessageRepr`.
     message_offset: u32,
+    /// The pool allocated the envelope if any, see `envelope_pool.rs`.
+    pool: Option<envelope_pool::Owner>,
+    /// Whether the size is counted by the memory budget, see `mailbox.rs`.
+    is_budgeted: bool,
 }
 
 assert_impl_all!(EnvelopeHeader: Send);
@@ -70,6 +76,8 @@ impl Drop for Envelope {
         let message_layout = message._repr_layout();
         let (layout, message_offset) = envelope_repr_layout(message_layout);
         debug_assert_eq!(message_offset, self.header().message_offset);
+        // SAFETY: the header is valid, it's dropped below anyway.
+        let pool = unsafe { (*self.0.as_ptr()).pool.take() };
 
         // Drop the message.
         // SAFETY: the message is not accessed anymore below.
@@ -80,8 +88,8 @@ impl Drop for Envelope {
         unsafe { ptr::drop_in_place(self.0.as_ptr()) }
 
         // Deallocate the whole envelope.
-        // SAFETY: memory was allocated by `alloc::alloc` with the same layout.
-        unsafe { alloc::dealloc(self.0.as_ptr().cast(), layout) };
+        // SAFETY: memory was allocated by `envelope_pool::alloc` with the same layout.
+        unsafe { envelope_pool::dealloc(self.0.cast(), layout, pool) };
     }
 }
 
@@ -90,7 +98,11 @@ impl Envelope {
     #[doc(hidden)]
     #[inline]
     pub fn new<M: Message>(message: M, kind: MessageKind) -> Self {
-        Self::with_trace_id(message, kind, crate::scope::trace_id())
+        let (trace_id, baggage, span_id) =
+            crate::scope::with(|scope| (scope.trace_id(), scope.baggage(), scope.span_id()));
+        Self::with_trace_id(message, kind, trace_id)
+            .with_baggage(baggage)
+            .with_parent_span_id(span_id)
     }
 
     // This is private API. Do not use it.
@@ -99,20 +111,18 @@ impl Envelope {
     pub fn with_trace_id<M: Message>(message: M, kind: MessageKind, trace_id: TraceId) -> Self {
         let message_layout = message._repr_layout();
         let (layout, message_offset) = envelope_repr_layout(message_layout);
+        let (ptr, pool) = envelope_pool::alloc(layout);
 
         let header = EnvelopeHeader {
             link: <_>::default(),
             created_time: Instant::now(),
             trace_id,
+            baggage: Baggage::default(),
+            parent_span_id: None,
             kind,
             message_offset,
-        };
-
-        // SAFETY: `layout` is correct and non-zero.
-        let ptr = unsafe { alloc::alloc(layout) };
-
-        let Some(ptr) = NonNull::new(ptr) else {
-            alloc::handle_alloc_error(layout);
+            pool,
+            is_budgeted: false,
         };
 
         // SAFETY: `ptr` is valid to write the header.
@@ -135,6 +145,36 @@ impl Envelope {
         )
     }
 
+    // This is private API. Do not use it.
+    #[doc(hidden)]
+    #[inline]
+    pub fn with_baggage(mut self, baggage: Baggage) -> Self {
+        // SAFETY: `self.0` is properly initialized and owned by `self`.
+        unsafe { self.0.as_mut() }.baggage = baggage;
+        self
+    }
+
+    // This is private API. Do not use it.
+    #[doc(hidden)]
+    #[inline]
+    pub fn with_parent_span_id(mut self, span_id: Option<TraceId>) -> Self {
+        // SAFETY: `self.0` is properly initialized and owned by `self`.
+        unsafe { self.0.as_mut() }.parent_span_id = span_id;
+        self
+    }
+
+    /// Marks whether the size is counted by the memory budget.
+    #[inline]
+    pub(crate) fn set_budgeted(&mut self, is_budgeted: bool) {
+        // SAFETY: `self.0` is properly initialized and owned by `self`.
+        unsafe { self.0.as_mut() }.is_budgeted = is_budgeted;
+    }
+
+    #[inline]
+    pub(crate) fn is_budgeted(&self) -> bool {
+        self.header().is_budgeted
+    }
+
     fn header(&self) -> &EnvelopeHeader {
         // SAFETY: `self.0` is properly initialized.
         unsafe { self.0.as_ref() }
@@ -145,6 +185,19 @@ impl Envelope {
         self.header().trace_id
     }
 
+    /// Returns the baggage of the sender at the moment of sending.
+    #[inline]
+    pub fn baggage(&self) -> &Baggage {
+        &self.header().baggage
+    }
+
+    /// Returns the id of the span in which the envelope has been sent.
+    /// It's set only if the sender's span has been recorded.
+    #[inline]
+    pub fn parent_span_id(&self) -> Option<TraceId> {
+        self.header().parent_span_id
+    }
+
     /// Returns a reference to the untyped message inside the envelope.
     #[inline]
     pub fn message(&self) -> AnyMessageRef<'_> {
@@ -164,6 +217,13 @@ impl Envelope {
         self.header().created_time
     }
 
+    /// Returns the size of the envelope's allocation (the header and the
+    /// message itself, but not heap allocations owned by the message).
+    pub(crate) fn allocated_size(&self) -> usize {
+        let (layout, _) = envelope_repr_layout(self.message()._repr_layout());
+        envelope_pool::block_layout(layout, self.header().pool.is_some()).size()
+    }
+
     #[inline]
     pub fn sender(&self) -> Addr {
         match self.message_kind() {
@@ -202,11 +262,14 @@ impl Envelope {
         let message_layout = message._repr_layout();
         let (layout, message_offset) = envelope_repr_layout(message_layout);
         debug_assert_eq!(message_offset, header.message_offset);
+        let (out_ptr, pool) = envelope_pool::alloc(layout);
 
         let out_header = EnvelopeHeader {
             link: <_>::default(),
             created_time: header.created_time,
             trace_id: header.trace_id,
+            baggage: header.baggage.clone(),
+            parent_span_id: header.parent_span_id,
             kind: match &header.kind {
                 MessageKind::Regular { sender } => MessageKind::Regular { sender: *sender },
                 MessageKind::RequestAny(token) => MessageKind::RequestAny(token.duplicate()),
@@ -217,13 +280,8 @@ impl Envelope {
                 },
             },
             message_offset,
-        };
-
-        // SAFETY: `layout` is correct and non-zero.
-        let out_ptr = unsafe { alloc::alloc(layout) };
-
-        let Some(out_ptr) = NonNull::new(out_ptr) else {
-            alloc::handle_alloc_error(layout);
+            pool,
+            is_budgeted: false,
         };
 
         // SAFETY: `out_ptr` is valid to write the header.
@@ -276,8 +334,10 @@ impl Envelope {
 
         let message = M::_read(self.message_repr_ptr());
         let kind = ptr::read(&self.0.as_ref().kind);
+        drop(ptr::read(&self.0.as_ref().baggage));
+        let pool = ptr::read(&self.0.as_ref().pool);
 
-        alloc::dealloc(self.0.as_ptr().cast(), layout);
+        envelope_pool::dealloc(self.0.cast(), layout, pool);
         mem::forget(self);
         (message, kind)
     }
@@ -0,0 +1,401 @@
+//! Thread-local pools recycling allocations of envelopes.
+//! Enabled per group by `system.mailbox.envelope_pool`.
+//!
+//! Envelopes have different layouts depending on messages, so small ones are
+//! rounded up to a few size classes. Blocks are taken from the pool of the
+//! current thread and returned to the pool of the thread allocated them, so
+//! producer→consumer pipelines recycle blocks too. Blocks freed by the owning
+//! thread are returned without synchronization, blocks freed by other threads
+//! are pushed to the owner's lock-free shared stack, which is taken entirely
+//! by the owner once its local list is empty. Envelopes allocated by groups
+//! without pooling aren't returned to pools.
+
+use std::{
+    alloc::Layout,
+    cell::RefCell,
+    ptr::{self, NonNull},
+    sync::{
+        atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use metrics::Key;
+
+use crate::scope;
+
+static HITS: Key = Key::from_static_name("elfo_envelope_pool_hits_total");
+static MISSES: Key = Key::from_static_name("elfo_envelope_pool_misses_total");
+
+const SIZE_CLASSES: [usize; 4] = [128, 256, 512, 1024];
+const ALIGN: usize = 16;
+/// Limits the memory kept by every thread, up to ~4MiB
+/// (half in the local lists, half in the shared ones).
+const MAX_BLOCKS_PER_CLASS: usize = 1024;
+
+type Blocks = [Vec<NonNull<u8>>; SIZE_CLASSES.len()];
+
+thread_local! {
+    static POOL: RefCell<LocalPool> = RefCell::new(LocalPool::default());
+}
+
+/// The pool of the current thread.
+#[derive(Default)]
+struct LocalPool {
+    classes: Blocks,
+    shared: Owner,
+}
+
+impl Drop for LocalPool {
+    fn drop(&mut self) {
+        // Blocks returned later by other threads are deallocated immediately.
+        let remote = &self.shared.0;
+        remote.is_closed.store(true, Ordering::Release);
+        remote.take_all(&mut self.classes);
+        dealloc_blocks(&self.classes);
+    }
+}
+
+/// The part of a thread's pool accessible by other threads.
+/// Pooled envelopes hold it to return blocks to the allocating thread.
+#[derive(Clone, Default)]
+pub(crate) struct Owner(Arc<RemotePool>);
+
+#[derive(Default)]
+struct RemotePool {
+    classes: [RemoteStack; SIZE_CLASSES.len()],
+    is_closed: AtomicBool,
+}
+
+impl RemotePool {
+    fn take_all(&self, classes: &mut Blocks) {
+        for (stack, blocks) in self.classes.iter().zip(classes) {
+            stack.take_all(blocks);
+        }
+    }
+}
+
+impl Drop for RemotePool {
+    fn drop(&mut self) {
+        // Blocks pushed while the owner thread was finishing.
+        let mut classes = Blocks::default();
+        self.take_all(&mut classes);
+        dealloc_blocks(&classes);
+    }
+}
+
+/// A Treiber stack of freed blocks, which store the pointer to the next block
+/// in their first bytes. Blocks are only pushed one by one and taken all at
+/// once, so it isn't prone to ABA.
+#[derive(Default)]
+struct RemoteStack {
+    head: AtomicPtr<u8>,
+    len: AtomicUsize,
+}
+
+impl RemoteStack {
+    /// Returns `false` if there is no room.
+    fn push(&self, block: NonNull<u8>) -> bool {
+        if self.len.fetch_add(1, Ordering::Relaxed) >= MAX_BLOCKS_PER_CLASS {
+            self.len.fetch_sub(1, Ordering::Relaxed);
+            return false;
+        }
+
+        let block = block.as_ptr();
+        let mut head = self.head.load(Ordering::Relaxed);
+
+        loop {
+            // SAFETY: blocks are unused and aligned to `ALIGN`, which fits a pointer.
+            unsafe { block.cast::<*mut u8>().write(head) };
+
+            match self
+                .head
+                .compare_exchange_weak(head, block, Ordering::Release, Ordering::Relaxed)
+            {
+                Ok(_) => return true,
+                Err(actual) => head = actual,
+            }
+        }
+    }
+
+    fn take_all(&self, blocks: &mut Vec<NonNull<u8>>) {
+        let mut next = self.head.swap(ptr::null_mut(), Ordering::Acquire);
+        let mut count = 0;
+
+        while let Some(block) = NonNull::new(next) {
+            // SAFETY: pushed blocks store the next pointer, see `push()`.
+            next = unsafe { block.as_ptr().cast::<*mut u8>().read() };
+            blocks.push(block);
+            count += 1;
+        }
+
+        self.len.fetch_sub(count, Ordering::Relaxed);
+    }
+}
+
+impl Owner {
+    fn is_current(&self, pool: &LocalPool) -> bool {
+        Arc::ptr_eq(&self.0, &pool.shared.0)
+    }
+
+    /// Returns `false` if there is no room or the owner thread has finished.
+    fn push(&self, class: usize, block: NonNull<u8>) -> bool {
+        // Blocks pushed after the check are deallocated with the pool.
+        !self.0.is_closed.load(Ordering::Acquire) && self.0.classes[class].push(block)
+    }
+}
+
+/// Allocates a block for the envelope with the provided layout.
+/// Returns the block and the pool owning it if the block is pooled,
+/// i.e. has the class layout.
+pub(crate) fn alloc(layout: Layout) -> (NonNull<u8>, Option<Owner>) {
+    let pooled_class = size_class(layout).filter(|_| is_enabled());
+
+    let Some(class) = pooled_class else {
+        return (alloc_block(layout), None);
+    };
+
+    let (block, owner) = POOL
+        .try_with(|pool| {
+            let mut pool = pool.borrow_mut();
+            let pool = &mut *pool;
+            let local = &mut pool.classes[class];
+
+            if local.is_empty() {
+                // Take blocks freed by other threads.
+                pool.shared.0.classes[class].take_all(local);
+            }
+
+            (local.pop(), Some(pool.shared.clone()))
+        })
+        .unwrap_or_default();
+
+    if let Some(recorder) = metrics::try_recorder() {
+        let key = if block.is_some() { &HITS } else { &MISSES };
+        recorder.increment_counter(key, 1);
+    }
+
+    // No pool if the thread is being destroyed.
+    let Some(owner) = owner else {
+        return (alloc_block(layout), None);
+    };
+
+    let block = block.unwrap_or_else(|| alloc_block(class_layout(class)));
+    (block, Some(owner))
+}
+
+/// Deallocates the block or returns it to the pool of the thread allocated it.
+///
+/// # Safety
+///
+/// The block must be allocated by [`alloc()`] with the same layout,
+/// `owner` must be the one returned along with the block.
+pub(crate) unsafe fn dealloc(block: NonNull<u8>, layout: Layout, owner: Option<Owner>) {
+    let layout = block_layout(layout, owner.is_some());
+
+    if let Some(owner) = owner {
+        let class = size_class(layout).expect("invalid pooled block");
+        let returned = POOL
+            .try_with(|pool| {
+                let mut pool = pool.borrow_mut();
+                if !owner.is_current(&pool) {
+                    return owner.push(class, block);
+                }
+
+                let blocks = &mut pool.classes[class];
+                let has_room = blocks.len() < MAX_BLOCKS_PER_CLASS;
+                if has_room {
+                    blocks.push(block);
+                }
+                has_room
+            })
+            .unwrap_or_else(|_| owner.push(class, block));
+
+        if returned {
+            return;
+        }
+    }
+
+    std::alloc::dealloc(block.as_ptr(), layout);
+}
+
+/// Returns the actual layout of the block.
+pub(crate) fn block_layout(layout: Layout, pooled: bool) -> Layout {
+    match size_class(layout).filter(|_| pooled) {
+        Some(class) => class_layout(class),
+        None => layout,
+    }
+}
+
+fn is_enabled() -> bool {
+    scope::try_with(|scope| scope.is_envelope_pool_enabled()).unwrap_or(false)
+}
+
+fn size_class(layout: Layout) -> Option<usize> {
+    if layout.align() > ALIGN {
+        return None;
+    }
+
+    SIZE_CLASSES.iter().position(|&size| layout.size() <= size)
+}
+
+fn class_layout(class: usize) -> Layout {
+    // SAFETY: sizes are multiple of `ALIGN`, which is a power of two.
+    unsafe { Layout::from_size_align_unchecked(SIZE_CLASSES[class], ALIGN) }
+}
+
+fn alloc_block(layout: Layout) -> NonNull<u8> {
+    // SAFETY: envelopes' layouts are non-zero.
+    let ptr = unsafe { std::alloc::alloc(layout) };
+
+    let Some(ptr) = NonNull::new(ptr) else {
+        std::alloc::handle_alloc_error(layout);
+    };
+
+    ptr
+}
+
+fn dealloc_blocks(classes: &Blocks) {
+    for (class, blocks) in classes.iter().enumerate() {
+        for block in blocks {
+            // SAFETY: blocks are allocated with the class layout.
+            unsafe { std::alloc::dealloc(block.as_ptr(), class_layout(class)) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::{
+        addr::Addr,
+        envelope::{Envelope, MessageKind},
+        message,
+        scope::Scope,
+    };
+
+    #[message]
+    struct Small(u64);
+
+    #[message]
+    struct Large([[u64; 32]; 5]);
+
+    fn scope(envelope_pool: bool) -> Scope {
+        Scope::test_configured(|config| config.mailbox.envelope_pool = envelope_pool)
+    }
+
+    fn envelope_addr(message: impl crate::Message) -> usize {
+        let envelope = Envelope::new(message, MessageKind::regular(Addr::NULL));
+        let ptr = envelope.into_header_ptr();
+        // SAFETY: the pointer is produced by `into_header_ptr()` above.
+        drop(unsafe { Envelope::from_header_ptr(ptr) });
+        ptr.as_ptr() as usize
+    }
+
+    #[test]
+    fn reuse() {
+        scope(true).sync_within(|| {
+            // Small envelopes are recycled.
+            let addr = envelope_addr(Small(1));
+            assert_eq!(envelope_addr(Small(2)), addr);
+
+            // Large envelopes are not pooled.
+            let large = Envelope::new(Large([[0; 32]; 5]), MessageKind::regular(Addr::NULL));
+            assert!(large.allocated_size() > SIZE_CLASSES[SIZE_CLASSES.len() - 1]);
+        });
+
+        // Envelopes aren't pooled if it's disabled.
+        scope(false).sync_within(|| {
+            let envelope = Envelope::new(Small(1), MessageKind::regular(Addr::NULL));
+            assert!(envelope.allocated_size() < SIZE_CLASSES[0]);
+        });
+    }
+
+    #[test]
+    fn cross_thread() {
+        let scope = scope(true);
+        let alloc = || {
+            scope.clone().sync_within(|| {
+                (0..10)
+                    .map(|i| {
+                        let envelope = Envelope::new(Small(i), MessageKind::regular(Addr::NULL));
+                        let ptr = envelope.into_header_ptr();
+                        // SAFETY: the pointer is produced by `into_header_ptr()` above.
+                        let envelope = unsafe { Envelope::from_header_ptr(ptr) };
+                        (ptr.as_ptr() as usize, envelope)
+                    })
+                    .unzip::<_, _, HashSet<_>, Vec<_>>()
+            })
+        };
+
+        // Envelopes are allocated by one thread and dropped by another one.
+        let (addrs, envelopes) = alloc();
+        std::thread::spawn(move || drop(envelopes)).join().unwrap();
+
+        // Blocks are returned to the pool of the allocating thread.
+        let (reused_addrs, _envelopes) = alloc();
+        assert_eq!(reused_addrs, addrs);
+    }
+
+    #[test]
+    fn remote_stack() {
+        let stack = RemoteStack::default();
+        let blocks = (0..MAX_BLOCKS_PER_CLASS + 10)
+            .map(|_| alloc_block(class_layout(0)).as_ptr() as usize)
+            .collect::<Vec<_>>();
+
+        // Blocks are pushed concurrently, extra ones are rejected.
+        let rejected = std::thread::scope(|s| {
+            let handles = blocks
+                .chunks(blocks.len() / 4 + 1)
+                .map(|chunk| {
+                    let stack = &stack;
+                    s.spawn(move || {
+                        let block = |&b: &usize| NonNull::new(b as *mut u8).unwrap();
+                        let is_rejected = |b: &&usize| !stack.push(block(b));
+                        chunk.iter().filter(is_rejected).count()
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            handles
+                .into_iter()
+                .map(|h| h.join().unwrap())
+                .sum::<usize>()
+        });
+        assert_eq!(rejected, 10);
+
+        let mut taken = Vec::new();
+        stack.take_all(&mut taken);
+        assert_eq!(taken.len(), MAX_BLOCKS_PER_CLASS);
+        assert_eq!(stack.len.load(Ordering::Relaxed), 0);
+
+        let taken = taken.iter().map(|b| b.as_ptr() as usize);
+        assert_eq!(taken.collect::<HashSet<_>>().len(), MAX_BLOCKS_PER_CLASS);
+
+        let mut classes = Blocks::default();
+        classes[0] = blocks
+            .into_iter()
+            .map(|b| NonNull::new(b as *mut u8).unwrap())
+            .collect();
+        dealloc_blocks(&classes);
+    }
+
+    #[test]
+    fn size_classes() {
+        let layout = |size, align| Layout::from_size_align(size, align).unwrap();
+
+        assert_eq!(size_class(layout(72, 8)), Some(0));
+        assert_eq!(size_class(layout(128, 16)), Some(0));
+        assert_eq!(size_class(layout(129, 8)), Some(1));
+        assert_eq!(size_class(layout(1024, 8)), Some(3));
+        assert_eq!(size_class(layout(1025, 8)), None);
+        assert_eq!(size_class(layout(64, 32)), None);
+
+        assert_eq!(block_layout(layout(72, 8), true), layout(128, 16));
+        assert_eq!(block_layout(layout(72, 8), false), layout(72, 8));
+        assert_eq!(block_layout(layout(2048, 8), true), layout(2048, 8));
+    }
+}
@@ -11,7 +11,8 @@ pub struct StartError {
 }
 
 impl StartError {
-    pub(crate) fn single(group: String, reason: String) -> Self {
+    /// Creates an error of the only group.
+    pub fn single(group: String, reason: String) -> Self {
         Self {
             errors: vec![StartGroupError { group, reason }],
         }
@@ -177,6 +178,51 @@ impl RequestError {
     }
 }
 
+// === TypedRequestError ===
+
+/// An error of requests responding with `Result<T, E>`, returned by
+/// `resolve_typed()` of request builders. It separates errors returned
+/// by the responder from [`RequestError`] of the request itself.
+#[derive(Debug, Display, Error)]
+pub enum TypedRequestError<E> {
+    /// The request hasn't been handled, see [`RequestError`].
+    #[display("{_0}")]
+    Request(#[error(not(source))] RequestError),
+    /// The responder has returned the error.
+    #[display("{_0}")]
+    Responded(#[error(not(source))] E),
+}
+
+impl<E> TypedRequestError<E> {
+    /// Returns whether the error is the `Request` variant.
+    #[inline]
+    pub fn is_request(&self) -> bool {
+        matches!(self, Self::Request(_))
+    }
+
+    /// Returns whether the error is the `Responded` variant.
+    #[inline]
+    pub fn is_responded(&self) -> bool {
+        matches!(self, Self::Responded(_))
+    }
+
+    /// Returns the error returned by the responder, if any.
+    #[inline]
+    pub fn into_responded(self) -> Option<E> {
+        match self {
+            Self::Request(_) => None,
+            Self::Responded(err) => Some(err),
+        }
+    }
+}
+
+impl<E> From<RequestError> for TypedRequestError<E> {
+    #[inline]
+    fn from(err: RequestError) -> Self {
+        Self::Request(err)
+    }
+}
+
 // === TryRecvError ===
 
 #[derive(Debug, Clone, Display, Error)]
@@ -0,0 +1,114 @@
+//! Faults injected into actors by `elfo-test` to exercise supervision and
+//! retries. Faults are applied to received messages in [`Context::recv()`]
+//! and [`Context::try_recv()`], system messages of `elfo-core` (e.g.
+//! `Terminate` or `UpdateConfig`) aren't affected.
+//!
+//! Faults are stored per thread, because addresses of groups are the same in
+//! topologies of different tests. So, it works with the current-thread runtime
+//! only, which is the default one for `#[tokio::test]`.
+//!
+//! [`Context::recv()`]: crate::Context::recv
+//! [`Context::try_recv()`]: crate::Context::try_recv
+
+use std::{cell::RefCell, time::Duration};
+
+use crate::{addr::Addr, envelope::Envelope, message::Message, scope};
+
+/// A fault injected into an actor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Fault {
+    /// The actor panics on the next message instead of handling it.
+    Panic,
+    /// Every message is delayed before handling, until faults are cleared.
+    /// The delay is based on `tokio::time`, so it respects the paused time.
+    Delay(Duration),
+    /// Next messages are dropped without handling.
+    Drop(usize),
+}
+
+struct Injected {
+    group: Addr,
+    key: String,
+    fault: Fault,
+}
+
+thread_local! {
+    static INJECTED: RefCell<Vec<Injected>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Injects the fault into the actor of the group with the key (rendered by
+/// `Display`). Faults of the actor are applied in the order of injection.
+pub fn inject(group: Addr, key: String, fault: Fault) {
+    if fault == Fault::Drop(0) {
+        return;
+    }
+
+    INJECTED.with(|injected| injected.borrow_mut().push(Injected { group, key, fault }));
+}
+
+/// Removes all faults injected into the actor.
+pub fn clear(group: Addr, key: &str) {
+    INJECTED.with(|injected| {
+        let mut injected = injected.borrow_mut();
+        injected.retain(|i| i.group != group || i.key != key);
+    });
+}
+
+/// Returns `None` if the message is dropped.
+pub(crate) async fn apply(envelope: Envelope) -> Option<Envelope> {
+    let is_empty = INJECTED
+        .try_with(|injected| injected.borrow().is_empty())
+        .unwrap_or(true);
+
+    if is_empty || is_system(&envelope) {
+        return Some(envelope);
+    }
+
+    let (group, key) = scope::with(|scope| (scope.group(), scope.meta().key.clone()));
+
+    let (fault, delay) = INJECTED.with(|injected| {
+        let mut injected = injected.borrow_mut();
+        let is_target = |i: &Injected| i.group == group && i.key == key;
+
+        let delay = injected
+            .iter()
+            .filter(|i| is_target(i))
+            .find_map(|i| match i.fault {
+                Fault::Delay(delay) => Some(delay),
+                _ => None,
+            });
+
+        let fault = injected
+            .iter()
+            .position(|i| is_target(i) && matches!(i.fault, Fault::Panic | Fault::Drop(_)))
+            .map(|idx| take(&mut injected, idx));
+
+        (fault, delay)
+    });
+
+    if let Some(delay) = delay {
+        tokio::time::sleep(delay).await;
+    }
+
+    match fault {
+        Some(Fault::Panic) => panic!("injected panic on {}", envelope.message().name()),
+        Some(Fault::Drop(_)) => None,
+        _ => Some(envelope),
+    }
+}
+
+/// Takes one use of the fault, removing exhausted ones.
+fn take(injected: &mut Vec<Injected>, idx: usize) -> Fault {
+    match &mut injected[idx].fault {
+        Fault::Drop(count) if *count > 1 => {
+            *count -= 1;
+            Fault::Drop(1)
+        }
+        _ => injected.remove(idx).fault,
+    }
+}
+
+fn is_system(envelope: &Envelope) -> bool {
+    envelope.message().protocol() == env!("CARGO_PKG_NAME")
+}
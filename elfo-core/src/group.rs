@@ -1,17 +1,29 @@
-use std::{fmt::Debug, future::Future, marker::PhantomData, sync::Arc};
+use std::{
+    fmt::{Debug, Write as _},
+    future::Future,
+    hash::Hash,
+    marker::PhantomData,
+    sync::Arc,
+    time::Duration,
+};
 
 use futures::future::BoxFuture;
 
 use crate::{
     addr::NodeNo,
-    config::Config,
+    config::{AnyConfig, Config},
     context::Context,
+    dedup::Deduplicators,
     envelope::Envelope,
     exec::{Exec, ExecResult},
+    inspection::GroupInspection,
+    message::{Message, Request},
     object::{GroupHandle, GroupVisitor, Object},
+    persistence::{EventSourced, Journal, Recovery, Snapshot},
+    response_cache::ResponseCaches,
     restarting::RestartPolicy,
     routers::Router,
-    runtime::RuntimeManager,
+    runtime::{DedicatedRuntime, RuntimeManager},
     supervisor::Supervisor,
 };
 
@@ -20,10 +32,18 @@ pub struct ActorGroup<R, C> {
     restart_policy: RestartPolicy,
     termination_policy: TerminationPolicy,
     stop_order: i8,
+    runtime: Option<DedicatedRuntime>,
+    response_caches: ResponseCaches,
+    deduplicators: Deduplicators,
+    recovery: Option<Recovery>,
     router: R,
+    config_schema: Option<ConfigSchemaFn>,
     _config: PhantomData<C>,
 }
 
+pub(crate) type ConfigSchemaFn = fn() -> serde_json::Value;
+pub(crate) type ConfigRenderFn = fn(&AnyConfig) -> Result<String, String>;
+
 impl ActorGroup<(), ()> {
     #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
@@ -32,6 +52,11 @@ impl ActorGroup<(), ()> {
             termination_policy: TerminationPolicy::default(),
             router: (),
             stop_order: 0,
+            runtime: None,
+            response_caches: ResponseCaches::default(),
+            deduplicators: Deduplicators::default(),
+            recovery: None,
+            config_schema: None,
             _config: PhantomData,
         }
     }
@@ -44,6 +69,11 @@ impl<R, C> ActorGroup<R, C> {
             termination_policy: self.termination_policy,
             router: self.router,
             stop_order: self.stop_order,
+            runtime: self.runtime,
+            response_caches: self.response_caches,
+            deduplicators: self.deduplicators,
+            recovery: self.recovery,
+            config_schema: None,
             _config: PhantomData,
         }
     }
@@ -71,6 +101,11 @@ impl<R, C> ActorGroup<R, C> {
             termination_policy: self.termination_policy,
             router,
             stop_order: self.stop_order,
+            runtime: self.runtime,
+            response_caches: self.response_caches,
+            deduplicators: self.deduplicators,
+            recovery: self.recovery,
+            config_schema: self.config_schema,
             _config: self._config,
         }
     }
@@ -93,6 +128,230 @@ impl<R, C> ActorGroup<R, C> {
         self
     }
 
+    /// Runs actors of the group on a dedicated tokio runtime, isolating them
+    /// from other groups running on the shared one.
+    ///
+    /// The runtime is started when the group is mounted.
+    /// It takes precedence over [`Topology::add_dedicated_rt()`].
+    ///
+    /// By default, the runtime used to start the system is used.
+    ///
+    /// # Example
+    /// ```
+    /// # use elfo_core as elfo;
+    /// use elfo::{ActorGroup, DedicatedRuntime};
+    ///
+    /// let group = ActorGroup::new().dedicated_runtime(DedicatedRuntime::current_thread());
+    /// ```
+    ///
+    /// [`Topology::add_dedicated_rt()`]: crate::Topology::add_dedicated_rt
+    pub fn dedicated_runtime(mut self, runtime: DedicatedRuntime) -> Self {
+        self.runtime = Some(runtime);
+        self
+    }
+
+    /// Caches responses to requests of the type `Q` for the provided TTL.
+    /// Identical requests (compared by content) within this window are
+    /// answered by the group itself without reaching actors.
+    ///
+    /// It's intended for idempotent read-style requests only.
+    /// Cached responses aren't dumped, but the original ones are.
+    ///
+    /// Only requests sent by `ctx.request(..)` (so-called "any" requests) are
+    /// cached. Requests sent by `ctx.request(..).all()` expect one response
+    /// per actor, so they always reach actors and their responses aren't
+    /// cached. The cache holds at most 4096 responses, the rest aren't cached
+    /// until old ones expire.
+    ///
+    /// Caches are configured per message type, calling this method again
+    /// for the same type replaces the previous cache.
+    ///
+    /// # Example
+    /// ```
+    /// # use elfo_core as elfo;
+    /// # use std::time::Duration;
+    /// use elfo::{message, ActorGroup};
+    ///
+    /// #[message(ret = u64)]
+    /// #[derive(Hash, PartialEq, Eq)]
+    /// struct GetBalance {
+    ///     account_id: u32,
+    /// }
+    ///
+    /// let group = ActorGroup::new().cache_responses::<GetBalance>(Duration::from_secs(1));
+    /// ```
+    pub fn cache_responses<Q>(self, ttl: Duration) -> Self
+    where
+        Q: Request + Hash + Eq,
+    {
+        self.cache_responses_by(ttl, Q::clone)
+    }
+
+    /// Like [`ActorGroup::cache_responses()`], but requests are compared by
+    /// the key returned by the provided function instead of their content.
+    ///
+    /// # Example
+    /// ```
+    /// # use elfo_core as elfo;
+    /// # use std::time::Duration;
+    /// use elfo::{message, ActorGroup};
+    ///
+    /// #[message(ret = u64)]
+    /// struct GetBalance {
+    ///     account_id: u32,
+    ///     comment: String,
+    /// }
+    ///
+    /// let group = ActorGroup::new()
+    ///     .cache_responses_by(Duration::from_secs(1), |req: &GetBalance| req.account_id);
+    /// ```
+    pub fn cache_responses_by<Q, K>(
+        mut self,
+        ttl: Duration,
+        key: impl Fn(&Q) -> K + Send + Sync + 'static,
+    ) -> Self
+    where
+        Q: Request,
+        K: Hash + Eq + Send + 'static,
+    {
+        self.response_caches.add(ttl, key);
+        self
+    }
+
+    /// Drops incoming messages of the type `M` if a message with the same
+    /// idempotency key has already been seen within the window, so actors
+    /// don't need to deduplicate messages delivered at least once.
+    ///
+    /// The window starts when the key is seen for the first time.
+    /// Dropped requests are answered with [`RequestError::Failed`].
+    /// Dropped messages are counted by the `elfo_deduplicated_messages_total`
+    /// metric.
+    ///
+    /// Deduplication is configured per message type, calling this method
+    /// again for the same type replaces the previous deduplicator.
+    ///
+    /// # Example
+    /// ```
+    /// # use elfo_core as elfo;
+    /// # use std::time::Duration;
+    /// use elfo::{message, ActorGroup};
+    ///
+    /// #[message]
+    /// struct OrderPlaced {
+    ///     order_id: u64,
+    ///     amount: u64,
+    /// }
+    ///
+    /// let group = ActorGroup::new()
+    ///     .deduplicate_by(Duration::from_secs(60), |msg: &OrderPlaced| msg.order_id);
+    /// ```
+    ///
+    /// [`RequestError::Failed`]: crate::errors::RequestError::Failed
+    pub fn deduplicate_by<M, K>(
+        mut self,
+        window: Duration,
+        key: impl Fn(&M) -> K + Send + Sync + 'static,
+    ) -> Self
+    where
+        M: Message,
+        K: Hash + Eq + Send + 'static,
+    {
+        self.deduplicators.add(window, key);
+        self
+    }
+
+    /// Recovers the state `S` of every actor from the journal before its exec
+    /// starts, so restarted actors get their state back without any code in
+    /// the exec. The recovered log is taken by [`Context::take_event_log()`].
+    ///
+    /// Every actor uses its own stream, see [`EventLog::recover()`].
+    /// If recovery fails, the actor fails and is restarted according to
+    /// the restart policy.
+    ///
+    /// # Example
+    /// ```
+    /// # use elfo_core as elfo;
+    /// # use std::sync::Arc;
+    /// # use serde::{Deserialize, Serialize};
+    /// use elfo::{
+    ///     persistence::{EventLog, EventSourced, FileJournal},
+    ///     ActorGroup,
+    /// };
+    ///
+    /// #[derive(Default)]
+    /// struct Counter(u64);
+    ///
+    /// #[derive(Serialize, Deserialize)]
+    /// struct Incremented(u64);
+    ///
+    /// impl EventSourced for Counter {
+    ///     type Event = Incremented;
+    ///
+    ///     fn apply(&mut self, event: &Incremented) {
+    ///         self.0 += event.0;
+    ///     }
+    /// }
+    ///
+    /// # fn blueprint() -> std::io::Result<()> {
+    /// let journal = Arc::new(FileJournal::new("journal")?);
+    /// let group = ActorGroup::new()
+    ///     .event_sourced::<Counter>(journal)
+    ///     .exec(|mut ctx| async move {
+    ///         let counter = ctx.take_event_log::<Counter>().unwrap();
+    ///         // ...
+    ///     });
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`Context::take_event_log()`]: crate::Context::take_event_log
+    /// [`EventLog::recover()`]: crate::persistence::EventLog::recover
+    pub fn event_sourced<S: EventSourced>(mut self, journal: Arc<dyn Journal>) -> Self {
+        self.recovery = Some(Recovery::new::<S>(journal));
+        self
+    }
+
+    /// Like [`ActorGroup::event_sourced()`], but starts with the last
+    /// snapshot and takes new ones periodically,
+    /// see [`EventLog::recover_with_snapshots()`].
+    ///
+    /// [`EventLog::recover_with_snapshots()`]: crate::persistence::EventLog::recover_with_snapshots
+    pub fn event_sourced_with_snapshots<S: Snapshot>(mut self, journal: Arc<dyn Journal>) -> Self {
+        self.recovery = Some(Recovery::with_snapshots::<S>(journal));
+        self
+    }
+
+    /// Attaches the JSON Schema of the group's config, which is generated by
+    /// [`schemars`]. Schemas of all groups can be exported by
+    /// [`Topology::export_config_schema()`] to validate config files.
+    ///
+    /// # Example
+    /// ```
+    /// # use elfo_core as elfo;
+    /// use elfo::ActorGroup;
+    /// use schemars::JsonSchema;
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Debug, Deserialize, JsonSchema)]
+    /// struct Config {
+    ///     /// The maximum number of items.
+    ///     limit: u32,
+    /// }
+    ///
+    /// let group = ActorGroup::new().config::<Config>().config_schema();
+    /// ```
+    ///
+    /// [`Topology::export_config_schema()`]: crate::Topology::export_config_schema
+    #[cfg(feature = "schema")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "schema")))]
+    pub fn config_schema(mut self) -> Self
+    where
+        C: schemars::JsonSchema,
+    {
+        self.config_schema = Some(schema_of::<C>);
+        self
+    }
+
     /// Builds the group with the specified executor function.
     ///
     /// The provided closure must return a future resolving to
@@ -107,8 +366,14 @@ impl<R, C> ActorGroup<R, C> {
         C: Config,
     {
         let mount =
-            move |ctx: Context, node_no: NodeNo, name: String, rt_manager: RuntimeManager| {
+            move |ctx: Context, node_no: NodeNo, name: String, mut rt_manager: RuntimeManager| {
                 let addr = ctx.group();
+
+                if let Some(runtime) = &self.runtime {
+                    let (handle, threads, shutdown) = runtime.start(&name);
+                    rt_manager.set_exclusive(handle, threads, shutdown);
+                }
+
                 let sv = Arc::new(Supervisor::new(
                     ctx,
                     node_no,
@@ -117,6 +382,9 @@ impl<R, C> ActorGroup<R, C> {
                     self.router,
                     self.restart_policy,
                     self.termination_policy,
+                    self.response_caches,
+                    self.deduplicators,
+                    self.recovery,
                     rt_manager,
                 ));
 
@@ -126,10 +394,43 @@ impl<R, C> ActorGroup<R, C> {
         Blueprint {
             mount: Box::new(mount),
             stop_order: self.stop_order,
+            config_schema: self.config_schema,
+            config_render: render_config::<C>,
         }
     }
 }
 
+/// Decodes the config and renders it by `Debug`, so defaults are filled in.
+/// Key overrides are rendered after the config, ordered by keys.
+fn render_config<C: Config>(config: &AnyConfig) -> Result<String, String> {
+    let config = config.decode::<C>()?;
+    let mut rendered = format!("{:#?}", config.get_user::<C>());
+
+    let mut overrides = config.get_overrides::<C>().iter().collect::<Vec<_>>();
+    overrides.sort_by_key(|(key, _)| *key);
+
+    for (key, config) in overrides {
+        write!(rendered, "\nsystem.key_overrides.{key}: {config:#?}").unwrap();
+    }
+
+    Ok(rendered)
+}
+
+#[cfg(feature = "schema")]
+fn schema_of<C: schemars::JsonSchema>() -> serde_json::Value {
+    // Subschemas are inlined, because schemas of groups are embedded into
+    // the common schema, so references to definitions would be broken.
+    let generator = schemars::gen::SchemaSettings::draft07()
+        .with(|settings| {
+            settings.inline_subschemas = true;
+            settings.meta_schema = None;
+        })
+        .into_generator();
+
+    let schema = generator.into_root_schema_for::<C>();
+    serde_json::to_value(schema).expect("cannot serialize the schema")
+}
+
 struct Handle<R: Router<C>, C, X>(Arc<Supervisor<R, C, X>>);
 
 impl<R, C, X> GroupHandle for Handle<R, C, X>
@@ -143,14 +444,28 @@ where
         self.0.handle(envelope, visitor)
     }
 
+    fn undelivered(&self, envelope: &Envelope) {
+        self.0.undelivered(envelope)
+    }
+
     fn finished(&self) -> BoxFuture<'static, ()> {
         self.0.finished()
     }
+
+    fn shutdown_runtime(&self) -> BoxFuture<'static, ()> {
+        self.0.shutdown_runtime()
+    }
+
+    fn inspect(&self) -> GroupInspection {
+        self.0.inspect()
+    }
 }
 
 pub struct Blueprint {
     pub(crate) mount: Box<dyn FnOnce(Context, NodeNo, String, RuntimeManager) -> Object>,
     pub(crate) stop_order: i8,
+    pub(crate) config_schema: Option<ConfigSchemaFn>,
+    pub(crate) config_render: ConfigRenderFn,
 }
 
 /// The behaviour on the `Terminate` message.
@@ -4,7 +4,7 @@ use std::{
     time::{Duration, SystemTime},
 };
 
-use futures::future::join_all;
+use futures::future::{join_all, AbortHandle};
 use tokio::{
     pin, select,
     time::{sleep, timeout},
@@ -170,7 +170,11 @@ pub async fn do_start<F: Future>(
     let group_no = GroupNo::new(SYSTEM_INIT_GROUP_NO, topology.launch_id()).unwrap();
     let entry = topology.book.vacant_entry(group_no);
     let addr = entry.addr();
-    let ctx = Context::new(topology.book.clone(), Demux::default());
+    let ctx = Context::new(
+        topology.book.clone(),
+        topology.topics.clone(),
+        Demux::default(),
+    );
 
     let meta = Arc::new(ActorMeta {
         group: INIT_GROUP_NAME.into(),
@@ -184,6 +188,8 @@ pub async fn do_start<F: Future>(
         &<_>::default(),
         <_>::default(),
         Arc::new(SubscriptionManager::new(ctx.clone())),
+        <_>::default(),
+        AbortHandle::new_pair().0,
     );
 
     let scope_shared = ScopeGroupShared::new(topology.node_no(), addr);
@@ -193,7 +199,7 @@ pub async fn do_start<F: Future>(
 
     let scope = Scope::new(TraceId::generate(), addr, meta, Arc::new(scope_shared));
     scope.clone().sync_within(|| actor.on_start()); // need to emit initial metrics
-    entry.insert(Object::new(addr, actor));
+    entry.insert(Object::new(addr, Box::new(actor)));
 
     // It must be called after `entry.insert()`.
     let ctx = ctx
@@ -214,7 +220,7 @@ struct TerminateSystem;
 struct CheckMemoryUsageTick;
 
 // TODO: make these values configurable.
-const SEND_CLOSING_TERMINATE_AFTER: Duration = Duration::from_secs(25);
+pub(crate) const SEND_CLOSING_TERMINATE_AFTER: Duration = Duration::from_secs(25);
 const STOP_GROUP_TERMINATION_AFTER: Duration = Duration::from_secs(35);
 
 async fn exec(mut ctx: Context, topology: Topology) {
@@ -0,0 +1,64 @@
+//! Snapshots of the internal state of local groups, e.g. to expose it for
+//! debugging purposes. See [`Topology::inspect()`].
+//!
+//! [`Topology::inspect()`]: crate::Topology::inspect
+
+use std::time::SystemTime;
+
+use serde::Serialize;
+
+use crate::{ActorStatus, Addr};
+
+/// The number of restarts remembered per group.
+pub(crate) const MAX_RESTART_RECORDS: usize = 16;
+
+/// A snapshot of a local group.
+#[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
+pub struct GroupInspection {
+    /// The group's name.
+    pub name: String,
+    /// Actors that are currently alive or failed, ordered by keys.
+    pub actors: Vec<ActorInspection>,
+    /// Recent restarts, the last one is the newest.
+    pub restarts: Vec<RestartRecord>,
+}
+
+/// A snapshot of an actor.
+#[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
+pub struct ActorInspection {
+    /// The actor's address.
+    #[serde(skip)]
+    pub addr: Addr,
+    /// The actor's key.
+    pub key: String,
+    /// The current status.
+    pub status: ActorStatus,
+    /// The state of the mailbox.
+    pub mailbox: MailboxInspection,
+}
+
+/// A snapshot of an actor's mailbox.
+#[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
+pub struct MailboxInspection {
+    /// The number of messages waiting in the mailbox.
+    /// Can exceed `capacity` because of unbounded sends.
+    pub len: usize,
+    /// The current capacity.
+    pub capacity: usize,
+}
+
+/// A record about a restarted actor.
+#[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
+pub struct RestartRecord {
+    /// The actor's key.
+    pub key: String,
+    /// When the actor finished and was scheduled to be restarted.
+    #[serde(with = "humantime_serde")]
+    pub at: SystemTime,
+    /// The status, which the actor finished with.
+    pub status: ActorStatus,
+}
@@ -10,6 +10,8 @@ extern crate elfo_utils;
 extern crate self as elfo_core;
 
 // TODO: revise this list
+#[cfg(feature = "unstable")]
+pub use crate::runtime::Runtime;
 pub use crate::{
     actor::{ActorMeta, ActorStartCause, ActorStartInfo},
     actor_status::{ActorStatus, ActorStatusKind},
@@ -20,8 +22,10 @@ pub use crate::{
     group::{ActorGroup, Blueprint, TerminationPolicy},
     local::{Local, MoveOwnership},
     message::{AnyMessage, AnyMessageRef, Message, Request},
-    request_table::{RequestId, ResponseToken},
+    request_table::{DetachedResponseToken, RequestId, ResponseToken},
     restarting::{RestartParams, RestartPolicy},
+    runtime::DedicatedRuntime,
+    sender::ExternalSender,
     source::{SourceHandle, UnattachedSource},
     topology::Topology,
 };
@@ -36,11 +40,16 @@ pub mod coop;
 pub mod dumping;
 pub mod errors;
 pub mod init;
+pub mod inspection;
 pub mod logging;
 pub mod messages;
+pub mod persistence;
+pub mod registry;
 pub mod routers;
+pub mod schema;
 pub mod scope;
 pub mod signal;
+pub mod source;
 pub mod stream;
 #[cfg(feature = "unstable-stuck-detection")]
 pub mod stuck_detection;
@@ -52,12 +61,17 @@ mod actor;
 mod actor_status;
 mod address_book;
 mod context;
+mod dedup;
 mod demux;
 mod envelope;
+mod envelope_pool;
 mod exec;
+#[cfg(feature = "test-util")]
+mod faults;
 mod group;
 mod local;
 mod mailbox;
+mod memory_budget;
 #[cfg(target_os = "linux")]
 mod memory_tracker;
 mod message;
@@ -69,13 +83,19 @@ pub mod remote;
 #[cfg(all(feature = "network", not(feature = "unstable")))]
 mod remote;
 mod request_table;
+mod response_cache;
 mod restarting;
 mod runtime;
-mod source;
+#[cfg(feature = "test-util")]
+mod scheduling;
+mod sender;
 mod subscription;
 mod supervisor;
 mod telemetry;
 mod thread;
+mod topic;
+#[cfg(feature = "test-util")]
+mod triggers;
 
 #[doc(hidden)]
 pub mod _priv {
@@ -86,6 +106,13 @@ pub mod _priv {
         message::*,
         object::{GroupVisitor, Object, OwnedObject},
         permissions::{AtomicPermissions, Permissions},
+        schema::MESSAGE_SCHEMAS,
+    };
+    #[cfg(feature = "test-util")]
+    pub use crate::{
+        faults::{clear as clear_faults, inject as inject_fault, Fault},
+        scheduling::set_seed as set_scheduling_seed,
+        triggers::{emit as emit_from_source, fire as fire_sources},
     };
     pub use erased_serde;
     pub use idr_ebr::EbrGuard;
@@ -42,3 +42,214 @@ macro_rules! cfg_network {
         )*
     }
 }
+
+/// Generates an enum of the listed messages, e.g. all messages of some
+/// protocol, to accept and route any of them without listing every type in
+/// every match.
+///
+/// Variants are written as `Name(Type)` or simply `Type` if the name is the
+/// same. Every listed type gets `From<Type>` for the enum. Requests cannot
+/// be listed, because the enum has no place for response tokens.
+///
+/// The enum implements [`Message`] as a supertype of listed types, like
+/// [`AnyMessage`] does for all types: it's sent as the inner message and can
+/// be matched by value in [`msg!`] or downcast from [`AnyMessage`]. However,
+/// it cannot be borrowed, so `msg!(match &envelope)` and `downcast_ref()`
+/// don't support it.
+///
+/// Also, the enum can be used to check that `msg!` matches all its messages
+/// by marking the match as `#[exhaustive(Enum)]`, see [`msg!`] for details.
+///
+/// # Example
+/// ```
+/// # use elfo_core as elfo;
+/// use elfo::{message, msg, protocol_enum, Envelope};
+///
+/// #[message]
+/// struct Start;
+///
+/// #[message]
+/// struct Stop {
+///     force: bool,
+/// }
+///
+/// protocol_enum! {
+///     /// Commands accepted by the gateway.
+///     pub enum Command {
+///         Start,
+///         Stop,
+///     }
+/// }
+///
+/// fn handle(envelope: Envelope) -> Option<Command> {
+///     msg!(match envelope {
+///         command @ Command => Some(command),
+///         _ => None,
+///     })
+/// }
+/// ```
+///
+/// [`Message`]: crate::Message
+/// [`AnyMessage`]: crate::AnyMessage
+/// [`msg!`]: crate::msg!
+#[macro_export]
+macro_rules! protocol_enum {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $($(#[$variant_meta:meta])* $variant:ident $(($ty:ty))?),* $(,)?
+        }
+    ) => {
+        $crate::protocol_enum!(
+            @normalize [$(#[$meta])*] $vis $name []
+            $([$(#[$variant_meta])*] $variant $(($ty))?,)*
+        );
+    };
+
+    // Converts `Type` variants to `Type(Type)` ones.
+    (@normalize $meta:tt $vis:vis $name:ident [$($done:tt)*]
+        $variant_meta:tt $variant:ident ($ty:ty), $($rest:tt)*
+    ) => {
+        $crate::protocol_enum!(
+            @normalize $meta $vis $name [$($done)* $variant_meta $variant ($ty),] $($rest)*
+        );
+    };
+    (@normalize $meta:tt $vis:vis $name:ident [$($done:tt)*]
+        $variant_meta:tt $variant:ident, $($rest:tt)*
+    ) => {
+        $crate::protocol_enum!(
+            @normalize $meta $vis $name [$($done)* $variant_meta $variant ($variant),] $($rest)*
+        );
+    };
+    (@normalize [$(#[$meta:meta])*] $vis:vis $name:ident
+        [$([$(#[$variant_meta:meta])*] $variant:ident ($ty:ty),)*]
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone)]
+        $vis enum $name {
+            $($(#[$variant_meta])* $variant($ty),)*
+        }
+
+        $(
+            impl ::std::convert::From<$ty> for $name {
+                #[inline]
+                fn from(message: $ty) -> Self {
+                    Self::$variant(message)
+                }
+            }
+        )*
+
+        #[doc(hidden)]
+        const _: fn() = || {
+            // Fails with "type annotations needed" if any type is a request.
+            trait AmbiguousIfRequest<A> {
+                fn some_item() {}
+            }
+            impl<T: ?Sized> AmbiguousIfRequest<()> for T {}
+            struct Invalid;
+            impl<T: ?Sized + $crate::Request> AmbiguousIfRequest<Invalid> for T {}
+            $(let _ = <$ty as AmbiguousIfRequest<_>>::some_item;)*
+        };
+
+        impl $crate::_priv::ProtocolEnum for $name {}
+
+        impl<A> $crate::_priv::MatchedBy<A> for $name
+        where
+            $(A: $crate::_priv::Matches<$ty>,)*
+        {
+        }
+
+        impl $crate::Message for $name {
+            #[inline(always)]
+            fn _type_id() -> $crate::_priv::MessageTypeId {
+                // There is no type id of the enum itself, like for `AnyMessage`.
+                $crate::_priv::MessageTypeId::any()
+            }
+
+            #[inline(always)]
+            fn _vtable(&self) -> &'static $crate::_priv::MessageVTable {
+                match self {
+                    $(Self::$variant(message) => message._vtable(),)*
+                }
+            }
+
+            #[inline(always)]
+            fn _is_supertype_of(type_id: $crate::_priv::MessageTypeId) -> bool {
+                false $(|| <$ty as $crate::Message>::_is_supertype_of(type_id))*
+            }
+
+            #[inline(always)]
+            fn _into_any(self) -> $crate::AnyMessage {
+                match self {
+                    $(Self::$variant(message) => message._into_any(),)*
+                }
+            }
+
+            #[inline(always)]
+            unsafe fn _from_any(any: $crate::AnyMessage) -> Self {
+                $(
+                    let any = match any.downcast::<$ty>() {
+                        Ok(message) => return Self::$variant(message),
+                        Err(any) => any,
+                    };
+                )*
+                let name = $crate::Message::name(&any);
+                unreachable!("{} is not a part of {}", name, stringify!($name))
+            }
+
+            // The enum isn't stored in the message, so it cannot be borrowed.
+            #[inline(always)]
+            unsafe fn _from_any_ref(_: &$crate::AnyMessage) -> ::std::option::Option<&Self> {
+                ::std::option::Option::None
+            }
+
+            #[inline(always)]
+            fn _erase(&self) -> $crate::dumping::ErasedMessage {
+                match self {
+                    $(Self::$variant(message) => message._erase(),)*
+                }
+            }
+
+            #[inline(always)]
+            unsafe fn _read(ptr: ::std::ptr::NonNull<$crate::_priv::MessageRepr>) -> Self {
+                Self::_from_any(<$crate::AnyMessage as $crate::Message>::_read(ptr))
+            }
+
+            #[inline(always)]
+            unsafe fn _write(self, ptr: ::std::ptr::NonNull<$crate::_priv::MessageRepr>) {
+                match self {
+                    $(Self::$variant(message) => message._write(ptr),)*
+                }
+            }
+        }
+
+        // The same format as `AnyMessage` has to be deserialized back.
+        impl $crate::_priv::serde::Serialize for $name {
+            fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+            where
+                S: $crate::_priv::serde::Serializer,
+            {
+                let any = $crate::Message::_into_any(self.clone());
+                $crate::_priv::serde::Serialize::serialize(&any, serializer)
+            }
+        }
+
+        impl<'de> $crate::_priv::serde::Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> ::std::result::Result<Self, D::Error>
+            where
+                D: $crate::_priv::serde::Deserializer<'de>,
+            {
+                let any: $crate::AnyMessage =
+                    $crate::_priv::serde::Deserialize::deserialize(deserializer)?;
+
+                any.downcast().map_err(|any| {
+                    <D::Error as $crate::_priv::serde::de::Error>::custom(format_args!(
+                        "unexpected message: {}/{}",
+                        $crate::Message::protocol(&any),
+                        $crate::Message::name(&any),
+                    ))
+                })
+            }
+        }
+    };
+}
@@ -7,6 +7,10 @@
 //! 3. The capacity is configurable on the fly.
 //! 4. Preallocates no additional memory.
 //!
+//! Optionally, the queue is split into several shards (see
+//! [`MailboxConfig::shards`]) to reduce contention between many producers.
+//! The capacity is shared by all shards.
+//!
 //! A simplified structure can be pictured in the following way:
 //! ```text
 //!   mailbox                       envelopes
@@ -24,8 +28,17 @@
 //!             │    └───────┘                                │
 //!             └─────────────────────────────────────────────┘
 //! ```
-
-use std::ptr::{self, NonNull};
+//!
+//! [`MailboxConfig::shards`]: config::MailboxConfig::shards
+
+use std::{
+    cell::Cell,
+    ptr::{self, NonNull},
+    sync::{
+        atomic::{self, AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+};
 
 use cordyceps::{
     mpsc_queue::{Links, MpscQueue},
@@ -39,6 +52,7 @@ use elfo_utils::CachePadded;
 use crate::{
     envelope::{Envelope, EnvelopeHeader},
     errors::{SendError, TrySendError},
+    memory_budget::MemoryBudget,
     tracing::TraceId,
 };
 
@@ -55,6 +69,8 @@ pub mod config {
     /// ```toml
     /// [some_group]
     /// system.mailbox.capacity = 1000
+    /// system.mailbox.envelope_pool = true
+    /// system.mailbox.shards = 8
     /// ```
     #[derive(Debug, PartialEq, serde::Deserialize)]
     #[serde(default)]
@@ -67,11 +83,36 @@ pub mod config {
         ///
         /// [`Context::set_mailbox_capacity()`]: crate::Context::set_mailbox_capacity
         pub capacity: usize,
+        /// Whether to recycle allocations of envelopes sent and received by
+        /// actors of the group, using thread-local pools. Envelopes dropped
+        /// by other threads are returned to the pool of the allocating one.
+        /// Useful for groups handling many messages to reduce allocator
+        /// pressure. The hit rate
+        /// is exposed as `elfo_envelope_pool_{hits,misses}_total` metrics.
+        ///
+        /// `false` by default.
+        pub envelope_pool: bool,
+        /// The number of queues the mailbox is split into. Producers are
+        /// spread across shards, which reduces contention when many threads
+        /// send to the same busy actor. Messages from the same sender are
+        /// always put into the same shard, so their order is preserved.
+        ///
+        /// Shards share the capacity of the mailbox, so they only reduce
+        /// contention on queues, not on acquiring free slots.
+        ///
+        /// It's applied only to newly started actors. Clamped to `64`.
+        ///
+        /// `1` (no sharding) by default.
+        pub shards: usize,
     }
 
     impl Default for MailboxConfig {
         fn default() -> Self {
-            Self { capacity: 100 }
+            Self {
+                capacity: 100,
+                envelope_pool: false,
+                shards: 1,
+            }
         }
     }
 }
@@ -114,20 +155,32 @@ unsafe impl Linked<Link> for EnvelopeHeader {
 }
 
 pub(crate) struct Mailbox {
-    /// A storage for envelopes based on an intrusive linked list.
+    /// Storages for envelopes based on intrusive linked lists, producers
+    /// enqueuing to different shards don't contend with each other.
     /// Note: `cordyceps` uses terms "head" and "tail" in the opposite way.
-    queue: MpscQueue<EnvelopeHeader>,
+    shards: Box<[CachePadded<MpscQueue<EnvelopeHeader>>]>,
 
-    /// A notifier of senders about the availability of new messages.
+    /// The shard to start the next dequeuing from, only used by the receiver.
+    next_shard: AtomicUsize,
+
+    /// A notifier of senders about the availability of free slots.
+    /// It limits the capacity of the whole mailbox, shared by all shards.
     // TODO: replace with a custom semaphore based on `async-event` (10-15% faster).
-    tx_semaphore: Semaphore,
+    tx_semaphore: CachePadded<Semaphore>,
 
     /// A notifier of a receiver about the availability of new messages.
     // TODO: replace with `diatomic-waker` (3-5% faster).
     rx_notify: CachePadded<Notify>,
 
+    /// Whether the receiver waits for new messages. Producers notify it only
+    /// in this case, so they don't contend on `rx_notify` while it's busy.
+    rx_waiting: CachePadded<AtomicBool>,
+
     /// Use `Mutex` here for synchronization on close/configure.
     control: Mutex<Control>,
+
+    /// A memory budget of the group, shared with other actors.
+    memory_budget: Arc<MemoryBudget>,
 }
 
 struct Control {
@@ -137,18 +190,44 @@ struct Control {
     capacity: usize,
 }
 
+thread_local! {
+    /// The key of the current `ExternalSender`, see `Mailbox::shard()`.
+    static EXTERNAL_KEY: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Puts messages without a sender, sent inside `f`, to the shard chosen by
+/// the provided key. Used by `ExternalSender` to preserve the order.
+pub(crate) fn with_external_key<R>(key: u64, f: impl FnOnce() -> R) -> R {
+    struct Guard(u64);
+
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            EXTERNAL_KEY.with(|k| k.set(self.0));
+        }
+    }
+
+    let _guard = Guard(EXTERNAL_KEY.with(|k| k.replace(key)));
+    f()
+}
+
 impl Mailbox {
-    pub(crate) fn new(config: &config::MailboxConfig) -> Self {
+    pub(crate) fn new(config: &config::MailboxConfig, memory_budget: Arc<MemoryBudget>) -> Self {
         let capacity = clamp_capacity(config.capacity);
+        let shards = config.shards.clamp(1, MAX_SHARDS);
 
         Self {
-            queue: MpscQueue::new_with_stub(Envelope::stub()),
-            tx_semaphore: Semaphore::new(capacity),
+            shards: (0..shards)
+                .map(|_| CachePadded::new(MpscQueue::new_with_stub(Envelope::stub())))
+                .collect(),
+            next_shard: AtomicUsize::new(0),
+            tx_semaphore: CachePadded::new(Semaphore::new(capacity)),
             rx_notify: CachePadded::new(Notify::new()),
+            rx_waiting: CachePadded::new(AtomicBool::new(false)),
             control: Mutex::new(Control {
                 closed_trace_id: None,
                 capacity,
             }),
+            memory_budget,
         }
     }
 
@@ -175,6 +254,21 @@ impl Mailbox {
         }
     }
 
+    /// Returns the number of stored envelopes, used only for inspection.
+    ///
+    /// It's computed from semaphores to avoid accounting on the hot path,
+    /// so envelopes sent by `unbounded_send()` aren't counted.
+    pub(crate) fn len(&self) -> usize {
+        let control = self.control.lock();
+        control
+            .capacity
+            .saturating_sub(self.tx_semaphore.available_permits())
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        self.control.lock().capacity
+    }
+
     pub(crate) async fn send(&self, envelope: Envelope) -> Result<(), SendError<Envelope>> {
         let permit = match self.tx_semaphore.acquire().await {
             Ok(permit) => permit,
@@ -182,17 +276,19 @@ impl Mailbox {
         };
 
         permit.forget();
-        self.queue.enqueue(envelope);
-        self.rx_notify.notify_one();
+        self.enqueue(envelope);
         Ok(())
     }
 
     pub(crate) fn try_send(&self, envelope: Envelope) -> Result<(), TrySendError<Envelope>> {
+        if self.memory_budget.should_reject_new() && !self.tx_semaphore.is_closed() {
+            return Err(TrySendError::Full(envelope));
+        }
+
         match self.tx_semaphore.try_acquire() {
             Ok(permit) => {
                 permit.forget();
-                self.queue.enqueue(envelope);
-                self.rx_notify.notify_one();
+                self.enqueue(envelope);
                 Ok(())
             }
             Err(TryAcquireError::NoPermits) => Err(TrySendError::Full(envelope)),
@@ -202,8 +298,7 @@ impl Mailbox {
 
     pub(crate) fn unbounded_send(&self, envelope: Envelope) -> Result<(), SendError<Envelope>> {
         if !self.tx_semaphore.is_closed() {
-            self.queue.enqueue(envelope);
-            self.rx_notify.notify_one();
+            self.enqueue(envelope);
             Ok(())
         } else {
             Err(SendError(envelope))
@@ -217,8 +312,7 @@ impl Mailbox {
             // by one consumer. However, it's not enough to create a dedicated
             // `MailboxConsumer` because users can steal `Context` to another
             // task/thread and create a race with the `drop_all()` method.
-            if let Some(envelope) = self.queue.dequeue() {
-                self.tx_semaphore.add_permits(1);
+            if let Some(envelope) = self.dequeue() {
                 return RecvResult::Data(envelope);
             }
 
@@ -226,16 +320,28 @@ impl Mailbox {
                 return self.on_close();
             }
 
-            self.rx_notify.notified().await;
+            // Announce waiting and check again, otherwise producers can enqueue
+            // between the check above and the announcement without notifying.
+            // Pairs with the fence in `enqueue()`.
+            self.rx_waiting.store(true, Ordering::Relaxed);
+            atomic::fence(Ordering::SeqCst);
+
+            if let Some(envelope) = self.dequeue() {
+                self.rx_waiting.store(false, Ordering::Relaxed);
+                return RecvResult::Data(envelope);
+            }
+
+            if !self.tx_semaphore.is_closed() {
+                self.rx_notify.notified().await;
+            }
+
+            self.rx_waiting.store(false, Ordering::Relaxed);
         }
     }
 
     pub(crate) fn try_recv(&self) -> Option<RecvResult> {
-        match self.queue.dequeue() {
-            Some(envelope) => {
-                self.tx_semaphore.add_permits(1);
-                Some(RecvResult::Data(envelope))
-            }
+        match self.dequeue() {
+            Some(envelope) => Some(RecvResult::Data(envelope)),
             None if self.tx_semaphore.is_closed() => Some(self.on_close()),
             None => None,
         }
@@ -256,19 +362,20 @@ impl Mailbox {
         control.closed_trace_id = Some(trace_id);
 
         self.tx_semaphore.close();
+
         self.rx_notify.notify_one();
         true
     }
 
     #[cold]
     pub(crate) fn drop_all(&self) {
-        while self.queue.dequeue().is_some() {}
+        while self.dequeue().is_some() {}
     }
 
     #[cold]
     fn on_close(&self) -> RecvResult {
         // Some messages may be in the queue after the channel is closed.
-        match self.queue.dequeue() {
+        match self.dequeue() {
             Some(envelope) => RecvResult::Data(envelope),
             None => {
                 let control = self.control.lock();
@@ -277,6 +384,81 @@ impl Mailbox {
             }
         }
     }
+
+    #[inline]
+    fn enqueue(&self, mut envelope: Envelope) {
+        // Envelopes are counted only if the budget is enabled, the mark is
+        // used on dequeuing, because the budget can be reconfigured meanwhile.
+        let is_budgeted = self.memory_budget.is_enabled();
+        if is_budgeted {
+            self.memory_budget.on_enqueued(envelope.allocated_size());
+        }
+        envelope.set_budgeted(is_budgeted);
+        self.shard(&envelope).enqueue(envelope);
+
+        // Pairs with the fence in `recv()`.
+        atomic::fence(Ordering::SeqCst);
+        if self.rx_waiting.load(Ordering::Relaxed) {
+            self.rx_notify.notify_one();
+        }
+    }
+
+    /// Dequeues the next envelope and releases its permit.
+    #[inline]
+    fn dequeue(&self) -> Option<Envelope> {
+        let envelope = match &*self.shards {
+            [shard] => shard.dequeue()?,
+            _ => self.dequeue_sharded()?,
+        };
+
+        self.tx_semaphore.add_permits(1);
+        if envelope.is_budgeted() {
+            self.memory_budget.on_dequeued(envelope.allocated_size());
+        }
+        Some(envelope)
+    }
+
+    #[inline]
+    fn shard(&self, envelope: &Envelope) -> &MpscQueue<EnvelopeHeader> {
+        if let [shard] = &*self.shards {
+            return shard;
+        }
+
+        // Messages of the same sender must be ordered, so the sender's address
+        // is used as a key. Messages without a sender are keyed by the
+        // `ExternalSender` they're sent by, all others share the same shard.
+        let sender = envelope.sender();
+        let key = if sender.is_null() {
+            EXTERNAL_KEY.with(Cell::get)
+        } else {
+            sender.into_bits()
+        };
+
+        &self.shards[fxhash::hash64(&key) as usize % self.shards.len()]
+    }
+
+    /// Dequeues in a round-robin manner to avoid starvation of shards.
+    fn dequeue_sharded(&self) -> Option<Envelope> {
+        let len = self.shards.len();
+        let start = self.next_shard.load(Ordering::Relaxed);
+
+        for offset in 0..len {
+            let index = (start + offset) % len;
+            if let Some(envelope) = self.shards[index].dequeue() {
+                self.next_shard.store(index + 1, Ordering::Relaxed);
+                return Some(envelope);
+            }
+        }
+
+        None
+    }
+}
+
+impl Drop for Mailbox {
+    fn drop(&mut self) {
+        // Release the memory budget occupied by remaining envelopes.
+        self.drop_all();
+    }
 }
 
 pub(crate) enum RecvResult {
@@ -287,3 +469,193 @@ pub(crate) enum RecvResult {
 fn clamp_capacity(capacity: usize) -> usize {
     capacity.min(Semaphore::MAX_PERMITS)
 }
+
+const MAX_SHARDS: usize = 64;
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{addr::Addr, envelope::MessageKind, message};
+
+    fn envelope(i: u32, sender: Addr) -> Envelope {
+        let trace_id = TraceId::try_from(1).unwrap();
+        Envelope::with_trace_id(Sample(i), MessageKind::regular(sender), trace_id)
+    }
+
+    #[message]
+    struct Sample(u32);
+
+    fn mailbox(shards: usize) -> Arc<Mailbox> {
+        let config = config::MailboxConfig {
+            capacity: 10_000,
+            shards,
+            ..Default::default()
+        };
+
+        Arc::new(Mailbox::new(&config, Default::default()))
+    }
+
+    fn recv_all(mailbox: &Mailbox) -> Vec<(Addr, u32)> {
+        std::iter::from_fn(|| match mailbox.try_recv()? {
+            RecvResult::Data(envelope) => {
+                let sender = envelope.sender();
+                let message = envelope.unpack::<Sample>().unwrap().0;
+                Some((sender, message.0))
+            }
+            RecvResult::Closed(_) => None,
+        })
+        .collect()
+    }
+
+    #[test]
+    fn sharded_order() {
+        const PRODUCERS: u64 = 32;
+        const MESSAGES: u32 = 100;
+
+        for shards in [1, 4, 1000] {
+            let mailbox = mailbox(shards);
+            assert_eq!(mailbox.shards.len(), shards.min(MAX_SHARDS));
+
+            let producers = (1..=PRODUCERS)
+                .map(|no| {
+                    let mailbox = mailbox.clone();
+                    std::thread::spawn(move || {
+                        let sender = Addr::from_bits(1 << 40 | no).unwrap();
+                        for i in 0..MESSAGES {
+                            mailbox.try_send(envelope(i, sender)).unwrap();
+                        }
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            for producer in producers {
+                producer.join().unwrap();
+            }
+
+            // Messages of every sender are received in order.
+            let received = recv_all(&mailbox);
+            assert_eq!(received.len(), (PRODUCERS as usize) * (MESSAGES as usize));
+
+            for no in 1..=PRODUCERS {
+                let sender = Addr::from_bits(1 << 40 | no).unwrap();
+                let messages = received
+                    .iter()
+                    .filter(|(addr, _)| *addr == sender)
+                    .map(|(_, i)| *i)
+                    .collect::<Vec<_>>();
+                assert_eq!(messages, (0..MESSAGES).collect::<Vec<_>>());
+            }
+
+            // The capacity is shared, so one sender can fill the whole mailbox.
+            assert_eq!(mailbox.capacity(), 10_000);
+
+            for i in 0..10_000 {
+                mailbox.try_send(envelope(i, Addr::NULL)).unwrap();
+            }
+            assert!(matches!(
+                mailbox.try_send(envelope(0, Addr::NULL)),
+                Err(TrySendError::Full(_))
+            ));
+            assert_eq!(mailbox.len(), 10_000);
+            assert_eq!(recv_all(&mailbox).len(), 10_000);
+        }
+    }
+
+    #[test]
+    fn external_order() {
+        const SENDERS: u64 = 32;
+        const MESSAGES: u32 = 100;
+
+        let mailbox = mailbox(8);
+
+        // Messages without a sender are keyed by the external key.
+        for i in 0..MESSAGES {
+            for key in 1..=SENDERS {
+                let envelope = envelope(key as u32 * MESSAGES + i, Addr::NULL);
+                with_external_key(key, || mailbox.try_send(envelope)).unwrap();
+            }
+        }
+
+        let received = recv_all(&mailbox);
+        assert_eq!(received.len(), (SENDERS as usize) * (MESSAGES as usize));
+
+        for key in 1..=SENDERS {
+            let key = key as u32;
+            let messages = received
+                .iter()
+                .map(|(_, i)| *i)
+                .filter(|i| i / MESSAGES == key)
+                .collect::<Vec<_>>();
+            let expected = (0..MESSAGES)
+                .map(|i| key * MESSAGES + i)
+                .collect::<Vec<_>>();
+            assert_eq!(messages, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn wakes_up_receiver() {
+        let mailbox = mailbox(4);
+
+        for i in 0..100 {
+            let producer = {
+                let mailbox = mailbox.clone();
+                std::thread::spawn(move || {
+                    let sender = Addr::from_bits(1 << 40 | i).unwrap();
+                    mailbox.try_send(envelope(i as u32, sender)).unwrap();
+                })
+            };
+
+            let recv = tokio::time::timeout(Duration::from_secs(5), mailbox.recv());
+            assert!(matches!(recv.await, Ok(RecvResult::Data(_))));
+            producer.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn len() {
+        let mailbox = mailbox(1);
+        assert_eq!(mailbox.len(), 0);
+        assert_eq!(mailbox.capacity(), 10_000);
+
+        mailbox.try_send(envelope(0, Addr::NULL)).unwrap();
+        mailbox.try_send(envelope(1, Addr::NULL)).unwrap();
+        assert_eq!(mailbox.len(), 2);
+
+        assert!(mailbox.try_recv().is_some());
+        assert_eq!(mailbox.len(), 1);
+
+        mailbox.drop_all();
+        assert_eq!(mailbox.len(), 0);
+    }
+
+    #[test]
+    fn memory_budget() {
+        use bytesize::ByteSize;
+
+        use crate::memory_budget::config::MemoryBudgetConfig;
+
+        let budget = Arc::new(MemoryBudget::default());
+        let mailbox = Mailbox::new(&Default::default(), budget.clone());
+        let size = envelope(0, Addr::NULL).allocated_size();
+
+        // Isn't accounted while the budget is disabled.
+        mailbox.try_send(envelope(0, Addr::NULL)).unwrap();
+        assert_eq!(budget.usage(), 0);
+
+        budget.configure(&MemoryBudgetConfig {
+            soft_limit: Some(ByteSize::mib(1)),
+            ..Default::default()
+        });
+        mailbox.try_send(envelope(1, Addr::NULL)).unwrap();
+        assert_eq!(budget.usage(), size);
+
+        // Dequeuing of the envelope sent before enabling doesn't affect usage.
+        assert!(matches!(mailbox.try_recv(), Some(RecvResult::Data(_))));
+        assert_eq!(budget.usage(), size);
+        assert!(matches!(mailbox.try_recv(), Some(RecvResult::Data(_))));
+        assert_eq!(budget.usage(), 0);
+    }
+}
@@ -0,0 +1,327 @@
+//! Contains `MemoryBudget` that tracks memory usage of a group.
+//!
+//! The usage is a sum of:
+//! * Sizes of envelopes stored in mailboxes of the group's actors. Only the
+//!   envelope's allocation is counted, heap allocations owned by messages are
+//!   not taken into account.
+//! * Amounts reported by actors via [`Context::report_memory_usage()`].
+//!
+//! Mailboxes are accounted only if the soft limit is configured. The usage of
+//! mailboxes is sharded by threads and summed only on evaluation, which
+//! happens once the usage changes by about 1/8 of the soft limit and on every
+//! report. Statuses of actors are updated by the supervisor once the soft
+//! limit is crossed in any direction. So, even stuck actors become `Alarming`
+//! if their mailboxes grow.
+//!
+//! [`Context::report_memory_usage()`]: crate::Context::report_memory_usage
+
+use std::{
+    sync::atomic::{AtomicBool, AtomicIsize, AtomicUsize, Ordering},
+    thread,
+};
+
+use tokio::sync::Notify;
+
+use elfo_utils::CachePadded;
+
+use self::config::{LoadShedding, MemoryBudgetConfig};
+
+// === MemoryBudgetConfig ===
+
+pub mod config {
+    //! [Config]
+    //!
+    //! [Config]: MemoryBudgetConfig
+
+    use bytesize::ByteSize;
+    use serde::Deserialize;
+
+    /// Memory budget configuration, disabled by default.
+    ///
+    /// If the soft limit is exceeded, actors of the group become
+    /// [`Alarming`] until the usage goes down. Also, the load shedding
+    /// policy is applied to mailboxes of the group.
+    ///
+    /// # Example
+    /// ```toml
+    /// [some_group]
+    /// system.memory_budget.soft_limit = "100MiB"
+    /// system.memory_budget.load_shedding = "RejectNew"
+    /// ```
+    ///
+    /// [`Alarming`]: crate::ActorStatusKind::Alarming
+    #[derive(Debug, Default, PartialEq, Deserialize)]
+    #[serde(default)]
+    pub struct MemoryBudgetConfig {
+        /// The soft limit of memory used by the group.
+        ///
+        /// `None` (disabled) by default.
+        pub soft_limit: Option<ByteSize>,
+        /// What to do with incoming messages if the limit is exceeded.
+        ///
+        /// `Disabled` by default.
+        pub load_shedding: LoadShedding,
+    }
+
+    /// Load shedding policies.
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+    pub enum LoadShedding {
+        /// Only changes statuses of actors.
+        #[default]
+        Disabled,
+        /// Rejects new messages sent by `try_send()`, they fail with
+        /// [`TrySendError::Full`]. `send()` and `unbounded_send()` are
+        /// unaffected, because they are already limited by the capacity.
+        ///
+        /// [`TrySendError::Full`]: crate::errors::TrySendError::Full
+        RejectNew,
+    }
+}
+
+// === MemoryBudget ===
+
+/// The maximum number of shards of the mailbox usage.
+const MAX_SHARDS: usize = 64;
+
+/// The usage can change by `soft_limit / PRECISION` before the budget is
+/// evaluated, see `MemoryBudget::batch`.
+const PRECISION: usize = 8;
+
+/// A memory budget shared by all actors of the same group.
+pub(crate) struct MemoryBudget {
+    /// Envelopes stored in mailboxes, in bytes, sharded by threads to avoid
+    /// contention. Shards can be negative, because envelopes are usually
+    /// dequeued by other threads than enqueued. Summed only on evaluation.
+    mailbox_usage: Box<[CachePadded<AtomicIsize>]>,
+    /// The budget is evaluated once any shard crosses a multiple of it,
+    /// so the usage cannot change by more than `batch * shards` unnoticed.
+    batch: AtomicUsize,
+    /// Reported by actors, in bytes.
+    reported_usage: AtomicUsize,
+    /// `0` means the budget is disabled.
+    soft_limit: AtomicUsize,
+    reject_new: AtomicBool,
+    /// Whether the soft limit is exceeded, updated on evaluation.
+    is_exceeded: AtomicBool,
+    /// Notified when `is_exceeded` is changed or the budget is closed.
+    changed: Notify,
+    is_closed: AtomicBool,
+}
+
+impl Default for MemoryBudget {
+    fn default() -> Self {
+        let shards = thread::available_parallelism()
+            .map_or(1, |n| n.get())
+            .next_power_of_two()
+            .min(MAX_SHARDS);
+
+        Self {
+            mailbox_usage: (0..shards).map(|_| CachePadded::default()).collect(),
+            batch: AtomicUsize::new(1),
+            reported_usage: AtomicUsize::new(0),
+            soft_limit: AtomicUsize::new(0),
+            reject_new: AtomicBool::new(false),
+            is_exceeded: AtomicBool::new(false),
+            changed: Notify::new(),
+            is_closed: AtomicBool::new(false),
+        }
+    }
+}
+
+thread_local! {
+    /// The index of the current thread, used to choose a shard.
+    static THREAD_INDEX: usize = {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    };
+}
+
+impl MemoryBudget {
+    pub(crate) fn configure(&self, config: &MemoryBudgetConfig) {
+        let soft_limit = config.soft_limit.map_or(0, |s| s.0.max(1) as usize);
+        self.soft_limit.store(soft_limit, Ordering::Relaxed);
+
+        let batch = soft_limit / (PRECISION * self.mailbox_usage.len());
+        self.batch.store(batch.max(1), Ordering::Relaxed);
+
+        let reject_new = config.load_shedding == LoadShedding::RejectNew;
+        self.reject_new.store(reject_new, Ordering::Relaxed);
+
+        self.evaluate();
+    }
+
+    /// Returns `true` if the soft limit is configured. Otherwise, the usage
+    /// of mailboxes isn't tracked at all.
+    #[inline]
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.soft_limit.load(Ordering::Relaxed) > 0
+    }
+
+    #[inline]
+    pub(crate) fn on_enqueued(&self, size: usize) {
+        self.add_mailbox_usage(size as isize);
+    }
+
+    #[inline]
+    pub(crate) fn on_dequeued(&self, size: usize) {
+        self.add_mailbox_usage(-(size as isize));
+    }
+
+    #[inline]
+    fn add_mailbox_usage(&self, delta: isize) {
+        let index = THREAD_INDEX.with(|index| *index) & (self.mailbox_usage.len() - 1);
+        let prev = self.mailbox_usage[index].fetch_add(delta, Ordering::Relaxed);
+
+        let batch = self.batch.load(Ordering::Relaxed) as isize;
+        if prev.div_euclid(batch) != (prev + delta).div_euclid(batch) {
+            self.evaluate();
+        }
+    }
+
+    /// Replaces the previously reported by an actor amount with a new one.
+    pub(crate) fn on_reported(&self, prev: usize, new: usize) {
+        if new >= prev {
+            self.reported_usage.fetch_add(new - prev, Ordering::Relaxed);
+        } else {
+            self.reported_usage.fetch_sub(prev - new, Ordering::Relaxed);
+        }
+
+        self.evaluate();
+    }
+
+    /// Notifies `changed()` if the soft limit is crossed in any direction.
+    fn evaluate(&self) {
+        let soft_limit = self.soft_limit.load(Ordering::Relaxed);
+        let exceeded = soft_limit > 0 && self.usage() > soft_limit;
+
+        // Avoid writing until the state is really changed.
+        if self.is_exceeded.load(Ordering::Relaxed) != exceeded
+            && self.is_exceeded.swap(exceeded, Ordering::Relaxed) != exceeded
+        {
+            self.changed.notify_one();
+        }
+    }
+
+    /// Waits until the soft limit is crossed in any direction since the last
+    /// call. Returns `false` if the budget is closed.
+    pub(crate) async fn changed(&self) -> bool {
+        self.changed.notified().await;
+        !self.is_closed.load(Ordering::Relaxed)
+    }
+
+    /// Wakes up `changed()` to stop watching the budget.
+    pub(crate) fn close(&self) {
+        self.is_closed.store(true, Ordering::Relaxed);
+        self.changed.notify_one();
+    }
+
+    pub(crate) fn usage(&self) -> usize {
+        let mailbox_usage = self
+            .mailbox_usage
+            .iter()
+            .map(|shard| shard.load(Ordering::Relaxed))
+            .sum::<isize>();
+
+        // Shards are updated independently, so the sum can be negative.
+        let mailbox_usage = mailbox_usage.max(0) as usize;
+        let reported_usage = self.reported_usage.load(Ordering::Relaxed);
+        mailbox_usage.saturating_add(reported_usage)
+    }
+
+    /// Returns the soft limit if it's configured.
+    pub(crate) fn soft_limit(&self) -> Option<usize> {
+        Some(self.soft_limit.load(Ordering::Relaxed)).filter(|&l| l > 0)
+    }
+
+    /// Returns `Some((usage, soft_limit))` if the soft limit is exceeded.
+    pub(crate) fn exceeded(&self) -> Option<(usize, usize)> {
+        let soft_limit = self.soft_limit()?;
+        let usage = self.usage();
+        (usage > soft_limit).then_some((usage, soft_limit))
+    }
+
+    /// Returns `true` if a new message should be rejected.
+    /// Uses the state of the last evaluation to avoid summing on every call.
+    #[inline]
+    pub(crate) fn should_reject_new(&self) -> bool {
+        self.reject_new.load(Ordering::Relaxed) && self.is_exceeded.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytesize::ByteSize;
+    use futures::FutureExt;
+
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let budget = MemoryBudget::default();
+        budget.on_enqueued(100);
+        budget.on_reported(0, 50);
+        assert_eq!(budget.usage(), 150);
+        assert_eq!(budget.soft_limit(), None);
+        assert_eq!(budget.exceeded(), None);
+        assert!(!budget.should_reject_new());
+
+        budget.configure(&MemoryBudgetConfig {
+            soft_limit: Some(ByteSize(120)),
+            load_shedding: LoadShedding::Disabled,
+        });
+        assert_eq!(budget.exceeded(), Some((150, 120)));
+        assert!(!budget.should_reject_new());
+        assert_eq!(budget.changed().now_or_never(), Some(true));
+        assert_eq!(budget.changed().now_or_never(), None);
+
+        budget.configure(&MemoryBudgetConfig {
+            soft_limit: Some(ByteSize(120)),
+            load_shedding: LoadShedding::RejectNew,
+        });
+        assert!(budget.should_reject_new());
+
+        budget.on_reported(50, 10);
+        assert_eq!(budget.usage(), 110);
+        assert_eq!(budget.exceeded(), None);
+        assert!(!budget.should_reject_new());
+        assert_eq!(budget.changed().now_or_never(), Some(true));
+
+        budget.on_dequeued(100);
+        budget.on_reported(10, 0);
+        assert_eq!(budget.usage(), 0);
+        assert_eq!(budget.changed().now_or_never(), None);
+
+        budget.close();
+        assert_eq!(budget.changed().now_or_never(), Some(false));
+    }
+
+    #[test]
+    fn batching() {
+        let budget = MemoryBudget::default();
+        let limit = PRECISION * budget.mailbox_usage.len() * 100;
+        budget.configure(&MemoryBudgetConfig {
+            soft_limit: Some(ByteSize(limit as u64)),
+            load_shedding: LoadShedding::RejectNew,
+        });
+        assert_eq!(budget.batch.load(Ordering::Relaxed), 100);
+
+        budget.on_enqueued(limit);
+        assert!(!budget.should_reject_new());
+
+        // Isn't evaluated until the shard crosses the next multiple of the batch.
+        budget.on_enqueued(50);
+        assert_eq!(budget.exceeded(), Some((limit + 50, limit)));
+        assert!(!budget.should_reject_new());
+        assert_eq!(budget.changed().now_or_never(), None);
+
+        budget.on_enqueued(50);
+        assert!(budget.should_reject_new());
+        assert_eq!(budget.changed().now_or_never(), Some(true));
+
+        // Shards can be negative, but not the usage.
+        budget.on_dequeued(limit + 200);
+        assert_eq!(budget.usage(), 0);
+        assert!(!budget.should_reject_new());
+        assert_eq!(budget.changed().now_or_never(), Some(true));
+    }
+}
@@ -83,13 +83,16 @@ pub trait Message:
         any.into_real()
     }
 
+    /// Returns `None` if the message cannot be borrowed as `Self`,
+    /// e.g. for enums made by `protocol_enum!`.
+    ///
     /// # Safety
     ///
     /// The caller must ensure that `any` holds this message type.
     #[doc(hidden)]
     #[inline(always)]
-    unsafe fn _from_any_ref(any: &AnyMessage) -> &Self {
-        any.as_real_ref()
+    unsafe fn _from_any_ref(any: &AnyMessage) -> Option<&Self> {
+        Some(any.as_real_ref())
     }
 
     #[doc(hidden)]
@@ -141,3 +144,24 @@ pub trait Request: Message {
     #[doc(hidden)]
     type Wrapper: Message + Into<Self::Response> + From<Self::Response>;
 }
+
+// === ProtocolEnum ===
+
+/// Implemented by enums generated by the [`protocol_enum!`] macro.
+/// Used by `msg!` to reject borrowing them.
+///
+/// [`protocol_enum!`]: crate::protocol_enum!
+#[doc(hidden)]
+pub trait ProtocolEnum: Message {}
+
+/// Implemented by `msg!` for every message matched by its arms,
+/// if the match is marked as `#[exhaustive(SomeProtocolEnum)]`.
+#[doc(hidden)]
+pub trait Matches<M> {}
+
+/// Implemented by [`protocol_enum!`] if `A` matches all messages of the enum.
+/// Used by `msg!` to check `#[exhaustive(SomeProtocolEnum)]` matches.
+///
+/// [`protocol_enum!`]: crate::protocol_enum!
+#[doc(hidden)]
+pub trait MatchedBy<A>: ProtocolEnum {}
@@ -1,7 +1,9 @@
 use std::{
-    alloc, fmt,
+    alloc,
+    convert::Infallible,
+    fmt,
     marker::PhantomData,
-    mem::{self, ManuallyDrop},
+    mem::{self, ManuallyDrop, MaybeUninit},
     ops::Deref,
     ptr::{self, NonNull},
 };
@@ -17,9 +19,11 @@ use crate::{dumping, scope::SerdeMode};
 
 // === AnyMessage ===
 
-/// A heap-allocated message that can be downcasted to a concrete message type.
+/// A message that can be downcasted to a concrete message type.
 ///
 /// It can be thought as a working version of `Box<dyn Message>`.
+/// However, small messages (up to three words on 64-bit targets, e.g. a
+/// `String` or a pair of `u64`s) are stored inline without heap allocation.
 ///
 /// [`AnyMessage`] implements [`Message`], thus it can be used in any context
 /// where a message is expected, e.g. sending via [`Context`]'s methods.
@@ -30,7 +34,17 @@ use crate::{dumping, scope::SerdeMode};
 ///
 /// [`Context`]: crate::Context
 /// [`Request`]: crate::Request
-pub struct AnyMessage(NonNull<MessageRepr>);
+pub struct AnyMessage(Storage);
+
+enum Storage {
+    /// Contains `MessageRepr` if it fits, see `fits_inline()`.
+    Inline(InlineRepr),
+    /// Allocated by `alloc_repr()` or borrowed by `AnyMessageRef`.
+    Heap(NonNull<MessageRepr>),
+}
+
+/// Enough for a vtable and three words of data.
+type InlineRepr = [MaybeUninit<usize>; 4];
 
 // Messages aren't required to be `Sync`.
 assert_not_impl_any!(AnyMessage: Sync);
@@ -50,10 +64,73 @@ impl AnyMessage {
     pub(super) fn from_real<M: Message>(message: M) -> Self {
         debug_assert_ne!(M::_type_id(), Self::_type_id());
 
-        let ptr = alloc_repr(message._vtable());
-        // SAFETY: allocation is done for `MessageRepr<M>`.
-        unsafe { message._write(ptr) };
-        Self(ptr)
+        let vtable = message._vtable();
+
+        // SAFETY: `_write()` initializes `MessageRepr<M>` for `M`'s vtable.
+        let result = unsafe {
+            Self::init_with(vtable, |out_ptr| {
+                message._write(out_ptr);
+                Ok::<_, Infallible>(())
+            })
+        };
+
+        match result {
+            Ok(this) => this,
+            Err(never) => match never {},
+        }
+    }
+
+    /// Creates a message by initializing its repr in place, inline if possible.
+    ///
+    /// # Safety
+    ///
+    /// `init` must initialize `MessageRepr` of the vtable's type if succeeded.
+    unsafe fn init_with<E>(
+        vtable: &'static MessageVTable,
+        init: impl FnOnce(NonNull<MessageRepr>) -> Result<(), E>,
+    ) -> Result<Self, E> {
+        if fits_inline(vtable.repr_layout) {
+            let mut inline: InlineRepr = [MaybeUninit::uninit(); 4];
+            init(NonNull::from(&mut inline).cast())?;
+            return Ok(Self(Storage::Inline(inline)));
+        }
+
+        let ptr = alloc_repr(vtable);
+
+        if let Err(err) = init(ptr) {
+            // The repr isn't initialized, so the vtable cannot be read from it.
+            alloc::dealloc(ptr.cast::<u8>().as_ptr(), vtable.repr_layout);
+            return Err(err);
+        }
+
+        Ok(Self(Storage::Heap(ptr)))
+    }
+
+    fn repr_ptr(&self) -> NonNull<MessageRepr> {
+        match &self.0 {
+            Storage::Inline(inline) => NonNull::from(inline).cast(),
+            Storage::Heap(ptr) => *ptr,
+        }
+    }
+
+    fn repr_ptr_mut(&mut self) -> NonNull<MessageRepr> {
+        match &mut self.0 {
+            Storage::Inline(inline) => NonNull::from(inline).cast(),
+            Storage::Heap(ptr) => *ptr,
+        }
+    }
+
+    /// Releases the memory without dropping the message.
+    ///
+    /// # Safety
+    ///
+    /// The message must be moved out or dropped before.
+    unsafe fn forget_repr(self) {
+        if let Storage::Heap(ptr) = self.0 {
+            dealloc_repr(ptr);
+        }
+
+        mem::forget(self);
     }
 
     /// # Safety
@@ -62,9 +139,8 @@ impl AnyMessage {
     pub(super) unsafe fn into_real<M: Message>(self) -> M {
         debug_assert_ne!(M::_type_id(), Self::_type_id());
 
-        let data = M::_read(self.0);
-        dealloc_repr(self.0);
-        mem::forget(self);
+        let data = M::_read(self.repr_ptr());
+        self.forget_repr();
         data
     }
 
@@ -74,13 +150,13 @@ impl AnyMessage {
     pub(super) unsafe fn as_real_ref<M: Message>(&self) -> &M {
         debug_assert_ne!(M::_type_id(), Self::_type_id());
 
-        &self.0.cast::<MessageRepr<M>>().as_ref().data
+        &self.repr_ptr().cast::<MessageRepr<M>>().as_ref().data
     }
 
     /// Returns [`AnyMessageRef`] that borrows the message.
     pub fn as_ref(&self) -> AnyMessageRef<'_> {
         // SAFETY: `self` is valid for reads.
-        unsafe { AnyMessageRef::new(self.0) }
+        unsafe { AnyMessageRef::new(self.repr_ptr()) }
     }
 
     pub(crate) fn type_id(&self) -> MessageTypeId {
@@ -99,12 +175,19 @@ impl AnyMessage {
 
     /// Tries to downcast the message to a reference to the concrete type.
     ///
-    /// Note: it returns `Some(&self)` if `M` is [`AnyMessage`].
+    /// Note: it returns `Some(&self)` if `M` is [`AnyMessage`] and `None` if
+    /// `M` is an enum made by [`protocol_enum!`], use
+    /// [`AnyMessage::downcast()`] for them.
+    ///
+    /// [`protocol_enum!`]: crate::protocol_enum
     #[inline]
     pub fn downcast_ref<M: Message>(&self) -> Option<&M> {
-        self.is::<M>()
-            // SAFETY: `self` is of type `M`, checked above.
-            .then(|| unsafe { self.downcast_ref_unchecked() })
+        if !self.is::<M>() {
+            return None;
+        }
+
+        // SAFETY: `self` is of type `M`, checked above.
+        unsafe { M::_from_any_ref(self) }
     }
 
     /// # Safety
@@ -113,7 +196,8 @@ impl AnyMessage {
     pub(crate) unsafe fn downcast_ref_unchecked<M: Message>(&self) -> &M {
         // If `M != AnyMessage` then `as_real_ref()` is called.
         // Otherwise, the message is returned as is.
-        M::_from_any_ref(self)
+        // Enums made by `protocol_enum!` are never borrowed, see `msg!`.
+        M::_from_any_ref(self).expect("the message cannot be borrowed")
     }
 
     /// Tries to downcast the message to a concrete type.
@@ -142,53 +226,71 @@ impl AnyMessage {
     /// where `M` is the same type that is hold by `self`.
     pub(crate) unsafe fn clone_into(&self, out_ptr: NonNull<MessageRepr>) {
         let vtable = self._vtable();
-        (vtable.clone)(self.0, out_ptr);
+        (vtable.clone)(self.repr_ptr(), out_ptr);
     }
 
     /// # Safety
     ///
     /// Data behind `self` cannot be accessed after this call.
+    /// Must be called only for borrowed messages (see `AnyMessageRef`).
     pub(crate) unsafe fn drop_in_place(&self) {
+        debug_assert!(matches!(self.0, Storage::Heap(_)));
+
         let vtable = self._vtable();
-        (vtable.drop_data)(self.0);
+        (vtable.drop_data)(self.repr_ptr());
     }
 
     fn as_serialize(&self) -> &(impl Serialize + ?Sized) {
         let vtable = self._vtable();
 
         // SAFETY: the resulting reference is bound to the lifetime of `self`.
-        unsafe { (vtable.as_serialize_any)(self.0).as_ref() }
+        unsafe { (vtable.as_serialize_any)(self.repr_ptr()).as_ref() }
     }
 }
 
 impl Drop for AnyMessage {
     fn drop(&mut self) {
-        // SAFETY: only a vtable is accessed below.
-        unsafe { self.drop_in_place() };
+        let vtable = self._vtable();
+
+        // SAFETY: the message is not accessed anymore below.
+        unsafe { (vtable.drop_data)(self.repr_ptr_mut()) };
 
-        // SAFETY: memory was allocated by `alloc_repr()`.
-        unsafe { dealloc_repr(self.0) };
+        if let Storage::Heap(ptr) = self.0 {
+            // SAFETY: memory was allocated by `alloc_repr()`.
+            // Only a vtable is accessed, it's not dropped above.
+            unsafe { dealloc_repr(ptr) };
+        }
     }
 }
 
 impl Clone for AnyMessage {
     fn clone(&self) -> Self {
-        let out_ptr = alloc_repr(self._vtable());
-
-        // SAFETY: `out_ptr` is based on the same message as `self`.
-        unsafe { self.clone_into(out_ptr) };
-
-        Self(out_ptr)
+        // SAFETY: `clone_into()` initializes the repr of the same type.
+        let result = unsafe {
+            Self::init_with(self._vtable(), |out_ptr| {
+                self.clone_into(out_ptr);
+                Ok::<_, Infallible>(())
+            })
+        };
+
+        match result {
+            Ok(this) => this,
+            Err(never) => match never {},
+        }
     }
 }
 
 impl fmt::Debug for AnyMessage {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         // SAFETY: the vtable belongs to `self`.
-        unsafe { (self._vtable().debug)(self.0, f) }
+        unsafe { (self._vtable().debug)(self.repr_ptr(), f) }
     }
 }
 
+fn fits_inline(layout: alloc::Layout) -> bool {
+    layout.size() <= mem::size_of::<InlineRepr>() && layout.align() <= mem::align_of::<InlineRepr>()
+}
+
 fn alloc_repr(vtable: &'static MessageVTable) -> NonNull<MessageRepr> {
     // SAFETY:
     // * `vtable` is valid (can be created only by `MessageVTable::new()`).
@@ -221,7 +323,7 @@ impl Message for AnyMessage {
     #[inline(always)]
     fn _vtable(&self) -> &'static MessageVTable {
         // SAFETY: `self` refers to the valid object.
-        unsafe { (*self.0.as_ptr()).vtable }
+        unsafe { (*self.repr_ptr().as_ptr()).vtable }
     }
 
     #[inline(always)]
@@ -240,8 +342,8 @@ impl Message for AnyMessage {
     }
 
     #[inline(always)]
-    unsafe fn _from_any_ref(any: &AnyMessage) -> &Self {
-        any
+    unsafe fn _from_any_ref(any: &AnyMessage) -> Option<&Self> {
+        Some(any)
     }
 
     #[inline(always)]
@@ -249,34 +351,37 @@ impl Message for AnyMessage {
         let vtable = self._vtable();
 
         // SAFETY: the vtable belongs to `self`.
-        unsafe { (vtable.erase)(self.0) }
+        unsafe { (vtable.erase)(self.repr_ptr()) }
     }
 
     #[inline(always)]
     unsafe fn _read(ptr: NonNull<MessageRepr>) -> Self {
         let vtable = (*ptr.as_ptr()).vtable;
-        let this = alloc_repr(vtable);
 
-        ptr::copy_nonoverlapping(
-            ptr.cast::<u8>().as_ptr(),
-            this.cast::<u8>().as_ptr(),
-            vtable.repr_layout.size(),
-        );
+        let result = Self::init_with(vtable, |out_ptr| {
+            ptr::copy_nonoverlapping(
+                ptr.cast::<u8>().as_ptr(),
+                out_ptr.cast::<u8>().as_ptr(),
+                vtable.repr_layout.size(),
+            );
+            Ok::<_, Infallible>(())
+        });
 
-        Self(this)
+        match result {
+            Ok(this) => this,
+            Err(never) => match never {},
+        }
     }
 
     #[inline(always)]
     unsafe fn _write(self, out_ptr: NonNull<MessageRepr>) {
         ptr::copy_nonoverlapping(
-            self.0.cast::<u8>().as_ptr(),
+            self.repr_ptr().cast::<u8>().as_ptr(),
             out_ptr.cast::<u8>().as_ptr(),
             self._vtable().repr_layout.size(),
         );
 
-        dealloc_repr(self.0);
-
-        mem::forget(self);
+        self.forget_repr();
     }
 }
 
@@ -359,14 +464,27 @@ impl<'de> de::DeserializeSeed<'de> for MessageTag<'_> {
         let vtable = MessageVTable::lookup(protocol, name)
             .ok_or_else(|| de::Error::custom(format_args!("unknown message: {protocol}/{name}")))?;
 
-        let out_ptr = alloc_repr(vtable);
+        AnyMessage::deserialize_with(vtable, deserializer)
+    }
+}
 
+impl AnyMessage {
+    /// Deserializes only the message's data, the type is defined by the vtable.
+    pub(crate) fn deserialize_with<'de, D>(
+        vtable: &'static MessageVTable,
+        deserializer: D,
+    ) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
         let mut deserializer = <dyn erased_serde::Deserializer<'_>>::erase(deserializer);
         // SAFETY: `out_ptr` belongs to the same object as the vtable.
-        unsafe { (vtable.deserialize_any)(&mut deserializer, out_ptr) }
-            .map_err(de::Error::custom)?;
-
-        Ok(AnyMessage(out_ptr))
+        unsafe {
+            AnyMessage::init_with(vtable, |out_ptr| {
+                (vtable.deserialize_any)(&mut deserializer, out_ptr)
+            })
+        }
+        .map_err(de::Error::custom)
     }
 }
 
@@ -385,12 +503,12 @@ cfg_network!({
                 return Ok(None);
             };
 
-            let out_ptr = alloc_repr(vtable);
-
             // SAFETY: `out_ptr` belongs to the same object as the vtable.
-            unsafe { (vtable.read_msgpack)(buffer, out_ptr) }?;
+            let this = unsafe {
+                Self::init_with(vtable, |out_ptr| (vtable.read_msgpack)(buffer, out_ptr))
+            }?;
 
-            Ok(Some(Self(out_ptr)))
+            Ok(Some(this))
         }
 
         #[doc(hidden)]
@@ -398,7 +516,7 @@ cfg_network!({
         pub fn write_msgpack(&self, out: &mut Vec<u8>, limit: usize) -> Result<(), encode::Error> {
             let vtable = self._vtable();
             // SAFETY: the vtable belongs to `self`.
-            unsafe { (vtable.write_msgpack)(self.0, out, limit) }
+            unsafe { (vtable.write_msgpack)(self.repr_ptr(), out, limit) }
         }
     }
 });
@@ -419,7 +537,7 @@ impl<'a> AnyMessageRef<'a> {
     /// `ptr` must be a valid pointer for reads.
     pub(crate) unsafe fn new(ptr: NonNull<MessageRepr>) -> Self {
         Self {
-            inner: ManuallyDrop::new(AnyMessage(ptr)),
+            inner: ManuallyDrop::new(AnyMessage(Storage::Heap(ptr))),
             marker: PhantomData,
         }
     }
@@ -490,6 +608,10 @@ mod tests_miri {
     #[derive(PartialEq)]
     struct P16(u128);
 
+    #[message]
+    #[derive(PartialEq)]
+    struct P32([u64; 4]);
+
     fn check_basic_ops<M: Message + PartialEq>(message: M) {
         let message_box = AnyMessage::new(message.clone());
 
@@ -542,6 +664,41 @@ mod tests_miri {
         check_basic_ops(P1(42));
         check_basic_ops(P8(424242));
         check_basic_ops(P16(424242424242));
+        check_basic_ops(P32([42, 4242, 424242, 42424242]));
+    }
+
+    #[message]
+    struct WithBigDrop(Arc<()>, [u64; 4]);
+
+    #[test]
+    fn storage() {
+        fn is_inline(message: &AnyMessage) -> bool {
+            matches!(message.0, Storage::Inline(_))
+        }
+
+        assert!(is_inline(&AnyMessage::new(P0)));
+        assert!(is_inline(&AnyMessage::new(P8(42))));
+        assert!(is_inline(&AnyMessage::new(WithImplicitDrop(Arc::new(())))));
+        assert!(!is_inline(&AnyMessage::new(P32([42; 4]))));
+        assert_eq!(
+            is_inline(&AnyMessage::new(P16(42))),
+            mem::align_of::<u128>() <= mem::align_of::<usize>()
+        );
+
+        // Clones use the same storage.
+        let counter = Arc::new(());
+        let message_box = AnyMessage::new(WithBigDrop(counter.clone(), [42; 4]));
+        let message_box_2 = message_box.clone();
+        assert!(!is_inline(&message_box_2));
+        assert_eq!(Arc::strong_count(&counter), 3);
+
+        // Messages are dropped or moved out regardless of storage.
+        drop(message_box);
+        assert_eq!(Arc::strong_count(&counter), 2);
+        let message = message_box_2.downcast::<WithBigDrop>().unwrap();
+        assert_eq!(message.1, [42; 4]);
+        drop(message);
+        assert_eq!(Arc::strong_count(&counter), 1);
     }
 
     #[message]
@@ -48,6 +48,14 @@ impl MessageVTable {
         MESSAGE_VTABLES_MAP.get(protocol, name)
     }
 
+    pub(crate) fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub(crate) fn protocol(&self) -> &'static str {
+        self.protocol
+    }
+
     #[cfg(miri)]
     pub(crate) fn register_for_miri(&'static self) {
         MESSAGE_VTABLES_MAP.register(self);
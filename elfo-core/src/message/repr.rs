@@ -38,7 +38,7 @@ impl MessageTypeId {
         Self(vtable as *const _ as *const ())
     }
 
-    pub(super) const fn any() -> Self {
+    pub const fn any() -> Self {
         Self(ptr::null())
     }
 }
@@ -55,7 +55,7 @@ impl PartialEq for MessageTypeId {
 /// A message representation as a cpp-style object.
 ///
 /// Initially, it's created from a typed message as [`MessageRepr<M>`], then
-/// * for [`AnyMessage`]: moved directly to heap
+/// * for [`AnyMessage`]: stored inline if small enough, otherwise moved to heap
 /// * for [`Envelope`]: becomes a part and whole envelope is moved to heap
 ///
 /// All subsequent accesses are done via `NonNull<MessageRepr>` and require
@@ -2,7 +2,7 @@ use std::{fmt::Display, sync::Arc};
 
 use derive_more::Constructor;
 
-use crate::{actor::ActorMeta, actor_status::ActorStatus, config::AnyConfig, message};
+use crate::{actor::ActorMeta, actor_status::ActorStatus, addr::Addr, config::AnyConfig, message};
 
 /// A helper type for using in generic code (e.g. as an associated type) to
 /// indicate a message that cannot be constructed.
@@ -24,6 +24,7 @@ pub struct Ping;
 #[derive(Constructor)]
 #[non_exhaustive]
 pub struct ValidateConfig {
+    #[message(redact)]
     pub config: AnyConfig,
 }
 
@@ -31,9 +32,29 @@ pub struct ValidateConfig {
 #[derive(Constructor)]
 #[non_exhaustive]
 pub struct UpdateConfig {
+    #[message(redact)]
     pub config: AnyConfig,
 }
 
+/// Updates the config only for a subset of actors (canaries) in the group.
+/// Actors spawned later with matching keys get this config too.
+/// The next `UpdateConfig` is applied to all actors and resets canaries.
+///
+/// Only the user part of the config is applied, the `system` section is
+/// applied on `UpdateConfig`.
+#[message]
+#[derive(Constructor)]
+#[non_exhaustive]
+pub struct UpdateConfigCanary {
+    #[message(redact)]
+    pub config: AnyConfig,
+    /// Keys of actors to update, as they are displayed.
+    pub keys: Vec<String>,
+    /// A fraction of other actors to update, in the `[0, 1]` range.
+    /// Actors are selected by hashes of their keys, so the selection is stable.
+    pub fraction: f64,
+}
+
 #[message]
 #[non_exhaustive]
 pub struct ConfigRejected {
@@ -90,6 +111,31 @@ impl Terminate {
     }
 }
 
+/// Asks the supervisor to abort the actor with the provided address,
+/// e.g. because it doesn't respond to pings. The actor is considered failed,
+/// so whether it's restarted is decided by its restart policy.
+///
+/// Note that the actor is aborted only at the next `.await` point, so actors
+/// stuck in blocking code cannot be aborted.
+///
+/// Must be sent to the actor's group. Ignored if there is no such actor in
+/// the group, e.g. if it has been already restarted and got a new address.
+#[message]
+#[non_exhaustive]
+pub struct RestartActor {
+    // `Addr` isn't serializable, because it's meaningful only locally.
+    pub(crate) addr: u64,
+}
+
+impl RestartActor {
+    /// Takes the address of the actor.
+    pub fn new(addr: Addr) -> Self {
+        Self {
+            addr: addr.into_bits(),
+        }
+    }
+}
+
 // === Status ===
 
 // TODO: should it be a request?
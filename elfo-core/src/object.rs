@@ -18,6 +18,7 @@ use crate::{
     addr::Addr,
     envelope::Envelope,
     errors::{RequestError, SendError, TrySendError},
+    inspection::GroupInspection,
     request_table::ResponseToken,
 };
 
@@ -35,7 +36,7 @@ pub type OwnedObject = OwnedEntry<Object>;
 
 #[derive(From)]
 pub(crate) enum ObjectKind {
-    Actor(Actor),
+    Actor(Box<Actor>),
     Group(Box<dyn GroupHandle>),
     #[cfg(feature = "network")]
     Remote(Box<dyn RemoteHandle>),
@@ -83,7 +84,25 @@ impl Object {
             ObjectKind::Group(handle) => {
                 let mut visitor = SendGroupVisitor::default();
                 handle.handle(envelope, &mut visitor);
-                SendFut::WaitGroup(visitor.finish())
+
+                if visitor.full.is_empty() {
+                    let result = visitor.into_result();
+                    if let Err(SendError(envelope)) = &result {
+                        handle.undelivered(envelope);
+                    }
+                    return SendFut::Ready(result);
+                }
+
+                let this = this.to_owned();
+                SendFut::WaitGroup(async move {
+                    let result = visitor.finish().await;
+                    if let (Err(SendError(envelope)), Some(this)) = (&result, this) {
+                        this.as_group()
+                            .expect("must be a group")
+                            .undelivered(envelope);
+                    }
+                    result
+                })
             }
             #[cfg(feature = "network")]
             ObjectKind::Remote(handle) => match handle.try_send(recipient, envelope) {
@@ -123,7 +142,12 @@ impl Object {
             ObjectKind::Group(handle) => {
                 let mut visitor = TrySendGroupVisitor::default();
                 handle.handle(envelope, &mut visitor);
-                visitor.finish()
+                let result = visitor.finish();
+                if let Err(TrySendError::Full(envelope) | TrySendError::Closed(envelope)) = &result
+                {
+                    handle.undelivered(envelope);
+                }
+                result
             }
             #[cfg(feature = "network")]
             ObjectKind::Remote(handle) => handle.try_send(recipient, envelope),
@@ -141,7 +165,11 @@ impl Object {
             ObjectKind::Group(handle) => {
                 let mut visitor = UnboundedSendGroupVisitor::default();
                 handle.handle(envelope, &mut visitor);
-                visitor.finish()
+                let result = visitor.finish();
+                if let Err(SendError(envelope)) = &result {
+                    handle.undelivered(envelope);
+                }
+                result
             }
             #[cfg(feature = "network")]
             ObjectKind::Remote(handle) => handle.unbounded_send(recipient, envelope),
@@ -167,6 +195,21 @@ impl Object {
         handle.handle(envelope, visitor);
     }
 
+    pub(crate) fn inspect_group(&self) -> Option<GroupInspection> {
+        match &self.kind {
+            ObjectKind::Group(handle) => Some(handle.inspect()),
+            _ => None,
+        }
+    }
+
+    #[allow(clippy::borrowed_box)]
+    fn as_group(&self) -> Option<&Box<dyn GroupHandle>> {
+        match &self.kind {
+            ObjectKind::Group(handle) => Some(handle),
+            _ => None,
+        }
+    }
+
     pub(crate) fn as_actor(&self) -> Option<&Actor> {
         match &self.kind {
             ObjectKind::Actor(handle) => Some(handle),
@@ -191,6 +234,12 @@ impl Object {
             ObjectKind::Remote(_) => todo!(),
         }
     }
+
+    pub(crate) async fn shutdown_runtime(&self) {
+        if let ObjectKind::Group(group) = &self.kind {
+            group.shutdown_runtime().await;
+        }
+    }
 }
 
 // === SendFut ===
@@ -252,7 +301,12 @@ where
 
 pub(crate) trait GroupHandle: Send + Sync + 'static {
     fn handle(&self, envelope: Envelope, visitor: &mut dyn GroupVisitor);
+    /// Called if the envelope passed to `handle()` hasn't been delivered.
+    fn undelivered(&self, envelope: &Envelope);
     fn finished(&self) -> BoxFuture<'static, ()>;
+    /// Stops the dedicated runtime of the group, if any.
+    fn shutdown_runtime(&self) -> BoxFuture<'static, ()>;
+    fn inspect(&self) -> GroupInspection;
 }
 
 /// The visitor of actors inside a group.
@@ -334,6 +388,10 @@ impl SendGroupVisitor {
             }
         }
 
+        self.into_result()
+    }
+
+    fn into_result(mut self) -> SendResult {
         debug_assert!(self.full.is_empty());
 
         if self.has_ok {
@@ -1,28 +1,210 @@
 use std::{
     any::Any,
+    backtrace::{Backtrace, BacktraceStatus},
+    cell::{Cell, RefCell},
+    fmt,
     future::Future,
     panic::{self, AssertUnwindSafe},
+    pin::Pin,
+    sync::Once,
+    task::{self, Poll},
 };
 
 use futures::FutureExt;
+use pin_project::pin_project;
 
-pub(crate) fn sync_catch<R>(f: impl FnOnce() -> R) -> Result<R, String> {
-    panic::catch_unwind(AssertUnwindSafe(f)).map_err(panic_to_string)
+/// A caught panic with its message and, if available, the location and
+/// the backtrace captured at the moment of panicking.
+///
+/// The backtrace is captured according to the `RUST_LIB_BACKTRACE` and
+/// `RUST_BACKTRACE` env variables, see [`Backtrace::capture()`] for details.
+pub(crate) struct Panic {
+    message: String,
+    location: Option<String>,
+    backtrace: Option<Backtrace>,
 }
 
-pub(crate) async fn catch<R>(f: impl Future<Output = R>) -> Result<R, String> {
-    AssertUnwindSafe(f)
+impl Panic {
+    pub(crate) fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub(crate) fn location(&self) -> Option<&str> {
+        self.location.as_deref()
+    }
+
+    pub(crate) fn backtrace(&self) -> Option<&Backtrace> {
+        self.backtrace.as_ref()
+    }
+}
+
+/// Writes `panic: <message>` only. The location and the backtrace are large
+/// and exposed separately, because this representation is used in statuses.
+impl fmt::Display for Panic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "panic: {}", self.message)
+    }
+}
+
+impl fmt::Debug for Panic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl From<Panic> for String {
+    fn from(panic: Panic) -> Self {
+        panic.to_string()
+    }
+}
+
+pub(crate) fn sync_catch<R>(f: impl FnOnce() -> R) -> Result<R, Panic> {
+    install_hook();
+    panic::catch_unwind(AssertUnwindSafe(|| {
+        let _guard = CatchingGuard::enter();
+        f()
+    }))
+    .map_err(make_panic)
+}
+
+pub(crate) async fn catch<R>(f: impl Future<Output = R>) -> Result<R, Panic> {
+    install_hook();
+    AssertUnwindSafe(Catching(f))
         .catch_unwind()
         .await
-        .map_err(panic_to_string)
+        .map_err(make_panic)
+}
+
+/// Marks the current thread as catching panics while polling the future.
+/// It's done on every poll, because the task can be moved between threads.
+#[pin_project]
+struct Catching<F>(#[pin] F);
+
+impl<F: Future> Future for Catching<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        let _guard = CatchingGuard::enter();
+        self.project().0.poll(cx)
+    }
+}
+
+struct CatchingGuard;
+
+impl CatchingGuard {
+    fn enter() -> Self {
+        CATCHING.with(|depth| depth.set(depth.get() + 1));
+        Self
+    }
+}
+
+impl Drop for CatchingGuard {
+    fn drop(&mut self) {
+        // `try_with` is used because the guard can be dropped during TLS destruction.
+        let _ = CATCHING.try_with(|depth| depth.set(depth.get() - 1));
+    }
 }
 
-fn panic_to_string(payload: Box<dyn Any + Send>) -> String {
+fn make_panic(payload: Box<dyn Any + Send>) -> Panic {
+    // The hook is called on the same thread right before unwinding, so the
+    // last captured info always belongs to this panic (if the hook is ours).
+    let (location, backtrace) = LAST_PANIC.with(|last| last.borrow_mut().take()).unzip();
+
+    Panic {
+        message: payload_to_string(&*payload),
+        location,
+        backtrace: backtrace.flatten(),
+    }
+}
+
+fn payload_to_string(payload: &(dyn Any + Send)) -> String {
     if let Some(message) = payload.downcast_ref::<&str>() {
-        format!("panic: {message}")
+        (*message).to_string()
     } else if let Some(message) = payload.downcast_ref::<String>() {
-        format!("panic: {message}")
+        message.clone()
     } else {
-        "panic: <unsupported payload>".to_string()
+        "<unsupported payload>".to_string()
+    }
+}
+
+thread_local! {
+    // A number of nested `sync_catch()` and `catch()` calls on this thread.
+    static CATCHING: Cell<u32> = const { Cell::new(0) };
+    static LAST_PANIC: RefCell<Option<(String, Option<Backtrace>)>> = const { RefCell::new(None) };
+}
+
+/// Installs a panic hook capturing the location and the backtrace of panics
+/// caught by `sync_catch()` and `catch()`, e.g. panics of actors. Other panics
+/// of the application are left as is. The previous hook is always called
+/// afterwards, so hooks installed by the application earlier are preserved.
+fn install_hook() {
+    static INSTALLED: Once = Once::new();
+
+    INSTALLED.call_once(|| {
+        let prev_hook = panic::take_hook();
+
+        panic::set_hook(Box::new(move |info| {
+            if CATCHING.try_with(Cell::get).unwrap_or(0) == 0 {
+                return prev_hook(info);
+            }
+
+            let location = info
+                .location()
+                .map_or_else(|| "<unknown>".into(), |l| l.to_string());
+            let backtrace = Backtrace::capture();
+            let backtrace = (backtrace.status() == BacktraceStatus::Captured).then_some(backtrace);
+
+            // `try_with` is used because the hook can be called during TLS destruction.
+            let _ = LAST_PANIC.try_with(|last| {
+                if let Ok(mut last) = last.try_borrow_mut() {
+                    *last = Some((location, backtrace));
+                }
+            });
+
+            prev_hook(info);
+        }));
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn message_and_location() {
+        let panic = sync_catch(|| panic!("oops {}", 42)).unwrap_err();
+        assert_eq!(panic.message(), "oops 42");
+        assert!(panic.location().unwrap().contains("panic.rs"));
+        assert_eq!(panic.to_string(), "panic: oops 42");
+
+        let panic = sync_catch(|| std::panic::panic_any(42)).unwrap_err();
+        assert_eq!(panic.message(), "<unsupported payload>");
+    }
+
+    #[tokio::test]
+    async fn async_catch() {
+        let panic = catch(async {
+            tokio::task::yield_now().await;
+            panic!("oops");
+        })
+        .await
+        .unwrap_err();
+
+        assert_eq!(panic.message(), "oops");
+        assert!(panic.location().is_some());
+    }
+
+    #[test]
+    fn uncaught_panics_are_ignored() {
+        install_hook();
+
+        // Emulate a panic caught by the application itself.
+        let res = std::panic::catch_unwind(|| panic!("app"));
+        assert!(res.is_err());
+        assert!(LAST_PANIC.with(|last| last.borrow().is_none()));
+        assert_eq!(CATCHING.with(Cell::get), 0);
+
+        let _ = sync_catch(|| panic!("actor"));
+        assert_eq!(CATCHING.with(Cell::get), 0);
     }
 }
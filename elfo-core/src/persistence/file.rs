@@ -0,0 +1,413 @@
+use std::{
+    fmt::Write as _,
+    fs::{self, File, OpenOptions},
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::PathBuf,
+};
+
+use fxhash::FxHashMap;
+use parking_lot::Mutex;
+
+use super::Journal;
+
+/// A journal storing every stream in a separate file inside the directory.
+///
+/// Every record is `seq_no: u64 (LE) | len: u32 (LE) | crc: u32 (LE) | event`,
+/// where `crc` is CRC-32 of `seq_no`, `len` and `event`. Events are limited
+/// by [`FileJournal::MAX_EVENT_SIZE`].
+///
+/// An incomplete or corrupted record (e.g. after a crash during writing)
+/// ends the valid part of a file: it's ignored on reading along with
+/// following records and overwritten by the next append.
+///
+/// Snapshots are stored in separate `.snapshot` files as
+/// `seq_no: u64 (LE) | snapshot` and replaced atomically.
+/// Pruning rewrites the stream's file without pruned events.
+pub struct FileJournal {
+    dir: PathBuf,
+    sync: bool,
+    files: Mutex<FxHashMap<String, File>>,
+}
+
+const HEADER_SIZE: usize = 16;
+
+impl FileJournal {
+    /// The maximum size of an event, larger ones are rejected by `append()`.
+    /// It also limits allocations while reading corrupted files.
+    pub const MAX_EVENT_SIZE: usize = 64 * 1024 * 1024;
+
+    /// Creates a journal in the provided directory, creating it if needed.
+    pub fn new(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+
+        Ok(Self {
+            dir,
+            sync: true,
+            files: Mutex::new(FxHashMap::default()),
+        })
+    }
+
+    /// Whether to call `fsync` after every append.
+    ///
+    /// `true` by default.
+    pub fn sync(mut self, sync: bool) -> Self {
+        self.sync = sync;
+        self
+    }
+
+    /// Returns the path to the file storing the stream.
+    pub fn path(&self, stream: &str) -> PathBuf {
+        self.dir.join(file_name(stream, "journal"))
+    }
+
+    fn snapshot_path(&self, stream: &str) -> PathBuf {
+        self.dir.join(file_name(stream, "snapshot"))
+    }
+
+    fn open(&self, stream: &str) -> io::Result<File> {
+        let path = self.path(stream);
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+
+        // Cut an incomplete or corrupted record off, if any.
+        let len = valid_len(&mut BufReader::new(&mut file))?;
+        file.set_len(len)?;
+        Ok(file)
+    }
+}
+
+impl Journal for FileJournal {
+    fn append(&self, stream: &str, seq_no: u64, event: &[u8]) -> io::Result<()> {
+        let mut files = self.files.lock();
+        if !files.contains_key(stream) {
+            let file = self.open(stream)?;
+            files.insert(stream.into(), file);
+        }
+
+        let file = files.get_mut(stream).expect("just inserted");
+        let len = file.seek(SeekFrom::End(0))?;
+
+        let result = append_record(file, seq_no, event, self.sync);
+
+        // Cut a torn record off, so the next append doesn't land after it.
+        // If it's impossible, the file is reopened and repaired on next append.
+        if result.is_err() && file.set_len(len).is_err() {
+            files.remove(stream);
+        }
+
+        result
+    }
+
+    fn read(&self, stream: &str, after: u64) -> io::Result<Vec<(u64, Vec<u8>)>> {
+        let file = match File::open(self.path(stream)) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        };
+
+        let mut events = Vec::new();
+        read_records(&mut BufReader::new(file), |seq_no, event| {
+            if seq_no > after {
+                events.push((seq_no, event));
+            }
+        })?;
+
+        Ok(events)
+    }
+
+    fn save_snapshot(&self, stream: &str, seq_no: u64, snapshot: &[u8]) -> io::Result<()> {
+        let path = self.snapshot_path(stream);
+        let tmp_path = path.with_extension("snapshot.tmp");
+
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(&seq_no.to_le_bytes())?;
+        file.write_all(snapshot)?;
+        if self.sync {
+            file.sync_data()?;
+        }
+        drop(file);
+
+        fs::rename(&tmp_path, &path)
+    }
+
+    fn load_snapshot(&self, stream: &str) -> io::Result<Option<(u64, Vec<u8>)>> {
+        let mut data = match fs::read(self.snapshot_path(stream)) {
+            Ok(data) => data,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+
+        if data.len() < 8 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "corrupted snapshot",
+            ));
+        }
+
+        let seq_no = u64::from_le_bytes(data[..8].try_into().unwrap());
+        data.drain(..8);
+        Ok(Some((seq_no, data)))
+    }
+
+    fn prune(&self, stream: &str, seq_no: u64) -> io::Result<()> {
+        // Hold the lock to prevent concurrent appends.
+        let mut files = self.files.lock();
+        let rest = self.read(stream, seq_no)?;
+
+        let path = self.path(stream);
+        let tmp_path = path.with_extension("journal.tmp");
+
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        for (seq_no, event) in &rest {
+            write_record(&mut writer, *seq_no, event)?;
+        }
+        let file = writer.into_inner().map_err(|err| err.into_error())?;
+        if self.sync {
+            file.sync_data()?;
+        }
+        drop(file);
+
+        // The cached file refers to the old inode, so reopen it lazily.
+        files.remove(stream);
+        fs::rename(&tmp_path, &path)
+    }
+}
+
+fn append_record(file: &File, seq_no: u64, event: &[u8], sync: bool) -> io::Result<()> {
+    let mut writer = BufWriter::new(file);
+    write_record(&mut writer, seq_no, event)?;
+    writer.flush()?;
+    drop(writer);
+
+    if sync {
+        file.sync_data()?;
+    }
+
+    Ok(())
+}
+
+fn write_record(writer: &mut impl Write, seq_no: u64, event: &[u8]) -> io::Result<()> {
+    if event.len() > FileJournal::MAX_EVENT_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "too large event",
+        ));
+    }
+
+    let seq_no = seq_no.to_le_bytes();
+    let len = (event.len() as u32).to_le_bytes();
+
+    writer.write_all(&seq_no)?;
+    writer.write_all(&len)?;
+    writer.write_all(&checksum(&seq_no, &len, event).to_le_bytes())?;
+    writer.write_all(event)
+}
+
+fn checksum(seq_no: &[u8], len: &[u8], event: &[u8]) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(seq_no);
+    hasher.update(len);
+    hasher.update(event);
+    hasher.finalize()
+}
+
+/// Reads complete and valid records, returns the length of the valid part.
+fn read_records(reader: &mut impl Read, mut f: impl FnMut(u64, Vec<u8>)) -> io::Result<u64> {
+    let mut valid_len = 0;
+
+    loop {
+        let mut header = [0; HEADER_SIZE];
+        if !read_exact_or_eof(reader, &mut header)? {
+            return Ok(valid_len);
+        }
+
+        let len = u32::from_le_bytes(header[8..12].try_into().unwrap()) as usize;
+        if len > FileJournal::MAX_EVENT_SIZE {
+            return Ok(valid_len);
+        }
+
+        let mut event = vec![0; len];
+        if !read_exact_or_eof(reader, &mut event)? {
+            return Ok(valid_len);
+        }
+
+        let crc = u32::from_le_bytes(header[12..].try_into().unwrap());
+        if crc != checksum(&header[..8], &header[8..12], &event) {
+            return Ok(valid_len);
+        }
+
+        let seq_no = u64::from_le_bytes(header[..8].try_into().unwrap());
+        valid_len += (HEADER_SIZE + len) as u64;
+        f(seq_no, event);
+    }
+}
+
+fn valid_len(reader: &mut impl Read) -> io::Result<u64> {
+    read_records(reader, |_, _| {})
+}
+
+/// Returns `false` if EOF is reached before the buffer is filled.
+fn read_exact_or_eof(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<bool> {
+    match reader.read_exact(buf) {
+        Ok(()) => Ok(true),
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+/// Escapes the stream name to be a valid and unique file name.
+fn file_name(stream: &str, extension: &str) -> PathBuf {
+    let mut name = String::with_capacity(stream.len() + 8);
+
+    for byte in stream.bytes() {
+        if byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_' {
+            name.push(byte as char);
+        } else {
+            let _ = write!(name, "%{byte:02X}");
+        }
+    }
+
+    name.push('.');
+    name.push_str(extension);
+    name.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = FileJournal::new(dir.path()).unwrap().sync(false);
+
+        assert!(journal.read("a/1", 0).unwrap().is_empty());
+        journal.append("a/1", 1, b"one").unwrap();
+        journal.append("a/1", 2, b"two").unwrap();
+        journal.append("a/2", 1, b"other").unwrap();
+
+        let events = journal.read("a/1", 0).unwrap();
+        assert_eq!(events, vec![(1, b"one".to_vec()), (2, b"two".to_vec())]);
+        assert_eq!(journal.read("a/1", 1).unwrap(), vec![(2, b"two".to_vec())]);
+        assert_eq!(
+            journal.read("a/2", 0).unwrap(),
+            vec![(1, b"other".to_vec())]
+        );
+
+        // Simulate a torn write.
+        let path = journal.path("a/1");
+        drop(journal);
+        OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(&[3, 0, 0])
+            .unwrap();
+
+        let journal = FileJournal::new(dir.path()).unwrap().sync(false);
+        assert_eq!(journal.read("a/1", 0).unwrap().len(), 2);
+        journal.append("a/1", 3, b"three").unwrap();
+        assert_eq!(
+            journal.read("a/1", 2).unwrap(),
+            vec![(3, b"three".to_vec())]
+        );
+    }
+
+    #[test]
+    fn corrupted_records() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = FileJournal::new(dir.path()).unwrap().sync(false);
+        journal.append("a", 1, b"one").unwrap();
+        journal.append("a", 2, b"two").unwrap();
+        journal.append("a", 3, b"three").unwrap();
+
+        let path = journal.path("a");
+        let record_len = (HEADER_SIZE + 3) as u64;
+        drop(journal);
+
+        let corrupt = |offset: u64, bytes: &[u8]| {
+            let mut file = OpenOptions::new().write(true).open(&path).unwrap();
+            file.seek(SeekFrom::Start(offset)).unwrap();
+            file.write_all(bytes).unwrap();
+        };
+
+        // A corrupted payload ends the valid prefix.
+        corrupt(2 * record_len - 1, b"X");
+        let journal = FileJournal::new(dir.path()).unwrap().sync(false);
+        assert_eq!(journal.read("a", 0).unwrap(), vec![(1, b"one".to_vec())]);
+        drop(journal);
+
+        // A huge length isn't allocated.
+        corrupt(8, &u32::MAX.to_le_bytes());
+        let journal = FileJournal::new(dir.path()).unwrap().sync(false);
+        assert!(journal.read("a", 0).unwrap().is_empty());
+
+        // The corrupted part is cut off by the next append.
+        journal.append("a", 1, b"uno").unwrap();
+        assert_eq!(journal.read("a", 0).unwrap(), vec![(1, b"uno".to_vec())]);
+        assert_eq!(fs::metadata(&path).unwrap().len(), record_len);
+
+        let too_large = vec![0; FileJournal::MAX_EVENT_SIZE + 1];
+        assert!(journal.append("a", 2, &too_large).is_err());
+    }
+
+    #[test]
+    fn failed_append() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = FileJournal::new(dir.path()).unwrap().sync(false);
+        journal.append("a", 1, b"one").unwrap();
+
+        // Writing to a read-only file fails.
+        let file = File::open(journal.path("a")).unwrap();
+        journal.files.lock().insert("a".into(), file);
+        assert!(journal.append("a", 2, b"two").is_err());
+
+        journal.append("a", 2, b"two").unwrap();
+        assert_eq!(
+            journal.read("a", 0).unwrap(),
+            vec![(1, b"one".to_vec()), (2, b"two".to_vec())]
+        );
+    }
+
+    #[test]
+    fn snapshots() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = FileJournal::new(dir.path()).unwrap().sync(false);
+
+        assert_eq!(journal.load_snapshot("a").unwrap(), None);
+        for seq_no in 1..=5 {
+            journal.append("a", seq_no, &[seq_no as u8]).unwrap();
+        }
+
+        journal.save_snapshot("a", 3, b"state").unwrap();
+        journal.prune("a", 3).unwrap();
+        assert_eq!(
+            journal.load_snapshot("a").unwrap(),
+            Some((3, b"state".to_vec()))
+        );
+        assert_eq!(
+            journal.read("a", 0).unwrap(),
+            vec![(4, vec![4]), (5, vec![5])]
+        );
+
+        // Appending after pruning.
+        journal.append("a", 6, &[6]).unwrap();
+        assert_eq!(journal.read("a", 5).unwrap(), vec![(6, vec![6])]);
+    }
+
+    #[test]
+    fn file_name_escaping() {
+        assert_eq!(
+            file_name("group/key", "journal"),
+            Path::new("group%2Fkey.journal")
+        );
+        assert_eq!(file_name("a_b-c", "snapshot"), Path::new("a_b-c.snapshot"));
+    }
+}
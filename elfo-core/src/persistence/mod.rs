@@ -0,0 +1,450 @@
+//! Event sourcing: actors persist events to a journal and replay them
+//! on start, so a restarted actor recovers its state.
+//!
+//! The journal is abstracted by the [`Journal`] trait. [`FileJournal`] is
+//! provided as a simple backend storing every stream in a separate file.
+//! `RocksDbJournal` stores all streams in a RocksDB database and is available
+//! with the `rocksdb` feature. Other backends can be implemented outside elfo.
+//!
+//! Events are encoded as JSON.
+//!
+//! States implementing [`Snapshot`] are periodically snapshotted to bound
+//! replay time, see [`EventLog::recover_with_snapshots()`] and
+//! `system.persistence` in the config.
+//!
+//! Logs can be recovered by actors manually as shown below or by the
+//! supervisor before actors start, see [`ActorGroup::event_sourced()`].
+//!
+//! [`ActorGroup::event_sourced()`]: crate::ActorGroup::event_sourced
+//!
+//! # Example
+//! ```
+//! # use elfo_core as elfo;
+//! # async fn exec(mut ctx: elfo::Context, journal: std::sync::Arc<dyn elfo::persistence::Journal>) {
+//! # use elfo::{message, msg};
+//! use elfo::persistence::{EventLog, EventSourced};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Default)]
+//! struct Counter(u64);
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Incremented(u64);
+//!
+//! impl EventSourced for Counter {
+//!     type Event = Incremented;
+//!
+//!     fn apply(&mut self, event: &Incremented) {
+//!         self.0 += event.0;
+//!     }
+//! }
+//!
+//! #[message]
+//! struct Increment(u64);
+//!
+//! // The stream is bound to the actor's group and key,
+//! // so the state is recovered after restarts.
+//! let mut counter = EventLog::<Counter>::recover(journal).await.unwrap();
+//!
+//! while let Some(envelope) = ctx.recv().await {
+//!     msg!(match envelope {
+//!         Increment(n) => counter.persist(Incremented(n)).await.unwrap(),
+//!     });
+//! }
+//! # }
+//! ```
+
+use std::{
+    any::Any,
+    fmt, io,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use futures::future::BoxFuture;
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::sync::oneshot;
+use tracing::warn;
+
+use crate::scope;
+
+use self::config::PersistenceConfig;
+pub use self::file::FileJournal;
+#[cfg(feature = "rocksdb")]
+pub use self::rocksdb::RocksDbJournal;
+
+mod file;
+#[cfg(feature = "rocksdb")]
+mod rocksdb;
+
+// === PersistenceConfig ===
+
+pub mod config {
+    //! [Config]
+    //!
+    //! [Config]: PersistenceConfig
+
+    use serde::Deserialize;
+
+    /// Persistence configuration.
+    ///
+    /// # Example
+    /// ```toml
+    /// [some_group]
+    /// system.persistence.snapshot_interval = 500
+    /// ```
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(default)]
+    pub struct PersistenceConfig {
+        /// How many events are persisted between snapshots.
+        /// `0` disables periodic snapshots.
+        ///
+        /// Used only by logs opened with snapshots, see
+        /// [`EventLog::recover_with_snapshots()`].
+        ///
+        /// `1000` by default.
+        ///
+        /// [`EventLog::recover_with_snapshots()`]: super::EventLog::recover_with_snapshots
+        pub snapshot_interval: u64,
+    }
+
+    impl Default for PersistenceConfig {
+        fn default() -> Self {
+            Self {
+                snapshot_interval: 1000,
+            }
+        }
+    }
+}
+
+/// Persistence settings shared by all actors of the same group.
+pub(crate) struct PersistenceControl {
+    snapshot_interval: AtomicU64,
+}
+
+impl Default for PersistenceControl {
+    fn default() -> Self {
+        Self {
+            snapshot_interval: AtomicU64::new(PersistenceConfig::default().snapshot_interval),
+        }
+    }
+}
+
+impl PersistenceControl {
+    pub(crate) fn configure(&self, config: &PersistenceConfig) {
+        self.snapshot_interval
+            .store(config.snapshot_interval, Ordering::Relaxed);
+    }
+
+    fn snapshot_interval(&self) -> u64 {
+        self.snapshot_interval.load(Ordering::Relaxed)
+    }
+}
+
+// === Journal ===
+
+/// A storage of events, divided into streams.
+///
+/// Every event in a stream has a sequence number, which is unique and
+/// increasing inside the stream.
+///
+/// Methods are blocking, they're called on a dedicated thread pool.
+pub trait Journal: Send + Sync + 'static {
+    /// Appends an event to the end of the stream.
+    fn append(&self, stream: &str, seq_no: u64, event: &[u8]) -> io::Result<()>;
+
+    /// Reads all events of the stream with sequence numbers greater than
+    /// `after` in order of sequence numbers.
+    fn read(&self, stream: &str, after: u64) -> io::Result<Vec<(u64, Vec<u8>)>>;
+
+    /// Stores a snapshot of the stream's state after the event `seq_no`,
+    /// replacing the previous one.
+    ///
+    /// Journals don't support snapshots by default.
+    fn save_snapshot(&self, stream: &str, seq_no: u64, snapshot: &[u8]) -> io::Result<()> {
+        let _ = (stream, seq_no, snapshot);
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "snapshots are not supported",
+        ))
+    }
+
+    /// Loads the last snapshot of the stream along with its sequence number.
+    fn load_snapshot(&self, stream: &str) -> io::Result<Option<(u64, Vec<u8>)>> {
+        let _ = stream;
+        Ok(None)
+    }
+
+    /// Removes events with sequence numbers up to `seq_no` (inclusive).
+    /// Called after a snapshot is saved, so these events aren't needed
+    /// anymore.
+    ///
+    /// It's allowed to keep some events, they're skipped on replay.
+    fn prune(&self, stream: &str, seq_no: u64) -> io::Result<()> {
+        let _ = (stream, seq_no);
+        Ok(())
+    }
+}
+
+// === EventSourced ===
+
+/// A state that is built by applying events.
+pub trait EventSourced: Default + Send + 'static {
+    /// A type of events changing the state.
+    type Event: Serialize + DeserializeOwned + Send + 'static;
+
+    /// Applies the event to the state.
+    ///
+    /// It's called both for new events and during replay, so it mustn't
+    /// have side effects.
+    fn apply(&mut self, event: &Self::Event);
+}
+
+// === Snapshot ===
+
+/// A state that can be snapshotted, so only events after the last snapshot
+/// are replayed on recovery.
+///
+/// Snapshots are encoded as JSON.
+pub trait Snapshot: EventSourced + Serialize + DeserializeOwned {}
+
+// === EventLog ===
+
+/// The state of an actor along with its stream in the journal.
+pub struct EventLog<S> {
+    journal: Arc<dyn Journal>,
+    stream: String,
+    seq_no: u64,
+    state: S,
+    snapshots: Option<Snapshots<S>>,
+}
+
+struct Snapshots<S> {
+    encode: fn(&S) -> serde_json::Result<Vec<u8>>,
+    seq_no: u64,
+}
+
+impl<S: EventSourced> EventLog<S> {
+    /// Replays the stream bound to the current actor (`<group>/<key>`),
+    /// starting with the default state.
+    ///
+    /// # Panics
+    /// If called outside the actor system.
+    pub async fn recover(journal: Arc<dyn Journal>) -> io::Result<Self> {
+        Self::open(journal, actor_stream()).await
+    }
+
+    /// Replays the provided stream, starting with the default state.
+    pub async fn open(journal: Arc<dyn Journal>, stream: impl Into<String>) -> io::Result<Self> {
+        let mut this = Self {
+            journal,
+            stream: stream.into(),
+            seq_no: 0,
+            state: S::default(),
+            snapshots: None,
+        };
+
+        this.replay().await?;
+        Ok(this)
+    }
+
+    /// Returns the current state.
+    #[inline]
+    pub fn state(&self) -> &S {
+        &self.state
+    }
+
+    /// Returns the name of the stream in the journal.
+    #[inline]
+    pub fn stream(&self) -> &str {
+        &self.stream
+    }
+
+    /// Returns the sequence number of the last applied event,
+    /// `0` if there are no events.
+    #[inline]
+    pub fn seq_no(&self) -> u64 {
+        self.seq_no
+    }
+
+    /// Persists the event to the journal and then applies it to the state.
+    ///
+    /// If persisting fails, the state isn't changed.
+    pub async fn persist(&mut self, event: S::Event) -> io::Result<()> {
+        let data = serde_json::to_vec(&event).map_err(invalid_data)?;
+        let seq_no = self.seq_no + 1;
+
+        let journal = self.journal.clone();
+        let stream = self.stream.clone();
+        blocking(move || journal.append(&stream, seq_no, &data)).await?;
+
+        self.state.apply(&event);
+        self.seq_no = seq_no;
+
+        if self.is_snapshot_needed() {
+            if let Err(error) = self.snapshot().await {
+                warn!(stream = %self.stream, %error, "cannot save a snapshot");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Saves a snapshot of the current state and prunes the journal.
+    ///
+    /// Does nothing if the log is opened without snapshots.
+    pub async fn snapshot(&mut self) -> io::Result<()> {
+        let snapshots = ward!(self.snapshots.as_mut(), return Ok(()));
+        let data = (snapshots.encode)(&self.state).map_err(invalid_data)?;
+
+        let journal = self.journal.clone();
+        let stream = self.stream.clone();
+        let seq_no = self.seq_no;
+        blocking(move || {
+            journal.save_snapshot(&stream, seq_no, &data)?;
+            journal.prune(&stream, seq_no)
+        })
+        .await?;
+
+        snapshots.seq_no = seq_no;
+        Ok(())
+    }
+
+    fn is_snapshot_needed(&self) -> bool {
+        let snapshots = ward!(self.snapshots.as_ref(), return false);
+        let interval = scope::try_with(|scope| scope.persistence().snapshot_interval())
+            .unwrap_or_else(|| PersistenceConfig::default().snapshot_interval);
+
+        interval > 0 && self.seq_no - snapshots.seq_no >= interval
+    }
+
+    async fn replay(&mut self) -> io::Result<()> {
+        let journal = self.journal.clone();
+        let stream = self.stream.clone();
+        let after = self.seq_no;
+        let events = blocking(move || journal.read(&stream, after)).await?;
+
+        for (seq_no, data) in events {
+            let event = serde_json::from_slice(&data).map_err(invalid_data)?;
+            self.state.apply(&event);
+            self.seq_no = seq_no;
+        }
+
+        Ok(())
+    }
+}
+
+impl<S: Snapshot> EventLog<S> {
+    /// Like [`EventLog::recover()`], but starts with the last snapshot
+    /// and takes new ones periodically, see `system.persistence` in the config.
+    ///
+    /// # Panics
+    /// If called outside the actor system.
+    pub async fn recover_with_snapshots(journal: Arc<dyn Journal>) -> io::Result<Self> {
+        Self::open_with_snapshots(journal, actor_stream()).await
+    }
+
+    /// Like [`EventLog::open()`], but starts with the last snapshot
+    /// and takes new ones periodically, see `system.persistence` in the config.
+    pub async fn open_with_snapshots(
+        journal: Arc<dyn Journal>,
+        stream: impl Into<String>,
+    ) -> io::Result<Self> {
+        let stream = stream.into();
+
+        let j = journal.clone();
+        let s = stream.clone();
+        let snapshot = blocking(move || j.load_snapshot(&s)).await?;
+
+        let (seq_no, state) = match snapshot {
+            Some((seq_no, data)) => (seq_no, serde_json::from_slice(&data).map_err(invalid_data)?),
+            None => (0, S::default()),
+        };
+
+        let mut this = Self {
+            journal,
+            stream,
+            seq_no,
+            state,
+            snapshots: Some(Snapshots {
+                encode: serde_json::to_vec::<S>,
+                seq_no,
+            }),
+        };
+
+        this.replay().await?;
+        Ok(this)
+    }
+}
+
+// === Recovery ===
+
+type RecoverFn = dyn Fn() -> BoxFuture<'static, io::Result<Box<dyn Any + Send>>> + Send + Sync;
+
+/// Recovers logs of actors before their execs start,
+/// see [`ActorGroup::event_sourced()`].
+///
+/// [`ActorGroup::event_sourced()`]: crate::ActorGroup::event_sourced
+#[derive(Clone)]
+pub(crate) struct Recovery(Arc<RecoverFn>);
+
+impl Recovery {
+    pub(crate) fn new<S: EventSourced>(journal: Arc<dyn Journal>) -> Self {
+        Self(Arc::new(move || {
+            let journal = journal.clone();
+            Box::pin(async move {
+                let log = EventLog::<S>::recover(journal).await?;
+                Ok(Box::new(log) as Box<dyn Any + Send>)
+            })
+        }))
+    }
+
+    pub(crate) fn with_snapshots<S: Snapshot>(journal: Arc<dyn Journal>) -> Self {
+        Self(Arc::new(move || {
+            let journal = journal.clone();
+            Box::pin(async move {
+                let log = EventLog::<S>::recover_with_snapshots(journal).await?;
+                Ok(Box::new(log) as Box<dyn Any + Send>)
+            })
+        }))
+    }
+
+    /// Returns a type-erased `EventLog<S>` of the current actor.
+    pub(crate) async fn recover(&self) -> io::Result<Box<dyn Any + Send>> {
+        (self.0)().await
+    }
+}
+
+impl fmt::Debug for Recovery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Recovery").finish_non_exhaustive()
+    }
+}
+
+fn actor_stream() -> String {
+    let meta = scope::with(|scope| scope.meta().clone());
+    format!("{}/{}", meta.group, meta.key)
+}
+
+/// Runs the function on the blocking pool of the actor's runtime.
+async fn blocking<R: Send + 'static>(
+    f: impl FnOnce() -> io::Result<R> + Send + 'static,
+) -> io::Result<R> {
+    let (tx, rx) = oneshot::channel();
+    scope::runtime().spawn_blocking(Box::new(move || {
+        let _ = tx.send(panic::catch_unwind(AssertUnwindSafe(f)));
+    }));
+
+    match rx.await {
+        Ok(Ok(result)) => result,
+        Ok(Err(payload)) => panic::resume_unwind(payload),
+        Err(err) => Err(io::Error::other(err)),
+    }
+}
+
+fn invalid_data(err: serde_json::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
@@ -0,0 +1,168 @@
+use std::{io, path::Path};
+
+use rocksdb::{Direction, IteratorMode, Options, WriteBatch, WriteOptions, DB};
+
+use super::Journal;
+
+/// A journal storing all streams in a RocksDB database.
+///
+/// Events are stored by `e | stream_len: u32 (BE) | stream | seq_no: u64 (BE)`
+/// keys, so events of a stream are adjacent and ordered by sequence numbers.
+///
+/// Snapshots are stored by `s | stream_len: u32 (BE) | stream` keys as
+/// `seq_no: u64 (LE) | snapshot`.
+#[cfg_attr(docsrs, doc(cfg(feature = "rocksdb")))]
+pub struct RocksDbJournal {
+    db: DB,
+    sync: bool,
+}
+
+impl RocksDbJournal {
+    /// Opens a database in the provided directory, creating it if needed.
+    pub fn new(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut options = Options::default();
+        options.create_if_missing(true);
+
+        Ok(Self {
+            db: DB::open(&options, path).map_err(io::Error::other)?,
+            sync: true,
+        })
+    }
+
+    /// Whether to sync the WAL after every write.
+    ///
+    /// `true` by default.
+    pub fn sync(mut self, sync: bool) -> Self {
+        self.sync = sync;
+        self
+    }
+
+    fn write_options(&self) -> WriteOptions {
+        let mut options = WriteOptions::default();
+        options.set_sync(self.sync);
+        options
+    }
+}
+
+impl Journal for RocksDbJournal {
+    fn append(&self, stream: &str, seq_no: u64, event: &[u8]) -> io::Result<()> {
+        let key = event_key(stream, seq_no);
+        self.db
+            .put_opt(key, event, &self.write_options())
+            .map_err(io::Error::other)
+    }
+
+    fn read(&self, stream: &str, after: u64) -> io::Result<Vec<(u64, Vec<u8>)>> {
+        let Some(from) = after.checked_add(1) else {
+            return Ok(Vec::new());
+        };
+
+        let prefix = key_prefix(b'e', stream);
+        let from = event_key(stream, from);
+        let mut events = Vec::new();
+
+        for item in self
+            .db
+            .iterator(IteratorMode::From(&from, Direction::Forward))
+        {
+            let (key, event) = item.map_err(io::Error::other)?;
+            let Some(seq_no) = key.strip_prefix(prefix.as_slice()) else {
+                break;
+            };
+
+            let seq_no = seq_no.try_into().map_err(|_| corrupted("invalid key"))?;
+            events.push((u64::from_be_bytes(seq_no), event.into_vec()));
+        }
+
+        Ok(events)
+    }
+
+    fn save_snapshot(&self, stream: &str, seq_no: u64, snapshot: &[u8]) -> io::Result<()> {
+        let mut value = Vec::with_capacity(8 + snapshot.len());
+        value.extend_from_slice(&seq_no.to_le_bytes());
+        value.extend_from_slice(snapshot);
+
+        self.db
+            .put_opt(key_prefix(b's', stream), value, &self.write_options())
+            .map_err(io::Error::other)
+    }
+
+    fn load_snapshot(&self, stream: &str) -> io::Result<Option<(u64, Vec<u8>)>> {
+        let key = key_prefix(b's', stream);
+        let Some(mut data) = self.db.get(key).map_err(io::Error::other)? else {
+            return Ok(None);
+        };
+
+        if data.len() < 8 {
+            return Err(corrupted("corrupted snapshot"));
+        }
+
+        let seq_no = u64::from_le_bytes(data[..8].try_into().unwrap());
+        data.drain(..8);
+        Ok(Some((seq_no, data)))
+    }
+
+    fn prune(&self, stream: &str, seq_no: u64) -> io::Result<()> {
+        // The end of a range is exclusive, so the last event is deleted apart.
+        let mut batch = WriteBatch::default();
+        batch.delete_range(event_key(stream, 0), event_key(stream, seq_no));
+        batch.delete(event_key(stream, seq_no));
+
+        self.db
+            .write_opt(batch, &self.write_options())
+            .map_err(io::Error::other)
+    }
+}
+
+fn key_prefix(kind: u8, stream: &str) -> Vec<u8> {
+    let mut key = Vec::with_capacity(1 + 4 + stream.len() + 8);
+    key.push(kind);
+    key.extend_from_slice(&(stream.len() as u32).to_be_bytes());
+    key.extend_from_slice(stream.as_bytes());
+    key
+}
+
+fn event_key(stream: &str, seq_no: u64) -> Vec<u8> {
+    let mut key = key_prefix(b'e', stream);
+    key.extend_from_slice(&seq_no.to_be_bytes());
+    key
+}
+
+fn corrupted(reason: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = RocksDbJournal::new(dir.path()).unwrap().sync(false);
+
+        assert!(journal.read("a", 0).unwrap().is_empty());
+        for seq_no in 1..=5 {
+            journal.append("a", seq_no, &[seq_no as u8]).unwrap();
+        }
+        journal.append("ab", 1, b"other").unwrap();
+
+        assert_eq!(
+            journal.read("a", 3).unwrap(),
+            vec![(4, vec![4]), (5, vec![5])]
+        );
+        assert_eq!(journal.read("ab", 0).unwrap(), vec![(1, b"other".to_vec())]);
+
+        assert_eq!(journal.load_snapshot("a").unwrap(), None);
+        journal.save_snapshot("a", 3, b"state").unwrap();
+        journal.prune("a", 3).unwrap();
+        assert_eq!(
+            journal.load_snapshot("a").unwrap(),
+            Some((3, b"state".to_vec()))
+        );
+        assert_eq!(
+            journal.read("a", 0).unwrap(),
+            vec![(4, vec![4]), (5, vec![5])]
+        );
+    }
+}
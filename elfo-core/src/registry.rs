@@ -0,0 +1,131 @@
+//! Types of messages: looked up by protocol and name at runtime.
+//!
+//! All messages defined by `#[message]` are registered at link time, so a
+//! generic gateway can turn incoming data (e.g. JSON from HTTP or WebSocket
+//! bridges) into typed messages and route them, without matching over every
+//! message type. See also [`schema`](crate::schema) to describe messages.
+//!
+//! Such data is usually untrusted, so only protocols explicitly allowed in
+//! [`Registry`] are looked up. Internal messages of elfo (e.g. `Terminate`
+//! or `UpdateConfig`) are never looked up, even if their protocol is allowed.
+//!
+//! # Example
+//! ```
+//! # use elfo_core as elfo;
+//! # async fn exec(ctx: elfo::Context, addr: elfo::Addr) {
+//! use elfo::{message, registry::Registry};
+//!
+//! #[message(protocol = "shop")]
+//! struct AddItem {
+//!     id: u32,
+//! }
+//!
+//! let registry = Registry::new().allow_protocol("shop");
+//!
+//! // E.g. received by an HTTP bridge.
+//! let body = serde_json::json!({ "id": 42 });
+//!
+//! let message = registry
+//!     .lookup("shop", "AddItem")
+//!     .expect("unknown message")
+//!     .deserialize(body)
+//!     .expect("invalid message");
+//!
+//! assert!(message.is::<AddItem>());
+//! let _ = ctx.send_to(addr, message).await;
+//! # }
+//! ```
+
+use std::fmt;
+
+use serde::Deserializer;
+
+use crate::{
+    message::{AnyMessage, MessageVTable, MESSAGE_VTABLES_LIST},
+    schema::{self, MessageSchema},
+};
+
+/// The protocol of internal messages, which are never looked up.
+const INTERNAL_PROTOCOL: &str = "elfo-core";
+
+/// A set of protocols, which message types are looked up in.
+#[derive(Debug, Clone, Default)]
+pub struct Registry {
+    protocols: Vec<String>,
+}
+
+impl Registry {
+    /// Creates a registry without allowed protocols.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allows looking up message types of the protocol.
+    ///
+    /// Internal messages of elfo are never looked up anyway.
+    pub fn allow_protocol(mut self, protocol: impl Into<String>) -> Self {
+        self.protocols.push(protocol.into());
+        self
+    }
+
+    /// Returns all message types of allowed protocols.
+    pub fn all(&self) -> impl Iterator<Item = MessageType> + '_ {
+        MESSAGE_VTABLES_LIST
+            .iter()
+            .filter(|vtable| self.is_allowed(vtable.protocol()))
+            .map(|vtable| MessageType { vtable })
+    }
+
+    /// Returns the message type with the provided protocol and name
+    /// if the protocol is allowed.
+    pub fn lookup(&self, protocol: &str, name: &str) -> Option<MessageType> {
+        if !self.is_allowed(protocol) {
+            return None;
+        }
+
+        MessageVTable::lookup(protocol, name).map(|vtable| MessageType { vtable })
+    }
+
+    fn is_allowed(&self, protocol: &str) -> bool {
+        protocol != INTERNAL_PROTOCOL && self.protocols.iter().any(|p| p == protocol)
+    }
+}
+
+/// A registered message type.
+#[derive(Clone, Copy)]
+pub struct MessageType {
+    vtable: &'static MessageVTable,
+}
+
+impl MessageType {
+    /// The protocol of the message.
+    pub fn protocol(&self) -> &'static str {
+        self.vtable.protocol()
+    }
+
+    /// The name of the message.
+    pub fn name(&self) -> &'static str {
+        self.vtable.name()
+    }
+
+    /// The schema of the message, only for messages with `#[message(schema)]`.
+    pub fn schema(&self) -> Option<&'static MessageSchema> {
+        schema::lookup(self.protocol(), self.name())
+    }
+
+    /// Deserializes a message of this type, e.g. from `serde_json::Value`.
+    ///
+    /// Only the message's data is expected, without protocol and name.
+    pub fn deserialize<'de, D>(&self, deserializer: D) -> Result<AnyMessage, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        AnyMessage::deserialize_with(self.vtable, deserializer)
+    }
+}
+
+impl fmt::Debug for MessageType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.protocol(), self.name())
+    }
+}
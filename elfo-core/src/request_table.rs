@@ -7,8 +7,14 @@ use smallvec::SmallVec;
 use tokio::sync::Notify;
 
 use crate::{
-    address_book::AddressBook, envelope::Envelope, errors::RequestError, message::AnyMessage,
-    tracing::TraceId, Addr,
+    address_book::AddressBook,
+    context,
+    envelope::Envelope,
+    errors::RequestError,
+    message::{AnyMessage, Request},
+    scope::{self, Scope},
+    tracing::TraceId,
+    Addr,
 };
 
 // === RequestId ===
@@ -297,6 +303,32 @@ impl<R> ResponseToken<R> {
     pub fn is_forgotten(&self) -> bool {
         self.data.is_none()
     }
+
+    /// Converts the token into [`DetachedResponseToken`], which can be
+    /// responded to without the context, e.g. from a spawned task.
+    ///
+    /// # Panics
+    /// If called outside the actor system.
+    pub fn detach(self) -> DetachedResponseToken<R> {
+        DetachedResponseToken {
+            token: self,
+            scope: scope::expose(),
+        }
+    }
+
+    /// Used for forwarding requests to other actors, so the token isn't
+    /// considered received until the next recipient gets it.
+    pub(crate) fn into_forwarded(mut self) -> ResponseToken {
+        ResponseToken {
+            data: self.data.take(),
+            received: false,
+            marker: PhantomData,
+        }
+    }
+
+    pub(crate) fn book(&self) -> Option<&AddressBook> {
+        self.data.as_ref().map(|data| &data.book)
+    }
 }
 
 impl<T> Drop for ResponseToken<T> {
@@ -328,6 +360,72 @@ impl<T> fmt::Debug for ResponseToken<T> {
     }
 }
 
+// === DetachedResponseToken ===
+
+/// A response token detached from the actor's context by
+/// [`ResponseToken::detach()`].
+///
+/// It can be stored or moved into a spawned task and responded to much later,
+/// without blocking the actor loop. The response is sent on behalf of the
+/// actor that has detached the token, inside its scope (e.g. for logging).
+///
+/// Like [`ResponseToken`], if it's dropped without responding, the requester
+/// receives [`RequestError::Failed`].
+///
+/// # Example
+/// ```
+/// # use elfo_core as elfo;
+/// # async fn exec(mut ctx: elfo::Context) {
+/// # use elfo::{message, msg};
+/// #[message(ret = u64)]
+/// struct Compute(u64);
+///
+/// while let Some(envelope) = ctx.recv().await {
+///     msg!(match envelope {
+///         (Compute(n), token) => {
+///             let token = token.detach();
+///             tokio::spawn(async move {
+///                 // Some long computation.
+///                 token.respond(n * 2);
+///             });
+///         }
+///     });
+/// }
+/// # }
+/// ```
+#[must_use]
+pub struct DetachedResponseToken<T = AnyMessage> {
+    token: ResponseToken<T>,
+    scope: Scope,
+}
+
+assert_impl_all!(DetachedResponseToken: Send);
+
+impl<R: Request> DetachedResponseToken<R> {
+    /// Responds to the requester with the provided response.
+    pub fn respond(self, message: R::Response) {
+        let responder = self.scope.actor();
+        let token = self.token;
+
+        self.scope
+            .sync_within(|| context::respond_detached(responder, token, message));
+    }
+}
+
+impl<T> DetachedResponseToken<T> {
+    /// Returns `true` if the response isn't needed.
+    #[inline]
+    pub fn is_forgotten(&self) -> bool {
+        self.token.is_forgotten()
+    }
+}
+
+impl<T> fmt::Debug for DetachedResponseToken<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DetachedResponseToken").finish()
+    }
+}
+
 #[cfg(test)]
 #[cfg(TODO)]
 mod tests {
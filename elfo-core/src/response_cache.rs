@@ -0,0 +1,231 @@
+//! Contains `ResponseCaches` that answer repeated requests without reaching
+//! actors. See [`ActorGroup::cache_responses()`] for details.
+//!
+//! [`ActorGroup::cache_responses()`]: crate::ActorGroup::cache_responses()
+
+use std::{
+    any::{Any, TypeId},
+    fmt,
+    hash::Hash,
+    time::Duration,
+};
+
+use fxhash::FxHashMap;
+use idr_ebr::EbrGuard;
+use parking_lot::Mutex;
+use tokio::time::Instant;
+use tracing::trace;
+
+use crate::{
+    address_book::AddressBook,
+    envelope::{Envelope, MessageKind},
+    message::{Message, Request},
+    request_table::{RequestId, ResponseToken},
+    Addr,
+};
+
+/// The maximum number of responses and pending requests in one cache.
+const MAX_ENTRIES: usize = 4096;
+
+/// Caches of responses to requests of different types, one per group.
+#[derive(Default)]
+pub(crate) struct ResponseCaches {
+    caches: Vec<Box<dyn AnyCache>>,
+}
+
+impl ResponseCaches {
+    /// Adds a cache for requests of the type `Q`, replacing the previous one.
+    pub(crate) fn add<Q, K>(&mut self, ttl: Duration, key: impl Fn(&Q) -> K + Send + Sync + 'static)
+    where
+        Q: Request,
+        K: Hash + Eq + Send + 'static,
+    {
+        let cache = Cache {
+            ttl,
+            key: Box::new(key),
+            inner: Mutex::new(CacheInner {
+                entries: FxHashMap::default(),
+                pending: FxHashMap::default(),
+                next_cleanup: Instant::now() + ttl,
+            }),
+        };
+
+        self.caches
+            .retain(|c| c.request_type() != TypeId::of::<Q>());
+        self.caches.push(Box::new(cache));
+    }
+
+    /// Responds to the request from the cache if possible.
+    /// Otherwise, returns the envelope back to be handled by actors.
+    pub(crate) fn try_respond(
+        &self,
+        envelope: Envelope,
+        book: &AddressBook,
+        responder: Addr,
+    ) -> Result<(), Envelope> {
+        if self.caches.is_empty() || request_token(envelope.message_kind()).is_none() {
+            return Err(envelope);
+        }
+
+        let mut envelope = envelope;
+        for cache in &self.caches {
+            envelope = match cache.try_respond(envelope, book, responder) {
+                Ok(()) => return Ok(()),
+                Err(envelope) => envelope,
+            };
+        }
+
+        Err(envelope)
+    }
+
+    /// Stores the response if the request is tracked by a cache.
+    pub(crate) fn on_response<Q: Request>(
+        &self,
+        recipient: Addr,
+        request_id: RequestId,
+        response: &Q::Wrapper,
+    ) {
+        for cache in &self.caches {
+            if cache.request_type() == TypeId::of::<Q>() {
+                cache.on_response(recipient, request_id, response);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for ResponseCaches {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResponseCaches")
+            .field("len", &self.caches.len())
+            .finish()
+    }
+}
+
+trait AnyCache: Send + Sync {
+    fn request_type(&self) -> TypeId;
+    fn try_respond(
+        &self,
+        envelope: Envelope,
+        book: &AddressBook,
+        responder: Addr,
+    ) -> Result<(), Envelope>;
+    fn on_response(&self, recipient: Addr, request_id: RequestId, response: &dyn Any);
+}
+
+struct Cache<Q: Request, K> {
+    ttl: Duration,
+    key: Box<dyn Fn(&Q) -> K + Send + Sync>,
+    inner: Mutex<CacheInner<Q, K>>,
+}
+
+struct CacheInner<Q: Request, K> {
+    entries: FxHashMap<K, (Instant, Q::Wrapper)>,
+    /// Requests passed to actors, whose responses should be cached.
+    pending: FxHashMap<(Addr, RequestId), (Instant, K)>,
+    next_cleanup: Instant,
+}
+
+impl<Q: Request, K: Hash + Eq> CacheInner<Q, K> {
+    /// Removes expired responses and requests that have not been answered
+    /// for too long (e.g. forwarded to other groups).
+    fn cleanup(&mut self, now: Instant, ttl: Duration) {
+        if now < self.next_cleanup {
+            return;
+        }
+
+        self.entries
+            .retain(|_, (at, _)| now.duration_since(*at) < ttl);
+        self.pending
+            .retain(|_, (at, _)| now.duration_since(*at) < ttl);
+        self.next_cleanup = now + ttl;
+    }
+}
+
+impl<Q, K> AnyCache for Cache<Q, K>
+where
+    Q: Request,
+    K: Hash + Eq + Send + 'static,
+{
+    fn request_type(&self) -> TypeId {
+        TypeId::of::<Q>()
+    }
+
+    fn try_respond(
+        &self,
+        envelope: Envelope,
+        book: &AddressBook,
+        responder: Addr,
+    ) -> Result<(), Envelope> {
+        let key = match envelope.message().downcast_ref::<Q>() {
+            Some(request) => (self.key)(request),
+            None => return Err(envelope),
+        };
+
+        let now = Instant::now();
+        let mut inner = self.inner.lock();
+        inner.cleanup(now, self.ttl);
+
+        let cached = inner
+            .entries
+            .get(&key)
+            .filter(|(at, _)| now.duration_since(*at) < self.ttl)
+            .map(|(_, response)| response.clone());
+
+        let Some(response) = cached else {
+            if let Some(token) = request_token(envelope.message_kind()) {
+                // Requests are still handled by actors, just not cached.
+                if inner.pending.len() < MAX_ENTRIES {
+                    let id = (token.sender(), token.request_id());
+                    inner.pending.insert(id, (now, key));
+                }
+            }
+            return Err(envelope);
+        };
+
+        drop(inner);
+
+        let (_, kind) = envelope.unpack::<Q>().expect("invalid message");
+        let MessageKind::RequestAny(token) = kind else {
+            unreachable!("checked in `ResponseCaches::try_respond()`");
+        };
+
+        respond(book, responder, token, response);
+        Ok(())
+    }
+
+    fn on_response(&self, recipient: Addr, request_id: RequestId, response: &dyn Any) {
+        let response = ward!(response.downcast_ref::<Q::Wrapper>());
+        let mut inner = self.inner.lock();
+        let (_, key) = ward!(inner.pending.remove(&(recipient, request_id)));
+        let now = Instant::now();
+        inner.cleanup(now, self.ttl);
+
+        if inner.entries.len() < MAX_ENTRIES || inner.entries.contains_key(&key) {
+            inner.entries.insert(key, (now, response.clone()));
+        }
+    }
+}
+
+/// Only `RequestAny` is cached: a cached response to `RequestAll` would
+/// answer the token once for the whole group instead of once per actor.
+fn request_token(kind: &MessageKind) -> Option<&ResponseToken> {
+    match kind {
+        MessageKind::RequestAny(token) if !token.is_forgotten() => Some(token),
+        _ => None,
+    }
+}
+
+fn respond<W: Message>(book: &AddressBook, responder: Addr, token: ResponseToken, message: W) {
+    let recipient = token.sender();
+    trace!(to = %recipient, "> {:?} (cached)", message);
+
+    let kind = MessageKind::Response {
+        sender: responder,
+        request_id: token.request_id(),
+    };
+
+    let envelope = Envelope::with_trace_id(message, kind, token.trace_id());
+    let guard = EbrGuard::new();
+    let object = ward!(book.get(recipient, &guard));
+    object.respond(token, Ok(envelope));
+}
@@ -1,7 +1,8 @@
 use std::time::Duration;
 
+use tokio::time::Instant;
+
 use crate::RestartParams;
-use elfo_utils::time::Instant;
 
 pub(crate) struct RestartBackoff {
     start_time: Instant,
@@ -57,48 +58,47 @@ impl RestartBackoff {
 
 #[cfg(test)]
 mod tests {
-    use elfo_utils::time;
     use std::num::NonZeroU64;
 
+    use tokio::time;
+
     use super::*;
 
-    #[test]
-    fn it_works() {
-        time::with_instant_mock(|mock| {
-            let mut backoff = RestartBackoff::default();
-            let params = RestartParams::new(Duration::from_secs(5), Duration::from_secs(30))
-                .max_retries(NonZeroU64::new(3).unwrap());
-            // Immediately failed.
-            assert_eq!(backoff.next(&params), Some(params.min_backoff));
-            mock.advance(params.min_backoff);
-            backoff.start();
-
-            // And again.
-            assert_eq!(backoff.next(&params), Some(2 * params.min_backoff));
-            mock.advance(2 * params.min_backoff);
-            backoff.start();
-
-            // After some, not enough to reset the backoff, time.
-            mock.advance(params.min_backoff * 2 / 3);
-            assert_eq!(backoff.next(&params), Some(4 * params.min_backoff));
-            mock.advance(3 * params.min_backoff);
-            backoff.start();
-
-            // After some, enough to reset the backoff, time.
-            mock.advance(params.min_backoff);
-            // The first retry.
-            assert_eq!(backoff.next(&params), Some(Duration::ZERO)); // resetted
-            backoff.start();
-
-            // After some, not enough to reset the backoff, time.
-            mock.advance(params.min_backoff * 2 / 3);
-            // The second retry.
-            assert_eq!(backoff.next(&params), Some(params.min_backoff));
-            // The third retry.
-            assert_eq!(backoff.next(&params), Some(2 * params.min_backoff));
-            // We reached the limit of reties.
-            assert_eq!(backoff.next(&params), None);
-        });
+    #[tokio::test(start_paused = true)]
+    async fn it_works() {
+        let mut backoff = RestartBackoff::default();
+        let params = RestartParams::new(Duration::from_secs(5), Duration::from_secs(30))
+            .max_retries(NonZeroU64::new(3).unwrap());
+        // Immediately failed.
+        assert_eq!(backoff.next(&params), Some(params.min_backoff));
+        time::advance(params.min_backoff).await;
+        backoff.start();
+
+        // And again.
+        assert_eq!(backoff.next(&params), Some(2 * params.min_backoff));
+        time::advance(2 * params.min_backoff).await;
+        backoff.start();
+
+        // After some, not enough to reset the backoff, time.
+        time::advance(params.min_backoff * 2 / 3).await;
+        assert_eq!(backoff.next(&params), Some(4 * params.min_backoff));
+        time::advance(3 * params.min_backoff).await;
+        backoff.start();
+
+        // After some, enough to reset the backoff, time.
+        time::advance(params.min_backoff).await;
+        // The first retry.
+        assert_eq!(backoff.next(&params), Some(Duration::ZERO)); // resetted
+        backoff.start();
+
+        // After some, not enough to reset the backoff, time.
+        time::advance(params.min_backoff * 2 / 3).await;
+        // The second retry.
+        assert_eq!(backoff.next(&params), Some(params.min_backoff));
+        // The third retry.
+        assert_eq!(backoff.next(&params), Some(2 * params.min_backoff));
+        // We reached the limit of reties.
+        assert_eq!(backoff.next(&params), None);
     }
 
     #[test]
@@ -1,36 +1,287 @@
-use std::sync::Arc;
+use std::{sync::Arc, thread, time::Duration};
 
-use tokio::runtime::Handle;
+use futures::future::BoxFuture;
+use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
+use tokio::{
+    runtime::{Builder, Handle},
+    sync::oneshot,
+};
 
+use self::affinity::ThreadRegistry;
 use crate::actor::ActorMeta;
 #[cfg(feature = "unstable-stuck-detection")]
 use crate::stuck_detection::StuckDetector;
 
+mod affinity;
+
 pub(crate) trait RuntimeFilter: Fn(&ActorMeta) -> bool + Send + Sync + 'static {}
 impl<F: Fn(&ActorMeta) -> bool + Send + Sync + 'static> RuntimeFilter for F {}
 
+// === Runtime ===
+
+/// An executor, which tasks of actors are spawned on, see
+/// [`Topology::add_dedicated_rt()`]. Tasks spawned by elfo on behalf of actors
+/// (e.g. HTTP connections of the configurer and the OTLP exporter, blocking
+/// work of dumpers and persistence journals) are also spawned on it, see
+/// [`scope::runtime()`]. Delays before restarting actors are awaited by
+/// [`Runtime::sleep()`].
+///
+/// It's implemented for [`tokio::runtime::Handle`], which is used by default.
+///
+/// The following still relies on drivers of tokio:
+/// * timers: time sources ([`Delay`], [`Interval`], [`Throttle`]) and timeouts
+///   of elfo, e.g. of the termination of groups;
+/// * IO: sockets of `elfo-network`, `elfo-admin`, `elfo-telemeter`, HTTP
+///   sources of the configurer and the OTLP exporter;
+/// * signals: [`Signal`].
+///
+/// So custom executors must poll tasks inside a context of a tokio runtime
+/// with enabled timers and IO, e.g. by entering a current-thread runtime on
+/// their threads. Task-locals, including the scope, don't depend on tokio's
+/// executor.
+///
+/// [`Topology::add_dedicated_rt()`]: crate::Topology::add_dedicated_rt
+/// [`scope::runtime()`]: crate::scope::runtime
+/// [`Delay`]: crate::time::Delay
+/// [`Interval`]: crate::time::Interval
+/// [`Throttle`]: crate::time::Throttle
+/// [`Signal`]: crate::signal::Signal
+#[stability::unstable]
+pub trait Runtime: Send + Sync + 'static {
+    /// Spawns a task, which must be polled until completion.
+    fn spawn(&self, task: BoxFuture<'static, ()>);
+
+    /// Runs a blocking function outside threads polling tasks.
+    ///
+    /// By default, it's run on a new thread.
+    fn spawn_blocking(&self, task: Box<dyn FnOnce() + Send>) {
+        thread::spawn(task);
+    }
+
+    /// Returns a future, which completes after the specified duration.
+    ///
+    /// By default, tokio's timer of the current context is used.
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+impl Runtime for Handle {
+    fn spawn(&self, task: BoxFuture<'static, ()>) {
+        // Tasks of actors are never joined.
+        drop(Handle::spawn(self, task));
+    }
+
+    fn spawn_blocking(&self, task: Box<dyn FnOnce() + Send>) {
+        drop(Handle::spawn_blocking(self, task));
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        // The timer is bound on creation, so use the one of this runtime.
+        let _guard = self.enter();
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+// === RuntimeConfig ===
+
+pub mod config {
+    //! [Config]
+    //!
+    //! [Config]: RuntimeConfig
+
+    use serde::Deserialize;
+
+    /// Configuration of a dedicated runtime of the group.
+    /// It's ignored for groups running on the shared runtime.
+    ///
+    /// See [`ActorGroup::dedicated_runtime()`] for details.
+    ///
+    /// # Example
+    /// ```toml
+    /// [some_group]
+    /// system.runtime.cpu_affinity = [2, 3]
+    /// ```
+    ///
+    /// [`ActorGroup::dedicated_runtime()`]: crate::ActorGroup::dedicated_runtime
+    #[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+    #[serde(default)]
+    pub struct RuntimeConfig {
+        /// CPU cores, which threads of the runtime are pinned to.
+        /// Supported only on Linux.
+        ///
+        /// `None` by default, threads can be scheduled on any core.
+        pub cpu_affinity: Option<Vec<usize>>,
+    }
+}
+
+// === DedicatedRuntime ===
+
+/// A tokio runtime dedicated to a group, see
+/// [`ActorGroup::dedicated_runtime()`] for details.
+///
+/// Multi-threaded runtimes require the `tokio-runtime` feature,
+/// which is enabled by default.
+///
+/// [`ActorGroup::dedicated_runtime()`]: crate::ActorGroup::dedicated_runtime
+#[derive(Debug, Clone)]
+pub struct DedicatedRuntime {
+    /// `None` means the current-thread runtime.
+    worker_threads: Option<usize>,
+}
+
+impl DedicatedRuntime {
+    /// A single-threaded runtime, driven by its own thread.
+    pub fn current_thread() -> Self {
+        Self {
+            worker_threads: None,
+        }
+    }
+
+    /// A multi-threaded runtime with the specified number of worker threads.
+    ///
+    /// # Panics
+    /// If `worker_threads` is zero.
+    #[cfg(feature = "tokio-runtime")]
+    #[track_caller]
+    pub fn multi_thread(worker_threads: usize) -> Self {
+        assert!(worker_threads > 0, "worker_threads must be positive");
+
+        Self {
+            worker_threads: Some(worker_threads),
+        }
+    }
+
+    /// Starts the runtime and returns its handle.
+    ///
+    /// The runtime is driven by a dedicated thread named after the group,
+    /// worker threads of the multi-threaded runtime are named the same.
+    /// It lives until the returned [`RuntimeShutdown`] is used or dropped,
+    /// e.g. when the group is unmounted.
+    pub(crate) fn start(&self, group: &str) -> (Handle, Arc<ThreadRegistry>, RuntimeShutdown) {
+        let threads = Arc::new(ThreadRegistry::new());
+        let mut builder = match self.worker_threads {
+            #[cfg(feature = "tokio-runtime")]
+            Some(worker_threads) => {
+                let mut builder = Builder::new_multi_thread();
+                builder.worker_threads(worker_threads);
+                builder
+            }
+            #[cfg(not(feature = "tokio-runtime"))]
+            Some(_) => unreachable!("constructed only with the `tokio-runtime` feature"),
+            None => Builder::new_current_thread(),
+        };
+
+        let threads1 = threads.clone();
+        let threads2 = threads.clone();
+        let threads3 = threads.clone();
+
+        let rt = builder
+            .thread_name(group)
+            .on_thread_start(move || threads1.register_current())
+            .on_thread_stop(move || threads2.unregister_current())
+            .enable_all()
+            .build()
+            .unwrap_or_else(|err| panic!("cannot build a runtime for `{group}`: {err}"));
+
+        let handle = rt.handle().clone();
+        let (stop_tx, stop_rx) = oneshot::channel();
+        let (stopped_tx, stopped_rx) = oneshot::channel();
+
+        thread::Builder::new()
+            .name(group.into())
+            .spawn(move || {
+                threads3.register_current();
+                let _ = rt.block_on(stop_rx);
+                // Waits for worker threads of the multi-threaded runtime.
+                drop(rt);
+                threads3.unregister_current();
+                let _ = stopped_tx.send(());
+            })
+            .unwrap_or_else(|err| panic!("cannot spawn a thread for `{group}`: {err}"));
+
+        let shutdown = RuntimeShutdown(Mutex::new(Some((stop_tx, stopped_rx))));
+        (handle, threads, shutdown)
+    }
+}
+
+/// Stops a dedicated runtime started by [`DedicatedRuntime::start()`].
+/// Dropping it also stops the runtime, but without waiting.
+pub(crate) struct RuntimeShutdown(Mutex<Option<(oneshot::Sender<()>, oneshot::Receiver<()>)>>);
+
+impl RuntimeShutdown {
+    /// Stops the runtime and waits until its threads exit.
+    /// Tasks still running on the runtime are dropped.
+    pub(crate) async fn shutdown(&self) {
+        let Some((stop_tx, stopped_rx)) = self.0.lock().take() else {
+            return;
+        };
+
+        let _ = stop_tx.send(());
+        let _ = stopped_rx.await;
+    }
+}
+
 // === RuntimeManager ===
 
 #[derive(Default, Clone)]
 pub(crate) struct RuntimeManager {
-    dedicated: Vec<(Arc<dyn RuntimeFilter>, Handle)>,
+    dedicated: Vec<(Arc<dyn RuntimeFilter>, Arc<dyn Runtime>)>,
+    /// Threads of the runtime set by `set_exclusive()`.
+    exclusive_threads: Option<Arc<ThreadRegistry>>,
+    /// Stops the runtime set by `set_exclusive()`.
+    exclusive_shutdown: Option<Arc<RuntimeShutdown>>,
+    /// The runtime used if no dedicated one matches, set on the first use.
+    default: OnceCell<Arc<dyn Runtime>>,
     #[cfg(feature = "unstable-stuck-detection")]
     stuck_detector: StuckDetector,
 }
 
 impl RuntimeManager {
-    pub(crate) fn add<F: RuntimeFilter>(&mut self, filter: F, handle: Handle) {
-        self.dedicated.push((Arc::new(filter), handle));
+    pub(crate) fn add<F: RuntimeFilter>(&mut self, filter: F, runtime: impl Runtime) {
+        self.dedicated.push((Arc::new(filter), Arc::new(runtime)));
+    }
+
+    /// Makes all actors use the provided runtime regardless of filters.
+    pub(crate) fn set_exclusive(
+        &mut self,
+        handle: Handle,
+        threads: Arc<ThreadRegistry>,
+        shutdown: RuntimeShutdown,
+    ) {
+        self.dedicated
+            .insert(0, (Arc::new(|_: &ActorMeta| true), Arc::new(handle)));
+        self.exclusive_threads = Some(threads);
+        self.exclusive_shutdown = Some(Arc::new(shutdown));
+    }
+
+    /// Stops the exclusive runtime, if any.
+    pub(crate) async fn shutdown(&self) {
+        if let Some(shutdown) = &self.exclusive_shutdown {
+            shutdown.shutdown().await;
+        }
+    }
+
+    /// Applies the config to the exclusive runtime, if any.
+    pub(crate) fn configure(&self, config: &config::RuntimeConfig) -> Result<(), String> {
+        let Some(threads) = &self.exclusive_threads else {
+            return Ok(());
+        };
+
+        threads.set_affinity(config.cpu_affinity.as_deref())
     }
 
-    pub(crate) fn get(&self, meta: &ActorMeta) -> Handle {
-        for (f, h) in &self.dedicated {
+    pub(crate) fn get(&self, meta: &ActorMeta) -> Arc<dyn Runtime> {
+        for (f, rt) in &self.dedicated {
             if f(meta) {
-                return h.clone();
+                return rt.clone();
             }
         }
 
-        tokio::runtime::Handle::current()
+        self.default
+            .get_or_init(|| Arc::new(Handle::current()))
+            .clone()
     }
 
     #[cfg(feature = "unstable-stuck-detection")]
@@ -0,0 +1,271 @@
+//! Contains `ThreadRegistry` that tracks threads of a dedicated runtime and
+//! pins them to CPU cores.
+
+use parking_lot::Mutex;
+
+use self::sys::{CpuSet, Tid};
+
+pub(crate) struct ThreadRegistry {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    threads: Vec<Tid>,
+    /// `None` means default affinity inherited from the process.
+    cpus: Option<CpuSet>,
+    /// The affinity of the thread that created the runtime, used to reset.
+    default_cpus: Option<CpuSet>,
+}
+
+impl ThreadRegistry {
+    pub(crate) fn new() -> Self {
+        let inner = Inner {
+            default_cpus: sys::get_affinity(sys::current_tid()).ok(),
+            ..Inner::default()
+        };
+
+        Self {
+            inner: Mutex::new(inner),
+        }
+    }
+
+    /// Registers the current thread and pins it if the affinity is set.
+    pub(crate) fn register_current(&self) {
+        let tid = sys::current_tid();
+        let mut inner = self.inner.lock();
+
+        if let Some(cpus) = &inner.cpus {
+            // Errors are reported on reconfiguration, so ignore them here.
+            let _ = sys::set_affinity(tid, cpus);
+        }
+
+        inner.threads.push(tid);
+    }
+
+    pub(crate) fn unregister_current(&self) {
+        let tid = sys::current_tid();
+        self.inner.lock().threads.retain(|t| *t != tid);
+    }
+
+    /// Pins all registered threads to the provided CPU cores.
+    /// If `None`, the default affinity is restored.
+    ///
+    /// On errors, already repinned threads are rolled back, so threads are
+    /// always pinned according to the last successfully applied config.
+    pub(crate) fn set_affinity(&self, cpus: Option<&[usize]>) -> Result<(), String> {
+        let mut inner = self.inner.lock();
+
+        let cpus = cpus.map(CpuSet::new).transpose()?;
+        if cpus == inner.cpus {
+            return Ok(());
+        }
+
+        let Some(target) = cpus.as_ref().or(inner.default_cpus.as_ref()) else {
+            return Err("cannot restore the default affinity".into());
+        };
+
+        let mut repinned = Vec::with_capacity(inner.threads.len());
+
+        for &tid in &inner.threads {
+            let result = sys::get_affinity(tid)
+                .map_err(|err| format!("cannot get affinity of thread {tid}: {err}"))
+                .and_then(|prev| {
+                    sys::set_affinity(tid, target)
+                        .map(|_| prev)
+                        .map_err(|err| format!("cannot set affinity of thread {tid}: {err}"))
+                });
+
+            match result {
+                Ok(prev) => repinned.push((tid, prev)),
+                Err(err) => {
+                    rollback(repinned);
+                    return Err(err);
+                }
+            }
+        }
+
+        inner.cpus = cpus;
+        Ok(())
+    }
+}
+
+fn rollback(repinned: Vec<(Tid, CpuSet)>) {
+    for (tid, prev) in repinned {
+        // Nothing to do if it fails, the thread has likely exited.
+        let _ = sys::set_affinity(tid, &prev);
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod sys {
+    use std::{fmt, io, mem};
+
+    pub(super) type Tid = libc::pid_t;
+
+    #[derive(Clone, Copy)]
+    pub(super) struct CpuSet(libc::cpu_set_t);
+
+    impl CpuSet {
+        pub(super) fn new(cpus: &[usize]) -> Result<Self, String> {
+            if cpus.is_empty() {
+                return Err("the list of CPU cores is empty".into());
+            }
+
+            // SAFETY: `cpu_set_t` is a plain bitmask, zeroed is a valid value.
+            let mut set: libc::cpu_set_t = unsafe { mem::zeroed() };
+
+            for &cpu in cpus {
+                if cpu >= libc::CPU_SETSIZE as usize {
+                    return Err(format!("invalid CPU core: {cpu}"));
+                }
+
+                // SAFETY: `cpu` is checked above to be inside the set.
+                unsafe { libc::CPU_SET(cpu, &mut set) };
+            }
+
+            Ok(Self(set))
+        }
+
+        #[cfg(test)]
+        pub(super) fn first(&self) -> Option<usize> {
+            (0..libc::CPU_SETSIZE as usize).find(|&cpu| {
+                // SAFETY: `cpu` is inside the set.
+                unsafe { libc::CPU_ISSET(cpu, &self.0) }
+            })
+        }
+    }
+
+    impl PartialEq for CpuSet {
+        fn eq(&self, other: &Self) -> bool {
+            // SAFETY: both sets are valid.
+            unsafe { libc::CPU_EQUAL(&self.0, &other.0) }
+        }
+    }
+
+    impl fmt::Debug for CpuSet {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let cpus = (0..libc::CPU_SETSIZE as usize).filter(|&cpu| {
+                // SAFETY: `cpu` is inside the set.
+                unsafe { libc::CPU_ISSET(cpu, &self.0) }
+            });
+            f.debug_list().entries(cpus).finish()
+        }
+    }
+
+    pub(super) fn current_tid() -> Tid {
+        // SAFETY: `gettid` is always successful.
+        unsafe { libc::syscall(libc::SYS_gettid) as Tid }
+    }
+
+    pub(super) fn get_affinity(tid: Tid) -> io::Result<CpuSet> {
+        // SAFETY: `cpu_set_t` is a plain bitmask, zeroed is a valid value.
+        let mut set: libc::cpu_set_t = unsafe { mem::zeroed() };
+
+        // SAFETY: `set` is a valid pointer to `cpu_set_t`.
+        let rv = unsafe { libc::sched_getaffinity(tid, mem::size_of_val(&set), &mut set) };
+
+        if rv == 0 {
+            Ok(CpuSet(set))
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+
+    pub(super) fn set_affinity(tid: Tid, cpus: &CpuSet) -> io::Result<()> {
+        // SAFETY: `cpus.0` is a valid `cpu_set_t`.
+        let rv = unsafe { libc::sched_setaffinity(tid, mem::size_of_val(&cpus.0), &cpus.0) };
+
+        if rv == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod sys {
+    use std::io;
+
+    pub(super) type Tid = u64;
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub(super) struct CpuSet;
+
+    impl CpuSet {
+        pub(super) fn new(_cpus: &[usize]) -> Result<Self, String> {
+            Err("CPU affinity is supported only on Linux".into())
+        }
+    }
+
+    pub(super) fn current_tid() -> Tid {
+        crate::thread::id()
+    }
+
+    pub(super) fn get_affinity(_tid: Tid) -> io::Result<CpuSet> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    pub(super) fn set_affinity(_tid: Tid, _cpus: &CpuSet) -> io::Result<()> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let registry = Arc::new(ThreadRegistry::new());
+        let default_cpus = registry.inner.lock().default_cpus.unwrap();
+        let cpu = default_cpus.first().unwrap();
+
+        let registry1 = registry.clone();
+        std::thread::spawn(move || {
+            registry1.register_current();
+            let tid = sys::current_tid();
+
+            registry1.set_affinity(Some(&[cpu])).unwrap();
+            let actual = sys::get_affinity(tid).unwrap();
+            assert_eq!(actual, CpuSet::new(&[cpu]).unwrap());
+
+            registry1.set_affinity(None).unwrap();
+            let actual = sys::get_affinity(tid).unwrap();
+            assert_eq!(actual, default_cpus);
+
+            registry1.unregister_current();
+        })
+        .join()
+        .unwrap();
+
+        assert!(registry.inner.lock().threads.is_empty());
+        assert!(registry.set_affinity(Some(&[])).is_err());
+        assert!(registry.set_affinity(Some(&[usize::MAX])).is_err());
+    }
+
+    #[test]
+    fn rollback() {
+        let registry = Arc::new(ThreadRegistry::new());
+        let default_cpus = registry.inner.lock().default_cpus.unwrap();
+        let cpu = default_cpus.first().unwrap();
+
+        let registry1 = registry.clone();
+        std::thread::spawn(move || {
+            registry1.register_current();
+            let tid = sys::current_tid();
+
+            // A thread that doesn't exist fails pinning after the current one.
+            registry1.inner.lock().threads.push(Tid::MAX);
+
+            assert!(registry1.set_affinity(Some(&[cpu])).is_err());
+            assert_eq!(sys::get_affinity(tid).unwrap(), default_cpus);
+            assert_eq!(registry1.inner.lock().cpus, None);
+        })
+        .join()
+        .unwrap();
+    }
+}
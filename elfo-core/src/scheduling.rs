@@ -0,0 +1,91 @@
+//! Seeded perturbation of scheduling, used by `elfo-test` to reproduce rare
+//! interleavings of messages.
+//!
+//! If a seed is set for the current thread, [`Context::recv()`] and
+//! [`Context::try_recv()`] yield to the executor a pseudo-random number of
+//! times before receiving. It changes the order in which ready actors run.
+//! The current-thread runtime polls tasks in FIFO order, so the same seed
+//! produces the same interleaving.
+//!
+//! [`Context::recv()`]: crate::Context::recv
+//! [`Context::try_recv()`]: crate::Context::try_recv
+
+use std::{cell::Cell, future::poll_fn, task::Poll};
+
+const MAX_YIELDS: u64 = 3;
+
+thread_local! {
+    static STATE: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+/// Sets the seed for the current thread, `None` disables perturbation.
+pub fn set_seed(seed: Option<u64>) {
+    STATE.with(|state| state.set(seed));
+}
+
+pub(crate) async fn perturb() {
+    let Some(random) = next_random() else {
+        return;
+    };
+
+    for _ in 0..random % (MAX_YIELDS + 1) {
+        yield_now().await;
+    }
+}
+
+// Unlike `tokio::task::yield_now()`, which defers waking until the driver is
+// polled, it reschedules the task immediately, so the order of tasks doesn't
+// depend on I/O and timers.
+async fn yield_now() {
+    let mut yielded = false;
+    poll_fn(|cx| {
+        if yielded {
+            return Poll::Ready(());
+        }
+
+        yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    })
+    .await
+}
+
+fn next_random() -> Option<u64> {
+    STATE
+        .try_with(|state| {
+            let mut s = state.get()?;
+            let random = splitmix64(&mut s);
+            state.set(Some(s));
+            Some(random)
+        })
+        .ok()
+        .flatten()
+}
+
+// See https://prng.di.unimi.it/splitmix64.c
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reproducible() {
+        let sequence = |seed| {
+            set_seed(Some(seed));
+            let sequence = (0..10).map(|_| next_random().unwrap()).collect::<Vec<_>>();
+            set_seed(None);
+            sequence
+        };
+
+        assert_eq!(sequence(42), sequence(42));
+        assert_ne!(sequence(42), sequence(43));
+        assert_eq!(next_random(), None);
+    }
+}
@@ -0,0 +1,186 @@
+//! Schemas of messages: names and types of fields and docs.
+//!
+//! Schemas are emitted by `#[message(schema)]` and registered at link time,
+//! so they can be listed and looked up at runtime, e.g. by gateways, admin
+//! UIs or dump tooling. Schemas are serializable to be exposed as is.
+//!
+//! Types are written as in the source code, so they aren't resolved, e.g.
+//! type aliases and generic parameters are kept.
+//!
+//! Schemas describe the serialized form, so `#[serde(rename)]`,
+//! `#[serde(rename_all)]`, `#[serde(skip)]`, `#[serde(flatten)]` and
+//! transparent structs are taken into account.
+
+use serde::Serialize;
+
+/// A list of all registered schemas via the `linkme` crate.
+// Reexported in `elfo::_priv`.
+#[doc(hidden)]
+#[linkme::distributed_slice]
+pub static MESSAGE_SCHEMAS: [&'static MessageSchema] = [..];
+
+/// Returns schemas of all messages with `#[message(schema)]`.
+pub fn all() -> impl Iterator<Item = &'static MessageSchema> {
+    MESSAGE_SCHEMAS.iter().copied()
+}
+
+/// Returns the schema of the message with the provided protocol and name.
+/// Only messages with `#[message(schema)]` have schemas.
+pub fn lookup(protocol: &str, name: &str) -> Option<&'static MessageSchema> {
+    all().find(|schema| schema.protocol == protocol && schema.name == name)
+}
+
+/// The schema of a message.
+#[derive(Debug, Serialize)]
+pub struct MessageSchema {
+    protocol: &'static str,
+    name: &'static str,
+    docs: &'static str,
+    response: Option<&'static str>,
+    shape: Shape,
+}
+
+impl MessageSchema {
+    #[doc(hidden)]
+    pub const fn new(
+        protocol: &'static str,
+        name: &'static str,
+        docs: &'static str,
+        response: Option<&'static str>,
+        shape: Shape,
+    ) -> Self {
+        Self {
+            protocol,
+            name,
+            docs,
+            response,
+            shape,
+        }
+    }
+
+    /// The protocol of the message.
+    pub fn protocol(&self) -> &'static str {
+        self.protocol
+    }
+
+    /// The name of the message.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Doc comments of the message, empty if there are no docs.
+    pub fn docs(&self) -> &'static str {
+        self.docs
+    }
+
+    /// The response type if the message is a request.
+    pub fn response(&self) -> Option<&'static str> {
+        self.response
+    }
+
+    /// Fields or variants of the message.
+    pub fn shape(&self) -> &Shape {
+        &self.shape
+    }
+}
+
+/// Fields of a struct or variants of an enum.
+#[derive(Debug, Serialize)]
+pub enum Shape {
+    /// A struct.
+    Struct(Fields),
+    /// An enum.
+    Enum(&'static [Variant]),
+    /// A struct serialized as its only field, e.g. `#[message(transparent)]`.
+    /// Contains the type of the field.
+    Transparent(&'static str),
+}
+
+/// Fields of a struct or an enum's variant.
+#[derive(Debug, Serialize)]
+pub enum Fields {
+    /// No fields, e.g. `struct Ping;`.
+    Unit,
+    /// Named fields, e.g. `struct Point { x: u32, y: u32 }`.
+    Named(&'static [Field]),
+    /// Unnamed fields, e.g. `struct Point(u32, u32)`.
+    /// Fields are named by their indices.
+    Unnamed(&'static [Field]),
+}
+
+/// A field of a struct or an enum's variant.
+#[derive(Debug, Serialize)]
+pub struct Field {
+    name: &'static str,
+    ty: &'static str,
+    docs: &'static str,
+    flatten: bool,
+}
+
+impl Field {
+    #[doc(hidden)]
+    pub const fn new(
+        name: &'static str,
+        ty: &'static str,
+        docs: &'static str,
+        flatten: bool,
+    ) -> Self {
+        Self {
+            name,
+            ty,
+            docs,
+            flatten,
+        }
+    }
+
+    /// The serialized name of the field or its index for unnamed fields.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// The type of the field as written in the source code.
+    pub fn ty(&self) -> &'static str {
+        self.ty
+    }
+
+    /// Doc comments of the field, empty if there are no docs.
+    pub fn docs(&self) -> &'static str {
+        self.docs
+    }
+
+    /// Whether fields of the field's type are inlined, see
+    /// `#[serde(flatten)]`.
+    pub fn is_flattened(&self) -> bool {
+        self.flatten
+    }
+}
+
+/// A variant of an enum.
+#[derive(Debug, Serialize)]
+pub struct Variant {
+    name: &'static str,
+    docs: &'static str,
+    fields: Fields,
+}
+
+impl Variant {
+    #[doc(hidden)]
+    pub const fn new(name: &'static str, docs: &'static str, fields: Fields) -> Self {
+        Self { name, docs, fields }
+    }
+
+    /// The serialized name of the variant.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Doc comments of the variant, empty if there are no docs.
+    pub fn docs(&self) -> &'static str {
+        self.docs
+    }
+
+    /// Fields of the variant.
+    pub fn fields(&self) -> &Fields {
+        &self.fields
+    }
+}
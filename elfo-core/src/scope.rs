@@ -1,14 +1,19 @@
 #![allow(clippy::declare_interior_mutable_const)] // see tokio#4872
 
 use std::{
-    cell::Cell,
+    any::{Any, TypeId},
+    cell::{Cell, RefCell},
     future::Future,
+    mem,
     sync::{
-        atomic::{AtomicUsize, Ordering},
+        atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering},
         Arc,
     },
 };
 
+use fxhash::FxHashMap;
+use parking_lot::Mutex;
+
 use crate::{
     actor::ActorMeta,
     addr::{Addr, NodeNo},
@@ -16,8 +21,11 @@ use crate::{
     dumping::DumpingControl,
     logging::_priv::LoggingControl,
     permissions::{AtomicPermissions, Permissions},
+    persistence::PersistenceControl,
+    response_cache::ResponseCaches,
+    runtime::Runtime,
     telemetry::config::TelemetryConfig,
-    tracing::TraceId,
+    tracing::{Baggage, SamplingControl, TraceId},
 };
 
 tokio::task_local! {
@@ -27,6 +35,11 @@ tokio::task_local! {
 #[derive(Clone)]
 pub struct Scope {
     trace_id: Cell<TraceId>,
+    baggage: RefCell<Baggage>,
+    /// The span of the currently handled message if it's recorded.
+    span_id: Cell<Option<TraceId>>,
+    /// The cached sampling decision for the current trace.
+    sampled: Cell<Option<bool>>,
     actor: Arc<ScopeActorShared>,
     group: Arc<ScopeGroupShared>,
 }
@@ -43,6 +56,27 @@ impl Scope {
         Self::new(TraceId::generate(), actor, meta, group_scope)
     }
 
+    /// Configures the group of the test scope.
+    #[cfg(test)]
+    pub(crate) fn with_config(self, config: &SystemConfig) -> Self {
+        self.group.configure(config);
+        self
+    }
+
+    /// Creates a test scope of a group configured by `configure`.
+    #[cfg(test)]
+    pub(crate) fn test_configured(configure: impl FnOnce(&mut SystemConfig)) -> Self {
+        let mut config = SystemConfig::default();
+        configure(&mut config);
+
+        let meta = Arc::new(ActorMeta {
+            group: "group".into(),
+            key: "key".into(),
+        });
+
+        Self::test(Addr::NULL, meta).with_config(&config)
+    }
+
     pub(crate) fn new(
         trace_id: TraceId,
         addr: Addr,
@@ -51,6 +85,9 @@ impl Scope {
     ) -> Self {
         Self {
             trace_id: Cell::new(trace_id),
+            baggage: RefCell::new(Baggage::default()),
+            span_id: Cell::new(None),
+            sampled: Cell::new(None),
             actor: Arc::new(ScopeActorShared::new(addr, meta)),
             group,
         }
@@ -61,6 +98,11 @@ impl Scope {
         self
     }
 
+    pub(crate) fn with_runtime(mut self, runtime: Arc<dyn Runtime>) -> Self {
+        self.actor = Arc::new(self.actor.with_runtime(runtime));
+        self
+    }
+
     #[inline]
     pub fn actor(&self) -> Addr {
         self.actor.addr
@@ -82,6 +124,12 @@ impl Scope {
         &self.actor.meta
     }
 
+    /// Returns the runtime the current actor is spawned on.
+    #[inline]
+    pub(crate) fn runtime(&self) -> Option<&Arc<dyn Runtime>> {
+        self.actor.runtime.as_ref()
+    }
+
     /// Private API for now.
     #[inline]
     #[stability::unstable]
@@ -100,6 +148,47 @@ impl Scope {
     #[inline]
     pub fn set_trace_id(&self, trace_id: TraceId) {
         self.trace_id.set(trace_id);
+        self.sampled.set(None);
+    }
+
+    /// Returns the current baggage.
+    #[inline]
+    pub fn baggage(&self) -> Baggage {
+        self.baggage.borrow().clone()
+    }
+
+    /// Replaces the current baggage with the provided one.
+    #[inline]
+    pub fn set_baggage(&self, baggage: Baggage) {
+        *self.baggage.borrow_mut() = baggage;
+        self.sampled.set(None);
+    }
+
+    /// Returns the id of the span of the currently handled message.
+    /// Envelopes sent inside the span refer to it as the parent one.
+    #[inline]
+    pub(crate) fn span_id(&self) -> Option<TraceId> {
+        self.span_id.get()
+    }
+
+    #[inline]
+    pub(crate) fn set_span_id(&self, span_id: Option<TraceId>) {
+        self.span_id.set(span_id);
+    }
+
+    /// Returns `true` if the current trace is sampled according to the
+    /// `system.tracing` section of the group's config.
+    /// Only sampled traces are dumped and exported as spans.
+    #[inline]
+    pub fn is_sampled(&self) -> bool {
+        if let Some(sampled) = self.sampled.get() {
+            return sampled;
+        }
+
+        let baggage = self.baggage.borrow();
+        let sampled = self.group.sampling.is_sampled(self.trace_id(), &baggage);
+        self.sampled.set(Some(sampled));
+        sampled
     }
 
     /// Returns the current permissions (for logging, telemetry and so on).
@@ -124,6 +213,28 @@ impl Scope {
         &self.group.dumping
     }
 
+    /// Whether envelopes are allocated using pools, see `envelope_pool.rs`.
+    #[inline]
+    pub(crate) fn is_envelope_pool_enabled(&self) -> bool {
+        self.group.envelope_pool.load(Ordering::Relaxed)
+    }
+
+    /// Returns the send budget of the group, see `coop.rs`.
+    #[inline]
+    pub(crate) fn send_budget(&self) -> u32 {
+        self.group.send_budget.load(Ordering::Relaxed)
+    }
+
+    #[inline]
+    pub(crate) fn persistence(&self) -> &PersistenceControl {
+        &self.group.persistence
+    }
+
+    #[inline]
+    pub(crate) fn response_caches(&self) -> &ResponseCaches {
+        &self.group.response_caches
+    }
+
     #[doc(hidden)]
     #[stability::unstable]
     pub fn increment_allocated_bytes(&self, by: usize) {
@@ -138,6 +249,30 @@ impl Scope {
             .fetch_add(by, Ordering::Relaxed);
     }
 
+    /// Returns the actor-local value of the type `T`.
+    ///
+    /// The value is created by `T::default()` on the first access and
+    /// dropped once the actor terminates or fails, even if clones of its
+    /// scope are still alive, e.g. in tasks spawned by the actor.
+    /// Use interior mutability to change the value.
+    pub fn local<T: Default + Send + Sync + 'static>(&self) -> Arc<T> {
+        let mut locals = self.actor.locals.lock();
+        let value = locals
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Arc::new(T::default()))
+            .clone();
+
+        drop(locals);
+        value.downcast().expect("invalid actor-local type")
+    }
+
+    /// Drops all actor-local values, called once the actor is finished.
+    pub(crate) fn clear_locals(&self) {
+        // Values are dropped outside the lock, because they can access locals.
+        let locals = mem::take(&mut *self.actor.locals.lock());
+        drop(locals);
+    }
+
     pub(crate) fn take_allocated_bytes(&self) -> usize {
         self.actor.allocated_bytes.swap(0, Ordering::Relaxed)
     }
@@ -161,8 +296,10 @@ struct ScopeActorShared {
     addr: Addr,
     meta: Arc<ActorMeta>,
     telemetry_meta: Arc<ActorMeta>,
+    runtime: Option<Arc<dyn Runtime>>,
     allocated_bytes: AtomicUsize,
     deallocated_bytes: AtomicUsize,
+    locals: Mutex<FxHashMap<TypeId, Arc<dyn Any + Send + Sync>>>,
 }
 
 impl ScopeActorShared {
@@ -171,8 +308,10 @@ impl ScopeActorShared {
             addr,
             meta: meta.clone(),
             telemetry_meta: meta,
+            runtime: None,
             allocated_bytes: AtomicUsize::new(0),
             deallocated_bytes: AtomicUsize::new(0),
+            locals: Default::default(),
         }
     }
 
@@ -190,8 +329,22 @@ impl ScopeActorShared {
                     })
                 })
                 .unwrap_or_else(|| self.meta.clone()),
+            runtime: self.runtime.clone(),
+            allocated_bytes: AtomicUsize::new(0),
+            deallocated_bytes: AtomicUsize::new(0),
+            locals: Default::default(),
+        }
+    }
+
+    fn with_runtime(&self, runtime: Arc<dyn Runtime>) -> Self {
+        Self {
+            addr: self.addr,
+            meta: self.meta.clone(),
+            telemetry_meta: self.telemetry_meta.clone(),
+            runtime: Some(runtime),
             allocated_bytes: AtomicUsize::new(0),
             deallocated_bytes: AtomicUsize::new(0),
+            locals: Default::default(),
         }
     }
 }
@@ -204,6 +357,11 @@ pub(crate) struct ScopeGroupShared {
     permissions: AtomicPermissions,
     logging: LoggingControl,
     dumping: DumpingControl,
+    persistence: PersistenceControl,
+    sampling: SamplingControl,
+    envelope_pool: AtomicBool,
+    send_budget: AtomicU32,
+    response_caches: ResponseCaches,
 }
 
 assert_impl_all!(ScopeGroupShared: Send, Sync);
@@ -216,9 +374,24 @@ impl ScopeGroupShared {
             permissions: Default::default(), // everything is disabled
             logging: Default::default(),
             dumping: Default::default(),
+            persistence: Default::default(),
+            sampling: Default::default(),
+            envelope_pool: AtomicBool::new(false),
+            send_budget: AtomicU32::new(0),
+            response_caches: Default::default(),
         }
     }
 
+    pub(crate) fn with_response_caches(mut self, response_caches: ResponseCaches) -> Self {
+        self.response_caches = response_caches;
+        self
+    }
+
+    #[inline]
+    pub(crate) fn response_caches(&self) -> &ResponseCaches {
+        &self.response_caches
+    }
+
     pub(crate) fn configure(&self, config: &SystemConfig) {
         // Update the logging subsystem.
         self.logging.configure(&config.logging);
@@ -226,10 +399,24 @@ impl ScopeGroupShared {
         // Update the dumping subsystem.
         self.dumping.configure(&config.dumping);
 
+        // Update the persistence subsystem.
+        self.persistence.configure(&config.persistence);
+
+        // Update the sampling of traces.
+        self.sampling.configure(&config.tracing);
+
+        // Update the envelope pool.
+        self.envelope_pool
+            .store(config.mailbox.envelope_pool, Ordering::Relaxed);
+
+        // Update the send budget.
+        self.send_budget
+            .store(config.coop.send_budget, Ordering::Relaxed);
+
         // Update permissions.
         let mut perm = self.permissions.load();
         perm.set_logging_enabled(config.logging.max_level.into());
-        perm.set_dumping_enabled(!config.dumping.disabled);
+        perm.set_dumping_enabled(config.dumping.is_enabled());
         perm.set_telemetry_per_actor_group_enabled(config.telemetry.per_actor_group);
         perm.set_telemetry_per_actor_key_enabled(config.telemetry.per_actor_key.is_enabled());
         self.permissions.store(perm);
@@ -267,6 +454,21 @@ pub fn try_with<R>(f: impl FnOnce(&Scope) -> R) -> Option<R> {
     SCOPE.try_with(|scope| f(scope)).ok()
 }
 
+/// Returns the runtime the current actor is spawned on, or the current tokio
+/// runtime if called outside actors.
+///
+/// Tasks and blocking work done on behalf of actors should be spawned on it
+/// instead of `tokio::spawn()` and `tokio::task::spawn_blocking()`.
+///
+/// # Panics
+/// This function will panic if called outside actors and a tokio runtime.
+#[stability::unstable]
+pub fn runtime() -> Arc<dyn Runtime> {
+    try_with(|scope| scope.runtime().cloned())
+        .flatten()
+        .unwrap_or_else(|| Arc::new(tokio::runtime::Handle::current()))
+}
+
 /// Returns the current trace id.
 ///
 /// # Panics
@@ -300,6 +502,40 @@ pub fn try_set_trace_id(trace_id: TraceId) -> bool {
     try_with(|scope| scope.set_trace_id(trace_id)).is_some()
 }
 
+/// Returns the current baggage.
+///
+/// # Panics
+/// This function will panic if called ouside the actor system.
+#[inline]
+pub fn baggage() -> Baggage {
+    with(Scope::baggage)
+}
+
+/// Returns the current baggage if inside the actor system.
+#[inline]
+pub fn try_baggage() -> Option<Baggage> {
+    try_with(Scope::baggage)
+}
+
+/// Replaces the current baggage with the provided one.
+/// It's propagated along with every message sent after that.
+///
+/// # Panics
+/// This function will panic if called ouside the actor system.
+#[inline]
+pub fn set_baggage(baggage: Baggage) {
+    with(|scope| scope.set_baggage(baggage));
+}
+
+/// Returns `true` if the current trace is sampled, see [`Scope::is_sampled()`].
+///
+/// # Panics
+/// This function will panic if called ouside the actor system.
+#[inline]
+pub fn is_sampled() -> bool {
+    with(Scope::is_sampled)
+}
+
 /// Returns the current object's meta.
 ///
 /// # Panics
@@ -330,6 +566,36 @@ pub fn try_node_no() -> Option<NodeNo> {
     try_with(|scope| scope.node_no())
 }
 
+/// Returns the actor-local value of the type `T`.
+/// See [`Scope::local()`] for details.
+///
+/// # Example
+/// ```
+/// # use std::sync::atomic::{AtomicU64, Ordering};
+/// # use elfo_core as elfo;
+/// #[derive(Default)]
+/// struct HandledRequests(AtomicU64);
+///
+/// # fn exec() {
+/// let handled = elfo::scope::local::<HandledRequests>();
+/// handled.0.fetch_add(1, Ordering::Relaxed);
+/// # }
+/// ```
+///
+/// # Panics
+/// This function will panic if called ouside the actor system.
+#[inline]
+pub fn local<T: Default + Send + Sync + 'static>() -> Arc<T> {
+    with(Scope::local)
+}
+
+/// Returns the actor-local value of the type `T` if inside the actor system.
+/// See [`Scope::local()`] for details.
+#[inline]
+pub fn try_local<T: Default + Send + Sync + 'static>() -> Option<Arc<T>> {
+    try_with(Scope::local)
+}
+
 thread_local! {
     static SERDE_MODE: Cell<SerdeMode> = const { Cell::new(SerdeMode::Normal) };
 }
@@ -402,3 +668,40 @@ fn serde_mode_works() {
     assert!(res.is_err());
     assert_eq!(serde_json::to_string(&value).unwrap(), r#"{"f":42}"#);
 }
+
+#[test]
+fn local_works() {
+    use std::sync::atomic::AtomicU64;
+
+    #[derive(Default)]
+    struct Counter(AtomicU64);
+
+    let meta = Arc::new(ActorMeta {
+        group: "group".into(),
+        key: "key".into(),
+    });
+
+    let scope1 = Scope::test(Addr::NULL, meta.clone());
+    let scope2 = Scope::test(Addr::NULL, meta);
+
+    assert!(try_local::<Counter>().is_none());
+
+    scope1.clone().sync_within(|| {
+        local::<Counter>().0.fetch_add(1, Ordering::Relaxed);
+        local::<Counter>().0.fetch_add(1, Ordering::Relaxed);
+    });
+
+    // The same actor, but a different clone of the scope.
+    let counter = scope1.clone().sync_within(local::<Counter>);
+    assert_eq!(counter.0.load(Ordering::Relaxed), 2);
+
+    // Another actor.
+    let counter2 = scope2.sync_within(local::<Counter>);
+    assert_eq!(counter2.0.load(Ordering::Relaxed), 0);
+
+    // Dropped once the actor is finished, even if the scope is retained.
+    scope1.clear_locals();
+    assert_eq!(Arc::strong_count(&counter), 1);
+    let counter = scope1.sync_within(local::<Counter>);
+    assert_eq!(counter.0.load(Ordering::Relaxed), 0);
+}
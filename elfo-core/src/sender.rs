@@ -0,0 +1,179 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::atomic::{AtomicU64, Ordering},
+    task::{Context, Poll},
+};
+
+use idr_ebr::EbrGuard;
+use pin_project::pin_project;
+use tracing::trace;
+
+use crate::{
+    address_book::AddressBook,
+    envelope::{Envelope, MessageKind},
+    errors::{SendError, TrySendError},
+    mailbox,
+    message::Message,
+    object::Object,
+    scope,
+    tracing::TraceId,
+    Addr,
+};
+
+/// A cheap cloneable handle to send messages into the actor system from code
+/// that isn't an actor: FFI callbacks, handlers of other frameworks and so on.
+///
+/// It's bound to the specific recipient, usually a group. In this case,
+/// messages are routed by the group's router as if they were sent by actors.
+///
+/// Can be obtained by [`Topology::sender_to()`] or [`Context::sender_to()`].
+///
+/// # Tracing
+///
+/// If called inside the actor system, the current trace is preserved.
+/// Otherwise, every message starts a new trace.
+///
+/// # Ordering
+///
+/// Messages sent by the same sender (and its clones) are received in the
+/// order they're sent, even if the recipient's mailbox is sharded.
+///
+/// # Example
+/// ```
+/// # use elfo_core as elfo;
+/// # fn exec(topology: elfo::Topology) {
+/// # use elfo::message;
+/// #[message]
+/// struct RequestReceived(String);
+///
+/// let handlers = topology.local("handlers");
+/// let sender = topology.sender_to(handlers.addr());
+///
+/// // Somewhere in a callback of another framework.
+/// let _ = sender.try_send(RequestReceived("/status".into()));
+/// # }
+/// ```
+///
+/// [`Topology::sender_to()`]: crate::Topology::sender_to()
+/// [`Context::sender_to()`]: crate::Context::sender_to()
+#[derive(Clone)]
+pub struct ExternalSender {
+    book: AddressBook,
+    recipient: Addr,
+    /// Chooses the mailbox shard, see `mailbox::with_external_key()`.
+    key: u64,
+}
+
+assert_impl_all!(ExternalSender: Send, Sync);
+
+impl ExternalSender {
+    pub(crate) fn new(book: AddressBook, recipient: Addr) -> Self {
+        // Zero is used by other messages without a sender.
+        static NEXT_KEY: AtomicU64 = AtomicU64::new(1);
+
+        Self {
+            book,
+            recipient,
+            key: NEXT_KEY.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    /// Returns the recipient's address.
+    #[inline]
+    pub fn recipient(&self) -> Addr {
+        self.recipient
+    }
+
+    /// Sends a message to the recipient.
+    /// Waits if the recipient's mailbox is full.
+    ///
+    /// Returns `Err` if the message hasn't reached any mailboxes.
+    ///
+    /// # Cancel safety
+    ///
+    /// If cancelled, recipients with full mailboxes wont't receive the message.
+    pub async fn send<M: Message>(&self, message: M) -> Result<(), SendError<M>> {
+        let recipient = self.recipient;
+
+        let fut = {
+            let guard = EbrGuard::new();
+            let entry = self.book.get(recipient, &guard);
+            let object = ward!(entry, return Err(SendError(message)));
+            Object::send(object, recipient, make_envelope(recipient, message))
+        };
+
+        WithExternalKey { key: self.key, fut }
+            .await
+            .map_err(|err| err.map(e2m))
+    }
+
+    /// Tries to send a message to the recipient.
+    /// Returns an error if the recipient's mailbox is full.
+    ///
+    /// Returns
+    /// * `Ok(())` if the message has been added to any mailbox.
+    /// * `Err(Full(_))` if some mailboxes are full.
+    /// * `Err(Closed(_))` otherwise.
+    pub fn try_send<M: Message>(&self, message: M) -> Result<(), TrySendError<M>> {
+        let recipient = self.recipient;
+        let guard = EbrGuard::new();
+        let entry = self.book.get(recipient, &guard);
+        let object = ward!(entry, return Err(TrySendError::Closed(message)));
+
+        let envelope = make_envelope(recipient, message);
+        mailbox::with_external_key(self.key, || object.try_send(recipient, envelope))
+            .map_err(|err| err.map(e2m))
+    }
+
+    /// Sends a message to the recipient.
+    /// Ignores the capacity of the recipient's mailbox.
+    ///
+    /// Usually this method shouldn't be used because it can lead to high memory
+    /// usage and even OOM if the recipient works too slowly.
+    /// Prefer [`ExternalSender::try_send()`] or [`ExternalSender::send()`].
+    ///
+    /// Returns `Err` if the message hasn't reached mailboxes.
+    pub fn unbounded_send<M: Message>(&self, message: M) -> Result<(), SendError<M>> {
+        let recipient = self.recipient;
+        let guard = EbrGuard::new();
+        let entry = self.book.get(recipient, &guard);
+        let object = ward!(entry, return Err(SendError(message)));
+
+        let envelope = make_envelope(recipient, message);
+        mailbox::with_external_key(self.key, || object.unbounded_send(recipient, envelope))
+            .map_err(|err| err.map(e2m))
+    }
+}
+
+fn make_envelope<M: Message>(recipient: Addr, message: M) -> Envelope {
+    trace!(to = %recipient, "> {:?}", message);
+
+    let trace_id = scope::try_trace_id().unwrap_or_else(TraceId::generate);
+    let baggage = scope::try_baggage().unwrap_or_default();
+    let kind = MessageKind::regular(Addr::NULL);
+    Envelope::with_trace_id(message, kind, trace_id).with_baggage(baggage)
+}
+
+/// Sets the key on every poll, because the envelope is enqueued only once
+/// the recipient's mailbox has room.
+#[pin_project]
+struct WithExternalKey<F> {
+    key: u64,
+    #[pin]
+    fut: F,
+}
+
+impl<F: Future> Future for WithExternalKey<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        mailbox::with_external_key(*this.key, || this.fut.poll(cx))
+    }
+}
+
+#[cold]
+fn e2m<M: Message>(envelope: Envelope) -> M {
+    envelope.unpack().expect("invalid message").0
+}
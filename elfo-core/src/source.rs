@@ -1,5 +1,6 @@
 use std::{
-    any::Any,
+    any::{Any, TypeId},
+    hash::{Hash, Hasher},
     marker::PhantomData,
     mem::ManuallyDrop,
     pin::Pin,
@@ -7,15 +8,31 @@ use std::{
     task::{self, Poll, RawWaker, RawWakerVTable, Waker},
 };
 
+use fxhash::{FxHashMap, FxHasher};
 use sealed::sealed;
 use unicycle::StreamsUnordered;
 
 use self::pinarcmutex::{PinArcMutex, PinArcMutexGuard};
-use crate::envelope::Envelope;
+#[cfg(feature = "test-util")]
+use crate::message::AnyMessage;
+use crate::{envelope::Envelope, stream::StreamItem, tracing::TraceId};
 
 pub(crate) trait SourceStream: Send + 'static {
     fn as_any_mut(self: Pin<&mut Self>) -> Pin<&mut dyn Any>;
     fn poll_recv(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Option<Envelope>>;
+
+    /// Returns the stored message if it has the provided type, see
+    /// `triggers.rs`.
+    #[cfg(feature = "test-util")]
+    fn fire(self: Pin<&mut Self>, _message_type: TypeId) -> Option<Envelope> {
+        None
+    }
+
+    /// Returns `true` if messages can be emitted from the source by tests.
+    #[cfg(feature = "test-util")]
+    fn is_stream(&self) -> bool {
+        false
+    }
 }
 
 /// A wrapper to indicate that a source hasn't been attached to a context yet.
@@ -42,9 +59,24 @@ impl<H> UnattachedSource<H> {
     }
 
     pub(crate) fn attach_to(self, sources: &mut Sources) -> H {
+        #[cfg(feature = "test-util")]
+        crate::triggers::register(self.source.to_handle());
         sources.push(self.source);
         self.handle
     }
+
+    pub(crate) fn attach_keyed_to<K>(
+        self,
+        sources: &mut Sources,
+        keyed: &mut KeyedSources,
+        key: K,
+    ) -> H
+    where
+        K: Hash + Eq + Send + Sync + 'static,
+    {
+        keyed.insert(key, self.source.to_handle());
+        self.attach_to(sources)
+    }
 }
 
 // === SourceHandle ===
@@ -59,6 +91,137 @@ pub trait SourceHandle {
     fn terminate(self);
 }
 
+// === Source ===
+
+/// A user-defined source of messages.
+///
+/// Implement it to integrate custom event sources (device readers, watch
+/// channels and so on) directly into [`Context::recv()`] without bridging
+/// them through extra channels and tasks. Attached sources are polled
+/// fairly along with the mailbox and other sources, and are dropped
+/// when the actor terminates or the source is terminated.
+///
+/// Possible items (the `Item` type) are the same as for [`Stream`].
+///
+/// # Tracing
+///
+/// Every message starts a new trace, thus a new trace id is generated and
+/// assigned to the current scope.
+///
+/// # Example
+/// ```
+/// # use std::{pin::Pin, task::{self, Poll}};
+/// # use elfo_core as elfo;
+/// # async fn exec(mut ctx: elfo::Context) {
+/// # use elfo::{message, msg};
+/// use elfo::source::{Custom, Source};
+///
+/// #[message]
+/// struct Changed(u32);
+///
+/// struct Watcher {
+///     rx: tokio::sync::watch::Receiver<u32>,
+/// }
+///
+/// impl Source for Watcher {
+///     type Item = Changed;
+///
+///     fn poll_recv(
+///         self: Pin<&mut Self>,
+///         cx: &mut task::Context<'_>,
+///     ) -> Poll<Option<Changed>> {
+///         // Poll the underlying resource, register the waker if pending.
+/// #       Poll::Pending
+///     }
+/// }
+///
+/// # let (_tx, rx) = tokio::sync::watch::channel(0);
+/// let watcher = ctx.attach(Custom::new(Watcher { rx }));
+///
+/// while let Some(envelope) = ctx.recv().await {
+///     msg!(match envelope {
+///         Changed(value) => { /* ... */ },
+///     });
+/// }
+/// # }
+/// ```
+///
+/// [`Context::recv()`]: crate::Context::recv()
+/// [`Stream`]: crate::stream::Stream
+pub trait Source: Send + 'static {
+    /// A type of produced messages.
+    type Item: StreamItem;
+
+    /// Attempts to pull out the next message of this source.
+    ///
+    /// Follows the [`futures::Stream::poll_next()`] contract: if the source
+    /// isn't ready, it must register the waker and return `Poll::Pending`.
+    /// Returning `Poll::Ready(None)` terminates the source.
+    fn poll_recv(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Option<Self::Item>>;
+}
+
+// === Custom ===
+
+/// A handle to an attached [`Source`] implemented by a user.
+pub struct Custom<S> {
+    source: SourceArc<CustomSource<S>>,
+}
+
+#[sealed]
+impl<S: Source> SourceHandle for Custom<S> {
+    fn is_terminated(&self) -> bool {
+        self.source.lock().is_none()
+    }
+
+    fn terminate(self) {
+        ward!(self.source.lock()).terminate();
+    }
+}
+
+impl<S: Source> Custom<S> {
+    /// Creates an unattached instance of [`Custom`].
+    pub fn new(source: S) -> UnattachedSource<Self> {
+        let source = SourceArc::new(CustomSource(source), false);
+        UnattachedSource::new(source, |source| Self { source })
+    }
+
+    /// Provides access to the source, e.g. to reconfigure it.
+    /// The source is polled again afterwards.
+    ///
+    /// Returns `None` if the source is terminated.
+    ///
+    /// Note: it cannot be called *inside* the source, because it leads
+    /// to a deadlock.
+    pub fn with<R>(&self, f: impl FnOnce(Pin<&mut S>) -> R) -> Option<R> {
+        let mut guard = self.source.lock()?;
+        let result = f(guard.stream().project());
+        guard.wake();
+        Some(result)
+    }
+}
+
+struct CustomSource<S>(S);
+
+impl<S> CustomSource<S> {
+    fn project(self: Pin<&mut Self>) -> Pin<&mut S> {
+        // SAFETY: `Pin`: `S` is pinned when `Self` is.
+        unsafe { self.map_unchecked_mut(|s| &mut s.0) }
+    }
+}
+
+impl<S: Source> SourceStream for CustomSource<S> {
+    fn as_any_mut(self: Pin<&mut Self>) -> Pin<&mut dyn Any> {
+        // SAFETY: we only cast here, it cannot move data.
+        unsafe { self.map_unchecked_mut(|s| s) }
+    }
+
+    fn poll_recv(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Option<Envelope>> {
+        self.project()
+            .poll_recv(cx)
+            .map(|item| item.map(|item| item.pack(TraceId::generate())))
+    }
+}
+
 // === SourceArc ===
 
 pub(crate) struct SourceArc<S: ?Sized> {
@@ -153,6 +316,8 @@ impl UntypedSourceArc {
                     StreamStatus::Stream
                 },
                 stream: ManuallyDrop::new(stream),
+                #[cfg(feature = "test-util")]
+                fired: std::collections::VecDeque::new(),
             }),
         }
     }
@@ -163,6 +328,66 @@ impl UntypedSourceArc {
             inner: self.inner.clone(),
         }
     }
+
+    pub(crate) fn to_handle(&self) -> Self {
+        Self {
+            is_owner: false,
+            inner: self.inner.clone(),
+        }
+    }
+
+    pub(crate) fn is_terminated(&self) -> bool {
+        self.inner.lock().status() == StreamStatus::Terminated
+    }
+
+    /// Returns `false` if the source is already terminated.
+    fn terminate_by_ref(&self) -> bool {
+        let mut inner = self.inner.lock();
+        if inner.status() == StreamStatus::Terminated {
+            return false;
+        }
+
+        inner.get_mut().terminate();
+
+        // Wake the stream inside `unicycle` to actually remove it from the list.
+        inner.wake();
+        true
+    }
+}
+
+#[cfg(feature = "test-util")]
+impl UntypedSourceArc {
+    /// Returns `false` if the source doesn't store a message of the type.
+    pub(crate) fn fire(&self, message_type: TypeId) -> bool {
+        let mut inner = self.inner.lock();
+        if inner.status() == StreamStatus::Terminated {
+            return false;
+        }
+
+        let Some(envelope) = inner.get_mut().stream().fire(message_type) else {
+            return false;
+        };
+
+        inner.get_mut().push_fired(envelope);
+        true
+    }
+
+    pub(crate) fn is_stream(&self) -> bool {
+        let inner = self.inner.lock();
+        inner.status() != StreamStatus::Terminated && inner.stream.is_stream()
+    }
+
+    pub(crate) fn emit(&self, message: AnyMessage) -> Result<(), AnyMessage> {
+        let mut inner = self.inner.lock();
+        if inner.status() == StreamStatus::Terminated {
+            return Err(message);
+        }
+
+        inner
+            .get_mut()
+            .push_fired(message.pack(TraceId::generate()));
+        Ok(())
+    }
 }
 
 impl Drop for UntypedSourceArc {
@@ -184,6 +409,9 @@ impl Drop for UntypedSourceArc {
 struct StreamWithWaker<S: ?Sized> {
     waker: Waker,
     status: StreamStatus,
+    // Messages emitted by `triggers.rs` before polling the stream.
+    #[cfg(feature = "test-util")]
+    fired: std::collections::VecDeque<Envelope>,
     // `stream` is considered pinned.
     stream: ManuallyDrop<S>,
 }
@@ -217,6 +445,20 @@ impl<S: ?Sized> StreamWithWaker<S> {
         self.waker.wake_by_ref();
     }
 
+    #[cfg(feature = "test-util")]
+    fn push_fired(self: Pin<&mut Self>, envelope: Envelope) {
+        // SAFETY: `fired` is not pinned.
+        let this = unsafe { self.get_unchecked_mut() };
+        this.fired.push_back(envelope);
+        this.wake();
+    }
+
+    #[cfg(feature = "test-util")]
+    fn pop_fired(self: Pin<&mut Self>) -> Option<Envelope> {
+        // SAFETY: `fired` is not pinned.
+        unsafe { self.get_unchecked_mut() }.fired.pop_front()
+    }
+
     fn stream(self: Pin<&mut Self>) -> Pin<&mut S> {
         assert_ne!(self.status, StreamStatus::Terminated);
 
@@ -249,6 +491,12 @@ impl futures::Stream for UntypedSourceArc {
             return Poll::Ready(None);
         }
 
+        #[cfg(feature = "test-util")]
+        let result = match guard.get_mut().pop_fired() {
+            Some(envelope) => Poll::Ready(Some(envelope)),
+            None => guard.get_mut().stream().poll_recv(cx),
+        };
+        #[cfg(not(feature = "test-util"))]
         let result = guard.get_mut().stream().poll_recv(cx);
 
         if result.is_pending() {
@@ -281,6 +529,89 @@ fn noop_raw_waker() -> RawWaker {
 
 pub(crate) type Sources = StreamsUnordered<UntypedSourceArc>;
 
+// === KeyedSources ===
+
+/// Handles of sources attached by `Context::attach_keyed()`.
+///
+/// Keys are type-erased, so entries are grouped by a hash of the key and its
+/// type, and then compared by downcasting.
+#[derive(Default)]
+pub(crate) struct KeyedSources {
+    buckets: FxHashMap<u64, Vec<KeyedEntry>>,
+    len: usize,
+    /// Terminated sources are removed lazily once `len` reaches this value.
+    prune_at: usize,
+}
+
+struct KeyedEntry {
+    key: Box<dyn Any + Send + Sync>,
+    source: UntypedSourceArc,
+}
+
+const MIN_PRUNE_AT: usize = 16;
+
+impl KeyedSources {
+    /// Inserts a new source, the previous one with the same key is terminated.
+    fn insert<K: Hash + Eq + Send + Sync + 'static>(&mut self, key: K, source: UntypedSourceArc) {
+        self.remove(&key);
+
+        if self.len >= self.prune_at {
+            self.prune();
+        }
+
+        let hash = hash_key(&key);
+        let entry = KeyedEntry {
+            key: Box::new(key),
+            source,
+        };
+
+        self.buckets.entry(hash).or_default().push(entry);
+        self.len += 1;
+    }
+
+    /// Removes and terminates the source with the provided key.
+    /// Returns `false` if there is no such source or it's already terminated.
+    pub(crate) fn remove<K: Hash + Eq + 'static>(&mut self, key: &K) -> bool {
+        let hash = hash_key(key);
+        let Some(bucket) = self.buckets.get_mut(&hash) else {
+            return false;
+        };
+
+        let Some(index) = bucket
+            .iter()
+            .position(|e| e.key.downcast_ref::<K>() == Some(key))
+        else {
+            return false;
+        };
+
+        let entry = bucket.swap_remove(index);
+        if bucket.is_empty() {
+            self.buckets.remove(&hash);
+        }
+        self.len -= 1;
+
+        entry.source.terminate_by_ref()
+    }
+
+    /// Removes entries of sources that have been terminated by themselves.
+    fn prune(&mut self) {
+        self.buckets.retain(|_, bucket| {
+            bucket.retain(|e| !e.source.is_terminated());
+            !bucket.is_empty()
+        });
+
+        self.len = self.buckets.values().map(Vec::len).sum();
+        self.prune_at = (self.len * 2).max(MIN_PRUNE_AT);
+    }
+}
+
+fn hash_key<K: Hash + 'static>(key: &K) -> u64 {
+    let mut hasher = FxHasher::default();
+    TypeId::of::<K>().hash(&mut hasher);
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
 // === PinArcMutex ===
 
 mod pinarcmutex {
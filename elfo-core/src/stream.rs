@@ -264,6 +264,11 @@ where
             }
         })
     }
+
+    #[cfg(feature = "test-util")]
+    fn is_stream(&self) -> bool {
+        true
+    }
 }
 
 // === Emitter ===
@@ -1,10 +1,17 @@
-use std::{future::Future, mem, ops::Deref, sync::Arc, time::Duration};
+use std::{
+    collections::VecDeque,
+    future::Future,
+    mem,
+    ops::Deref,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
 
 use dashmap::DashMap;
-use futures::future::BoxFuture;
+use futures::future::{AbortHandle, Abortable, Aborted, BoxFuture};
 use fxhash::FxBuildHasher;
-use metrics::{decrement_gauge, increment_gauge};
-use parking_lot::RwLock;
+use metrics::{decrement_gauge, increment_counter, increment_gauge};
+use parking_lot::{Mutex, RwLock};
 use tracing::{debug, error, error_span, info, warn, Instrument, Span};
 
 use elfo_utils::CachePadded;
@@ -15,14 +22,20 @@ use crate::{
     actor_status::ActorStatus,
     addr::{Addr, NodeNo},
     config::{AnyConfig, Config, SystemConfig},
-    context::Context,
-    envelope::Envelope,
+    context::{Context, DUMPER},
+    dedup::Deduplicators,
+    dumping::{Direction, Dump},
+    envelope::{Envelope, MessageKind},
     exec::{Exec, ExecResult},
     group::TerminationPolicy,
-    message::Request,
+    inspection::{GroupInspection, RestartRecord, MAX_RESTART_RECORDS},
+    memory_budget::MemoryBudget,
+    message::{Message, Request},
     messages, msg,
     object::{GroupVisitor, Object, OwnedObject},
     panic,
+    persistence::Recovery,
+    response_cache::ResponseCaches,
     restarting::{RestartBackoff, RestartPolicy},
     routers::{Outcome, Router},
     runtime::RuntimeManager,
@@ -47,14 +60,45 @@ pub(crate) struct Supervisor<R: Router<C>, C, X> {
     control: CachePadded<RwLock<Control<C>>>,
     scope_shared: Arc<ScopeGroupShared>,
     status_subscription: Arc<SubscriptionManager>,
+    memory_budget: Arc<MemoryBudget>,
+    deduplicators: Deduplicators,
+    recovery: Option<Recovery>,
     rt_manager: RuntimeManager,
+    /// Recent restarts, see `inspect()`.
+    restarts: Mutex<VecDeque<RestartRecord>>,
 }
 
 struct Control<C> {
     system_config: Arc<SystemConfig>,
     user_config: Option<Arc<C>>,
+    /// The last applied config, used to get configs of actors by keys.
+    config: Option<AnyConfig>,
+    canary: Option<Canary>,
     is_started: bool,
     stop_spawning: bool,
+    /// Whether the task watching the memory budget is spawned.
+    is_budget_watched: bool,
+}
+
+/// The config applied only to a subset of actors, see `UpdateConfigCanary`.
+struct Canary {
+    config: AnyConfig,
+    selector: CanarySelector,
+}
+
+#[derive(Clone)]
+struct CanarySelector {
+    keys: Vec<String>,
+    fraction: f64,
+}
+
+impl CanarySelector {
+    fn matches(&self, key: &str) -> bool {
+        const RESOLUTION: u64 = 10_000;
+
+        self.keys.iter().any(|k| k == key)
+            || fxhash::hash64(key) % RESOLUTION < (self.fraction * RESOLUTION as f64) as u64
+    }
 }
 
 /// Returns `None` if cannot be spawned.
@@ -89,13 +133,19 @@ where
         router: R,
         restart_policy: RestartPolicy,
         termination_policy: TerminationPolicy,
+        response_caches: ResponseCaches,
+        deduplicators: Deduplicators,
+        recovery: Option<Recovery>,
         rt_manager: RuntimeManager,
     ) -> Self {
         let control = Control {
             system_config: Default::default(),
             user_config: None,
+            config: None,
+            canary: None,
             is_started: false,
             stop_spawning: false,
+            is_budget_watched: false,
         };
 
         let status_subscription = SubscriptionManager::new(ctx.clone());
@@ -112,10 +162,16 @@ where
             router,
             exec,
             control: CachePadded::new(RwLock::new(control)),
-            scope_shared: Arc::new(ScopeGroupShared::new(node_no, ctx.group())),
+            scope_shared: Arc::new(
+                ScopeGroupShared::new(node_no, ctx.group()).with_response_caches(response_caches),
+            ),
             status_subscription: Arc::new(status_subscription),
+            memory_budget: Default::default(),
+            deduplicators,
+            recovery,
             context: ctx,
             rt_manager,
+            restarts: Mutex::new(VecDeque::with_capacity(MAX_RESTART_RECORDS)),
         }
     }
 
@@ -131,7 +187,23 @@ where
         .sync_within(|| self.span.in_scope(f));
     }
 
-    pub(crate) fn handle(self: &Arc<Self>, mut envelope: Envelope, visitor: &mut dyn GroupVisitor) {
+    pub(crate) fn handle(self: &Arc<Self>, envelope: Envelope, visitor: &mut dyn GroupVisitor) {
+        if self.deduplicators.is_duplicate(&envelope) {
+            let name = envelope.message().name();
+            self.in_scope(|| {
+                debug!(message = name, "duplicate message is dropped");
+                increment_counter!("elfo_deduplicated_messages_total", "message" => name);
+            });
+            return visitor.done();
+        }
+
+        let caches = self.scope_shared.response_caches();
+        let mut envelope =
+            match caches.try_respond(envelope, self.context.book(), self.context.group()) {
+                Ok(()) => return visitor.done(),
+                Err(envelope) => envelope,
+            };
+
         let outcome = msg!(match &envelope {
             messages::ValidateConfig { config } => match config.decode::<C>() {
                 Ok(config) => {
@@ -196,11 +268,62 @@ where
                     return visitor.done();
                 }
             },
+            messages::UpdateConfigCanary {
+                config,
+                keys,
+                fraction,
+            } => match config.decode::<C>() {
+                Ok(config) => {
+                    let selector = CanarySelector {
+                        keys: keys.clone(),
+                        fraction: *fraction,
+                    };
+                    let canary = Canary {
+                        config: config.clone(),
+                        selector: selector.clone(),
+                    };
+
+                    // Set the canary first to pass the config to actors being spawned.
+                    // The lock is released before iterating over actors, because
+                    // spawning locks them in the reverse order.
+                    self.control.write().canary = Some(canary);
+
+                    let addrs = self
+                        .objects
+                        .iter()
+                        .filter(|object| selector.matches(&object.key().to_string()))
+                        .map(|object| object.value().addr())
+                        .collect::<Vec<_>>();
+
+                    self.in_scope(|| info!(actors = addrs.len(), "config is applied to canaries"));
+
+                    for addr in addrs {
+                        let message = messages::UpdateConfig {
+                            config: config.clone(),
+                        };
+                        let _ = self.context.unbounded_send_to(addr, message);
+                    }
+
+                    return visitor.done();
+                }
+                Err(reason) => {
+                    self.in_scope(
+                        || error!(group = %self.meta.group, %reason, "invalid config is ignored"),
+                    );
+                    return visitor.done();
+                }
+            },
             messages::SubscribeToActorStatuses { forcing } => {
                 let sender = envelope.sender();
                 self.in_scope(|| self.subscribe_to_statuses(sender, *forcing));
                 return visitor.done();
             }
+            messages::RestartActor { addr } => {
+                if let Some(addr) = Addr::from_bits(*addr) {
+                    self.restart_actor(addr);
+                }
+                return visitor.done();
+            }
             messages::Terminate => {
                 if self.termination_policy.stop_spawning {
                     let is_newly = !mem::replace(&mut self.control.write().stop_spawning, true);
@@ -296,10 +419,14 @@ where
         let system_config = control.system_config.clone();
 
         let user_config = control
-            .user_config
+            .canary
             .as_ref()
-            .cloned()
-            .expect("config is unset");
+            .filter(|canary| canary.selector.matches(&key_str))
+            .map(|canary| &canary.config)
+            .or(control.config.as_ref())
+            .expect("config is unset")
+            .get_user_for::<C>(&key_str)
+            .clone();
 
         let ctx = self
             .context
@@ -311,17 +438,22 @@ where
             group: self.meta.group.clone(),
             key: key_str,
         });
+        let (abort_handle, abort_registration) = AbortHandle::new_pair();
         let actor = Actor::new(
             meta.clone(),
             addr,
             &system_config.mailbox,
             self.termination_policy.clone(),
             self.status_subscription.clone(),
+            self.memory_budget.clone(),
+            abort_handle,
         );
 
         drop(control);
 
         let sv = self.clone();
+        let actor_meta = meta.clone();
+        let actor_key = meta.key.clone();
 
         // TODO: move to `harness.rs`.
         let fut = async move {
@@ -338,12 +470,55 @@ where
 
             // It must be called after `entry.insert()`.
             let ctx = ctx.with_addr(addr).with_start_info(start_info);
-            let fut = async { sv.exec.exec(ctx).await.unify() };
-            let new_status = match panic::catch(fut).await {
-                Ok(Ok(())) => ActorStatus::TERMINATED,
-                Ok(Err(err)) => ActorStatus::FAILED.with_details(ErrorChain(&*err)),
-                Err(panic) => ActorStatus::FAILED.with_details(panic),
+            let fut = async {
+                let ctx = match &sv.recovery {
+                    Some(recovery) => match recovery.recover().await {
+                        Ok(log) => ctx.with_event_log(log),
+                        Err(err) => return Err(format!("cannot recover the state: {err}").into()),
+                    },
+                    None => ctx,
+                };
+
+                sv.exec.exec(ctx).await.unify()
             };
+            let fut = Abortable::new(fut, abort_registration);
+            let (new_status, panic) = match panic::catch(fut).await {
+                Ok(Ok(Ok(()))) => (ActorStatus::TERMINATED, None),
+                Ok(Ok(Err(err))) => (ActorStatus::FAILED.with_details(ErrorChain(&*err)), None),
+                Ok(Err(Aborted)) => (
+                    ActorStatus::FAILED.with_details("aborted as unresponsive"),
+                    None,
+                ),
+                Err(panic) => {
+                    let mut status = ActorStatus::FAILED.with_details(&panic);
+                    if let Some(backtrace) = panic.backtrace() {
+                        status = status.with_backtrace(backtrace);
+                    }
+                    (status, Some(panic))
+                }
+            };
+
+            // Clones of the scope can outlive the actor, e.g. in detached tokens.
+            scope::with(Scope::clear_locals);
+            sv.context.topics().unsubscribe_all(addr);
+
+            if let Some(panic) = &panic {
+                // The backtrace is too large to be a part of details, which are
+                // logged on every status change, so it's logged once here.
+                error!(
+                    location = panic.location(),
+                    backtrace = new_status.backtrace(),
+                    "actor panicked"
+                );
+
+                if sv.control.read().system_config.dumping.failures {
+                    dump_failure(actor_meta, addr, &new_status);
+                }
+            }
+
+            // Used in records about restarting.
+            let panic_message = panic.as_ref().map(|p| p.message());
+            let panic_location = panic.as_ref().and_then(|p| p.location());
 
             let restart_after = {
                 let object = sv.objects.get(&key).expect("where is the current actor?");
@@ -364,25 +539,41 @@ where
                 let restarting_allowed = restart_policy.restarting_allowed(&new_status)
                     && !sv.control.read().stop_spawning;
 
+                let finished_status = new_status.clone();
                 actor.set_status(new_status);
 
-                restarting_allowed
+                let restart_after = restarting_allowed
                     .then(|| {
                         restart_policy
                             .restart_params()
                             .and_then(|p| backoff.next(&p))
                     })
-                    .flatten()
+                    .flatten();
+
+                if restart_after.is_some() {
+                    sv.remember_restart(actor_key, finished_status);
+                }
+
+                restart_after
             };
 
             let _ = if let Some(after) = restart_after {
                 if after == Duration::ZERO {
-                    debug!("actor will be restarted immediately");
+                    debug!(
+                        panic = panic_message,
+                        location = panic_location,
+                        "actor will be restarted immediately"
+                    );
                 } else {
-                    debug!(?after, "actor will be restarted");
+                    debug!(
+                        ?after,
+                        panic = panic_message,
+                        location = panic_location,
+                        "actor will be restarted"
+                    );
 
                     increment_gauge!("elfo_restarting_actors", 1.);
-                    tokio::time::sleep(after).await;
+                    scope::runtime().sleep(after).await;
                     decrement_gauge!("elfo_restarting_actors", 1.);
                 }
 
@@ -396,7 +587,11 @@ where
                     sv.objects.remove(&key).map(|(_, v)| v)
                 }
             } else {
-                debug!("actor won't be restarted");
+                debug!(
+                    panic = panic_message,
+                    location = panic_location,
+                    "actor won't be restarted"
+                );
                 sv.objects.remove(&key).map(|(_, v)| v)
             }
             .expect("where is the current actor?");
@@ -407,17 +602,18 @@ where
 
         let rt = self.rt_manager.get(&meta);
 
-        entry.insert(Object::new(addr, actor));
+        entry.insert(Object::new(addr, Box::new(actor)));
 
         let scope = Scope::new(scope::trace_id(), addr, meta, self.scope_shared.clone())
-            .with_telemetry(&system_config.telemetry);
+            .with_telemetry(&system_config.telemetry)
+            .with_runtime(rt.clone());
 
         #[cfg(feature = "unstable-stuck-detection")]
         let fut = MeasurePoll::new(fut.instrument(span), self.rt_manager.stuck_detector());
         #[cfg(not(feature = "unstable-stuck-detection"))]
         let fut = MeasurePoll::new(fut.instrument(span));
 
-        rt.spawn(scope.within(fut));
+        rt.spawn(Box::pin(scope.within(fut)));
         let object = self.context.book().get_owned(addr).expect("just created");
         Some(object)
     }
@@ -441,15 +637,29 @@ where
         }
     }
 
-    fn update_config(&self, control: &mut Control<C>, config: &AnyConfig) {
+    fn update_config(self: &Arc<Self>, control: &mut Control<C>, config: &AnyConfig) {
         let system = config.get_system();
         self.scope_shared.configure(system);
+        self.memory_budget.configure(&system.memory_budget);
+
+        if system.memory_budget.soft_limit.is_some() && !control.is_budget_watched {
+            control.is_budget_watched = true;
+            self.watch_memory_budget();
+        }
+
+        if control.system_config.runtime != system.runtime {
+            if let Err(err) = self.rt_manager.configure(&system.runtime) {
+                self.in_scope(|| error!(error = %err, "cannot configure the runtime"));
+            }
+        }
 
         let need_to_update_actors = control.system_config.mailbox != system.mailbox;
 
         // Update user's config.
         control.system_config = system.clone();
         control.user_config = Some(config.get_user::<C>().clone());
+        control.config = Some(config.clone());
+        control.canary = None;
 
         self.router
             .update(control.user_config.as_ref().expect("just saved"));
@@ -474,6 +684,33 @@ where
         });
     }
 
+    /// Updates statuses of actors once the memory budget is crossed. It's done
+    /// apart from actors, because stuck actors are the most likely reason.
+    fn watch_memory_budget(self: &Arc<Self>) {
+        let budget = self.memory_budget.clone();
+        let sv = Arc::downgrade(self);
+
+        let fut = async move {
+            while budget.changed().await {
+                let Some(sv) = sv.upgrade() else { break };
+
+                // Statuses are sent to subscribers, so avoid locking `objects`.
+                let addrs = sv.objects.iter().map(|o| o.addr()).collect::<Vec<_>>();
+
+                for addr in addrs {
+                    let object = ward!(sv.context.book().get_owned(addr), continue);
+                    let actor = object.as_actor().expect("a supervisor stores only actors");
+                    let meta = actor.meta().clone();
+
+                    Scope::new(TraceId::generate(), addr, meta, sv.scope_shared.clone())
+                        .sync_within(|| actor.check_memory_budget());
+                }
+            }
+        };
+
+        self.rt_manager.get(&self.meta).spawn(Box::pin(fut));
+    }
+
     fn subscribe_to_statuses(&self, addr: Addr, forcing: bool) {
         // Firstly, add the subscriber to handle new objects right way.
         if !self.status_subscription.add(addr) && !forcing {
@@ -499,6 +736,60 @@ where
         }
     }
 
+    fn remember_restart(&self, key: String, status: ActorStatus) {
+        let mut restarts = self.restarts.lock();
+        if restarts.len() == MAX_RESTART_RECORDS {
+            restarts.pop_front();
+        }
+        restarts.push_back(RestartRecord {
+            key,
+            at: SystemTime::now(),
+            status,
+        });
+    }
+
+    fn restart_actor(&self, addr: Addr) {
+        // Only actors of this group can be restarted.
+        if addr.group_no() != self.context.group().group_no() {
+            return;
+        }
+
+        let object = ward!(self.context.book().get_owned(addr), return);
+        let actor = ward!(object.as_actor(), return);
+
+        if actor.status_kind().is_finished() {
+            return;
+        }
+
+        self.in_scope(|| {
+            let key = &actor.meta().key;
+            warn!(%addr, %key, "aborting the unresponsive actor");
+            increment_counter!("elfo_aborted_actors_total");
+        });
+
+        actor.abort();
+    }
+
+    /// Called if the envelope handled by `handle()` hasn't been delivered.
+    pub(crate) fn undelivered(&self, envelope: &Envelope) {
+        self.deduplicators.forget(envelope);
+    }
+
+    pub(crate) fn inspect(&self) -> GroupInspection {
+        let mut actors = self
+            .objects
+            .iter()
+            .filter_map(|r| Some(r.value().as_actor()?.inspect(r.value().addr())))
+            .collect::<Vec<_>>();
+        actors.sort_unstable_by(|a, b| a.key.cmp(&b.key));
+
+        GroupInspection {
+            name: self.meta.group.clone(),
+            actors,
+            restarts: self.restarts.lock().iter().cloned().collect(),
+        }
+    }
+
     pub(crate) fn finished(self: &Arc<Self>) -> BoxFuture<'static, ()> {
         let sv = self.clone();
         let addrs = self
@@ -517,6 +808,32 @@ where
 
         Box::pin(fut)
     }
+
+    pub(crate) fn shutdown_runtime(self: &Arc<Self>) -> BoxFuture<'static, ()> {
+        let sv = self.clone();
+        Box::pin(async move { sv.rt_manager.shutdown().await })
+    }
+}
+
+impl<R: Router<C>, C, X> Drop for Supervisor<R, C, X> {
+    fn drop(&mut self) {
+        // Stops the task spawned by `watch_memory_budget()`.
+        self.memory_budget.close();
+    }
+}
+
+// Dumps the failure to make it visible next to the messages handled by the
+// actor.
+fn dump_failure(meta: Arc<ActorMeta>, addr: Addr, status: &ActorStatus) {
+    let report = messages::ActorStatusReport {
+        meta,
+        status: status.clone(),
+    };
+
+    if let Some(permit) = DUMPER.acquire_m(&report) {
+        let kind = MessageKind::regular(addr);
+        permit.record(Dump::message(&report, &kind, Direction::Out));
+    }
 }
 
 fn extract_response_token<R: Request>(envelope: Envelope) -> ResponseToken<R> {
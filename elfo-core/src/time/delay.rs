@@ -130,4 +130,17 @@ impl<M: Message> SourceStream for DelaySource<M> {
 
         Poll::Ready(Some(envelope))
     }
+
+    #[cfg(feature = "test-util")]
+    fn fire(self: Pin<&mut Self>, message_type: std::any::TypeId) -> Option<Envelope> {
+        if message_type != std::any::TypeId::of::<M>() {
+            return None;
+        }
+
+        let this = self.project();
+        let message = this.message.take()?;
+        let kind = MessageKind::regular(Addr::NULL);
+        let trace_id = this.trace_id.take().unwrap_or_else(TraceId::generate);
+        Some(Envelope::with_trace_id(message, kind, trace_id))
+    }
 }
@@ -252,4 +252,16 @@ impl<M: Message> SourceStream for IntervalSource<M> {
 
         Poll::Ready(Some(envelope))
     }
+
+    #[cfg(feature = "test-util")]
+    fn fire(self: Pin<&mut Self>, message_type: std::any::TypeId) -> Option<Envelope> {
+        if message_type != std::any::TypeId::of::<M>() {
+            return None;
+        }
+
+        let message = self.project().message.clone();
+        let kind = MessageKind::regular(Addr::NULL);
+        let trace_id = TraceId::generate();
+        Some(Envelope::with_trace_id(message, kind, trace_id))
+    }
 }
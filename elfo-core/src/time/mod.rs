@@ -2,10 +2,13 @@ use std::time::Duration;
 
 use tokio::time::Instant;
 
-pub use self::{delay::Delay, interval::Interval};
+pub use elfo_utils::{KeyedRateLimiter, RateLimit, RateLimiter};
+
+pub use self::{delay::Delay, interval::Interval, throttle::Throttle};
 
 mod delay;
 mod interval;
+mod throttle;
 
 fn far_future() -> Instant {
     // Copied from `tokio`.
@@ -0,0 +1,128 @@
+use tokio::time::{Duration, Instant};
+
+use elfo_utils::{RateLimit, RateLimiter};
+
+use crate::{addr::Addr, context::Context, errors::SendError, message::Message};
+
+/// How often to check if the limit is changed while it forbids everything.
+const DISABLED_RECHECK_PERIOD: Duration = Duration::from_secs(1);
+
+/// An async wrapper around [`RateLimiter`], which waits for a permit instead
+/// of rejecting an operation, e.g. to throttle outbound messages.
+///
+/// Unlike [`RateLimiter`], time is measured by the tokio's clock, which is
+/// also used to wait, so the throttle works with the paused time in tests.
+///
+/// Can be shared between actors by wrapping into `Arc`.
+///
+/// # Example
+///
+/// ```
+/// # use elfo_core as elfo;
+/// # async fn exec(mut ctx: elfo::Context) {
+/// # use elfo::{message, msg};
+/// # #[message]
+/// # struct SomeEvent;
+/// # let downstream = elfo::Addr::NULL;
+/// use elfo::time::{RateLimit, Throttle};
+///
+/// let throttle = Throttle::new(RateLimit::Rps(100));
+///
+/// while let Some(envelope) = ctx.recv().await {
+///     msg!(match envelope {
+///         event @ SomeEvent => {
+///             // The same as `throttle.acquire().await` followed by `ctx.send_to()`.
+///             let _ = throttle.send_to(&ctx, downstream, event).await;
+///         },
+///     });
+/// }
+/// # }
+/// ```
+pub struct Throttle {
+    limiter: RateLimiter,
+    origin: Instant,
+}
+
+impl Throttle {
+    /// Creates a new throttle.
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            limiter: RateLimiter::new(limit),
+            origin: Instant::now(),
+        }
+    }
+
+    /// Reconfigures the throttle, e.g. on config updates.
+    pub fn configure(&self, limit: RateLimit) {
+        self.limiter.configure(limit);
+    }
+
+    /// Acquires one permit without waiting.
+    /// Returns `true` if an operation is allowed.
+    pub fn try_acquire(&self) -> bool {
+        self.limiter.acquire_at(self.now())
+    }
+
+    /// Waits until one permit is acquired.
+    ///
+    /// If the limit forbids everything, waits until the throttle is
+    /// reconfigured.
+    pub async fn acquire(&self) {
+        while !self.try_acquire() {
+            let wait = self
+                .limiter
+                .next_permit_at(self.now())
+                .unwrap_or(DISABLED_RECHECK_PERIOD);
+
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Waits for a permit and sends a message using the inter-group routing,
+    /// see [`Context::send()`].
+    pub async fn send<M: Message, C, K>(
+        &self,
+        ctx: &Context<C, K>,
+        message: M,
+    ) -> Result<(), SendError<M>> {
+        self.acquire().await;
+        ctx.send(message).await
+    }
+
+    /// Waits for a permit and sends a message to the specified recipient,
+    /// see [`Context::send_to()`].
+    pub async fn send_to<M: Message, C, K>(
+        &self,
+        ctx: &Context<C, K>,
+        recipient: Addr,
+        message: M,
+    ) -> Result<(), SendError<M>> {
+        self.acquire().await;
+        ctx.send_to(recipient, message).await
+    }
+
+    fn now(&self) -> Duration {
+        self.origin.elapsed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn acquire() {
+        let throttle = Throttle::new(RateLimit::Custom(1, Duration::from_millis(50)));
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            throttle.acquire().await;
+        }
+
+        assert!(start.elapsed() >= Duration::from_millis(50));
+        assert!(!throttle.try_acquire());
+
+        throttle.configure(RateLimit::Unlimited);
+        assert!(throttle.try_acquire());
+    }
+}
@@ -0,0 +1,143 @@
+//! Contains `Topics` that stores subscribers of named topics.
+//! See [`Context::subscribe()`] and [`Context::publish()`] for details.
+//!
+//! [`Context::subscribe()`]: crate::Context::subscribe()
+//! [`Context::publish()`]: crate::Context::publish()
+
+use std::sync::Arc;
+
+use fxhash::FxHashMap;
+use idr_ebr::EbrGuard;
+use parking_lot::RwLock;
+use smallvec::SmallVec;
+
+use crate::{address_book::AddressBook, Addr};
+
+pub(crate) type Subscribers = SmallVec<[Addr; 4]>;
+
+/// Subscribers of topics, shared by all actors of the same system.
+#[derive(Clone, Default)]
+pub(crate) struct Topics {
+    inner: Arc<RwLock<Inner>>,
+}
+
+#[derive(Default)]
+struct Inner {
+    subscribers: FxHashMap<String, Vec<Addr>>,
+    /// Topics of every subscribed actor, see `Topics::unsubscribe_all()`.
+    topics: FxHashMap<Addr, Vec<String>>,
+}
+
+impl Inner {
+    fn remove(&mut self, topic: &str, addr: Addr) -> bool {
+        let subscribers = ward!(self.subscribers.get_mut(topic), return false);
+        let len = subscribers.len();
+        subscribers.retain(|a| *a != addr);
+        let removed = subscribers.len() != len;
+
+        if subscribers.is_empty() {
+            self.subscribers.remove(topic);
+        }
+
+        if let Some(topics) = self.topics.get_mut(&addr) {
+            topics.retain(|t| t != topic);
+
+            if topics.is_empty() {
+                self.topics.remove(&addr);
+            }
+        }
+
+        removed
+    }
+}
+
+impl Topics {
+    /// Returns `false` if the actor is already subscribed to the topic.
+    pub(crate) fn subscribe(&self, topic: &str, addr: Addr) -> bool {
+        let mut inner = self.inner.write();
+
+        match inner.subscribers.get_mut(topic) {
+            Some(subscribers) if subscribers.contains(&addr) => return false,
+            Some(subscribers) => subscribers.push(addr),
+            None => {
+                inner.subscribers.insert(topic.into(), vec![addr]);
+            }
+        }
+
+        inner.topics.entry(addr).or_default().push(topic.into());
+        true
+    }
+
+    /// Returns `false` if the actor isn't subscribed to the topic.
+    pub(crate) fn unsubscribe(&self, topic: &str, addr: Addr) -> bool {
+        self.inner.write().remove(topic, addr)
+    }
+
+    /// Unsubscribes the actor from all topics, called once the actor is
+    /// finished, so topics that are rarely published don't grow.
+    pub(crate) fn unsubscribe_all(&self, addr: Addr) {
+        if !self.inner.read().topics.contains_key(&addr) {
+            return;
+        }
+
+        let mut inner = self.inner.write();
+        for topic in inner.topics.remove(&addr).unwrap_or_default() {
+            inner.remove(&topic, addr);
+        }
+    }
+
+    /// Returns alive subscribers of the topic.
+    ///
+    /// Actors are unsubscribed once they are finished, but it's racy, so
+    /// terminated actors are also detected and removed here.
+    pub(crate) fn subscribers(&self, topic: &str, book: &AddressBook) -> Subscribers {
+        let mut subscribers = Subscribers::new();
+        let mut dead = Subscribers::new();
+
+        {
+            let inner = self.inner.read();
+            let guard = EbrGuard::new();
+
+            for addr in ward!(inner.subscribers.get(topic), return subscribers) {
+                if book.get(*addr, &guard).is_some() {
+                    subscribers.push(*addr);
+                } else {
+                    dead.push(*addr);
+                }
+            }
+        }
+
+        if !dead.is_empty() {
+            let mut inner = self.inner.write();
+            for addr in dead {
+                inner.remove(topic, addr);
+            }
+        }
+
+        subscribers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unsubscribe_all() {
+        let topics = Topics::default();
+        let a = Addr::from_bits(1 << 40 | 1).unwrap();
+        let b = Addr::from_bits(1 << 40 | 2).unwrap();
+
+        assert!(topics.subscribe("x", a));
+        assert!(topics.subscribe("y", a));
+        assert!(topics.subscribe("x", b));
+
+        topics.unsubscribe_all(a);
+
+        let inner = topics.inner.read();
+        assert_eq!(inner.subscribers.len(), 1);
+        assert_eq!(inner.subscribers["x"], vec![b]);
+        assert_eq!(inner.topics.len(), 1);
+        assert_eq!(inner.topics[&b], vec!["x".to_string()]);
+    }
+}
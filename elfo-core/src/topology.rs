@@ -1,20 +1,27 @@
-use std::{cell::RefCell, sync::Arc};
+use std::{fmt::Write as _, sync::Arc};
 
 use parking_lot::RwLock;
 use sealed::sealed;
-use tokio::runtime::Handle;
+use serde::Serialize;
+use tokio::time::timeout;
 
 #[cfg(feature = "unstable-stuck-detection")]
 use crate::stuck_detection::StuckDetector;
 use crate::{
     addr::{Addr, GroupNo, NodeLaunchId, NodeNo},
     address_book::{AddressBook, VacantEntry},
+    config::AnyConfig,
     context::Context,
     demux::Demux,
     envelope::Envelope,
-    group::Blueprint,
+    group::{Blueprint, ConfigRenderFn, ConfigSchemaFn},
+    init::SEND_CLOSING_TERMINATE_AFTER,
+    inspection::GroupInspection,
+    messages::Terminate,
     object::Object,
-    runtime::RuntimeManager,
+    runtime::{Runtime, RuntimeManager},
+    sender::ExternalSender,
+    topic::Topics,
 };
 
 pub(crate) const SYSTEM_INIT_GROUP_NO: u8 = 1;
@@ -25,6 +32,7 @@ pub struct Topology {
     node_no: NodeNo,
     launch_id: NodeLaunchId,
     pub(crate) book: AddressBook,
+    pub(crate) topics: Topics,
     inner: Arc<RwLock<Inner>>,
 }
 
@@ -58,6 +66,22 @@ pub struct LocalActorGroup {
     pub name: String,
     pub is_entrypoint: bool,
     pub(crate) stop_order: i8,
+    pub(crate) config_schema: Option<ConfigSchemaFn>,
+    pub(crate) config_render: Option<ConfigRenderFn>,
+    pub(crate) demux: Demux,
+}
+
+impl LocalActorGroup {
+    /// Decodes the config by the config type of the mounted blueprint and
+    /// renders it by `Debug`, so default values are filled in.
+    /// Returns `None` if the group isn't mounted yet.
+    ///
+    /// Used to snapshot configs in tests, so rendered configs are not masked
+    /// unless their types mask values in `Debug`.
+    #[stability::unstable]
+    pub fn render_config(&self, config: &AnyConfig) -> Option<Result<String, String>> {
+        self.config_render.map(|render| render(config))
+    }
 }
 
 /// Represents a connection between two groups.
@@ -102,6 +126,7 @@ impl Topology {
             node_no: NodeNo::generate(),
             launch_id,
             book: AddressBook::new(launch_id),
+            topics: Topics::default(),
             inner: Arc::new(RwLock::new(Inner::default())),
         }
     }
@@ -123,13 +148,17 @@ impl Topology {
         self.launch_id
     }
 
+    /// Runs actors matching the filter on the provided runtime, e.g. a tokio
+    /// [`Handle`](tokio::runtime::Handle) or a custom [`Runtime`]. Filters are
+    /// checked in the order they're added, other actors run on the runtime
+    /// used to start the system.
     #[stability::unstable]
     pub fn add_dedicated_rt<F: Fn(&crate::ActorMeta) -> bool + Send + Sync + 'static>(
         &self,
         filter: F,
-        handle: Handle,
+        runtime: impl Runtime,
     ) {
-        self.inner.write().rt_manager.add(filter, handle);
+        self.inner.write().rt_manager.add(filter, runtime);
     }
 
     #[cfg(feature = "unstable-stuck-detection")]
@@ -137,6 +166,14 @@ impl Topology {
         self.inner.read().rt_manager.stuck_detector()
     }
 
+    /// Returns a handle to send messages to the specified recipient (usually,
+    /// a group) from code outside the actor system.
+    ///
+    /// See [`ExternalSender`] for details.
+    pub fn sender_to(&self, recipient: Addr) -> ExternalSender {
+        ExternalSender::new(self.book.clone(), recipient)
+    }
+
     /// Declares a new local group.
     ///
     /// # Panics
@@ -157,19 +194,90 @@ impl Topology {
         let group_no = GroupNo::new(inner.last_group_no, self.launch_id).expect("invalid group no");
 
         let entry = self.book.vacant_entry(group_no);
+        let demux = Demux::default();
         inner.locals.push(LocalActorGroup {
             addr: entry.addr(),
             name: name.clone(),
             is_entrypoint: false,
             stop_order: 0,
+            config_schema: None,
+            config_render: None,
+            demux: demux.clone(),
         });
 
         Local {
+            topology: self,
             name,
+            addr: entry.addr(),
+            entry: Some(entry),
+            demux,
+        }
+    }
+
+    /// Returns settings of an already declared local group, e.g. to add
+    /// routes from it to a group declared at runtime.
+    ///
+    /// Returns `None` if there is no such group.
+    pub fn existing_local(&self, name: &str) -> Option<Local<'_>> {
+        let inner = self.inner.read();
+        let group = inner.locals.iter().find(|group| group.name == name)?;
+
+        Some(Local {
             topology: self,
-            entry,
-            demux: RefCell::new(Demux::default()),
+            name: group.name.clone(),
+            addr: group.addr,
+            entry: None,
+            demux: group.demux.clone(),
+        })
+    }
+
+    /// Removes a local group from the running topology.
+    ///
+    /// Routes from and to the group are removed atomically, so no new messages
+    /// are routed to it. Then, the group is terminated like on the system
+    /// shutdown: it receives the polite [`Terminate`] and then, if it's still
+    /// alive after some time, the closing one. Finally, the group's dedicated
+    /// runtime, if any, is stopped and the group's address becomes invalid.
+    ///
+    /// Returns `false` if there is no such group.
+    pub async fn unmount(&self, name: &str) -> bool {
+        let addr = {
+            let mut inner = self.inner.write();
+            let Some(index) = inner.locals.iter().position(|group| group.name == name) else {
+                return false;
+            };
+
+            let addr = inner.locals.remove(index).addr;
+
+            for group in &inner.locals {
+                group.demux.remove_routes_to(addr);
+            }
+
+            inner.connections.retain(|connection| {
+                connection.from != addr
+                    && !matches!(connection.to, ConnectionTo::Local(to) if to == addr)
+            });
+
+            addr
+        };
+
+        if let Some(object) = self.book.get_owned(addr) {
+            let sender = self.sender_to(addr);
+            let _ = sender.unbounded_send(Terminate::default());
+
+            if timeout(SEND_CLOSING_TERMINATE_AFTER, object.finished())
+                .await
+                .is_err()
+            {
+                let _ = sender.unbounded_send(Terminate::closing());
+                object.finished().await;
+            }
+
+            object.shutdown_runtime().await;
         }
+
+        self.book.remove(addr);
+        true
     }
 
     /// Returns an iterator over all local groups.
@@ -183,6 +291,216 @@ impl Topology {
         let inner = self.inner.read();
         inner.connections.clone().into_iter()
     }
+
+    /// Returns snapshots of mounted local groups: statuses and mailboxes of
+    /// actors and recent restarts. See [`inspection`](crate::inspection).
+    #[stability::unstable]
+    pub fn inspect(&self) -> Vec<GroupInspection> {
+        self.locals()
+            .filter_map(|group| self.book.get_owned(group.addr)?.inspect_group())
+            .collect()
+    }
+
+    /// Exports groups and connections between them in the Graphviz DOT
+    /// format. Remote groups are rendered with dashed borders and nodes
+    /// they're available on.
+    ///
+    /// Groups are identified by names, so exports of different launches and
+    /// releases can be compared.
+    pub fn export_dot(&self) -> String {
+        let export = self.export();
+        let mut dot = String::from("digraph topology {\n");
+
+        for group in &export.groups {
+            let style = if group.is_entrypoint {
+                ", style=bold"
+            } else {
+                ""
+            };
+            let _ = writeln!(dot, "    {} [shape=box{style}];", quote(&group.name));
+        }
+
+        for remote in &export.remotes {
+            let nodes = remote.nodes.iter().map(ToString::to_string);
+            let nodes = nodes.collect::<Vec<_>>().join(", ");
+            let label = format!("{} (nodes: {nodes})", remote.name);
+            let _ = writeln!(
+                dot,
+                "    {} [shape=box, style=dashed, label={}];",
+                quote(&format!("remote:{}", remote.name)),
+                quote(&label)
+            );
+        }
+
+        for connection in &export.connections {
+            let to = if connection.remote {
+                format!("remote:{}", connection.to)
+            } else {
+                connection.to.clone()
+            };
+
+            let _ = writeln!(dot, "    {} -> {};", quote(&connection.from), quote(&to));
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Exports groups and connections between them as JSON.
+    /// See [`Topology::export_dot()`] for details.
+    pub fn export_json(&self) -> String {
+        serde_json::to_string_pretty(&self.export()).expect("cannot serialize the topology")
+    }
+
+    /// Exports the JSON Schema (draft 7) of the config file, combined from
+    /// schemas of groups' configs attached by
+    /// [`ActorGroup::config_schema()`](crate::ActorGroup::config_schema).
+    /// Groups without attached schemas accept any config.
+    ///
+    /// Sections are nested by dot-separated names of groups, e.g.
+    /// the `system.configurers` group is described as `[system.configurers]`.
+    pub fn export_config_schema(&self) -> String {
+        let inner = self.inner.read();
+        let mut root = serde_json::Map::new();
+
+        for group in &inner.locals {
+            let Some(schema) = group.config_schema else {
+                continue;
+            };
+
+            let mut properties = &mut root;
+            let mut parts = group.name.split('.').peekable();
+            while let Some(part) = parts.next() {
+                let section = properties
+                    .entry(part)
+                    .or_insert_with(|| serde_json::json!({ "type": "object", "properties": {} }));
+
+                if parts.peek().is_none() {
+                    *section = schema();
+                    break;
+                }
+
+                // Groups nested into non-object configs are ignored.
+                let Some(nested) = section["properties"].as_object_mut() else {
+                    break;
+                };
+                properties = nested;
+            }
+        }
+
+        let schema = serde_json::json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "type": "object",
+            "properties": root,
+        });
+
+        serde_json::to_string_pretty(&schema).expect("cannot serialize the schema")
+    }
+
+    fn export(&self) -> Export {
+        let inner = self.inner.read();
+
+        let name_of = |addr: Addr| {
+            inner
+                .locals
+                .iter()
+                .find(|group| group.addr == addr)
+                .map_or_else(|| addr.to_string(), |group| group.name.clone())
+        };
+
+        let groups = inner
+            .locals
+            .iter()
+            .map(|group| ExportedGroup {
+                name: group.name.clone(),
+                is_entrypoint: group.is_entrypoint,
+                stop_order: group.stop_order,
+            })
+            .collect();
+
+        #[cfg(feature = "network")]
+        let remotes = inner
+            .remotes
+            .iter()
+            .map(|group| {
+                let mut nodes = group
+                    .nodes
+                    .values()
+                    .flat_map(|nodes| nodes.load().keys().copied().collect::<Vec<_>>())
+                    .collect::<Vec<_>>();
+
+                nodes.sort_unstable();
+                nodes.dedup();
+
+                ExportedRemote {
+                    name: group.name.clone(),
+                    nodes,
+                }
+            })
+            .collect();
+
+        #[cfg(not(feature = "network"))]
+        let remotes = Vec::new();
+
+        let mut connections = Vec::<ExportedConnection>::new();
+        for connection in &inner.connections {
+            let (to, remote) = match &connection.to {
+                ConnectionTo::Local(addr) => (name_of(*addr), false),
+                #[cfg(feature = "network")]
+                ConnectionTo::Remote(name) => (name.clone(), true),
+            };
+
+            let connection = ExportedConnection {
+                from: name_of(connection.from),
+                to,
+                remote,
+            };
+
+            // Several routes between the same groups are exported once.
+            if !connections.contains(&connection) {
+                connections.push(connection);
+            }
+        }
+
+        Export {
+            node_no: self.node_no,
+            groups,
+            remotes,
+            connections,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Export {
+    node_no: NodeNo,
+    groups: Vec<ExportedGroup>,
+    remotes: Vec<ExportedRemote>,
+    connections: Vec<ExportedConnection>,
+}
+
+#[derive(Serialize)]
+struct ExportedGroup {
+    name: String,
+    is_entrypoint: bool,
+    stop_order: i8,
+}
+
+#[derive(Serialize)]
+struct ExportedRemote {
+    name: String,
+    nodes: Vec<NodeNo>,
+}
+
+#[derive(PartialEq, Serialize)]
+struct ExportedConnection {
+    from: String,
+    to: String,
+    remote: bool,
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
 }
 
 /// Represents a local group's settings.
@@ -190,14 +508,16 @@ impl Topology {
 pub struct Local<'t> {
     topology: &'t Topology,
     name: String,
-    entry: VacantEntry<'t>,
-    demux: RefCell<Demux>,
+    addr: Addr,
+    /// `None` if the group is obtained by `Topology::existing_local()`.
+    entry: Option<VacantEntry<'t>>,
+    demux: Demux,
 }
 
 impl Local<'_> {
     #[doc(hidden)]
     pub fn addr(&self) -> Addr {
-        self.entry.addr()
+        self.addr
     }
 
     /// Mark this group as an entrypoint.
@@ -233,38 +553,56 @@ impl Local<'_> {
     /// ```
     ///
     /// Local to remote (requires the `network` feature): TODO
+    ///
+    /// Routes can be added at runtime, they're applied to running actors.
     pub fn route_to<F>(&self, dest: &impl Destination<F>, filter: F) {
         dest.extend_demux(
-            self.entry.addr().group_no().expect("invalid addr"),
-            &mut self.demux.borrow_mut(),
+            self.addr.group_no().expect("invalid addr"),
+            &self.demux,
             filter,
         );
 
         let mut inner = self.topology.inner.write();
         inner.connections.push(Connection {
-            from: self.entry.addr(),
+            from: self.addr,
             to: dest.connection_endpoint(),
         });
     }
 
     // TODO: deprecate?
     pub fn route_all_to(&self, dest: &Local<'_>) {
-        let addr = dest.entry.addr();
+        let addr = dest.addr;
         self.demux
-            .borrow_mut()
-            .append(move |_, addrs| addrs.push(addr));
+            .append(Some(addr), move |_, addrs| addrs.push(addr));
     }
 
     /// Mounts a blueprint to this group.
+    ///
+    /// Groups can be mounted at runtime. Such groups start once they receive
+    /// a config, e.g. after `ReloadConfigs` is sent to configurers.
+    ///
+    /// # Panics
+    /// If the group is already mounted.
     pub fn mount(self, blueprint: Blueprint) {
-        self.with_group_mut(|group| group.stop_order = blueprint.stop_order);
+        let entry = self.entry.expect("the group is already mounted");
+
+        let mut inner = self.topology.inner.write();
+        let group = inner
+            .locals
+            .iter_mut()
+            .find(|group| group.addr == self.addr)
+            .expect("no corresponding group for Local<_>");
+        group.stop_order = blueprint.stop_order;
+        group.config_schema = blueprint.config_schema;
+        group.config_render = Some(blueprint.config_render);
+        let rt_manager = inner.rt_manager.clone();
+        drop(inner);
 
-        let addr = self.entry.addr();
         let book = self.topology.book.clone();
-        let ctx = Context::new(book, self.demux.into_inner()).with_group(addr);
-        let rt_manager = self.topology.inner.read().rt_manager.clone();
+        let topics = self.topology.topics.clone();
+        let ctx = Context::new(book, topics, self.demux).with_group(self.addr);
         let object = (blueprint.mount)(ctx, self.topology.node_no, self.name, rt_manager);
-        self.entry.insert(object);
+        entry.insert(object);
     }
 
     fn with_group_mut(&self, f: impl FnOnce(&mut LocalActorGroup)) {
@@ -272,7 +610,7 @@ impl Local<'_> {
         let group = inner
             .locals
             .iter_mut()
-            .find(|group| group.addr == self.entry.addr())
+            .find(|group| group.addr == self.addr)
             .expect("no corresponding group for Local<_>");
         f(group);
     }
@@ -281,7 +619,7 @@ impl Local<'_> {
 #[sealed]
 pub trait Destination<F> {
     #[doc(hidden)]
-    fn extend_demux(&self, source_group_no: GroupNo, demux: &mut Demux, filter: F);
+    fn extend_demux(&self, source_group_no: GroupNo, demux: &Demux, filter: F);
 
     #[doc(hidden)]
     fn connection_endpoint(&self) -> ConnectionTo;
@@ -292,9 +630,9 @@ impl<F> Destination<F> for Local<'_>
 where
     F: Fn(&Envelope) -> bool + Send + Sync + 'static,
 {
-    fn extend_demux(&self, _: GroupNo, demux: &mut Demux, filter: F) {
-        let addr = self.entry.addr();
-        demux.append(move |envelope, addrs| {
+    fn extend_demux(&self, _: GroupNo, demux: &Demux, filter: F) {
+        let addr = self.addr;
+        demux.append(Some(addr), move |envelope, addrs| {
             if filter(envelope) {
                 addrs.push(addr);
             }
@@ -302,7 +640,7 @@ where
     }
 
     fn connection_endpoint(&self) -> ConnectionTo {
-        ConnectionTo::Local(self.entry.addr())
+        ConnectionTo::Local(self.addr)
     }
 }
 
@@ -424,7 +762,7 @@ cfg_network!({
     where
         F: Fn(&Envelope, &NodeDiscovery) -> Outcome + Send + Sync + 'static,
     {
-        fn extend_demux(&self, local_group_no: GroupNo, demux: &mut Demux, filter: F) {
+        fn extend_demux(&self, local_group_no: GroupNo, demux: &Demux, filter: F) {
             let nodes = self
                 .topology
                 .inner
@@ -438,7 +776,7 @@ cfg_network!({
                 .or_default()
                 .clone();
 
-            demux.append(move |envelope, addrs| {
+            demux.append(None, move |envelope, addrs| {
                 let discovery = NodeDiscovery(());
 
                 match filter(envelope, &discovery) {
@@ -532,3 +870,101 @@ cfg_network!({
         }
     }
 });
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn topology() -> Topology {
+        let mut topology = Topology::empty();
+        topology.set_node_no(NodeNo::from_bits(7).unwrap());
+
+        {
+            let _configurers = topology.local("system.configurers").entrypoint();
+            let producers = topology.local("producers");
+            let consumers = topology.local("consumers");
+
+            producers.route_to(&consumers, |_| true);
+            producers.route_to(&consumers, |_| false);
+            consumers.route_to(&producers, |_| true);
+        }
+
+        topology
+    }
+
+    #[test]
+    fn export_dot() {
+        assert_eq!(
+            topology().export_dot(),
+            r#"digraph topology {
+    "system.configurers" [shape=box, style=bold];
+    "producers" [shape=box];
+    "consumers" [shape=box];
+    "producers" -> "consumers";
+    "consumers" -> "producers";
+}
+"#
+        );
+
+        assert_eq!(quote(r#"a"b\c"#), r#""a\"b\\c""#);
+    }
+
+    #[cfg(feature = "schema")]
+    #[test]
+    fn export_config_schema() {
+        #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+        struct Config {
+            #[allow(dead_code)]
+            limit: u32,
+        }
+
+        let topology = topology();
+        let group = crate::ActorGroup::new()
+            .config::<Config>()
+            .config_schema()
+            .exec(|_| async {});
+
+        let schema = group.config_schema.unwrap()();
+        topology.inner.write().locals[0].config_schema = group.config_schema;
+
+        let json: serde_json::Value =
+            serde_json::from_str(&topology.export_config_schema()).unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "$schema": "http://json-schema.org/draft-07/schema#",
+                "type": "object",
+                "properties": {
+                    "system": {
+                        "type": "object",
+                        "properties": { "configurers": schema },
+                    },
+                },
+            })
+        );
+        assert_eq!(schema["properties"]["limit"]["type"], "integer");
+    }
+
+    #[test]
+    fn export_json() {
+        let json: serde_json::Value = serde_json::from_str(&topology().export_json()).unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "node_no": 7,
+                "groups": [
+                    { "name": "system.configurers", "is_entrypoint": true, "stop_order": 0 },
+                    { "name": "producers", "is_entrypoint": false, "stop_order": 0 },
+                    { "name": "consumers", "is_entrypoint": false, "stop_order": 0 },
+                ],
+                "remotes": [],
+                "connections": [
+                    { "from": "producers", "to": "consumers", "remote": false },
+                    { "from": "consumers", "to": "producers", "remote": false },
+                ],
+            })
+        );
+    }
+}
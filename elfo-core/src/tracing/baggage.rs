@@ -0,0 +1,146 @@
+use std::{fmt, sync::Arc};
+
+use serde::{ser::SerializeMap, Serialize, Serializer};
+
+/// Small key-value pairs attached to the current scope, e.g. a tenant id or
+/// experiment flags.
+///
+/// The baggage is propagated along with every sent message (including
+/// responses and messages to other nodes) and restored on the receiving
+/// side, like [`TraceId`]. Also, it's available in logs and dumps.
+///
+/// The baggage is immutable and cheap to clone. Use [`scope::baggage()`] and
+/// [`scope::set_baggage()`] to get and replace the current one.
+///
+/// # Example
+/// ```
+/// # use elfo_core as elfo;
+/// use elfo::scope;
+///
+/// # fn exec() {
+/// scope::set_baggage(scope::baggage().with("tenant", "acme"));
+///
+/// // Later, possibly in another actor.
+/// let tenant = scope::baggage().get("tenant").map(str::to_owned);
+/// # }
+/// ```
+///
+/// [`TraceId`]: super::TraceId
+/// [`scope::baggage()`]: crate::scope::baggage()
+/// [`scope::set_baggage()`]: crate::scope::set_baggage()
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Baggage(Option<Arc<Vec<(String, String)>>>);
+
+assert_eq_size!(Baggage, usize);
+
+impl Baggage {
+    /// Returns the value by the key.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.iter().find(|(k, _)| *k == key).map(|(_, v)| v)
+    }
+
+    /// Returns a new baggage with the item added or replaced.
+    #[must_use]
+    pub fn with(&self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        let key = key.into();
+        let value = value.into();
+
+        let mut items = self.0.as_deref().cloned().unwrap_or_default();
+        match items.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, v)) => *v = value,
+            None => items.push((key, value)),
+        }
+
+        Self(Some(Arc::new(items)))
+    }
+
+    /// Returns a new baggage without the item.
+    #[must_use]
+    pub fn without(&self, key: &str) -> Self {
+        let items = self
+            .iter()
+            .filter(|(k, _)| *k != key)
+            .map(|(k, v)| (k.to_owned(), v.to_owned()))
+            .collect::<Vec<_>>();
+
+        Self((!items.is_empty()).then(|| Arc::new(items)))
+    }
+
+    /// Returns an iterator over items in the order they were added.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> + '_ {
+        self.0
+            .iter()
+            .flat_map(|items| items.iter())
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// Returns the number of items.
+    pub fn len(&self) -> usize {
+        self.0.as_ref().map_or(0, |items| items.len())
+    }
+
+    /// Returns `true` if there are no items.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for Baggage {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        iter.into_iter()
+            .fold(Self::default(), |baggage, (k, v)| baggage.with(k, v))
+    }
+}
+
+impl fmt::Debug for Baggage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// Formats the baggage as `key=value,key=value`.
+impl fmt::Display for Baggage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (key, value)) in self.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{key}={value}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Serializes the baggage as a map.
+impl Serialize for Baggage {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.len()))?;
+        for (key, value) in self.iter() {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
+#[test]
+fn it_works() {
+    let baggage = Baggage::default();
+    assert!(baggage.is_empty());
+    assert_eq!(baggage.get("a"), None);
+    assert_eq!(baggage.to_string(), "");
+
+    let baggage = baggage.with("a", "1").with("b", "2").with("a", "3");
+    assert_eq!(baggage.len(), 2);
+    assert_eq!(baggage.get("a"), Some("3"));
+    assert_eq!(baggage.get("b"), Some("2"));
+    assert_eq!(baggage.to_string(), "a=3,b=2");
+    assert_eq!(format!("{baggage:?}"), r#"{"a": "3", "b": "2"}"#);
+    assert_eq!(baggage, [("a", "3"), ("b", "2")].into_iter().collect());
+
+    let without = baggage.without("a");
+    assert_eq!(without.get("a"), None);
+    assert_eq!(without.to_string(), "b=2");
+    assert_eq!(baggage.get("a"), Some("3"));
+    assert_eq!(without.without("b"), Baggage::default());
+}
@@ -0,0 +1,68 @@
+//! [Config].
+//!
+//! [Config]: TracingConfig
+
+use serde::{de::Error as _, Deserialize, Deserializer};
+
+/// Tracing configuration, namely trace-level sampling.
+///
+/// The sampling decision is made for the whole trace: a message is dumped or
+/// exported as a span only if its trace is sampled. Decisions are consistent
+/// across actors and nodes, because they depend only on the trace id and the
+/// [baggage]. Moreover, traces sampled with some rate are also sampled with
+/// any higher rate, so groups can have different rates without breaking
+/// traces.
+///
+/// # Example
+/// ```toml
+/// [some_group]
+/// system.tracing.sampling_rate = 0.01
+/// system.tracing.always_sample = [
+///     { baggage = "debug" },
+///     { baggage = "tenant", value = "acme" },
+/// ]
+/// ```
+///
+/// [baggage]: super::Baggage
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct TracingConfig {
+    /// The fraction of traces to sample, from `0.0` to `1.0`.
+    ///
+    /// `1.0` (all traces) by default.
+    #[serde(deserialize_with = "deserialize_rate")]
+    pub sampling_rate: f64,
+    /// Traces matching any of these rules are sampled regardless of
+    /// `sampling_rate`.
+    ///
+    /// Empty by default.
+    pub always_sample: Vec<SamplingRule>,
+}
+
+impl Default for TracingConfig {
+    fn default() -> Self {
+        Self {
+            sampling_rate: 1.0,
+            always_sample: Vec::new(),
+        }
+    }
+}
+
+/// A rule matching traces by their baggage.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SamplingRule {
+    /// The key of a baggage item.
+    pub baggage: String,
+    /// The value of the item. If omitted, any value matches.
+    pub value: Option<String>,
+}
+
+fn deserialize_rate<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    let rate = f64::deserialize(deserializer)?;
+
+    if !(0.0..=1.0).contains(&rate) {
+        return Err(D::Error::custom("must be in the range [0.0, 1.0]"));
+    }
+
+    Ok(rate)
+}
@@ -3,22 +3,98 @@
 
 use std::cell::RefCell;
 
+use once_cell::sync::OnceCell;
+
 use self::generator::{ChunkRegistry, Generator};
 
-pub use self::{trace_id::TraceId, validator::TraceIdValidator};
+pub use self::{
+    baggage::Baggage, trace_id::TraceId, validator::TraceIdValidator, w3c::W3cTraceContext,
+};
+
+#[cfg(feature = "unstable")] // TODO: patch `stability`, again.
+pub use self::span::{set_span_recorder, Span, SpanEvent, SpanEventKind, SpanRecorder};
+#[cfg(not(feature = "unstable"))] // TODO: patch `stability`, again.
+pub(crate) use self::span::{set_span_recorder, Span, SpanEvent, SpanEventKind, SpanRecorder};
+
+pub(crate) use self::{sampling::SamplingControl, span::span_recorder};
 
 impl TraceId {
-    /// Generates a new trace id according to [the schema](https://actoromicon.rs/ch05-04-tracing.html#traceid).
+    /// Generates a new trace id for a new trace.
+    ///
+    /// Uses the generator installed by [`set_trace_id_generator()`] if any,
+    /// otherwise, it's the same as [`TraceId::generate_default()`].
     pub fn generate() -> Self {
+        match CUSTOM_GENERATOR.get() {
+            Some(generator) => generator.generate(),
+            None => Self::generate_default(),
+        }
+    }
+
+    /// Generates a new trace id according to [the schema](https://actoromicon.rs/ch05-04-tracing.html#traceid),
+    /// even if a custom generator is installed.
+    pub fn generate_default() -> Self {
         GENERATOR.with(|cell| cell.borrow_mut().generate(&CHUNK_REGISTRY))
     }
 }
 
+/// A strategy to generate trace ids for new traces.
+///
+/// Implemented for closures returning [`TraceId`].
+pub trait TraceIdGenerator: Send + Sync + 'static {
+    /// Generates a new trace id.
+    fn generate(&self) -> TraceId;
+}
+
+impl<F> TraceIdGenerator for F
+where
+    F: Fn() -> TraceId + Send + Sync + 'static,
+{
+    fn generate(&self) -> TraceId {
+        self()
+    }
+}
+
+/// Installs a custom trace id generator used by [`TraceId::generate()`],
+/// i.e. whenever a new trace starts. It should be called before starting
+/// the actor system. Returns `false` if a generator is already installed.
+///
+/// Generated trace ids should be unique enough and 63-bit (the highest bit
+/// must be zero), see [`TraceIdValidator`]. 128-bit ids can be generated by
+/// [`W3cTraceContext::set_current()`], which keeps the full id in the baggage.
+///
+/// # Example
+/// Embed the datacenter number into the highest bits:
+/// ```
+/// # use elfo_core as elfo;
+/// use std::sync::atomic::{AtomicU64, Ordering};
+///
+/// use elfo::tracing::{self, TraceId};
+///
+/// const DATACENTER: u64 = 3;
+/// static COUNTER: AtomicU64 = AtomicU64::new(1);
+///
+/// tracing::set_trace_id_generator(|| {
+///     // 1 zero bit, 4 bits for the datacenter, 59 bits for the counter.
+///     let counter = COUNTER.fetch_add(1, Ordering::Relaxed) & ((1 << 59) - 1);
+///     TraceId::try_from(DATACENTER << 59 | counter).unwrap()
+/// });
+/// ```
+pub fn set_trace_id_generator(generator: impl TraceIdGenerator) -> bool {
+    CUSTOM_GENERATOR.set(Box::new(generator)).is_ok()
+}
+
+static CUSTOM_GENERATOR: OnceCell<Box<dyn TraceIdGenerator>> = OnceCell::new();
 static CHUNK_REGISTRY: ChunkRegistry = ChunkRegistry::new(0);
 thread_local! {
     static GENERATOR: RefCell<Generator> = RefCell::new(Generator::default());
 }
 
+pub mod config;
+
+mod baggage;
 mod generator;
+mod sampling;
+mod span;
 mod trace_id;
 mod validator;
+mod w3c;
@@ -0,0 +1,134 @@
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+
+use super::{
+    config::{SamplingRule, TracingConfig},
+    Baggage, TraceId,
+};
+
+/// Makes trace-level sampling decisions for a group, see `TracingConfig`.
+pub(crate) struct SamplingControl {
+    sampler: ArcSwap<Sampler>,
+}
+
+struct Sampler {
+    /// `None` means all traces are sampled.
+    threshold: Option<u64>,
+    always_sample: Vec<SamplingRule>,
+}
+
+impl Default for SamplingControl {
+    fn default() -> Self {
+        Self {
+            sampler: ArcSwap::from_pointee(Sampler {
+                threshold: None,
+                always_sample: Vec::new(),
+            }),
+        }
+    }
+}
+
+impl SamplingControl {
+    pub(crate) fn configure(&self, config: &TracingConfig) {
+        let threshold =
+            (config.sampling_rate < 1.0).then_some((config.sampling_rate * u64::MAX as f64) as u64);
+
+        self.sampler.store(Arc::new(Sampler {
+            threshold,
+            always_sample: config.always_sample.clone(),
+        }));
+    }
+
+    pub(crate) fn is_sampled(&self, trace_id: TraceId, baggage: &Baggage) -> bool {
+        let sampler = self.sampler.load();
+
+        let Some(threshold) = sampler.threshold else {
+            return true;
+        };
+
+        // The same hash is used on all nodes, so traces sampled with some
+        // rate are also sampled with any higher rate.
+        hash(trace_id) < threshold
+            || sampler.always_sample.iter().any(|rule| {
+                baggage
+                    .get(&rule.baggage)
+                    .is_some_and(|value| rule.value.iter().all(|v| v == value))
+            })
+    }
+}
+
+/// Trace ids are structured (timestamp, node, counter), so their bits are
+/// mixed to be uniformly distributed (the `splitmix64` finalizer).
+fn hash(trace_id: TraceId) -> u64 {
+    let mut x = u64::from(trace_id);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sampled_count(control: &SamplingControl, baggage: &Baggage) -> usize {
+        (1..=10_000)
+            .map(|i| TraceId::try_from(i).unwrap())
+            .filter(|trace_id| control.is_sampled(*trace_id, baggage))
+            .count()
+    }
+
+    #[test]
+    fn rate() {
+        let control = SamplingControl::default();
+        let empty = Baggage::default();
+        assert_eq!(sampled_count(&control, &empty), 10_000);
+
+        for (rate, expected) in [(0.0, 0), (0.1, 1_000), (0.5, 5_000)] {
+            control.configure(&TracingConfig {
+                sampling_rate: rate,
+                always_sample: Vec::new(),
+            });
+
+            let count = sampled_count(&control, &empty);
+            assert!(count.abs_diff(expected) <= 200, "{rate}: {count}");
+        }
+
+        // Traces sampled with lower rates are sampled with higher rates.
+        let low = SamplingControl::default();
+        low.configure(&TracingConfig {
+            sampling_rate: 0.1,
+            always_sample: Vec::new(),
+        });
+
+        for i in 1..=10_000 {
+            let trace_id = TraceId::try_from(i).unwrap();
+            assert!(!low.is_sampled(trace_id, &empty) || control.is_sampled(trace_id, &empty));
+        }
+    }
+
+    #[test]
+    fn always_sample() {
+        let control = SamplingControl::default();
+        control.configure(&TracingConfig {
+            sampling_rate: 0.0,
+            always_sample: vec![
+                SamplingRule {
+                    baggage: "debug".into(),
+                    value: None,
+                },
+                SamplingRule {
+                    baggage: "tenant".into(),
+                    value: Some("acme".into()),
+                },
+            ],
+        });
+
+        let check = |baggage: Baggage| sampled_count(&control, &baggage) == 10_000;
+
+        assert!(!check(Baggage::default()));
+        assert!(check(Baggage::default().with("debug", "")));
+        assert!(check(Baggage::default().with("tenant", "acme")));
+        assert!(!check(Baggage::default().with("tenant", "other")));
+    }
+}
@@ -0,0 +1,63 @@
+use std::{sync::Arc, time::Duration};
+
+use once_cell::sync::OnceCell;
+
+use elfo_utils::time::SystemTime;
+
+use super::{Baggage, TraceId};
+use crate::{actor::ActorMeta, dumping::MessageKind};
+
+static RECORDER: OnceCell<Arc<dyn SpanRecorder>> = OnceCell::new();
+
+/// Handling of one incoming message by an actor: from receiving the message
+/// until the actor asks for the next one.
+#[derive(Debug)]
+#[stability::unstable]
+pub struct Span {
+    pub meta: Arc<ActorMeta>,
+    pub trace_id: TraceId,
+    /// Unique among all spans, see [`TraceId::generate_default()`].
+    pub span_id: TraceId,
+    /// The span in which the message has been sent, if it's recorded.
+    pub parent_span_id: Option<TraceId>,
+    /// The baggage of the handled message.
+    pub baggage: Baggage,
+    pub message_name: &'static str,
+    pub message_protocol: &'static str,
+    pub message_kind: MessageKind,
+    pub start_time: SystemTime,
+    pub duration: Duration,
+    /// Messages sent while handling.
+    pub events: Vec<SpanEvent>,
+}
+
+#[derive(Debug)]
+#[stability::unstable]
+pub struct SpanEvent {
+    pub timestamp: SystemTime,
+    pub kind: SpanEventKind,
+    pub message_name: &'static str,
+    pub message_protocol: &'static str,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[stability::unstable]
+pub enum SpanEventKind {
+    Sent,
+    Responded,
+}
+
+#[stability::unstable]
+pub trait SpanRecorder: Send + Sync {
+    fn enabled(&self) -> bool;
+    fn record(&self, span: Span);
+}
+
+#[stability::unstable]
+pub fn set_span_recorder(recorder: Arc<dyn SpanRecorder>) -> bool {
+    RECORDER.set(recorder).is_ok()
+}
+
+pub(crate) fn span_recorder() -> Option<&'static dyn SpanRecorder> {
+    RECORDER.get().map(|recorder| &**recorder)
+}
@@ -0,0 +1,344 @@
+use std::fmt;
+
+use super::{Baggage, TraceId};
+use crate::scope;
+
+/// A trace context in the [W3C Trace Context] format, i.e. the `traceparent`
+/// and `tracestate` HTTP headers.
+///
+/// elfo's [`TraceId`] is 63-bit, so the W3C trace id (128-bit) cannot be
+/// converted losslessly. Instead, [`W3cTraceContext::set_current()`] uses the
+/// lower 63 bits as the current trace id and puts the original context into
+/// the current [`Baggage`] under the `traceparent` and `tracestate` keys.
+/// The baggage is propagated along with messages, so
+/// [`W3cTraceContext::current()`] restores the context later in any actor
+/// handling the same trace, even on another node.
+///
+/// # Example
+/// ```
+/// # use elfo_core as elfo;
+/// use elfo::{scope, tracing::W3cTraceContext};
+///
+/// # fn handle(traceparent: &str) {
+/// // Incoming HTTP request.
+/// if let Ok(context) = W3cTraceContext::parse(traceparent, None) {
+///     context.set_current();
+/// }
+///
+/// // Outgoing HTTP request, possibly in another actor.
+/// let context = W3cTraceContext::current();
+/// let header = context.traceparent();
+/// # }
+/// ```
+///
+/// [W3C Trace Context]: https://www.w3.org/TR/trace-context/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct W3cTraceContext {
+    /// The 128-bit trace id.
+    pub trace_id: u128,
+    /// The id of the caller's span.
+    pub parent_id: u64,
+    /// Trace flags, only the `sampled` flag (`0x01`) is defined for now.
+    pub flags: u8,
+    /// The raw `tracestate` header, passed as is.
+    pub tracestate: Option<String>,
+}
+
+// Errors
+const INVALID_FORMAT: &str = "invalid format";
+const INVALID_VERSION: &str = "invalid version";
+const ZERO_TRACE_ID: &str = "trace id cannot be zero";
+const ZERO_PARENT_ID: &str = "parent id cannot be zero";
+
+// Baggage keys
+const TRACEPARENT: &str = "traceparent";
+const TRACESTATE: &str = "tracestate";
+
+impl W3cTraceContext {
+    /// Parses the `traceparent` and `tracestate` headers.
+    pub fn parse(traceparent: &str, tracestate: Option<&str>) -> Result<Self, &'static str> {
+        let mut parts = traceparent.trim().split('-');
+        let version = parse_hex::<u8>(parts.next(), 2).ok_or(INVALID_FORMAT)?;
+        let trace_id = parse_hex::<u128>(parts.next(), 32).ok_or(INVALID_FORMAT)?;
+        let parent_id = parse_hex::<u64>(parts.next(), 16).ok_or(INVALID_FORMAT)?;
+        let flags = parse_hex::<u8>(parts.next(), 2).ok_or(INVALID_FORMAT)?;
+
+        // Future versions can add fields, but the known ones must be the same.
+        match version {
+            0xff => return Err(INVALID_VERSION),
+            0x00 if parts.next().is_some() => return Err(INVALID_FORMAT),
+            _ => {}
+        }
+
+        if trace_id == 0 {
+            return Err(ZERO_TRACE_ID);
+        }
+
+        if parent_id == 0 {
+            return Err(ZERO_PARENT_ID);
+        }
+
+        Ok(Self {
+            trace_id,
+            parent_id,
+            flags,
+            tracestate: tracestate
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(Into::into),
+        })
+    }
+
+    /// Returns the context for outgoing requests in the current trace.
+    /// See [`W3cTraceContext::from_trace()`] for details.
+    ///
+    /// The parent id is the id of the currently recorded span if any,
+    /// so spans of the callee are linked to the caller's ones.
+    pub fn current() -> Self {
+        let (trace_id, baggage, span_id) =
+            scope::with(|scope| (scope.trace_id(), scope.baggage(), scope.span_id()));
+
+        let mut context = Self::from_trace(trace_id, &baggage);
+        if let Some(span_id) = span_id {
+            context.parent_id = u64::from(span_id);
+        }
+        context
+    }
+
+    /// Returns the context for outgoing requests in the provided trace.
+    ///
+    /// If the trace has been started by [`W3cTraceContext::set_current()`],
+    /// the original trace id, flags and `tracestate` are restored from the
+    /// baggage. Otherwise, the trace id is elfo's one extended to 128 bits
+    /// and the trace is marked as sampled.
+    ///
+    /// A new parent id is generated in both cases.
+    pub fn from_trace(trace_id: TraceId, baggage: &Baggage) -> Self {
+        let parent_id = u64::from(TraceId::generate_default());
+
+        if let Some(original) = Self::from_baggage(trace_id, baggage) {
+            return Self {
+                parent_id,
+                ..original
+            };
+        }
+
+        Self {
+            trace_id: u128::from(u64::from(trace_id)),
+            parent_id,
+            flags: 0x01,
+            tracestate: None,
+        }
+    }
+
+    /// Returns the 128-bit trace id for the provided elfo's trace id:
+    /// the original one if the trace has been started by
+    /// [`W3cTraceContext::set_current()`], the extended one otherwise.
+    ///
+    /// Useful to export traces to systems using 128-bit trace ids.
+    pub fn trace_id_128(trace_id: TraceId, baggage: &Baggage) -> u128 {
+        Self::from_baggage(trace_id, baggage)
+            .map_or(u128::from(u64::from(trace_id)), |context| context.trace_id)
+    }
+
+    /// Converts the context to elfo's trace id.
+    ///
+    /// Only the lower 63 bits of the trace id are used (the upper ones if
+    /// the lower are zero), so prefer [`W3cTraceContext::set_current()`]
+    /// to keep the original context.
+    ///
+    /// The conversion is deterministic: the same context always gives
+    /// the same trace id, which is required to restore it from the baggage.
+    pub fn to_trace_id(&self) -> TraceId {
+        // The highest bit must be zero, see `TraceIdValidator`.
+        const MASK: u64 = !(1 << 63);
+
+        let lower = self.trace_id as u64 & MASK;
+        let upper = (self.trace_id >> 64) as u64 & MASK;
+
+        // Both are zero only for `1 << 63`, which is still a valid trace id.
+        [lower, upper, 1]
+            .into_iter()
+            .find_map(|raw| TraceId::try_from(raw).ok())
+            .expect("1 is a valid trace id")
+    }
+
+    /// Replaces the current trace id with the converted one and puts the
+    /// context into the current baggage if it cannot be restored without it.
+    ///
+    /// Note that baggage items longer than 255 bytes aren't sent to other
+    /// nodes, so a long `tracestate` is available only on the current node.
+    pub fn set_current(&self) {
+        let trace_id = self.to_trace_id();
+        let mut baggage = scope::baggage().without(TRACEPARENT).without(TRACESTATE);
+
+        // Don't put contexts that can be restored without it.
+        if u128::from(u64::from(trace_id)) != self.trace_id
+            || self.flags != 0x01
+            || self.tracestate.is_some()
+        {
+            baggage = baggage.with(TRACEPARENT, self.traceparent());
+        }
+        if let Some(tracestate) = &self.tracestate {
+            baggage = baggage.with(TRACESTATE, tracestate.as_str());
+        }
+
+        scope::set_trace_id(trace_id);
+        scope::set_baggage(baggage);
+    }
+
+    fn from_baggage(trace_id: TraceId, baggage: &Baggage) -> Option<Self> {
+        let traceparent = baggage.get(TRACEPARENT)?;
+        let context = Self::parse(traceparent, baggage.get(TRACESTATE)).ok()?;
+
+        // The trace could have been replaced after the context was set.
+        (context.to_trace_id() == trace_id).then_some(context)
+    }
+
+    /// Returns the `traceparent` header.
+    pub fn traceparent(&self) -> String {
+        self.to_string()
+    }
+
+    /// Returns the `tracestate` header.
+    pub fn tracestate(&self) -> Option<&str> {
+        self.tracestate.as_deref()
+    }
+
+    /// Returns `true` if the caller has recorded the trace.
+    pub fn is_sampled(&self) -> bool {
+        self.flags & 0x01 != 0
+    }
+}
+
+impl fmt::Display for W3cTraceContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "00-{:032x}-{:016x}-{:02x}",
+            self.trace_id, self.parent_id, self.flags
+        )
+    }
+}
+
+fn parse_hex<T: FromHex>(part: Option<&str>, len: usize) -> Option<T> {
+    let part = part?;
+
+    // Only lowercase hex digits are allowed.
+    let is_valid = part.len() == len
+        && part
+            .bytes()
+            .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b));
+
+    is_valid.then(|| T::from_hex(part))?
+}
+
+trait FromHex: Sized {
+    fn from_hex(s: &str) -> Option<Self>;
+}
+
+macro_rules! impl_from_hex {
+    ($($t:ty),*) => {
+        $(impl FromHex for $t {
+            fn from_hex(s: &str) -> Option<Self> {
+                <$t>::from_str_radix(s, 16).ok()
+            }
+        })*
+    };
+}
+
+impl_from_hex!(u8, u64, u128);
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{actor::ActorMeta, scope::Scope, Addr};
+
+    const TRACEPARENT_HEADER: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    #[test]
+    fn parse() {
+        let context =
+            W3cTraceContext::parse(TRACEPARENT_HEADER, Some("congo=t61rcWkgMzE")).unwrap();
+        assert_eq!(context.trace_id, 0x4bf92f3577b34da6a3ce929d0e0e4736);
+        assert_eq!(context.parent_id, 0x00f067aa0ba902b7);
+        assert!(context.is_sampled());
+        assert_eq!(context.tracestate(), Some("congo=t61rcWkgMzE"));
+        assert_eq!(context.traceparent(), TRACEPARENT_HEADER);
+
+        // Future versions.
+        let future = "cc-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00-what";
+        assert!(!W3cTraceContext::parse(future, None).unwrap().is_sampled());
+
+        for invalid in [
+            "",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-01",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e473-600f067aa0ba902b7-01",
+            "00-+bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+        ] {
+            assert!(W3cTraceContext::parse(invalid, None).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn roundtrip() {
+        let meta = Arc::new(ActorMeta {
+            group: "test".into(),
+            key: "_".into(),
+        });
+
+        Scope::test(Addr::NULL, meta).sync_within(|| {
+            let context = W3cTraceContext::parse(TRACEPARENT_HEADER, Some("a=b")).unwrap();
+            context.set_current();
+            let trace_id = scope::trace_id();
+            assert_eq!(u64::from(trace_id), 0x23ce929d0e0e4736);
+            assert_eq!(context.to_trace_id(), trace_id);
+
+            let restored = W3cTraceContext::current();
+            assert_eq!(restored.trace_id, context.trace_id);
+            assert_ne!(restored.parent_id, context.parent_id);
+            assert_eq!(restored.flags, context.flags);
+            assert_eq!(restored.tracestate, context.tracestate);
+
+            let baggage = scope::baggage();
+            assert_eq!(
+                W3cTraceContext::trace_id_128(trace_id, &baggage),
+                context.trace_id
+            );
+
+            // The context doesn't belong to other traces.
+            let other = TraceId::try_from(42).unwrap();
+            assert_eq!(W3cTraceContext::trace_id_128(other, &baggage), 42);
+
+            // elfo's trace ids are extended to 128 bits.
+            scope::set_trace_id(other);
+            let context = W3cTraceContext::current();
+            assert_eq!(context.trace_id, 42);
+            assert!(context.is_sampled());
+            assert_eq!(context.tracestate, None);
+            assert_eq!(context.to_trace_id(), other);
+
+            // Compatible contexts aren't put into the baggage.
+            context.set_current();
+            assert!(scope::baggage().is_empty());
+
+            // Contexts with zero lower bits are converted deterministically.
+            for trace_id in [0xabc << 64 | 1 << 63, 1 << 63] {
+                let header = format!("00-{trace_id:032x}-00f067aa0ba902b7-01");
+                let context = W3cTraceContext::parse(&header, None).unwrap();
+                assert_eq!(context.to_trace_id(), context.to_trace_id());
+
+                context.set_current();
+                assert_eq!(scope::trace_id(), context.to_trace_id());
+                assert_eq!(W3cTraceContext::current().trace_id, trace_id);
+            }
+        });
+    }
+}
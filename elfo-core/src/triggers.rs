@@ -0,0 +1,70 @@
+//! Manual triggering of attached sources by `elfo-test`, e.g. to fire an
+//! [`Interval`] without waiting for real time or to deliver a [`Signal`]
+//! without OS signals.
+//!
+//! Sources are registered per thread once attached, like faults in
+//! `faults.rs`. So, it works with the current-thread runtime only, which is
+//! the default one for `#[tokio::test]`.
+//!
+//! [`Interval`]: crate::time::Interval
+//! [`Signal`]: crate::signal::Signal
+
+use std::{any::TypeId, cell::RefCell};
+
+use crate::{addr::Addr, message::AnyMessage, scope, source::UntypedSourceArc};
+
+struct Attached {
+    group: Addr,
+    key: String,
+    source: UntypedSourceArc,
+}
+
+thread_local! {
+    static ATTACHED: RefCell<Vec<Attached>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Registers the source attached by the current actor.
+pub(crate) fn register(source: UntypedSourceArc) {
+    let Some((group, key)) = scope::try_with(|scope| (scope.group(), scope.meta().key.clone()))
+    else {
+        return;
+    };
+
+    // Sources can be attached while the thread is being destroyed.
+    let _ = ATTACHED.try_with(|attached| {
+        let mut attached = attached.borrow_mut();
+        attached.retain(|a| !a.source.is_terminated());
+        attached.push(Attached { group, key, source });
+    });
+}
+
+/// Fires all sources of the actor storing a message of the provided type
+/// (`Interval`, `Delay` and `Signal`), so they emit the message immediately.
+/// Returns the number of fired sources.
+pub fn fire(group: Addr, key: &str, message_type: TypeId) -> usize {
+    ATTACHED.with(|attached| {
+        attached
+            .borrow()
+            .iter()
+            .filter(|a| a.group == group && a.key == key)
+            .filter(|a| a.source.fire(message_type))
+            .count()
+    })
+}
+
+/// Emits the message from the earliest attached live stream of the actor
+/// as if the stream produced it. Returns the message back if there is no
+/// such stream.
+pub fn emit(group: Addr, key: &str, message: AnyMessage) -> Result<(), AnyMessage> {
+    ATTACHED.with(|attached| {
+        let attached = attached.borrow();
+        let mut streams = attached
+            .iter()
+            .filter(|a| a.group == group && a.key == key && a.source.is_stream());
+
+        match streams.next() {
+            Some(a) => a.source.emit(message),
+            None => Err(message),
+        }
+    })
+}
@@ -1,5 +1,6 @@
 use std::{
-    iter, panic,
+    iter,
+    panic::{self, AssertUnwindSafe},
     sync::Arc,
     time::{Duration, SystemTime},
 };
@@ -7,7 +8,7 @@ use std::{
 use eyre::{Result, WrapErr};
 use fxhash::FxHashSet;
 use parking_lot::Mutex;
-use tokio::task;
+use tokio::sync::oneshot;
 use tracing::{error, info};
 
 use elfo_core::{
@@ -197,14 +198,21 @@ impl Dumper {
 
                     // Run the background task and wait until it's completed.
                     let scope = scope::expose();
-                    match task::spawn_blocking(|| scope.sync_within(background)).await {
+                    let (tx, rx) = oneshot::channel();
+                    scope::runtime().spawn_blocking(Box::new(move || {
+                        let res =
+                            panic::catch_unwind(AssertUnwindSafe(|| scope.sync_within(background)));
+                        let _ = tx.send(res);
+                    }));
+
+                    match rx.await.expect("the background task is dropped") {
                         Ok(Ok(state)) => {
                             serializer = state.0;
                             rule_set = state.1;
                             reporter = state.2;
                         }
                         Ok(Err(err)) => return Err(err),
-                        Err(err) => panic::resume_unwind(err.into_panic()),
+                        Err(payload) => panic::resume_unwind(payload),
                     }
 
                     if need_to_terminate {
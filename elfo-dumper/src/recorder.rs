@@ -7,16 +7,23 @@ use crate::dump_storage::DumpRegistry;
 
 impl Recorder for DumpRegistry {
     fn enabled(&self) -> bool {
-        scope::try_with(|scope| match scope.dumping().check(self.class()) {
-            CheckResult::Passed => {
-                // TODO: `elfo_lost_dumps_total`
-                // TODO: `elfo_emitted_dumps_total`
-                true
+        scope::try_with(|scope| {
+            // Only sampled traces are dumped, see `system.tracing`.
+            if !scope.is_sampled() {
+                return false;
             }
-            CheckResult::NotInterested => false,
-            CheckResult::Limited => {
-                // TODO: `elfo_lost_dumps_total`
-                false
+
+            match scope.dumping().check(self.class()) {
+                CheckResult::Passed => {
+                    // TODO: `elfo_lost_dumps_total`
+                    // TODO: `elfo_emitted_dumps_total`
+                    true
+                }
+                CheckResult::NotInterested => false,
+                CheckResult::Limited => {
+                    // TODO: `elfo_lost_dumps_total`
+                    false
+                }
             }
         })
         // TODO: limit dumps outside the actor system?
@@ -185,6 +185,7 @@ impl serde::Serialize for CompactDump<'_> {
     fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
         let field_count = 12
             + !self.dump.meta.key.is_empty() as usize // "k"
+            + !self.dump.baggage.is_empty() as usize // "b"
             + !matches!(self.dump.message_kind, MessageKind::Regular) as usize; // "c"
 
         let mut s = serializer.serialize_struct("Dump", field_count)?;
@@ -200,6 +201,11 @@ impl serde::Serialize for CompactDump<'_> {
         s.serialize_field("n", &self.node_no)?;
         s.serialize_field("s", &self.dump.sequence_no)?;
         s.serialize_field("t", &self.dump.trace_id)?;
+
+        if !self.dump.baggage.is_empty() {
+            s.serialize_field("b", &self.dump.baggage)?;
+        }
+
         s.serialize_field("th", &self.dump.thread_id)?;
         s.serialize_field("d", &self.dump.direction)?;
         s.serialize_field("cl", &self.class)?;
@@ -449,6 +455,20 @@ mod tests {
         assert_eq!(report.failed.len(), 0);
     }
 
+    #[test]
+    fn baggage() {
+        let mut serializer = serializer(1024, "some");
+
+        let mut sample = dump(42, 4, true);
+        sample.baggage = sample.baggage.with("tenant", "acme");
+        let expected = line(42, 4).replace(r#""t":1,"#, r#""t":1,"b":{"tenant":"acme"},"#);
+
+        assert!(serializer.append(&sample, &DumpParams::default()).is_none());
+        let (chunk, _) = serializer.take();
+        let chunk = std::str::from_utf8(chunk.unwrap()).unwrap();
+        assert_eq!(chunk, format!("{expected}\n"));
+    }
+
     #[test]
     fn take() {
         let chunk_size = 1024;
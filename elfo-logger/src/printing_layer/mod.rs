@@ -62,17 +62,24 @@ impl<S: Subscriber> Layer<S> for PrintingLayer {
     fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
         let current_span = ctx.current_span();
         let level = *event.metadata().level();
-        let payload_id = ward!(self.prepare(true, |visitor| event.record(visitor)), {
-            stats::counter_per_level("elfo_lost_events_total", level);
-            return;
-        });
-
-        let data = scope::try_with(|scope| (scope.meta().clone(), scope.trace_id()));
-        let (object, trace_id) = match data {
-            Some((meta, trace_id)) => (Some(meta), Some(trace_id)),
-            None => (None, None),
+        let data =
+            scope::try_with(|scope| (scope.meta().clone(), scope.trace_id(), scope.baggage()));
+        let (object, trace_id, baggage) = match data {
+            Some((meta, trace_id, baggage)) => (Some(meta), Some(trace_id), baggage),
+            None => (None, None, Default::default()),
         };
 
+        let payload_id = ward!(
+            self.prepare(true, |visitor| {
+                event.record(visitor);
+                visitor.record_baggage(&baggage);
+            }),
+            {
+                stats::counter_per_level("elfo_lost_events_total", level);
+                return;
+            }
+        );
+
         let event = PreparedEvent {
             timestamp: SystemTime::now(),
             trace_id,
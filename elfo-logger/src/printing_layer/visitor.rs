@@ -6,6 +6,8 @@ use std::{
 use sharded_slab::Pool;
 use tracing::field::{Field, Visit};
 
+use elfo_core::tracing::Baggage;
+
 use crate::Shared;
 
 const MAX_ERROR_SOURCES: u8 = 5;
@@ -28,6 +30,13 @@ impl<'a> Visitor<'a> {
     pub(super) fn push(&mut self, str: &str) {
         self.output.push_str(str);
     }
+
+    /// Adds items of the baggage as `baggage.<key>=<value>` fields.
+    pub(super) fn record_baggage(&mut self, baggage: &Baggage) {
+        for (key, value) in baggage.iter() {
+            let _ = write!(self.output, "\tbaggage.{key}={value}");
+        }
+    }
 }
 
 impl Visit for Visitor<'_> {
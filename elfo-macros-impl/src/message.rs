@@ -1,15 +1,21 @@
-use proc_macro2::TokenStream;
+use proc_macro2::{Span, TokenStream};
 use quote::{quote, ToTokens};
 use syn::{
     parenthesized,
     parse::{Error as ParseError, Parse, ParseStream},
     parse_macro_input,
     spanned::Spanned,
-    Data, DeriveInput, Ident, LitStr, Path, Token, Type,
+    Attribute, Data, DeriveInput, Expr, ExprLit, GenericArgument, GenericParam, Ident, Lit, LitInt,
+    LitStr, Meta, Path, PathArguments, Token, Type,
 };
 
+use self::{redact::Redaction, version::Removed};
 use crate::errors::emit_error;
 
+mod redact;
+mod schema;
+mod version;
+
 #[derive(Debug)]
 struct MessageArgs {
     name: Option<LitStr>,
@@ -17,9 +23,13 @@ struct MessageArgs {
     ret: Option<Type>,
     part: bool,
     transparent: bool,
+    schema: bool,
     dumping_allowed: Option<bool>,
     crate_: Option<Path>,
     not: Vec<String>,
+    instances: Vec<Type>,
+    version: Option<LitInt>,
+    removed: Vec<Removed>,
 }
 
 impl Parse for MessageArgs {
@@ -30,9 +40,13 @@ impl Parse for MessageArgs {
             protocol: None,
             part: false,
             transparent: false,
+            schema: false,
             dumping_allowed: None,
             crate_: None,
             not: Vec::new(),
+            instances: Vec::new(),
+            version: None,
+            removed: Vec::new(),
         };
 
         // `#[message]`
@@ -41,9 +55,13 @@ impl Parse for MessageArgs {
         // `#[message(ret = A)]`
         // `#[message(part)]`
         // `#[message(part, transparent)]`
+        // `#[message(schema)]`
         // `#[message(elfo = some)]`
         // `#[message(not(Debug))]`
         // `#[message(dumping = "disabled")]`
+        // `#[message(instances(Generic<A>, Generic<B>))]`
+        // `#[message(version = 2)]`
+        // `#[message(version = 3, removed(field: Type = default))]`
         while !input.is_empty() {
             let ident: Ident = input.parse()?;
 
@@ -62,6 +80,7 @@ impl Parse for MessageArgs {
                 }
                 "part" => args.part = true,
                 "transparent" => args.transparent = true,
+                "schema" => args.schema = true,
                 "dumping" => {
                     // TODO: introduce `DumpingMode`.
                     let _: Token![=] = input.parse()?;
@@ -87,6 +106,26 @@ impl Parse for MessageArgs {
                         .map(|ident| ident.to_string())
                         .collect();
                 }
+                "instances" => {
+                    let content;
+                    parenthesized!(content in input);
+                    args.instances = content
+                        .parse_terminated(Type::parse, Token![,])?
+                        .into_iter()
+                        .collect();
+                }
+                "version" => {
+                    let _: Token![=] = input.parse()?;
+                    args.version = Some(input.parse()?);
+                }
+                "removed" => {
+                    let content;
+                    parenthesized!(content in input);
+                    args.removed = content
+                        .parse_terminated(Removed::parse, Token![,])?
+                        .into_iter()
+                        .collect();
+                }
                 _ => return Err(input.error("unknown attribute")),
             }
 
@@ -112,6 +151,29 @@ impl MessageArgs {
             incompatible(&self.name, "name");
             incompatible(&self.protocol, "protocol");
             incompatible(&self.dumping_allowed, "dumping_allowed");
+            incompatible(&self.instances.first(), "instances");
+
+            if self.schema {
+                emit_error!(
+                    Span::call_site(),
+                    "`part` and `schema` attributes are incompatible"
+                );
+            }
+        }
+
+        if self.version.is_none() {
+            if let Some(removed) = self.removed.first() {
+                emit_error!(removed.span(), "`removed` requires `version`");
+            }
+        }
+
+        if self.transparent {
+            if let Some(version) = &self.version {
+                emit_error!(
+                    version.span(),
+                    "`transparent` and `version` are incompatible"
+                );
+            }
         }
     }
 }
@@ -124,29 +186,50 @@ fn gen_derive_attr(blacklist: &[String], name: &str, path: TokenStream) -> Token
         .into_token_stream()
 }
 
-fn gen_impl_debug(input: &DeriveInput) -> TokenStream {
-    let name = &input.ident;
-    let field = match &input.data {
-        Data::Struct(data) if data.fields.len() == 1 => Some(data.fields.iter().next().unwrap()),
-        _ => None,
-    };
+/// Returns the only field of `#[message(transparent)]` structs.
+fn transparent_field(input: &DeriveInput) -> Option<&syn::Field> {
+    match &input.data {
+        Data::Struct(data) if data.fields.len() == 1 => data.fields.iter().next(),
+        _ => {
+            emit_error!(
+                input.ident.span(),
+                "`transparent` is applicable only for structs with one field"
+            );
+            None
+        }
+    }
+}
 
-    let Some(field) = field else {
-        emit_error!(
-            name.span(),
-            "`transparent` is applicable only for structs with one field"
-        );
-        return TokenStream::new();
-    };
+fn gen_impl_debug(
+    input: &DeriveInput,
+    field: &syn::Field,
+    redaction: Option<Redaction>,
+    crate_: &Path,
+) -> TokenStream {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
 
-    let propagate_fmt = if let Some(ident) = field.ident.as_ref() {
-        quote! { ::std::fmt::Debug::fmt(&self.#ident, f) }
+    let value = if let Some(ident) = field.ident.as_ref() {
+        quote! { &self.#ident }
     } else {
-        quote! { ::std::fmt::Debug::fmt(&self.0, f) }
+        quote! { &self.0 }
     };
+    let value = redact::gen_debug_field(value, redaction, crate_);
+    let propagate_fmt = quote! { ::std::fmt::Debug::fmt(#value, f) };
+
+    // Generic fields must be `Debug` too.
+    let field_ty = &field.ty;
+    let mut where_clause = where_clause
+        .cloned()
+        .unwrap_or_else(|| syn::parse_quote!(where));
+    if redaction.is_none() {
+        where_clause
+            .predicates
+            .push(syn::parse_quote!(#field_ty: ::std::fmt::Debug));
+    }
 
     quote! {
-        impl ::std::fmt::Debug for #name {
+        impl #impl_generics ::std::fmt::Debug for #name #ty_generics #where_clause {
             #[inline]
             fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
                 #propagate_fmt
@@ -155,6 +238,152 @@ fn gen_impl_debug(input: &DeriveInput) -> TokenStream {
     }
 }
 
+/// A concrete type the message is implemented for.
+/// Non-generic messages have the only instance, the type itself.
+struct Instance {
+    ty: TokenStream,
+    name: String,
+    // Aliases of generic parameters, e.g. `type T = u32;`, to use them in `ret`.
+    aliases: TokenStream,
+}
+
+fn collect_instances(input: &DeriveInput, instances: &[Type], name_str: &str) -> Vec<Instance> {
+    let name = &input.ident;
+    let params = &input.generics.params;
+
+    if params.is_empty() {
+        if let Some(instance) = instances.first() {
+            emit_error!(
+                instance.span(),
+                "`instances` is applicable only for generic messages"
+            );
+        }
+
+        return vec![Instance {
+            ty: quote! { #name },
+            name: name_str.into(),
+            aliases: TokenStream::new(),
+        }];
+    }
+
+    if let Some(param) = params
+        .iter()
+        .find(|p| matches!(p, GenericParam::Lifetime(_)))
+    {
+        emit_error!(param.span(), "messages cannot have lifetime parameters");
+        return Vec::new();
+    }
+
+    if instances.is_empty() {
+        emit_error!(
+            name.span(),
+            "generic messages require `instances(..)` to list concrete types"
+        );
+    }
+
+    instances
+        .iter()
+        .filter_map(|instance| {
+            let generic_args = match instance {
+                Type::Path(path) => path.path.segments.last().and_then(|segment| {
+                    match (&segment.ident == name, &segment.arguments) {
+                        (true, PathArguments::AngleBracketed(args)) => Some(&args.args),
+                        _ => None,
+                    }
+                }),
+                _ => None,
+            };
+
+            let Some(generic_args) = generic_args.filter(|args| args.len() == params.len()) else {
+                emit_error!(
+                    instance.span(),
+                    "expected `{name}<..>` with all generic arguments"
+                );
+                return None;
+            };
+
+            let mut rendered = Vec::new();
+            let mut aliases = TokenStream::new();
+
+            for (param, arg) in params.iter().zip(generic_args) {
+                let arg = match arg {
+                    GenericArgument::Type(ty) => ty.to_token_stream(),
+                    GenericArgument::Const(expr) => expr.to_token_stream(),
+                    _ => {
+                        emit_error!(arg.span(), "expected a type or a constant");
+                        return None;
+                    }
+                };
+
+                rendered.push(render_tokens(&arg));
+                aliases.extend(match param {
+                    GenericParam::Type(param) => {
+                        let ident = &param.ident;
+                        quote! { #[allow(dead_code)] type #ident = #arg; }
+                    }
+                    GenericParam::Const(param) => {
+                        let (ident, ty) = (&param.ident, &param.ty);
+                        quote! { #[allow(dead_code)] const #ident: #ty = #arg; }
+                    }
+                    GenericParam::Lifetime(_) => unreachable!(),
+                });
+            }
+
+            Some(Instance {
+                ty: instance.to_token_stream(),
+                name: format!("{name_str}<{}>", rendered.join(", ")),
+                aliases,
+            })
+        })
+        .collect()
+}
+
+fn gen_docs(attrs: &[Attribute]) -> String {
+    let lines = attrs.iter().filter_map(|attr| match &attr.meta {
+        Meta::NameValue(nv) if nv.path.is_ident("doc") => match &nv.value {
+            Expr::Lit(ExprLit {
+                lit: Lit::Str(lit), ..
+            }) => Some(lit.value()),
+            _ => None,
+        },
+        _ => None,
+    });
+
+    lines
+        .map(|line| line.strip_prefix(' ').map(String::from).unwrap_or(line))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .into()
+}
+
+/// Renders tokens the way they are usually written, e.g. `Vec<Option<u8>>`
+/// instead of `Vec < Option < u8 > >`, to be used in names.
+fn render_tokens(tokens: &TokenStream) -> String {
+    let is_ident_char = |c: char| c.is_alphanumeric() || c == '_';
+    let raw = tokens.to_string();
+    let chars = raw.chars().collect::<Vec<_>>();
+    let mut rendered = String::with_capacity(raw.len());
+
+    for (i, &c) in chars.iter().enumerate() {
+        if c != ' ' {
+            rendered.push(c);
+            if c == ',' || c == ';' {
+                rendered.push(' ');
+            }
+            continue;
+        }
+
+        let prev = rendered.chars().last();
+        let next = chars.get(i + 1).copied();
+        if prev.is_some_and(is_ident_char) && next.is_some_and(is_ident_char) {
+            rendered.push(' ');
+        }
+    }
+
+    rendered
+}
+
 /// Implementation of the `#[message]` macro.
 pub fn message_impl(
     args: proc_macro::TokenStream,
@@ -167,8 +396,8 @@ pub fn message_impl(
     let crate_ = args.crate_.unwrap_or(default_path_to_elfo);
 
     // TODO: what about parsing into something cheaper?
-    let input = parse_macro_input!(input as DeriveInput);
-    let name = &input.ident;
+    let mut input = parse_macro_input!(input as DeriveInput);
+    let redactions = redact::strip_redactions(&mut input, &crate_);
     let serde_crate = format!("{}::_priv::serde", crate_.to_token_stream());
     let internal = quote![#crate_::_priv];
 
@@ -178,21 +407,56 @@ pub fn message_impl(
         .map(LitStr::value)
         .unwrap_or_else(|| input.ident.to_string());
 
-    let derive_debug =
-        (!args.transparent).then(|| gen_derive_attr(&args.not, "Debug", quote![Debug]));
+    // Invalid `transparent` is ignored to avoid noisy errors of derives.
+    let transparent_field = args
+        .transparent
+        .then(|| transparent_field(&input).cloned())
+        .flatten();
+
+    // Generated before `serde` attributes are stripped from versioned messages.
+    let schema_shape = args
+        .schema
+        .then(|| schema::gen_schema_shape(&input, transparent_field.is_some(), &crate_));
+
+    // Redacted fields require a custom `Debug` impl.
+    let derive_debug = (transparent_field.is_none() && redactions.is_empty())
+        .then(|| gen_derive_attr(&args.not, "Debug", quote![Debug]));
     let derive_clone = gen_derive_attr(&args.not, "Clone", quote![Clone]);
-    let derive_serialize =
+    let mut derive_serialize =
         gen_derive_attr(&args.not, "Serialize", quote![#internal::serde::Serialize]);
-    let derive_deserialize = gen_derive_attr(
+    let mut derive_deserialize = gen_derive_attr(
         &args.not,
         "Deserialize",
         quote![#internal::serde::Deserialize],
     );
 
+    // Versioned messages are serialized by generated impls instead of derives.
+    let impl_versioned = if let Some(version) = &args.version {
+        let impls = version::gen_versioned(
+            &mut input,
+            version,
+            &args.removed,
+            !derive_serialize.is_empty(),
+            !derive_deserialize.is_empty(),
+            &crate_,
+        );
+
+        if impls.is_some() {
+            derive_serialize = TokenStream::new();
+            derive_deserialize = TokenStream::new();
+        }
+        impls
+    } else {
+        version::reject_field_attrs(&mut input);
+        None
+    };
+
     let serde_crate_attr = (!derive_serialize.is_empty() || !derive_deserialize.is_empty())
         .then(|| quote! { #[serde(crate = #serde_crate)] });
 
-    let serde_transparent_attr = args.transparent.then(|| quote! { #[serde(transparent)] });
+    let serde_transparent_attr = transparent_field
+        .is_some()
+        .then(|| quote! { #[serde(transparent)] });
 
     // TODO: pass to `ElfoResponseWrapper`.
     let dumping_allowed = args.dumping_allowed.unwrap_or(true);
@@ -203,9 +467,42 @@ pub fn message_impl(
         quote! { #crate_::get_protocol!() }
     };
 
-    let impl_message = (!args.part).then(|| {
-        quote! {
-            impl #crate_::Message for #name {
+    let is_generic = !input.generics.params.is_empty();
+    let instances = if args.part {
+        Vec::new()
+    } else {
+        collect_instances(&input, &args.instances, &name_str)
+    };
+
+    let schema_docs = gen_docs(&input.attrs);
+    let schema_response = match &args.ret {
+        Some(ret) => {
+            let ret = render_tokens(&ret.to_token_stream());
+            quote! { Some(#ret) }
+        }
+        None => quote! { None },
+    };
+
+    let impl_instances = instances.iter().map(|instance| {
+        let Instance { ty, name, aliases } = instance;
+
+        let impl_schema = schema_shape.as_ref().map(|shape| {
+            quote! {
+                #[#internal::linkme::distributed_slice(#internal::MESSAGE_SCHEMAS)]
+                #[linkme(crate = #internal::linkme)]
+                static SCHEMA: &#crate_::schema::MessageSchema =
+                    &#crate_::schema::MessageSchema::new(
+                        #protocol,
+                        #name,
+                        #schema_docs,
+                        #schema_response,
+                        #shape,
+                    );
+            }
+        });
+
+        let impl_message = quote! {
+            impl #crate_::Message for #ty {
                 #[inline(always)]
                 fn _type_id() -> #internal::MessageTypeId {
                     #internal::MessageTypeId::new(VTABLE)
@@ -219,52 +516,75 @@ pub fn message_impl(
 
             #[#internal::linkme::distributed_slice(#internal::MESSAGE_VTABLES_LIST)]
             #[linkme(crate = #internal::linkme)]
-            static VTABLE: &#internal::MessageVTable = &#internal::MessageVTable::new::<#name>(
-                #name_str,
+            static VTABLE: &#internal::MessageVTable = &#internal::MessageVTable::new::<#ty>(
+                #name,
                 #protocol,
                 #dumping_allowed
             );
-        }
-    });
+        };
 
-    let impl_request = args.ret.as_ref().map(|ret| {
-        let wrapper_name_str = format!("{name_str}::Response");
-        let protocol = args.protocol.as_ref().map(|p| quote! { protocol = #p, });
+        let impl_request = args.ret.as_ref().map(|ret| {
+            let wrapper_name_str = format!("{name}::Response");
+            let protocol = args.protocol.as_ref().map(|p| quote! { protocol = #p, });
 
-        quote! {
-            impl #crate_::Request for #name {
-                type Response = #ret;
-                type Wrapper = ElfoResponseWrapper;
-            }
+            quote! {
+                impl #crate_::Request for #ty {
+                    type Response = #ret;
+                    type Wrapper = ElfoResponseWrapper;
+                }
 
-            #[message(not(Debug), #protocol name = #wrapper_name_str, elfo = #crate_)]
-            pub struct ElfoResponseWrapper(#ret);
+                #[message(not(Debug), #protocol name = #wrapper_name_str, elfo = #crate_)]
+                pub struct ElfoResponseWrapper(#ret);
 
-            impl ::std::fmt::Debug for ElfoResponseWrapper {
-                #[inline]
-                fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
-                    ::std::fmt::Debug::fmt(&self.0, f)
+                impl ::std::fmt::Debug for ElfoResponseWrapper {
+                    #[inline]
+                    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                        ::std::fmt::Debug::fmt(&self.0, f)
+                    }
                 }
-            }
 
-            impl From<#ret> for ElfoResponseWrapper {
-                #[inline]
-                fn from(inner: #ret) -> Self {
-                    ElfoResponseWrapper(inner)
+                impl From<#ret> for ElfoResponseWrapper {
+                    #[inline]
+                    fn from(inner: #ret) -> Self {
+                        ElfoResponseWrapper(inner)
+                    }
                 }
-            }
 
-            impl From<ElfoResponseWrapper> for #ret {
-                #[inline]
-                fn from(wrapper: ElfoResponseWrapper) -> Self {
-                    wrapper.0
+                impl From<ElfoResponseWrapper> for #ret {
+                    #[inline]
+                    fn from(wrapper: ElfoResponseWrapper) -> Self {
+                        wrapper.0
+                    }
                 }
             }
+        });
+
+        // Every instance has its own `VTABLE` and `ElfoResponseWrapper`.
+        if is_generic {
+            quote! {
+                const _: () = {
+                    #aliases
+                    #impl_message
+                    #impl_request
+                    #impl_schema
+                };
+            }
+        } else {
+            quote! {
+                #impl_message
+                #impl_request
+                #impl_schema
+            }
         }
     });
 
-    let impl_debug =
-        (args.transparent && args.not.iter().all(|x| x != "Debug")).then(|| gen_impl_debug(&input));
+    let impl_debug = transparent_field
+        .filter(|_| args.not.iter().all(|x| x != "Debug"))
+        .map(|field| gen_impl_debug(&input, &field, redactions.single(), &crate_))
+        .or_else(|| {
+            let custom = !redactions.is_empty() && args.not.iter().all(|x| x != "Debug");
+            custom.then(|| redact::gen_impl_debug(&input, &redactions, &crate_))
+        });
 
     // Don't add `use` statements here to avoid possible collisions with user code.
     let expanded = quote! {
@@ -279,9 +599,9 @@ pub fn message_impl(
         #[doc(hidden)]
         #[allow(unreachable_code)] // for `enum Impossible {}`
         const _: () = {
-            #impl_message
-            #impl_request
+            #(#impl_instances)*
             #impl_debug
+            #impl_versioned
         };
     };
 
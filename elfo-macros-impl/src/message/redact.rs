@@ -0,0 +1,201 @@
+//! Redaction of fields, see `#[message(redact)]`.
+//!
+//! Redacted fields are masked or hashed both in `Debug` output and in dumps,
+//! but they're still sent as is across nodes.
+
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::{
+    parse::{Error as ParseError, Parse, ParseStream},
+    parse_quote, Attribute, Data, DeriveInput, Fields, Ident, Member, Path,
+};
+
+use crate::errors::emit_error;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(super) enum Redaction {
+    /// `#[message(redact)]`
+    Mask,
+    /// `#[message(redact(hash))]`
+    Hash,
+}
+
+impl Parse for Redaction {
+    fn parse(input: ParseStream<'_>) -> Result<Self, ParseError> {
+        let ident: Ident = input.parse()?;
+        assert_eq!(ident, "redact");
+
+        if input.is_empty() {
+            return Ok(Self::Mask);
+        }
+
+        let content;
+        syn::parenthesized!(content in input);
+        let kind: Ident = content.parse()?;
+        if kind != "hash" || !content.is_empty() || !input.is_empty() {
+            return Err(ParseError::new(
+                kind.span(),
+                "expected `redact` or `redact(hash)`",
+            ));
+        }
+
+        Ok(Self::Hash)
+    }
+}
+
+fn is_redact_attr(attr: &Attribute) -> bool {
+    attr.path().is_ident("message")
+        && attr
+            .parse_args_with(|input: ParseStream<'_>| {
+                let ident: Ident = input.parse()?;
+                let _: TokenStream = input.parse()?;
+                Ok(ident == "redact")
+            })
+            .unwrap_or(false)
+}
+
+/// Redactions of fields, indexed like `DeriveInput` fields
+/// (by variants for enums).
+pub(super) struct Redactions(Vec<Vec<Option<Redaction>>>);
+
+impl Redactions {
+    pub(super) fn is_empty(&self) -> bool {
+        self.0.iter().flatten().all(Option::is_none)
+    }
+
+    /// Returns the redaction of the only field of a struct.
+    pub(super) fn single(&self) -> Option<Redaction> {
+        self.0.first()?.first().copied().flatten()
+    }
+}
+
+/// Strips `#[message(redact)]` attributes and replaces them with
+/// `#[serde(serialize_with = "..")]` to redact fields in dumps.
+pub(super) fn strip_redactions(input: &mut DeriveInput, crate_: &Path) -> Redactions {
+    let fields = match &mut input.data {
+        Data::Struct(data) => vec![&mut data.fields],
+        Data::Enum(data) => data.variants.iter_mut().map(|v| &mut v.fields).collect(),
+        Data::Union(_) => return Redactions(Vec::new()),
+    };
+
+    let redactions = fields
+        .into_iter()
+        .map(|fields| {
+            fields
+                .iter_mut()
+                .map(|field| {
+                    let mut redaction = None;
+                    for attr in field.attrs.iter().filter(|a| is_redact_attr(a)) {
+                        match attr.parse_args::<Redaction>() {
+                            Ok(parsed) => redaction = Some(parsed),
+                            Err(err) => crate::errors::emit(err),
+                        }
+                    }
+                    field.attrs.retain(|a| !is_redact_attr(a));
+
+                    let serialize_with = match redaction? {
+                        Redaction::Mask => quote!(#crate_::dumping::redact).to_string(),
+                        Redaction::Hash => quote!(#crate_::dumping::redact_hash).to_string(),
+                    };
+                    let serialize_with = serialize_with.replace(' ', "");
+                    field
+                        .attrs
+                        .push(parse_quote!(#[serde(serialize_with = #serialize_with)]));
+
+                    redaction
+                })
+                .collect()
+        })
+        .collect();
+
+    Redactions(redactions)
+}
+
+/// Wraps the expression of a field into a redacting `Debug` wrapper.
+pub(super) fn gen_debug_field(
+    value: TokenStream,
+    redaction: Option<Redaction>,
+    crate_: &Path,
+) -> TokenStream {
+    match redaction {
+        Some(Redaction::Mask) => quote! { &#crate_::dumping::RedactedMask },
+        Some(Redaction::Hash) => quote! { &#crate_::dumping::RedactedHash(#value) },
+        None => value,
+    }
+}
+
+/// Generates `Debug` like `#[derive(Debug)]` does, but redacting fields.
+pub(super) fn gen_impl_debug(
+    input: &DeriveInput,
+    redactions: &Redactions,
+    crate_: &Path,
+) -> TokenStream {
+    let name = &input.ident;
+
+    let gen_arm = |path: TokenStream, name: &Ident, fields: &Fields, redactions: &[_]| {
+        let name = name.to_string();
+        let members = fields.members().collect::<Vec<_>>();
+        let bindings = (0..members.len())
+            .map(|i| format_ident!("__self_{}", i, span = Span::mixed_site()))
+            .collect::<Vec<_>>();
+        let values = bindings
+            .iter()
+            .zip(redactions)
+            .map(|(binding, redaction)| gen_debug_field(quote!(#binding), *redaction, crate_));
+
+        let body = match fields {
+            Fields::Named(_) => {
+                let names = members.iter().map(|m| match m {
+                    Member::Named(ident) => ident.to_string(),
+                    Member::Unnamed(_) => unreachable!(),
+                });
+                quote! { f.debug_struct(#name)#(.field(#names, #values))*.finish() }
+            }
+            Fields::Unnamed(_) => quote! { f.debug_tuple(#name)#(.field(#values))*.finish() },
+            Fields::Unit => quote! { f.write_str(#name) },
+        };
+
+        quote! { #path { #(#members: #bindings),* } => #body, }
+    };
+
+    let arms = match &input.data {
+        Data::Struct(data) => vec![gen_arm(quote!(Self), name, &data.fields, &redactions.0[0])],
+        Data::Enum(data) => data
+            .variants
+            .iter()
+            .zip(&redactions.0)
+            .map(|(variant, redactions)| {
+                let ident = &variant.ident;
+                gen_arm(quote!(Self::#ident), ident, &variant.fields, redactions)
+            })
+            .collect(),
+        Data::Union(_) => {
+            emit_error!(name.span(), "unions cannot be messages");
+            return TokenStream::new();
+        }
+    };
+
+    // Generic parameters must be `Debug` too, like for `#[derive(Debug)]`.
+    let mut generics = input.generics.clone();
+    let params = generics
+        .type_params()
+        .map(|p| p.ident.clone())
+        .collect::<Vec<_>>();
+    let where_clause = generics.make_where_clause();
+    for param in params {
+        where_clause
+            .predicates
+            .push(parse_quote!(#param: ::std::fmt::Debug));
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics ::std::fmt::Debug for #name #ty_generics #where_clause {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,222 @@
+//! Schemas of messages, see `#[message(schema)]`.
+//!
+//! Schemas describe the serialized form, so `#[serde(..)]` attributes are
+//! respected: fields and variants are renamed, skipped ones are omitted,
+//! flattened fields are marked and transparent structs are unwrapped.
+
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
+use syn::{
+    meta::ParseNestedMeta, token, Attribute, Data, DeriveInput, Expr, Fields, LitStr, Path, Token,
+};
+
+use super::{gen_docs, render_tokens};
+use crate::errors::emit_error;
+
+/// `#[serde(..)]` attributes affecting the serialized form.
+#[derive(Default)]
+struct SerdeAttrs {
+    rename: Option<String>,
+    rename_all: Option<String>,
+    skip: bool,
+    flatten: bool,
+    transparent: bool,
+}
+
+impl SerdeAttrs {
+    fn parse(attrs: &[Attribute]) -> Self {
+        let mut this = Self::default();
+
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+            // Invalid attributes are reported by serde itself.
+            let _ = attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    this.rename = parse_serialize_name(&meta)?;
+                } else if meta.path.is_ident("rename_all") {
+                    this.rename_all = parse_serialize_name(&meta)?;
+                } else if meta.path.is_ident("skip") || meta.path.is_ident("skip_serializing") {
+                    this.skip = true;
+                } else if meta.path.is_ident("flatten") {
+                    this.flatten = true;
+                } else if meta.path.is_ident("transparent") {
+                    this.transparent = true;
+                } else {
+                    skip_meta(&meta)?;
+                }
+                Ok(())
+            });
+        }
+
+        this
+    }
+}
+
+/// Parses `name = "a"` and `name(serialize = "a", deserialize = "b")`.
+fn parse_serialize_name(meta: &ParseNestedMeta<'_>) -> syn::Result<Option<String>> {
+    if meta.input.peek(Token![=]) {
+        return Ok(Some(meta.value()?.parse::<LitStr>()?.value()));
+    }
+
+    let mut name = None;
+    meta.parse_nested_meta(|meta| {
+        let value = meta.value()?.parse::<LitStr>()?.value();
+        if meta.path.is_ident("serialize") {
+            name = Some(value);
+        }
+        Ok(())
+    })?;
+    Ok(name)
+}
+
+fn skip_meta(meta: &ParseNestedMeta<'_>) -> syn::Result<()> {
+    if meta.input.peek(Token![=]) {
+        meta.value()?.parse::<Expr>()?;
+    } else if meta.input.peek(token::Paren) {
+        let content;
+        syn::parenthesized!(content in meta.input);
+        content.parse::<TokenStream>()?;
+    }
+    Ok(())
+}
+
+/// Applies `#[serde(rename_all = "..")]` the same way serde does.
+/// Fields are expected to be in `snake_case`, variants in `PascalCase`.
+fn apply_rename_all(rule: &str, name: &str, is_variant: bool) -> String {
+    let snake = if is_variant {
+        let mut snake = String::with_capacity(name.len() + 4);
+        for (i, c) in name.char_indices() {
+            if i > 0 && c.is_uppercase() {
+                snake.push('_');
+            }
+            snake.push(c.to_ascii_lowercase());
+        }
+        snake
+    } else {
+        name.to_owned()
+    };
+
+    let pascal = || {
+        if is_variant {
+            return name.to_owned();
+        }
+
+        let mut pascal = String::with_capacity(name.len());
+        let mut capitalize = true;
+        for c in name.chars() {
+            if c == '_' {
+                capitalize = true;
+            } else if capitalize {
+                pascal.push(c.to_ascii_uppercase());
+                capitalize = false;
+            } else {
+                pascal.push(c);
+            }
+        }
+        pascal
+    };
+
+    match rule {
+        "lowercase" if is_variant => name.to_ascii_lowercase(),
+        "UPPERCASE" if is_variant => name.to_ascii_uppercase(),
+        "lowercase" => name.to_owned(),
+        "UPPERCASE" => name.to_ascii_uppercase(),
+        "PascalCase" => pascal(),
+        "camelCase" => {
+            let pascal = pascal();
+            let mut chars = pascal.chars();
+            chars.next().map_or_else(String::new, |first| {
+                first.to_ascii_lowercase().to_string() + chars.as_str()
+            })
+        }
+        "snake_case" => snake,
+        "SCREAMING_SNAKE_CASE" => snake.to_ascii_uppercase(),
+        "kebab-case" => snake.replace('_', "-"),
+        "SCREAMING-KEBAB-CASE" => snake.replace('_', "-").to_ascii_uppercase(),
+        // Unknown rules are reported by serde itself.
+        _ => name.to_owned(),
+    }
+}
+
+fn gen_schema_fields(fields: &Fields, rename_all: Option<&str>, crate_: &Path) -> TokenStream {
+    let schema = quote![#crate_::schema];
+    let items = fields.iter().enumerate().filter_map(|(index, field)| {
+        let attrs = SerdeAttrs::parse(&field.attrs);
+        if attrs.skip {
+            return None;
+        }
+
+        let name = match (&field.ident, attrs.rename) {
+            (None, _) => index.to_string(),
+            (Some(_), Some(rename)) => rename,
+            (Some(ident), None) => {
+                let name = ident.to_string();
+                let name = name.strip_prefix("r#").unwrap_or(&name);
+                rename_all.map_or_else(|| name.into(), |rule| apply_rename_all(rule, name, false))
+            }
+        };
+        let ty = render_tokens(&field.ty.to_token_stream());
+        let docs = gen_docs(&field.attrs);
+        let flatten = attrs.flatten;
+        Some(quote! { #schema::Field::new(#name, #ty, #docs, #flatten) })
+    });
+
+    match fields {
+        Fields::Named(_) => quote! { #schema::Fields::Named(&[#(#items),*]) },
+        Fields::Unnamed(_) => quote! { #schema::Fields::Unnamed(&[#(#items),*]) },
+        Fields::Unit => quote! { #schema::Fields::Unit },
+    }
+}
+
+/// Generates `schema::Shape` of the message.
+pub(super) fn gen_schema_shape(
+    input: &DeriveInput,
+    transparent: bool,
+    crate_: &Path,
+) -> TokenStream {
+    let schema = quote![#crate_::schema];
+    let container = SerdeAttrs::parse(&input.attrs);
+    let rename_all = container.rename_all.as_deref();
+
+    match &input.data {
+        Data::Struct(data) if transparent || container.transparent => {
+            // Other fields can be skipped only, otherwise serde rejects the struct.
+            let field = data
+                .fields
+                .iter()
+                .find(|field| !SerdeAttrs::parse(&field.attrs).skip);
+            let ty = field.map_or_else(String::new, |field| {
+                render_tokens(&field.ty.to_token_stream())
+            });
+            quote! { #schema::Shape::Transparent(#ty) }
+        }
+        Data::Struct(data) => {
+            let fields = gen_schema_fields(&data.fields, rename_all, crate_);
+            quote! { #schema::Shape::Struct(#fields) }
+        }
+        Data::Enum(data) => {
+            let variants = data.variants.iter().filter_map(|variant| {
+                let attrs = SerdeAttrs::parse(&variant.attrs);
+                if attrs.skip {
+                    return None;
+                }
+
+                let name = attrs.rename.unwrap_or_else(|| {
+                    let name = variant.ident.to_string();
+                    match rename_all {
+                        Some(rule) => apply_rename_all(rule, &name, true),
+                        None => name,
+                    }
+                });
+                let docs = gen_docs(&variant.attrs);
+                let fields =
+                    gen_schema_fields(&variant.fields, attrs.rename_all.as_deref(), crate_);
+                Some(quote! { #schema::Variant::new(#name, #docs, #fields) })
+            });
+            quote! { #schema::Shape::Enum(&[#(#variants),*]) }
+        }
+        Data::Union(_) => {
+            emit_error!(input.ident.span(), "`schema` is not supported for unions");
+            quote! { #schema::Shape::Struct(#schema::Fields::Unit) }
+        }
+    }
+}
@@ -0,0 +1,444 @@
+//! Versioning of messages, see `#[message(version = N)]`.
+//!
+//! Versioned messages are serialized through hidden wire structs:
+//! * writers add `@version` and fields with old names and removed fields, so
+//!   nodes with older versions can still read messages;
+//! * readers accept payloads of older versions and migrate them by filling
+//!   added fields with defaults and reading renamed fields by old names.
+
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::{
+    parenthesized,
+    parse::{Error as ParseError, Parse, ParseStream},
+    spanned::Spanned,
+    Attribute, Data, DeriveInput, Expr, Fields, Ident, LitInt, LitStr, Path, Token, Type,
+};
+
+use crate::errors::emit_error;
+
+/// `removed(name: Type)` or `removed(name: Type = expr)`.
+#[derive(Debug)]
+pub(super) struct Removed {
+    ident: Ident,
+    ty: Type,
+    default: Option<Expr>,
+}
+
+impl Parse for Removed {
+    fn parse(input: ParseStream<'_>) -> Result<Self, ParseError> {
+        let ident = input.parse()?;
+        let _: Token![:] = input.parse()?;
+        let ty = input.parse()?;
+        let default = if input.peek(Token![=]) {
+            let _: Token![=] = input.parse()?;
+            Some(input.parse()?)
+        } else {
+            None
+        };
+
+        Ok(Self { ident, ty, default })
+    }
+}
+
+impl Removed {
+    pub(super) fn span(&self) -> Span {
+        self.ident.span()
+    }
+}
+
+#[derive(Default)]
+struct FieldArgs {
+    added: Option<LitInt>,
+    default: Option<Expr>,
+    renamed: Option<(LitStr, LitInt)>,
+}
+
+impl Parse for FieldArgs {
+    fn parse(input: ParseStream<'_>) -> Result<Self, ParseError> {
+        let mut args = Self::default();
+
+        // `#[message(added = 2)]`
+        // `#[message(added = 2, default = expr)]`
+        // `#[message(renamed(from = "old", since = 2))]`
+        while !input.is_empty() {
+            let ident: Ident = input.parse()?;
+
+            match ident.to_string().as_str() {
+                "added" => {
+                    let _: Token![=] = input.parse()?;
+                    args.added = Some(input.parse()?);
+                }
+                "default" => {
+                    let _: Token![=] = input.parse()?;
+                    args.default = Some(input.parse()?);
+                }
+                "renamed" => {
+                    let content;
+                    parenthesized!(content in input);
+
+                    let (mut from, mut since) = (None, None);
+                    while !content.is_empty() {
+                        let ident: Ident = content.parse()?;
+                        let _: Token![=] = content.parse()?;
+
+                        match ident.to_string().as_str() {
+                            "from" => from = Some(content.parse()?),
+                            "since" => since = Some(content.parse()?),
+                            _ => return Err(content.error("expected `from` or `since`")),
+                        }
+
+                        if !content.is_empty() {
+                            let _: Token![,] = content.parse()?;
+                        }
+                    }
+
+                    match (from, since) {
+                        (Some(from), Some(since)) => args.renamed = Some((from, since)),
+                        _ => {
+                            return Err(input.error("expected `renamed(from = \"..\", since = N)`"))
+                        }
+                    }
+                }
+                _ => return Err(input.error("unknown attribute")),
+            }
+
+            if !input.is_empty() {
+                let _: Token![,] = input.parse()?;
+            }
+        }
+
+        Ok(args)
+    }
+}
+
+enum Evolution {
+    None,
+    Added { since: u32, default: TokenStream },
+    Renamed { from: LitStr, since: u32 },
+}
+
+struct Field {
+    ident: Ident,
+    ty: Type,
+    serde_attrs: Vec<Attribute>,
+    evolution: Evolution,
+}
+
+fn is_message_attr(attr: &Attribute) -> bool {
+    attr.path().is_ident("message")
+}
+
+fn is_serde_attr(attr: &Attribute) -> bool {
+    attr.path().is_ident("serde")
+}
+
+/// Removes field-level `#[message(..)]` attributes of unversioned messages,
+/// emitting errors for them.
+pub(super) fn reject_field_attrs(input: &mut DeriveInput) {
+    let fields = match &mut input.data {
+        Data::Struct(data) => data.fields.iter_mut().collect::<Vec<_>>(),
+        Data::Enum(data) => data
+            .variants
+            .iter_mut()
+            .flat_map(|v| v.fields.iter_mut())
+            .collect(),
+        Data::Union(_) => return,
+    };
+
+    for field in fields {
+        for attr in field.attrs.iter().filter(|a| is_message_attr(a)) {
+            emit_error!(
+                attr.span(),
+                "field attributes require `#[message(version = N)]`"
+            );
+        }
+        field.attrs.retain(|a| !is_message_attr(a));
+    }
+}
+
+fn parse_since(lit: &LitInt, version: u32) -> u32 {
+    match lit.base10_parse::<u32>() {
+        Ok(since) if (2..=version).contains(&since) => since,
+        _ => {
+            emit_error!(lit.span(), "expected a version between 2 and {version}");
+            version
+        }
+    }
+}
+
+/// Strips evolution and `serde` attributes from the input and returns
+/// impls of `Serialize` and `Deserialize` for it (if not disabled).
+/// Returns `None` if the input cannot be versioned, so the usual derives
+/// should be used to avoid excess errors.
+pub(super) fn gen_versioned(
+    input: &mut DeriveInput,
+    version: &LitInt,
+    removed: &[Removed],
+    serialize: bool,
+    deserialize: bool,
+    crate_: &Path,
+) -> Option<TokenStream> {
+    let name = input.ident.clone();
+    let serde_crate = format!("{}::_priv::serde", quote!(#crate_));
+    let serde = quote![#crate_::_priv::serde];
+
+    let version = match version.base10_parse::<u32>() {
+        Ok(version) if version > 0 => version,
+        _ => {
+            emit_error!(version.span(), "expected a positive version");
+            return None;
+        }
+    };
+
+    if !input.generics.params.is_empty() {
+        emit_error!(
+            input.generics.span(),
+            "versioned messages cannot be generic"
+        );
+        return None;
+    }
+
+    let data_fields = match &mut input.data {
+        Data::Struct(data) if matches!(data.fields, Fields::Named(_)) => &mut data.fields,
+        _ => {
+            emit_error!(
+                name.span(),
+                "`version` is applicable only for structs with named fields"
+            );
+            return None;
+        }
+    };
+
+    let container_serde_attrs = input
+        .attrs
+        .iter()
+        .filter(|a| is_serde_attr(a))
+        .cloned()
+        .collect::<Vec<_>>();
+    input.attrs.retain(|a| !is_serde_attr(a));
+
+    let mut fields = Vec::new();
+    for field in data_fields.iter_mut() {
+        let mut args = FieldArgs::default();
+        for attr in field.attrs.iter().filter(|a| is_message_attr(a)) {
+            match attr.parse_args::<FieldArgs>() {
+                Ok(parsed) => args = parsed,
+                Err(err) => crate::errors::emit(err),
+            }
+        }
+
+        let evolution = match args {
+            FieldArgs {
+                added: Some(added),
+                renamed: None,
+                default,
+            } => Evolution::Added {
+                since: parse_since(&added, version),
+                default: default.map_or_else(
+                    || quote! { ::std::default::Default::default() },
+                    |expr| quote! { #expr },
+                ),
+            },
+            FieldArgs {
+                added: None,
+                renamed: Some((from, since)),
+                default: None,
+            } => Evolution::Renamed {
+                since: parse_since(&since, version),
+                from,
+            },
+            FieldArgs {
+                added: None,
+                renamed: None,
+                default: None,
+            } => Evolution::None,
+            FieldArgs {
+                added: None,
+                default: Some(default),
+                ..
+            } => {
+                emit_error!(default.span(), "`default` requires `added`");
+                Evolution::None
+            }
+            FieldArgs {
+                added: Some(added), ..
+            } => {
+                emit_error!(added.span(), "`added` and `renamed` are incompatible");
+                Evolution::None
+            }
+        };
+
+        fields.push(Field {
+            ident: field.ident.clone().expect("named field"),
+            ty: field.ty.clone(),
+            serde_attrs: field
+                .attrs
+                .iter()
+                .filter(|a| is_serde_attr(a))
+                .cloned()
+                .collect(),
+            evolution,
+        });
+
+        field
+            .attrs
+            .retain(|a| !is_message_attr(a) && !is_serde_attr(a));
+    }
+
+    let renamed = fields
+        .iter()
+        .filter_map(|field| match &field.evolution {
+            Evolution::Renamed { from, .. } => Some((field, from)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    let impl_serialize = serialize.then(|| {
+        let idents = fields.iter().map(|f| &f.ident);
+        let tys = fields.iter().map(|f| &f.ty);
+        let attrs = fields.iter().map(|f| &f.serde_attrs);
+        let renamed_idents = renamed
+            .iter()
+            .map(|(f, _)| format_ident!("elfo_renamed_{}", f.ident))
+            .collect::<Vec<_>>();
+        let renamed_froms = renamed.iter().map(|(_, from)| from);
+        let renamed_tys = renamed.iter().map(|(f, _)| &f.ty);
+        let renamed_attrs = renamed.iter().map(|(f, _)| &f.serde_attrs);
+        let renamed_sources = renamed.iter().map(|(f, _)| &f.ident);
+        let removed_idents = removed
+            .iter()
+            .map(|r| format_ident!("elfo_removed_{}", r.ident))
+            .collect::<Vec<_>>();
+        let removed_names = removed.iter().map(|r| r.ident.to_string());
+        let removed_tys = removed.iter().map(|r| &r.ty);
+        let removed_defaults = removed.iter().map(|r| match &r.default {
+            Some(expr) => quote! { #expr },
+            None => quote! { ::std::default::Default::default() },
+        });
+        let self_idents = fields.iter().map(|f| &f.ident);
+
+        quote! {
+            #[derive(#serde::Serialize)]
+            #[serde(crate = #serde_crate)]
+            #(#container_serde_attrs)*
+            struct ElfoWireRef<'a> {
+                #[serde(rename = "@version")]
+                elfo_version: u32,
+                #(#(#attrs)* #idents: &'a #tys,)*
+                #(#(#renamed_attrs)* #[serde(rename = #renamed_froms)] #renamed_idents: &'a #renamed_tys,)*
+                #(#[serde(rename = #removed_names)] #removed_idents: #removed_tys,)*
+                #[serde(skip)]
+                elfo_marker: ::std::marker::PhantomData<&'a ()>,
+            }
+
+            impl #serde::Serialize for #name {
+                fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+                where
+                    S: #serde::Serializer,
+                {
+                    let wire = ElfoWireRef {
+                        elfo_version: #version,
+                        #(#self_idents: &self.#self_idents,)*
+                        #(#renamed_idents: &self.#renamed_sources,)*
+                        #(#removed_idents: #removed_defaults,)*
+                        elfo_marker: ::std::marker::PhantomData,
+                    };
+                    #serde::Serialize::serialize(&wire, serializer)
+                }
+            }
+        }
+    });
+
+    let impl_deserialize = deserialize.then(|| {
+        let wire_fields = fields.iter().map(|f| {
+            let Field {
+                ident,
+                ty,
+                serde_attrs,
+                ..
+            } = f;
+
+            match &f.evolution {
+                Evolution::None => quote! { #(#serde_attrs)* #ident: #ty, },
+                Evolution::Added { .. } => quote! {
+                    #(#serde_attrs)* #[serde(default)] #ident: ::std::option::Option<#ty>,
+                },
+                Evolution::Renamed { from, .. } => {
+                    let renamed_ident = format_ident!("elfo_renamed_{}", ident);
+                    quote! {
+                        #(#serde_attrs)* #[serde(default)] #ident: ::std::option::Option<#ty>,
+                        #(#serde_attrs)* #[serde(default, rename = #from)]
+                        #renamed_ident: ::std::option::Option<#ty>,
+                    }
+                }
+            }
+        });
+
+        let migrations = fields.iter().map(|f| {
+            let ident = &f.ident;
+            let ident_str = ident.to_string();
+            let missing = quote! {
+                return ::std::result::Result::Err(
+                    <D::Error as #serde::de::Error>::missing_field(#ident_str),
+                )
+            };
+
+            match &f.evolution {
+                Evolution::None => quote! { #ident: wire.#ident, },
+                Evolution::Added { since, default } => quote! {
+                    #ident: match wire.#ident {
+                        ::std::option::Option::Some(value) => value,
+                        ::std::option::Option::None if version < #since => #default,
+                        ::std::option::Option::None => #missing,
+                    },
+                },
+                Evolution::Renamed { since, .. } => {
+                    let renamed_ident = format_ident!("elfo_renamed_{}", ident);
+                    quote! {
+                        #ident: match if version < #since {
+                            wire.#renamed_ident.or(wire.#ident)
+                        } else {
+                            wire.#ident.or(wire.#renamed_ident)
+                        } {
+                            ::std::option::Option::Some(value) => value,
+                            ::std::option::Option::None => #missing,
+                        },
+                    }
+                }
+            }
+        });
+
+        quote! {
+            #[derive(#serde::Deserialize)]
+            #[serde(crate = #serde_crate)]
+            #(#container_serde_attrs)*
+            struct ElfoWire {
+                #[serde(rename = "@version", default)]
+                elfo_version: ::std::option::Option<u32>,
+                #(#wire_fields)*
+            }
+
+            impl<'de> #serde::Deserialize<'de> for #name {
+                fn deserialize<D>(deserializer: D) -> ::std::result::Result<Self, D::Error>
+                where
+                    D: #serde::Deserializer<'de>,
+                {
+                    let wire = <ElfoWire as #serde::Deserialize>::deserialize(deserializer)?;
+                    // Unversioned messages are the first version.
+                    #[allow(unused_variables)]
+                    let version = wire.elfo_version.unwrap_or(1);
+
+                    ::std::result::Result::Ok(Self {
+                        #(#migrations)*
+                    })
+                }
+            }
+        }
+    });
+
+    Some(quote! {
+        #impl_serialize
+        #impl_deserialize
+    })
+}
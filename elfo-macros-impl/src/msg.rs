@@ -1,9 +1,10 @@
 use std::{char, collections::HashMap};
 
-use proc_macro2::Span;
-use quote::quote_spanned;
+use proc_macro2::{Span, TokenStream};
+use quote::{quote, quote_spanned, ToTokens};
 use syn::{
-    parse_macro_input, spanned::Spanned, Arm, ExprMatch, Ident, Pat, PatIdent, PatWild, Path, Token,
+    parse_macro_input, spanned::Spanned, Arm, Attribute, ExprMatch, Ident, Pat, PatIdent, PatWild,
+    Path, Token,
 };
 
 use crate::errors::emit_error;
@@ -216,11 +217,108 @@ fn add_groups(groups: &mut Vec<MessageGroup>, mut arm: Arm) {
     }
 }
 
+/// Extracts `#[exhaustive(SomeProtocolEnum)]` from attributes of the match.
+fn extract_exhaustive(attrs: &mut Vec<Attribute>) -> Option<Path> {
+    let index = attrs
+        .iter()
+        .position(|attr| attr.path().is_ident("exhaustive"))?;
+    let attr = attrs.remove(index);
+
+    match attr.parse_args::<Path>() {
+        Ok(path) => Some(path),
+        Err(err) => {
+            emit_error!(
+                attr.span(),
+                "expected `#[exhaustive(SomeProtocolEnum)]`: {err}"
+            );
+            None
+        }
+    }
+}
+
+/// Checks if the pattern matches any value of its type.
+/// Called for refined patterns, so `Msg` is already replaced with `_`.
+fn is_irrefutable(pat: &Pat) -> bool {
+    match pat {
+        Pat::Wild(_) | Pat::Rest(_) => true,
+        Pat::Ident(pat) => pat.subpat.as_ref().map_or(true, |sp| is_irrefutable(&sp.1)),
+        Pat::Or(pat) => pat.cases.iter().any(is_irrefutable),
+        Pat::Paren(pat) => is_irrefutable(&pat.pat),
+        Pat::Reference(pat) => is_irrefutable(&pat.pat),
+        // Variants of enums are refutable: `Enum::Variant { .. }`.
+        Pat::Struct(pat) => {
+            extract_path_to_type(&pat.path) == pat.path
+                && pat.fields.iter().all(|field| is_irrefutable(&field.pat))
+        }
+        Pat::Tuple(pat) => pat.elems.iter().all(is_irrefutable),
+        Pat::TupleStruct(pat) => {
+            extract_path_to_type(&pat.path) == pat.path && pat.elems.iter().all(is_irrefutable)
+        }
+        Pat::Type(pat) => is_irrefutable(&pat.pat),
+        _ => false,
+    }
+}
+
+/// Generates a check that all messages of the protocol enum are matched.
+///
+/// A message is considered matched only by an arm without a guard and with
+/// an irrefutable pattern, e.g. `Msg`, `msg @ Msg` or `Msg { .. }`.
+fn gen_exhaustive_check(
+    protocol_enum: &Path,
+    groups: &[MessageGroup],
+    crate_: &Path,
+) -> TokenStream {
+    let mut matched = Vec::<&Path>::new();
+    for group in groups {
+        let is_matched = group
+            .arms
+            .iter()
+            .any(|arm| arm.guard.is_none() && is_irrefutable(&arm.pat));
+
+        if !is_matched {
+            continue;
+        }
+
+        if let GroupKind::Regular(path) | GroupKind::Request(path) = &group.kind {
+            let repr = path.to_token_stream().to_string();
+            if !matched
+                .iter()
+                .any(|p| p.to_token_stream().to_string() == repr)
+            {
+                matched.push(path);
+            }
+        }
+    }
+
+    // The enum itself is matched, so all its messages are matched too.
+    let protocol_enum_repr = protocol_enum.to_token_stream().to_string();
+    if matched
+        .iter()
+        .any(|p| p.to_token_stream().to_string() == protocol_enum_repr)
+    {
+        return TokenStream::new();
+    }
+
+    let check = quote_spanned! {protocol_enum.span()=>
+        must_match_all::<#protocol_enum>();
+    };
+
+    quote! {
+        {
+            struct Arms;
+            #(impl #crate_::_priv::Matches<#matched> for Arms {})*
+            fn must_match_all<P: #crate_::_priv::MatchedBy<Arms>>() {}
+            #check
+        }
+    }
+}
+
 /// Implements the `msg!` macro.
 pub fn msg_impl(input: proc_macro::TokenStream, path_to_elfo: Path) -> proc_macro::TokenStream {
     let crate_ = path_to_elfo;
     let mixed_site = Span::mixed_site();
-    let input = parse_macro_input!(input as ExprMatch);
+    let mut input = parse_macro_input!(input as ExprMatch);
+    let exhaustive = extract_exhaustive(&mut input.attrs);
     let mut groups = Vec::<MessageGroup>::with_capacity(input.arms.len());
 
     for arm in input.arms.into_iter() {
@@ -229,6 +327,9 @@ pub fn msg_impl(input: proc_macro::TokenStream, path_to_elfo: Path) -> proc_macr
 
     // println!(">>> HERE {:#?}", groups);
 
+    let exhaustive_check =
+        exhaustive.map(|protocol_enum| gen_exhaustive_check(&protocol_enum, &groups, &crate_));
+
     let groups = groups
         .iter()
         .map(|group| match (&group.kind, &group.arms[..]) {
@@ -236,7 +337,8 @@ pub fn msg_impl(input: proc_macro::TokenStream, path_to_elfo: Path) -> proc_macr
             // - used the regular syntax while the request one is expected
             // - unexhaustive match
             (GroupKind::Regular(path), arms) => quote_spanned! {mixed_site=>
-                else if type_id == <#path as #crate_::Message>::_type_id() {
+                // Use `_is_supertype_of()` to support enums made by `protocol_enum!`.
+                else if <#path as #crate_::Message>::_is_supertype_of(type_id) {
                     // Ensure it's not a request, or a request but only in a borrowed context.
                     // We cannot use `static_assertions` here because it wraps the check into
                     // a closure that forbids us to use generic `msg!`: (`msg!(match e { M => .. })`).
@@ -249,6 +351,17 @@ pub fn msg_impl(input: proc_macro::TokenStream, path_to_elfo: Path) -> proc_macr
                         <#path as MustBeRegularNotRequest<_, _>>::test(&envelope)
                     }
 
+                    // Ensure it's not a protocol enum in a borrowed context,
+                    // because such enums cannot be borrowed from envelopes.
+                    {
+                        trait MustBeOwnedIfProtocolEnum<A, E> { fn test(_: &E) {} }
+                        impl<E, M> MustBeOwnedIfProtocolEnum<(), E> for M {}
+                        struct Invalid;
+                        impl<'a, M: internal::ProtocolEnum>
+                            MustBeOwnedIfProtocolEnum<Invalid, &'a #crate_::Envelope> for M {}
+                        <#path as MustBeOwnedIfProtocolEnum<_, _>>::test(&envelope)
+                    }
+
                     #[allow(unknown_lints, clippy::blocks_in_conditions)]
                     match {
                         // Support both owned and borrowed contexts, relying on the type inference.
@@ -304,6 +417,7 @@ pub fn msg_impl(input: proc_macro::TokenStream, path_to_elfo: Path) -> proc_macr
     // TODO: propagate `input.attrs`?
     let expanded = quote_spanned!(mixed_site=> {
         use #crate_::_priv as internal;
+        #exhaustive_check
         let envelope = #match_expr;
         let type_id = envelope.type_id();
         #[allow(clippy::suspicious_else_formatting)]
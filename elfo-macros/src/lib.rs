@@ -6,6 +6,21 @@ use syn::parse_quote;
 use elfo_macros_impl::{message_impl, msg_impl};
 
 /// Matches a message based on the provided envelope.
+///
+/// The match can be marked as `#[exhaustive(SomeProtocolEnum)]` to check at
+/// compile time that every message of the enum generated by `protocol_enum!`
+/// is matched by some arm, e.g. `Stop | Pause => {}` to default them
+/// explicitly. Only arms without guards and with irrefutable patterns, e.g.
+/// `Stop`, `stop @ Stop` or `Stop { .. }`, are counted. Thus, adding a new
+/// message to the enum breaks the build instead of hitting the `_` arm
+/// silently, which is still allowed for other messages:
+/// ```ignore
+/// msg!(#[exhaustive(Command)] match envelope {
+///     Start => { .. }
+///     Stop | Pause => {}
+///     _ => { .. }
+/// })
+/// ```
 #[proc_macro]
 pub fn msg(input: TokenStream) -> TokenStream {
     msg_impl(input, parse_quote!(::elfo))
@@ -23,9 +38,37 @@ pub fn msg_core(input: TokenStream) -> TokenStream {
 /// * `part` — do not derive `Message`. Useful for parts of messages.
 /// * `ret = SomeType` — also derive `Request` with the provided response type.
 /// * `name = "SomeName"` — override a message name.
+/// * `transparent` — serialize, dump and print (`Debug`) a single-field struct
+///   as its field without nesting, e.g. `42` instead of `{"id":42}` for `struct
+///   UserId { id: u32 }`. It's still a distinct type for routing and matching.
 /// * `not(Debug)` — do not derive `Debug`. Useful for custom instances.
 /// * `not(Clone)` — the same for `Clone`.
 /// * `elfo = some::path` — override a path to elfo.
+/// * `instances(Generic<A>, Generic<B>)` — implement `Message` for listed
+///   instances of a generic type. Names of instances include generic arguments,
+///   e.g. `Generic<A>`. `ret` can refer to generic parameters.
+/// * `version = N` — version the message to evolve it across nodes with
+///   different versions. Only for structs with named fields. The version is
+///   written with the message, and older payloads are migrated according to
+///   field attributes:
+///   * `#[message(added = N)]` — the field is added in the version `N`, older
+///     payloads get `Default::default()` or `default = expr` if specified.
+///   * `#[message(renamed(from = "old", since = N))]` — the field is renamed in
+///     the version `N`. It's also written by the old name for older nodes.
+/// * `removed(field: Type = expr)` — the field is removed from the versioned
+///   message, but still written (`Default::default()` if `expr` is omitted) for
+///   older nodes.
+/// * `schema` — register the schema of the message (names and types of fields,
+///   docs) to be available at runtime in `elfo::schema`.
+///
+/// Field attributes to redact sensitive data in `Debug` output and dumps (but
+/// not in messages sent to other nodes):
+/// * `#[message(redact)]` — print and dump the field as `<redacted>`.
+/// * `#[message(redact(hash))]` — print and dump the field as a keyed hash,
+///   e.g. `<redacted:0123456789abcdef>`, to correlate equal values without
+///   revealing them. The key is taken from the `ELFO_REDACT_KEY` env variable,
+///   so hashes are stable across runs and nodes only if it's set. It's meant
+///   for correlation, not secrecy: guesses can be checked with the key.
 #[proc_macro_attribute]
 pub fn message(attr: TokenStream, input: TokenStream) -> TokenStream {
     message_impl(attr, input, parse_quote!(::elfo))
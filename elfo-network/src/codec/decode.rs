@@ -4,13 +4,17 @@ use byteorder::{LittleEndian, ReadBytesExt};
 use eyre::{ensure, eyre, Error, WrapErr};
 use tracing::error;
 
-use elfo_core::{errors::RequestError, tracing::TraceId, AnyMessage, RequestId};
+use elfo_core::{
+    errors::RequestError,
+    tracing::{Baggage, TraceId},
+    AnyMessage, RequestId,
+};
 use elfo_utils::likely;
 
 use crate::codec::format::{
-    NetworkAddr, NetworkEnvelope, NetworkEnvelopePayload, FLAG_IS_LAST_RESPONSE, KIND_MASK,
-    KIND_REGULAR, KIND_REQUEST_ALL, KIND_REQUEST_ANY, KIND_RESPONSE_FAILED, KIND_RESPONSE_IGNORED,
-    KIND_RESPONSE_OK,
+    NetworkAddr, NetworkEnvelope, NetworkEnvelopePayload, FLAG_HAS_BAGGAGE, FLAG_HAS_PARENT_SPAN,
+    FLAG_IS_LAST_RESPONSE, KIND_MASK, KIND_REGULAR, KIND_REQUEST_ALL, KIND_REQUEST_ANY,
+    KIND_RESPONSE_FAILED, KIND_RESPONSE_IGNORED, KIND_RESPONSE_OK,
 };
 
 #[derive(Default)]
@@ -205,6 +209,16 @@ fn do_decode(frame: &mut Cursor<&[u8]>) -> Result<NetworkEnvelope, DecodeError>
     let sender = get_addr(frame)?;
     let recipient = get_addr(frame)?;
     let trace_id = TraceId::try_from(frame.read_u64::<LittleEndian>()?)?;
+    let parent_span_id = if flags & FLAG_HAS_PARENT_SPAN != 0 {
+        Some(TraceId::try_from(frame.read_u64::<LittleEndian>()?)?)
+    } else {
+        None
+    };
+    let baggage = if flags & FLAG_HAS_BAGGAGE != 0 {
+        get_baggage(frame)?
+    } else {
+        Baggage::default()
+    };
 
     let map_decode_error = |result: Result<AnyMessage, MessageDecodeError>,
                             request_id: Option<RequestId>|
@@ -265,6 +279,15 @@ fn do_decode(frame: &mut Cursor<&[u8]>) -> Result<NetworkEnvelope, DecodeError>
         sender,
         recipient,
         trace_id,
+        parent_span_id,
+        baggage,
         payload,
     })
 }
+
+fn get_baggage(frame: &mut Cursor<&[u8]>) -> eyre::Result<Baggage> {
+    let count = frame.read_u8()?;
+    (0..count)
+        .map(|_| Ok((get_str(frame)?, get_str(frame)?)))
+        .collect()
+}
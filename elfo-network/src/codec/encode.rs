@@ -1,13 +1,16 @@
+use std::time::Duration;
+
 use byteorder::{LittleEndian, WriteBytesExt};
 use derive_more::{Display, From};
-use tracing::error;
+use tracing::{error, warn};
 
 use elfo_core::{errors::RequestError, scope, Message};
-use elfo_utils::likely;
+use elfo_utils::{cooldown, likely};
 
 use crate::codec::format::{
-    NetworkEnvelope, NetworkEnvelopePayload, FLAG_IS_LAST_RESPONSE, KIND_REGULAR, KIND_REQUEST_ALL,
-    KIND_REQUEST_ANY, KIND_RESPONSE_FAILED, KIND_RESPONSE_IGNORED, KIND_RESPONSE_OK,
+    NetworkEnvelope, NetworkEnvelopePayload, FLAG_HAS_BAGGAGE, FLAG_HAS_PARENT_SPAN,
+    FLAG_IS_LAST_RESPONSE, KIND_REGULAR, KIND_REQUEST_ALL, KIND_REQUEST_ANY, KIND_RESPONSE_FAILED,
+    KIND_RESPONSE_IGNORED, KIND_RESPONSE_OK,
 };
 
 #[derive(Debug, Display, From)]
@@ -105,6 +108,12 @@ fn do_encode(
     if is_last_response {
         flags |= FLAG_IS_LAST_RESPONSE;
     }
+    if envelope.parent_span_id.is_some() {
+        flags |= FLAG_HAS_PARENT_SPAN;
+    }
+    if !envelope.baggage.is_empty() {
+        flags |= FLAG_HAS_BAGGAGE;
+    }
     dst.write_u8(flags | kind)?;
 
     // sender
@@ -116,6 +125,40 @@ fn do_encode(
     // trace_id
     dst.write_u64::<LittleEndian>(u64::from(envelope.trace_id))?;
 
+    // parent_span_id
+    if let Some(span_id) = envelope.parent_span_id {
+        dst.write_u64::<LittleEndian>(u64::from(span_id))?;
+    }
+
+    // baggage
+    if !envelope.baggage.is_empty() {
+        let items = envelope
+            .baggage
+            .iter()
+            .filter(|(key, value)| {
+                let fits = key.len() <= 255 && value.len() <= 255;
+                if !fits && cooldown!(Duration::from_secs(15)) {
+                    warn!(%key, "baggage item is too long, skipping");
+                }
+                fits
+            })
+            .take(255);
+
+        let count_pos = dst.len();
+        dst.write_u8(0)?;
+
+        let mut count = 0;
+        for (key, value) in items {
+            for s in [key, value] {
+                dst.write_u8(s.len() as u8)?;
+                dst.extend_from_slice(s.as_bytes());
+            }
+            count += 1;
+        }
+
+        dst[count_pos] = count;
+    }
+
     // request_id
     if let Some(request_id) = request_id {
         dst.write_u64::<LittleEndian>(request_id.to_ffi())?;
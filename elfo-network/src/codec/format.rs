@@ -5,8 +5,8 @@
 //! │ size of whole frame   │ 32 │                     │
 //! ├───────────────────────┼────┤                     │
 //! │ flags                 │  4 │                     │ flags:
-//! ├───────────────────────┼────┤                     │ - <reserved>       = 1
-//! │ kind                  │  4 │                     │ - <reserved>       = 2
+//! ├───────────────────────┼────┤                     │ - has baggage      = 1
+//! │ kind                  │  4 │                     │ - has parent span  = 2
 //! ├───────────────────────┼────┤       always        │ - <reserved>       = 4
 //! │ sender                │ 64 │                     │ - is last response = 8
 //! ├───────────────────────┼────┤                     │
@@ -14,11 +14,17 @@
 //! ├───────────────────────┼────┤                     │
 //! │ trace id              │ 64 │                     │ kinds:
 //! ├───────────────────────┼────┼─────────────────────┤ - Regular           = 0
-//! │ request id            │ 64 │ if kind != Regular  │ - RequestAny        = 1
+//! │ parent span id        │ 64 │ if has parent span  │ - RequestAny        = 1
 //! ├───────────────────────┼────┼─────────────────────┤ - RequestAll        = 2
-//! │ protocol's length (P) │  8 │                     │ - Response::Ok      = 3
-//! ├───────────────────────┼────┤                     │ - Response::Failed  = 4
-//! │ protocol              │ 8P │                     │ - Response::Ignored = 5
+//! │ baggage's length (B)  │  8 │                     │ - Response::Ok      = 3
+//! ├───────────────────────┼────┤ if has baggage      │ - Response::Failed  = 4
+//! │ baggage               │ .. │                     │ - Response::Ignored = 5
+//! ├───────────────────────┼────┼─────────────────────┤
+//! │ request id            │ 64 │ if kind != Regular  │
+//! ├───────────────────────┼────┼─────────────────────┤
+//! │ protocol's length (P) │  8 │                     │
+//! ├───────────────────────┼────┤                     │
+//! │ protocol              │ 8P │                     │
 //! ├───────────────────────┼────┤ if kind !=          │
 //! │ msg name's length (N) │  8 │ - Response::Failed  │
 //! ├───────────────────────┼────┤ - Response::Ignored │
@@ -28,6 +34,14 @@
 //! └───────────────────────┴────┴─────────────────────┘
 //! ```
 //!
+//! The baggage consists of `B` items, every item is a key followed by a value,
+//! both are encoded as a length (8 bits) and UTF-8 bytes. Items with a key
+//! or value longer than 255 bytes are skipped with a warning.
+//!
+//! The `has baggage` flag was reserved in older versions, so the baggage is
+//! sent only if the peer has the `BAGGAGE` capability in the handshake.
+//! The same applies to the `has parent span` flag and the `SPANS` capability.
+//!
 //! All fields are encoded using LE ordering.
 
 // TODO: send message ID instead of protocol/name.
@@ -37,12 +51,14 @@ use derive_more::Display;
 use elfo_core::{
     addr::{Addr, NodeNo},
     errors::RequestError,
-    tracing::TraceId,
+    tracing::{Baggage, TraceId},
     AnyMessage, Message, RequestId,
 };
 use elfo_utils::likely;
 
 // Flags are shifted by 4 bits to the left because of the kind.
+pub(crate) const FLAG_HAS_BAGGAGE: u8 = 1 << 4;
+pub(crate) const FLAG_HAS_PARENT_SPAN: u8 = 1 << 5;
 pub(crate) const FLAG_IS_LAST_RESPONSE: u8 = 1 << 7;
 
 pub(crate) const KIND_MASK: u8 = 0xF;
@@ -58,6 +74,8 @@ pub(crate) struct NetworkEnvelope {
     pub(crate) sender: NetworkAddr,
     pub(crate) recipient: NetworkAddr,
     pub(crate) trace_id: TraceId,
+    pub(crate) parent_span_id: Option<TraceId>,
+    pub(crate) baggage: Baggage,
     pub(crate) payload: NetworkEnvelopePayload,
 }
 
@@ -4,7 +4,12 @@ pub(crate) mod format;
 
 #[cfg(test)]
 mod tests {
-    use elfo_core::{message, tracing::TraceId, Message, _priv::AnyMessage};
+    use elfo_core::{
+        _priv::AnyMessage,
+        message,
+        tracing::{Baggage, TraceId},
+        Message,
+    };
     use std::convert::TryFrom;
 
     use super::{
@@ -26,6 +31,11 @@ mod tests {
             sender: NetworkAddr::NULL,
             recipient: NetworkAddr::NULL,
             trace_id: TraceId::try_from(trace_index).unwrap(),
+            parent_span_id: (trace_index % 2 == 0).then(|| TraceId::try_from(42).unwrap()),
+            // 0, 1 or 2 items.
+            baggage: (0..trace_index % 3)
+                .map(|i| (format!("key{i}"), format!("value{trace_index}")))
+                .collect::<Baggage>(),
             payload: NetworkEnvelopePayload::Regular {
                 message: AnyMessage::new(message),
             },
@@ -91,6 +101,11 @@ mod tests {
 
             // Check that the message was decoded correctly.
             assert_eq!(decoded_small_envelope.trace_id, small_envelope.trace_id);
+            assert_eq!(
+                decoded_small_envelope.parent_span_id,
+                small_envelope.parent_span_id
+            );
+            assert_eq!(decoded_small_envelope.baggage, small_envelope.baggage);
             assert_eq!(decoded_small_envelope.sender, small_envelope.sender);
             assert_eq!(decoded_small_envelope.recipient, small_envelope.recipient);
 
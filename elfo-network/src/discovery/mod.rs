@@ -5,9 +5,9 @@ use futures::StreamExt;
 use tracing::{debug, error, info, warn};
 
 use elfo_core::{
-    message, msg, scope, tracing::TraceId, AnyMessage, Envelope, Message, MoveOwnership,
-    RestartPolicy, _priv::MessageKind, addr::GroupNo, messages::ConfigUpdated, stream::Stream,
-    RestartParams, Topology,
+    _priv::MessageKind, addr::GroupNo, message, messages::ConfigUpdated, msg, scope,
+    stream::Stream, tracing::TraceId, AnyMessage, Envelope, Message, MoveOwnership, RestartParams,
+    RestartPolicy, Topology,
 };
 
 use crate::{
@@ -136,7 +136,7 @@ impl Discovery {
     }
 
     fn get_capabilities(&self) -> socket::Capabilities {
-        let mut capabilities = socket::Capabilities::empty();
+        let mut capabilities = socket::Capabilities::BAGGAGE | socket::Capabilities::SPANS;
         if self.cfg.compression.algorithm == CompressionAlgorithm::Lz4 {
             capabilities |= socket::Capabilities::LZ4;
         }
@@ -540,6 +540,8 @@ async fn send_regular<M: Message>(
         sender: NetworkAddr::NULL,    // doesn't matter
         recipient: NetworkAddr::NULL, // doesn't matter
         trace_id: scope::trace_id(),
+        parent_span_id: None,
+        baggage: Default::default(),
         payload: NetworkEnvelopePayload::Regular {
             message: AnyMessage::new(message),
         },
@@ -28,12 +28,17 @@ bitflags::bitflags! {
     #[derive(Clone, Copy)]
     pub(crate) struct Capabilities: u32 {
         const LZ4 = 1 << 8;
+        /// Envelopes can have the `has baggage` flag, see `codec/format.rs`.
+        const BAGGAGE = 1 << 9;
+        /// Envelopes can have the `has parent span` flag, see `codec/format.rs`.
+        const SPANS = 1 << 10;
     }
 }
 
 pub(crate) struct Socket {
     pub(crate) info: raw::SocketInfo,
     pub(crate) peer: Peer,
+    pub(crate) capabilities: Capabilities,
     pub(crate) read: ReadHalf,
     pub(crate) write: WriteHalf,
     pub(crate) idle: IdleTracker,
@@ -61,6 +66,7 @@ impl Socket {
         Self {
             info: raw.info,
             peer: Peer::new(handshake.node_no, handshake.launch_id),
+            capabilities: handshake.capabilities,
             read: ReadHalf::new(framed_read, raw.read, idle_track),
             write: WriteHalf::new(framed_write, raw.write),
             idle: idle_tracker,
@@ -379,6 +385,8 @@ mod tests {
                 sender: NetworkAddr::NULL,
                 recipient: NetworkAddr::NULL,
                 trace_id: TraceId::try_from(1).unwrap(),
+                parent_span_id: None,
+                baggage: Default::default(),
                 payload: NetworkEnvelopePayload::Regular {
                     message: AnyMessage::new(TestSocketMessage("a".repeat(i * 10))),
                 },
@@ -413,4 +421,28 @@ mod tests {
         )
         .await;
     }
+
+    #[tokio::test]
+    async fn capabilities_are_negotiated() {
+        let transport = "tcp://127.0.0.1:9202".parse().unwrap();
+        let node_no = NodeNo::from_bits(2).unwrap();
+        let launch_id = NodeLaunchId::from_bits(1);
+
+        // An older node doesn't know about baggage.
+        let mut listen_stream = listen(&transport, node_no, launch_id, Capabilities::LZ4)
+            .await
+            .expect("failed to bind server to a port");
+
+        let node_no = NodeNo::from_bits(1).unwrap();
+        let capabilities = Capabilities::LZ4 | Capabilities::BAGGAGE;
+        let client_socket_fut = connect(&transport, node_no, launch_id, capabilities);
+
+        let (server_socket, client_socket) =
+            future::join(listen_stream.next(), client_socket_fut).await;
+
+        for socket in [server_socket.unwrap(), client_socket.unwrap()] {
+            assert!(socket.capabilities.contains(Capabilities::LZ4));
+            assert!(!socket.capabilities.contains(Capabilities::BAGGAGE));
+        }
+    }
 }
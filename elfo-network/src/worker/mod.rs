@@ -6,15 +6,16 @@ use parking_lot::Mutex;
 use tracing::{debug, error, info, trace, warn};
 
 use elfo_core::{
-    addr::{Addr, NodeNo},
-    message, Local, Message,
     _priv::{AnyMessage, EbrGuard, GroupVisitor, MessageKind, Object, OwnedObject},
+    addr::{Addr, NodeNo},
     errors::{RequestError, SendError, TrySendError},
+    message,
     messages::{ConfigUpdated, Impossible},
     msg, remote, scope,
     stream::Stream,
     time::Interval,
-    Context, Envelope, ResponseToken, Topology,
+    tracing::Baggage,
+    Context, Envelope, Local, Message, ResponseToken, Topology,
 };
 use elfo_utils::{likely, time::Instant, unlikely};
 
@@ -36,7 +37,7 @@ use crate::{
     frame::write::FrameState,
     protocol::{internode, DataConnectionFailed, GroupInfo, HandleConnection},
     rtt::Rtt,
-    socket::{ReadError, ReadHalf, WriteHalf},
+    socket::{Capabilities, ReadError, ReadHalf, WriteHalf},
     NetworkContext,
 };
 
@@ -139,6 +140,8 @@ impl Worker {
             rx: local_rx,
             tx: socket.write,
             requests: requests.clone(),
+            // Older nodes would misdecode envelopes with baggage or parent spans.
+            capabilities: socket.capabilities,
         };
         self.ctx.attach(Stream::once(sw.exec()));
 
@@ -230,6 +233,7 @@ struct SocketWriter {
     rx: kanal::AsyncReceiver<KanalItem>,
     tx: WriteHalf,
     requests: Arc<Mutex<OutgoingRequests>>,
+    capabilities: Capabilities,
 }
 
 impl SocketWriter {
@@ -250,7 +254,8 @@ impl SocketWriter {
             // TODO: error handling, metrics.
             let mut item = self.rx.recv().await.unwrap();
             loop {
-                let (network_envelope, response_token) = make_network_envelope(item, self.node_no);
+                let (network_envelope, response_token) =
+                    make_network_envelope(item, self.node_no, self.capabilities);
                 scope::set_trace_id(network_envelope.trace_id);
 
                 // NOTE: We use `unwrap()` for results from all `self.tx` methods because these
@@ -281,12 +286,30 @@ impl SocketWriter {
 fn make_network_envelope(
     item: KanalItem,
     node_no: NodeNo,
+    capabilities: Capabilities,
 ) -> (NetworkEnvelope, Option<ResponseToken>) {
-    let (sender, trace_id, payload, token) = match (item.envelope, item.token) {
+    let get_span_id = |envelope: &Envelope| {
+        if capabilities.contains(Capabilities::SPANS) {
+            envelope.parent_span_id()
+        } else {
+            None
+        }
+    };
+    let get_baggage = |envelope: &Envelope| {
+        if capabilities.contains(Capabilities::BAGGAGE) {
+            envelope.baggage().clone()
+        } else {
+            Baggage::default()
+        }
+    };
+
+    let (sender, trace_id, span_id, baggage, payload, token) = match (item.envelope, item.token) {
         // Regular, RequestAny, RequestAll
         (Ok(envelope), None) => {
             let sender = envelope.sender();
             let trace_id = envelope.trace_id();
+            let span_id = get_span_id(&envelope);
+            let baggage = get_baggage(&envelope);
             let (message, kind) = envelope.unpack::<AnyMessage>().expect("impossible");
 
             let (payload, token) = match kind {
@@ -308,12 +331,14 @@ fn make_network_envelope(
                 MessageKind::Response { .. } => unreachable!(),
             };
 
-            (sender, trace_id, payload, token)
+            (sender, trace_id, span_id, baggage, payload, token)
         }
         // Response
         (Ok(envelope), Some(token)) => {
             let sender = envelope.sender();
             let trace_id = envelope.trace_id();
+            let span_id = get_span_id(&envelope);
+            let baggage = get_baggage(&envelope);
             let (message, kind) = envelope.unpack::<AnyMessage>().expect("impossible");
 
             let payload = match kind {
@@ -331,7 +356,7 @@ fn make_network_envelope(
             // The token is semantically moved to another node.
             token.forget();
 
-            (sender, trace_id, payload, None)
+            (sender, trace_id, span_id, baggage, payload, None)
         }
         // Failed/Ignored Response
         (Err(err), Some(token)) => {
@@ -347,7 +372,7 @@ fn make_network_envelope(
             // The token is semantically moved to another node.
             token.forget();
 
-            (sender, trace_id, payload, None)
+            (sender, trace_id, None, Default::default(), payload, None)
         }
         (Err(_), None) => unreachable!(),
     };
@@ -356,6 +381,8 @@ fn make_network_envelope(
         sender: NetworkAddr::from_local(sender, node_no),
         recipient: item.recipient,
         trace_id,
+        parent_span_id: span_id,
+        baggage,
         payload,
     };
 
@@ -397,6 +424,7 @@ impl SocketReader {
             };
 
             scope::set_trace_id(network_envelope.trace_id);
+            scope::set_baggage(network_envelope.baggage.clone());
 
             let (sender, recipient) = (network_envelope.sender, network_envelope.recipient);
             let envelope = ward!(self.make_envelope(network_envelope), continue);
@@ -488,6 +516,8 @@ impl SocketReader {
         let sender = network_envelope.sender.into_remote();
         let recipient = network_envelope.recipient.into_local();
         let trace_id = network_envelope.trace_id;
+        let span_id = network_envelope.parent_span_id;
+        let baggage = network_envelope.baggage;
 
         let (message, message_kind) = match network_envelope.payload {
             NetworkEnvelopePayload::Regular { message } => {
@@ -555,6 +585,8 @@ impl SocketReader {
                         MessageKind::Response { sender, request_id },
                         trace_id,
                     )
+                    .with_baggage(baggage)
+                    .with_parent_span_id(span_id)
                 });
 
                 // Since this is a response to a request which originated from this node,
@@ -565,7 +597,11 @@ impl SocketReader {
             }
         };
 
-        Some(Envelope::with_trace_id(message, message_kind, trace_id))
+        Some(
+            Envelope::with_trace_id(message, message_kind, trace_id)
+                .with_baggage(baggage)
+                .with_parent_span_id(span_id),
+        )
     }
 
     fn handle_system_message(&mut self, envelope: &Envelope) -> bool {
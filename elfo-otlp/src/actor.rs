@@ -0,0 +1,87 @@
+use std::{sync::Arc, time::Duration};
+
+use metrics::counter;
+use tracing::{debug, warn};
+
+use elfo_core::{
+    message,
+    messages::{ConfigUpdated, Terminate},
+    msg, scope,
+    time::Interval,
+    tracing::Span,
+    ActorGroup, Blueprint, Context, RestartParams, RestartPolicy, TerminationPolicy,
+};
+
+use crate::{client, config::Config, encoder, storage::SpanStorage};
+
+#[message]
+struct ExportTick;
+
+pub(crate) fn new(storage: Arc<SpanStorage>) -> Blueprint {
+    ActorGroup::new()
+        .config::<Config>()
+        .termination_policy(TerminationPolicy::manually())
+        .restart_policy(RestartPolicy::on_failure(RestartParams::new(
+            Duration::from_secs(5),
+            Duration::from_secs(30),
+        )))
+        .stop_order(100)
+        .exec(move |ctx| exec(ctx, storage.clone()))
+}
+
+async fn exec(mut ctx: Context<Config>, storage: Arc<SpanStorage>) {
+    let group = scope::with(|scope| scope.meta().group.clone());
+    storage.configure(&group, ctx.config().buffer_capacity);
+
+    let interval = ctx.attach(Interval::new(ExportTick));
+    interval.start(ctx.config().export_interval);
+
+    while let Some(envelope) = ctx.recv().await {
+        msg!(match envelope {
+            ConfigUpdated => {
+                let config = ctx.config();
+                interval.set_period(config.export_interval);
+                storage.configure(&group, config.buffer_capacity);
+            }
+            ExportTick => export(ctx.config(), &storage).await,
+            Terminate => {
+                // Send spans collected before termination.
+                storage.disable();
+                export(ctx.config(), &storage).await;
+                break;
+            }
+        });
+    }
+}
+
+async fn export(config: &Config, storage: &SpanStorage) {
+    let (spans, lost) = storage.drain();
+
+    if lost > 0 {
+        counter!("elfo_otlp_lost_spans_total", lost as u64);
+    }
+
+    for batch in spans.chunks(config.max_batch_size.max(1)) {
+        send(config, batch).await;
+    }
+}
+
+async fn send(config: &Config, spans: &[Span]) {
+    let body = encoder::encode(&config.service_name, spans);
+
+    match client::post(&config.endpoint, body, config.export_timeout).await {
+        Ok(()) => {
+            debug!(count = spans.len(), "spans are exported");
+            counter!("elfo_otlp_exported_spans_total", spans.len() as u64);
+        }
+        Err(err) => {
+            warn!(
+                message = "cannot export spans",
+                error = %err,
+                count = spans.len(),
+                endpoint = %config.endpoint,
+            );
+            counter!("elfo_otlp_lost_spans_total", spans.len() as u64);
+        }
+    }
+}
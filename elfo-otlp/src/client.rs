@@ -0,0 +1,73 @@
+use std::{io, time::Duration};
+
+use http_body_util::{BodyExt, Full};
+use hyper::{
+    body::Bytes,
+    client::conn::http1,
+    header::{CONTENT_TYPE, HOST},
+    Method, Request, Uri,
+};
+use hyper_util::rt::TokioIo;
+use tokio::{net::TcpStream, time::timeout};
+use tracing::debug;
+
+use elfo_core::scope;
+
+/// Sends the body to the OTLP/HTTP endpoint.
+/// * It supports only HTTP/1.
+/// * It doesn't support keep-alive connections, exports are rare.
+/// * It doesn't support TLS.
+pub(crate) async fn post(endpoint: &str, body: Vec<u8>, limit: Duration) -> io::Result<()> {
+    timeout(limit, do_post(endpoint, body))
+        .await
+        .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "the collector is too slow"))?
+}
+
+async fn do_post(endpoint: &str, body: Vec<u8>) -> io::Result<()> {
+    // The endpoint is validated while parsing the config.
+    let uri = endpoint.parse::<Uri>().map_err(io::Error::other)?;
+    let authority = uri.authority().expect("invalid endpoint").clone();
+    let port = authority.port_u16().unwrap_or(80);
+
+    let stream = TcpStream::connect((authority.host(), port)).await?;
+    let (mut sender, connection) = http1::handshake(TokioIo::new(stream))
+        .await
+        .map_err(io::Error::other)?;
+
+    scope::runtime().spawn(Box::pin(async move {
+        if let Err(err) = connection.await {
+            debug!(error = %err, "the connection to the collector is closed");
+        }
+    }));
+
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri(uri.path_and_query().map_or("/", |p| p.as_str()))
+        .header(HOST, authority.as_str())
+        .header(CONTENT_TYPE, "application/json")
+        .body(Full::new(Bytes::from(body)))
+        .map_err(io::Error::other)?;
+
+    let response = sender
+        .send_request(request)
+        .await
+        .map_err(io::Error::other)?;
+    let status = response.status();
+
+    if status.is_success() {
+        return Ok(());
+    }
+
+    // The collector describes the problem in the body.
+    let body = response
+        .into_body()
+        .collect()
+        .await
+        .map_err(io::Error::other)?
+        .to_bytes();
+
+    Err(io::Error::other(format!(
+        "the collector responded with {status}: {}",
+        String::from_utf8_lossy(&body)
+    )))
+}
@@ -0,0 +1,98 @@
+//! Configuration for the OTLP exporter.
+//!
+//! Note: all types here are exported only for documentation purposes
+//! and are not subject to stable guarantees. However, the config
+//! structure (usually encoded in TOML) follows stable guarantees.
+
+use std::time::Duration;
+
+use hyper::Uri;
+use serde::{de::Error as _, Deserialize, Deserializer};
+
+/// The OTLP exporter's config.
+///
+/// # Example
+/// ```toml
+/// [system.otlp]
+/// endpoint = "http://otel-collector:4318/v1/traces"
+/// service_name = "matcher"
+/// export_interval = "2s"
+/// ```
+#[derive(Debug, Deserialize)]
+pub struct Config {
+    /// The URL of the OTLP/HTTP traces endpoint of a collector.
+    /// Only plain HTTP is supported, spans are sent as JSON.
+    ///
+    /// `http://localhost:4318/v1/traces` by default.
+    #[serde(
+        deserialize_with = "deserialize_endpoint",
+        default = "default_endpoint"
+    )]
+    pub endpoint: String,
+    /// The `service.name` resource attribute.
+    ///
+    /// The name of the executable by default.
+    #[serde(default = "default_service_name")]
+    pub service_name: String,
+    /// How often spans are sent to the collector.
+    ///
+    /// `1s` by default.
+    #[serde(with = "humantime_serde", default = "default_export_interval")]
+    pub export_interval: Duration,
+    /// How long to wait for the collector to accept spans.
+    ///
+    /// `5s` by default.
+    #[serde(with = "humantime_serde", default = "default_export_timeout")]
+    pub export_timeout: Duration,
+    /// The maximum number of spans in memory. If exceeded, new spans are
+    /// dropped until the next export.
+    ///
+    /// `100_000` by default.
+    #[serde(default = "default_buffer_capacity")]
+    pub buffer_capacity: usize,
+    /// The maximum number of spans in one request to the collector.
+    ///
+    /// `4096` by default.
+    #[serde(default = "default_max_batch_size")]
+    pub max_batch_size: usize,
+}
+
+fn deserialize_endpoint<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    let endpoint = String::deserialize(deserializer)?;
+    let uri = endpoint.parse::<Uri>().map_err(D::Error::custom)?;
+
+    if uri.scheme_str() != Some("http") || uri.host().is_none() {
+        return Err(D::Error::custom(
+            "only `http://host[:port]/path` endpoints are supported",
+        ));
+    }
+
+    Ok(endpoint)
+}
+
+fn default_endpoint() -> String {
+    "http://localhost:4318/v1/traces".into()
+}
+
+fn default_service_name() -> String {
+    std::env::current_exe()
+        .ok()
+        .and_then(|path| Some(path.file_stem()?.to_str()?.to_owned()))
+        .unwrap_or_else(|| "elfo".into())
+}
+
+fn default_export_interval() -> Duration {
+    Duration::from_secs(1)
+}
+
+fn default_export_timeout() -> Duration {
+    Duration::from_secs(5)
+}
+
+fn default_buffer_capacity() -> usize {
+    100_000
+}
+
+fn default_max_batch_size() -> usize {
+    4096
+}
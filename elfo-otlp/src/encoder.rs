@@ -0,0 +1,248 @@
+//! Encodes spans as `ExportTraceServiceRequest` in the OTLP/JSON format.
+//! See <https://opentelemetry.io/docs/specs/otlp/#json-protobuf-encoding>.
+
+use std::borrow::Cow;
+
+use serde::Serialize;
+
+use elfo_core::{
+    dumping::MessageKind,
+    tracing::{Baggage, Span, SpanEvent, SpanEventKind, TraceId, W3cTraceContext},
+};
+
+pub(crate) fn encode(service_name: &str, spans: &[Span]) -> Vec<u8> {
+    let request = ExportRequest {
+        resource_spans: [ResourceSpans {
+            resource: Resource {
+                attributes: vec![KeyValue::string("service.name", service_name)],
+            },
+            scope_spans: [ScopeSpans {
+                scope: InstrumentationScope {
+                    name: "elfo",
+                    version: env!("CARGO_PKG_VERSION"),
+                },
+                spans: spans.iter().map(OtlpSpan::from).collect(),
+            }],
+        }],
+    };
+
+    serde_json::to_vec(&request).expect("cannot serialize spans")
+}
+
+/// Traces started by W3C trace context keep their original trace id,
+/// so spans are grouped together with spans of other services.
+fn trace_id_to_hex(trace_id: TraceId, baggage: &Baggage) -> String {
+    format!("{:032x}", W3cTraceContext::trace_id_128(trace_id, baggage))
+}
+
+fn span_id_to_hex(span_id: TraceId) -> String {
+    format!("{:016x}", u64::from(span_id))
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportRequest<'a> {
+    resource_spans: [ResourceSpans<'a>; 1],
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ResourceSpans<'a> {
+    resource: Resource<'a>,
+    scope_spans: [ScopeSpans<'a>; 1],
+}
+
+#[derive(Serialize)]
+struct Resource<'a> {
+    attributes: Vec<KeyValue<'a>>,
+}
+
+#[derive(Serialize)]
+struct ScopeSpans<'a> {
+    scope: InstrumentationScope,
+    spans: Vec<OtlpSpan<'a>>,
+}
+
+#[derive(Serialize)]
+struct InstrumentationScope {
+    name: &'static str,
+    version: &'static str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OtlpSpan<'a> {
+    trace_id: String,
+    span_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parent_span_id: Option<String>,
+    name: String,
+    kind: u8,
+    // 64-bit integers are encoded as strings in OTLP/JSON.
+    start_time_unix_nano: String,
+    end_time_unix_nano: String,
+    attributes: Vec<KeyValue<'a>>,
+    events: Vec<Event<'a>>,
+}
+
+// See `SpanKind` in the OTLP protocol.
+const SPAN_KIND_SERVER: u8 = 2;
+const SPAN_KIND_CONSUMER: u8 = 5;
+
+impl<'a> From<&'a Span> for OtlpSpan<'a> {
+    fn from(span: &'a Span) -> Self {
+        let start_time = span.start_time.to_unix_time_nanos();
+        let end_time = start_time + span.duration.as_nanos() as u64;
+
+        let (kind, message_kind) = match span.message_kind {
+            MessageKind::Regular => (SPAN_KIND_CONSUMER, "Regular"),
+            MessageKind::Request(_) => (SPAN_KIND_SERVER, "Request"),
+            MessageKind::Response(_) => (SPAN_KIND_CONSUMER, "Response"),
+        };
+
+        Self {
+            trace_id: trace_id_to_hex(span.trace_id, &span.baggage),
+            span_id: span_id_to_hex(span.span_id),
+            parent_span_id: span.parent_span_id.map(span_id_to_hex),
+            name: format!("{} {}", span.meta.group, span.message_name),
+            kind,
+            start_time_unix_nano: start_time.to_string(),
+            end_time_unix_nano: end_time.to_string(),
+            attributes: vec![
+                KeyValue::string("elfo.actor_group", &span.meta.group),
+                KeyValue::string("elfo.actor_key", &span.meta.key),
+                KeyValue::string("elfo.message.name", span.message_name),
+                KeyValue::string("elfo.message.protocol", span.message_protocol),
+                KeyValue::string("elfo.message.kind", message_kind),
+                KeyValue::string("elfo.trace_id", span.trace_id.to_string()),
+            ],
+            events: span.events.iter().map(Event::from).collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Event<'a> {
+    time_unix_nano: String,
+    name: &'static str,
+    attributes: Vec<KeyValue<'a>>,
+}
+
+impl<'a> From<&'a SpanEvent> for Event<'a> {
+    fn from(event: &'a SpanEvent) -> Self {
+        Self {
+            time_unix_nano: event.timestamp.to_unix_time_nanos().to_string(),
+            name: match event.kind {
+                SpanEventKind::Sent => "send",
+                SpanEventKind::Responded => "respond",
+            },
+            attributes: vec![
+                KeyValue::string("elfo.message.name", event.message_name),
+                KeyValue::string("elfo.message.protocol", event.message_protocol),
+            ],
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct KeyValue<'a> {
+    key: &'static str,
+    value: AnyValue<'a>,
+}
+
+impl<'a> KeyValue<'a> {
+    fn string(key: &'static str, value: impl Into<Cow<'a, str>>) -> Self {
+        Self {
+            key,
+            value: AnyValue::StringValue(value.into()),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+enum AnyValue<'a> {
+    StringValue(Cow<'a, str>),
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use serde_json::{json, Value};
+
+    use elfo_core::ActorMeta;
+    use elfo_utils::time::SystemTime;
+
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let span = Span {
+            meta: Arc::new(ActorMeta {
+                group: "group".into(),
+                key: "key".into(),
+            }),
+            trace_id: TraceId::try_from(0x1234).unwrap(),
+            span_id: TraceId::try_from(0xabcd).unwrap(),
+            parent_span_id: Some(TraceId::try_from(0xef).unwrap()),
+            // Started by `W3cTraceContext::set_current()`.
+            baggage: Baggage::default().with(
+                "traceparent",
+                "00-0000000000000abc0000000000001234-00f067aa0ba902b7-01",
+            ),
+            message_name: "SomeRequest",
+            message_protocol: "proto",
+            message_kind: MessageKind::Request(1),
+            start_time: SystemTime::from_unix_time_nanos(1_000),
+            duration: Duration::from_nanos(500),
+            events: vec![SpanEvent {
+                timestamp: SystemTime::from_unix_time_nanos(1_200),
+                kind: SpanEventKind::Responded,
+                message_name: "SomeResponse",
+                message_protocol: "proto",
+            }],
+        };
+
+        let actual: Value = serde_json::from_slice(&encode("service", &[span])).unwrap();
+        let attr =
+            |key: &str, value: &str| json!({ "key": key, "value": { "stringValue": value } });
+
+        let expected = json!({
+            "resourceSpans": [{
+                "resource": { "attributes": [attr("service.name", "service")] },
+                "scopeSpans": [{
+                    "scope": { "name": "elfo", "version": env!("CARGO_PKG_VERSION") },
+                    "spans": [{
+                        "traceId": "0000000000000abc0000000000001234",
+                        "spanId": "000000000000abcd",
+                        "parentSpanId": "00000000000000ef",
+                        "name": "group SomeRequest",
+                        "kind": 2,
+                        "startTimeUnixNano": "1000",
+                        "endTimeUnixNano": "1500",
+                        "attributes": [
+                            attr("elfo.actor_group", "group"),
+                            attr("elfo.actor_key", "key"),
+                            attr("elfo.message.name", "SomeRequest"),
+                            attr("elfo.message.protocol", "proto"),
+                            attr("elfo.message.kind", "Request"),
+                            attr("elfo.trace_id", "4660"),
+                        ],
+                        "events": [{
+                            "timeUnixNano": "1200",
+                            "name": "respond",
+                            "attributes": [
+                                attr("elfo.message.name", "SomeResponse"),
+                                attr("elfo.message.protocol", "proto"),
+                            ],
+                        }],
+                    }],
+                }],
+            }],
+        });
+
+        assert_eq!(actual, expected);
+    }
+}
@@ -0,0 +1,51 @@
+//! Exports handling of messages by actors as OpenTelemetry spans.
+//! [Configuration].
+//!
+//! Every span covers handling of one incoming message: from receiving it
+//! until the actor asks for the next one. Sent messages and responses are
+//! attached to the span as events. Spans carry elfo's trace id, so they are
+//! grouped into the same traces as spans of other services if the trace id
+//! is propagated, e.g. by [`W3cTraceContext`].
+//!
+//! Spans are sent to a collector using OTLP/HTTP with JSON encoding.
+//!
+//! [Configuration]: crate::config::Config
+//! [`W3cTraceContext`]: elfo_core::tracing::W3cTraceContext
+
+use std::sync::Arc;
+
+use tracing::error;
+
+use elfo_core::{tracing::set_span_recorder, Blueprint};
+
+use self::storage::SpanStorage;
+
+mod actor;
+mod client;
+mod encoder;
+mod storage;
+
+pub mod config;
+
+/// Installs a global span recorder and returns a group to export spans.
+///
+/// # Example
+/// ```
+/// # use elfo_core as elfo;
+/// let topology = elfo::Topology::empty();
+/// let otlp = topology.local("system.otlp");
+///
+/// // Usually, it's `elfo::batteries::otlp::new()`.
+/// otlp.mount(elfo_otlp::new());
+/// ```
+pub fn new() -> Blueprint {
+    let storage = Arc::new(SpanStorage::new());
+    let blueprint = actor::new(storage.clone());
+
+    // Spans are collected only after the exporter is started and configured.
+    if !set_span_recorder(storage) {
+        error!("failed to set a span recorder");
+    }
+
+    blueprint
+}
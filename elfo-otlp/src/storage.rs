@@ -0,0 +1,71 @@
+use std::{
+    mem,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
+
+use elfo_core::tracing::{Span, SpanRecorder};
+
+/// A bounded buffer of spans waiting to be exported.
+pub(crate) struct SpanStorage {
+    // Spans aren't collected until the exporter is started.
+    enabled: AtomicBool,
+    capacity: AtomicUsize,
+    // Spans produced by the exporter itself are ignored.
+    // The group is set once, so recording locks only the buffer.
+    own_group: OnceCell<String>,
+    spans: Mutex<Vec<Span>>,
+    lost: AtomicUsize,
+}
+
+impl SpanStorage {
+    pub(crate) fn new() -> Self {
+        Self {
+            enabled: AtomicBool::new(false),
+            capacity: AtomicUsize::new(0),
+            own_group: OnceCell::new(),
+            spans: Mutex::new(Vec::new()),
+            lost: AtomicUsize::new(0),
+        }
+    }
+
+    pub(crate) fn configure(&self, own_group: &str, capacity: usize) {
+        // The exporter's group never changes, so only the first call matters.
+        self.own_group.get_or_init(|| own_group.into());
+        self.capacity.store(capacity, Ordering::Relaxed);
+        self.enabled.store(true, Ordering::Relaxed);
+    }
+
+    pub(crate) fn disable(&self) {
+        self.enabled.store(false, Ordering::Relaxed);
+    }
+
+    /// Takes all collected spans and the number of spans lost since
+    /// the previous call because of the exceeded capacity.
+    pub(crate) fn drain(&self) -> (Vec<Span>, usize) {
+        let spans = mem::take(&mut *self.spans.lock());
+        (spans, self.lost.swap(0, Ordering::Relaxed))
+    }
+}
+
+impl SpanRecorder for SpanStorage {
+    fn enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    fn record(&self, span: Span) {
+        if self.own_group.get() == Some(&span.meta.group) {
+            return;
+        }
+
+        let mut spans = self.spans.lock();
+        if spans.len() < self.capacity.load(Ordering::Relaxed) {
+            spans.push(span);
+        } else {
+            drop(spans);
+            self.lost.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
@@ -1,35 +1,64 @@
-use std::time::Duration;
+use std::{sync::Arc, time::Duration};
 
-use tokio::{select, time};
+use futures::future::{self, BoxFuture};
+use fxhash::{FxHashMap, FxHashSet};
+use tokio::{
+    select,
+    time::{self, Instant},
+};
 use tracing::{debug, info, warn};
 
 use elfo_core::{
-    message, messages::Ping, scope, time::Interval, topology::LocalActorGroup, ActorStatus, Addr,
-    Context, Topology,
+    message,
+    messages::{Ping, RestartActor},
+    scope,
+    time::Interval,
+    topology::LocalActorGroup,
+    ActorStatus, Addr, Context, Topology,
 };
 use elfo_utils::ward;
 
-use crate::config::Config;
+use crate::{
+    config::Config,
+    probe::{Probe, Probes},
+};
 
 #[message]
 struct PingTick;
 
-pub(crate) async fn exec(mut ctx: Context<Config>, topology: Topology) {
-    let interval = ctx.attach(Interval::new(PingTick));
+struct Target {
+    group: LocalActorGroup,
+    next_at: Instant,
+}
 
-    let mut groups = collect_groups(&topology, &[ctx.group()]);
-    let group_count = groups.len() as u32;
+/// An actor pinged individually, see `Config::restart_after_missed`.
+struct ActorTarget {
+    addr: Addr,
+    key: String,
+}
+
+struct Report {
+    group: LocalActorGroup,
+    responsive: bool,
+    /// Individually pinged actors and whether they have responded.
+    actors: Vec<(ActorTarget, bool)>,
+}
 
-    if groups.is_empty() {
+pub(crate) async fn exec(mut ctx: Context<Config>, topology: Topology, probes: Arc<Probes>) {
+    let interval = ctx.attach(Interval::new(PingTick));
+
+    if topology.locals().all(|group| group.addr == ctx.group()) {
         info!("no groups to ping, terminating");
         return;
     }
 
-    let mut is_alarming = false;
-    let mut timed_out = 0;
+    let mut targets = collect_targets(&topology, ctx.config(), ctx.group(), &[]);
+    let mut unresponsive = FxHashSet::default();
+    // The number of pings missed in a row by individually pinged actors.
+    let mut missed = FxHashMap::<String, FxHashMap<Addr, u32>>::default();
     let mut pinging = None;
 
-    interval.start(ctx.config().ping_interval / group_count);
+    interval.start(tick_period(ctx.config(), &targets));
 
     // Accept envelopes from the mailbox concurrently with pinging
     // in order to avoid getting stuck with the configurer.
@@ -38,63 +67,238 @@ pub(crate) async fn exec(mut ctx: Context<Config>, topology: Topology) {
         select! {
             envelope = ctx.recv() => {
                 let envelope = ward!(envelope, break);
-                interval.set_period(ctx.config().ping_interval / group_count);
+
+                // Pick up new groups and changes of the config.
+                targets = collect_targets(&topology, ctx.config(), ctx.group(), &targets);
+                interval.set_period(tick_period(ctx.config(), &targets));
+
+                let len = unresponsive.len();
+                unresponsive.retain(|name: &String| targets.iter().any(|t| t.group.name == *name));
+                if unresponsive.len() != len {
+                    update_status(&ctx, &unresponsive);
+                }
 
                 if !envelope.is::<PingTick>() || pinging.is_some() {
                     continue;
                 }
 
-                if groups.is_empty() {
-                    if timed_out == 0 && is_alarming {
-                        is_alarming = false;
-                        ctx.set_status(ActorStatus::NORMAL);
-                    }
-
-                    groups = collect_groups(&topology, &[ctx.group()]);
-                    timed_out = 0;
-                }
+                // Ping the most overdue group.
+                let now = Instant::now();
+                let target = targets
+                    .iter_mut()
+                    .filter(|t| t.next_at <= now)
+                    .min_by_key(|t| t.next_at);
+                let target = ward!(target, continue);
 
-                let group = groups.pop().unwrap();
-                let warn_threshold = ctx.config().warn_threshold;
+                let config = ctx.config();
+                let name = &target.group.name;
+                target.next_at = now + config.ping_interval(name);
+                let warn_threshold = config.warn_threshold(name);
+                let probes = probes.get(name).to_vec();
+                let actors = config
+                    .restart_after_missed(name)
+                    .map(|_| collect_actors(&topology, name));
 
                 // Expose a current scope to preserve an original trace id.
-                let fut = scope::expose().within(ping_group(ctx.pruned(), group, warn_threshold));
+                let group = target.group.clone();
+                let fut = ping_group(ctx.pruned(), group, actors, warn_threshold, probes);
+                let fut = scope::expose().within(fut);
                 pinging = Some(Box::pin(fut));
             },
-            responsive = async { pinging.as_mut().unwrap().await }, if pinging.is_some() => {
+            report = async { pinging.as_mut().unwrap().await }, if pinging.is_some() => {
                 pinging = None;
 
-                if !responsive {
-                    timed_out += 1;
+                let restart_after = ctx.config().restart_after_missed(&report.group.name);
+                let counts = missed.entry(report.group.name.clone()).or_default();
+                let prev_counts = std::mem::take(counts);
 
-                    if !is_alarming {
-                        is_alarming = true;
-                        ctx.set_status(ActorStatus::ALARMING);
+                for (actor, responded) in report.actors {
+                    if responded {
+                        continue;
+                    }
+
+                    let count = prev_counts.get(&actor.addr).copied().unwrap_or(0) + 1;
+
+                    if restart_after.is_some_and(|limit| count >= limit) {
+                        restart_actor(&ctx, &report.group, actor, count);
+                    } else {
+                        counts.insert(actor.addr, count);
                     }
                 }
+
+                let group = report.group.name;
+                let changed = if report.responsive {
+                    unresponsive.remove(&group)
+                } else {
+                    unresponsive.insert(group)
+                };
+
+                if changed {
+                    update_status(&ctx, &unresponsive);
+                }
             },
         }
     }
 }
 
-fn collect_groups(topology: &Topology, exclude: &[Addr]) -> Vec<LocalActorGroup> {
+fn collect_targets(
+    topology: &Topology,
+    config: &Config,
+    exclude: Addr,
+    prev: &[Target],
+) -> Vec<Target> {
     topology
         .locals()
-        .filter(|group| !exclude.contains(&group.addr))
+        .filter(|group| group.addr != exclude && config.is_pinged(&group.name))
+        .map(|group| Target {
+            next_at: prev
+                .iter()
+                .find(|t| t.group.addr == group.addr)
+                .map_or_else(Instant::now, |t| t.next_at),
+            group,
+        })
         .collect()
 }
 
-async fn ping_group(ctx: Context, group: LocalActorGroup, warn_threshold: Duration) -> bool {
-    debug!(group = %group.name, "checking a group");
-    let fut = ctx.request_to(group.addr, Ping::default()).all().resolve();
-    if time::timeout(warn_threshold, fut).await.is_err() {
-        warn!(
-            message = "group hasn't responded in the allowed time",
-            group = %group.name,
-            timeout = ?warn_threshold,
-        );
-        false
+fn collect_actors(topology: &Topology, group: &str) -> Vec<ActorTarget> {
+    topology
+        .inspect()
+        .into_iter()
+        .find(|g| g.name == group)
+        .map(|g| g.actors)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|actor| !actor.status.kind().is_finished())
+        .map(|actor| ActorTarget {
+            addr: actor.addr,
+            key: actor.key,
+        })
+        .collect()
+}
+
+/// Every tick pings one group, so groups are pinged uniformly in time.
+fn tick_period(config: &Config, targets: &[Target]) -> Duration {
+    let min_interval = targets
+        .iter()
+        .map(|t| config.ping_interval(&t.group.name))
+        .min()
+        .unwrap_or(config.ping_interval);
+
+    min_interval / targets.len().max(1) as u32
+}
+
+fn update_status(ctx: &Context<Config>, unresponsive: &FxHashSet<String>) {
+    if unresponsive.is_empty() {
+        ctx.set_status(ActorStatus::NORMAL);
     } else {
-        true
+        let mut groups = unresponsive.iter().map(String::as_str).collect::<Vec<_>>();
+        groups.sort_unstable();
+        let details = format!("unresponsive: {}", groups.join(", "));
+        ctx.set_status(ActorStatus::ALARMING.with_details(details));
+    }
+}
+
+fn restart_actor(ctx: &Context<Config>, group: &LocalActorGroup, actor: ActorTarget, missed: u32) {
+    warn!(
+        message = "restarting the unresponsive actor",
+        group = %group.name,
+        key = %actor.key,
+        missed = missed,
+    );
+
+    // Handled by the supervisor, so it doesn't depend on the mailbox.
+    if ctx
+        .try_send_to(group.addr, RestartActor::new(actor.addr))
+        .is_err()
+    {
+        warn!(group = %group.name, "cannot request the supervisor to restart the actor");
+    }
+}
+
+async fn ping_actor(ctx: Context, addr: Addr, timeout: Duration) -> bool {
+    let fut = ctx.request_to(addr, Ping::default()).resolve();
+    time::timeout(timeout, fut).await.is_ok()
+}
+
+/// If `actors` are provided, they're pinged individually instead of the group.
+async fn ping_group(
+    ctx: Context,
+    group: LocalActorGroup,
+    actors: Option<Vec<ActorTarget>>,
+    warn_threshold: Duration,
+    probes: Vec<Arc<Probe>>,
+) -> Report {
+    debug!(group = %group.name, "checking a group");
+
+    let ping = async {
+        if actors.is_none() {
+            ctx.request_to(group.addr, Ping::default())
+                .all()
+                .resolve()
+                .await;
+        }
+        Ok(())
+    };
+
+    let custom = probes.iter().map(|probe| {
+        let fut = probe.run(ctx.clone(), group.addr);
+        async move { fut.await.map_err(|err| (probe.name(), err)) }
+    });
+
+    let checks = future::try_join(ping, future::try_join_all(custom));
+    // Boxed, otherwise the compiler fails to prove that the exec is `Send`.
+    let checks: BoxFuture<'_, _> = Box::pin(time::timeout(warn_threshold, checks));
+
+    let pings = actors
+        .iter()
+        .flatten()
+        .map(|actor| ping_actor(ctx.clone(), actor.addr, warn_threshold))
+        .collect::<Vec<_>>();
+
+    let (checks, responded) = future::join(checks, future::join_all(pings)).await;
+
+    let mut responsive = match checks {
+        Ok(Ok(_)) => true,
+        Ok(Err((probe, error))) => {
+            warn!(
+                message = "group has failed a probe",
+                group = %group.name,
+                probe = probe,
+                error = %error,
+            );
+            false
+        }
+        Err(_) => {
+            warn!(
+                message = "group hasn't responded in the allowed time",
+                group = %group.name,
+                timeout = ?warn_threshold,
+            );
+            false
+        }
+    };
+
+    let actors = actors
+        .into_iter()
+        .flatten()
+        .zip(responded)
+        .inspect(|(actor, responded)| {
+            if !responded {
+                warn!(
+                    message = "actor hasn't responded in the allowed time",
+                    group = %group.name,
+                    key = %actor.key,
+                    timeout = ?warn_threshold,
+                );
+            }
+        })
+        .collect::<Vec<_>>();
+
+    responsive &= actors.iter().all(|(_, responded)| *responded);
+
+    Report {
+        group,
+        responsive,
+        actors,
     }
 }
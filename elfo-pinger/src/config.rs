@@ -6,6 +6,7 @@
 
 use std::time::Duration;
 
+use fxhash::FxHashMap;
 use serde::Deserialize;
 
 /// The pinger's config.
@@ -14,6 +15,8 @@ use serde::Deserialize;
 /// ```toml
 /// [system.pingers]
 /// ping_interval = "30s"
+/// exclude = ["system.loggers"]
+/// groups.orders = { ping_interval = "5s", warn_threshold = "1s", restart_after_missed = 3 }
 /// ```
 #[derive(Debug, Deserialize)]
 pub struct Config {
@@ -27,6 +30,70 @@ pub struct Config {
     /// `5s` by default.
     #[serde(with = "humantime_serde", default = "default_warn_threshold")]
     pub warn_threshold: Duration,
+    /// If specified, actors are pinged individually and an actor that has
+    /// missed the specified number of pings in a row is aborted. Whether it's
+    /// restarted then is decided by its restart policy.
+    ///
+    /// Disabled by default.
+    #[serde(default)]
+    pub restart_after_missed: Option<u32>,
+    /// Groups to ping. If not specified, all local groups are pinged.
+    #[serde(default)]
+    pub include: Option<Vec<String>>,
+    /// Groups not to ping.
+    ///
+    /// Empty by default.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Overrides for specific groups.
+    ///
+    /// Empty by default.
+    #[serde(default)]
+    pub groups: FxHashMap<String, GroupConfig>,
+}
+
+/// Overrides of [`Config`] for a specific group.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct GroupConfig {
+    /// Overrides `ping_interval` for the group.
+    #[serde(with = "humantime_serde")]
+    pub ping_interval: Option<Duration>,
+    /// Overrides `warn_threshold` for the group.
+    #[serde(with = "humantime_serde")]
+    pub warn_threshold: Option<Duration>,
+    /// Overrides `restart_after_missed` for the group.
+    pub restart_after_missed: Option<u32>,
+}
+
+impl Config {
+    pub(crate) fn is_pinged(&self, group: &str) -> bool {
+        self.include
+            .as_ref()
+            .map_or(true, |include| include.iter().any(|g| g == group))
+            && !self.exclude.iter().any(|g| g == group)
+    }
+
+    pub(crate) fn ping_interval(&self, group: &str) -> Duration {
+        self.groups
+            .get(group)
+            .and_then(|c| c.ping_interval)
+            .unwrap_or(self.ping_interval)
+    }
+
+    pub(crate) fn warn_threshold(&self, group: &str) -> Duration {
+        self.groups
+            .get(group)
+            .and_then(|c| c.warn_threshold)
+            .unwrap_or(self.warn_threshold)
+    }
+
+    pub(crate) fn restart_after_missed(&self, group: &str) -> Option<u32> {
+        self.groups
+            .get(group)
+            .and_then(|c| c.restart_after_missed)
+            .or(self.restart_after_missed)
+    }
 }
 
 fn default_ping_interval() -> Duration {
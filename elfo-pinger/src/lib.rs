@@ -1,15 +1,21 @@
 //! Periodically pings all actors in the topology to check if they are alive.
 //! [Configuration].
 //!
+//! Besides the built-in ping, which is answered by elfo itself, custom
+//! application-level probes can be registered by [`Builder::probe()`].
+//!
 //! [Configuration]: config::Config
 
 use std::time::Duration;
 
-use elfo_core::{ActorGroup, Blueprint, RestartParams, RestartPolicy, Topology};
+use elfo_core::{ActorGroup, Blueprint, Request, RestartParams, RestartPolicy, Topology};
+
+use self::probe::{Probe, Probes};
 
 pub mod config;
 
 mod actor;
+mod probe;
 
 /// Creates a blueprint.
 ///
@@ -23,13 +29,64 @@ mod actor;
 /// pingers.mount(elfo_pinger::new(&topology));
 /// ```
 pub fn new(topology: &Topology) -> Blueprint {
-    let topology = topology.clone();
-    ActorGroup::new()
-        .config::<config::Config>()
-        .restart_policy(RestartPolicy::on_failure(RestartParams::new(
-            Duration::from_secs(5),
-            Duration::from_secs(30),
-        )))
-        .stop_order(100)
-        .exec(move |ctx| actor::exec(ctx, topology.clone()))
+    builder(topology).build()
+}
+
+/// Creates a builder to register custom probes.
+///
+/// # Example
+/// ```
+/// # use elfo_core as elfo;
+/// # use elfo::message;
+/// #[message(ret = ())]
+/// struct CheckHealth;
+///
+/// let topology = elfo::Topology::empty();
+/// let pingers = topology.local("pingers");
+///
+/// pingers.mount(
+///     elfo_pinger::builder(&topology)
+///         .probe("orders", CheckHealth)
+///         .build(),
+/// );
+/// ```
+pub fn builder(topology: &Topology) -> Builder {
+    Builder {
+        topology: topology.clone(),
+        probes: Probes::default(),
+    }
+}
+
+/// A builder of the pinger, see [`builder()`].
+#[must_use]
+pub struct Builder {
+    topology: Topology,
+    probes: Probes,
+}
+
+impl Builder {
+    /// Adds a custom probe for the group. The request is sent to all actors
+    /// of the group along with the built-in ping, so actors must handle it.
+    ///
+    /// The group is considered unresponsive if any actor doesn't respond to
+    /// any probe in `warn_threshold` or ignores the request.
+    pub fn probe<R: Request>(mut self, group: impl Into<String>, request: R) -> Self {
+        self.probes.add(group.into(), Probe::new(request));
+        self
+    }
+
+    /// Creates a blueprint.
+    pub fn build(self) -> Blueprint {
+        let topology = self.topology;
+        let probes = self.probes.into_shared();
+
+        ActorGroup::new()
+            .config::<config::Config>()
+            .restart_policy(RestartPolicy::on_failure(RestartParams::new(
+                Duration::from_secs(5),
+                Duration::from_secs(30),
+            )))
+            .stop_order(100)
+            .exec(move |ctx| actor::exec(ctx, topology.clone(), probes.clone()))
+    }
 }
@@ -0,0 +1,63 @@
+use std::sync::{Arc, Mutex};
+
+use futures::future::BoxFuture;
+use fxhash::FxHashMap;
+
+use elfo_core::{Addr, Context, Request};
+
+type RunFn = dyn Fn(Context, Addr) -> BoxFuture<'static, Result<(), String>> + Send + Sync;
+
+/// A custom probe, see `Builder::probe()`.
+pub(crate) struct Probe {
+    name: &'static str,
+    run: Box<RunFn>,
+}
+
+impl Probe {
+    pub(crate) fn new<R: Request>(request: R) -> Self {
+        let name = request.name();
+        // Messages aren't required to be `Sync`.
+        let request = Mutex::new(request);
+
+        Self {
+            name,
+            run: Box::new(move |ctx, addr| {
+                let request = request.lock().unwrap().clone();
+                Box::pin(async move {
+                    let responses = ctx.request_to(addr, request).all().resolve().await;
+                    match responses.into_iter().find_map(Result::err) {
+                        Some(err) => Err(err.to_string()),
+                        None => Ok(()),
+                    }
+                })
+            }),
+        }
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Sends the request to all actors of the group.
+    pub(crate) fn run(&self, ctx: Context, group: Addr) -> BoxFuture<'static, Result<(), String>> {
+        (self.run)(ctx, group)
+    }
+}
+
+/// Custom probes by groups' names.
+#[derive(Default)]
+pub(crate) struct Probes(FxHashMap<String, Vec<Arc<Probe>>>);
+
+impl Probes {
+    pub(crate) fn add(&mut self, group: String, probe: Probe) {
+        self.0.entry(group).or_default().push(Arc::new(probe));
+    }
+
+    pub(crate) fn into_shared(self) -> Arc<Self> {
+        Arc::new(self)
+    }
+
+    pub(crate) fn get(&self, group: &str) -> &[Arc<Probe>] {
+        self.0.get(group).map_or(&[], Vec::as_slice)
+    }
+}
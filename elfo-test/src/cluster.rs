@@ -0,0 +1,247 @@
+//! A harness to test several nodes connected by `elfo-network` inside one
+//! process. Every node listens on a Unix domain socket in a temporary
+//! directory and connects to all other nodes.
+
+use std::collections::BTreeMap;
+
+use futures_intrusive::channel::shared;
+use serde::{de::Deserializer, Deserialize};
+use serde_value::Value;
+use tempfile::TempDir;
+use tokio::{sync::oneshot, task::JoinHandle};
+
+use elfo_core::{
+    _priv::do_start, addr::NodeNo, messages::Terminate, topology::Local, Context, Topology,
+};
+
+use crate::proxy::{self, Proxy};
+
+/// A cluster of nodes running in the current process.
+///
+/// Every node consists of groups mounted by the user, `system.configurers`,
+/// `system.network` and `system.testers` used by the node's [`Proxy`].
+///
+/// # Example
+/// ```ignore
+/// let mut cluster = Cluster::builder()
+///     .node(1, AnyConfig::default(), |topology, testers| {
+///         let consumers = topology.remote("consumers");
+///         testers.route_to(&consumers, |_, _| Outcome::Broadcast);
+///     })
+///     .node(2, AnyConfig::default(), |topology, testers| {
+///         let consumers = topology.local("consumers");
+///         consumers.route_to(testers, |_| true);
+///         consumers.mount(consumers::new());
+///     })
+///     .start()
+///     .await;
+///
+/// cluster.proxy(1).send(SomeMessage).await;
+/// assert_msg!(cluster.proxy(2).recv().await, SomeReply);
+///
+/// // Test failover.
+/// cluster.stop(2).await;
+/// ```
+pub struct Cluster {
+    // Removed with all sockets on drop.
+    _dir: TempDir,
+    nodes: BTreeMap<u16, Node>,
+}
+
+struct Node {
+    proxy: Proxy,
+    stop: Option<oneshot::Sender<()>>,
+    handle: JoinHandle<()>,
+}
+
+type Setup = Box<dyn FnOnce(&Topology, &Local<'_>)>;
+
+/// A builder of [`Cluster`].
+pub struct ClusterBuilder {
+    nodes: Vec<(u16, Value, Setup)>,
+}
+
+impl Cluster {
+    /// Creates a builder of the cluster.
+    pub fn builder() -> ClusterBuilder {
+        ClusterBuilder { nodes: Vec::new() }
+    }
+
+    /// Returns the proxy of the node, which is mounted as `system.testers`.
+    ///
+    /// # Panics
+    /// If there is no such node.
+    #[track_caller]
+    pub fn proxy(&mut self, node_no: u16) -> &mut Proxy {
+        &mut self.nodes.get_mut(&node_no).expect("no such node").proxy
+    }
+
+    /// Terminates all groups of the node, closing its connections.
+    /// User's groups are terminated first, then system ones.
+    ///
+    /// # Panics
+    /// If there is no such node.
+    #[track_caller]
+    pub fn stop(&mut self, node_no: u16) -> impl std::future::Future<Output = ()> + '_ {
+        let node = self.nodes.get_mut(&node_no).expect("no such node");
+        let stop = node.stop.take();
+
+        async move {
+            if let Some(stop) = stop {
+                let _ = stop.send(());
+                let _ = (&mut node.handle).await;
+            }
+        }
+    }
+}
+
+impl ClusterBuilder {
+    /// Adds a node with the config and a function to set up its topology.
+    /// The function gets the topology and the `system.testers` group to
+    /// route messages sent by the node's [`Proxy`].
+    ///
+    /// `[system.network]` sections are filled by the harness, but other
+    /// network options (e.g. `ping_interval`) can be specified.
+    ///
+    /// # Panics
+    /// If the node number is zero or the config is invalid.
+    #[track_caller]
+    pub fn node(
+        mut self,
+        node_no: u16,
+        config: impl for<'de> Deserializer<'de>,
+        setup: impl FnOnce(&Topology, &Local<'_>) + 'static,
+    ) -> Self {
+        assert_ne!(node_no, 0, "node numbers start from 1");
+        let config = Value::deserialize(config).expect("invalid config");
+        self.nodes.push((node_no, config, Box::new(setup)));
+        self
+    }
+
+    /// Starts all nodes in the order of adding.
+    ///
+    /// # Panics
+    /// If any node cannot start.
+    pub async fn start(self) -> Cluster {
+        proxy::setup_logger();
+
+        let dir = tempfile::Builder::new()
+            .prefix("elfo-cluster-")
+            .tempdir()
+            .expect("cannot create a dir for sockets");
+
+        let socket =
+            |node_no: u16| Value::String(format!("uds://{}/{node_no}.sock", dir.path().display()));
+        let all = self.nodes.iter().map(|(no, _, _)| *no).collect::<Vec<_>>();
+
+        let mut nodes = BTreeMap::new();
+
+        for (node_no, config, setup) in self.nodes {
+            let others = all.iter().filter(|no| **no != node_no).copied();
+            let network = [
+                ("listen", Value::Seq(vec![socket(node_no)])),
+                (
+                    "discovery.predefined",
+                    Value::Seq(others.map(socket).collect()),
+                ),
+                ("discovery.attempt_interval", Value::String("100ms".into())),
+            ];
+
+            let mut config = config;
+            for (path, value) in network {
+                insert(&mut config, &format!("system.network.{path}"), value);
+            }
+
+            let node = start_node(node_no, config, setup).await;
+            nodes.insert(node_no, node);
+        }
+
+        Cluster { _dir: dir, nodes }
+    }
+}
+
+async fn start_node(node_no: u16, config: Value, setup: Setup) -> Node {
+    let mut topology = Topology::empty();
+    topology.set_node_no(NodeNo::from_bits(node_no).expect("invalid node number"));
+
+    let testers = topology.local("system.testers");
+    let configurers = topology.local("system.configurers").entrypoint();
+    let network = topology.local("system.network");
+
+    setup(&topology, &testers);
+
+    let (tx, rx) = shared::oneshot_channel();
+    testers.mount(proxy::testers(tx));
+    network.mount(elfo_network::new(&topology));
+    configurers.mount(elfo_configurer::fixture(&topology, config));
+
+    let (stop_tx, stop_rx) = oneshot::channel();
+    let mut handle = tokio::spawn(async move {
+        let result = do_start(topology, false, |ctx, topology| async move {
+            let _ = stop_rx.await;
+            terminate(ctx, topology).await;
+        });
+
+        if let Err(err) = result.await {
+            panic!("cannot start the node {node_no}: {err}");
+        }
+    });
+
+    let context = tokio::select! {
+        context = rx.receive() => context.expect("testers are not started"),
+        result = &mut handle => match result {
+            Ok(()) => unreachable!("the node is stopped before started"),
+            Err(err) => std::panic::resume_unwind(err.into_panic()),
+        },
+    };
+
+    Node {
+        proxy: Proxy::new(context, elfo_core::Addr::NULL),
+        stop: Some(stop_tx),
+        handle,
+    }
+}
+
+async fn terminate(ctx: Context, topology: Topology) {
+    let (system, user): (Vec<_>, Vec<_>) = topology
+        .locals()
+        .filter(|group| group.name != "system.testers")
+        .partition(|group| group.name.starts_with("system."));
+
+    for groups in [user, system] {
+        for group in &groups {
+            let _ = ctx.send_to(group.addr, Terminate::closing()).await;
+        }
+
+        for group in &groups {
+            ctx.finished(group.addr).await;
+        }
+    }
+}
+
+/// Inserts the value by the dot-separated path, creating maps if needed.
+fn insert(config: &mut Value, path: &str, value: Value) {
+    let (head, rest) = match path.split_once('.') {
+        Some((head, rest)) => (head, Some(rest)),
+        None => (path, None),
+    };
+
+    if !matches!(config, Value::Map(_)) {
+        *config = Value::Map(BTreeMap::new());
+    }
+
+    let Value::Map(map) = config else {
+        unreachable!()
+    };
+
+    let key = Value::String(head.into());
+    match rest {
+        Some(rest) => {
+            let nested = map.entry(key).or_insert(Value::Map(BTreeMap::new()));
+            insert(nested, rest, value);
+        }
+        None => {
+            map.insert(key, value);
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use std::{
+    fmt::Write as _,
+    future::{Future, IntoFuture},
+    marker::PhantomData,
+    panic::Location,
+    pin::Pin,
+    time::{Duration, Instant},
+};
+
+use elfo_core::{dumping::extract_name_by_type, AnyMessage, Envelope, Message};
+
+use crate::proxy::Proxy;
+
+/// An expectation of a message, created by [`Proxy::expect()`].
+///
+/// Messages received before the expected one are kept in the proxy and
+/// returned by next calls of [`Proxy::recv()`] and [`Proxy::try_recv()`].
+///
+/// # Example
+/// ```ignore
+/// let order = proxy
+///     .expect::<OrderPlaced>()
+///     .matching(|order| order.qty > 0)
+///     .within(Duration::from_secs(1))
+///     .await;
+/// ```
+#[must_use = "expectations do nothing unless awaited"]
+pub struct Expect<'a, M> {
+    proxy: &'a mut Proxy,
+    predicate: Option<Predicate<'a, M>>,
+    timeout: Duration,
+    location: &'static Location<'static>,
+    _marker: PhantomData<fn() -> M>,
+}
+
+type Predicate<'a, M> = Box<dyn Fn(&M) -> bool + 'a>;
+
+impl<'a, M: Message> Expect<'a, M> {
+    pub(crate) fn new(proxy: &'a mut Proxy, location: &'static Location<'static>) -> Self {
+        Self {
+            timeout: proxy.recv_timeout(),
+            proxy,
+            predicate: None,
+            location,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Expects the message to satisfy the predicate.
+    /// Messages of the same type not satisfying it are skipped.
+    pub fn matching(mut self, predicate: impl Fn(&M) -> bool + 'a) -> Self {
+        self.predicate = Some(Box::new(predicate));
+        self
+    }
+
+    /// Sets how long to wait for the message.
+    /// By default, it's the timeout of [`Proxy::recv()`].
+    pub fn within(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    async fn run(self) -> M {
+        let deadline = Instant::now() + self.timeout;
+        let mut skipped = Vec::new();
+
+        while let Some(envelope) = self
+            .proxy
+            .recv_within(deadline.saturating_duration_since(Instant::now()))
+            .await
+        {
+            let is_matched = envelope.is::<M>()
+                && self.predicate.as_ref().map_or(true, |predicate| {
+                    match envelope.message().downcast_ref::<M>() {
+                        Some(message) => predicate(message),
+                        // Enums made by `protocol_enum!` cannot be borrowed.
+                        None => predicate(
+                            &AnyMessage::clone(&envelope.message())
+                                .downcast::<M>()
+                                .expect("invalid message"),
+                        ),
+                    }
+                });
+
+            if is_matched {
+                self.proxy.unrecv(skipped);
+                return envelope.unpack::<M>().expect("invalid message").0;
+            }
+
+            skipped.push(envelope);
+        }
+
+        let mut expected = extract_name_by_type::<M>().to_string();
+        if self.predicate.is_some() {
+            expected.push_str(" matching the predicate");
+        }
+        write!(expected, " within {:?}", self.timeout).unwrap();
+
+        // Mark messages of the expected type rejected by the predicate.
+        let report = report(&expected, &skipped, Envelope::is::<M>);
+        panic!("message expectation failed at {}\n{report}", self.location);
+    }
+}
+
+impl<'a, M: Message> IntoFuture for Expect<'a, M> {
+    type IntoFuture = Pin<Box<dyn Future<Output = M> + 'a>>;
+    type Output = M;
+
+    fn into_future(self) -> Self::IntoFuture {
+        Box::pin(self.run())
+    }
+}
+
+pub(crate) async fn expect_no_message<M: Message>(
+    proxy: &mut Proxy,
+    location: &'static Location<'static>,
+) {
+    proxy.sync().await;
+
+    let mut received = Vec::new();
+    while let Some(envelope) = proxy.try_recv().await {
+        received.push(envelope);
+    }
+
+    if !received.iter().any(Envelope::is::<M>) {
+        proxy.unrecv(received);
+        return;
+    }
+
+    let expected = format!("no {}", extract_name_by_type::<M>());
+    let report = report(&expected, &received, Envelope::is::<M>);
+    panic!("message expectation failed at {}\n{report}", location);
+}
+
+/// Renders a diff-like report, marking some received messages with `!`.
+fn report(expected: &str, received: &[Envelope], mark: impl Fn(&Envelope) -> bool) -> String {
+    let mut report = format!("- expected: {expected}\n");
+
+    if received.is_empty() {
+        report.push_str("+ received: nothing\n");
+        return report;
+    }
+
+    report.push_str("+ received:\n");
+    for envelope in received {
+        let marker = if mark(envelope) { '!' } else { ' ' };
+        writeln!(report, "+ {marker} {:?}", envelope.message()).unwrap();
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use elfo_core::{assert_msg_eq, config::AnyConfig, message, msg, ActorGroup};
+
+    use super::*;
+
+    #[message]
+    #[derive(PartialEq)]
+    struct Emit(u32);
+
+    #[message]
+    #[derive(PartialEq)]
+    struct Order(u32);
+
+    #[message]
+    #[derive(PartialEq)]
+    struct Cancel;
+
+    async fn sample() -> Proxy {
+        crate::proxy(
+            ActorGroup::new().exec(|mut ctx| async move {
+                while let Some(envelope) = ctx.recv().await {
+                    msg!(match envelope {
+                        Emit(qty) => {
+                            ctx.send(Cancel).await.unwrap();
+                            ctx.send(Order(qty)).await.unwrap();
+                        }
+                    });
+                }
+            }),
+            AnyConfig::default(),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn expect() {
+        let mut proxy = sample().await;
+        proxy.send(Emit(0)).await;
+        proxy.send(Emit(5)).await;
+
+        let order = proxy
+            .expect::<Order>()
+            .matching(|order| order.0 > 0)
+            .within(Duration::from_secs(1))
+            .await;
+        assert_eq!(order, Order(5));
+
+        // Skipped messages are kept.
+        assert_msg_eq!(proxy.recv().await, Cancel);
+        assert_msg_eq!(proxy.recv().await, Order(0));
+        assert_msg_eq!(proxy.recv().await, Cancel);
+        assert!(proxy.try_recv().await.is_none());
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "- expected: Order matching the predicate within 150ms\n\
+                               + received:\n\
+                               +   Cancel\n\
+                               + ! Order(0)\n")]
+    async fn expect_failed() {
+        let mut proxy = sample().await;
+        proxy.send(Emit(0)).await;
+        proxy.expect::<Order>().matching(|order| order.0 > 0).await;
+    }
+
+    #[tokio::test]
+    async fn expect_no_message() {
+        let mut proxy = sample().await;
+        proxy.expect_no_message::<Cancel>().await;
+
+        proxy.send(Emit(1)).await;
+        proxy.expect_no_message::<Emit>().await;
+        assert_eq!(proxy.expect::<Cancel>().await, Cancel);
+        assert_eq!(proxy.expect::<Order>().await, Order(1));
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "- expected: no Cancel\n+ received:\n+ ! Cancel\n+   Order(1)\n")]
+    async fn expect_no_message_failed() {
+        let mut proxy = sample().await;
+        proxy.send(Emit(1)).await;
+        proxy.expect_no_message::<Cancel>().await;
+    }
+}
@@ -0,0 +1,248 @@
+//! Property-based testing of groups with sequences of random messages.
+//! Failing sequences are shrunk by `proptest` to minimal ones.
+
+use std::{future::Future, pin::Pin};
+
+use proptest::{
+    arbitrary::{any, Arbitrary},
+    collection,
+    strategy::{BoxedStrategy, Strategy},
+    test_runner::{Config, TestRunner},
+};
+use serde::{de::Deserializer, Deserialize};
+use serde_value::Value;
+
+use elfo_core::{
+    messages::{ActorStatusReport, SubscribeToActorStatuses},
+    msg, AnyMessage, Blueprint, Message,
+};
+
+use crate::{logs, proxy::Proxy};
+
+/// Returns a strategy generating messages of the type.
+/// The type must implement `Arbitrary`, e.g. by
+/// `#[derive(proptest_derive::Arbitrary)]` next to `#[message]`.
+///
+/// Strategies of messages of different types are combined by
+/// `prop_oneof![]`. Other strategies are converted by
+/// `.prop_map(AnyMessage::new)`.
+pub fn message<M: Message + Arbitrary>() -> BoxedStrategy<AnyMessage> {
+    any::<M>().prop_map(AnyMessage::new).boxed()
+}
+
+type Check = Box<dyn Fn(Proxy) -> Pin<Box<dyn Future<Output = ()>>>>;
+
+/// Feeds sequences of random messages to a group, checking that its actors
+/// don't fail and, optionally, other properties.
+///
+/// Every sequence is sent to a newly started group by a [`Proxy`] in its own
+/// runtime, so it must be run outside of the tokio runtime, i.e. in `#[test]`
+/// instead of `#[tokio::test]`.
+///
+/// # Example
+/// ```ignore
+/// #[test]
+/// fn parser_never_fails() {
+///     Fuzzer::new(parser::new, AnyConfig::default())
+///         .max_len(32)
+///         .check(|mut proxy| async move {
+///             proxy.expect_no_message::<InternalError>().await;
+///         })
+///         .run(prop_oneof![message::<RawFrame>(), message::<Reset>()]);
+/// }
+/// ```
+pub struct Fuzzer {
+    blueprint: Box<dyn Fn() -> Blueprint>,
+    config: Value,
+    runner: Config,
+    max_len: usize,
+    check: Option<Check>,
+}
+
+impl Fuzzer {
+    /// Creates a fuzzer of groups created by the function with the config.
+    ///
+    /// # Panics
+    /// If the config is invalid.
+    pub fn new(
+        blueprint: impl Fn() -> Blueprint + 'static,
+        config: impl for<'de> Deserializer<'de>,
+    ) -> Self {
+        Self {
+            blueprint: Box::new(blueprint),
+            config: Value::deserialize(config).expect("invalid config"),
+            // There is no source file to persist failures near.
+            runner: Config {
+                failure_persistence: None,
+                ..Config::default()
+            },
+            max_len: 16,
+            check: None,
+        }
+    }
+
+    /// Sets the number of sequences to test.
+    /// By default, it's `PROPTEST_CASES` or 256.
+    pub fn cases(mut self, cases: u32) -> Self {
+        self.runner.cases = cases;
+        self
+    }
+
+    /// Sets the maximum length of sequences, 16 by default.
+    ///
+    /// # Panics
+    /// If the length is zero.
+    #[track_caller]
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        assert_ne!(max_len, 0, "sequences cannot be empty");
+        self.max_len = max_len;
+        self
+    }
+
+    /// Sets the function called after the whole sequence is handled.
+    /// It gets the proxy to check replies, metrics or logs of the group and
+    /// must panic if the property doesn't hold.
+    pub fn check<F>(mut self, check: impl Fn(Proxy) -> F + 'static) -> Self
+    where
+        F: Future<Output = ()> + 'static,
+    {
+        self.check = Some(Box::new(move |proxy| Box::pin(check(proxy))));
+        self
+    }
+
+    /// Runs the fuzzer.
+    ///
+    /// # Panics
+    /// If any sequence fails, with the minimal failing sequence.
+    pub fn run(self, messages: impl Strategy<Value = AnyMessage>) {
+        let sequences = collection::vec(messages, 1..=self.max_len);
+        let mut runner = TestRunner::new(self.runner.clone());
+
+        let result = runner.run(&sequences, |sequence| {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .expect("cannot build a runtime")
+                .block_on(self.feed(sequence));
+            Ok(())
+        });
+
+        if let Err(err) = result {
+            panic!("{err}");
+        }
+    }
+
+    async fn feed(&self, sequence: Vec<AnyMessage>) {
+        // Records of previous sequences have the same group address.
+        logs::clear();
+
+        let mut proxy = crate::proxy((self.blueprint)(), self.config.clone()).await;
+
+        // Statuses are reported to another proxy to not mix them with replies.
+        let mut statuses = proxy.subproxy().await;
+        statuses.send(SubscribeToActorStatuses::default()).await;
+
+        for message in sequence {
+            proxy.send(message).await;
+        }
+        proxy.sync().await;
+
+        while let Some(envelope) = statuses.try_recv().await {
+            msg!(match envelope {
+                ActorStatusReport { status, .. } => {
+                    if status.kind().is_failed() {
+                        let details = status.details().unwrap_or("no details");
+                        panic!("the actor failed: {details}");
+                    }
+                }
+                _ => {}
+            });
+        }
+
+        if let Some(check) = &self.check {
+            check(proxy).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic::{self, AssertUnwindSafe};
+
+    use proptest::{prop_oneof, strategy::Just};
+
+    use elfo_core::{config::AnyConfig, message, msg, ActorGroup};
+
+    use super::*;
+
+    #[message]
+    struct Push(u8);
+
+    #[message]
+    struct Pop;
+
+    #[message]
+    struct Len(usize);
+
+    impl Arbitrary for Push {
+        type Parameters = ();
+        type Strategy = BoxedStrategy<Self>;
+
+        fn arbitrary_with((): ()) -> Self::Strategy {
+            any::<u8>().prop_map(Push).boxed()
+        }
+    }
+
+    // Fails on popping from the empty stack or pushing more than 3 items.
+    fn stack() -> Blueprint {
+        ActorGroup::new().exec(|mut ctx| async move {
+            let mut stack = Vec::new();
+            while let Some(envelope) = ctx.recv().await {
+                msg!(match envelope {
+                    Push(item) => {
+                        assert!(stack.len() < 3, "overflow");
+                        stack.push(item);
+                    }
+                    Pop => {
+                        stack.pop().expect("underflow");
+                    }
+                });
+                ctx.send(Len(stack.len())).await.unwrap();
+            }
+        })
+    }
+
+    #[test]
+    fn passed() {
+        Fuzzer::new(stack, AnyConfig::default())
+            .cases(16)
+            .max_len(3)
+            .check(|mut proxy| async move {
+                let _ = proxy.expect::<Len>().await;
+            })
+            .run(message::<Push>());
+    }
+
+    #[test]
+    fn shrunk() {
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            Fuzzer::new(stack, AnyConfig::default())
+                .max_len(8)
+                .run(prop_oneof![
+                    message::<Push>(),
+                    Just(Pop).prop_map(AnyMessage::new)
+                ]);
+        }));
+
+        let err = result.expect_err("not failed");
+        let err = err.downcast_ref::<String>().expect("invalid panic");
+        let (_, input) = err.split_once("minimal failing input: ").expect(err);
+
+        // Either `[Pop]` or four pushes of the minimal value.
+        let push = "    Push(\n        0,\n    ),\n";
+        assert!(
+            input == "[\n    Pop,\n]" || input == format!("[\n{}]", push.repeat(4)),
+            "{err}"
+        );
+    }
+}
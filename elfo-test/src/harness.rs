@@ -0,0 +1,260 @@
+//! A harness to test pipelines of several groups. Unlike [`proxy()`], it
+//! allows to create many named proxies and to put proxies between groups
+//! in order to observe, modify or hold messages flowing between them.
+//!
+//! [`proxy()`]: crate::proxy()
+
+use std::{collections::BTreeMap, future};
+
+use futures_intrusive::channel::shared;
+use serde::{de::Deserializer, Deserialize};
+use serde_value::Value;
+
+use elfo_core::{_priv::do_start, topology::Local, Addr, Topology};
+
+use crate::proxy::{self, Proxy, ProxyContext};
+
+/// A topology with named proxies.
+///
+/// # Example
+/// ```ignore
+/// let mut harness = Harness::start(config, |topology, proxies| {
+///     let producers = topology.local("producers");
+///     let consumers = topology.local("consumers");
+///
+///     // Messages from producers go to consumers through the interceptor.
+///     let interceptor = proxies.intercept("interceptor", &consumers);
+///     producers.route_all_to(&interceptor);
+///
+///     let input = proxies.proxy("input");
+///     input.route_all_to(&producers);
+///
+///     producers.mount(producers::new());
+///     consumers.mount(consumers::new());
+/// })
+/// .await;
+///
+/// harness.proxy("input").send(Produce).await;
+///
+/// let interceptor = harness.proxy("interceptor");
+/// let envelope = interceptor.recv().await;
+/// // Check, hold or replace the message before passing it on.
+/// interceptor.forward(envelope).await;
+/// ```
+pub struct Harness {
+    proxies: BTreeMap<String, Proxy>,
+}
+
+/// Named proxies created while setting up the topology of [`Harness`].
+pub struct Proxies<'t> {
+    topology: &'t Topology,
+    pending: Vec<Pending>,
+}
+
+struct Pending {
+    name: String,
+    subject_addr: Addr,
+    rx: shared::OneshotReceiver<ProxyContext>,
+}
+
+impl<'t> Proxies<'t> {
+    /// Adds a proxy mounted as `system.proxies.<name>` and returns its group
+    /// to route messages from and to it.
+    ///
+    /// # Panics
+    /// If the name is already used.
+    #[track_caller]
+    pub fn proxy(&mut self, name: &str) -> Local<'t> {
+        self.add(name, Addr::NULL)
+    }
+
+    /// Adds a proxy intercepting messages to the target group.
+    /// Messages must be routed to the returned group instead of the target
+    /// one, then the test decides which ones to pass on by
+    /// [`Proxy::forward()`]. Messages sent by [`Proxy::send()`] of the
+    /// interceptor go to the target group by default.
+    ///
+    /// # Panics
+    /// If the name is already used.
+    #[track_caller]
+    pub fn intercept(&mut self, name: &str, target: &Local<'_>) -> Local<'t> {
+        let interceptor = self.add(name, target.addr());
+        interceptor.route_all_to(target);
+        interceptor
+    }
+
+    #[track_caller]
+    fn add(&mut self, name: &str, subject_addr: Addr) -> Local<'t> {
+        assert!(
+            self.pending.iter().all(|pending| pending.name != name),
+            "the proxy {name:?} already exists"
+        );
+
+        let group_name = format!("system.proxies.{name}");
+        let (tx, rx) = shared::oneshot_channel();
+        self.topology.local(&group_name).mount(proxy::testers(tx));
+
+        self.pending.push(Pending {
+            name: name.into(),
+            subject_addr,
+            rx,
+        });
+
+        self.topology
+            .existing_local(&group_name)
+            .expect("the group is just mounted")
+    }
+}
+
+impl Harness {
+    /// Starts the topology set up by the function, which gets the topology
+    /// and [`Proxies`] to add proxies to it. `system.configurers` is added
+    /// by the harness and provides the config to groups.
+    ///
+    /// # Panics
+    /// If the config is invalid or the topology cannot start.
+    pub async fn start(
+        config: impl for<'de> Deserializer<'de>,
+        setup: impl FnOnce(&Topology, &mut Proxies<'_>),
+    ) -> Self {
+        proxy::setup_logger();
+
+        let config = Value::deserialize(config).expect("invalid config");
+
+        let topology = Topology::empty();
+        let configurers = topology.local("system.configurers").entrypoint();
+
+        let mut proxies = Proxies {
+            topology: &topology,
+            pending: Vec::new(),
+        };
+        setup(&topology, &mut proxies);
+        let pending = proxies.pending;
+
+        configurers.mount(elfo_configurer::fixture(&topology, config));
+        do_start(topology, false, |_, _| future::ready(()))
+            .await
+            .expect("cannot start");
+
+        let mut proxies = BTreeMap::new();
+        for pending in pending {
+            let context = pending.rx.receive().await.expect("proxy is not started");
+            let proxy = Proxy::new(context, pending.subject_addr);
+            proxies.insert(pending.name, proxy);
+        }
+
+        Self { proxies }
+    }
+
+    /// Returns the proxy by its name.
+    ///
+    /// # Panics
+    /// If there is no such proxy.
+    #[track_caller]
+    pub fn proxy(&mut self, name: &str) -> &mut Proxy {
+        self.proxies.get_mut(name).expect("no such proxy")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use elfo_core::{assert_msg_eq, config::AnyConfig, message, msg, ActorGroup};
+
+    use super::*;
+
+    #[message]
+    #[derive(PartialEq)]
+    struct Produce(u32);
+
+    #[message]
+    #[derive(PartialEq)]
+    struct Item(u32);
+
+    #[message]
+    #[derive(PartialEq)]
+    struct Consumed(u32);
+
+    #[message(ret = u32)]
+    struct Count;
+
+    #[tokio::test]
+    async fn intercept() {
+        let mut harness = Harness::start(AnyConfig::default(), |topology, proxies| {
+            let producers = topology.local("producers");
+            let consumers = topology.local("consumers");
+
+            let input = proxies.proxy("input");
+            let output = proxies.proxy("output");
+            let interceptor = proxies.intercept("interceptor", &consumers);
+
+            input.route_all_to(&producers);
+            producers.route_all_to(&interceptor);
+            consumers.route_all_to(&output);
+
+            producers.mount(ActorGroup::new().exec(|mut ctx| async move {
+                while let Some(envelope) = ctx.recv().await {
+                    msg!(match envelope {
+                        Produce(no) => ctx.send(Item(no)).await.unwrap(),
+                    });
+                }
+            }));
+
+            consumers.mount(ActorGroup::new().exec(|mut ctx| async move {
+                while let Some(envelope) = ctx.recv().await {
+                    msg!(match envelope {
+                        Item(no) => ctx.send(Consumed(no)).await.unwrap(),
+                    });
+                }
+            }));
+        })
+        .await;
+
+        harness.proxy("input").send(Produce(1)).await;
+        harness.proxy("input").send(Produce(2)).await;
+
+        // Hold the first item, modify the second one.
+        let interceptor = harness.proxy("interceptor");
+        let held = interceptor.recv().await;
+        assert_eq!(held.message().downcast_ref(), Some(&Item(1)));
+        assert_msg_eq!(interceptor.recv().await, Item(2));
+        interceptor.send(Item(20)).await;
+
+        assert_msg_eq!(harness.proxy("output").recv().await, Consumed(20));
+        harness
+            .proxy("output")
+            .expect_no_message::<Consumed>()
+            .await;
+
+        harness.proxy("interceptor").forward(held).await;
+        assert_msg_eq!(harness.proxy("output").recv().await, Consumed(1));
+    }
+
+    #[tokio::test]
+    async fn forward_requests() {
+        let mut harness = Harness::start(AnyConfig::default(), |topology, proxies| {
+            let counters = topology.local("counters");
+
+            let input = proxies.proxy("input");
+            let interceptor = proxies.intercept("interceptor", &counters);
+            input.route_all_to(&interceptor);
+
+            counters.mount(ActorGroup::new().exec(|mut ctx| async move {
+                while let Some(envelope) = ctx.recv().await {
+                    msg!(match envelope {
+                        (Count, token) => ctx.respond(token, 42),
+                    });
+                }
+            }));
+        })
+        .await;
+
+        let response = tokio::spawn(harness.proxy("input").request(Count));
+
+        // The group responds directly to the requester.
+        let interceptor = harness.proxy("interceptor");
+        let envelope = interceptor.recv().await;
+        assert!(envelope.request_id().is_some());
+        interceptor.forward(envelope).await;
+        assert_eq!(response.await.unwrap(), 42);
+    }
+}
@@ -1,10 +1,33 @@
 //! Utils for unit testing actors.
 
+pub use self::metrics::Metrics;
+pub use elfo_core::_priv::Fault;
+pub use expect::Expect;
+pub use harness::{Harness, Proxies};
+pub use logs::LogRecord;
 pub use proxy::{proxy, Proxy};
-pub use utils::{extract_message, extract_request};
+pub use scheduling::{seeded_scheduling, SeededScheduling};
+pub use utils::{config_snapshot, extract_message, extract_request};
 
 #[cfg(feature = "unstable")]
 pub use proxy::proxy_with_route;
 
+#[cfg(feature = "network")]
+#[cfg_attr(docsrs, doc(cfg(feature = "network")))]
+pub use cluster::{Cluster, ClusterBuilder};
+
+#[cfg(feature = "proptest")]
+#[cfg_attr(docsrs, doc(cfg(feature = "proptest")))]
+pub use fuzz::{message, Fuzzer};
+
+#[cfg(feature = "network")]
+mod cluster;
+mod expect;
+#[cfg(feature = "proptest")]
+mod fuzz;
+mod harness;
+mod logs;
+mod metrics;
 mod proxy;
+mod scheduling;
 mod utils;
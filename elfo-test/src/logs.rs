@@ -0,0 +1,207 @@
+//! Captures log records emitted in tests, see [`Proxy::logs()`].
+//!
+//! Like metrics, records are stored per thread, which isolates tests using
+//! the current-thread runtime. Records are cleared once a new proxy is created
+//! and only last `MAX_RECORDS` records are kept.
+//!
+//! Records are captured according to `system.logging.max_level` of groups,
+//! regardless of `RUST_LOG`. Records emitted outside actors are captured up to
+//! `INFO`, as in `elfo-logger`.
+//!
+//! [`Proxy::logs()`]: crate::Proxy::logs
+
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, VecDeque},
+    fmt,
+};
+
+use tracing::{
+    field::{Field, Visit},
+    Event, Level, Metadata, Subscriber,
+};
+use tracing_subscriber::{layer::Context, Layer};
+
+use elfo_core::{scope, tracing::TraceId, Addr};
+
+const MAX_RECORDS: usize = 10_000;
+
+thread_local! {
+    static RECORDS: RefCell<VecDeque<(Addr, LogRecord)>> = const { RefCell::new(VecDeque::new()) };
+}
+
+/// A log record captured by [`Proxy::logs()`](crate::Proxy::logs).
+///
+/// # Example
+/// ```ignore
+/// let logs = proxy.logs();
+/// assert!(logs
+///     .iter()
+///     .any(|r| r.level == Level::WARN && r.field("order_id") == Some("42")));
+/// ```
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct LogRecord {
+    /// The level of the record.
+    pub level: Level,
+    /// The target of the record, usually a module path.
+    pub target: String,
+    /// The message of the record.
+    pub message: String,
+    /// Other fields of the record, formatted by `Debug` except strings.
+    pub fields: BTreeMap<String, String>,
+    /// The trace id of the scope in which the record is emitted.
+    pub trace_id: Option<TraceId>,
+}
+
+impl LogRecord {
+    /// Returns the formatted value of the field.
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.fields.get(name).map(String::as_str)
+    }
+}
+
+/// Returns records emitted by the group, or by all groups if `Addr::NULL`.
+pub(crate) fn captured(group: Addr) -> Vec<LogRecord> {
+    RECORDS.with(|records| {
+        records
+            .borrow()
+            .iter()
+            .filter(|(addr, _)| group == Addr::NULL || *addr == group)
+            .map(|(_, record)| record.clone())
+            .collect()
+    })
+}
+
+/// Removes all captured records.
+pub(crate) fn clear() {
+    RECORDS.with(|records| records.borrow_mut().clear());
+}
+
+/// Checks the configured level, used as a filter of [`CapturingLayer`].
+pub(crate) fn is_enabled(meta: &Metadata<'_>) -> bool {
+    let level = *meta.level();
+    scope::try_with(|scope| scope.permissions().is_logging_enabled(level))
+        .unwrap_or(level <= Level::INFO)
+}
+
+/// A layer storing events as [`LogRecord`]s.
+pub(crate) struct CapturingLayer;
+
+impl<S: Subscriber> Layer<S> for CapturingLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let mut visitor = Visitor::default();
+        event.record(&mut visitor);
+
+        let (group, trace_id) = scope::try_with(|scope| (scope.group(), Some(scope.trace_id())))
+            .unwrap_or((Addr::NULL, None));
+
+        let record = LogRecord {
+            level: *metadata.level(),
+            target: metadata.target().to_owned(),
+            message: visitor.message,
+            fields: visitor.fields,
+            trace_id,
+        };
+
+        // Records can be emitted while the thread is being destroyed.
+        let _ = RECORDS.try_with(|records| {
+            let mut records = records.borrow_mut();
+            if records.len() == MAX_RECORDS {
+                records.pop_front();
+            }
+            records.push_back((group, record));
+        });
+    }
+}
+
+#[derive(Default)]
+struct Visitor {
+    message: String,
+    fields: BTreeMap<String, String>,
+}
+
+impl Visit for Visitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.record(field, value.to_owned());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.record(field, format!("{value:?}"));
+    }
+}
+
+impl Visitor {
+    fn record(&mut self, field: &Field, value: String) {
+        if field.name() == "message" {
+            self.message = value;
+        } else {
+            self.fields.insert(field.name().to_owned(), value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use toml::toml;
+    use tracing::{debug, warn};
+
+    use elfo_core::{config::AnyConfig, message, messages::UpdateConfig, msg, ActorGroup};
+
+    use super::*;
+
+    #[message]
+    struct Order(u32);
+
+    #[tokio::test]
+    async fn capture() {
+        let group = ActorGroup::new().exec(|mut ctx| async move {
+            while let Some(envelope) = ctx.recv().await {
+                msg!(match envelope {
+                    Order(id) => {
+                        debug!(id, "got an order");
+                        warn!(order_id = id, reason = "no funds", "order rejected");
+                    }
+                });
+            }
+        });
+
+        let config = AnyConfig::deserialize(toml! {
+            system.logging.max_level = "Debug"
+        })
+        .unwrap();
+        let mut proxy = crate::proxy(group, config).await;
+        proxy.send(Order(42)).await;
+        proxy.sync().await;
+
+        let logs = proxy.logs();
+        let record = logs
+            .iter()
+            .find(|record| record.level == Level::WARN)
+            .expect("no warnings");
+
+        assert_eq!(record.message, "order rejected");
+        assert_eq!(record.field("order_id"), Some("42"));
+        assert_eq!(record.field("reason"), Some("no funds"));
+        assert!(record.trace_id.is_some());
+
+        // Records are captured regardless of `RUST_LOG`.
+        assert!(logs.iter().any(|record| record.level == Level::DEBUG));
+
+        // But according to the configured level.
+        let config = AnyConfig::deserialize(toml! {
+            system.logging.max_level = "Warn"
+        })
+        .unwrap();
+        proxy.send(UpdateConfig::new(config)).await;
+        proxy.sync().await;
+        let captured = proxy.logs().len();
+        proxy.send(Order(43)).await;
+        proxy.sync().await;
+        let logs = proxy.logs();
+        assert_eq!(logs.len(), captured + 1);
+        assert_eq!(logs.last().unwrap().level, Level::WARN);
+    }
+}
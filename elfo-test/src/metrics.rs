@@ -0,0 +1,269 @@
+//! Captures metrics emitted in tests, see [`Proxy::capture_metrics()`].
+//!
+//! `metrics` allows only a global recorder, so the captured values are stored
+//! per thread, and only threads that opted in are captured. It isolates tests
+//! using the current-thread runtime, which is the default one for
+//! `#[tokio::test]`.
+//!
+//! [`Proxy::capture_metrics()`]: crate::Proxy::capture_metrics
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::BTreeMap,
+    fmt,
+};
+
+use metrics::{GaugeValue, Key, Recorder, Unit};
+use once_cell::sync::Lazy;
+
+use elfo_core::{scope, Addr};
+
+static INSTALLED: Lazy<bool> =
+    Lazy::new(|| metrics::set_boxed_recorder(Box::new(CapturingRecorder)).is_ok());
+
+thread_local! {
+    static CAPTURING: Cell<bool> = const { Cell::new(false) };
+    static SERIES: RefCell<BTreeMap<SeriesKey, Value>> = const { RefCell::new(BTreeMap::new()) };
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct SeriesKey {
+    group: Addr,
+    name: String,
+    labels: Vec<(String, String)>,
+}
+
+#[derive(Clone, Debug)]
+enum Value {
+    Counter(u64),
+    Gauge(f64),
+    Histogram(Vec<f64>),
+}
+
+/// Installs the capturing recorder if needed and starts capturing metrics
+/// emitted on the current thread.
+pub(crate) fn capture() {
+    assert!(
+        *INSTALLED,
+        "cannot capture metrics, another recorder is installed"
+    );
+    CAPTURING.with(|capturing| capturing.set(true));
+}
+
+/// Returns metrics emitted by the group, or by all groups if `Addr::NULL`.
+pub(crate) fn captured(group: Addr) -> Metrics {
+    let series = SERIES.with(|series| {
+        series
+            .borrow()
+            .iter()
+            .filter(|(key, _)| group == Addr::NULL || key.group == group)
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect()
+    });
+
+    Metrics { series }
+}
+
+struct CapturingRecorder;
+
+impl CapturingRecorder {
+    fn record(&self, key: &Key, f: impl FnOnce(Option<&mut Value>) -> Option<Value>) {
+        // Metrics can be emitted while the thread is being destroyed.
+        if !CAPTURING.try_with(Cell::get).unwrap_or(false) {
+            return;
+        }
+
+        let mut labels = key
+            .labels()
+            .map(|label| (label.key().to_owned(), label.value().to_owned()))
+            .collect::<Vec<_>>();
+        labels.sort();
+
+        let key = SeriesKey {
+            group: scope::try_with(|scope| scope.group()).unwrap_or(Addr::NULL),
+            name: key.name().to_owned(),
+            labels,
+        };
+
+        let _ = SERIES.try_with(|series| {
+            let mut series = series.borrow_mut();
+            let value = series.get_mut(&key);
+            if let Some(value) = f(value) {
+                series.insert(key, value);
+            }
+        });
+    }
+}
+
+impl Recorder for CapturingRecorder {
+    fn register_counter(&self, _key: &Key, _unit: Option<Unit>, _desc: Option<&'static str>) {}
+
+    fn register_gauge(&self, _key: &Key, _unit: Option<Unit>, _desc: Option<&'static str>) {}
+
+    fn register_histogram(&self, _key: &Key, _unit: Option<Unit>, _desc: Option<&'static str>) {}
+
+    fn increment_counter(&self, key: &Key, value: u64) {
+        self.record(key, |prev| match prev {
+            Some(Value::Counter(prev)) => {
+                *prev += value;
+                None
+            }
+            _ => Some(Value::Counter(value)),
+        });
+    }
+
+    fn update_gauge(&self, key: &Key, value: GaugeValue) {
+        self.record(key, |prev| match prev {
+            Some(Value::Gauge(prev)) => {
+                *prev = value.update_value(*prev);
+                None
+            }
+            _ => Some(Value::Gauge(value.update_value(0.))),
+        });
+    }
+
+    fn record_histogram(&self, key: &Key, value: f64) {
+        self.record(key, |prev| match prev {
+            Some(Value::Histogram(prev)) => {
+                prev.push(value);
+                None
+            }
+            _ => Some(Value::Histogram(vec![value])),
+        });
+    }
+}
+
+/// Metrics captured by [`Proxy::metrics()`](crate::Proxy::metrics).
+///
+/// Methods look for series with the name and containing all provided labels,
+/// so `&[]` matches all series of the metric. Values of matched series are
+/// combined: counters and gauges are summed up, histograms are concatenated.
+/// `None` is returned if there are no matched series.
+///
+/// # Example
+/// ```ignore
+/// proxy.capture_metrics();
+/// // ...
+/// let metrics = proxy.metrics();
+/// assert_eq!(metrics.counter("orders_total", &[("status", "rejected")]), Some(1));
+/// assert_eq!(metrics.gauge("pending_orders", &[]), Some(0.));
+/// assert_eq!(metrics.histogram("order_size", &[]).unwrap().len(), 5);
+/// ```
+pub struct Metrics {
+    series: Vec<(SeriesKey, Value)>,
+}
+
+impl Metrics {
+    /// Returns the value of the counter.
+    pub fn counter(&self, name: &str, labels: &[(&str, &str)]) -> Option<u64> {
+        self.find(name, labels, |value| match value {
+            Value::Counter(value) => Some(*value),
+            _ => None,
+        })
+        .reduce(|a, b| a + b)
+    }
+
+    /// Returns the value of the gauge.
+    pub fn gauge(&self, name: &str, labels: &[(&str, &str)]) -> Option<f64> {
+        self.find(name, labels, |value| match value {
+            Value::Gauge(value) => Some(*value),
+            _ => None,
+        })
+        .reduce(|a, b| a + b)
+    }
+
+    /// Returns values recorded into the histogram in the order of recording.
+    /// Values of different series are ordered by labels.
+    pub fn histogram(&self, name: &str, labels: &[(&str, &str)]) -> Option<Vec<f64>> {
+        self.find(name, labels, |value| match value {
+            Value::Histogram(values) => Some(values.clone()),
+            _ => None,
+        })
+        .reduce(|mut a, b| {
+            a.extend(b);
+            a
+        })
+    }
+
+    fn find<'a, T>(
+        &'a self,
+        name: &'a str,
+        labels: &'a [(&str, &str)],
+        f: impl Fn(&Value) -> Option<T> + 'a,
+    ) -> impl Iterator<Item = T> + 'a {
+        self.series
+            .iter()
+            .filter(move |(key, _)| {
+                key.name == name
+                    && labels
+                        .iter()
+                        .all(|(k, v)| key.labels.iter().any(|(lk, lv)| lk == k && lv == v))
+            })
+            .filter_map(move |(_, value)| f(value))
+    }
+}
+
+impl fmt::Debug for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut list = f.debug_list();
+
+        for (key, value) in &self.series {
+            let labels = key
+                .labels
+                .iter()
+                .map(|(k, v)| format!("{k}={v:?}"))
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            list.entry(&format_args!("{}{{{labels}}}: {value:?}", key.name));
+        }
+
+        list.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use elfo_core::{config::AnyConfig, message, msg, ActorGroup};
+
+    #[message]
+    struct Order(u32);
+
+    #[tokio::test]
+    async fn capture() {
+        let group = ActorGroup::new().exec(|mut ctx| async move {
+            while let Some(envelope) = ctx.recv().await {
+                msg!(match envelope {
+                    Order(qty) => {
+                        let status = if qty > 0 { "accepted" } else { "rejected" };
+                        metrics::increment_counter!("orders_total", "status" => status);
+                        metrics::gauge!("last_qty", f64::from(qty));
+                        metrics::histogram!("qty", f64::from(qty));
+                    }
+                });
+            }
+        });
+
+        let mut proxy = crate::proxy(group, AnyConfig::default()).await;
+        proxy.capture_metrics();
+        let metrics = proxy.metrics();
+        assert_eq!(metrics.counter("orders_total", &[]), None);
+
+        for qty in [5, 0, 3] {
+            proxy.send(Order(qty)).await;
+        }
+        proxy.sync().await;
+
+        let metrics = proxy.metrics();
+        assert_eq!(metrics.counter("orders_total", &[]), Some(3));
+        let accepted = [("status", "accepted")];
+        assert_eq!(metrics.counter("orders_total", &accepted), Some(2));
+        assert_eq!(metrics.counter("orders_total", &[("status", "a")]), None);
+        assert_eq!(metrics.gauge("last_qty", &[]), Some(3.));
+        assert_eq!(metrics.histogram("qty", &[]), Some(vec![5., 0., 3.]));
+
+        // Captured series are printed on failed assertions.
+        let debug = format!("{metrics:?}");
+        assert!(debug.contains(r#"orders_total{status="rejected"}: Counter(1)"#));
+    }
+}
@@ -1,5 +1,7 @@
 use std::{
-    collections::BTreeMap,
+    any::TypeId,
+    collections::{BTreeMap, VecDeque},
+    fmt::Display,
     future::{self, Future},
     panic::Location,
     sync::{
@@ -20,9 +22,10 @@ use serde_value::Value;
 use tokio::task;
 
 use elfo_core::{
-    ActorGroup, ActorMeta, Addr, Blueprint, Context, Envelope, Local, Message, Request,
-    ResponseToken,
-    _priv::do_start,
+    ActorGroup, ActorMeta, Addr, AnyMessage, Blueprint, Context, Envelope, Local, Message,
+    Request, ResponseToken,
+    _priv::{self, do_start, Fault},
+    dumping::extract_name_by_type,
     errors::TrySendError,
     message, msg,
     routers::{MapRouter, Outcome},
@@ -30,6 +33,12 @@ use elfo_core::{
     topology::Topology,
 };
 
+use crate::{
+    expect::{self, Expect},
+    logs::{self, LogRecord},
+    metrics::{self, Metrics},
+};
+
 const SYNC_YIELD_COUNT: usize = 32;
 
 /// A proxy for testing actors.
@@ -38,11 +47,28 @@ pub struct Proxy {
     scope: Scope,
     subject_addr: Addr,
     recv_timeout: Duration,
+    // Messages skipped by expectations, see `expect.rs`.
+    stash: VecDeque<Envelope>,
 }
 
-type ProxyContext = Context<(), usize>;
+pub(crate) type ProxyContext = Context<(), usize>;
 
 impl Proxy {
+    pub(crate) fn new(context: ProxyContext, subject_addr: Addr) -> Self {
+        let meta = Arc::new(ActorMeta {
+            group: "proxy".into(), // TODO: use a normal group here.
+            key: String::new(),
+        });
+
+        Self {
+            scope: Scope::test(context.addr(), meta),
+            context,
+            subject_addr,
+            recv_timeout: Duration::from_millis(150),
+            stash: VecDeque::new(),
+        }
+    }
+
     /// Returns an address of the proxy.
     pub fn addr(&self) -> Addr {
         self.context.addr()
@@ -128,6 +154,42 @@ impl Proxy {
         })
     }
 
+    /// Forwards the received message to the group intercepted by the proxy,
+    /// see [`Proxies::intercept()`]. Regular messages are sent on behalf of
+    /// the proxy, so replies of the group come back to the proxy. Requests
+    /// are forwarded with their tokens, so the group responds directly to
+    /// the requester.
+    ///
+    /// # Panics
+    /// If the proxy doesn't intercept any group or the group is closed.
+    ///
+    /// [`Proxies::intercept()`]: crate::Proxies::intercept
+    #[track_caller]
+    pub fn forward(&self, envelope: Envelope) -> impl Future<Output = ()> + '_ {
+        assert_ne!(
+            self.subject_addr,
+            Addr::NULL,
+            "the proxy intercepts nothing"
+        );
+
+        let location = Location::caller();
+        self.scope.clone().within(async move {
+            let name = envelope.message().name();
+            let result = if envelope.request_id().is_some() {
+                let result = self.context.forward_envelope_to(self.subject_addr, envelope);
+                result.await.map_err(|err| err.to_string())
+            } else {
+                let message = (*envelope.message()).clone();
+                let result = self.context.send_to(self.subject_addr, message);
+                result.await.map_err(|err| err.to_string())
+            };
+
+            if let Err(err) = result {
+                panic!("cannot forward {} ({}) at {}", name, err, location);
+            }
+        })
+    }
+
     /// See [`Context::respond()`] for details.
     pub fn respond<R: Request>(&self, token: ResponseToken<R>, response: R::Response) {
         self.scope
@@ -138,6 +200,152 @@ impl Proxy {
     /// See [`Context::recv()`] for details.
     #[track_caller]
     pub fn recv(&mut self) -> impl Future<Output = Envelope> + '_ {
+        let location = Location::caller();
+        async move {
+            let recv_timeout = self.recv_timeout;
+            match self.recv_within(recv_timeout).await {
+                Some(envelope) => envelope,
+                None => panic!(
+                    "timeout ({:?}) while receiving a message at {}",
+                    recv_timeout, location,
+                ),
+            }
+        }
+    }
+
+    /// See [`Context::try_recv()`] for details.
+    pub async fn try_recv(&mut self) -> Option<Envelope> {
+        if let Some(envelope) = self.stash.pop_front() {
+            return Some(envelope);
+        }
+
+        self.scope
+            .clone()
+            .within(async move { self.context.try_recv().await.ok() })
+            .await
+    }
+
+    /// Waits for a message of the type, skipping other ones. Skipped messages
+    /// are returned by next calls of [`Proxy::recv()`], so it can replace
+    /// hand-written loops of `recv()` and `assert_msg!`.
+    ///
+    /// See [`Expect`] for options. Awaiting panics with a report of received
+    /// messages if there is no expected message within the timeout.
+    #[track_caller]
+    pub fn expect<M: Message>(&mut self) -> Expect<'_, M> {
+        Expect::new(self, Location::caller())
+    }
+
+    /// Waits until the testable actor handles all previously sent messages
+    /// (see [`Proxy::sync()`]) and checks that the proxy hasn't received
+    /// messages of the type. Other messages are kept in the proxy.
+    ///
+    /// # Panics
+    /// If there is a message of the type, with a report of received messages.
+    #[track_caller]
+    pub fn expect_no_message<M: Message>(&mut self) -> impl Future<Output = ()> + '_ {
+        expect::expect_no_message::<M>(self, Location::caller())
+    }
+
+    /// Starts capturing metrics emitted on the current thread, see
+    /// [`Proxy::metrics()`]. Metrics emitted before aren't captured.
+    ///
+    /// It requires that the test uses the current-thread runtime, which is the
+    /// default one for `#[tokio::test]`.
+    ///
+    /// # Panics
+    /// If another metric recorder (e.g. `elfo-telemeter`) is installed.
+    pub fn capture_metrics(&self) {
+        metrics::capture();
+    }
+
+    /// Returns metrics emitted by the testable actor since
+    /// [`Proxy::capture_metrics()`] is called.
+    /// Metrics of the whole node are returned for proxies of [`Cluster`].
+    ///
+    /// [`Cluster`]: crate::Cluster
+    pub fn metrics(&self) -> Metrics {
+        metrics::captured(self.subject_addr)
+    }
+
+    /// Returns log records emitted by the testable actor so far, regardless
+    /// of `RUST_LOG`. Records of the whole node are returned for proxies of
+    /// [`Cluster`].
+    ///
+    /// Like [`Proxy::capture_metrics()`], it requires the current-thread
+    /// runtime.
+    ///
+    /// [`Cluster`]: crate::Cluster
+    pub fn logs(&self) -> Vec<LogRecord> {
+        logs::captured(self.subject_addr)
+    }
+
+    /// Injects the fault into the actor of the testable group with the key,
+    /// e.g. `Singleton` for groups without a router. Faults are applied to
+    /// messages received by the actor, see [`Fault`] for details.
+    ///
+    /// Like [`Proxy::capture_metrics()`], it requires the current-thread
+    /// runtime.
+    ///
+    /// # Example
+    /// ```ignore
+    /// // The next message kills the actor, then it's restarted.
+    /// proxy.inject_fault(Singleton, Fault::Panic);
+    /// // Two next messages are lost.
+    /// proxy.inject_fault(Singleton, Fault::Drop(2));
+    /// ```
+    pub fn inject_fault(&self, key: impl Display, fault: Fault) {
+        _priv::inject_fault(self.subject_addr, key.to_string(), fault);
+    }
+
+    /// Removes all faults injected into the actor of the testable group.
+    pub fn clear_faults(&self, key: impl Display) {
+        _priv::clear_faults(self.subject_addr, &key.to_string());
+    }
+
+    /// Fires attached sources of the actor of the testable group storing
+    /// messages of the type: [`Interval`], [`Delay`] and [`Signal`]. They
+    /// emit the message immediately without waiting for time or OS signals.
+    /// Schedules of intervals aren't changed.
+    ///
+    /// # Panics
+    /// If the actor has no such sources.
+    ///
+    /// # Example
+    /// ```ignore
+    /// proxy.fire::<FlushTick>(Singleton);
+    /// assert_msg!(proxy.recv().await, Flushed);
+    /// ```
+    ///
+    /// [`Interval`]: elfo_core::time::Interval
+    /// [`Delay`]: elfo_core::time::Delay
+    /// [`Signal`]: elfo_core::signal::Signal
+    #[track_caller]
+    pub fn fire<M: Message>(&self, key: impl Display) {
+        let key = key.to_string();
+        let fired = _priv::fire_sources(self.subject_addr, &key, TypeId::of::<M>());
+        let name = extract_name_by_type::<M>();
+        assert_ne!(fired, 0, "no attached sources of {name} on {key}");
+    }
+
+    /// Emits the message from the earliest attached [`Stream`] of the actor
+    /// of the testable group, as if the stream produced it.
+    ///
+    /// # Panics
+    /// If the actor has no attached streams.
+    ///
+    /// [`Stream`]: elfo_core::stream::Stream
+    #[track_caller]
+    pub fn emit<M: Message>(&self, key: impl Display, message: M) {
+        let key = key.to_string();
+        let message = AnyMessage::new(message);
+        if _priv::emit_from_source(self.subject_addr, &key, message).is_err() {
+            panic!("no attached streams on {key}");
+        }
+    }
+
+    /// Receives a message, returns `None` on timeout.
+    pub(crate) async fn recv_within(&mut self, timeout: Duration) -> Option<Envelope> {
         // We use a separate timer here to avoid interaction with the tokio's timer.
         static STD_CLOCK: Lazy<StdClock> = Lazy::new(StdClock::new);
         static TIMER_SERVICE: Lazy<Arc<TimerService>> = Lazy::new(|| {
@@ -152,30 +360,32 @@ impl Proxy {
             timer_service
         });
 
-        let location = Location::caller();
-        self.scope.clone().within(async move {
-            tokio::select! {
-                Some(envelope) = self.context.recv() => {
-                    envelope
-                },
-                _ = TIMER_SERVICE.delay(self.recv_timeout) => {
-                    panic!(
-                        "timeout ({:?}) while receiving a message at {}",
-                        self.recv_timeout, location,
-                    );
-                }
-            }
-        })
-    }
+        if let Some(envelope) = self.stash.pop_front() {
+            return Some(envelope);
+        }
 
-    /// See [`Context::try_recv()`] for details.
-    pub async fn try_recv(&mut self) -> Option<Envelope> {
         self.scope
             .clone()
-            .within(async move { self.context.try_recv().await.ok() })
+            .within(async move {
+                tokio::select! {
+                    Some(envelope) = self.context.recv() => Some(envelope),
+                    _ = TIMER_SERVICE.delay(timeout) => None,
+                }
+            })
             .await
     }
 
+    pub(crate) fn recv_timeout(&self) -> Duration {
+        self.recv_timeout
+    }
+
+    /// Returns messages to the proxy, they're received before new ones.
+    pub(crate) fn unrecv(&mut self, envelopes: impl IntoIterator<Item = Envelope>) {
+        let mut envelopes = envelopes.into_iter().collect::<VecDeque<_>>();
+        envelopes.append(&mut self.stash);
+        self.stash = envelopes;
+    }
+
     /// Waits until the testable actor handles all previously sent messages.
     ///
     /// Now it's implemented as multiple calls `yield_now()`,
@@ -192,6 +402,24 @@ impl Proxy {
         self.recv_timeout = recv_timeout;
     }
 
+    /// Advances the paused time by the duration and waits until actors
+    /// handle what expired timers produce. It affects everything based on
+    /// `tokio::time`: `Interval`, `Delay`, timeouts of requests and restart
+    /// backoffs. Timers fire in order, so it's equivalent to a long run of
+    /// the system, but it doesn't take real time.
+    ///
+    /// Use `#[tokio::test(start_paused = true)]` to pause the time.
+    ///
+    /// # Panics
+    /// If the time isn't paused.
+    pub async fn advance_time(&mut self, duration: Duration) {
+        // `advance()` jumps over intermediate timers, so only check the time is
+        // paused and then sleep, allowing tokio to auto-advance between timers.
+        tokio::time::advance(Duration::ZERO).await;
+        tokio::time::sleep(duration).await;
+        self.sync().await;
+    }
+
     /// Creates a subproxy with a different address.
     /// The main purpose is to test `send_to(..)` and `request_to(..)`
     /// calls. It's likely to be changed in the future.
@@ -216,6 +444,7 @@ impl Proxy {
             context,
             subject_addr: self.subject_addr,
             recv_timeout: self.recv_timeout,
+            stash: VecDeque::new(),
         }
     }
 
@@ -234,7 +463,7 @@ impl Proxy {
 #[message(ret = Local<ProxyContext>)]
 struct StealContext;
 
-fn testers(tx: shared::OneshotSender<ProxyContext>) -> Blueprint {
+pub(crate) fn testers(tx: shared::OneshotSender<ProxyContext>) -> Blueprint {
     let tx = Arc::new(tx);
     let next_tester_key = AtomicUsize::new(1);
 
@@ -278,11 +507,7 @@ pub async fn proxy_with_route<F>(
 where
     F: Fn(&Envelope) -> bool + Send + Sync + 'static,
 {
-    let _ = tracing_subscriber::fmt()
-        .with_target(false)
-        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
-        .with_test_writer()
-        .try_init();
+    setup_logger();
 
     let config = Value::deserialize(config).expect("invalid config");
     let mut map = BTreeMap::new();
@@ -299,8 +524,6 @@ where
     testers.route_all_to(&subject);
     subject.route_to(&testers, route_filter);
 
-    // TODO: capture log messages.
-    // TODO: capture metrics.
     configurers.mount(elfo_configurer::fixture(&topology, config));
     subject.mount(blueprint);
 
@@ -311,17 +534,26 @@ where
         .expect("cannot start");
 
     let context = rx.receive().await.unwrap();
-    let meta = Arc::new(ActorMeta {
-        group: "proxy".into(), // TODO: use a normal group here.
-        key: String::new(),
-    });
+    Proxy::new(context, subject_addr)
+}
 
-    Proxy {
-        scope: Scope::test(context.addr(), meta),
-        context,
-        subject_addr,
-        recv_timeout: Duration::from_millis(150),
-    }
+pub(crate) fn setup_logger() {
+    use tracing_subscriber::{filter, fmt, prelude::*, EnvFilter};
+
+    // `RUST_LOG` affects only printed records, see `logs.rs` for captured ones.
+    let printer = fmt::layer()
+        .with_target(false)
+        .with_test_writer()
+        .with_filter(EnvFilter::from_default_env());
+
+    let capturer = logs::CapturingLayer.with_filter(filter::filter_fn(logs::is_enabled));
+
+    let _ = tracing_subscriber::registry()
+        .with(printer)
+        .with(capturer)
+        .try_init();
+
+    logs::clear();
 }
 
 /// Creates a proxy for testing actors.
@@ -0,0 +1,148 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    thread,
+};
+
+use elfo_core::_priv::set_scheduling_seed;
+
+const SEED_ENV: &str = "ELFO_TEST_SEED";
+
+/// Enables the seeded scheduling mode for the current thread until the
+/// returned guard is dropped.
+///
+/// In this mode, actors yield to the executor a pseudo-random number of times
+/// before receiving messages, which shuffles interleavings of messages. The
+/// same seed reproduces the same interleaving, if the test uses the
+/// current-thread runtime (the default one for `#[tokio::test]`) and doesn't
+/// depend on other sources of randomness.
+///
+/// The seed is taken from the `ELFO_TEST_SEED` environment variable or
+/// generated randomly. If the test panics, the seed is printed to reproduce
+/// the failed run.
+///
+/// # Example
+/// ```ignore
+/// #[tokio::test]
+/// async fn concurrent_orders() {
+///     let _scheduling = elfo::test::seeded_scheduling();
+///     let mut proxy = elfo::test::proxy(blueprint, config).await;
+///     // ...
+/// }
+/// ```
+///
+/// # Panics
+/// If `ELFO_TEST_SEED` isn't a number.
+pub fn seeded_scheduling() -> SeededScheduling {
+    let seed = match std::env::var(SEED_ENV) {
+        Ok(seed) => seed.parse().expect("invalid ELFO_TEST_SEED"),
+        Err(_) => RandomState::new().build_hasher().finish(),
+    };
+
+    SeededScheduling::new(seed)
+}
+
+/// A guard of the seeded scheduling mode, see [`seeded_scheduling()`].
+#[must_use = "the mode is disabled on drop"]
+pub struct SeededScheduling {
+    seed: u64,
+}
+
+impl SeededScheduling {
+    /// Enables the mode with the provided seed, ignoring `ELFO_TEST_SEED`.
+    pub fn new(seed: u64) -> Self {
+        set_scheduling_seed(Some(seed));
+        Self { seed }
+    }
+
+    /// Returns the used seed.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl Drop for SeededScheduling {
+    fn drop(&mut self) {
+        set_scheduling_seed(None);
+
+        if thread::panicking() {
+            eprintln!(
+                "the test failed in the seeded scheduling mode, rerun it with {SEED_ENV}={}",
+                self.seed
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use elfo_core::{
+        config::AnyConfig,
+        message, msg,
+        routers::{MapRouter, Outcome},
+        ActorGroup,
+    };
+
+    use super::*;
+
+    #[message]
+    struct Start;
+
+    #[message]
+    struct Step(u32);
+
+    #[message]
+    struct Done(u32, u32);
+
+    async fn interleaving(seed: u64) -> Vec<(u32, u32)> {
+        let _scheduling = SeededScheduling::new(seed);
+
+        let group = ActorGroup::new()
+            .router(MapRouter::new(|envelope| {
+                msg!(match envelope {
+                    Start => Outcome::Multicast(vec![0, 1, 2]),
+                    _ => Outcome::Default,
+                })
+            }))
+            .exec(|mut ctx| async move {
+                let key = *ctx.key();
+
+                while let Some(envelope) = ctx.recv().await {
+                    msg!(match envelope {
+                        Start => ctx.send_to(ctx.addr(), Step(0)).await.unwrap(),
+                        Step(step) => {
+                            ctx.send(Done(key, step)).await.unwrap();
+                            if step < 5 {
+                                ctx.send_to(ctx.addr(), Step(step + 1)).await.unwrap();
+                            }
+                        }
+                    });
+                }
+            });
+
+        let mut proxy = crate::proxy(group, AnyConfig::default()).await;
+        proxy.send(Start).await;
+
+        let mut order = Vec::new();
+        for _ in 0..18 {
+            msg!(match proxy.recv().await {
+                Done(key, step) => order.push((key, step)),
+            });
+        }
+        order
+    }
+
+    #[tokio::test]
+    async fn reproducible() {
+        let reference = interleaving(42).await;
+        for _ in 0..5 {
+            assert_eq!(interleaving(42).await, reference);
+        }
+
+        let mut is_shuffled = false;
+        for seed in 0..10 {
+            is_shuffled |= interleaving(seed).await != reference;
+        }
+        assert!(is_shuffled);
+    }
+}
@@ -1,4 +1,6 @@
-use elfo_core::{msg, Envelope, Message, Request, ResponseToken};
+use std::path::Path;
+
+use elfo_core::{msg, Envelope, Message, Request, ResponseToken, Topology};
 
 /// Extracts message with the provided type from [`Envelope`], panics otherwise.
 #[track_caller]
@@ -27,6 +29,20 @@ pub fn extract_request<R: Request>(envelope: Envelope) -> (R, ResponseToken<R>)
     })
 }
 
+/// Renders sections of the config file for all mounted groups with default
+/// values filled in, e.g. to compare with a stored snapshot.
+/// See [`elfo_configurer::snapshot_config()`] for details.
+///
+/// # Panics
+/// If the config cannot be loaded or any section is invalid.
+pub async fn config_snapshot(topology: &Topology, path_to_config: impl AsRef<Path>) -> String {
+    let path = path_to_config.as_ref();
+    match elfo_configurer::snapshot_config(topology, path).await {
+        Ok(snapshot) => snapshot,
+        Err(err) => panic!("invalid config {}: {err}", path.display()),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use elfo_core::{_priv::MessageKind, message, scope::Scope, ActorMeta, Addr};
@@ -2,7 +2,7 @@
 
 pub use self::{
     likely::*,
-    rate_limiter::{RateLimit, RateLimiter},
+    rate_limiter::{KeyedRateLimiter, RateLimit, RateLimiter},
 };
 
 mod likely;
@@ -1,4 +1,7 @@
 use std::{
+    borrow::Borrow,
+    collections::HashMap,
+    hash::Hash,
     sync::atomic::{AtomicU64, Ordering::Relaxed},
     time::Duration,
 };
@@ -6,6 +9,26 @@ use std::{
 use crate::time;
 
 /// A rate limiter implementing [GCRA](https://en.wikipedia.org/wiki/Generic_cell_rate_algorithm).
+///
+/// It's equivalent to a token bucket, which capacity is the limit per period
+/// and which is refilled uniformly. The limiter is lock-free and can be shared
+/// between threads.
+///
+/// Time is measured by [`time::Instant`], so it can be mocked in tests.
+/// Use [`RateLimiter::acquire_at()`] to measure time by another clock.
+///
+/// # Example
+/// ```
+/// use elfo_utils::{RateLimit, RateLimiter};
+///
+/// let limiter = RateLimiter::new(RateLimit::Rps(100));
+///
+/// for i in 0..1000 {
+///     if limiter.acquire() {
+///         println!("processing #{i}");
+///     }
+/// }
+/// ```
 pub struct RateLimiter {
     step: AtomicU64,
     period: AtomicU64,
@@ -20,7 +43,7 @@ impl Default for RateLimiter {
 }
 
 /// A rate limit configuration.
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RateLimit {
     /// Unlimited rate.
     Unlimited,
@@ -75,6 +98,21 @@ impl RateLimiter {
     /// Returns `true` if an operation is allowed.
     #[inline]
     pub fn acquire(&self) -> bool {
+        self.do_acquire(time::nanos_since_unknown_epoch)
+    }
+
+    /// Acquires one permit at the provided time, which is the time elapsed
+    /// since any fixed moment, e.g. to use the same clock as timers.
+    /// Returns `true` if an operation is allowed.
+    ///
+    /// The same clock must be used for all calls on the limiter.
+    #[inline]
+    pub fn acquire_at(&self, now: Duration) -> bool {
+        self.do_acquire(|| now.as_nanos() as u64)
+    }
+
+    #[inline]
+    fn do_acquire(&self, now: impl FnOnce() -> u64) -> bool {
         let step = self.step.load(Relaxed);
 
         // Handle special cases.
@@ -86,7 +124,7 @@ impl RateLimiter {
         }
 
         let period = self.period.load(Relaxed);
-        let now = time::nanos_since_unknown_epoch();
+        let now = now();
         let deadline = now + period;
 
         // GCRA logic.
@@ -101,6 +139,125 @@ impl RateLimiter {
             })
             .is_ok()
     }
+
+    /// Returns how long to wait until a permit can be acquired,
+    /// [`Duration::ZERO`] if it can be acquired right now.
+    /// Returns `None` if the limit forbids everything.
+    ///
+    /// Note that the permit isn't reserved, so it can be taken by someone else.
+    pub fn next_permit_in(&self) -> Option<Duration> {
+        self.do_next_permit_in(time::nanos_since_unknown_epoch)
+    }
+
+    /// Like [`RateLimiter::next_permit_in()`], but at the provided time,
+    /// see [`RateLimiter::acquire_at()`].
+    pub fn next_permit_at(&self, now: Duration) -> Option<Duration> {
+        self.do_next_permit_in(|| now.as_nanos() as u64)
+    }
+
+    fn do_next_permit_in(&self, now: impl FnOnce() -> u64) -> Option<Duration> {
+        let step = self.step.load(Relaxed);
+
+        if step == UNLIMITED {
+            return Some(Duration::ZERO);
+        }
+        if step == DISABLED {
+            return None;
+        }
+
+        let period = self.period.load(Relaxed);
+        let now = now();
+        let vtime = self.vtime.load(Relaxed);
+
+        // See `acquire()`: a permit is available if `vtime < now + period`.
+        Some(Duration::from_nanos(
+            (vtime + 1).saturating_sub(now + period),
+        ))
+    }
+
+    /// Returns `true` if the limiter is indistinguishable from a new one.
+    fn is_restored(&self, now: u64) -> bool {
+        self.vtime.load(Relaxed) <= now
+    }
+}
+
+/// A set of rate limiters with the same limit, one per key,
+/// e.g. to throttle work per client.
+///
+/// Limiters are created on demand, so call [`KeyedRateLimiter::purge()`]
+/// periodically to remove ones for inactive keys.
+///
+/// # Example
+/// ```
+/// use elfo_utils::{KeyedRateLimiter, RateLimit};
+///
+/// let mut limiter = KeyedRateLimiter::<String>::new(RateLimit::Rps(10));
+///
+/// for client in ["alice", "bob", "alice"] {
+///     if limiter.acquire(client) {
+///         println!("handling a request from {client}");
+///     }
+/// }
+///
+/// // Sometimes, e.g. by a timer.
+/// limiter.purge();
+/// ```
+pub struct KeyedRateLimiter<K> {
+    limit: RateLimit,
+    limiters: HashMap<K, RateLimiter>,
+}
+
+impl<K: Hash + Eq> KeyedRateLimiter<K> {
+    /// Creates a new limiter.
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            limiters: HashMap::new(),
+        }
+    }
+
+    /// Reconfigures limiters for all keys.
+    pub fn configure(&mut self, limit: RateLimit) {
+        self.limit = limit;
+
+        for limiter in self.limiters.values() {
+            limiter.configure(limit);
+        }
+    }
+
+    /// Acquires one permit for the provided key.
+    /// Returns `true` if an operation is allowed.
+    pub fn acquire<Q>(&mut self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = K> + ?Sized,
+    {
+        if let Some(limiter) = self.limiters.get(key) {
+            return limiter.acquire();
+        }
+
+        let limiter = RateLimiter::new(self.limit);
+        let allowed = limiter.acquire();
+        self.limiters.insert(key.to_owned(), limiter);
+        allowed
+    }
+
+    /// Removes limiters that have been fully restored since the last use,
+    /// they are indistinguishable from new ones.
+    pub fn purge(&mut self) {
+        let now = time::nanos_since_unknown_epoch();
+        self.limiters.retain(|_, limiter| !limiter.is_restored(now));
+    }
+
+    /// Returns the number of tracked keys.
+    pub fn len(&self) -> usize {
+        self.limiters.len()
+    }
+
+    /// Returns `true` if there are no tracked keys.
+    pub fn is_empty(&self) -> bool {
+        self.limiters.is_empty()
+    }
 }
 
 fn calculate_step(max_rate: u64, period: u64) -> u64 {
@@ -207,6 +364,72 @@ mod tests {
         }
     }
 
+    #[test]
+    fn next_permit_in() {
+        time::with_instant_mock(|mock| {
+            assert_eq!(
+                RateLimiter::new(RateLimit::Unlimited).next_permit_in(),
+                Some(Duration::ZERO)
+            );
+            assert_eq!(RateLimiter::new(RateLimit::Rps(0)).next_permit_in(), None);
+
+            let limiter = RateLimiter::new(RateLimit::Rps(4));
+            for _ in 0..4 {
+                assert_eq!(limiter.next_permit_in(), Some(Duration::ZERO));
+                assert!(limiter.acquire());
+            }
+
+            let wait = limiter.next_permit_in().unwrap();
+            assert!(wait > Duration::ZERO && wait <= ns(SEC / 4));
+
+            mock.advance(wait - ns(1));
+            assert!(!limiter.acquire());
+            mock.advance(ns(1));
+            assert_eq!(limiter.next_permit_in(), Some(Duration::ZERO));
+            assert!(limiter.acquire());
+        });
+    }
+
+    #[test]
+    fn custom_clock() {
+        let limiter = RateLimiter::new(RateLimit::Rps(2));
+        let start = ns(10 * SEC);
+
+        assert!(limiter.acquire_at(start));
+        assert!(limiter.acquire_at(start));
+        assert!(!limiter.acquire_at(start));
+
+        let wait = limiter.next_permit_at(start).unwrap();
+        assert!(wait > Duration::ZERO && wait <= ns(SEC / 2));
+        assert!(!limiter.acquire_at(start + wait - ns(1)));
+        assert_eq!(limiter.next_permit_at(start + wait), Some(Duration::ZERO));
+        assert!(limiter.acquire_at(start + wait));
+    }
+
+    #[test]
+    fn keyed() {
+        time::with_instant_mock(|mock| {
+            let mut limiter = KeyedRateLimiter::<String>::new(RateLimit::Rps(2));
+
+            for key in ["a", "b"] {
+                assert!(limiter.acquire(key));
+                assert!(limiter.acquire(key));
+                assert!(!limiter.acquire(key));
+            }
+            assert_eq!(limiter.len(), 2);
+
+            limiter.configure(RateLimit::Rps(3));
+            mock.advance(ns(SEC / 2));
+            limiter.purge();
+            assert_eq!(limiter.len(), 2);
+            assert!(limiter.acquire("a"));
+
+            mock.advance(ns(SEC));
+            limiter.purge();
+            assert!(limiter.is_empty());
+        });
+    }
+
     #[test]
     fn reset() {
         time::with_instant_mock(|mock| {
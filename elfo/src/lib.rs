@@ -7,6 +7,11 @@
 pub use elfo_core::*;
 pub use elfo_macros::{message, msg};
 
+#[cfg(feature = "elfo-configurer")]
+#[cfg_attr(docsrs, doc(cfg(feature = "full")))]
+#[doc(inline)]
+pub use elfo_configurer::check_config;
+
 #[cfg(feature = "test-util")]
 #[cfg_attr(docsrs, doc(cfg(feature = "test-util")))]
 #[doc(inline)]
@@ -14,6 +19,10 @@ pub use elfo_test as test;
 
 /// A set of actors for common tasks.
 pub mod batteries {
+    #[cfg(feature = "elfo-admin")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "full")))]
+    #[doc(inline)]
+    pub use elfo_admin as admin;
     #[cfg(feature = "elfo-configurer")]
     #[cfg_attr(docsrs, doc(cfg(feature = "full")))]
     #[doc(inline)]
@@ -30,6 +39,10 @@ pub mod batteries {
     #[cfg_attr(docsrs, doc(cfg(feature = "full")))]
     #[doc(inline)]
     pub use elfo_network as network;
+    #[cfg(feature = "elfo-otlp")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "otlp")))]
+    #[doc(inline)]
+    pub use elfo_otlp as otlp;
     #[cfg(feature = "elfo-pinger")]
     #[cfg_attr(docsrs, doc(cfg(feature = "full")))]
     #[doc(inline)]
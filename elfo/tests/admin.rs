@@ -0,0 +1,376 @@
+#![allow(missing_docs)]
+#![cfg(all(feature = "test-util", feature = "full", unix))]
+
+use std::{
+    net::SocketAddr,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use serde_json::{json, Value};
+use tempfile::TempDir;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpStream, UnixStream},
+    sync::mpsc,
+};
+
+use elfo::{
+    _priv::do_start,
+    batteries::{
+        admin,
+        configurer::{self, ConfigChanged, ReloadConfigs, CONFIG_CHANGES_TOPIC},
+    },
+    prelude::*,
+    RestartParams, RestartPolicy, Topology,
+};
+
+#[message(ret = ())]
+struct Subscribe;
+
+fn write_config(dir: &TempDir, config: &str) -> PathBuf {
+    let path = dir.path().join("config.toml");
+    std::fs::write(&path, config).unwrap();
+    path
+}
+
+fn socket_config(socket: &Path) -> String {
+    format!(
+        "[system.admins]\nsocket = {:?}\n",
+        socket.display().to_string()
+    )
+}
+
+struct Client {
+    lines: tokio::io::Lines<BufReader<tokio::net::unix::OwnedReadHalf>>,
+    writer: tokio::net::unix::OwnedWriteHalf,
+}
+
+impl Client {
+    async fn connect(socket: &PathBuf) -> Self {
+        // The server is started asynchronously.
+        let stream = loop {
+            match UnixStream::connect(socket).await {
+                Ok(stream) => break stream,
+                Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        };
+
+        let (reader, writer) = stream.into_split();
+        Self {
+            lines: BufReader::new(reader).lines(),
+            writer,
+        }
+    }
+
+    async fn call(&mut self, command: Value) -> Value {
+        let line = format!("{command}\n");
+        self.writer.write_all(line.as_bytes()).await.unwrap();
+        let response = self.lines.next_line().await.unwrap().unwrap();
+        serde_json::from_str(&response).unwrap()
+    }
+}
+
+#[tokio::test]
+async fn control_socket() {
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("admin.sock");
+    let path = write_config(&dir, &socket_config(&socket));
+
+    let topology = Topology::empty();
+    let configurers = topology.local("system.configurers").entrypoint();
+    let configurers_addr = configurers.addr();
+    let admins = topology.local("system.admins");
+    let watcher = topology.local("watcher");
+    let watcher_addr = watcher.addr();
+
+    let (tx, mut rx) = mpsc::unbounded_channel::<ConfigChanged>();
+
+    admins.route_all_to(&configurers);
+    configurers.mount(configurer::from_path(&topology, &path));
+    admins.mount(admin::new(&topology));
+    watcher.mount(ActorGroup::new().exec(move |mut ctx| {
+        let tx = tx.clone();
+        async move {
+            while let Some(envelope) = ctx.recv().await {
+                msg!(match envelope {
+                    (Subscribe, token) => {
+                        ctx.subscribe(CONFIG_CHANGES_TOPIC);
+                        ctx.respond(token, ());
+                    }
+                    msg @ ConfigChanged => tx.send(msg).unwrap(),
+                    _ => {}
+                });
+            }
+        }
+    }));
+
+    do_start(topology, false, |ctx, _| async move {
+        ctx.request_to(watcher_addr, Subscribe)
+            .resolve()
+            .await
+            .unwrap();
+
+        let mut client = Client::connect(&socket).await;
+
+        // Only the owner can connect by default.
+        let mode = std::fs::metadata(&socket).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        let response = client.call(json!({"cmd": "status", "group": "watcher"})).await;
+        assert_eq!(response["ok"][0]["group"], "watcher");
+        assert_eq!(response["ok"][0]["status"]["kind"], "Normal");
+
+        // Connections are served concurrently.
+        let mut other = Client::connect(&socket).await;
+        let response = other.call(json!({"cmd": "status", "group": "watcher"})).await;
+        assert_eq!(response["ok"][0]["group"], "watcher");
+
+        let response = client
+            .call(json!({"cmd": "set_log_level", "group": "watcher", "level": "debug"}))
+            .await;
+        assert_eq!(response, json!({"ok": null}));
+        let changed = rx.recv().await.unwrap();
+        assert_eq!(changed.group, "watcher");
+        assert!(changed.diff[0].new.as_ref().unwrap().contains(r#""max_level":"Debug""#));
+
+        let response = client
+            .call(json!({"cmd": "set_dump_class", "group": "watcher", "class": "slow", "enabled": false}))
+            .await;
+        assert_eq!(response, json!({"ok": null}));
+        let changed = rx.recv().await.unwrap();
+        assert_eq!(changed.diff[0].path, "system.dumping");
+        assert_eq!(changed.diff[0].new.as_deref(), Some(r#"{"classes":{"slow":{"disabled":true}}}"#));
+
+        // The previous override is restored after TTL.
+        let response = client
+            .call(json!({"cmd": "set_dump_class", "group": "watcher", "class": "slow", "enabled": true, "ttl": "100ms"}))
+            .await;
+        assert_eq!(response, json!({"ok": null}));
+        let changed = rx.recv().await.unwrap();
+        assert_eq!(changed.diff[0].path, "system.dumping.classes.slow.disabled");
+        assert_eq!(changed.diff[0].new.as_deref(), Some("false"));
+        let changed = rx.recv().await.unwrap();
+        assert_eq!(changed.diff[0].path, "system.dumping.classes.slow.disabled");
+        assert_eq!(changed.diff[0].new.as_deref(), Some("true"));
+
+        let response = client.call(json!({"cmd": "reload_configs"})).await;
+        assert_eq!(response, json!({"ok": null}));
+
+        let response = client
+            .call(json!({"cmd": "set_log_level", "group": "watcher", "level": "loud"}))
+            .await;
+        assert!(response["error"].as_str().unwrap().contains("unknown level"));
+
+        let response = client
+            .call(json!({"cmd": "set_log_level", "group": "unknown"}))
+            .await;
+        assert_eq!(response, json!({"error": "unknown group `unknown`"}));
+
+        let response = client.call(json!({"cmd": "explode"})).await;
+        assert!(response["error"].as_str().unwrap().starts_with("invalid command"));
+
+        // Too long commands are rejected, and the connection is closed.
+        let response = client.call(json!({"cmd": "x".repeat(100_000)})).await;
+        assert!(response["error"].as_str().unwrap().contains("command is longer"));
+        assert!(!matches!(client.lines.next_line().await, Ok(Some(_))));
+
+        // The socket is removed once the server is stopped.
+        let new_socket = socket.with_file_name("admin2.sock");
+        std::fs::write(&path, socket_config(&new_socket)).unwrap();
+        ctx.request_to(configurers_addr, ReloadConfigs::default())
+            .resolve()
+            .await
+            .unwrap()
+            .unwrap();
+
+        drop(Client::connect(&new_socket).await);
+        assert!(!socket.exists());
+    })
+    .await
+    .expect("cannot start");
+}
+
+#[tokio::test]
+async fn restarts_failed_server() {
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("admin.sock");
+    let path = write_config(&dir, &socket_config(&socket));
+
+    // Files other than sockets aren't removed, so the server fails.
+    std::fs::write(&socket, "occupied").unwrap();
+
+    let topology = Topology::empty();
+    let configurers = topology.local("system.configurers").entrypoint();
+    let admins = topology.local("system.admins");
+
+    admins.route_all_to(&configurers);
+    configurers.mount(configurer::from_path(&topology, &path));
+    admins.mount(admin::new(&topology));
+
+    do_start(topology, false, |_, _| async move {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        std::fs::remove_file(&socket).unwrap();
+
+        // The admin isn't crashed, the server is restarted.
+        let client = tokio::time::timeout(Duration::from_secs(10), Client::connect(&socket));
+        drop(client.await.expect("the server isn't restarted"));
+
+        // The private directory used for binding is removed.
+        let entries = std::fs::read_dir(dir.path()).unwrap();
+        let names = entries
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        assert!(names.iter().all(|name| !name.starts_with(".elfo-admin")));
+    })
+    .await
+    .expect("cannot start");
+}
+
+#[tokio::test]
+async fn statuses_of_groups_mounted_later() {
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("admin.sock");
+    let path = write_config(&dir, &socket_config(&socket));
+
+    let topology = Topology::empty();
+    let configurers = topology.local("system.configurers").entrypoint();
+    let configurers_addr = configurers.addr();
+    let admins = topology.local("system.admins");
+
+    admins.route_all_to(&configurers);
+    configurers.mount(configurer::from_path(&topology, &path));
+    admins.mount(admin::new(&topology));
+
+    do_start(topology, false, |ctx, topology| async move {
+        let mut client = Client::connect(&socket).await;
+
+        // Mount a new group after the admin has started.
+        topology.local("late").mount(
+            ActorGroup::new().exec(|mut ctx| async move { while ctx.recv().await.is_some() {} }),
+        );
+        ctx.request_to(configurers_addr, ReloadConfigs::default())
+            .resolve()
+            .await
+            .unwrap()
+            .unwrap();
+
+        let status = async {
+            loop {
+                let response = client.call(json!({"cmd": "status", "group": "late"})).await;
+                if response["ok"][0]["status"]["kind"] == "Normal" {
+                    break response;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+        let response = tokio::time::timeout(Duration::from_secs(10), status)
+            .await
+            .expect("statuses of the new group aren't subscribed to");
+        assert_eq!(response["ok"][0]["group"], "late");
+    })
+    .await
+    .expect("cannot start");
+}
+
+async fn http_get(addr: SocketAddr, path: &str) -> Value {
+    // The server is started asynchronously.
+    let mut stream = loop {
+        match TcpStream::connect(addr).await {
+            Ok(stream) => break stream,
+            Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+        }
+    };
+
+    let request = format!("GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    stream.write_all(request.as_bytes()).await.unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    assert!(head.starts_with("HTTP/1.1 200"), "{head}");
+    serde_json::from_str(body).unwrap()
+}
+
+#[tokio::test]
+async fn http_introspection() {
+    static FAILED: AtomicBool = AtomicBool::new(false);
+
+    let addr = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let config =
+        format!("[system.admins]\nhttp = \"{addr}\"\n[flaky]\nlimit = 1\npassword = \"qwerty\"\n");
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_config(&dir, &config);
+
+    let topology = Topology::empty();
+    let configurers = topology.local("system.configurers").entrypoint();
+    let admins = topology.local("system.admins");
+    let flaky = topology.local("flaky");
+
+    admins.route_all_to(&configurers);
+    configurers.mount(configurer::from_path(&topology, &path));
+    admins.mount(admin::new(&topology));
+    flaky.mount(
+        ActorGroup::new()
+            .restart_policy(RestartPolicy::on_failure(RestartParams::new(
+                Duration::from_millis(1),
+                Duration::from_millis(10),
+            )))
+            .exec(|mut ctx| async move {
+                if !FAILED.swap(true, Ordering::SeqCst) {
+                    panic!("oops");
+                }
+                while ctx.recv().await.is_some() {}
+            }),
+    );
+
+    do_start(topology, false, |_, _| async move {
+        let topology = http_get(addr, "/topology").await;
+        let groups = topology["groups"].as_array().unwrap();
+        assert!(groups.iter().any(|g| g["name"] == "flaky"));
+
+        let restarts = loop {
+            let restarts = http_get(addr, "/restarts").await;
+            if !restarts.as_array().unwrap().is_empty() {
+                break restarts;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        };
+        assert_eq!(restarts[0]["group"], "flaky");
+        assert_eq!(restarts[0]["status"]["kind"], "Failed");
+        assert!(restarts[0]["at"].is_string());
+
+        let actor = loop {
+            let actors = http_get(addr, "/actors").await;
+            let flaky = actors
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|a| a["group"] == "flaky" && a["status"]["kind"] == "Normal")
+                .cloned();
+            match flaky {
+                Some(actor) => break actor,
+                None => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        };
+        assert_eq!(actor["mailbox"]["len"], 0);
+        assert!(actor["mailbox"]["capacity"].as_u64().unwrap() > 0);
+
+        let configs = http_get(addr, "/configs").await;
+        let flaky = configs
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|c| c["group"] == "flaky")
+            .unwrap();
+        assert_eq!(flaky["config"], json!({"limit": 1, "password": "***"}));
+        assert_eq!(flaky["staged"], false);
+    })
+    .await
+    .expect("cannot start");
+}
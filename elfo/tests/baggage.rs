@@ -0,0 +1,51 @@
+#![allow(missing_docs)]
+#![cfg(feature = "test-util")]
+
+use elfo::{config::AnyConfig, prelude::*, scope, Addr};
+
+#[message]
+struct Start;
+
+#[message]
+struct Check;
+
+#[message]
+#[derive(PartialEq)]
+struct Observed(String);
+
+#[tokio::test]
+async fn propagated() {
+    let blueprint = ActorGroup::new().exec(|mut ctx| async move {
+        let mut proxy_addr = Addr::NULL;
+
+        while let Some(envelope) = ctx.recv().await {
+            let sender = envelope.sender();
+
+            msg!(match envelope {
+                Start => {
+                    proxy_addr = sender;
+                    scope::set_baggage(scope::baggage().with("tenant", "acme"));
+                    ctx.send_to(ctx.addr(), Check).await.unwrap();
+                }
+                Check => {
+                    let observed = Observed(scope::baggage().to_string());
+                    ctx.send_to(proxy_addr, observed).await.unwrap();
+                }
+            });
+        }
+    });
+
+    let mut proxy = elfo::test::proxy(blueprint, AnyConfig::default()).await;
+
+    // The baggage is restored on the receiving side and passed further.
+    proxy.send(Start).await;
+    let envelope = proxy.recv().await;
+    assert_eq!(envelope.baggage().get("tenant"), Some("acme"));
+    assert_msg_eq!(envelope, Observed("tenant=acme".into()));
+
+    // Messages without the baggage reset it.
+    proxy.send(Check).await;
+    let envelope = proxy.recv().await;
+    assert!(envelope.baggage().is_empty());
+    assert_msg_eq!(envelope, Observed(String::new()));
+}
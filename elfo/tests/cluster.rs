@@ -0,0 +1,65 @@
+#![allow(missing_docs)]
+#![cfg(feature = "test-util")]
+#![cfg(feature = "network")]
+#![cfg(unix)]
+
+use std::time::Duration;
+
+use elfo::{config::AnyConfig, prelude::*, test::Cluster, topology};
+
+#[message]
+struct Ping(u32);
+
+#[message]
+#[derive(PartialEq)]
+struct Pong(u32);
+
+fn ponger() -> Blueprint {
+    ActorGroup::new().exec(|mut ctx| async move {
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                Ping(no) => {
+                    let _ = ctx.send(Pong(no)).await;
+                }
+            });
+        }
+    })
+}
+
+#[tokio::test]
+async fn cluster() {
+    let mut cluster = Cluster::builder()
+        .node(1, AnyConfig::default(), |topology, testers| {
+            let pongers = topology.remote("pongers");
+            testers.route_to(&pongers, |_, _| topology::Outcome::Broadcast);
+        })
+        .node(2, AnyConfig::default(), |topology, testers| {
+            let pongers = topology.local("pongers");
+            pongers.route_to(testers, |_| true);
+            pongers.mount(ponger());
+        })
+        .start()
+        .await;
+
+    cluster.proxy(2).set_recv_timeout(Duration::from_secs(5));
+
+    // Wait until nodes are connected.
+    while cluster.proxy(1).try_send(Ping(0)).is_err() {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    assert_msg_eq!(cluster.proxy(2).recv().await, Pong(0));
+    cluster.proxy(1).send(Ping(1)).await;
+    assert_msg_eq!(cluster.proxy(2).recv().await, Pong(1));
+
+    // Remote groups become unavailable after the node is stopped.
+    cluster.stop(2).await;
+
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while cluster.proxy(1).try_send(Ping(2)).is_ok() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("the stopped node is still available");
+}
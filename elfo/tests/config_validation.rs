@@ -144,3 +144,97 @@ async fn actor_group_with_custom_validate_config() {
         .await
         .unwrap();
 }
+
+#[tokio::test]
+async fn tree_validation() {
+    use elfo::{batteries::configurer, init, Topology};
+    use serde::Deserialize;
+
+    #[derive(Deserialize)]
+    struct Batch {
+        size: u32,
+    }
+
+    async fn check(config: AnyConfig) -> Result<(), String> {
+        let topology = Topology::empty();
+        let configurers = topology.local("system.configurers").entrypoint();
+        let a = topology.local("a");
+        let b = topology.local("b");
+
+        let blueprint = configurer::builder(&topology)
+            .validate_tree(|tree| {
+                let a = tree.get::<Batch>("a").unwrap().unwrap();
+                let b = tree.get::<Batch>("b").unwrap().unwrap();
+
+                if b.size % a.size == 0 {
+                    Ok(())
+                } else {
+                    let reason = "must divide `b.size`";
+                    Err(vec![configurer::ReloadConfigsError::new("a", reason)])
+                }
+            })
+            .fixture(config);
+
+        configurers.mount(blueprint);
+        a.mount(ActorGroup::new().exec(|_| async {}));
+        b.mount(ActorGroup::new().exec(|_| async {}));
+
+        init::check_only(topology)
+            .await
+            .map_err(|err| err.to_string())
+    }
+
+    let config = |a: u32, b: u32| {
+        AnyConfig::deserialize(toml::toml! {
+            [a]
+            size = a
+            [b]
+            size = b
+        })
+        .unwrap()
+    };
+
+    check(config(2, 10)).await.unwrap();
+
+    let error = check(config(3, 10)).await.unwrap_err();
+    assert!(error.contains("must divide `b.size`"), "{error}");
+}
+
+#[tokio::test]
+async fn check_config() {
+    use elfo::Topology;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize)]
+    struct Config {
+        #[allow(dead_code)]
+        limit: u32,
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.toml");
+
+    let check = |content: &str| {
+        let path = path.clone();
+        std::fs::write(&path, content).unwrap();
+
+        let topology = Topology::empty();
+        let group = topology.local("group");
+        group.mount(ActorGroup::new().config::<Config>().exec(|_| async {}));
+
+        async move { elfo::check_config(topology, path).await }
+    };
+
+    check("[group]\nlimit = 10").await.unwrap();
+
+    let error = check("[group]\nlimit = \"10\"").await.unwrap_err();
+    assert_eq!(error.errors.len(), 1);
+    assert_eq!(error.errors[0].group, "group");
+
+    // `check_config()` adds its own configurer.
+    let topology = Topology::empty();
+    let configurers = topology.local("system.configurers").entrypoint();
+    configurers.mount(ActorGroup::new().exec(|_| async {}));
+    let error = elfo::check_config(topology, &path).await.unwrap_err();
+    assert_eq!(error.errors[0].group, "system.configurers");
+}
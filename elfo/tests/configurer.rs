@@ -0,0 +1,492 @@
+#![allow(missing_docs)]
+#![cfg(feature = "test-util")]
+
+use std::{path::PathBuf, time::Duration};
+
+use tempfile::TempDir;
+use tokio::sync::mpsc;
+
+use elfo::{
+    _priv::do_start,
+    batteries::configurer::{
+        self, ConfigChanged, GetConfigs, OverrideConfig, ReloadConfigs, RollbackConfig,
+        CONFIG_CHANGES_TOPIC,
+    },
+    messages::ValidateConfig,
+    prelude::*,
+    routers::{MapRouter, Outcome, Singleton},
+    Blueprint, Topology,
+};
+
+#[message(ret = ())]
+struct Subscribe;
+
+fn prepare(content: &str) -> (TempDir, PathBuf) {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.toml");
+    std::fs::write(&path, content).unwrap();
+    (dir, path)
+}
+
+fn subscriber(tx: mpsc::UnboundedSender<ConfigChanged>) -> Blueprint {
+    ActorGroup::new().exec(move |mut ctx| {
+        let tx = tx.clone();
+        async move {
+            while let Some(envelope) = ctx.recv().await {
+                msg!(match envelope {
+                    (Subscribe, token) => {
+                        ctx.subscribe(CONFIG_CHANGES_TOPIC);
+                        ctx.respond(token, ());
+                    }
+                    msg @ ConfigChanged => tx.send(msg).unwrap(),
+                    _ => {}
+                });
+            }
+        }
+    })
+}
+
+#[tokio::test]
+async fn config_changes() {
+    let (dir, path) = prepare("");
+    let pin_path = dir.path().join("pin");
+    std::fs::write(&pin_path, "1234").unwrap();
+    let pin = pin_path.display().to_string();
+    let content = move |limit, url| {
+        format!(
+            "[watched]\nlimit = {limit}\nname = \"a\"\nurl = \"${{ELFO_TEST_UNSET_URL:-{url}}}\"\n\
+             pin = {{ \"$secret\" = \"file:{pin}\" }}"
+        )
+    };
+    std::fs::write(&path, content(1, "http://a")).unwrap();
+
+    let topology = Topology::empty();
+    let configurers = topology.local("system.configurers").entrypoint();
+    let configurers_addr = configurers.addr();
+    let watched = topology.local("watched");
+    let watcher = topology.local("watcher");
+    let watcher_addr = watcher.addr();
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+
+    configurers.mount(configurer::from_path(&topology, &path));
+    watched.mount(ActorGroup::new().exec(|_| async {}));
+    watcher.mount(subscriber(tx));
+
+    do_start(topology, false, |ctx, _| async move {
+        ctx.request_to(watcher_addr, Subscribe)
+            .resolve()
+            .await
+            .unwrap();
+
+        std::fs::write(&pin_path, "5678").unwrap();
+        std::fs::write(&path, content(2, "http://b")).unwrap();
+        ctx.request_to(configurers_addr, ReloadConfigs::default())
+            .resolve()
+            .await
+            .unwrap()
+            .unwrap();
+
+        let changed = rx.recv().await.unwrap();
+        assert_eq!(changed.group, "watched");
+        assert_eq!(changed.diff.len(), 3);
+        assert_eq!(changed.diff[0].path, "limit");
+        assert_eq!(changed.diff[0].old.as_deref(), Some("1"));
+        assert_eq!(changed.diff[0].new.as_deref(), Some("2"));
+
+        // Resolved secrets are masked.
+        assert_eq!(changed.diff[1].path, "pin");
+        assert_eq!(changed.diff[1].old.as_deref(), Some("***"));
+        assert_eq!(changed.diff[1].new.as_deref(), Some("***"));
+
+        // Values with substituted environment variables aren't secrets.
+        assert_eq!(changed.diff[2].path, "url");
+        assert_eq!(changed.diff[2].old.as_deref(), Some("\"http://a\""));
+        assert_eq!(changed.diff[2].new.as_deref(), Some("\"http://b\""));
+
+        let configs = ctx
+            .request_to(configurers_addr, GetConfigs::group("watched"))
+            .resolve()
+            .await
+            .unwrap();
+        assert_eq!(
+            configs[0].config,
+            r#"{"limit":2,"name":"a","pin":"***","url":"http://b"}"#
+        );
+
+        // Nothing is published if configs are up-to-date.
+        ctx.request_to(configurers_addr, ReloadConfigs::forcing())
+            .resolve()
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(rx.try_recv().is_err());
+    })
+    .await
+    .expect("cannot start");
+}
+
+#[tokio::test]
+async fn watch_files() {
+    let own = "[system.configurers]\nwatch = true\nwatch_debounce = \"50ms\"\n";
+    let (_dir, path) = prepare(&format!("{own}[watched]\nlimit = 1"));
+
+    let topology = Topology::empty();
+    let configurers = topology.local("system.configurers").entrypoint();
+    let watched = topology.local("watched");
+    let watcher = topology.local("watcher");
+    let watcher_addr = watcher.addr();
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+
+    configurers.mount(configurer::from_path(&topology, &path));
+    watched.mount(ActorGroup::new().exec(|_| async {}));
+    watcher.mount(subscriber(tx));
+
+    do_start(topology, false, |ctx, _| async move {
+        ctx.request_to(watcher_addr, Subscribe)
+            .resolve()
+            .await
+            .unwrap();
+
+        // Several writes are debounced into one reload.
+        for limit in 2..=4 {
+            std::fs::write(&path, format!("{own}[watched]\nlimit = {limit}")).unwrap();
+        }
+
+        let changed = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .expect("the change is not detected")
+            .unwrap();
+
+        assert_eq!(changed.group, "watched");
+        assert_eq!(changed.diff.len(), 1);
+        assert_eq!(changed.diff[0].old.as_deref(), Some("1"));
+        assert_eq!(changed.diff[0].new.as_deref(), Some("4"));
+    })
+    .await
+    .expect("cannot start");
+}
+
+#[tokio::test]
+async fn rollback() {
+    let (_dir, path) = prepare("[watched]\nlimit = 1");
+
+    let topology = Topology::empty();
+    let configurers = topology.local("system.configurers").entrypoint();
+    let configurers_addr = configurers.addr();
+    let watched = topology.local("watched");
+    let watcher = topology.local("watcher");
+    let watcher_addr = watcher.addr();
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+
+    configurers.mount(configurer::from_path(&topology, &path));
+    watched.mount(ActorGroup::new().exec(|_| async {}));
+    watcher.mount(subscriber(tx));
+
+    do_start(topology, false, |ctx, _| async move {
+        ctx.request_to(watcher_addr, Subscribe)
+            .resolve()
+            .await
+            .unwrap();
+
+        for limit in 2..=3 {
+            std::fs::write(&path, format!("[watched]\nlimit = {limit}")).unwrap();
+            ctx.request_to(configurers_addr, ReloadConfigs::default())
+                .resolve()
+                .await
+                .unwrap()
+                .unwrap();
+            rx.recv().await.unwrap();
+        }
+
+        let rollback = |steps| {
+            ctx.request_to(configurers_addr, RollbackConfig::new(steps))
+                .resolve()
+        };
+
+        // Repeated rollbacks go further back.
+        for (old, new) in [("3", "2"), ("2", "1")] {
+            rollback(1).await.unwrap().unwrap();
+            let changed = rx.recv().await.unwrap();
+            assert_eq!(changed.diff[0].old.as_deref(), Some(old));
+            assert_eq!(changed.diff[0].new.as_deref(), Some(new));
+        }
+
+        let rejected = rollback(1).await.unwrap().unwrap_err();
+        assert!(rejected.errors[0]
+            .reason
+            .contains("only 0 previous configs"));
+        assert!(rx.try_recv().is_err());
+    })
+    .await
+    .expect("cannot start");
+}
+
+#[tokio::test]
+async fn rollback_full_history() {
+    let own = "[system.configurers]\nhistory_size = 3\n";
+    let (_dir, path) = prepare(&format!("{own}[watched]\nlimit = 1"));
+
+    let topology = Topology::empty();
+    let configurers = topology.local("system.configurers").entrypoint();
+    let configurers_addr = configurers.addr();
+    let watched = topology.local("watched");
+    let watcher = topology.local("watcher");
+    let watcher_addr = watcher.addr();
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+
+    configurers.mount(configurer::from_path(&topology, &path));
+    watched.mount(ActorGroup::new().exec(|_| async {}));
+    watcher.mount(subscriber(tx));
+
+    do_start(topology, false, |ctx, _| async move {
+        ctx.request_to(watcher_addr, Subscribe)
+            .resolve()
+            .await
+            .unwrap();
+
+        // Only the last three configs are kept: 3, 4 and 5.
+        for limit in 2..=5 {
+            std::fs::write(&path, format!("{own}[watched]\nlimit = {limit}")).unwrap();
+            ctx.request_to(configurers_addr, ReloadConfigs::default())
+                .resolve()
+                .await
+                .unwrap()
+                .unwrap();
+            rx.recv().await.unwrap();
+        }
+
+        let rollback = |steps| {
+            ctx.request_to(configurers_addr, RollbackConfig::new(steps))
+                .resolve()
+        };
+
+        for (old, new) in [("5", "4"), ("4", "3")] {
+            rollback(1).await.unwrap().unwrap();
+            let changed = rx.recv().await.unwrap();
+            assert_eq!(changed.diff[0].old.as_deref(), Some(old));
+            assert_eq!(changed.diff[0].new.as_deref(), Some(new));
+        }
+
+        let rejected = rollback(1).await.unwrap().unwrap_err();
+        assert!(rejected.errors[0]
+            .reason
+            .contains("only 0 previous configs"));
+        assert!(rx.try_recv().is_err());
+    })
+    .await
+    .expect("cannot start");
+}
+
+#[tokio::test]
+async fn overrides() {
+    #[derive(Debug, serde::Deserialize)]
+    #[allow(dead_code)]
+    struct Config {
+        limit: u32,
+    }
+
+    let (_dir, path) = prepare("[watched]\nlimit = 1");
+
+    let topology = Topology::empty();
+    let configurers = topology.local("system.configurers").entrypoint();
+    let configurers_addr = configurers.addr();
+    let watched = topology.local("watched");
+    let watcher = topology.local("watcher");
+    let watcher_addr = watcher.addr();
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+
+    configurers.mount(configurer::from_path(&topology, &path));
+    watched.mount(ActorGroup::new().config::<Config>().exec(|_| async {}));
+    watcher.mount(subscriber(tx));
+
+    do_start(topology, false, |ctx, _| async move {
+        ctx.request_to(watcher_addr, Subscribe)
+            .resolve()
+            .await
+            .unwrap();
+
+        let request = |req| ctx.request_to(configurers_addr, req).resolve();
+
+        request(OverrideConfig::new("watched.limit", "5"))
+            .await
+            .unwrap()
+            .unwrap();
+        let changed = rx.recv().await.unwrap();
+        assert_eq!(changed.diff[0].old.as_deref(), Some("1"));
+        assert_eq!(changed.diff[0].new.as_deref(), Some("5"));
+
+        // Overrides are kept on reloading.
+        request(OverrideConfig::remove("unknown"))
+            .await
+            .unwrap()
+            .unwrap();
+        ctx.request_to(configurers_addr, ReloadConfigs::forcing())
+            .resolve()
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(rx.try_recv().is_err());
+
+        // Invalid overrides are reverted.
+        let rejected = request(OverrideConfig::new("watched.limit", "\"x\""))
+            .await
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(rejected.errors[0].group, "watched");
+        let rejected = request(OverrideConfig::new("watched.limit", "{"))
+            .await
+            .unwrap()
+            .unwrap_err();
+        assert!(rejected.errors[0].reason.contains("invalid value"));
+        assert!(rx.try_recv().is_err());
+
+        request(OverrideConfig::remove("watched.limit"))
+            .await
+            .unwrap()
+            .unwrap();
+        let changed = rx.recv().await.unwrap();
+        assert_eq!(changed.diff[0].old.as_deref(), Some("5"));
+        assert_eq!(changed.diff[0].new.as_deref(), Some("1"));
+
+        // Temporary overrides restore the value before the first one.
+        for limit in ["6", "7"] {
+            let ttl = Duration::from_millis(100);
+            request(OverrideConfig::new("watched.limit", limit).with_ttl(ttl))
+                .await
+                .unwrap()
+                .unwrap();
+            let changed = rx.recv().await.unwrap();
+            assert_eq!(changed.diff[0].new.as_deref(), Some(limit));
+        }
+        let changed = rx.recv().await.unwrap();
+        assert_eq!(changed.diff[0].old.as_deref(), Some("7"));
+        assert_eq!(changed.diff[0].new.as_deref(), Some("1"));
+        assert!(rx.try_recv().is_err());
+    })
+    .await
+    .expect("cannot start");
+}
+
+#[tokio::test]
+async fn reload_report() {
+    let own = "[system.configurers]\nvalidation_timeout = \"100ms\"\n";
+    let (_dir, path) = prepare(own);
+
+    let topology = Topology::empty();
+    let configurers = topology.local("system.configurers").entrypoint();
+    let configurers_addr = configurers.addr();
+
+    configurers.mount(configurer::from_path(&topology, &path));
+
+    for name in ["valid", "invalid", "slow"] {
+        let blueprint = ActorGroup::new()
+            .router(MapRouter::new(|e| {
+                msg!(match e {
+                    ValidateConfig => Outcome::Unicast(Singleton),
+                    _ => Outcome::Default,
+                })
+            }))
+            .exec(move |mut ctx| async move {
+                let mut tokens = Vec::new();
+                while let Some(envelope) = ctx.recv().await {
+                    msg!(match envelope {
+                        (ValidateConfig { .. }, token) => match name {
+                            "valid" => ctx.respond(token, Ok(())),
+                            "invalid" => ctx.respond(token, Err("bad limit".into())),
+                            // Never respond, but keep the request alive.
+                            _ => tokens.push(token),
+                        },
+                        _ => {}
+                    });
+                }
+            });
+
+        topology.local(name).mount(blueprint);
+    }
+
+    do_start(topology, false, |ctx, _| async move {
+        let rejected = ctx
+            .request_to(configurers_addr, ReloadConfigs::forcing())
+            .resolve()
+            .await
+            .unwrap()
+            .unwrap_err();
+
+        assert!(rejected.validated.iter().any(|group| group == "valid"));
+        assert_eq!(rejected.errors.len(), 1);
+        assert_eq!(rejected.errors[0].group, "invalid");
+        assert_eq!(rejected.errors[0].reason, "bad limit");
+        assert_eq!(rejected.timed_out, ["slow"]);
+    })
+    .await
+    .expect("cannot start");
+}
+
+#[tokio::test]
+async fn snapshot() {
+    #[derive(Debug, serde::Deserialize)]
+    #[allow(dead_code)]
+    struct Config {
+        limit: u32,
+        #[serde(default = "default_retries")]
+        retries: u32,
+    }
+
+    fn default_retries() -> u32 {
+        3
+    }
+
+    let (_dir, path) = prepare(
+        r#"
+        [common]
+        limit = 1
+
+        [producers]
+        limit = 10
+
+        [producers.system.key_overrides.b]
+        retries = 5
+
+        [producers.system.key_overrides.a]
+        limit = 20
+        "#,
+    );
+
+    let topology = Topology::empty();
+    let blueprint = || ActorGroup::new().config::<Config>().exec(|_| async {});
+    topology.local("producers").mount(blueprint());
+    topology.local("consumers").mount(blueprint());
+    // Unmounted groups are skipped.
+    let _unmounted = topology.local("unmounted");
+
+    let snapshot = elfo::test::config_snapshot(&topology, &path).await;
+    assert_eq!(
+        snapshot,
+        "\
+[consumers]
+Config {
+    limit: 1,
+    retries: 3,
+}
+
+[producers]
+Config {
+    limit: 10,
+    retries: 3,
+}
+system.key_overrides.a: Config {
+    limit: 20,
+    retries: 3,
+}
+system.key_overrides.b: Config {
+    limit: 10,
+    retries: 5,
+}
+"
+    );
+}
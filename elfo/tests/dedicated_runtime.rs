@@ -0,0 +1,417 @@
+#![allow(missing_docs)]
+#![cfg(feature = "test-util")]
+
+use elfo::{config::AnyConfig, prelude::*, DedicatedRuntime};
+
+#[message(ret = Option<String>)]
+struct GetThreadName;
+
+#[message(ret = Vec<usize>)]
+struct GetCpuAffinity;
+
+fn testee(runtime: Option<DedicatedRuntime>) -> Blueprint {
+    let group = ActorGroup::new();
+    let group = match runtime {
+        Some(runtime) => group.dedicated_runtime(runtime),
+        None => group,
+    };
+
+    group.exec(move |mut ctx| async move {
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                (GetThreadName, token) => {
+                    let name = std::thread::current().name().map(String::from);
+                    ctx.respond(token, name);
+                }
+                (GetCpuAffinity, token) => ctx.respond(token, cpu_affinity()),
+            });
+        }
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn cpu_affinity() -> Vec<usize> {
+    // SAFETY: `cpu_set_t` is a plain bitmask, zeroed is a valid value.
+    let mut set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+    // SAFETY: `set` is a valid pointer to `cpu_set_t`.
+    let rv = unsafe { libc::sched_getaffinity(0, std::mem::size_of_val(&set), &mut set) };
+    assert_eq!(rv, 0);
+
+    (0..libc::CPU_SETSIZE as usize)
+        .filter(|&cpu| {
+            // SAFETY: `cpu` is inside the set.
+            unsafe { libc::CPU_ISSET(cpu, &set) }
+        })
+        .collect()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn cpu_affinity() -> Vec<usize> {
+    Vec::new()
+}
+
+#[tokio::test]
+async fn shared() {
+    let proxy = elfo::test::proxy(testee(None), AnyConfig::default()).await;
+    let name = proxy.request(GetThreadName).await;
+    assert_ne!(name.as_deref(), Some("subject"));
+}
+
+#[tokio::test]
+async fn current_thread() {
+    let runtime = DedicatedRuntime::current_thread();
+    let proxy = elfo::test::proxy(testee(Some(runtime)), AnyConfig::default()).await;
+
+    for _ in 0..3 {
+        let name = proxy.request(GetThreadName).await;
+        assert_eq!(name.as_deref(), Some("subject"));
+    }
+}
+
+#[cfg(feature = "tokio-runtime")]
+#[tokio::test]
+async fn multi_thread() {
+    let runtime = DedicatedRuntime::multi_thread(2);
+    let proxy = elfo::test::proxy(testee(Some(runtime)), AnyConfig::default()).await;
+
+    for _ in 0..3 {
+        let name = proxy.request(GetThreadName).await;
+        assert_eq!(name.as_deref(), Some("subject"));
+    }
+}
+
+#[cfg(all(target_os = "linux", feature = "tokio-runtime"))]
+#[tokio::test]
+async fn cpu_affinity_from_config() {
+    use serde::Deserialize;
+    use toml::toml;
+
+    use elfo::messages::UpdateConfig;
+
+    let available = cpu_affinity();
+    let cpu = available[0];
+
+    let runtime = DedicatedRuntime::multi_thread(2);
+    let config = AnyConfig::deserialize(toml! { system.runtime.cpu_affinity = [cpu] }).unwrap();
+    let proxy = elfo::test::proxy(testee(Some(runtime)), config).await;
+
+    for _ in 0..3 {
+        assert_eq!(proxy.request(GetCpuAffinity).await, vec![cpu]);
+    }
+
+    // Reset to the default affinity.
+    proxy.send(UpdateConfig::new(AnyConfig::default())).await;
+    assert_eq!(proxy.request(GetCpuAffinity).await, available);
+}
+
+#[cfg(all(feature = "unstable", feature = "full"))]
+#[tokio::test]
+async fn custom() {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use futures::future::BoxFuture;
+    use tokio::runtime::Handle;
+
+    use elfo::{_priv::do_start, batteries, Runtime, Topology};
+
+    struct Counting {
+        spawned: Arc<AtomicUsize>,
+        handle: Handle,
+    }
+
+    impl Runtime for Counting {
+        fn spawn(&self, task: BoxFuture<'static, ()>) {
+            self.spawned.fetch_add(1, Ordering::SeqCst);
+            self.handle.spawn(task);
+        }
+    }
+
+    let spawned = Arc::new(AtomicUsize::new(0));
+    let topology = Topology::empty();
+    topology.add_dedicated_rt(
+        |meta| meta.group == "testee",
+        Counting {
+            spawned: spawned.clone(),
+            handle: Handle::current(),
+        },
+    );
+
+    let configurers = topology.local("system.configurers").entrypoint();
+    let group = topology.local("testee");
+    let testee_addr = group.addr();
+
+    configurers.mount(batteries::configurer::fixture(
+        &topology,
+        AnyConfig::default(),
+    ));
+    group.mount(testee(None));
+
+    do_start(topology, false, |ctx, _| async move {
+        ctx.request_to(testee_addr, GetThreadName)
+            .resolve()
+            .await
+            .unwrap();
+        // Only the testee is spawned on the custom runtime.
+        assert_eq!(spawned.load(Ordering::SeqCst), 1);
+    })
+    .await
+    .expect("cannot start");
+}
+
+#[cfg(all(feature = "unstable", feature = "full"))]
+#[tokio::test]
+async fn custom_blocking() {
+    use std::{
+        io,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    use futures::future::BoxFuture;
+    use serde::{Deserialize, Serialize};
+    use tokio::runtime::Handle;
+
+    use elfo::{
+        _priv::do_start,
+        batteries,
+        persistence::{EventLog, EventSourced, Journal},
+        Runtime, Topology,
+    };
+
+    struct Counting {
+        blocking: Arc<AtomicUsize>,
+        handle: Handle,
+    }
+
+    impl Runtime for Counting {
+        fn spawn(&self, task: BoxFuture<'static, ()>) {
+            self.handle.spawn(task);
+        }
+
+        fn spawn_blocking(&self, task: Box<dyn FnOnce() + Send>) {
+            self.blocking.fetch_add(1, Ordering::SeqCst);
+            self.handle.spawn_blocking(task);
+        }
+    }
+
+    struct Empty;
+
+    impl Journal for Empty {
+        fn append(&self, _stream: &str, _seq_no: u64, _event: &[u8]) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn read(&self, _stream: &str, _after: u64) -> io::Result<Vec<(u64, Vec<u8>)>> {
+            Ok(Vec::new())
+        }
+    }
+
+    #[derive(Default, Serialize, Deserialize)]
+    struct State;
+
+    impl EventSourced for State {
+        type Event = ();
+
+        fn apply(&mut self, _event: &()) {}
+    }
+
+    let blocking = Arc::new(AtomicUsize::new(0));
+    let topology = Topology::empty();
+    topology.add_dedicated_rt(
+        |meta| meta.group == "testee",
+        Counting {
+            blocking: blocking.clone(),
+            handle: Handle::current(),
+        },
+    );
+
+    let configurers = topology.local("system.configurers").entrypoint();
+    let group = topology.local("testee");
+    let testee_addr = group.addr();
+
+    configurers.mount(batteries::configurer::fixture(
+        &topology,
+        AnyConfig::default(),
+    ));
+    group.mount(ActorGroup::new().exec(|mut ctx| async move {
+        let mut log = EventLog::<State>::recover(Arc::new(Empty)).await.unwrap();
+
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                (GetThreadName, token) => {
+                    log.persist(()).await.unwrap();
+                    ctx.respond(token, None);
+                }
+            });
+        }
+    }));
+
+    do_start(topology, false, |ctx, _| async move {
+        ctx.request_to(testee_addr, GetThreadName)
+            .resolve()
+            .await
+            .unwrap();
+        // Both recovering and persisting are run on the custom runtime.
+        assert_eq!(blocking.load(Ordering::SeqCst), 2);
+    })
+    .await
+    .expect("cannot start");
+}
+
+#[cfg(all(feature = "unstable", feature = "full"))]
+#[tokio::test]
+async fn custom_sleep() {
+    use std::{
+        sync::{
+            atomic::{AtomicBool, AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use futures::future::BoxFuture;
+    use tokio::runtime::Handle;
+
+    use elfo::{_priv::do_start, batteries, RestartParams, RestartPolicy, Runtime, Topology};
+
+    struct Counting {
+        slept: Arc<AtomicUsize>,
+        handle: Handle,
+    }
+
+    impl Runtime for Counting {
+        fn spawn(&self, task: BoxFuture<'static, ()>) {
+            self.handle.spawn(task);
+        }
+
+        fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+            self.slept.fetch_add(1, Ordering::SeqCst);
+            Box::pin(tokio::time::sleep(duration))
+        }
+    }
+
+    let slept = Arc::new(AtomicUsize::new(0));
+    let topology = Topology::empty();
+    topology.add_dedicated_rt(
+        |meta| meta.group == "testee",
+        Counting {
+            slept: slept.clone(),
+            handle: Handle::current(),
+        },
+    );
+
+    let configurers = topology.local("system.configurers").entrypoint();
+    let group = topology.local("testee");
+    let testee_addr = group.addr();
+
+    configurers.mount(batteries::configurer::fixture(
+        &topology,
+        AnyConfig::default(),
+    ));
+
+    let panicked = Arc::new(AtomicBool::new(false));
+    group.mount(
+        ActorGroup::new()
+            .restart_policy(RestartPolicy::on_failure(
+                RestartParams::new(Duration::from_millis(50), Duration::from_millis(50))
+                    // Otherwise, a slow first start leads to an immediate restart.
+                    .auto_reset(Duration::from_secs(60)),
+            ))
+            .exec(move |mut ctx| {
+                let panicked = panicked.clone();
+                async move {
+                    assert!(panicked.swap(true, Ordering::SeqCst), "first start");
+
+                    while let Some(envelope) = ctx.recv().await {
+                        msg!(match envelope {
+                            (GetThreadName, token) => ctx.respond(token, None),
+                        });
+                    }
+                }
+            }),
+    );
+
+    do_start(topology, false, |ctx, _| async move {
+        while ctx
+            .request_to(testee_addr, GetThreadName)
+            .resolve()
+            .await
+            .is_err()
+        {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        // The restart is delayed by the custom runtime.
+        assert_eq!(slept.load(Ordering::SeqCst), 1);
+    })
+    .await
+    .expect("cannot start");
+}
+
+#[cfg(all(feature = "unstable", feature = "full"))]
+#[tokio::test]
+async fn custom_spawn_of_connections() {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use futures::future::BoxFuture;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+        runtime::Handle,
+    };
+
+    use elfo::{
+        _priv::do_start,
+        batteries::configurer::{self, HttpSource},
+        Runtime, Topology,
+    };
+
+    struct Counting {
+        spawned: Arc<AtomicUsize>,
+        handle: Handle,
+    }
+
+    impl Runtime for Counting {
+        fn spawn(&self, task: BoxFuture<'static, ()>) {
+            self.spawned.fetch_add(1, Ordering::SeqCst);
+            self.handle.spawn(task);
+        }
+    }
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut buf = [0; 1024];
+        let _ = stream.read(&mut buf).await.unwrap();
+        let response = "HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n";
+        stream.write_all(response.as_bytes()).await.unwrap();
+    });
+
+    let spawned = Arc::new(AtomicUsize::new(0));
+    let topology = Topology::empty();
+    topology.add_dedicated_rt(
+        |meta| meta.group == "system.configurers",
+        Counting {
+            spawned: spawned.clone(),
+            handle: Handle::current(),
+        },
+    );
+
+    let configurers = topology.local("system.configurers").entrypoint();
+    configurers.mount(configurer::from_source(&topology, HttpSource::new(&url)));
+
+    do_start(topology, false, |_, _| async move {
+        // The configurer itself and its connection to the config source.
+        assert_eq!(spawned.load(Ordering::SeqCst), 2);
+    })
+    .await
+    .expect("cannot start");
+}
@@ -0,0 +1,90 @@
+#![allow(missing_docs)]
+#![cfg(feature = "test-util")]
+
+use std::time::Duration;
+
+use serde::Deserialize;
+use toml::toml;
+
+use elfo::{config::AnyConfig, errors::TrySendError, prelude::*};
+
+#[message]
+struct Event {
+    id: u32,
+    payload: u32,
+}
+
+#[message]
+#[derive(PartialEq, Eq)]
+struct Received(u32);
+
+#[message(ret = ())]
+struct Freeze;
+
+#[tokio::test(start_paused = true)]
+async fn it_works() {
+    let group = ActorGroup::new()
+        .deduplicate_by(Duration::from_secs(10), |event: &Event| event.id)
+        .exec(|mut ctx| async move {
+            while let Some(envelope) = ctx.recv().await {
+                msg!(match envelope {
+                    Event { payload, .. } => ctx.send(Received(payload)).await.unwrap(),
+                });
+            }
+        });
+
+    let mut proxy = elfo::test::proxy(group, AnyConfig::default()).await;
+    let event = |id, payload| Event { id, payload };
+
+    proxy.send(event(1, 10)).await;
+    proxy.send(event(1, 11)).await;
+    proxy.send(event(2, 20)).await;
+    assert_msg_eq!(proxy.recv().await, Received(10));
+    assert_msg_eq!(proxy.recv().await, Received(20));
+    assert!(proxy.try_recv().await.is_none());
+
+    // The window is over.
+    tokio::time::sleep(Duration::from_secs(10)).await;
+    proxy.send(event(1, 12)).await;
+    proxy.send(event(1, 13)).await;
+    assert_msg_eq!(proxy.recv().await, Received(12));
+    assert!(proxy.try_recv().await.is_none());
+}
+
+#[tokio::test(start_paused = true)]
+async fn retry_after_full_mailbox() {
+    let group = ActorGroup::new()
+        .deduplicate_by(Duration::from_secs(10), |event: &Event| event.id)
+        .exec(|mut ctx| async move {
+            while let Some(envelope) = ctx.recv().await {
+                msg!(match envelope {
+                    Event { payload, .. } => ctx.send(Received(payload)).await.unwrap(),
+                    (Freeze, token) => {
+                        ctx.respond(token, ());
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                    }
+                });
+            }
+        });
+
+    let config = AnyConfig::deserialize(toml! {
+        system.mailbox.capacity = 1
+    })
+    .unwrap();
+
+    let mut proxy = elfo::test::proxy(group, config).await;
+    let event = |id, payload| Event { id, payload };
+
+    proxy.request(Freeze).await;
+    proxy.try_send(event(1, 10)).unwrap();
+    assert!(matches!(
+        proxy.try_send(event(2, 20)),
+        Err(TrySendError::Full(_))
+    ));
+    assert_msg_eq!(proxy.recv().await, Received(10));
+
+    // The undelivered message isn't considered a duplicate.
+    proxy.try_send(event(2, 21)).unwrap();
+    assert_msg_eq!(proxy.recv().await, Received(21));
+    assert!(proxy.try_recv().await.is_none());
+}
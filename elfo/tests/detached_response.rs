@@ -0,0 +1,57 @@
+#![allow(missing_docs)]
+#![cfg(feature = "test-util")]
+
+use std::{collections::HashMap, time::Duration};
+
+use elfo::{config::AnyConfig, prelude::*};
+
+#[message(ret = u32)]
+struct Compute(u32);
+
+#[message(ret = u32)]
+struct Deferred(u32);
+
+#[message]
+struct Flush;
+
+#[tokio::test(start_paused = true)]
+async fn it_works() {
+    let group = ActorGroup::new().exec(|mut ctx| async move {
+        let mut deferred = HashMap::new();
+
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                (Compute(n), token) => {
+                    let token = token.detach();
+                    assert!(!token.is_forgotten());
+
+                    // Respond from a spawned task without blocking the actor.
+                    tokio::spawn(async move {
+                        tokio::time::sleep(Duration::from_millis(u64::from(n))).await;
+                        token.respond(n * 2);
+                    });
+                }
+                (Deferred(n), token) => {
+                    deferred.insert(n, token.detach());
+                }
+                Flush => {
+                    for (n, token) in deferred.drain() {
+                        token.respond(n + 1);
+                    }
+                }
+            });
+        }
+    });
+
+    let proxy = elfo::test::proxy(group, AnyConfig::default()).await;
+
+    let (a, b) = tokio::join!(proxy.request(Compute(20)), proxy.request(Compute(10)));
+    assert_eq!((a, b), (40, 20));
+
+    let (a, b, ()) = tokio::join!(
+        proxy.request(Deferred(1)),
+        proxy.request(Deferred(2)),
+        proxy.send(Flush),
+    );
+    assert_eq!((a, b), (2, 3));
+}
@@ -0,0 +1,163 @@
+#![allow(missing_docs)]
+#![cfg(feature = "test-util")]
+
+use tokio::sync::mpsc;
+
+use elfo::{
+    _priv::do_start, batteries::configurer::ReloadConfigs, config::AnyConfig, messages::Ping,
+    prelude::*, DedicatedRuntime, Topology,
+};
+
+#[message(ret = ())]
+struct Produce;
+
+#[message]
+struct Event;
+
+fn producer() -> Blueprint {
+    ActorGroup::new().exec(|mut ctx| async move {
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                (Produce, token) => {
+                    let _ = ctx.send(Event).await;
+                    ctx.respond(token, ());
+                }
+            });
+        }
+    })
+}
+
+fn plugin(tx: mpsc::UnboundedSender<()>) -> Blueprint {
+    ActorGroup::new().exec(move |mut ctx| {
+        let tx = tx.clone();
+        async move {
+            while let Some(envelope) = ctx.recv().await {
+                msg!(match envelope {
+                    Event => tx.send(()).unwrap(),
+                });
+            }
+        }
+    })
+}
+
+fn mount_plugin(topology: &Topology, tx: &mpsc::UnboundedSender<()>) {
+    let plugin_group = topology.local("plugin");
+    let producer_group = topology.existing_local("producer").unwrap();
+
+    producer_group.route_to(&plugin_group, |envelope| {
+        msg!(match envelope {
+            Event => true,
+            _ => false,
+        })
+    });
+
+    plugin_group.mount(plugin(tx.clone()));
+}
+
+#[tokio::test]
+async fn mount_and_unmount() {
+    let topology = Topology::empty();
+    let configurers = topology.local("system.configurers").entrypoint();
+    let configurers_addr = configurers.addr();
+    let producer_group = topology.local("producer");
+    let producer_addr = producer_group.addr();
+
+    configurers.mount(elfo::batteries::configurer::fixture(
+        &topology,
+        AnyConfig::default(),
+    ));
+    producer_group.mount(producer());
+
+    do_start(topology, false, |ctx, topology| async move {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let reload = || ctx.request_to(configurers_addr, ReloadConfigs::default());
+
+        // No routes yet.
+        ctx.request_to(producer_addr, Produce)
+            .resolve()
+            .await
+            .unwrap();
+        assert!(rx.try_recv().is_err());
+
+        // Mount a new group at runtime.
+        mount_plugin(&topology, &tx);
+        reload().resolve().await.unwrap().unwrap();
+        ctx.request_to(producer_addr, Produce)
+            .resolve()
+            .await
+            .unwrap();
+        rx.recv().await.unwrap();
+
+        // Unmount it.
+        assert!(topology.unmount("plugin").await);
+        assert!(!topology.unmount("plugin").await);
+        assert!(topology.locals().all(|group| group.name != "plugin"));
+        assert!(topology.connections().all(|c| c.from != producer_addr));
+        ctx.request_to(producer_addr, Produce)
+            .resolve()
+            .await
+            .unwrap();
+        assert!(rx.try_recv().is_err());
+
+        // Mount again with the same name.
+        mount_plugin(&topology, &tx);
+        reload().resolve().await.unwrap().unwrap();
+        ctx.request_to(producer_addr, Produce)
+            .resolve()
+            .await
+            .unwrap();
+        rx.recv().await.unwrap();
+    })
+    .await
+    .expect("cannot start");
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn unmount_stops_dedicated_runtime() {
+    fn count_threads(name: &str) -> usize {
+        std::fs::read_dir("/proc/self/task")
+            .unwrap()
+            .filter_map(|task| std::fs::read_to_string(task.ok()?.path().join("comm")).ok())
+            .filter(|comm| comm.trim_end() == name)
+            .count()
+    }
+
+    let topology = Topology::empty();
+    let configurers = topology.local("system.configurers").entrypoint();
+    let configurers_addr = configurers.addr();
+
+    configurers.mount(elfo::batteries::configurer::fixture(
+        &topology,
+        AnyConfig::default(),
+    ));
+
+    do_start(topology, false, |ctx, topology| async move {
+        for _ in 0..3 {
+            let group = ActorGroup::new()
+                .dedicated_runtime(DedicatedRuntime::current_thread())
+                .exec(|mut ctx| async move { while ctx.recv().await.is_some() {} });
+
+            let local = topology.local("dedicated-rt");
+            let addr = local.addr();
+            local.mount(group);
+            ctx.request_to(configurers_addr, ReloadConfigs::default())
+                .resolve()
+                .await
+                .unwrap()
+                .unwrap();
+
+            // Wait for the actor to start on the runtime.
+            ctx.request_to(addr, Ping::default())
+                .resolve()
+                .await
+                .unwrap();
+            assert_eq!(count_threads("dedicated-rt"), 1);
+
+            assert!(topology.unmount("dedicated-rt").await);
+            assert_eq!(count_threads("dedicated-rt"), 0);
+        }
+    })
+    .await
+    .expect("cannot start");
+}
@@ -0,0 +1,58 @@
+#![allow(missing_docs)]
+#![cfg(feature = "test-util")]
+
+use std::collections::HashSet;
+
+use elfo::{config::AnyConfig, prelude::*, scope, tracing::TraceId};
+
+#[message]
+struct Start;
+
+#[message]
+struct Produced(u32);
+
+#[message]
+struct Handled(u32, TraceId);
+
+#[tokio::test]
+async fn it_works() {
+    let group = ActorGroup::new().exec(|mut ctx| async move {
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                Start => {
+                    let sender = ctx.sender_to(ctx.group());
+                    assert_eq!(sender.recipient(), ctx.group());
+
+                    // Send messages from a thread outside the actor system.
+                    std::thread::spawn(move || {
+                        sender.try_send(Produced(1)).unwrap();
+                        sender.unbounded_send(Produced(2)).unwrap();
+                        futures::executor::block_on(sender.send(Produced(3))).unwrap();
+                    })
+                    .join()
+                    .unwrap();
+                }
+                Produced(no) => {
+                    let _ = ctx.send(Handled(no, scope::trace_id())).await;
+                }
+            });
+        }
+    });
+
+    let mut proxy = elfo::test::proxy(group, AnyConfig::default()).await;
+    proxy.send(Start).await;
+
+    let mut trace_ids = HashSet::new();
+    for expected in 1..=3 {
+        let envelope = proxy.recv().await;
+        msg!(match envelope {
+            Handled(no, trace_id) => {
+                assert_eq!(no, expected);
+                trace_ids.insert(trace_id);
+            }
+        });
+    }
+
+    // Every message starts a new trace.
+    assert_eq!(trace_ids.len(), 3);
+}
@@ -0,0 +1,87 @@
+#![allow(missing_docs)]
+#![cfg(feature = "test-util")]
+
+use std::time::Duration;
+
+use elfo::{
+    config::AnyConfig,
+    prelude::*,
+    routers::Singleton,
+    test::{Fault, Proxy},
+    RestartParams, RestartPolicy,
+};
+
+#[message]
+struct Started;
+
+#[message]
+#[derive(PartialEq)]
+struct Ping(u32);
+
+async fn sample() -> Proxy {
+    let group = ActorGroup::new()
+        .restart_policy(RestartPolicy::on_failure(RestartParams::new(
+            Duration::from_secs(1),
+            Duration::from_secs(10),
+        )))
+        .exec(|mut ctx| async move {
+            ctx.send(Started).await.unwrap();
+
+            while let Some(envelope) = ctx.recv().await {
+                msg!(match envelope {
+                    msg @ Ping => ctx.send(msg).await.unwrap(),
+                });
+            }
+        });
+
+    let mut proxy = elfo::test::proxy(group, AnyConfig::default()).await;
+    assert_msg!(proxy.recv().await, Started);
+    proxy
+}
+
+#[tokio::test(start_paused = true)]
+async fn panic() {
+    let mut proxy = sample().await;
+
+    proxy.inject_fault(Singleton, Fault::Panic);
+    proxy.send(Ping(1)).await;
+    proxy.expect_no_message::<Ping>().await;
+
+    // The actor is restarted according to the restart policy.
+    proxy.advance_time(Duration::from_secs(1)).await;
+    assert_msg!(proxy.recv().await, Started);
+
+    proxy.send(Ping(2)).await;
+    assert_msg_eq!(proxy.recv().await, Ping(2));
+}
+
+#[tokio::test]
+async fn drop() {
+    let mut proxy = sample().await;
+
+    proxy.inject_fault(Singleton, Fault::Drop(2));
+    for no in 1..=4 {
+        proxy.send(Ping(no)).await;
+    }
+
+    assert_msg_eq!(proxy.recv().await, Ping(3));
+    assert_msg_eq!(proxy.recv().await, Ping(4));
+}
+
+#[tokio::test(start_paused = true)]
+async fn delay() {
+    let mut proxy = sample().await;
+
+    proxy.inject_fault(Singleton, Fault::Delay(Duration::from_secs(5)));
+    proxy.send(Ping(1)).await;
+
+    proxy.advance_time(Duration::from_secs(4)).await;
+    assert!(proxy.try_recv().await.is_none());
+    proxy.advance_time(Duration::from_secs(1)).await;
+    assert_msg_eq!(proxy.try_recv().await.unwrap(), Ping(1));
+
+    proxy.clear_faults(Singleton);
+    proxy.send(Ping(2)).await;
+    proxy.sync().await;
+    assert_msg_eq!(proxy.try_recv().await.unwrap(), Ping(2));
+}
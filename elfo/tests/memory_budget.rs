@@ -0,0 +1,130 @@
+#![allow(missing_docs)]
+#![cfg(feature = "test-util")]
+
+use std::time::Duration;
+
+use serde::Deserialize;
+use toml::toml;
+
+use elfo::{
+    config::AnyConfig,
+    messages::{ActorStatusReport, Ping, SubscribeToActorStatuses},
+    prelude::*,
+    ActorStatusKind,
+};
+
+#[message]
+struct Dummy;
+
+#[message(ret = ())]
+struct Freeze;
+
+#[message]
+struct Report(usize);
+
+#[message(ret = ActorStatusKind)]
+struct GetStatus;
+
+fn testee() -> Blueprint {
+    ActorGroup::new().exec(move |mut ctx| async move {
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                Dummy => {}
+                (Freeze, token) => {
+                    ctx.respond(token, ());
+                    tokio::time::sleep(Duration::from_secs(60)).await
+                }
+                Report(usage) => ctx.report_memory_usage(usage),
+                (GetStatus, token) => ctx.respond(token, ctx.status_kind()),
+            });
+        }
+    })
+}
+
+#[tokio::test(start_paused = true)]
+async fn alarming() {
+    let config = AnyConfig::deserialize(toml! {
+        system.memory_budget.soft_limit = "1KiB"
+    })
+    .unwrap();
+
+    let proxy = elfo::test::proxy(testee(), config).await;
+    let mut statuses = proxy.subproxy().await;
+    statuses.send(SubscribeToActorStatuses::default()).await;
+    statuses
+        .expect::<ActorStatusReport>()
+        .matching(|r| r.status.kind().is_normal())
+        .await;
+    assert_eq!(proxy.request(GetStatus).await, ActorStatusKind::Normal);
+
+    // Statuses are updated by the supervisor, not by the actor itself.
+    proxy.send(Report(2000)).await;
+    statuses
+        .expect::<ActorStatusReport>()
+        .matching(|r| r.status.kind().is_alarming())
+        .await;
+    assert_eq!(proxy.request(GetStatus).await, ActorStatusKind::Alarming);
+
+    // Load shedding is disabled by default.
+    assert!(proxy.try_send(Dummy).is_ok());
+
+    proxy.send(Report(0)).await;
+    statuses
+        .expect::<ActorStatusReport>()
+        .matching(|r| r.status.kind().is_normal())
+        .await;
+    assert_eq!(proxy.request(GetStatus).await, ActorStatusKind::Normal);
+}
+
+#[tokio::test(start_paused = true)]
+async fn alarming_if_stuck() {
+    let config = AnyConfig::deserialize(toml! {
+        system.memory_budget.soft_limit = "1KiB"
+    })
+    .unwrap();
+
+    let proxy = elfo::test::proxy(testee(), config).await;
+    let mut statuses = proxy.subproxy().await;
+    statuses.send(SubscribeToActorStatuses::default()).await;
+    statuses
+        .expect::<ActorStatusReport>()
+        .matching(|r| r.status.kind().is_normal())
+        .await;
+
+    // The actor doesn't receive messages, but its mailbox grows.
+    proxy.request(Freeze).await;
+    for _ in 0..100 {
+        proxy.send(Dummy).await;
+    }
+
+    statuses
+        .expect::<ActorStatusReport>()
+        .matching(|r| r.status.kind().is_alarming())
+        .within(Duration::from_secs(1))
+        .await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn load_shedding() {
+    let capacity = 1000;
+    let config = AnyConfig::deserialize(toml! {
+        system.mailbox.capacity = capacity
+        system.memory_budget.soft_limit = "1KiB"
+        system.memory_budget.load_shedding = "RejectNew"
+    })
+    .unwrap();
+
+    let proxy = elfo::test::proxy(testee(), config).await;
+    proxy.request(Freeze).await;
+
+    let mut sent = 0;
+    while proxy.try_send(Dummy).is_ok() {
+        sent += 1;
+        assert!(sent < capacity, "should be rejected before the capacity");
+    }
+    assert!(sent > 0);
+
+    // Ensure that all sent messages are handled and the budget is released.
+    proxy.request(Ping::default()).await;
+    assert!(proxy.try_send(Dummy).is_ok());
+}
@@ -3,7 +3,7 @@
 use serde::Serialize;
 use static_assertions::*;
 
-use elfo::{message, set_protocol, Message, Request};
+use elfo::{message, set_protocol, AnyMessage, Message, Request};
 
 #[message]
 struct SimpleMessage {}
@@ -26,12 +26,36 @@ struct TransparentMessagePart(u32);
 assert_impl_all!(TransparentMessagePart: std::fmt::Debug, Serialize);
 assert_not_impl_all!(TransparentMessagePart: Message, Request);
 
+#[message(transparent)]
+#[derive(PartialEq)]
+struct TransparentMessage(String);
+
+#[message(transparent)]
+#[derive(PartialEq)]
+struct OtherTransparentMessage {
+    value: String,
+}
+
 #[message(protocol = "override")]
 struct SimpleMessageWithOverridedProtocol {}
 
 #[message(protocol = "override", ret = ())]
 struct SimpleRequestWithOverridedProtocol {}
 
+#[message(instances(Generic<u32>, Generic<Vec<String>>))]
+struct Generic<T> {
+    value: T,
+}
+assert_impl_all!(Generic<u32>: Message);
+assert_not_impl_all!(Generic<u64>: Message);
+
+#[message(name = "Take", ret = [T; N], instances(GenericRequest<u8, 2>))]
+struct GenericRequest<T, const N: usize>(Vec<T>);
+assert_impl_all!(GenericRequest<u8, 2>: Message, Request);
+
+#[message(part, transparent)]
+struct GenericPart<T>(T);
+
 mod one {
     use super::*;
 
@@ -67,6 +91,33 @@ fn transparent() {
     assert_eq!(format!("{:?}", TransparentMessagePart(42)), "42");
 }
 
+#[test]
+fn transparent_message() {
+    let message = TransparentMessage("hello".into());
+    assert_eq!(format!("{message:?}"), r#""hello""#);
+    assert_eq!(message.name(), "TransparentMessage");
+
+    // Serialized (and dumped) as the inner value without nesting.
+    let serialized = serde_json::to_string(&message).unwrap();
+    assert_eq!(serialized, r#""hello""#);
+    let other = OtherTransparentMessage {
+        value: "hello".into(),
+    };
+    assert_eq!(serde_json::to_string(&other).unwrap(), serialized);
+
+    let deserialized: TransparentMessage = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(deserialized, message);
+
+    // But still distinct types for routing and matching.
+    let any = AnyMessage::new(message);
+    assert!(any.is::<TransparentMessage>());
+    assert!(!any.is::<OtherTransparentMessage>());
+    assert_eq!(
+        serde_json::to_string(&any).unwrap(),
+        r#"["elfo","TransparentMessage","hello"]"#
+    );
+}
+
 #[test]
 fn name() {
     assert_eq!(SimpleMessage {}.name(), "SimpleMessage");
@@ -111,6 +162,21 @@ fn protocol() {
     assert_eq!(elfo::messages::Ping::default().protocol(), "elfo-core");
 }
 
+#[test]
+fn generic() {
+    assert_eq!(Generic { value: 0u32 }.name(), "Generic<u32>");
+    assert_eq!(
+        Generic::<Vec<String>> { value: vec![] }.name(),
+        "Generic<Vec<String>>"
+    );
+    assert_eq!(GenericRequest::<u8, 2>(vec![]).name(), "Take<u8, 2>");
+    assert_eq!(
+        <GenericRequest<u8, 2> as Request>::Wrapper::from([1, 2]).name(),
+        "Take<u8, 2>::Response"
+    );
+    assert_eq!(format!("{:?}", GenericPart(42)), "42");
+}
+
 #[test]
 fn uniqueness() {
     // Duplicate message definition.
@@ -0,0 +1,132 @@
+#![allow(missing_docs)]
+
+use elfo::message;
+
+#[message]
+struct Login {
+    user: String,
+    #[message(redact)]
+    password: String,
+    #[message(redact(hash))]
+    email: String,
+}
+
+#[message]
+enum Payment {
+    Card(#[message(redact)] String, u32),
+    Transfer {
+        #[message(redact(hash))]
+        account: u64,
+    },
+    Cash,
+}
+
+#[message(transparent)]
+struct Token(#[message(redact)] String);
+
+#[message(part)]
+struct Credentials<T> {
+    #[message(redact)]
+    secret: T,
+    kind: T,
+}
+
+#[message(version = 2)]
+struct Signup {
+    #[message(redact)]
+    password: String,
+    #[message(added = 2)]
+    referrer: Option<String>,
+}
+
+fn login() -> Login {
+    Login {
+        user: "alice".into(),
+        password: "qwerty".into(),
+        email: "alice@example.com".into(),
+    }
+}
+
+fn hashed<T: serde::Serialize>(value: T) -> String {
+    let hashed = elfo::dumping::RedactedHash(&value).to_string();
+    assert!(hashed.starts_with("<redacted:") && hashed.len() == 27);
+    hashed
+}
+
+#[test]
+fn debug() {
+    let email = hashed("alice@example.com");
+    assert_eq!(
+        format!("{:?}", login()),
+        format!(r#"Login {{ user: "alice", password: <redacted>, email: {email} }}"#)
+    );
+
+    assert_eq!(
+        format!("{:?}", Payment::Card("4242".into(), 12)),
+        "Card(<redacted>, 12)"
+    );
+    assert_eq!(
+        format!("{:?}", Payment::Transfer { account: 42 }),
+        format!("Transfer {{ account: {} }}", hashed(42u64))
+    );
+    assert_eq!(format!("{:?}", Payment::Cash), "Cash");
+
+    assert_eq!(format!("{:?}", Token("secret".into())), "<redacted>");
+
+    let credentials = Credentials { secret: 1, kind: 2 };
+    assert_eq!(
+        format!("{credentials:?}"),
+        "Credentials { secret: <redacted>, kind: 2 }"
+    );
+}
+
+#[test]
+fn serialize() {
+    // Sent across nodes as is.
+    let serialized = serde_json::to_string(&login()).unwrap();
+    assert_eq!(
+        serialized,
+        r#"{"user":"alice","password":"qwerty","email":"alice@example.com"}"#
+    );
+}
+
+#[cfg(feature = "unstable")]
+#[test]
+fn dump() {
+    use elfo::scope::{with_serde_mode, SerdeMode};
+
+    let dumped = with_serde_mode(SerdeMode::Dumping, || {
+        serde_json::to_string(&login()).unwrap()
+    });
+    let email = hashed("alice@example.com");
+    assert_eq!(
+        dumped,
+        format!(r#"{{"user":"alice","password":"<redacted>","email":"{email}"}}"#)
+    );
+
+    let dumped = with_serde_mode(SerdeMode::Dumping, || {
+        serde_json::to_string(&Payment::Card("4242".into(), 12)).unwrap()
+    });
+    assert_eq!(dumped, r#"{"Card":["<redacted>",12]}"#);
+
+    let signup = Signup {
+        password: "qwerty".into(),
+        referrer: None,
+    };
+    assert_eq!(
+        format!("{signup:?}"),
+        "Signup { password: <redacted>, referrer: None }"
+    );
+    let dumped = with_serde_mode(SerdeMode::Dumping, || {
+        serde_json::to_string(&signup).unwrap()
+    });
+    assert!(dumped.contains(r#""password":"<redacted>""#), "{dumped}");
+
+    // Configs contain resolved secrets.
+    let config = serde_json::from_str(r#"{"url":"http://a"}"#).unwrap();
+    let update = elfo::messages::UpdateConfig::new(config);
+    let dumped = with_serde_mode(SerdeMode::Dumping, || {
+        serde_json::to_string(&update).unwrap()
+    });
+    assert_eq!(dumped, r#"{"config":"<redacted>"}"#);
+}
@@ -0,0 +1,82 @@
+#![allow(missing_docs)]
+
+use serde_json::json;
+
+use elfo::{message, messages::Terminate, registry::Registry, AnyMessage, Message};
+
+#[message(schema)]
+struct Deposit {
+    account: String,
+    amount: u64,
+}
+
+#[message(protocol = "gateway")]
+enum Command {
+    Stop,
+    Rotate { angle: i32 },
+}
+
+fn registry() -> Registry {
+    Registry::new()
+        .allow_protocol("elfo")
+        .allow_protocol("gateway")
+}
+
+fn parse(protocol: &str, name: &str, body: serde_json::Value) -> Result<AnyMessage, String> {
+    let ty = registry().lookup(protocol, name).ok_or("unknown message")?;
+    ty.deserialize(body).map_err(|err| err.to_string())
+}
+
+#[test]
+fn lookup() {
+    let registry = registry();
+
+    let ty = registry.lookup("elfo", "Deposit").unwrap();
+    assert_eq!(ty.protocol(), "elfo");
+    assert_eq!(ty.name(), "Deposit");
+    assert_eq!(format!("{ty:?}"), "elfo/Deposit");
+    assert!(ty.schema().is_some());
+
+    let ty = registry.lookup("gateway", "Command").unwrap();
+    assert!(ty.schema().is_none());
+
+    assert!(registry.lookup("elfo", "Command").is_none());
+    assert!(registry.lookup("gateway", "Unknown").is_none());
+
+    assert!(registry
+        .all()
+        .any(|ty| ty.protocol() == "gateway" && ty.name() == "Command"));
+}
+
+#[test]
+fn deserialize() {
+    let body = json!({ "account": "alice", "amount": 42 });
+    let message = parse("elfo", "Deposit", body).unwrap();
+
+    let deposit = message.downcast::<Deposit>().unwrap();
+    assert_eq!(deposit.account, "alice");
+    assert_eq!(deposit.amount, 42);
+
+    let message = parse("gateway", "Command", json!({ "Rotate": { "angle": -90 } })).unwrap();
+    let command = message.downcast::<Command>().unwrap();
+    assert!(matches!(command, Command::Rotate { angle: -90 }));
+
+    let err = parse("elfo", "Deposit", json!({ "account": "bob" })).unwrap_err();
+    assert!(err.contains("amount"), "{err}");
+}
+
+#[test]
+fn disallowed() {
+    assert!(Registry::new().lookup("elfo", "Deposit").is_none());
+    assert!(registry().lookup("other", "Deposit").is_none());
+    assert!(registry().all().all(|ty| ty.protocol() != "elfo-core"));
+
+    // Internal messages are rejected even if explicitly allowed.
+    assert_eq!(Terminate::default().protocol(), "elfo-core");
+    let registry = registry().allow_protocol("elfo-core");
+    assert!(registry.lookup("elfo-core", "Terminate").is_none());
+    assert!(registry.lookup("elfo-core", "UpdateConfig").is_none());
+
+    let err = parse("elfo-core", "Terminate", json!({ "kind": "Closing" })).unwrap_err();
+    assert_eq!(err, "unknown message");
+}
@@ -0,0 +1,163 @@
+#![allow(missing_docs)]
+
+use elfo::{
+    message,
+    schema::{self, Fields, Shape},
+};
+
+/// Moves the robot.
+/// Coordinates are absolute.
+#[message(schema)]
+struct Move {
+    /// The target position.
+    position: (i32, i32),
+    speed: Option<u32>,
+}
+
+#[message(schema, ret = Result<Vec<u8>, String>)]
+struct Read(u64, u32);
+
+#[message(schema)]
+enum Command {
+    /// Stops immediately.
+    Stop,
+    Rotate {
+        angle: f32,
+    },
+}
+
+#[message(schema, instances(Wrapped<u8>, Wrapped<String>))]
+struct Wrapped<T>(T);
+
+#[message(schema)]
+#[serde(rename_all = "camelCase")]
+struct Renamed {
+    max_speed: u32,
+    #[serde(rename = "pos")]
+    position: (i32, i32),
+    #[serde(skip)]
+    #[allow(dead_code)]
+    cache: Vec<u8>,
+    #[serde(flatten)]
+    extra: std::collections::HashMap<String, String>,
+}
+
+#[message(schema)]
+#[serde(rename_all = "snake_case")]
+enum Mode {
+    FastForward,
+    #[serde(rename = "slow")]
+    SlowMotion,
+    #[serde(skip)]
+    #[allow(dead_code)]
+    Hidden,
+    #[serde(rename_all = "UPPERCASE")]
+    Custom {
+        frame_rate: u32,
+    },
+}
+
+#[message(schema, transparent)]
+struct Token(String);
+
+#[message]
+struct NoSchema;
+
+#[test]
+fn struct_schema() {
+    let schema = schema::lookup("elfo", "Move").unwrap();
+    assert_eq!(schema.name(), "Move");
+    assert_eq!(schema.docs(), "Moves the robot.\nCoordinates are absolute.");
+    assert_eq!(schema.response(), None);
+
+    let Shape::Struct(Fields::Named(fields)) = schema.shape() else {
+        panic!("unexpected shape: {:?}", schema.shape());
+    };
+    assert_eq!(fields.len(), 2);
+    assert_eq!(fields[0].name(), "position");
+    assert_eq!(fields[0].ty(), "(i32, i32)");
+    assert_eq!(fields[0].docs(), "The target position.");
+    assert_eq!(fields[1].name(), "speed");
+    assert_eq!(fields[1].ty(), "Option<u32>");
+    assert_eq!(fields[1].docs(), "");
+}
+
+#[test]
+fn request_schema() {
+    let schema = schema::lookup("elfo", "Read").unwrap();
+    assert_eq!(schema.response(), Some("Result<Vec<u8>, String>"));
+
+    let Shape::Struct(Fields::Unnamed(fields)) = schema.shape() else {
+        panic!("unexpected shape: {:?}", schema.shape());
+    };
+    let names = fields.iter().map(|f| f.name()).collect::<Vec<_>>();
+    assert_eq!(names, ["0", "1"]);
+}
+
+#[test]
+fn enum_schema() {
+    let schema = schema::lookup("elfo", "Command").unwrap();
+
+    let Shape::Enum(variants) = schema.shape() else {
+        panic!("unexpected shape: {:?}", schema.shape());
+    };
+    assert_eq!(variants.len(), 2);
+    assert_eq!(variants[0].name(), "Stop");
+    assert_eq!(variants[0].docs(), "Stops immediately.");
+    assert!(matches!(variants[0].fields(), Fields::Unit));
+    assert_eq!(variants[1].name(), "Rotate");
+    assert!(matches!(variants[1].fields(), Fields::Named(f) if f[0].ty() == "f32"));
+}
+
+#[test]
+fn serde_attrs() {
+    let schema = schema::lookup("elfo", "Renamed").unwrap();
+    let Shape::Struct(Fields::Named(fields)) = schema.shape() else {
+        panic!("unexpected shape: {:?}", schema.shape());
+    };
+    let names = fields.iter().map(|f| f.name()).collect::<Vec<_>>();
+    assert_eq!(names, ["maxSpeed", "pos", "extra"]);
+    assert!(!fields[0].is_flattened());
+    assert!(fields[2].is_flattened());
+
+    let schema = schema::lookup("elfo", "Mode").unwrap();
+    let Shape::Enum(variants) = schema.shape() else {
+        panic!("unexpected shape: {:?}", schema.shape());
+    };
+    let names = variants.iter().map(|v| v.name()).collect::<Vec<_>>();
+    assert_eq!(names, ["fast_forward", "slow", "custom"]);
+    assert!(matches!(variants[2].fields(), Fields::Named(f) if f[0].name() == "FRAME_RATE"));
+
+    let schema = schema::lookup("elfo", "Token").unwrap();
+    assert!(matches!(schema.shape(), Shape::Transparent("String")));
+}
+
+#[test]
+fn generic_schema() {
+    assert!(schema::lookup("elfo", "Wrapped<u8>").is_some());
+    assert!(schema::lookup("elfo", "Wrapped<String>").is_some());
+}
+
+#[test]
+fn registry() {
+    assert!(schema::lookup("elfo", "NoSchema").is_none());
+
+    let mut names = schema::all().map(|s| s.name()).collect::<Vec<_>>();
+    names.sort_unstable();
+    assert_eq!(
+        names,
+        [
+            "Command",
+            "Mode",
+            "Move",
+            "Read",
+            "Renamed",
+            "Token",
+            "Wrapped<String>",
+            "Wrapped<u8>"
+        ]
+    );
+
+    let json = serde_json::to_value(schema::lookup("elfo", "Command").unwrap()).unwrap();
+    assert_eq!(json["shape"]["Enum"][1]["name"], "Rotate");
+}
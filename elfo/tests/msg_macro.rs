@@ -33,6 +33,14 @@ enum ReqEnum {
     B,
 }
 
+#[message(instances(Generic<u32>, Generic<String>))]
+enum Generic<T> {
+    Value(T),
+    Empty,
+}
+#[message(ret = T, instances(ReqGeneric<u32>))]
+struct ReqGeneric<T>(T);
+
 #[message]
 #[derive(PartialEq)]
 enum Type {
@@ -40,6 +48,7 @@ enum Type {
     Tuple(u32),
     Struct(u32),
     Enum(u32),
+    Generic(u32),
 }
 
 fn sample() -> Blueprint {
@@ -93,6 +102,12 @@ fn sample() -> Blueprint {
                     })
                     .await
                     .unwrap(),
+
+                // Generic.
+                Generic::<u32>::Value(a) => ctx.send(Type::Generic(a)).await.unwrap(),
+                Generic::<u32>::Empty => ctx.send(Type::Generic(0)).await.unwrap(),
+                Generic::<String> => ctx.send(Type::Generic(1)).await.unwrap(),
+                (ReqGeneric::<u32>(a), token) => ctx.respond(token, a + 1),
             });
         }
     })
@@ -140,3 +155,15 @@ async fn it_handles_enum() {
     proxy.send(Enum::A { a: 1 }).await;
     assert_msg_eq!(proxy.recv().await, Type::Enum(4));
 }
+
+#[tokio::test]
+async fn it_handles_generic() {
+    let mut proxy = elfo::test::proxy(sample(), AnyConfig::default()).await;
+    proxy.send(Generic::Value(42u32)).await;
+    assert_msg_eq!(proxy.recv().await, Type::Generic(42));
+    proxy.send(Generic::<u32>::Empty).await;
+    assert_msg_eq!(proxy.recv().await, Type::Generic(0));
+    proxy.send(Generic::Value(String::from("42"))).await;
+    assert_msg_eq!(proxy.recv().await, Type::Generic(1));
+    assert_eq!(proxy.request(ReqGeneric(42u32)).await, 43);
+}
@@ -0,0 +1,164 @@
+#![allow(missing_docs)]
+#![cfg(all(feature = "test-util", feature = "otlp"))]
+
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+    sync::mpsc,
+    time::timeout,
+};
+
+use elfo::{_priv::do_start, config::AnyConfig, prelude::*, Addr, Topology};
+
+#[message(ret = ())]
+struct Question;
+
+fn echo() -> Blueprint {
+    ActorGroup::new().exec(|mut ctx| async move {
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                (Question, token) => ctx.respond(token, ()),
+            });
+        }
+    })
+}
+
+// Asks `echo` while handling the question, so its span is the parent one.
+fn relay(echo_addr: Addr) -> Blueprint {
+    ActorGroup::new().exec(move |mut ctx| async move {
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                (Question, token) => {
+                    ctx.request_to(echo_addr, Question).resolve().await.unwrap();
+                    ctx.respond(token, ());
+                }
+            });
+        }
+    })
+}
+
+// Accepts OTLP/HTTP requests and passes their bodies to the channel.
+async fn collector(listener: TcpListener, tx: mpsc::UnboundedSender<String>) {
+    loop {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut request = Vec::new();
+        let mut buffer = [0; 4096];
+
+        let body = loop {
+            let n = stream.read(&mut buffer).await.unwrap();
+            assert_ne!(n, 0, "unexpected EOF");
+            request.extend_from_slice(&buffer[..n]);
+
+            let text = String::from_utf8_lossy(&request);
+            let Some((head, body)) = text.split_once("\r\n\r\n") else {
+                continue;
+            };
+
+            let length = head
+                .lines()
+                .find_map(|line| {
+                    line.to_lowercase()
+                        .strip_prefix("content-length: ")?
+                        .parse()
+                        .ok()
+                })
+                .unwrap_or(0);
+
+            if body.len() >= length {
+                break body.to_string();
+            }
+        };
+
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+            .await
+            .unwrap();
+
+        let _ = tx.send(body);
+    }
+}
+
+#[tokio::test]
+async fn exports_spans() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("http://{}/v1/traces", listener.local_addr().unwrap());
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    tokio::spawn(collector(listener, tx));
+
+    let config: toml::Table = toml::from_str(&format!(
+        r#"
+        [system.otlp]
+        endpoint = "{endpoint}"
+        service_name = "test"
+        export_interval = "10ms"
+        "#
+    ))
+    .unwrap();
+
+    let topology = Topology::empty();
+    let configurers = topology.local("system.configurers").entrypoint();
+    let otlp = topology.local("system.otlp");
+    let echo_group = topology.local("echo");
+    let echo_addr = echo_group.addr();
+    let relay_group = topology.local("relay");
+    let relay_addr = relay_group.addr();
+
+    configurers.mount(elfo::batteries::configurer::fixture(
+        &topology,
+        AnyConfig::deserialize(config).unwrap(),
+    ));
+    otlp.mount(elfo::batteries::otlp::new());
+    echo_group.mount(echo());
+    relay_group.mount(relay(echo_addr));
+
+    let (body, spans) = do_start(topology, false, |ctx, _| async move {
+        let mut spans = Vec::new();
+
+        // Spans are collected only after the exporter is started,
+        // so repeat requests until the span is exported.
+        loop {
+            ctx.request_to(relay_addr, Question)
+                .resolve()
+                .await
+                .unwrap();
+
+            while let Ok(Some(body)) = timeout(Duration::from_millis(50), rx.recv()).await {
+                let json: Value = serde_json::from_str(&body).unwrap();
+                spans.extend(
+                    json["resourceSpans"][0]["scopeSpans"][0]["spans"]
+                        .as_array()
+                        .unwrap()
+                        .iter()
+                        .cloned(),
+                );
+
+                if body.contains(r#""name":"echo Question""#) {
+                    return (body, spans);
+                }
+            }
+        }
+    })
+    .await
+    .expect("cannot start");
+
+    assert!(body.contains(r#""stringValue":"test""#));
+    assert!(body.contains(r#""traceId":"0000000000000000"#));
+    assert!(body.contains(r#""kind":2"#));
+    assert!(body.contains(r#""name":"respond""#));
+
+    // The echo's span is a child of the relay's one.
+    let echo_span = spans
+        .iter()
+        .rfind(|s| s["name"] == "echo Question")
+        .unwrap();
+    let relay_span = spans
+        .iter()
+        .find(|s| s["spanId"] == echo_span["parentSpanId"])
+        .expect("no parent span");
+    assert_eq!(relay_span["name"], "relay Question");
+    assert_eq!(relay_span["traceId"], echo_span["traceId"]);
+}
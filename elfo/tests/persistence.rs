@@ -0,0 +1,161 @@
+#![allow(missing_docs)]
+#![cfg(feature = "test-util")]
+
+use std::{path::Path, sync::Arc, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use toml::toml;
+
+use elfo::{
+    config::AnyConfig,
+    persistence::{EventLog, EventSourced, FileJournal, Journal, Snapshot},
+    prelude::*,
+    RestartParams, RestartPolicy,
+};
+
+#[derive(Default, Serialize, Deserialize)]
+struct Counter(u64);
+
+#[derive(Serialize, Deserialize)]
+struct Incremented(u64);
+
+impl EventSourced for Counter {
+    type Event = Incremented;
+
+    fn apply(&mut self, event: &Incremented) {
+        self.0 += event.0;
+    }
+}
+
+impl Snapshot for Counter {}
+
+#[message]
+struct Increment(u64);
+
+#[message]
+struct Crash;
+
+#[message]
+#[derive(PartialEq, Eq)]
+struct Started(u64);
+
+fn journal(dir: &Path) -> Arc<dyn Journal> {
+    Arc::new(FileJournal::new(dir).unwrap().sync(false))
+}
+
+fn restart_policy() -> RestartPolicy {
+    RestartPolicy::on_failure(RestartParams::new(
+        Duration::from_millis(1),
+        Duration::from_millis(1),
+    ))
+}
+
+async fn serve(mut ctx: Context, mut counter: EventLog<Counter>) {
+    ctx.send(Started(counter.state().0)).await.unwrap();
+
+    while let Some(envelope) = ctx.recv().await {
+        msg!(match envelope {
+            Increment(n) => counter.persist(Incremented(n)).await.unwrap(),
+            Crash => panic!("boom!"),
+        });
+    }
+}
+
+fn counter(dir: &Path, snapshots: bool) -> Blueprint {
+    let journal = journal(dir);
+
+    ActorGroup::new()
+        .restart_policy(restart_policy())
+        .exec(move |ctx| {
+            let journal = journal.clone();
+
+            async move {
+                let counter = if snapshots {
+                    EventLog::<Counter>::recover_with_snapshots(journal).await
+                } else {
+                    EventLog::<Counter>::recover(journal).await
+                }
+                .unwrap();
+
+                serve(ctx, counter).await;
+            }
+        })
+}
+
+fn supervised_counter(dir: &Path) -> Blueprint {
+    ActorGroup::new()
+        .restart_policy(restart_policy())
+        .event_sourced::<Counter>(journal(dir))
+        .exec(|mut ctx| async move {
+            let counter = ctx.take_event_log::<Counter>().unwrap();
+            serve(ctx, counter).await;
+        })
+}
+
+fn count_files(dir: &Path, extension: &str) -> usize {
+    std::fs::read_dir(dir)
+        .unwrap()
+        .filter(|entry| entry.as_ref().unwrap().path().extension().unwrap() == extension)
+        .count()
+}
+
+#[tokio::test]
+async fn recovers_after_restart() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut proxy = elfo::test::proxy(counter(dir.path(), false), AnyConfig::default()).await;
+    // Recovery reads files on the blocking pool, which is slow under load.
+    proxy.set_recv_timeout(Duration::from_secs(5));
+    assert_msg_eq!(proxy.recv().await, Started(0));
+
+    proxy.send(Increment(2)).await;
+    proxy.send(Increment(3)).await;
+    proxy.send(Crash).await;
+    assert_msg_eq!(proxy.recv().await, Started(5));
+
+    proxy.send(Increment(1)).await;
+    proxy.send(Crash).await;
+    assert_msg_eq!(proxy.recv().await, Started(6));
+    assert_eq!(count_files(dir.path(), "snapshot"), 0);
+}
+
+#[tokio::test]
+async fn recovers_from_snapshot() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = AnyConfig::deserialize(toml! {
+        system.persistence.snapshot_interval = 2
+    })
+    .unwrap();
+
+    let mut proxy = elfo::test::proxy(counter(dir.path(), true), config).await;
+    proxy.set_recv_timeout(Duration::from_secs(5));
+    assert_msg_eq!(proxy.recv().await, Started(0));
+
+    for n in 1..=5 {
+        proxy.send(Increment(n)).await;
+    }
+    proxy.send(Crash).await;
+    assert_msg_eq!(proxy.recv().await, Started(15));
+    assert_eq!(count_files(dir.path(), "snapshot"), 1);
+
+    proxy.send(Increment(1)).await;
+    proxy.send(Crash).await;
+    assert_msg_eq!(proxy.recv().await, Started(16));
+}
+
+#[tokio::test]
+async fn recovers_by_supervisor() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut proxy = elfo::test::proxy(supervised_counter(dir.path()), AnyConfig::default()).await;
+    proxy.set_recv_timeout(Duration::from_secs(5));
+    assert_msg_eq!(proxy.recv().await, Started(0));
+
+    proxy.send(Increment(2)).await;
+    proxy.send(Increment(3)).await;
+    proxy.send(Crash).await;
+    assert_msg_eq!(proxy.recv().await, Started(5));
+
+    // Logs written by the exec itself are recovered the same way.
+    let mut proxy = elfo::test::proxy(counter(dir.path(), false), AnyConfig::default()).await;
+    proxy.set_recv_timeout(Duration::from_secs(5));
+    assert_msg_eq!(proxy.recv().await, Started(5));
+}
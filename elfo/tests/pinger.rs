@@ -0,0 +1,146 @@
+#![allow(missing_docs)]
+#![cfg(all(feature = "test-util", feature = "full"))]
+
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use serde::Deserialize;
+
+use elfo::{
+    _priv::do_start, batteries, config::AnyConfig, prelude::*, ActorStatus, RestartParams,
+    RestartPolicy, Topology,
+};
+
+#[message(ret = ())]
+struct CheckHealth;
+
+static CHECKED: AtomicUsize = AtomicUsize::new(0);
+
+fn checked(respond: bool) -> Blueprint {
+    ActorGroup::new().exec(move |mut ctx| async move {
+        let mut tokens = Vec::new();
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                (CheckHealth, token) if respond => {
+                    CHECKED.fetch_add(1, Ordering::SeqCst);
+                    ctx.respond(token, ());
+                }
+                // Never respond, but keep the request alive.
+                (CheckHealth, token) => tokens.push(token),
+                _ => {}
+            });
+        }
+    })
+}
+
+fn pinger_status(topology: &Topology) -> Option<ActorStatus> {
+    let group = topology
+        .inspect()
+        .into_iter()
+        .find(|g| g.name == "system.pingers")?;
+    Some(group.actors.first()?.status.clone())
+}
+
+#[tokio::test]
+async fn probes() {
+    let config = toml::toml! {
+        [system.pingers]
+        ping_interval = "10ms"
+        warn_threshold = "1s"
+        exclude = ["excluded"]
+        groups.sick.warn_threshold = "20ms"
+    };
+
+    let topology = Topology::empty();
+    let configurers = topology.local("system.configurers").entrypoint();
+    let pingers = topology.local("system.pingers");
+
+    configurers.mount(batteries::configurer::fixture(
+        &topology,
+        AnyConfig::deserialize(config).unwrap(),
+    ));
+    pingers.mount(
+        batteries::pinger::builder(&topology)
+            .probe("healthy", CheckHealth)
+            .probe("sick", CheckHealth)
+            .probe("excluded", CheckHealth)
+            .build(),
+    );
+    topology.local("healthy").mount(checked(true));
+    topology.local("sick").mount(checked(false));
+    topology.local("excluded").mount(checked(false));
+
+    let inspected = topology.clone();
+    do_start(topology, false, |_, _| async move {
+        let status = loop {
+            match pinger_status(&inspected) {
+                Some(status) if status.kind().is_alarming() => break status,
+                _ => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        };
+
+        // Only `sick` is reported, `excluded` isn't pinged at all.
+        assert_eq!(status.details(), Some("unresponsive: sick"));
+        assert!(CHECKED.load(Ordering::SeqCst) > 0);
+    })
+    .await
+    .expect("cannot start");
+}
+
+#[tokio::test]
+async fn restart_unresponsive() {
+    let config = toml::toml! {
+        [system.pingers]
+        ping_interval = "10ms"
+        warn_threshold = "20ms"
+        include = ["stuck"]
+        groups.stuck.restart_after_missed = 2
+    };
+
+    let topology = Topology::empty();
+    let configurers = topology.local("system.configurers").entrypoint();
+    let pingers = topology.local("system.pingers");
+
+    configurers.mount(batteries::configurer::fixture(
+        &topology,
+        AnyConfig::deserialize(config).unwrap(),
+    ));
+    pingers.mount(batteries::pinger::new(&topology));
+    topology.local("stuck").mount(
+        ActorGroup::new()
+            .restart_policy(RestartPolicy::on_failure(RestartParams::new(
+                Duration::from_millis(1),
+                Duration::from_millis(1),
+            )))
+            .exec(|mut ctx| async move {
+                // Get stuck only on the first start.
+                if !ctx.start_info().cause.is_restarted() {
+                    futures::future::pending::<()>().await;
+                }
+                while ctx.recv().await.is_some() {}
+            }),
+    );
+
+    let inspected = topology.clone();
+    do_start(topology, false, |_, _| async move {
+        let restart = loop {
+            let group = inspected.inspect().into_iter().find(|g| g.name == "stuck");
+            match group.and_then(|g| g.restarts.first().cloned()) {
+                Some(restart) => break restart,
+                None => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        };
+
+        assert!(restart.status.kind().is_failed());
+        assert_eq!(restart.status.details(), Some("aborted as unresponsive"));
+
+        // The restarted actor responds to pings.
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let group = inspected.inspect().into_iter().find(|g| g.name == "stuck");
+        assert_eq!(group.unwrap().restarts.len(), 1);
+    })
+    .await
+    .expect("cannot start");
+}
@@ -0,0 +1,140 @@
+#![allow(missing_docs)]
+#![cfg(feature = "test-util")]
+
+use elfo::{config::AnyConfig, prelude::*, protocol_enum, AnyMessage, Message};
+
+#[message]
+#[derive(PartialEq)]
+struct Start;
+
+#[message]
+#[derive(PartialEq)]
+struct Stop {
+    force: bool,
+}
+
+#[message]
+#[derive(PartialEq)]
+struct Unrelated;
+
+#[message]
+#[derive(PartialEq)]
+struct Routed(String);
+
+protocol_enum! {
+    /// Commands accepted by the gateway.
+    enum Command {
+        Start,
+        Halt(Stop),
+    }
+}
+
+#[tokio::test]
+async fn routing() {
+    let gateway = ActorGroup::new().exec(|mut ctx| async move {
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                Start => ctx.send(Routed("start".into())).await.unwrap(),
+                command @ Command => {
+                    let routed = format!("{command:?}");
+                    ctx.send(command).await.unwrap();
+                    ctx.send(Routed(routed)).await.unwrap();
+                }
+                _ => ctx.send(Routed("other".into())).await.unwrap(),
+            });
+        }
+    });
+
+    let mut proxy = elfo::test::proxy(gateway, AnyConfig::default()).await;
+
+    // Arms are checked in order.
+    proxy.send(Start).await;
+    assert_msg_eq!(proxy.recv().await, Routed("start".into()));
+
+    // The inner message is sent.
+    proxy.send(Stop { force: true }).await;
+    assert_msg_eq!(proxy.recv().await, Stop { force: true });
+    assert_msg_eq!(
+        proxy.recv().await,
+        Routed("Halt(Stop { force: true })".into())
+    );
+
+    proxy.send(Unrelated).await;
+    assert_msg_eq!(proxy.recv().await, Routed("other".into()));
+
+    // Enums can be expected.
+    proxy.send(Start).await;
+    proxy.send(Stop { force: true }).await;
+    let command = proxy
+        .expect::<Command>()
+        .matching(|command| matches!(command, Command::Halt(_)))
+        .await;
+    assert!(matches!(command, Command::Halt(Stop { force: true })));
+    assert_msg_eq!(proxy.recv().await, Routed("start".into()));
+}
+
+#[test]
+fn conversions() {
+    let command = Command::from(Stop { force: false });
+    assert_eq!(command.name(), "Stop");
+
+    let any = AnyMessage::new(command);
+    assert!(any.is::<Stop>());
+    assert!(any.is::<Command>());
+    assert!(any.downcast_ref::<Stop>().is_some());
+    // Enums cannot be borrowed.
+    assert!(any.downcast_ref::<Command>().is_none());
+    assert!(matches!(
+        any.downcast::<Command>(),
+        Ok(Command::Halt(Stop { force: false }))
+    ));
+
+    assert!(AnyMessage::new(Unrelated).downcast::<Command>().is_err());
+}
+
+#[test]
+fn serde() {
+    let command = Command::from(Start);
+    let serialized = serde_json::to_string(&command).unwrap();
+    assert_eq!(serialized, r#"["elfo","Start",null]"#);
+
+    let command: Command = serde_json::from_str(&serialized).unwrap();
+    assert!(matches!(command, Command::Start(Start)));
+
+    let unrelated = serde_json::to_string(&AnyMessage::new(Unrelated)).unwrap();
+    let err = serde_json::from_str::<Command>(&unrelated).unwrap_err();
+    assert!(err
+        .to_string()
+        .starts_with("unexpected message: elfo/Unrelated"));
+}
+
+#[tokio::test]
+async fn exhaustive() {
+    let gateway = ActorGroup::new().exec(|mut ctx| async move {
+        while let Some(envelope) = ctx.recv().await {
+            let routed = msg!(
+                #[exhaustive(Command)]
+                match envelope {
+                    Start => "start",
+                    Stop { force: true } => "forced stop",
+                    Stop { .. } => "stop",
+                    // Messages outside the protocol are allowed to fall back.
+                    _ => "other",
+                }
+            );
+
+            ctx.send(Routed(routed.into())).await.unwrap();
+        }
+    });
+
+    let mut proxy = elfo::test::proxy(gateway, AnyConfig::default()).await;
+
+    proxy.send(Start).await;
+    assert_msg_eq!(proxy.recv().await, Routed("start".into()));
+    proxy.send(Stop { force: true }).await;
+    assert_msg_eq!(proxy.recv().await, Routed("forced stop".into()));
+    proxy.send(Stop { force: false }).await;
+    assert_msg_eq!(proxy.recv().await, Routed("stop".into()));
+    proxy.send(Unrelated).await;
+    assert_msg_eq!(proxy.recv().await, Routed("other".into()));
+}
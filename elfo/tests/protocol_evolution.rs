@@ -320,3 +320,78 @@ fn enum_untagged() {
     ensure_parsable(SE0 { a: 42 }, SE1 { a: A::Num(42) });
     ensure_parsable(SE1 { a: A::Num(42) }, SE0 { a: 42 });
 }
+
+#[test]
+fn versioned_added_field() {
+    #[message(version = 2)]
+    #[derive(PartialEq, Eq)]
+    struct V1 {
+        a: u32,
+        #[message(added = 2, default = 7)]
+        b: u32,
+    }
+
+    #[message(version = 2)]
+    #[derive(PartialEq, Eq)]
+    struct V2 {
+        a: u32,
+    }
+
+    ensure_parsable(S0 { a: 42 }, V1 { a: 42, b: 7 });
+    ensure_parsable(V1 { a: 42, b: 1 }, S0 { a: 42 });
+    ensure_parsable(V1 { a: 42, b: 1 }, V1 { a: 42, b: 1 });
+    // The field is required since the second version.
+    ensure_unparsable(V2 { a: 42 }, V1 { a: 42, b: 7 });
+}
+
+#[test]
+fn versioned_renamed_field() {
+    #[message(version = 2)]
+    #[derive(PartialEq, Eq)]
+    struct V3 {
+        #[message(renamed(from = "a", since = 2))]
+        b: u32,
+    }
+
+    ensure_parsable(S0 { a: 42 }, V3 { b: 42 });
+    ensure_parsable(V3 { b: 42 }, S0 { a: 42 });
+    ensure_parsable(V3 { b: 42 }, V3 { b: 42 });
+}
+
+#[test]
+fn versioned_removed_field() {
+    #[message(version = 3, removed(a: u32 = 5))]
+    #[derive(PartialEq, Eq)]
+    struct V4 {
+        #[message(added = 3)]
+        b: u32,
+    }
+
+    ensure_parsable(S0 { a: 42 }, V4 { b: 0 });
+    ensure_parsable(V4 { b: 1 }, S0 { a: 5 });
+    ensure_parsable(V4 { b: 1 }, V4 { b: 1 });
+}
+
+#[test]
+fn versioned_serde_attrs() {
+    #[message(version = 2)]
+    #[derive(PartialEq, Eq)]
+    #[serde(rename_all = "UPPERCASE")]
+    struct V5 {
+        #[serde(rename = "a")]
+        x: u32,
+        #[message(added = 2)]
+        b: u32,
+    }
+
+    #[message]
+    #[derive(PartialEq, Eq)]
+    struct S10 {
+        a: u32,
+        #[serde(rename = "B")]
+        b: u32,
+    }
+
+    ensure_parsable(S0 { a: 42 }, V5 { x: 42, b: 0 });
+    ensure_parsable(V5 { x: 42, b: 1 }, S10 { a: 42, b: 1 });
+}
@@ -0,0 +1,43 @@
+#![allow(missing_docs)]
+#![cfg(feature = "test-util")]
+
+use elfo::{config::AnyConfig, prelude::*, Addr, Local};
+
+#[message(ret = u32)]
+struct Compute(u32);
+
+#[message]
+struct SetWorker(Local<Addr>);
+
+#[tokio::test]
+async fn it_works() {
+    let group = ActorGroup::new().exec(|mut ctx| async move {
+        let mut worker = Addr::NULL;
+
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                SetWorker(addr) => worker = *addr,
+                (msg @ Compute, token) => {
+                    ctx.forward_to(worker, msg, token).await.unwrap();
+                }
+            });
+        }
+    });
+
+    let proxy = elfo::test::proxy(group, AnyConfig::default()).await;
+    let mut worker = proxy.subproxy().await;
+    proxy.send(SetWorker(worker.addr().into())).await;
+
+    for n in 0..3 {
+        let (response, ()) = tokio::join!(proxy.request(Compute(n)), async {
+            // The worker responds directly to the original requester.
+            let envelope = worker.recv().await;
+            assert_eq!(envelope.sender(), proxy.addr());
+            msg!(match envelope {
+                (Compute(n), token) => worker.respond(token, n * 2),
+            });
+        });
+
+        assert_eq!(response, n * 2);
+    }
+}
@@ -0,0 +1,73 @@
+#![allow(missing_docs)]
+#![cfg(feature = "test-util")]
+
+use std::time::Duration;
+
+use elfo::{config::AnyConfig, prelude::*};
+
+#[message(ret = u32)]
+#[derive(Hash, PartialEq, Eq)]
+struct Get(u32);
+
+#[message(ret = u32)]
+struct GetBy {
+    key: u32,
+    comment: String,
+}
+
+#[message(ret = u32)]
+struct Uncached(u32);
+
+#[tokio::test(start_paused = true)]
+async fn it_works() {
+    let group = ActorGroup::new()
+        .cache_responses::<Get>(Duration::from_secs(1))
+        .cache_responses_by(Duration::from_secs(1), |req: &GetBy| req.key)
+        .exec(|mut ctx| async move {
+            // Counts requests that reached the actor.
+            let mut counter = 0;
+
+            while let Some(envelope) = ctx.recv().await {
+                msg!(match envelope {
+                    (Get(n), token) => {
+                        counter += 1;
+                        ctx.respond(token, n * 100 + counter);
+                    }
+                    (GetBy { key, comment }, token) => {
+                        assert!(!comment.is_empty());
+                        counter += 1;
+                        ctx.respond(token, key * 100 + counter);
+                    }
+                    (Uncached(n), token) => {
+                        counter += 1;
+                        ctx.respond(token, n * 100 + counter);
+                    }
+                });
+            }
+        });
+
+    let proxy = elfo::test::proxy(group, AnyConfig::default()).await;
+
+    // Keyed by content.
+    assert_eq!(proxy.request(Get(1)).await, 101);
+    assert_eq!(proxy.request(Get(1)).await, 101);
+    assert_eq!(proxy.request(Get(2)).await, 202);
+    assert_eq!(proxy.request(Get(1)).await, 101);
+
+    // Expired.
+    tokio::time::sleep(Duration::from_millis(1001)).await;
+    assert_eq!(proxy.request(Get(1)).await, 103);
+    assert_eq!(proxy.request(Get(1)).await, 103);
+
+    // Keyed by the explicit key.
+    let get_by = |comment: &str| GetBy {
+        key: 5,
+        comment: comment.into(),
+    };
+    assert_eq!(proxy.request(get_by("a")).await, 504);
+    assert_eq!(proxy.request(get_by("b")).await, 504);
+
+    // Not cached at all.
+    assert_eq!(proxy.request(Uncached(1)).await, 105);
+    assert_eq!(proxy.request(Uncached(1)).await, 106);
+}
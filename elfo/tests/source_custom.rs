@@ -0,0 +1,91 @@
+#![allow(missing_docs)]
+#![cfg(feature = "test-util")]
+
+use std::{
+    pin::Pin,
+    task::{self, Poll},
+};
+
+use elfo::{
+    config::AnyConfig,
+    prelude::*,
+    source::{Custom, Source},
+};
+use tokio::sync::mpsc;
+
+#[message]
+#[derive(PartialEq, Eq)]
+struct Produced(u32);
+
+struct Receiver {
+    rx: mpsc::UnboundedReceiver<u32>,
+    multiplier: u32,
+}
+
+impl Source for Receiver {
+    type Item = Produced;
+
+    fn poll_recv(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Option<Produced>> {
+        let this = self.get_mut();
+        let multiplier = this.multiplier;
+        this.rx
+            .poll_recv(cx)
+            .map(|value| value.map(|v| Produced(v * multiplier)))
+    }
+}
+
+#[tokio::test]
+async fn it_works() {
+    #[message]
+    struct SetMultiplier(u32);
+
+    #[message(ret = bool)]
+    struct IsTerminated;
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    let rx = parking_lot::Mutex::new(Some(rx));
+
+    let group = ActorGroup::new().exec(move |mut ctx| {
+        let rx = rx.lock().take().unwrap();
+
+        async move {
+            let receiver = ctx.attach(Custom::new(Receiver { rx, multiplier: 1 }));
+
+            while let Some(envelope) = ctx.recv().await {
+                msg!(match envelope {
+                    SetMultiplier(multiplier) => {
+                        let prev = receiver
+                            .with(|r| std::mem::replace(&mut r.get_mut().multiplier, multiplier));
+                        assert!(prev.is_some());
+                    }
+                    (IsTerminated, token) => {
+                        ctx.respond(token, receiver.is_terminated());
+                    }
+                    msg @ Produced => {
+                        ctx.send(msg).await.unwrap();
+                    }
+                });
+            }
+        }
+    });
+
+    let mut proxy = elfo::test::proxy(group, AnyConfig::default()).await;
+    assert!(proxy.try_recv().await.is_none());
+
+    tx.send(1).unwrap();
+    tx.send(2).unwrap();
+    assert_msg_eq!(proxy.recv().await, Produced(1));
+    assert_msg_eq!(proxy.recv().await, Produced(2));
+
+    proxy.send(SetMultiplier(10)).await;
+    proxy.sync().await;
+    tx.send(3).unwrap();
+    assert_msg_eq!(proxy.recv().await, Produced(30));
+    assert!(!proxy.request(IsTerminated).await);
+
+    // The source is terminated once the channel is closed.
+    drop(tx);
+    proxy.sync().await;
+    assert!(proxy.request(IsTerminated).await);
+    assert!(proxy.try_recv().await.is_none());
+}
@@ -207,6 +207,68 @@ async fn terminate() {
     assert!(proxy.try_recv().await.is_none());
 }
 
+#[tokio::test(start_paused = true)]
+async fn keyed() {
+    #[message]
+    struct Start {
+        key: u32,
+        period: u64,
+    }
+
+    #[message(ret = bool)]
+    struct Detach(u32);
+
+    #[message]
+    #[derive(PartialEq, Eq)]
+    struct Produced(u32, u64);
+
+    let group = ActorGroup::new().exec(|mut ctx| async move {
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                Start { key, period } => {
+                    let stream = futures::stream::unfold(0, move |no| async move {
+                        time::sleep(Duration::from_millis(period)).await;
+                        Some((Produced(key, period), no + 1))
+                    });
+
+                    let handle = ctx.attach_keyed(key, Stream::from_futures03(stream));
+                    assert!(!handle.is_terminated());
+                }
+                (Detach(key), token) => {
+                    let detached = ctx.detach(&key);
+                    ctx.respond(token, detached);
+                }
+                msg @ Produced => {
+                    ctx.send(msg).await.unwrap();
+                }
+            });
+        }
+    });
+
+    let mut proxy = elfo::test::proxy(group, AnyConfig::default()).await;
+    assert!(proxy.try_recv().await.is_none());
+
+    proxy.send(Start { key: 1, period: 11 }).await;
+    proxy.send(Start { key: 2, period: 23 }).await;
+
+    assert_msg_eq!(proxy.recv().await, Produced(1, 11)); // 11
+    assert_msg_eq!(proxy.recv().await, Produced(1, 11)); // 22
+    assert_msg_eq!(proxy.recv().await, Produced(2, 23)); // 23
+
+    // Replace the source with the same key.
+    proxy.send(Start { key: 1, period: 30 }).await;
+    assert_msg_eq!(proxy.recv().await, Produced(2, 23)); // 46
+    assert_msg_eq!(proxy.recv().await, Produced(1, 30)); // 53
+
+    assert!(proxy.request(Detach(1)).await);
+    assert!(!proxy.request(Detach(1)).await);
+    assert_msg_eq!(proxy.recv().await, Produced(2, 23)); // 69
+
+    assert!(proxy.request(Detach(2)).await);
+    assert!(!proxy.request(Detach(3)).await);
+    assert!(proxy.try_recv().await.is_none());
+}
+
 #[tokio::test(start_paused = true)]
 async fn generate() {
     #[message]
@@ -0,0 +1,99 @@
+#![allow(missing_docs)]
+#![cfg(feature = "test-util")]
+
+use std::time::Duration;
+
+use elfo::{
+    config::AnyConfig,
+    prelude::*,
+    routers::Singleton,
+    signal::{Signal, SignalKind},
+    stream::Stream,
+    test::Proxy,
+    time::{Delay, Interval},
+};
+
+#[message]
+struct Started;
+
+#[message]
+struct Tick;
+
+#[message]
+struct Timeout;
+
+#[message]
+struct Reload;
+
+#[message]
+struct Item(u32);
+
+#[message]
+#[derive(PartialEq)]
+struct Handled(String);
+
+async fn sample() -> Proxy {
+    let group = ActorGroup::new().exec(|mut ctx| async move {
+        let interval = ctx.attach(Interval::new(Tick));
+        interval.start(Duration::from_secs(3600));
+        ctx.attach(Delay::new(Duration::from_secs(3600), Timeout));
+        ctx.attach(Signal::new(SignalKind::UnixUser1, Reload));
+        ctx.attach(Stream::from_futures03(futures::stream::pending::<Item>()));
+        ctx.send(Started).await.unwrap();
+
+        while let Some(envelope) = ctx.recv().await {
+            let handled = msg!(match envelope {
+                Tick => "tick".into(),
+                Timeout => "timeout".into(),
+                Reload => "reload".into(),
+                Item(no) => format!("item {no}"),
+                _ => continue,
+            });
+
+            ctx.send(Handled(handled)).await.unwrap();
+        }
+    });
+
+    let mut proxy = elfo::test::proxy(group, AnyConfig::default()).await;
+    assert_msg!(proxy.recv().await, Started);
+    proxy
+}
+
+#[tokio::test]
+async fn fire() {
+    let mut proxy = sample().await;
+
+    for _ in 0..2 {
+        proxy.fire::<Tick>(Singleton);
+        assert_msg_eq!(proxy.recv().await, Handled("tick".into()));
+    }
+
+    proxy.fire::<Reload>(Singleton);
+    assert_msg_eq!(proxy.recv().await, Handled("reload".into()));
+
+    proxy.fire::<Timeout>(Singleton);
+    assert_msg_eq!(proxy.recv().await, Handled("timeout".into()));
+    proxy.expect_no_message::<Handled>().await;
+}
+
+#[tokio::test]
+#[should_panic(expected = "no attached sources of Timeout on _")]
+async fn fire_fired_delay() {
+    let mut proxy = sample().await;
+
+    proxy.fire::<Timeout>(Singleton);
+    assert_msg_eq!(proxy.recv().await, Handled("timeout".into()));
+
+    // Delays are oneshot sources.
+    proxy.fire::<Timeout>(Singleton);
+}
+
+#[tokio::test]
+async fn emit() {
+    let mut proxy = sample().await;
+
+    proxy.emit(Singleton, Item(0));
+    proxy.emit(Singleton, Item(1));
+    assert_msg_eq!(proxy.recv().await, Handled("item 0".into()));
+    assert_msg_eq!(proxy.recv().await, Handled("item 1".into()));
+}
@@ -0,0 +1,89 @@
+#![allow(missing_docs)]
+#![cfg(feature = "test-util")]
+
+use std::time::Duration;
+
+use elfo::{
+    config::AnyConfig,
+    prelude::*,
+    time::{Delay, Interval},
+    RestartParams, RestartPolicy,
+};
+
+#[message]
+struct Tick;
+
+#[message]
+struct Fired;
+
+#[tokio::test(start_paused = true)]
+async fn timers() {
+    let group = ActorGroup::new().exec(|mut ctx| async move {
+        ctx.attach(Interval::new(Tick))
+            .start(Duration::from_secs(10));
+        ctx.attach(Delay::new(Duration::from_secs(25), Fired));
+
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                msg @ Tick => ctx.send(msg).await.unwrap(),
+                msg @ Fired => ctx.send(msg).await.unwrap(),
+            });
+        }
+    });
+
+    let mut proxy = elfo::test::proxy(group, AnyConfig::default()).await;
+
+    proxy.advance_time(Duration::from_secs(9)).await;
+    assert!(proxy.try_recv().await.is_none());
+
+    proxy.advance_time(Duration::from_secs(1)).await;
+    assert_msg!(proxy.try_recv().await.unwrap(), Tick);
+    assert!(proxy.try_recv().await.is_none());
+
+    // Intermediate timers are fired in order.
+    proxy.advance_time(Duration::from_secs(20)).await;
+    assert_msg!(proxy.try_recv().await.unwrap(), Tick);
+    assert_msg!(proxy.try_recv().await.unwrap(), Fired);
+    assert_msg!(proxy.try_recv().await.unwrap(), Tick);
+    assert!(proxy.try_recv().await.is_none());
+}
+
+#[tokio::test(start_paused = true)]
+async fn restart_backoff() {
+    #[message]
+    struct Started;
+
+    let group = ActorGroup::new()
+        .restart_policy(RestartPolicy::on_failure(RestartParams::new(
+            Duration::from_secs(10),
+            Duration::from_secs(60),
+        )))
+        .exec(|ctx| async move {
+            let _ = ctx.send(Started).await;
+            panic!("boom!");
+        });
+
+    let mut proxy = elfo::test::proxy(group, AnyConfig::default()).await;
+    assert_msg!(proxy.try_recv().await.unwrap(), Started);
+
+    proxy.advance_time(Duration::from_secs(9)).await;
+    assert!(proxy.try_recv().await.is_none());
+
+    proxy.advance_time(Duration::from_secs(1)).await;
+    assert_msg!(proxy.try_recv().await.unwrap(), Started);
+
+    // The backoff grows.
+    proxy.advance_time(Duration::from_secs(19)).await;
+    assert!(proxy.try_recv().await.is_none());
+
+    proxy.advance_time(Duration::from_secs(1)).await;
+    assert_msg!(proxy.try_recv().await.unwrap(), Started);
+}
+
+#[tokio::test]
+#[should_panic(expected = "not frozen")]
+async fn unpaused() {
+    let group = ActorGroup::new().exec(|_| async {});
+    let mut proxy = elfo::test::proxy(group, AnyConfig::default()).await;
+    proxy.advance_time(Duration::from_secs(1)).await;
+}
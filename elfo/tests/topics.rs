@@ -0,0 +1,68 @@
+#![allow(missing_docs)]
+#![cfg(feature = "test-util")]
+
+use elfo::{config::AnyConfig, prelude::*};
+
+#[message(ret = bool)]
+struct Subscribe(String);
+
+#[message(ret = bool)]
+struct Unsubscribe(String);
+
+#[message(ret = bool)]
+struct Publish(String, u32);
+
+#[message]
+struct Event(u32);
+
+#[message]
+#[derive(PartialEq, Eq)]
+struct Received(u32);
+
+#[tokio::test]
+async fn it_works() {
+    let group = ActorGroup::new().exec(|mut ctx| async move {
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                (Subscribe(topic), token) => {
+                    let ok = ctx.subscribe(&topic);
+                    ctx.respond(token, ok);
+                }
+                (Unsubscribe(topic), token) => {
+                    let ok = ctx.unsubscribe(&topic);
+                    ctx.respond(token, ok);
+                }
+                (Publish(topic, no), token) => {
+                    let ok = if no % 2 == 0 {
+                        ctx.publish(&topic, Event(no)).await.is_ok()
+                    } else {
+                        ctx.try_publish(&topic, Event(no)).is_ok()
+                    };
+                    ctx.respond(token, ok);
+                }
+                Event(no) => {
+                    ctx.send(Received(no)).await.unwrap();
+                }
+            });
+        }
+    });
+
+    let mut proxy = elfo::test::proxy(group, AnyConfig::default()).await;
+
+    // No subscribers.
+    assert!(!proxy.request(Publish("a".into(), 0)).await);
+
+    assert!(proxy.request(Subscribe("a".into())).await);
+    assert!(!proxy.request(Subscribe("a".into())).await);
+
+    assert!(proxy.request(Publish("a".into(), 1)).await);
+    assert!(proxy.request(Publish("a".into(), 2)).await);
+    assert!(!proxy.request(Publish("b".into(), 3)).await);
+    assert_msg_eq!(proxy.recv().await, Received(1));
+    assert_msg_eq!(proxy.recv().await, Received(2));
+
+    assert!(proxy.request(Unsubscribe("a".into())).await);
+    assert!(!proxy.request(Unsubscribe("a".into())).await);
+    assert!(!proxy.request(Publish("a".into(), 4)).await);
+    assert!(proxy.try_recv().await.is_none());
+}
@@ -0,0 +1,40 @@
+#![allow(missing_docs)]
+#![cfg(feature = "test-util")]
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use elfo::{config::AnyConfig, prelude::*, scope, tracing::TraceId};
+
+#[message(ret = TraceId)]
+struct StartTrace;
+
+#[tokio::test]
+async fn custom_generator() {
+    const MARKER: u64 = 0x2a << 56;
+    static COUNTER: AtomicU64 = AtomicU64::new(1);
+
+    assert!(elfo::tracing::set_trace_id_generator(|| {
+        let counter = COUNTER.fetch_add(1, Ordering::Relaxed);
+        TraceId::try_from(MARKER | counter).unwrap()
+    }));
+    assert!(!elfo::tracing::set_trace_id_generator(
+        TraceId::generate_default
+    ));
+
+    let blueprint = ActorGroup::new().exec(|mut ctx| async move {
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                (StartTrace, token) => {
+                    scope::set_trace_id(TraceId::generate());
+                    ctx.respond(token, scope::trace_id());
+                }
+            });
+        }
+    });
+
+    let proxy = elfo::test::proxy(blueprint, AnyConfig::default()).await;
+
+    let trace_id = u64::from(proxy.request(StartTrace).await);
+    assert_eq!(trace_id & MARKER, MARKER);
+    assert_ne!(u64::from(proxy.request(StartTrace).await), trace_id);
+}
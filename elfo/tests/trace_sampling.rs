@@ -0,0 +1,44 @@
+#![allow(missing_docs)]
+#![cfg(feature = "test-util")]
+
+use serde::Deserialize;
+use toml::toml;
+
+use elfo::{config::AnyConfig, prelude::*, scope, tracing::TraceId};
+
+#[message(ret = bool)]
+struct IsSampled {
+    debug: bool,
+}
+
+#[tokio::test]
+async fn sampling_rules() {
+    let blueprint = ActorGroup::new().exec(|mut ctx| async move {
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                (IsSampled { debug }, token) => {
+                    scope::set_trace_id(TraceId::generate());
+
+                    if debug {
+                        scope::set_baggage(scope::baggage().with("debug", "1"));
+                    }
+
+                    ctx.respond(token, scope::is_sampled());
+                }
+            });
+        }
+    });
+
+    let config = AnyConfig::deserialize(toml! {
+        system.tracing.sampling_rate = 0.0
+        system.tracing.always_sample = [{ baggage = "debug" }]
+    })
+    .unwrap();
+
+    let proxy = elfo::test::proxy(blueprint, config).await;
+
+    for _ in 0..10 {
+        assert!(!proxy.request(IsSampled { debug: false }).await);
+        assert!(proxy.request(IsSampled { debug: true }).await);
+    }
+}
@@ -0,0 +1,61 @@
+#![allow(missing_docs)]
+#![cfg(feature = "test-util")]
+
+use elfo::{config::AnyConfig, errors::TypedRequestError, prelude::*};
+
+#[message]
+#[derive(PartialEq)]
+enum DivisionError {
+    ByZero,
+}
+
+#[message(ret = Result<u32, DivisionError>)]
+struct Divide(u32, u32);
+
+#[message]
+struct Check(u32, u32);
+
+#[message]
+#[derive(PartialEq)]
+enum Outcome {
+    Quotient(u32),
+    ByZero,
+    Failed,
+}
+
+#[tokio::test]
+async fn it_separates_responded_errors() {
+    let requester = ActorGroup::new().exec(|mut ctx| async move {
+        while let Some(envelope) = ctx.recv().await {
+            msg!(match envelope {
+                Check(a, b) => {
+                    let outcome = match ctx.request(Divide(a, b)).resolve_typed().await {
+                        Ok(quotient) => Outcome::Quotient(quotient),
+                        Err(TypedRequestError::Responded(DivisionError::ByZero)) => Outcome::ByZero,
+                        Err(TypedRequestError::Request(_)) => Outcome::Failed,
+                    };
+                    ctx.send(outcome).await.unwrap();
+                }
+            });
+        }
+    });
+
+    let mut proxy = elfo::test::proxy(requester, AnyConfig::default()).await;
+
+    for (a, b, expected) in [(6, 3, Outcome::Quotient(2)), (6, 0, Outcome::ByZero)] {
+        proxy.send(Check(a, b)).await;
+        msg!(match proxy.recv().await {
+            (Divide(a, b), token) => {
+                proxy.respond(token, a.checked_div(b).ok_or(DivisionError::ByZero));
+            }
+        });
+        assert_msg_eq!(proxy.recv().await, expected);
+    }
+
+    // The token is dropped, so the request fails.
+    proxy.send(Check(6, 3)).await;
+    msg!(match proxy.recv().await {
+        (Divide, token) => drop(token),
+    });
+    assert_msg_eq!(proxy.recv().await, Outcome::Failed);
+}
@@ -0,0 +1,12 @@
+use elfo::message;
+
+#[message]
+struct Generic<T>(T);
+
+#[message(instances(Other<u32>))]
+struct Another<T>(T);
+
+#[message(instances(u32))]
+struct NotGeneric;
+
+fn main() {}
@@ -0,0 +1,9 @@
+use elfo::message;
+
+#[message]
+struct UnknownKind {
+    #[message(redact(sha256))]
+    password: String,
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use elfo::message;
+
+#[message(transparent)]
+struct TwoFields(u32, u32);
+
+#[message(transparent)]
+enum NotStruct {
+    A(u32),
+}
+
+fn main() {}
@@ -0,0 +1,27 @@
+use elfo::message;
+
+#[message]
+struct Unversioned {
+    #[message(added = 2)]
+    a: u32,
+}
+
+#[message(version = 2)]
+struct Tuple(u32);
+
+#[message(version = 2)]
+struct FromFuture {
+    #[message(added = 3)]
+    a: u32,
+}
+
+#[message(version = 2)]
+struct DefaultOnly {
+    #[message(default = 1)]
+    a: u32,
+}
+
+#[message(removed(a: u32))]
+struct RemovedOnly {}
+
+fn main() {}
@@ -0,0 +1,53 @@
+use elfo::{message, msg, protocol_enum, Envelope};
+
+#[message]
+struct Start;
+
+#[message]
+struct Stop {
+    force: bool,
+}
+
+#[message]
+struct Pause;
+
+protocol_enum! {
+    enum Command {
+        Start,
+        Stop,
+        Pause,
+    }
+}
+
+fn forgotten(envelope: Envelope) {
+    msg!(#[exhaustive(Command)] match envelope {
+        Start => {}
+        Stop { .. } => {}
+        _ => {}
+    });
+}
+
+fn refutable(envelope: Envelope) {
+    msg!(#[exhaustive(Command)] match envelope {
+        Start => {}
+        Stop { force: true } => {}
+        Stop { force: false } => {}
+        Pause => {}
+    });
+}
+
+fn defaulted(envelope: Envelope) {
+    msg!(#[exhaustive(Command)] match envelope {
+        Start => {}
+        Stop | Pause => {}
+    });
+}
+
+fn not_protocol_enum(envelope: Envelope) {
+    msg!(#[exhaustive(Start)] match envelope {
+        Stop { .. } => {}
+        _ => {}
+    });
+}
+
+fn main() {}
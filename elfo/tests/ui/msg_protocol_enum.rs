@@ -0,0 +1,27 @@
+use elfo::{message, msg, protocol_enum, Envelope};
+
+#[message]
+struct SomeEvent;
+
+#[message(ret = ())]
+struct SomeRequest;
+
+protocol_enum! {
+    enum Borrowed {
+        SomeEvent,
+    }
+}
+
+protocol_enum! {
+    enum WithRequest {
+        SomeRequest,
+    }
+}
+
+fn test(envelope: Envelope) {
+    msg!(match &envelope {
+        Borrowed => {}
+    });
+}
+
+fn main() {}
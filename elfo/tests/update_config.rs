@@ -6,8 +6,9 @@ use toml::toml;
 
 use elfo::{
     config::AnyConfig,
-    messages::{ConfigRejected, ConfigUpdated, UpdateConfig},
+    messages::{ConfigRejected, ConfigUpdated, UpdateConfig, UpdateConfigCanary},
     prelude::*,
+    routers::{MapRouter, Outcome},
 };
 
 #[tokio::test]
@@ -77,6 +78,170 @@ async fn singleton_actor_update_config() {
     assert_eq!(proxy.request(GetLimit).await, 512);
 }
 
+#[tokio::test]
+async fn canary_update_config() {
+    #[message(ret = usize)]
+    struct GetLimit(u32);
+
+    #[derive(Debug, Clone, Deserialize)]
+    struct Config {
+        limit: usize,
+    }
+
+    let blueprint = ActorGroup::new()
+        .config::<Config>()
+        .router(MapRouter::new(|e| {
+            msg!(match e {
+                GetLimit(key) => Outcome::Unicast(*key),
+                _ => Outcome::Default,
+            })
+        }))
+        .exec(move |mut ctx| async move {
+            while let Some(envelope) = ctx.recv().await {
+                msg!(match envelope {
+                    ConfigUpdated => continue,
+                    (GetLimit(_), token) => {
+                        ctx.respond(token, ctx.config().limit);
+                    }
+                    _ => unreachable!(),
+                });
+            }
+        });
+
+    let proxy = elfo::test::proxy(blueprint, toml! { limit = 1 }).await;
+    let limits = || async {
+        let mut limits = Vec::new();
+        for key in 0..4 {
+            limits.push(proxy.request(GetLimit(key)).await);
+        }
+        limits
+    };
+
+    assert_eq!(limits().await, [1, 1, 1, 1]);
+
+    // Only canaries are updated.
+    let config = AnyConfig::deserialize(toml! { limit = 2 }).unwrap();
+    let keys = vec!["1".into(), "3".into()];
+    proxy.send(UpdateConfigCanary::new(config, keys, 0.)).await;
+    assert_eq!(limits().await, [1, 2, 1, 2]);
+
+    // Canaries spawned later get the new config too.
+    let config = AnyConfig::deserialize(toml! { limit = 3 }).unwrap();
+    let keys = vec!["4".into()];
+    proxy.send(UpdateConfigCanary::new(config, keys, 0.)).await;
+    assert_eq!(proxy.request(GetLimit(4)).await, 3);
+    assert_eq!(proxy.request(GetLimit(5)).await, 1);
+    assert_eq!(limits().await, [1, 2, 1, 2]);
+
+    // The whole group is updated.
+    let config = AnyConfig::deserialize(toml! { limit = 4 }).unwrap();
+    proxy.send(UpdateConfig::new(config)).await;
+    assert_eq!(limits().await, [4, 4, 4, 4]);
+    assert_eq!(proxy.request(GetLimit(4)).await, 4);
+    assert_eq!(proxy.request(GetLimit(6)).await, 4);
+}
+
+#[tokio::test]
+async fn key_overrides() {
+    #[message(ret = (usize, usize))]
+    struct GetLimits(u32);
+
+    #[derive(Debug, Clone, Deserialize)]
+    struct Config {
+        limit: usize,
+        nested: Nested,
+    }
+
+    #[derive(Debug, Clone, Deserialize)]
+    struct Nested {
+        limit: usize,
+        other: usize,
+    }
+
+    let blueprint = ActorGroup::new()
+        .config::<Config>()
+        .router(MapRouter::new(|e| {
+            msg!(match e {
+                GetLimits(key) => Outcome::Unicast(*key),
+                _ => Outcome::Default,
+            })
+        }))
+        .exec(move |mut ctx| async move {
+            while let Some(envelope) = ctx.recv().await {
+                msg!(match envelope {
+                    ConfigUpdated => continue,
+                    (GetLimits(_), token) => {
+                        let config = ctx.config();
+                        let limits = (config.limit, config.nested.limit + config.nested.other);
+                        ctx.respond(token, limits);
+                    }
+                    _ => unreachable!(),
+                });
+            }
+        });
+
+    let config = toml! {
+        limit = 1
+        nested = { limit = 10, other = 5 }
+        system.key_overrides.1 = { limit = 2, nested = { limit = 20 } }
+    };
+    let proxy = elfo::test::proxy(blueprint, config).await;
+
+    // Overrides are applied at spawn, nested maps are merged.
+    assert_eq!(proxy.request(GetLimits(0)).await, (1, 15));
+    assert_eq!(proxy.request(GetLimits(1)).await, (2, 25));
+
+    // Overrides are applied on updates.
+    let config = AnyConfig::deserialize(toml! {
+        limit = 3
+        nested = { limit = 10, other = 5 }
+        system.key_overrides.0 = { limit = 4 }
+    })
+    .unwrap();
+    proxy.send(UpdateConfig::new(config)).await;
+    assert_eq!(proxy.request(GetLimits(0)).await, (4, 15));
+    assert_eq!(proxy.request(GetLimits(1)).await, (3, 15));
+
+    // Invalid overrides reject the whole config.
+    let config = AnyConfig::deserialize(toml! {
+        limit = 5
+        nested = { limit = 10, other = 5 }
+        system.key_overrides.0 = { limit = -1 }
+    })
+    .unwrap();
+    let err = proxy.request(UpdateConfig::new(config)).await.unwrap_err();
+    assert!(err.reason.contains("system.key_overrides.0"), "{}", err.reason);
+    assert_eq!(proxy.request(GetLimits(1)).await, (3, 15));
+}
+
+#[tokio::test]
+async fn key_overrides_field() {
+    #[message(ret = Vec<String>)]
+    struct GetOverrides;
+
+    #[derive(Debug, Clone, Deserialize)]
+    struct Config {
+        key_overrides: Vec<String>,
+    }
+
+    let blueprint = ActorGroup::new()
+        .config::<Config>()
+        .exec(move |mut ctx| async move {
+            while let Some(envelope) = ctx.recv().await {
+                msg!(match envelope {
+                    (GetOverrides, token) => {
+                        ctx.respond(token, ctx.config().key_overrides.clone());
+                    }
+                    _ => {}
+                });
+            }
+        });
+
+    // User configs can have their own `key_overrides` field.
+    let proxy = elfo::test::proxy(blueprint, toml! { key_overrides = ["a"] }).await;
+    assert_eq!(proxy.request(GetOverrides).await, vec!["a".to_string()]);
+}
+
 #[tokio::test]
 #[should_panic(expected = "subject:\n- panic: intentional panic")]
 async fn panic_in_deserialize() {
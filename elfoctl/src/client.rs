@@ -0,0 +1,75 @@
+use std::path::Path;
+
+use elfo_admin::protocol::Command;
+
+#[cfg(unix)]
+pub(crate) use self::unix::Client;
+
+/// Unix sockets aren't supported on this platform.
+#[cfg(not(unix))]
+pub(crate) enum Client {}
+
+#[cfg(not(unix))]
+impl Client {
+    pub(crate) fn connect(_path: &Path) -> Result<Self, String> {
+        Err("unix sockets are not supported on this platform".into())
+    }
+
+    pub(crate) fn call(&mut self, _command: Command) -> Result<serde_json::Value, String> {
+        match *self {}
+    }
+}
+
+#[cfg(unix)]
+mod unix {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        os::unix::net::UnixStream,
+    };
+
+    use elfo_admin::protocol::Response;
+
+    use super::*;
+
+    /// A blocking client of the admin socket.
+    pub(crate) struct Client {
+        reader: BufReader<UnixStream>,
+        writer: UnixStream,
+    }
+
+    impl Client {
+        pub(crate) fn connect(path: &Path) -> Result<Self, String> {
+            let stream = UnixStream::connect(path)
+                .map_err(|err| format!("cannot connect to {}: {err}", path.display()))?;
+            let writer = stream.try_clone().map_err(|err| err.to_string())?;
+
+            Ok(Self {
+                reader: BufReader::new(stream),
+                writer,
+            })
+        }
+
+        /// Executes the command and returns the value of a successful response.
+        pub(crate) fn call(&mut self, command: Command) -> Result<serde_json::Value, String> {
+            let mut line = serde_json::to_string(&command).map_err(|err| err.to_string())?;
+            line.push('\n');
+            self.writer
+                .write_all(line.as_bytes())
+                .map_err(|err| format!("cannot send a command: {err}"))?;
+
+            line.clear();
+            match self.reader.read_line(&mut line) {
+                Ok(0) => return Err("the connection is closed by the node".into()),
+                Ok(_) => {}
+                Err(err) => return Err(format!("cannot receive a response: {err}")),
+            }
+
+            match serde_json::from_str(&line) {
+                Ok(Response::Ok(value)) => Ok(value),
+                Ok(Response::Error(err)) => Err(err),
+                Ok(_) => Err(format!("unsupported response: {line}")),
+                Err(err) => Err(format!("invalid response: {err}")),
+            }
+        }
+    }
+}
@@ -0,0 +1,234 @@
+//! A command-line tool to control running elfo nodes via the control socket
+//! exposed by `elfo-admin`.
+//!
+//! # Examples
+//! ```text
+//! elfoctl status
+//! elfoctl config reload --force
+//! elfoctl log-level orders=debug payments=reset
+//! elfoctl dump record --class slow --secs 30
+//! ```
+//!
+//! The socket path is provided by `--socket` or the `ELFOCTL_SOCKET` env.
+
+use std::{path::PathBuf, process::ExitCode, time::Duration};
+
+use clap::{Args, Parser, Subcommand};
+
+use elfo_admin::protocol::{ActorInfo, Command};
+
+use self::client::Client;
+
+mod client;
+
+#[derive(Parser)]
+#[command(version, about = "Controls a running elfo node via its admin socket")]
+struct Cli {
+    /// The path to the admin socket.
+    #[arg(long, short, env = "ELFOCTL_SOCKET")]
+    socket: PathBuf,
+    #[command(subcommand)]
+    command: Cmd,
+}
+
+#[derive(Subcommand)]
+enum Cmd {
+    /// Shows statuses of actors.
+    Status {
+        /// Shows only actors of the group.
+        group: Option<String>,
+        /// Prints statuses as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Manages configs.
+    #[command(subcommand)]
+    Config(ConfigCmd),
+    /// Changes log levels of groups, e.g. `orders=debug`.
+    /// Use `reset` as a level to restore the configured one.
+    LogLevel {
+        #[arg(required = true, value_parser = parse_log_level)]
+        levels: Vec<(String, Option<String>)>,
+    },
+    /// Manages dump classes.
+    #[command(subcommand)]
+    Dump(DumpCmd),
+}
+
+#[derive(Subcommand)]
+enum ConfigCmd {
+    /// Reloads configs.
+    Reload {
+        /// Resends up-to-date configs too.
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum DumpCmd {
+    /// Enables the dump class.
+    Enable(DumpClass),
+    /// Disables the dump class.
+    Disable(DumpClass),
+    /// Restores the configured state of the dump class.
+    Reset(DumpClass),
+    /// Enables the dump class for some time, then the node restores it back.
+    Record {
+        #[command(flatten)]
+        class: DumpClass,
+        /// How long to record dumps.
+        #[arg(long, default_value_t = 10)]
+        secs: u64,
+    },
+}
+
+#[derive(Args)]
+struct DumpClass {
+    /// The dump class, e.g. `slow`.
+    #[arg(long)]
+    class: String,
+    /// The group to change. All groups are changed by default.
+    #[arg(long)]
+    group: Option<String>,
+}
+
+fn parse_log_level(s: &str) -> Result<(String, Option<String>), String> {
+    let (group, level) = s
+        .split_once('=')
+        .filter(|(group, level)| !group.is_empty() && !level.is_empty())
+        .ok_or_else(|| format!("expected `<group>=<level>`, got `{s}`"))?;
+
+    let level = (level != "reset").then(|| level.to_string());
+    Ok((group.to_string(), level))
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    match run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<(), String> {
+    let mut client = Client::connect(&cli.socket)?;
+
+    match cli.command {
+        Cmd::Status { group, json } => {
+            let statuses = client.call(Command::Status { group })?;
+
+            if json {
+                println!("{statuses:#}");
+            } else {
+                let statuses: Vec<ActorInfo> =
+                    serde_json::from_value(statuses).map_err(|err| err.to_string())?;
+                print_statuses(&statuses);
+            }
+        }
+        Cmd::Config(ConfigCmd::Reload { force }) => {
+            client.call(Command::ReloadConfigs { force })?;
+        }
+        Cmd::LogLevel { levels } => {
+            for (group, level) in levels {
+                client.call(Command::SetLogLevel { group, level })?;
+            }
+        }
+        Cmd::Dump(DumpCmd::Enable(class)) => {
+            set_dump_class(&mut client, class, Some(true), None)?;
+        }
+        Cmd::Dump(DumpCmd::Disable(class)) => {
+            set_dump_class(&mut client, class, Some(false), None)?;
+        }
+        Cmd::Dump(DumpCmd::Reset(class)) => set_dump_class(&mut client, class, None, None)?,
+        Cmd::Dump(DumpCmd::Record { class, secs }) => {
+            let name = class.class.clone();
+            let ttl = Duration::from_secs(secs);
+            // The node restores previous states itself, even if it's changed meanwhile.
+            set_dump_class(&mut client, class, Some(true), Some(ttl))?;
+            eprintln!("recording `{name}` dumps for {secs}s");
+        }
+    }
+
+    Ok(())
+}
+
+fn set_dump_class(
+    client: &mut Client,
+    class: DumpClass,
+    enabled: Option<bool>,
+    ttl: Option<Duration>,
+) -> Result<(), String> {
+    let groups = match class.group {
+        Some(group) => vec![group],
+        None => all_groups(client)?,
+    };
+
+    for group in groups {
+        client.call(Command::SetDumpClass {
+            group,
+            class: class.class.clone(),
+            enabled,
+            ttl,
+        })?;
+    }
+
+    Ok(())
+}
+
+fn all_groups(client: &mut Client) -> Result<Vec<String>, String> {
+    let statuses = client.call(Command::Status { group: None })?;
+    let statuses: Vec<ActorInfo> =
+        serde_json::from_value(statuses).map_err(|err| err.to_string())?;
+
+    let mut groups = statuses
+        .into_iter()
+        .map(|info| info.group)
+        .collect::<Vec<_>>();
+    groups.dedup();
+    Ok(groups)
+}
+
+fn print_statuses(statuses: &[ActorInfo]) {
+    let width = |f: fn(&ActorInfo) -> usize| statuses.iter().map(f).max().unwrap_or(0);
+    let group_width = width(|info| info.group.len()).max(5);
+    let key_width = width(|info| info.key.len()).max(3);
+
+    println!("{:group_width$}  {:key_width$}  STATUS", "GROUP", "KEY");
+    for info in statuses {
+        println!(
+            "{:group_width$}  {:key_width$}  {}",
+            info.group, info.key, info.status
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_levels() {
+        assert_eq!(
+            parse_log_level("orders=debug"),
+            Ok(("orders".into(), Some("debug".into())))
+        );
+        assert_eq!(
+            parse_log_level("system.loggers=reset"),
+            Ok(("system.loggers".into(), None))
+        );
+        assert!(parse_log_level("orders").is_err());
+        assert!(parse_log_level("=debug").is_err());
+        assert!(parse_log_level("orders=").is_err());
+    }
+
+    #[test]
+    fn cli() {
+        use clap::CommandFactory;
+        Cli::command().debug_assert();
+    }
+}